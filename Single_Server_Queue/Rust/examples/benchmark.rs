@@ -0,0 +1,95 @@
+//! Events per second of the plain event loop on a fixed seeded run, plus a digest
+//! of the final statistics so refactors can be checked for identical results.
+//!
+//! Run with `cargo run --release --example benchmark [events] [--instrumented] [--sampled] [--driver] [--rho <ρ>]`;
+//! `--instrumented` turns on engine instrumentation to measure its overhead,
+//! `--sampled` feeds every event to a time series as the binary's run loop does,
+//! `--driver` runs the events through `Simulation::run` without an observer
+//! instead of stepping by hand, and `--rho` changes the traffic intensity from the default 0.8, e.g. 1.2 for an
+//! overloaded run whose queue keeps growing. Sampling only reads the statistics,
+//! so the digest is the same either way.
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::time_series::{DEFAULT_MAX_SAMPLES, SimulationTimeSeries};
+use std::time::Instant;
+
+/// FNV-1a over the bit patterns of the given values
+fn digest(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+fn main() {
+    let events: u64 = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(50_000_000);
+    let instrumented = std::env::args().any(|arg| arg == "--instrumented");
+    let sampled = std::env::args().any(|arg| arg == "--sampled");
+    let driver = std::env::args().any(|arg| arg == "--driver");
+    let rho: Option<f64> = std::env::args()
+        .skip_while(|arg| arg != "--rho")
+        .nth(1)
+        .and_then(|arg| arg.parse().ok());
+    let repetitions = 5;
+
+    let mut best = f64::INFINITY;
+    let mut result = 0;
+    for _ in 0..repetitions {
+        let defaults = SimulationConfig::default();
+        let mut simulation = Simulation::new(SimulationConfig {
+            seed: Some(42),
+            lambda: rho.map_or(defaults.lambda, |rho| rho * defaults.mu),
+            instrumentation: instrumented,
+            ..defaults
+        });
+
+        // The binary starts a run stopped by its event count at one sample per mean interarrival time
+        let mut time_series =
+            SimulationTimeSeries::new(1.0 / simulation.config().lambda, DEFAULT_MAX_SAMPLES);
+        let start = Instant::now();
+        if sampled {
+            while simulation.event_count() < events {
+                if let Some(event) = simulation.step() {
+                    let now = simulation.now();
+                    time_series.record_event(
+                        event.event_type,
+                        now,
+                        simulation.total_arrivals(),
+                        simulation.total_departures(),
+                    );
+                    time_series.sample_statistics(now, simulation.statistics());
+                }
+            }
+        } else if driver {
+            simulation.run(&StopCondition::Events(events), &mut ());
+        } else {
+            while simulation.event_count() < events {
+                simulation.step();
+            }
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+
+        let now = simulation.now();
+        let stats = simulation.statistics();
+        result = digest(&[
+            now.to_bits(),
+            stats.served_customers(),
+            stats.average_wait_time().to_bits(),
+            stats.average_queue_length(now).to_bits(),
+            stats.average_customers_in_system(now).to_bits(),
+            stats.utilization(now).to_bits(),
+        ]);
+    }
+
+    println!("Events: {}", events);
+    println!("Best of {}: {:.3}s", repetitions, best);
+    println!("Events per second: {:.0}", events as f64 / best);
+    println!("Result digest: {:016x}", result);
+}
@@ -0,0 +1,13 @@
+//! Print the event sequence `--verify` checks the engine against, to regenerate
+//! `golden/event_order.csv` after a change meant to reorder events.
+
+use rust_single_server_queue::event_order::{
+    GOLDEN_EVENT_COUNT, golden_config, record_events, to_csv,
+};
+
+fn main() {
+    print!(
+        "{}",
+        to_csv(&record_events(golden_config(), GOLDEN_EVENT_COUNT))
+    );
+}
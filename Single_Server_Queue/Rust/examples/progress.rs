@@ -0,0 +1,54 @@
+//! Drive a simulation through its progress iterator, printing a live table and
+//! stopping as soon as the mean wait is known to within 2%.
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::simulation::Simulation;
+
+fn main() {
+    let config = SimulationConfig::builder()
+        .lambda(0.9)
+        .mu(1.0)
+        .warm_up_time(1_000.0)
+        .seed(7)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    let max_events = 50_000_000;
+
+    println!(
+        "{:>12} {:>12} {:>10} {:>7} {:>10} {:>10} {:>8}",
+        "time", "events", "served", "queue", "mean wait", "± 95%", "util"
+    );
+    for report in simulation.progress_iter(500_000) {
+        let half_width = report
+            .mean_wait_ci
+            .map(|(lower, upper)| (upper - lower) / 2.0);
+        println!(
+            "{:>12.0} {:>12} {:>10} {:>7} {:>10.4} {:>10} {:>8.4}",
+            report.time,
+            report.event_count,
+            report.customers_served,
+            report.queue_length,
+            report.mean_wait,
+            half_width.map_or("-".to_string(), |h| format!("{:.4}", h)),
+            report.utilization
+        );
+        if half_width.is_some_and(|h| h < 0.02 * report.mean_wait)
+            || report.event_count >= max_events
+        {
+            break;
+        }
+    }
+
+    // The iterator is gone; the simulation is intact and its statistics final
+    let stats = simulation.statistics();
+    println!();
+    println!("Stopped at t = {:.0}", simulation.now());
+    println!(
+        "Mean wait: {:.4} (theory 9.0000)",
+        stats.average_wait_time()
+    );
+    if let Some((lower, upper)) = stats.wait_confidence_interval() {
+        println!("95% interval: {:.4} to {:.4}", lower, upper);
+    }
+}
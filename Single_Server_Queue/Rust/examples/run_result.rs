@@ -0,0 +1,51 @@
+//! Run a simulation to a time limit and read everything a dashboard needs off
+//! its result: the final clock and counts, the wall-clock time and the sampled
+//! time series.
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::{RunEnd, StopCondition};
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::time_series::{DEFAULT_MAX_SAMPLES, SimulationTimeSeries};
+
+fn main() {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .seed(11)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    let max_time = 100_000.0;
+
+    let result = simulation.run_sampled(
+        &StopCondition::Time(max_time),
+        SimulationTimeSeries::new(max_time / DEFAULT_MAX_SAMPLES as f64, DEFAULT_MAX_SAMPLES),
+        &mut (),
+    );
+
+    // The result holds the same figures the simulation reports afterwards
+    assert_eq!(result.outcome.end, RunEnd::StopCondition);
+    assert_eq!(result.total_time, simulation.now());
+    assert_eq!(result.event_count, simulation.event_count());
+    assert_eq!(result.outcome.events, simulation.event_count());
+    assert_eq!(
+        result.served_customers,
+        simulation.statistics().served_customers()
+    );
+    assert!(result.total_time <= max_time);
+
+    let stats = &result.statistics;
+    println!("Simulated time: {:.2}", result.total_time);
+    println!("Events processed: {}", result.event_count);
+    println!("Customers served: {}", result.served_customers);
+    println!("Wall-clock time: {:.3}s", result.elapsed.as_secs_f64());
+    println!(
+        "Mean wait: {:.4} (theory 4.0000), utilization {:.4}",
+        stats.average_wait_time(),
+        stats.utilization(result.total_time)
+    );
+    println!(
+        "Samples of the mean wait: {}",
+        result.time_series.mean_wait_time.len()
+    );
+}
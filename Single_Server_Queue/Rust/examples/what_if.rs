@@ -0,0 +1,47 @@
+//! Pause a heavily loaded queue, branch it, and compare "keep going" against
+//! "speed up the server now".
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::simulation::Simulation;
+
+fn main() {
+    let branch_time = 50_000.0;
+    let end_time = 100_000.0;
+
+    let mut simulation = Simulation::new(SimulationConfig {
+        lambda: 0.95,
+        mu: 1.0,
+        seed: Some(42),
+        ..Default::default()
+    });
+    simulation.run_until(branch_time);
+
+    let (served_at_branch, queue_at_branch) = {
+        let stats = simulation.statistics();
+        (stats.served_customers(), stats.current_queue_length())
+    };
+
+    let mut baseline = simulation.fork();
+    let mut faster = simulation.fork_with(|cfg| cfg.mu = 1.5);
+    baseline.run_until(end_time);
+    faster.run_until(end_time);
+
+    println!("Branched at t = {:.0}", branch_time);
+    println!("  customers served: {}", served_at_branch);
+    println!("  queue length: {}", queue_at_branch);
+    println!();
+    println!(
+        "{:<12} {:>14} {:>14} {:>14}",
+        "branch", "served after", "final queue", "mean wait"
+    );
+    for (label, branch) in [("μ = 1.0", &baseline), ("μ = 1.5", &faster)] {
+        let stats = branch.statistics();
+        println!(
+            "{:<12} {:>14} {:>14} {:>14.4}",
+            label,
+            stats.served_customers() - served_at_branch,
+            stats.current_queue_length(),
+            stats.average_wait_time()
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Labelled moments on the time axis of a run, drawn on every plot and exported with the results
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Annotations kept per category and run; later ones are counted but dropped, so a
+/// run with frequent failures cannot fill memory or the plots with lines, nor crowd
+/// out the rare phase changes
+pub const MAX_ANNOTATIONS_PER_CATEGORY: usize = 1_000;
+
+/// What kind of moment an annotation marks; the viewer colors and filters by it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnnotationCategory {
+    /// A change of regime such as the end of the warm-up or the arrival cutoff
+    Phase,
+    /// A scheduled parameter change, labelled with the old and the new value
+    Change,
+    Failure,
+    Repair,
+    /// A metric crossing a limit for the first time, e.g. the queue reaching its cap
+    Threshold,
+    /// Added by library users through [`crate::simulation::Simulation::annotate`]
+    Custom,
+}
+
+impl AnnotationCategory {
+    pub const ALL: [Self; 6] = [
+        Self::Phase,
+        Self::Change,
+        Self::Failure,
+        Self::Repair,
+        Self::Threshold,
+        Self::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Phase => "phase",
+            Self::Change => "change",
+            Self::Failure => "failure",
+            Self::Repair => "repair",
+            Self::Threshold => "threshold",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for AnnotationCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub time: f64,
+    pub label: String,
+    pub category: AnnotationCategory,
+}
+
+/// The annotations of one run in the order they were added
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    items: Vec<Annotation>,
+    per_category: [usize; AnnotationCategory::ALL.len()],
+    dropped: u64,
+}
+
+impl Annotations {
+    /// Add an annotation, or only count it once its category has [`MAX_ANNOTATIONS_PER_CATEGORY`]
+    pub fn push(&mut self, time: f64, label: impl Into<String>, category: AnnotationCategory) {
+        let kept = &mut self.per_category[category as usize];
+        if *kept < MAX_ANNOTATIONS_PER_CATEGORY {
+            *kept += 1;
+            self.items.push(Annotation {
+                time,
+                label: label.into(),
+                category,
+            });
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn as_slice(&self) -> &[Annotation] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Annotations left out because the cap was reached
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.per_category = Default::default();
+        self.dropped = 0;
+    }
+
+    /// `time,category,label` rows, the label quoted
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "time,category,label")?;
+        for annotation in &self.items {
+            writeln!(
+                out,
+                "{},{},\"{}\"",
+                annotation.time,
+                annotation.category,
+                annotation.label.replace('"', "\"\"")
+            )?;
+        }
+        out.flush()
+    }
+
+    /// JSON array of `{"time", "category", "label"}` objects
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .items
+            .iter()
+            .map(|annotation| {
+                format!(
+                    "{{\"time\": {}, \"category\": \"{}\", \"label\": \"{}\"}}",
+                    annotation.time,
+                    annotation.category,
+                    json_escape(&annotation.label)
+                )
+            })
+            .collect();
+        format!("[{}]", entries.join(", "))
+    }
+}
+
+/// Escape a string for a JSON string literal
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
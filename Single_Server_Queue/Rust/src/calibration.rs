@@ -0,0 +1,273 @@
+//! Fit a model rate so the simulation reproduces an observed metric
+
+use crate::config::SimulationConfig;
+use crate::simulation::Simulation;
+use crate::statistics::t_quantile_975;
+use std::fmt;
+
+/// Output of the simulation that is matched to the observed value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationMetric {
+    MeanWait,
+    /// 90th percentile of the individual waits, from the customer trace
+    WaitP90,
+    Utilization,
+}
+
+impl CalibrationMetric {
+    /// Value of the metric at the end of a run
+    ///
+    /// [`CalibrationMetric::WaitP90`] needs the customer trace, so the run must have
+    /// been configured with a `customer_trace_limit`.
+    pub fn measure(self, simulation: &Simulation) -> f64 {
+        let stats = simulation.statistics();
+        match self {
+            Self::MeanWait => stats.average_wait_time(),
+            Self::Utilization => stats.utilization(simulation.now()),
+            Self::WaitP90 => {
+                let warm_up_time = simulation.config().warm_up_time;
+                let mut waits: Vec<f64> = simulation
+                    .server()
+                    .customer_trace()
+                    .records()
+                    .iter()
+                    .filter(|record| record.arrival >= warm_up_time)
+                    .map(|record| record.wait())
+                    .collect();
+                quantile(&mut waits, 0.9)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CalibrationMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MeanWait => f.pad("mean wait"),
+            Self::WaitP90 => f.pad("90th percentile wait"),
+            Self::Utilization => f.pad("utilization"),
+        }
+    }
+}
+
+/// Rate of the model that is searched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibratedParameter {
+    ArrivalRate,
+    ServiceRate,
+}
+
+impl CalibratedParameter {
+    /// Set this parameter of `config` to `value`
+    pub fn apply(self, config: &mut SimulationConfig, value: f64) {
+        match self {
+            Self::ArrivalRate => config.lambda = value,
+            Self::ServiceRate => config.mu = value,
+        }
+    }
+
+    /// Values to search between: utilization from 5% to 99% given the other rate of `config`
+    pub fn bracket(self, config: &SimulationConfig) -> (f64, f64) {
+        match self {
+            Self::ArrivalRate => (config.mu * 0.05, config.mu * 0.99),
+            Self::ServiceRate => (config.lambda / 0.99, config.lambda / 0.05),
+        }
+    }
+}
+
+impl fmt::Display for CalibratedParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ArrivalRate => f.pad("λ"),
+            Self::ServiceRate => f.pad("μ"),
+        }
+    }
+}
+
+/// The metric estimated at one parameter value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    pub parameter: f64,
+    /// Mean of the metric over the replications
+    pub mean: f64,
+    /// 95% confidence half-width of `mean` across replications
+    pub half_width: f64,
+}
+
+/// Mean of `metric` over `replications` runs of `config`, each until `customers`
+/// have been served, with its 95% confidence half-width (NaN for one replication)
+///
+/// Replication i always uses seed `seed + i`, so evaluating several parameter
+/// values compares them under common random numbers: the difference between
+/// two evaluations reflects the parameter change, not a change of luck.
+pub fn evaluate(
+    config: &SimulationConfig,
+    metric: CalibrationMetric,
+    replications: usize,
+    customers: u64,
+    seed: u64,
+) -> (f64, f64) {
+    let replication_config = |i: usize| SimulationConfig {
+        seed: Some(seed.wrapping_add(i as u64)),
+        customer_trace_limit: match metric {
+            CalibrationMetric::WaitP90 => customers as usize,
+            _ => config.customer_trace_limit,
+        },
+        ..config.clone()
+    };
+
+    // One simulation reset for every replication, so its buffers are only allocated once
+    let mut simulation = Simulation::new(replication_config(0));
+    let values: Vec<f64> = (0..replications)
+        .map(|i| {
+            if i > 0 {
+                simulation.reset(replication_config(i));
+            }
+            while simulation.statistics().served_customers() < customers
+                && simulation.engine().has_next_event()
+            {
+                simulation.step();
+            }
+            metric.measure(&simulation)
+        })
+        .collect();
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let half_width = if values.len() > 1 {
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        t_quantile_975(n - 1.0) * (variance / n).sqrt()
+    } else {
+        f64::NAN
+    };
+    (mean, half_width)
+}
+
+/// Outcome of [`calibrate`]
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Parameter value whose estimate came closest to the target
+    pub value: f64,
+    /// 95% half-width of `value`, the metric's half-width divided by the local slope
+    pub half_width: Option<f64>,
+    /// Estimate of the metric at `value`
+    pub estimate: Evaluation,
+    /// Whether the estimate is within the tolerance of the target
+    pub converged: bool,
+    /// Every evaluation in the order the search made them, bracket ends first
+    pub evaluations: Vec<Evaluation>,
+}
+
+/// Find the parameter value in `bracket` at which a monotone response hits `target`
+///
+/// `evaluate` returns the estimated response at one parameter value and its
+/// confidence half-width, e.g. from [`evaluate`]; the driver does not care which
+/// parameter that is. The search is false position with the Illinois
+/// modification: a secant step between the ends of a bracket that always
+/// contains the root, halving the weight of an end that is kept twice in a row
+/// so it cannot stall, and falling back to bisection should the secant step
+/// leave the bracket. It stops once an estimate is within `tolerance` of the
+/// target or after `max_iterations` evaluations inside the bracket.
+pub fn calibrate<F>(
+    mut evaluate: F,
+    bracket: (f64, f64),
+    target: f64,
+    tolerance: f64,
+    max_iterations: usize,
+) -> Result<Calibration, String>
+where
+    F: FnMut(f64) -> (f64, f64),
+{
+    let mut evaluations = Vec::new();
+    let mut evaluate_at = |parameter: f64| {
+        let (mean, half_width) = evaluate(parameter);
+        let evaluation = Evaluation {
+            parameter,
+            mean,
+            half_width,
+        };
+        evaluations.push(evaluation);
+        evaluation
+    };
+
+    let (mut a, mut b) = bracket;
+    let low = evaluate_at(a);
+    let high = evaluate_at(b);
+    let (mut fa, mut fb) = (low.mean - target, high.mean - target);
+    if fa * fb > 0.0 {
+        return Err(format!(
+            "the target {} is outside the range {:.4} to {:.4} simulated between {:.4} and {:.4}",
+            target, low.mean, high.mean, a, b
+        ));
+    }
+
+    // Which end was replaced last: -1 for b, 1 for a. The kept end's residual may
+    // have been halved, so convergence is judged on the fresh estimate only
+    let mut side = 0;
+    let mut converged = fa.abs() <= tolerance || fb.abs() <= tolerance;
+    for _ in 0..max_iterations {
+        if converged {
+            break;
+        }
+        let secant = (a * fb - b * fa) / (fb - fa);
+        let c = if secant > a.min(b) && secant < a.max(b) {
+            secant
+        } else {
+            (a + b) / 2.0
+        };
+        let fc = evaluate_at(c).mean - target;
+        converged = fc.abs() <= tolerance;
+
+        if fc * fb > 0.0 {
+            b = c;
+            fb = fc;
+            if side == -1 {
+                fa /= 2.0;
+            }
+            side = -1;
+        } else {
+            a = c;
+            fa = fc;
+            if side == 1 {
+                fb /= 2.0;
+            }
+            side = 1;
+        }
+    }
+
+    let best = *evaluations
+        .iter()
+        .min_by(|x, y| (x.mean - target).abs().total_cmp(&(y.mean - target).abs()))
+        .expect("both bracket ends were evaluated");
+    // Local slope to the nearest other evaluation turns the metric's
+    // uncertainty into an uncertainty of the parameter
+    let half_width = evaluations
+        .iter()
+        .filter(|e| e.parameter != best.parameter)
+        .min_by(|x, y| {
+            (x.parameter - best.parameter)
+                .abs()
+                .total_cmp(&(y.parameter - best.parameter).abs())
+        })
+        .map(|neighbour| (neighbour.mean - best.mean) / (neighbour.parameter - best.parameter))
+        .filter(|slope| *slope != 0.0 && best.half_width.is_finite())
+        .map(|slope| best.half_width / slope.abs());
+
+    Ok(Calibration {
+        value: best.parameter,
+        half_width,
+        estimate: best,
+        converged,
+        evaluations,
+    })
+}
+
+/// Nearest-rank `p`-quantile of `values`, NaN if empty; sorts `values` in place
+fn quantile(values: &mut [f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    values.sort_by(f64::total_cmp);
+    let rank = ((p * values.len() as f64).ceil() as usize).clamp(1, values.len());
+    values[rank - 1]
+}
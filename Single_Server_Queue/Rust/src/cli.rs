@@ -0,0 +1,1096 @@
+use crate::report::{METRIC_KEYS, NumberFormat, OutputFormat};
+use rust_single_server_queue::config::{
+    ChangedParameter, ParameterChange, QueueDiscipline, ServiceKind,
+};
+use rust_single_server_queue::cost::CostModel;
+use rust_single_server_queue::experiment::Factor;
+use rust_single_server_queue::gantt::MAX_GANTT_CUSTOMERS;
+use rust_single_server_queue::rng::RngBackend;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Most decimals `--precision` accepts, about what an f64 holds
+const MAX_PRECISION: usize = 15;
+
+pub const USAGE: &str = "\
+Usage: rust_single_server_queue [OPTIONS]
+       rust_single_server_queue view <session-file>
+       rust_single_server_queue analyze-input <data-file>
+       rust_single_server_queue samples <sample-file> [<from> <to>]
+
+Model parameters are asked interactively; press Enter to accept defaults.
+`view` reopens a session saved from the viewer's File menu without simulating.
+`analyze-input` fits service-time distributions to a column of measured durations.
+`samples` prints the samples of a --stream-chunks file as CSV, only those from <from>
+to <to> if given, reading only the chunks that cover them.
+
+Options:
+  --compare                  Ask for a second configuration and overlay both runs
+  --calibrate                Search for the λ or μ that reproduces an observed metric
+  --step                     Process one event per Enter press and print the state
+  --pace <units>             Print events as they happen, <units> simulated time per second
+  --fast-forward <time>      With --step or --pace, process the events before this time
+                             without printing them
+  --live                     Open a window with Step / Run controls driving the simulation
+  --replications <n>         Run the configuration n times and plot queue length quantiles
+  --ensemble-csv <path>      Write the cross-replication quantiles to a CSV file
+  --gantt <path>             Draw each of the first customers as a bar from arrival to
+                             departure, split into waiting and service, to an SVG or PNG
+  --gantt-customers <n>      Customers on the --gantt chart, by arrival (default 50, at
+                             most 2000)
+  --annotations-csv <path>   Write the marked moments (warm-up end, failures, ...) to a CSV file
+  --server-utilization-csv <path>
+                             Write each server's share of the capacity over time to a CSV file
+  --stream-csv <path>        Write every sample to a CSV file as it is taken, at the finest
+                             interval and without holding the run in memory
+  --stream-chunks <path>     Append every sample to a binary file in chunks, keeping only the
+                             chunk being filled and the latest samples in memory; the viewer
+                             reads older ranges from the file as you zoom out to them, and
+                             `samples` prints it
+  --chunk-size <n>           Samples per chunk of --stream-chunks (default 4096)
+  --occupancy-csv <path>     Write the share of time at each queue length per time bucket,
+                             the data behind the heatmap, to a CSV file
+  --wait-ecdf-csv <path>     Write the empirical CDF of the waits, with the M/M/1 CDF where
+                             it applies, to a CSV file
+  --wait-qq-csv <path>       Write the quantiles of the waits against the M/M/1 ones to a
+                             CSV file, the data of a QQ plot
+  --seed <n>                 Seed the random streams for a reproducible run
+  --seed-scan <n>            Run the configuration with n consecutive seeds and report how
+                             much each metric varies across them
+  --seed-scan-csv <path>     Where the per-seed metrics go (default seed_scan.csv)
+  --seed-scan-cv <threshold> Flag metrics whose coefficient of variation across seeds
+                             exceeds the threshold (default 0.05)
+  --days <n>                 Terminating simulation: run n independent days, each from the
+                             initial state up to the simulation time, and report the
+                             cross-day mean and 95% CI of the daily metrics
+  --plan-run-length <r>      Run a short pilot and estimate how many customers a run needs
+                             for a 95% CI of the mean wait within r of it, e.g. 0.01
+  --pilot-customers <n>      Customers served in the pilot of --plan-run-length (default 20000)
+  --rng <backend>            Random number generator: fastrand (default), xoshiro or stable;
+                             only stable (an in-tree PCG32) keeps seeded runs reproducible
+                             across dependency updates
+  --parquet <path>           Alias of --format parquet --out <path>
+  --plot-config <path>       Choose the viewer's plots, their order, colors, titles, y ranges
+                             and log scales from the [plots] section of a TOML file
+  --instrument               Count events per type and time their handlers
+  --clock-audit              Check that the clock resolves the shortest inter-event times,
+                             warning when a long horizon quantizes them
+  --waiting-cost <c>         Price each customer in the system at c per time unit
+  --server-cost <c>          Price each server at c per time unit, busy or idle
+  --loss-cost <c>            Charge c for each customer turned away or dropped late; with
+                             any cost set, runs report their cost and seed scans and
+                             experiments rank their runs by cost per time unit
+  --format <format>          Print the observed-vs-theoretical comparison as plain
+                             (default), markdown or json; parquet instead writes the
+                             samples of every run into the one Parquet file given by --out
+                             and leaves the printed comparison plain
+  --out <path>               Where --format parquet writes its file
+  --precision <digits>       Decimals of the printed metrics (default 4); times, costs and
+                             percentages get two fewer. JSON always keeps full precision
+  --scientific               Print counts and events per second from 100000 on in
+                             scientific notation
+  --validate <tolerance>     Check the results against theory and exit with status 1 if
+                             a relative error exceeds the tolerance, e.g. 0.05
+  --validate-metrics <list>  Metrics to check, comma-separated (default all):
+                             wait, waiting, queue, system, utilization, throughput,
+                             blocking, carried_load
+  --verify                   Simulate built-in M/M/1, M/D/1, M/M/2 and M/M/1/K cases with
+                             fixed seeds, check them against theory, compare the order
+                             of a seeded run's first events with golden/event_order.csv,
+                             pin the stable generator's output and exit with status 1 if
+                             anything is off
+  --analytic-only            Print the closed-form metrics of the configured model (M/M/c,
+                             M/G/1, M/M/1/K or M/M/c/c) without simulating; with
+                             --format json as the report schema marked analytic
+  --analytic-compare         After the run, print the observed metrics next to the exact
+                             closed forms of the configured model, including M/G/1 and
+                             M/M/1/K
+  --max-wall-time <seconds>  Stop each run after this much real time, whatever the stop
+                             condition, and report how far it got
+  --record-rng <path>        Record every random draw of the run, tagged by stream and
+                             event, to a binary file for --replay-rng
+  --replay-rng <path>        Draw the random numbers from a recording instead of the
+                             generator, stopping with an error once a stream runs out or
+                             draws a different number of times in an event
+  --samples <n>              Keep at most n samples per plotted series (default 5000)
+  --skip-initial-sample      Start sampling one interval in instead of at the empty t = 0 state
+  --interval-averages        Also sample the queue length and customers in system averaged
+                             over each sample interval, for smoother plots
+  --event-log <n>            Keep the last n events for a post-mortem dump (default 10000, 0 = off)
+  --event-dump <path>        Where the events before an anomaly go (default post_mortem.csv)
+  --anomaly-wait <k>         Flag a mean wait above k times the M/M/c mean (default 10)
+  --anomaly-queue <n>        Flag more than n customers waiting in the queue
+  --consistency-check <tol>  Check every 10000 events that the queue obeys Little's law and
+                             utilization matches throughput within a relative tolerance,
+                             warning once either is off, e.g. 0.05
+  --throughput-window <w>    Time units covered by the windowed throughput (default 1000)
+  --stats-window <w>         Also report the mean wait, queue length and utilization over the
+                             last w time units, and plot the windowed mean wait and utilization
+  --initial-queue <n>        Start with n customers in the system instead of empty
+  --arrivals <shape>         Interarrival times with mean 1/λ: poisson (default),
+                             deterministic, erlang:<k>, gamma:<shape> or lognormal:<σ>;
+                             with exponential service the mean wait is compared with
+                             the exact G/M/1 one
+  --admission <periods>      Only admit arrivals in the open periods, e.g. 0-480,540-1020
+  --defer-closed             Let arrivals while closed come back at the next opening
+                             instead of leaving
+  --setup <mean>             Switch the station on for a setup of this mean duration at the
+                             start of every busy period
+  --setup-deterministic      Make every setup last exactly its mean instead of an
+                             exponential time
+  --wait-speed-up <k>,<cap>  Serve a customer who waited w at rate μ · min(1 + k·w, cap),
+                             and compare with a paired run at μ from the same seed
+  --deadline <mean>          Give every customer a deadline this long on average after its
+                             arrival and report how many leave late
+  --deadline-deterministic   Make every deadline exactly its mean instead of an
+                             exponential time
+  --edf                      Serve the waiting customer with the soonest deadline first
+                             instead of the one that arrived first
+  --drop-late                Drop customers whose deadline passed while they waited
+                             instead of serving them late
+  --compare-disciplines <n>  Run n seeds under FIFO and under earliest deadline first and
+                             report the paired differences of the deadline metrics
+  --round-robin <quantum>    Serve each customer for at most this long at a time, then send
+                             it to the back of the queue if unfinished
+  --min-quantum <q>          Refuse quanta below q, which would flood the run with quantum
+                             events (default 0.001)
+  --compare-quanta <q,...>   Run 10 seeds under FIFO and under round robin with each quantum
+                             and report sojourn, slowdown and event inflation against
+                             processor sharing
+  --experiment <r>           Run the 2^k factorial design of the --factor levels with r seeds
+                             per cell and report main and two-way interaction effects on
+                             the mean wait
+  --factor <name>=<low>,<high>
+                             Vary lambda, mu, capacity, servers or discipline (fifo, edf,
+                             rr) between two levels in --experiment; repeat per factor
+  --center-points <n>        Add n runs with every factor at its midpoint to --experiment
+                             to check for curvature
+  --experiment-csv <path>    Where the runs of --experiment go (default experiment.csv)
+  --change <time>:<param>=<value>
+                             Set lambda, mu or servers to a new value at this time, e.g.
+                             500:mu=2; repeat for more changes, in order of time.
+                             Customers waiting at a change of mu are served at the
+                             new rate
+  --max-queue <n>            Turn away arrivals beyond n waiting customers instead of
+                             letting an overloaded queue exhaust memory
+  --explosion-limit <n>      Abort a run as unstable once more than n customers wait
+  --sqlite <db>              Store samples and customer traces in an SQLite database
+  --sqlite-replace <run_id>  Overwrite an existing run instead of appending a new one
+  -h, --help                 Print this help";
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    pub compare: bool,
+    pub calibrate: bool,
+    pub step: bool,
+    pub pace: Option<f64>,
+    pub fast_forward: Option<f64>,
+    pub live: bool,
+    pub replications: usize,
+    pub ensemble_csv: Option<PathBuf>,
+    pub gantt: Option<PathBuf>,
+    pub gantt_customers: Option<usize>,
+    pub annotations_csv: Option<PathBuf>,
+    pub server_utilization_csv: Option<PathBuf>,
+    pub occupancy_csv: Option<PathBuf>,
+    pub wait_ecdf_csv: Option<PathBuf>,
+    pub wait_qq_csv: Option<PathBuf>,
+    pub stream_csv: Option<PathBuf>,
+    pub stream_chunks: Option<PathBuf>,
+    pub chunk_size: Option<usize>,
+    pub seed: Option<u64>,
+    pub seed_scan: Option<usize>,
+    pub seed_scan_csv: Option<PathBuf>,
+    pub seed_scan_cv: Option<f64>,
+    /// Independent days of a terminating simulation
+    pub days: Option<usize>,
+    /// Target relative half-width of the mean wait's interval to plan a run length for
+    pub plan_run_length: Option<f64>,
+    pub pilot_customers: Option<u64>,
+    pub rng_backend: RngBackend,
+    pub parquet_path: Option<PathBuf>,
+    /// `--format parquet`, which takes its file from `--out`
+    pub parquet_format: bool,
+    pub out_path: Option<PathBuf>,
+    /// TOML file whose `[plots]` section lays out the viewer
+    pub plot_config: Option<PathBuf>,
+    pub instrument: bool,
+    pub clock_audit: bool,
+    pub cost_model: CostModel,
+    pub format: OutputFormat,
+    pub numbers: NumberFormat,
+    pub validate: Option<f64>,
+    pub validate_metrics: Option<Vec<String>>,
+    pub verify: bool,
+    pub analytic_only: bool,
+    pub analytic_compare: bool,
+    pub max_wall_time: Option<Duration>,
+    pub record_rng: Option<PathBuf>,
+    pub replay_rng: Option<PathBuf>,
+    pub max_queue_length: Option<usize>,
+    pub explosion_limit: Option<usize>,
+    pub initial_queue: usize,
+    /// Shape of the interarrival times, exponential for Poisson arrivals
+    pub arrival_kind: ServiceKind,
+    pub admission_periods: Option<Vec<(f64, f64)>>,
+    pub defer_closed: bool,
+    pub setup_mean: Option<f64>,
+    pub setup_deterministic: bool,
+    /// Slope and cap of the service speed-up by the wait
+    pub wait_speed_up: Option<(f64, f64)>,
+    pub deadline_mean: Option<f64>,
+    pub deadline_deterministic: bool,
+    pub edf: bool,
+    pub drop_late: bool,
+    pub compare_disciplines: Option<usize>,
+    pub quantum: Option<f64>,
+    pub min_quantum: Option<f64>,
+    pub compare_quanta: Option<Vec<f64>>,
+    pub experiment: Option<usize>,
+    pub factors: Vec<Factor>,
+    pub center_points: usize,
+    pub experiment_csv: Option<PathBuf>,
+    pub parameter_changes: Vec<ParameterChange>,
+    pub throughput_window: Option<f64>,
+    pub statistics_window: Option<f64>,
+    pub max_samples: Option<usize>,
+    pub event_log_length: Option<usize>,
+    pub event_dump: Option<PathBuf>,
+    pub anomaly_wait_multiple: Option<f64>,
+    pub anomaly_queue_length: Option<usize>,
+    pub consistency_tolerance: Option<f64>,
+    pub skip_initial_sample: bool,
+    pub interval_averages: bool,
+    pub sqlite_path: Option<PathBuf>,
+    pub sqlite_replace: Option<i64>,
+    /// Session file to open in the viewer instead of running a simulation
+    pub view_session: Option<PathBuf>,
+    /// Data file of durations to fit distributions to instead of running a simulation
+    pub analyze_input: Option<PathBuf>,
+    /// Sample file to print instead of running a simulation, with the time range to print
+    pub read_samples: Option<(PathBuf, Option<(f64, f64)>)>,
+    pub help: bool,
+}
+
+impl CliOptions {
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Self::default();
+        let mut args = args.into_iter().peekable();
+
+        if args.next_if(|arg| arg == "view").is_some() {
+            let path = args
+                .next()
+                .ok_or_else(|| "missing session file for view".to_string())?;
+            options.view_session = Some(PathBuf::from(path));
+            return match args.next() {
+                None => Ok(options),
+                Some(arg) => Err(format!(
+                    "unexpected argument '{}' after the session file",
+                    arg
+                )),
+            };
+        }
+        if args.next_if(|arg| arg == "analyze-input").is_some() {
+            let path = args
+                .next()
+                .ok_or_else(|| "missing data file for analyze-input".to_string())?;
+            options.analyze_input = Some(PathBuf::from(path));
+            return match args.next() {
+                None => Ok(options),
+                Some(arg) => Err(format!("unexpected argument '{}' after the data file", arg)),
+            };
+        }
+
+        if args.next_if(|arg| arg == "samples").is_some() {
+            let path = args
+                .next()
+                .ok_or_else(|| "missing sample file for samples".to_string())?;
+            let range = match (args.next(), args.next()) {
+                (None, _) => None,
+                (Some(from), Some(to)) => {
+                    let parse = |value: &str| {
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|time| !time.is_nan())
+                            .ok_or_else(|| format!("invalid time for samples: '{}'", value))
+                    };
+                    Some((parse(&from)?, parse(&to)?))
+                }
+                (Some(_), None) => return Err("samples needs both <from> and <to>".to_string()),
+            };
+            options.read_samples = Some((PathBuf::from(path), range));
+            return match args.next() {
+                None => Ok(options),
+                Some(arg) => Err(format!(
+                    "unexpected argument '{}' after the time range",
+                    arg
+                )),
+            };
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replications" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.replications = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                }
+                "--ensemble-csv" => {
+                    options.ensemble_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--gantt" => {
+                    options.gantt = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--gantt-customers" => {
+                    let value = value_for(&arg, args.next())?;
+                    let customers = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| (1..=MAX_GANTT_CUSTOMERS).contains(n))
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid count for {}: '{}' (1 to {})",
+                                arg, value, MAX_GANTT_CUSTOMERS
+                            )
+                        })?;
+                    options.gantt_customers = Some(customers);
+                }
+                "--annotations-csv" => {
+                    options.annotations_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--server-utilization-csv" => {
+                    options.server_utilization_csv =
+                        Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--stream-csv" => {
+                    options.stream_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--stream-chunks" => {
+                    options.stream_chunks = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--chunk-size" => {
+                    let value = value_for(&arg, args.next())?;
+                    let size = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.chunk_size = Some(size as usize);
+                }
+                "--occupancy-csv" => {
+                    options.occupancy_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--wait-ecdf-csv" => {
+                    options.wait_ecdf_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--wait-qq-csv" => {
+                    options.wait_qq_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--seed" => {
+                    let value = value_for(&arg, args.next())?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed for {}: '{}'", arg, value))?;
+                    options.seed = Some(seed);
+                }
+                "--seed-scan" => {
+                    let value = value_for(&arg, args.next())?;
+                    let seeds = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 2)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.seed_scan = Some(seeds);
+                }
+                "--waiting-cost" | "--server-cost" | "--loss-cost" => {
+                    let value = value_for(&arg, args.next())?;
+                    let cost = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|cost| cost.is_finite() && *cost >= 0.0)
+                        .ok_or_else(|| format!("invalid cost for {}: '{}'", arg, value))?;
+                    match arg.as_str() {
+                        "--waiting-cost" => options.cost_model.waiting_cost = cost,
+                        "--server-cost" => options.cost_model.server_cost = cost,
+                        _ => options.cost_model.loss_cost = cost,
+                    }
+                }
+                "--seed-scan-csv" => {
+                    options.seed_scan_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--seed-scan-cv" => {
+                    let value = value_for(&arg, args.next())?;
+                    let threshold = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|threshold| *threshold >= 0.0)
+                        .ok_or_else(|| format!("invalid threshold for {}: '{}'", arg, value))?;
+                    options.seed_scan_cv = Some(threshold);
+                }
+                "--days" => {
+                    let value = value_for(&arg, args.next())?;
+                    let days = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 2)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.days = Some(days);
+                }
+                "--plan-run-length" => {
+                    let value = value_for(&arg, args.next())?;
+                    let target = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|target| *target > 0.0 && *target < 1.0)
+                        .ok_or_else(|| {
+                            format!("invalid relative half-width for {}: '{}'", arg, value)
+                        })?;
+                    options.plan_run_length = Some(target);
+                }
+                "--pilot-customers" => {
+                    let value = value_for(&arg, args.next())?;
+                    let customers = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 100)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.pilot_customers = Some(customers);
+                }
+                "--rng" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.rng_backend = match value.as_str() {
+                        "fastrand" => RngBackend::Fastrand,
+                        "xoshiro" => RngBackend::Xoshiro256PlusPlus,
+                        "stable" | "pcg32" => RngBackend::Pcg32,
+                        _ => return Err(format!("unknown backend for {}: '{}'", arg, value)),
+                    };
+                }
+                "--parquet" => {
+                    options.parquet_path = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--plot-config" => {
+                    options.plot_config = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--sqlite" => {
+                    options.sqlite_path = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--sqlite-replace" => {
+                    let value = value_for(&arg, args.next())?;
+                    let run_id = value
+                        .parse()
+                        .map_err(|_| format!("invalid run id for {}: '{}'", arg, value))?;
+                    options.sqlite_replace = Some(run_id);
+                }
+                "--throughput-window" => {
+                    let value = value_for(&arg, args.next())?;
+                    let window = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|window| *window > 0.0)
+                        .ok_or_else(|| format!("invalid window for {}: '{}'", arg, value))?;
+                    options.throughput_window = Some(window);
+                }
+                "--stats-window" => {
+                    let value = value_for(&arg, args.next())?;
+                    let window = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|window| *window > 0.0 && window.is_finite())
+                        .ok_or_else(|| format!("invalid window for {}: '{}'", arg, value))?;
+                    options.statistics_window = Some(window);
+                }
+                "--record-rng" => {
+                    options.record_rng = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--replay-rng" => {
+                    options.replay_rng = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--max-wall-time" => {
+                    let value = value_for(&arg, args.next())?;
+                    let seconds = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|seconds| *seconds > 0.0 && seconds.is_finite())
+                        .ok_or_else(|| format!("invalid seconds for {}: '{}'", arg, value))?;
+                    options.max_wall_time = Some(Duration::from_secs_f64(seconds));
+                }
+                "--samples" => {
+                    let value = value_for(&arg, args.next())?;
+                    let samples = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 2)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.max_samples = Some(samples);
+                }
+                "--event-log" => {
+                    let value = value_for(&arg, args.next())?;
+                    let length = value
+                        .parse()
+                        .map_err(|_| format!("invalid length for {}: '{}'", arg, value))?;
+                    options.event_log_length = Some(length);
+                }
+                "--event-dump" => {
+                    options.event_dump = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--anomaly-wait" => {
+                    let value = value_for(&arg, args.next())?;
+                    let multiple = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|multiple| *multiple > 0.0)
+                        .ok_or_else(|| format!("invalid multiple for {}: '{}'", arg, value))?;
+                    options.anomaly_wait_multiple = Some(multiple);
+                }
+                "--anomaly-queue" => {
+                    let value = value_for(&arg, args.next())?;
+                    let limit = value
+                        .parse()
+                        .map_err(|_| format!("invalid length for {}: '{}'", arg, value))?;
+                    options.anomaly_queue_length = Some(limit);
+                }
+                "--consistency-check" => {
+                    let value = value_for(&arg, args.next())?;
+                    let tolerance = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|tolerance| *tolerance > 0.0)
+                        .ok_or_else(|| format!("invalid tolerance for {}: '{}'", arg, value))?;
+                    options.consistency_tolerance = Some(tolerance);
+                }
+                "--max-queue" => {
+                    let value = value_for(&arg, args.next())?;
+                    let limit = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("invalid length for {}: '{}'", arg, value))?;
+                    options.max_queue_length = Some(limit);
+                }
+                "--explosion-limit" => {
+                    let value = value_for(&arg, args.next())?;
+                    let limit = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("invalid length for {}: '{}'", arg, value))?;
+                    options.explosion_limit = Some(limit);
+                }
+                "--initial-queue" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.initial_queue = value
+                        .parse()
+                        .map_err(|_| format!("invalid count for {}: '{}'", arg, value))?;
+                }
+                "--arrivals" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.arrival_kind = parse_arrivals(&value)
+                        .ok_or_else(|| format!("invalid arrivals for {}: '{}'", arg, value))?;
+                }
+                "--admission" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.admission_periods = Some(
+                        parse_periods(&value)
+                            .ok_or_else(|| format!("invalid periods for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--setup" => {
+                    let value = value_for(&arg, args.next())?;
+                    let mean = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|mean| *mean > 0.0 && mean.is_finite())
+                        .ok_or_else(|| format!("invalid mean for {}: '{}'", arg, value))?;
+                    options.setup_mean = Some(mean);
+                }
+                "--wait-speed-up" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.wait_speed_up = Some(
+                        parse_speed_up(&value)
+                            .ok_or_else(|| format!("invalid speed-up for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--deadline" => {
+                    let value = value_for(&arg, args.next())?;
+                    let mean = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|mean| *mean > 0.0 && mean.is_finite())
+                        .ok_or_else(|| format!("invalid mean for {}: '{}'", arg, value))?;
+                    options.deadline_mean = Some(mean);
+                }
+                "--experiment" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.experiment = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--factor" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.factors.push(
+                        parse_factor(&value)
+                            .ok_or_else(|| format!("invalid factor for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--center-points" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.center_points = value
+                        .parse()
+                        .map_err(|_| format!("invalid count for {}: '{}'", arg, value))?;
+                }
+                "--experiment-csv" => {
+                    options.experiment_csv = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--change" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.parameter_changes.push(
+                        parse_change(&value)
+                            .ok_or_else(|| format!("invalid change for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--compare-disciplines" => {
+                    let value = value_for(&arg, args.next())?;
+                    let seeds = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 2)
+                        .ok_or_else(|| format!("invalid count for {}: '{}'", arg, value))?;
+                    options.compare_disciplines = Some(seeds);
+                }
+                "--round-robin" => {
+                    let value = value_for(&arg, args.next())?;
+                    let quantum = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|quantum| *quantum > 0.0 && quantum.is_finite())
+                        .ok_or_else(|| format!("invalid quantum for {}: '{}'", arg, value))?;
+                    options.quantum = Some(quantum);
+                }
+                "--min-quantum" => {
+                    let value = value_for(&arg, args.next())?;
+                    let quantum = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|quantum| *quantum > 0.0 && quantum.is_finite())
+                        .ok_or_else(|| format!("invalid quantum for {}: '{}'", arg, value))?;
+                    options.min_quantum = Some(quantum);
+                }
+                "--compare-quanta" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.compare_quanta = Some(
+                        parse_quanta(&value)
+                            .ok_or_else(|| format!("invalid quanta for {}: '{}'", arg, value))?,
+                    );
+                }
+                "--validate" => {
+                    let value = value_for(&arg, args.next())?;
+                    let tolerance = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|tolerance| *tolerance >= 0.0)
+                        .ok_or_else(|| format!("invalid tolerance for {}: '{}'", arg, value))?;
+                    options.validate = Some(tolerance);
+                }
+                "--validate-metrics" => {
+                    let value = value_for(&arg, args.next())?;
+                    let metrics: Vec<String> =
+                        value.split(',').map(|key| key.trim().to_string()).collect();
+                    if let Some(unknown) = metrics
+                        .iter()
+                        .find(|key| !METRIC_KEYS.contains(&key.as_str()))
+                    {
+                        return Err(format!("unknown metric for {}: '{}'", arg, unknown));
+                    }
+                    options.validate_metrics = Some(metrics);
+                }
+                "--format" => {
+                    let value = value_for(&arg, args.next())?;
+                    if value == "parquet" {
+                        options.parquet_format = true;
+                    } else {
+                        options.format = value
+                            .parse()
+                            .map_err(|_| format!("unknown format for {}: '{}'", arg, value))?;
+                    }
+                }
+                "--out" => {
+                    options.out_path = Some(PathBuf::from(value_for(&arg, args.next())?));
+                }
+                "--precision" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.numbers.precision = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| *digits <= MAX_PRECISION)
+                        .ok_or_else(|| format!("invalid digits for {}: '{}'", arg, value))?;
+                }
+                "--scientific" => options.numbers.scientific = true,
+                "--compare" => options.compare = true,
+                "--calibrate" => options.calibrate = true,
+                "--step" => options.step = true,
+                "--pace" => {
+                    let value = value_for(&arg, args.next())?;
+                    let pace = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|pace| *pace > 0.0)
+                        .ok_or_else(|| format!("invalid speed for {}: '{}'", arg, value))?;
+                    options.pace = Some(pace);
+                }
+                "--fast-forward" => {
+                    let value = value_for(&arg, args.next())?;
+                    let time = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|time| *time >= 0.0 && time.is_finite())
+                        .ok_or_else(|| format!("invalid time for {}: '{}'", arg, value))?;
+                    options.fast_forward = Some(time);
+                }
+                "--live" => options.live = true,
+                "--instrument" => options.instrument = true,
+                "--clock-audit" => options.clock_audit = true,
+                "--verify" => options.verify = true,
+                "--analytic-only" => options.analytic_only = true,
+                "--analytic-compare" => options.analytic_compare = true,
+                "--skip-initial-sample" => options.skip_initial_sample = true,
+                "--interval-averages" => options.interval_averages = true,
+                "--defer-closed" => options.defer_closed = true,
+                "--setup-deterministic" => options.setup_deterministic = true,
+                "--deadline-deterministic" => options.deadline_deterministic = true,
+                "--edf" => options.edf = true,
+                "--drop-late" => options.drop_late = true,
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        match (options.parquet_format, options.out_path.take()) {
+            (true, None) => return Err("--format parquet requires --out <path>".to_string()),
+            (false, Some(_)) => return Err("--out requires --format parquet".to_string()),
+            (true, Some(_)) if options.parquet_path.is_some() => {
+                return Err(
+                    "--parquet is an alias of --format parquet --out <path>; give only one"
+                        .to_string(),
+                );
+            }
+            (true, Some(path)) => options.parquet_path = Some(path),
+            (false, None) => {}
+        }
+        if options.ensemble_csv.is_some() && options.replications < 2 {
+            return Err("--ensemble-csv requires --replications <n> with n >= 2".to_string());
+        }
+        if options.replications > 1 && options.compare {
+            return Err("--replications cannot be combined with --compare".to_string());
+        }
+        if options.seed_scan.is_some() && (options.compare || options.replications > 1) {
+            return Err(
+                "--seed-scan cannot be combined with --compare or --replications".to_string(),
+            );
+        }
+        if (options.seed_scan_csv.is_some() || options.seed_scan_cv.is_some())
+            && options.seed_scan.is_none()
+        {
+            return Err("--seed-scan-csv and --seed-scan-cv require --seed-scan <n>".to_string());
+        }
+        if options.days.is_some()
+            && (options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.compare_disciplines.is_some())
+        {
+            return Err(
+                "--days cannot be combined with --compare, --replications, --seed-scan or --compare-disciplines"
+                    .to_string(),
+            );
+        }
+        if options.pilot_customers.is_some() && options.plan_run_length.is_none() {
+            return Err("--pilot-customers requires --plan-run-length <r>".to_string());
+        }
+        if options.plan_run_length.is_some()
+            && (options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.days.is_some()
+                || options.compare_disciplines.is_some()
+                || options.compare_quanta.is_some()
+                || options.analytic_only)
+        {
+            return Err(
+                "--plan-run-length cannot be combined with --compare, --replications, --seed-scan, --days, --compare-disciplines, --compare-quanta or --analytic-only"
+                    .to_string(),
+            );
+        }
+        if options.validate_metrics.is_some() && options.validate.is_none() {
+            return Err("--validate-metrics requires --validate <tolerance>".to_string());
+        }
+        if options.defer_closed && options.admission_periods.is_none() {
+            return Err("--defer-closed requires --admission <periods>".to_string());
+        }
+        if options.setup_deterministic && options.setup_mean.is_none() {
+            return Err("--setup-deterministic requires --setup <mean>".to_string());
+        }
+        if options.chunk_size.is_some() && options.stream_chunks.is_none() {
+            return Err("--chunk-size requires --stream-chunks <path>".to_string());
+        }
+        if (options.deadline_deterministic
+            || options.edf
+            || options.drop_late
+            || options.compare_disciplines.is_some())
+            && options.deadline_mean.is_none()
+        {
+            return Err(
+                "--deadline-deterministic, --edf, --drop-late and --compare-disciplines require --deadline <mean>"
+                    .to_string(),
+            );
+        }
+        if options.compare_disciplines.is_some()
+            && (options.compare || options.replications > 1 || options.seed_scan.is_some())
+        {
+            return Err(
+                "--compare-disciplines cannot be combined with --compare, --replications or --seed-scan"
+                    .to_string(),
+            );
+        }
+        if options.quantum.is_some() && options.edf {
+            return Err("--round-robin cannot be combined with --edf".to_string());
+        }
+        if options.min_quantum.is_some()
+            && options.quantum.is_none()
+            && options.compare_quanta.is_none()
+        {
+            return Err(
+                "--min-quantum requires --round-robin <quantum> or --compare-quanta <q,...>"
+                    .to_string(),
+            );
+        }
+        if options.compare_quanta.is_some()
+            && (options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.compare_disciplines.is_some()
+                || options.days.is_some())
+        {
+            return Err(
+                "--compare-quanta cannot be combined with --compare, --replications, --seed-scan, --compare-disciplines or --days"
+                    .to_string(),
+            );
+        }
+        if (!options.factors.is_empty()
+            || options.center_points > 0
+            || options.experiment_csv.is_some())
+            && options.experiment.is_none()
+        {
+            return Err(
+                "--factor, --center-points and --experiment-csv require --experiment <r>"
+                    .to_string(),
+            );
+        }
+        if options.experiment.is_some() && options.factors.is_empty() {
+            return Err("--experiment needs at least one --factor <name>=<low>,<high>".to_string());
+        }
+        if options.experiment.is_some()
+            && (options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.compare_disciplines.is_some()
+                || options.compare_quanta.is_some()
+                || options.days.is_some()
+                || options.plan_run_length.is_some())
+        {
+            return Err(
+                "--experiment cannot be combined with --compare, --replications, --seed-scan, --compare-disciplines, --compare-quanta, --days or --plan-run-length"
+                    .to_string(),
+            );
+        }
+        if options.analytic_only
+            && (options.analytic_compare
+                || options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.days.is_some()
+                || options.compare_disciplines.is_some()
+                || options.compare_quanta.is_some()
+                || options.validate.is_some()
+                || options.live
+                || options.step
+                || options.pace.is_some())
+        {
+            return Err(
+                "--analytic-only does not simulate, so it cannot be combined with --analytic-compare, --compare, --replications, --seed-scan, --days, --compare-disciplines, --compare-quanta, --validate, --live, --step or --pace"
+                    .to_string(),
+            );
+        }
+        if options.max_wall_time.is_some()
+            && (options.analytic_only
+                || options.seed_scan.is_some()
+                || options.days.is_some()
+                || options.compare_disciplines.is_some()
+                || options.compare_quanta.is_some()
+                || options.live
+                || options.step
+                || options.pace.is_some())
+        {
+            return Err(
+                "--max-wall-time only limits regular runs and replications, not --analytic-only, --seed-scan, --days, --compare-disciplines, --compare-quanta, --live, --step or --pace"
+                    .to_string(),
+            );
+        }
+        if options.record_rng.is_some() && options.replay_rng.is_some() {
+            return Err("--record-rng cannot be combined with --replay-rng".to_string());
+        }
+        if (options.record_rng.is_some() || options.replay_rng.is_some())
+            && (options.analytic_only
+                || options.compare
+                || options.replications > 1
+                || options.seed_scan.is_some()
+                || options.days.is_some()
+                || options.compare_disciplines.is_some()
+                || options.compare_quanta.is_some()
+                || options.live
+                || options.step
+                || options.pace.is_some())
+        {
+            return Err(
+                "--record-rng and --replay-rng only apply to a single regular run, not --analytic-only, --compare, --replications, --seed-scan, --days, --compare-disciplines, --compare-quanta, --live, --step or --pace"
+                    .to_string(),
+            );
+        }
+        if options.fast_forward.is_some() && !options.step && options.pace.is_none() {
+            return Err("--fast-forward requires --step or --pace <units>".to_string());
+        }
+        if options.sqlite_replace.is_some() && options.sqlite_path.is_none() {
+            return Err("--sqlite-replace requires --sqlite <db>".to_string());
+        }
+        if options.gantt_customers.is_some() && options.gantt.is_none() {
+            return Err("--gantt-customers requires --gantt <path>".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+fn value_for(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("missing value for {}", flag))
+}
+
+/// `poisson`, `deterministic`, `erlang:<k>`, `gamma:<shape>` or `lognormal:<σ>`;
+/// the parameter is checked with the configuration
+fn parse_arrivals(value: &str) -> Option<ServiceKind> {
+    let (shape, parameter) = match value.split_once(':') {
+        Some((shape, parameter)) => (shape.trim(), Some(parameter.trim())),
+        None => (value.trim(), None),
+    };
+    Some(match (shape, parameter) {
+        ("poisson" | "exponential", None) => ServiceKind::Exponential,
+        ("deterministic", None) => ServiceKind::Deterministic,
+        ("erlang", Some(phases)) => ServiceKind::Erlang(phases.parse().ok()?),
+        ("gamma", Some(shape)) => ServiceKind::Gamma(shape.parse().ok()?),
+        ("lognormal", Some(sigma)) => ServiceKind::Lognormal(sigma.parse().ok()?),
+        _ => return None,
+    })
+}
+
+/// Comma-separated `open-close` pairs; their order is checked with the configuration
+fn parse_periods(value: &str) -> Option<Vec<(f64, f64)>> {
+    value
+        .split(',')
+        .map(|period| {
+            let (open, close) = period.trim().split_once('-')?;
+            Some((open.trim().parse().ok()?, close.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// `k,cap` as a slope per time unit waited and the largest multiple of μ; the
+/// rates they give are checked with the configuration
+fn parse_speed_up(value: &str) -> Option<(f64, f64)> {
+    let (per_wait, max_factor) = value.split_once(',')?;
+    Some((
+        per_wait.trim().parse().ok()?,
+        max_factor.trim().parse().ok()?,
+    ))
+}
+
+/// Comma-separated positive quanta; their minimum is checked with the configuration
+fn parse_quanta(value: &str) -> Option<Vec<f64>> {
+    value
+        .split(',')
+        .map(|quantum| {
+            quantum
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|quantum| *quantum > 0.0 && quantum.is_finite())
+        })
+        .collect()
+}
+
+/// `name=low,high` with the name `lambda`, `mu`, `capacity`, `servers` or
+/// `discipline`, whose levels are `fifo`, `edf` or `rr`; the levels are checked
+/// with the configuration of each cell
+fn parse_factor(value: &str) -> Option<Factor> {
+    let (name, levels) = value.split_once('=')?;
+    let (low, high) = levels.split_once(',')?;
+    let (low, high) = (low.trim(), high.trim());
+    let rate = |level: &str| level.parse::<f64>().ok();
+    let count = |level: &str| level.parse::<usize>().ok();
+    let discipline = |level: &str| match level {
+        "fifo" => Some(QueueDiscipline::Fifo),
+        "edf" => Some(QueueDiscipline::EarliestDeadlineFirst),
+        "rr" => Some(QueueDiscipline::RoundRobin),
+        _ => None,
+    };
+    Some(match name.trim() {
+        "lambda" => Factor::ArrivalRate {
+            low: rate(low)?,
+            high: rate(high)?,
+        },
+        "mu" => Factor::ServiceRate {
+            low: rate(low)?,
+            high: rate(high)?,
+        },
+        "capacity" => Factor::Capacity {
+            low: count(low)?,
+            high: count(high)?,
+        },
+        "servers" => Factor::Servers {
+            low: count(low)?,
+            high: count(high)?,
+        },
+        "discipline" => Factor::Discipline {
+            low: discipline(low)?,
+            high: discipline(high)?,
+        },
+        _ => return None,
+    })
+}
+
+/// `time:parameter=value` with the parameter `lambda`, `mu` or `servers`; the
+/// values are checked with the configuration
+fn parse_change(value: &str) -> Option<ParameterChange> {
+    let (time, assignment) = value.split_once(':')?;
+    let (parameter, value) = assignment.split_once('=')?;
+    let parameter = match parameter.trim() {
+        "lambda" => ChangedParameter::ArrivalRate,
+        "mu" => ChangedParameter::ServiceRate,
+        "servers" => ChangedParameter::Servers,
+        _ => return None,
+    };
+    Some(ParameterChange {
+        time: time.trim().parse().ok()?,
+        parameter,
+        value: value.trim().parse().ok()?,
+    })
+}
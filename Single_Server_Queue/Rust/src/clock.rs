@@ -0,0 +1,292 @@
+//! Precision of the simulation clock at long horizons
+//!
+//! The clock is an `f64`, which resolves a time `t` to about `t · 2^-52`: at
+//! t = 1e12 that is about 2e-4 time units, at t = 1e15 already 0.125. A step
+//! much shorter than that resolution is rounded when added to the clock, by up
+//! to half a resolution, so short inter-event times are quantized and,
+//! once they drop below half a resolution, lost altogether. The results are
+//! biased without any error being raised.
+//!
+//! [`ClockAudit`] watches the steps a run actually applies and reports how close
+//! the shortest ones came to the resolution of the clock. [`EpochClock`] avoids
+//! the problem for a loop that advances time by steps: it keeps whole epochs in
+//! a `u64` and only the time since the current epoch in an `f64`, so the
+//! resolution of a step stays that of a time below [`EPOCH_LENGTH`] however long
+//! the run. [`measure_clock_precision`] compares both clocks on the same steps.
+
+use crate::distributions::{Distribution, Exponential};
+use crate::rng::RngBackend;
+
+/// Time units in one epoch of an [`EpochClock`]
+///
+/// A power of two, so an epoch count times the length is exact and taking
+/// whole epochs off the offset loses nothing.
+pub const EPOCH_LENGTH: f64 = 1_048_576.0;
+
+/// Relative rounding of a step above which [`ClockAudit::warning`] warns
+pub const CLOCK_AUDIT_THRESHOLD: f64 = 1e-6;
+
+/// Largest relative error rounding can add to a step of `dt` that ends at `time`
+#[inline]
+fn step_rounding(time: f64, dt: f64) -> f64 {
+    time.abs() * f64::EPSILON / (2.0 * dt)
+}
+
+/// A clock kept as a whole number of epochs and an offset into the current epoch
+///
+/// The offset stays below [`EPOCH_LENGTH`] after every [`EpochClock::advance`],
+/// which moves whole epochs into the count, so a step is rounded to the
+/// resolution of the offset instead of that of the total time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EpochClock {
+    epoch: u64,
+    offset: f64,
+}
+
+impl EpochClock {
+    /// Clock at t = 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clock at `time`; negative times are taken as 0
+    pub fn at(time: f64) -> Self {
+        let mut clock = Self::new();
+        clock.advance(time);
+        clock
+    }
+
+    /// Move the clock `dt` forward, rebasing into the next epochs once the offset
+    /// reaches [`EPOCH_LENGTH`]; negative and non-finite steps are ignored
+    #[inline]
+    pub fn advance(&mut self, dt: f64) {
+        if !(dt.is_finite() && dt > 0.0) {
+            return;
+        }
+        self.offset += dt;
+        if self.offset >= EPOCH_LENGTH {
+            let whole = (self.offset / EPOCH_LENGTH).floor();
+            self.epoch += whole as u64;
+            self.offset -= whole * EPOCH_LENGTH;
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Time since the start of the current epoch
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// The time as an `f64`, rounded to the resolution of a plain clock
+    #[inline]
+    pub fn now(&self) -> f64 {
+        self.epoch as f64 * EPOCH_LENGTH + self.offset
+    }
+
+    /// Time from `earlier` to this clock, exact to the resolution of the offsets
+    pub fn since(&self, earlier: &EpochClock) -> f64 {
+        let epochs = self.epoch as i128 - earlier.epoch as i128;
+        epochs as f64 * EPOCH_LENGTH + (self.offset - earlier.offset)
+    }
+}
+
+/// Shortest steps a clock was advanced by, relative to its magnitude
+///
+/// Zero steps, between events at the same instant, carry no rounding and are
+/// not counted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClockAudit {
+    steps: u64,
+    /// Steps whose rounding could exceed [`CLOCK_AUDIT_THRESHOLD`]
+    coarse_steps: u64,
+    /// The step with the smallest ratio of step to clock, as `(time, dt)`
+    smallest: Option<(f64, f64)>,
+}
+
+impl ClockAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note a step of `dt` that brought the clock to `time`
+    #[inline]
+    pub fn record(&mut self, time: f64, dt: f64) {
+        if !(dt > 0.0 && time.is_finite()) {
+            return;
+        }
+        self.steps += 1;
+        if step_rounding(time, dt) > CLOCK_AUDIT_THRESHOLD {
+            self.coarse_steps += 1;
+        }
+        let smaller = self
+            .smallest
+            .is_none_or(|(t, d)| dt * t.abs() < d * time.abs());
+        if smaller {
+            self.smallest = Some((time, dt));
+        }
+    }
+
+    /// Positive steps recorded
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Steps the clock could have rounded by more than [`CLOCK_AUDIT_THRESHOLD`] of their length
+    pub fn coarse_steps(&self) -> u64 {
+        self.coarse_steps
+    }
+
+    /// The step that was shortest relative to the clock, as `(time, dt)`
+    pub fn smallest_step(&self) -> Option<(f64, f64)> {
+        self.smallest
+    }
+
+    /// Smallest ratio of a step to the clock it was added to, `dt / time`
+    pub fn smallest_ratio(&self) -> Option<f64> {
+        self.smallest.map(|(time, dt)| dt / time.abs())
+    }
+
+    /// Largest relative error rounding could have added to a step
+    pub fn worst_rounding(&self) -> Option<f64> {
+        self.smallest.map(|(time, dt)| step_rounding(time, dt))
+    }
+
+    /// A warning if some step could have been rounded by more than
+    /// [`CLOCK_AUDIT_THRESHOLD`] of its length, `None` if the clock kept up
+    pub fn warning(&self) -> Option<String> {
+        let (time, dt) = self.smallest?;
+        let rounding = step_rounding(time, dt);
+        (rounding > CLOCK_AUDIT_THRESHOLD).then(|| {
+            format!(
+                "{} of {} steps were short for the clock: a step of {:.3e} at t = {:.3e} \
+                 may be off by {:.2e} of its length. Inter-event times are quantized at \
+                 this horizon; shorten the run or rescale the time unit",
+                self.coarse_steps, self.steps, dt, time, rounding
+            )
+        })
+    }
+}
+
+/// Error of a plain `f64` clock and of an [`EpochClock`] on the same steps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockPrecision {
+    /// Time both clocks started at
+    pub start: f64,
+    pub steps: u64,
+    /// Exact sum of the steps
+    pub elapsed: f64,
+    /// Relative error of the elapsed time read off the plain clock
+    pub plain_error: f64,
+    /// Relative error of the elapsed time read off the epoch clock
+    pub epoch_error: f64,
+}
+
+/// Advance a plain and an epoch clock from `start` by the same `steps`
+/// exponential steps of mean `mean_step`, and compare the time each says has
+/// passed with the exact sum of the steps
+///
+/// The steps come from the stable generator, so a seed gives the same result
+/// on every platform. The sum of the steps is taken apart from either clock,
+/// starting at 0 with Kahan compensation, so it is exact to well below the
+/// errors measured.
+pub fn measure_clock_precision(
+    start: f64,
+    mean_step: f64,
+    steps: u64,
+    seed: u64,
+) -> ClockPrecision {
+    let mut rng = RngBackend::Pcg32.create(Some(seed));
+    let mut step = Exponential::new(1.0 / mean_step);
+
+    let mut plain = start;
+    let origin = EpochClock::at(start);
+    let mut epoch = origin;
+    let mut elapsed = 0.0;
+    let mut compensation = 0.0;
+    for _ in 0..steps {
+        let dt = step.sample(rng.as_mut());
+        plain += dt;
+        epoch.advance(dt);
+
+        let y = dt - compensation;
+        let sum = elapsed + y;
+        compensation = (sum - elapsed) - y;
+        elapsed = sum;
+    }
+
+    let error = |measured: f64| ((measured - elapsed) / elapsed).abs();
+    ClockPrecision {
+        start,
+        steps,
+        elapsed,
+        plain_error: error(plain - start),
+        epoch_error: error(epoch.since(&origin)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_from_zero_the_plain_clock_loses_short_steps_and_the_epoch_clock_does_not() {
+        // Near 1e15 the plain clock resolves 0.125, so steps of mean 0.01 are mostly lost
+        let far = measure_clock_precision(1e15, 0.01, 100_000, 1);
+        assert!(far.plain_error > 0.5, "{:?}", far);
+        assert!(far.epoch_error < 1e-9, "{:?}", far);
+
+        // Near 1e9 both keep up, the plain clock already to fewer digits
+        let near = measure_clock_precision(1e9, 0.01, 100_000, 1);
+        assert!(near.plain_error < 1e-4, "{:?}", near);
+        assert!(near.epoch_error < 1e-9, "{:?}", near);
+        assert!(near.epoch_error < near.plain_error);
+        assert_eq!(near.elapsed, far.elapsed);
+    }
+
+    #[test]
+    fn the_epoch_clock_keeps_its_offset_within_an_epoch() {
+        let mut clock = EpochClock::at(2.5 * EPOCH_LENGTH);
+        assert_eq!((clock.epoch(), clock.offset()), (2, 0.5 * EPOCH_LENGTH));
+        clock.advance(0.75 * EPOCH_LENGTH);
+        assert_eq!((clock.epoch(), clock.offset()), (3, 0.25 * EPOCH_LENGTH));
+        assert_eq!(clock.now(), 3.25 * EPOCH_LENGTH);
+
+        let before = clock;
+        for dt in [-1.0, 0.0, f64::NAN, f64::INFINITY] {
+            clock.advance(dt);
+        }
+        assert_eq!(clock, before);
+
+        let mut later = EpochClock::at(1e15);
+        let start = later;
+        later.advance(1e-3);
+        // Resolved to the offset's 1e-10, where a plain clock says 0
+        assert!((later.since(&start) - 1e-3).abs() < 1e-9);
+        assert!((start.since(&later) + 1e-3).abs() < 1e-9);
+        assert_eq!((1e15 + 1e-3) - 1e15, 0.0);
+    }
+
+    #[test]
+    fn the_audit_warns_only_about_steps_too_short_for_the_clock() {
+        let mut audit = ClockAudit::new();
+        audit.record(10.0, 0.0);
+        audit.record(10.0, 1.0);
+        audit.record(1e3, 0.5);
+        assert_eq!(audit.steps(), 2);
+        assert_eq!(audit.smallest_step(), Some((1e3, 0.5)));
+        assert_eq!(audit.warning(), None);
+
+        audit.record(1e12, 1e-3);
+        assert_eq!(audit.coarse_steps(), 1);
+        assert_eq!(audit.smallest_ratio(), Some(1e-15));
+        let warning = audit.warning().expect("a step of 1e-3 at 1e12 is coarse");
+        assert!(
+            warning.starts_with("1 of 3 steps were short"),
+            "{}",
+            warning
+        );
+    }
+}
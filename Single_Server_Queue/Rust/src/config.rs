@@ -0,0 +1,1061 @@
+use crate::consistency::ConsistencyCheck;
+use crate::distributions::{
+    Deterministic, Distribution, Erlang, Exponential, Gamma, Lognormal, Truncated,
+};
+use crate::event_log::{AnomalyLimits, DEFAULT_EVENT_LOG_LENGTH};
+use crate::rng::RngBackend;
+use crate::statistics::DEFAULT_THROUGHPUT_WINDOW;
+use std::fmt;
+use std::sync::Arc;
+
+/// What happens to a customer whose service is interrupted by a breakdown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InterruptedService {
+    /// Continue with the remaining service time after the repair
+    #[default]
+    Resume,
+    /// Draw a fresh service time after the repair; the work done so far is lost
+    Restart,
+}
+
+impl fmt::Display for InterruptedService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterruptedService::Resume => f.pad("resume"),
+            InterruptedService::Restart => f.pad("restart"),
+        }
+    }
+}
+
+/// Shape of a service, setup or deadline distribution; a service's mean is always 1/μ
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ServiceKind {
+    #[default]
+    Exponential,
+    /// Every service takes exactly 1/μ, making an M/D/c queue
+    Deterministic,
+    /// Sum of this many exponential phases
+    Erlang(u32),
+    /// Gamma distribution of this shape
+    Gamma(f64),
+    /// Lognormal distribution whose logarithm has this standard deviation
+    Lognormal(f64),
+}
+
+impl ServiceKind {
+    /// A distribution of this shape with the given mean
+    pub fn distribution(self, mean: f64) -> Box<dyn Distribution> {
+        match self {
+            ServiceKind::Exponential => Box::new(Exponential::new(1.0 / mean)),
+            ServiceKind::Deterministic => Box::new(Deterministic::new(mean)),
+            ServiceKind::Erlang(phases) => Box::new(Erlang::new(phases, phases as f64 / mean)),
+            ServiceKind::Gamma(shape) => Box::new(Gamma::new(shape, mean / shape)),
+            ServiceKind::Lognormal(sigma) => {
+                Box::new(Lognormal::new(mean.ln() - 0.5 * sigma * sigma, sigma))
+            }
+        }
+    }
+
+    /// Laplace-Stieltjes transform E[e^{-sX}] at `s` of the distribution with the given
+    /// mean, e.g. for [`crate::theory::gm1_wait`]; `None` for the lognormal, which has
+    /// no closed form
+    pub fn laplace_transform(self, mean: f64, s: f64) -> Option<f64> {
+        match self {
+            ServiceKind::Exponential => Some(1.0 / (1.0 + mean * s)),
+            ServiceKind::Deterministic => Some((-mean * s).exp()),
+            ServiceKind::Erlang(phases) => {
+                Some((1.0 + mean * s / phases as f64).powi(-(phases as i32)))
+            }
+            ServiceKind::Gamma(shape) => Some((1.0 + mean * s / shape).powf(-shape)),
+            ServiceKind::Lognormal(_) => None,
+        }
+    }
+
+    /// Squared coefficient of variation, which does not depend on the mean
+    pub fn scv(self) -> f64 {
+        match self {
+            ServiceKind::Exponential => 1.0,
+            ServiceKind::Deterministic => 0.0,
+            ServiceKind::Erlang(phases) => 1.0 / phases as f64,
+            ServiceKind::Gamma(shape) => 1.0 / shape,
+            ServiceKind::Lognormal(sigma) => (sigma * sigma).exp_m1(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceKind::Exponential => f.pad("exponential"),
+            ServiceKind::Deterministic => f.pad("deterministic"),
+            ServiceKind::Erlang(phases) => f.pad(&format!("Erlang-{}", phases)),
+            ServiceKind::Gamma(shape) => f.pad(&format!("gamma (shape {})", shape)),
+            ServiceKind::Lognormal(sigma) => f.pad(&format!("lognormal (σ {})", sigma)),
+        }
+    }
+}
+
+/// Unreliable servers: failures while busy, followed by a repair
+///
+/// The station fails as a whole after an exponential time to failure that only
+/// runs while at least one customer is in service, and comes back up after an
+/// exponential repair time. With exponential service both interruption modes
+/// give the same results, since the remaining service time is memoryless.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakdowns {
+    /// Mean busy time between failures (1/f)
+    pub mean_time_to_failure: f64,
+
+    /// Mean duration of a repair (r)
+    pub mean_repair_time: f64,
+
+    pub interrupted_service: InterruptedService,
+}
+
+/// A warm standby server that takes over while the primary one is under repair
+///
+/// The primary is the server that fails as set by [`Breakdowns`]. When it fails,
+/// the standby takes over after a switchover during which nobody is served, and
+/// the customers in service carry on with it as they would after a repair. The
+/// repaired primary takes back over at once. Like the primary, the standby only
+/// fails while it is serving a customer; while both are down the station is out
+/// of service until either is repaired.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Standby {
+    /// Time the standby needs to take over; 0 for an instant failover
+    pub switchover_time: f64,
+
+    /// Mean busy time between failures of the standby while it serves
+    pub mean_time_to_failure: f64,
+
+    /// Mean duration of a repair of the standby
+    pub mean_repair_time: f64,
+}
+
+/// Switch-on time the station needs before serving an arrival that finds it empty
+///
+/// Customers arriving during the setup queue behind the one who triggered it, and
+/// services start once it is complete. The station is switched off again as soon
+/// as it empties, so every busy period begins with a setup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetupTime {
+    /// Mean duration of a setup
+    pub mean: f64,
+
+    pub kind: ServiceKind,
+}
+
+impl SetupTime {
+    pub fn distribution(&self) -> Box<dyn Distribution> {
+        self.kind.distribution(self.mean)
+    }
+
+    /// E[S²] of the setup duration
+    pub fn second_moment(&self) -> f64 {
+        self.mean * self.mean * (1.0 + self.kind.scv())
+    }
+}
+
+/// Relative deadline of every customer: the time after its arrival by which it should have left
+///
+/// Customers leaving later are late, by their tardiness. A customer whose deadline
+/// passes while it waits is still served, and counted late, unless `drop_late` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deadlines {
+    /// Mean relative deadline
+    pub mean: f64,
+
+    pub kind: ServiceKind,
+
+    /// Drop customers whose deadline has passed by the time they would start service
+    pub drop_late: bool,
+}
+
+impl Deadlines {
+    pub fn distribution(&self) -> Box<dyn Distribution> {
+        self.kind.distribution(self.mean)
+    }
+}
+
+/// Order in which waiting customers are served
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueDiscipline {
+    /// First come, first served
+    #[default]
+    Fifo,
+    /// The customer with the soonest absolute deadline first, the earlier arrival on a tie
+    EarliestDeadlineFirst,
+    /// Each customer in turn for at most one quantum, then to the back of the queue
+    /// if unfinished; needs a [`RoundRobin`] quantum
+    RoundRobin,
+}
+
+impl fmt::Display for QueueDiscipline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueDiscipline::Fifo => f.pad("FIFO"),
+            QueueDiscipline::EarliestDeadlineFirst => f.pad("EDF"),
+            QueueDiscipline::RoundRobin => f.pad("RR"),
+        }
+    }
+}
+
+/// Smallest quantum accepted unless configured otherwise
+pub const DEFAULT_MIN_QUANTUM: f64 = 1e-3;
+
+/// Time slicing of the round-robin discipline
+///
+/// A customer needing service s passes through about s/quantum quantum ends, each
+/// an event, so a tiny quantum makes a run astronomically long. The quantum must
+/// be at least `min_quantum`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundRobin {
+    /// Longest uninterrupted service a customer gets before the next one's turn
+    pub quantum: f64,
+    pub min_quantum: f64,
+}
+
+impl RoundRobin {
+    pub fn new(quantum: f64) -> Self {
+        Self {
+            quantum,
+            min_quantum: DEFAULT_MIN_QUANTUM,
+        }
+    }
+}
+
+/// What happens to an arrival while the queue is closed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClosedArrivals {
+    /// The customer leaves and is counted as lost
+    #[default]
+    Lost,
+    /// The customer comes back when the queue next opens; all deferred customers
+    /// arrive at that instant. After the last closing there is no next opening, so
+    /// they are lost
+    Deferred,
+}
+
+impl fmt::Display for ClosedArrivals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClosedArrivals::Lost => f.pad("lost"),
+            ClosedArrivals::Deferred => f.pad("deferred"),
+        }
+    }
+}
+
+/// Opening hours of the queue: arrivals are only admitted during the open periods
+///
+/// The arrival process runs on regardless; only admission is gated. The queue is
+/// closed before the first period, between periods and after the last one, and
+/// customers already inside are always served to the end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissionSchedule {
+    /// `(open, close)` times in increasing order; an arrival at `open` is admitted,
+    /// one at `close` is not
+    pub open_periods: Vec<(f64, f64)>,
+
+    pub closed_arrivals: ClosedArrivals,
+}
+
+impl AdmissionSchedule {
+    fn validate(&self) -> Result<(), String> {
+        if self.open_periods.is_empty() {
+            return Err("the admission schedule needs at least one open period".to_string());
+        }
+        let mut previous_close = 0.0;
+        for &(open, close) in &self.open_periods {
+            if !(open >= previous_close && open < close && close.is_finite()) {
+                return Err(format!(
+                    "admission periods must be finite, non-negative and in increasing order, got {} to {}",
+                    open, close
+                ));
+            }
+            previous_close = close;
+        }
+        Ok(())
+    }
+}
+
+/// Waits at which [`SimulationConfig::validate`] checks that a [`ServiceModulation`] stays positive
+const MODULATION_PROBE_WAITS: [f64; 9] = [0.0, 1e-3, 0.1, 1.0, 10.0, 100.0, 1e3, 1e4, 1e6];
+
+/// A service rate that depends on how long the customer waited, μ_eff = f(μ, wait)
+///
+/// The service time drawn at rate μ is scaled by μ / μ_eff when the customer
+/// first enters service, so staff who rush the customers that waited long make
+/// their services shorter. The modulation draws no random numbers, so a run with
+/// the same seed and without it is a paired baseline.
+#[derive(Clone)]
+pub enum ServiceModulation {
+    /// μ_eff = μ · min(1 + `per_wait` · wait, `max_factor`)
+    Linear { per_wait: f64, max_factor: f64 },
+    /// μ_eff from a function of μ and the wait
+    Custom(Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>),
+}
+
+impl ServiceModulation {
+    /// Modulation by `rate`, which is called with μ and the wait and returns μ_eff
+    pub fn custom(rate: impl Fn(f64, f64) -> f64 + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(rate))
+    }
+
+    /// Effective service rate of a customer who waited `wait` at base rate `mu`
+    #[inline]
+    pub fn rate(&self, mu: f64, wait: f64) -> f64 {
+        match self {
+            Self::Linear {
+                per_wait,
+                max_factor,
+            } => mu * (1.0 + per_wait * wait).min(*max_factor),
+            Self::Custom(rate) => rate(mu, wait),
+        }
+    }
+
+    /// Check that the rate is positive and finite for `mu` at waits from 0 to 10⁶
+    ///
+    /// A custom function can only be probed, not proven positive everywhere.
+    fn validate(&self, mu: f64) -> Result<(), String> {
+        if let Self::Linear {
+            per_wait,
+            max_factor,
+        } = *self
+            && !(per_wait.is_finite() && max_factor > 0.0 && max_factor.is_finite())
+        {
+            return Err(format!(
+                "the service speed-up needs a finite slope and a positive finite cap, got {} and {}",
+                per_wait, max_factor
+            ));
+        }
+        for wait in MODULATION_PROBE_WAITS {
+            let rate = self.rate(mu, wait);
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(format!(
+                    "the modulated service rate must be positive and finite, got {} for μ = {} after a wait of {}",
+                    rate, mu, wait
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ServiceModulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear {
+                per_wait,
+                max_factor,
+            } => f
+                .debug_struct("Linear")
+                .field("per_wait", per_wait)
+                .field("max_factor", max_factor)
+                .finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Custom modulations are equal only if they share the same function
+impl PartialEq for ServiceModulation {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Linear {
+                    per_wait,
+                    max_factor,
+                },
+                Self::Linear {
+                    per_wait: other_per_wait,
+                    max_factor: other_max_factor,
+                },
+            ) => per_wait == other_per_wait && max_factor == other_max_factor,
+            (Self::Custom(rate), Self::Custom(other_rate)) => Arc::ptr_eq(rate, other_rate),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ServiceModulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear {
+                per_wait,
+                max_factor,
+            } => write!(f, "μ · min(1 + {} · wait, {})", per_wait, max_factor),
+            Self::Custom(_) => f.pad("custom function of μ and the wait"),
+        }
+    }
+}
+
+/// Model parameter a [`ParameterChange`] sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangedParameter {
+    /// Arrival rate λ
+    ArrivalRate,
+    /// Service rate μ; customers waiting at the change are served at the new rate,
+    /// while the services in progress finish at the old one
+    ServiceRate,
+    /// Number of parallel servers c
+    Servers,
+}
+
+impl fmt::Display for ChangedParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangedParameter::ArrivalRate => f.pad("λ"),
+            ChangedParameter::ServiceRate => f.pad("μ"),
+            ChangedParameter::Servers => f.pad("servers"),
+        }
+    }
+}
+
+/// A parameter set to a new value at a given time of the run
+///
+/// Only later draws use the new value: a customer in service, or already waiting
+/// with its service time drawn on arrival, keeps its duration, and the arrival
+/// already scheduled keeps its time. Fewer servers take effect as the surplus
+/// ones finish their customers; more start on the queue at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterChange {
+    pub time: f64,
+    pub parameter: ChangedParameter,
+    /// The new rate, or the new number of servers as a whole number
+    pub value: f64,
+}
+
+impl fmt::Display for ParameterChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.parameter, self.value)
+    }
+}
+
+/// Model parameters for a single server queue run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationConfig {
+    /// Arrival rate (λ)
+    pub lambda: f64,
+
+    /// Service rate (μ)
+    pub mu: f64,
+
+    /// Number of parallel servers (c)
+    pub servers: usize,
+
+    /// Whether customers finding every server busy wait; `false` makes an M/M/c/c loss system
+    pub waiting_room: bool,
+
+    /// Hard cap on waiting customers; arrivals finding the queue full are lost, which
+    /// turns the model into a finite-capacity M/M/c/K system but bounds its memory
+    pub max_queue_length: Option<usize>,
+
+    /// Waiting customers beyond which the run is aborted as unstable, see
+    /// [`crate::simulation::Simulation::queue_explosion`]; `None` lets the queue
+    /// of an unstable model grow as long as the run lasts
+    pub explosion_limit: Option<usize>,
+
+    /// Shape of the interarrival time distribution, whose mean is always 1/λ;
+    /// exponential makes the arrivals Poisson
+    pub arrival_kind: ServiceKind,
+
+    /// Shape of the service time distribution
+    pub service_kind: ServiceKind,
+
+    /// Upper bound on exponential service times; `None` for no truncation
+    pub max_service_time: Option<f64>,
+
+    /// Service rate that rises with the customer's wait; `None` serves every
+    /// customer at μ
+    pub service_modulation: Option<ServiceModulation>,
+
+    /// Customers in the system at t = 0: the first `servers` of them in service, the
+    /// rest waiting. Starting near the steady-state backlog shortens the transient;
+    /// a large backlog with arrivals cut off shows the queue draining
+    pub initial_queue: usize,
+
+    /// Arrivals stop at this time while customers already inside are still served
+    pub arrival_cutoff: Option<f64>,
+
+    /// Server failures and repairs; `None` for servers that never fail
+    pub breakdowns: Option<Breakdowns>,
+
+    /// Server taking over while the failed one is repaired; needs `breakdowns`
+    pub standby: Option<Standby>,
+
+    /// Switch-on time at the start of every busy period; `None` for a station that is always on
+    pub setup: Option<SetupTime>,
+
+    /// Relative deadline of each customer; `None` for customers without one
+    pub deadlines: Option<Deadlines>,
+
+    /// Order the queue is served in; earliest deadline first needs `deadlines`
+    /// and round robin `round_robin`
+    pub discipline: QueueDiscipline,
+
+    /// Quantum of the round-robin discipline; ignored under the others
+    pub round_robin: Option<RoundRobin>,
+
+    /// Opening hours of the queue; `None` admits arrivals at all times
+    pub admission: Option<AdmissionSchedule>,
+
+    /// Parameters changed during the run, in order of time; the fields above stay
+    /// the initial values
+    pub parameter_changes: Vec<ParameterChange>,
+
+    /// Statistics collected before this time are discarded (0 for no warm-up)
+    pub warm_up_time: f64,
+
+    /// Time units covered by the sliding-window throughput
+    pub throughput_window: f64,
+
+    /// Time units covered by the windowed mean wait, queue length and
+    /// utilization, which are only kept when set
+    pub statistics_window: Option<f64>,
+
+    /// Most recent events kept for a post-mortem dump (0 disables the log)
+    pub event_log_length: usize,
+
+    /// Bounds whose violation copies the event log into a post-mortem
+    pub anomaly_limits: AnomalyLimits,
+
+    /// Check periodically that the statistics agree with Little's law; `None` never checks
+    pub consistency_check: Option<ConsistencyCheck>,
+
+    /// Waits for which the share of customers served within them is counted
+    pub service_level_targets: Vec<f64>,
+
+    /// Number of departing customers whose timeline is kept (0 disables tracing)
+    pub customer_trace_limit: usize,
+
+    /// Seed for the random streams; `None` seeds from system entropy
+    ///
+    /// Runs with the same seed and different parameters share common random
+    /// numbers, see [`crate::entities::ServerStreams`].
+    pub seed: Option<u64>,
+
+    /// Generator behind every random stream of the run
+    pub rng_backend: RngBackend,
+
+    /// Count events per type and time their handlers in the engine
+    pub instrumentation: bool,
+
+    /// Audit how short the clock's steps get relative to its magnitude, see [`crate::clock`]
+    pub clock_audit: bool,
+}
+
+impl SimulationConfig {
+    /// Builder starting from the defaults, validated on [`SimulationConfigBuilder::build`]
+    pub fn builder() -> SimulationConfigBuilder {
+        SimulationConfigBuilder::default()
+    }
+
+    /// Pure loss system: arrivals that find all servers busy are turned away
+    pub fn is_loss_system(&self) -> bool {
+        !self.waiting_room
+    }
+
+    /// Check every parameter, so a bad value fails here instead of corrupting a run
+    pub fn validate(&self) -> Result<(), String> {
+        fn positive(name: &str, value: f64) -> Result<(), String> {
+            if value > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("{} must be positive, got {}", name, value))
+            }
+        }
+
+        if !(self.lambda > 0.0 && self.lambda.is_finite()) {
+            return Err(format!(
+                "arrival rate λ must be positive and finite, got {}",
+                self.lambda
+            ));
+        }
+        if self.servers == 0 {
+            return Err("the number of servers must be at least 1".to_string());
+        }
+        if self.max_queue_length == Some(0) {
+            return Err("the queue cap must be at least 1".to_string());
+        }
+        if self.explosion_limit == Some(0) {
+            return Err("the explosion limit must be at least 1".to_string());
+        }
+        let initial_waiting = self.initial_queue.saturating_sub(self.servers);
+        if initial_waiting > 0 && self.is_loss_system() {
+            return Err(format!(
+                "a loss system has no room for {} initial customers beyond its {} servers",
+                initial_waiting, self.servers
+            ));
+        }
+        if let Some(cap) = self.max_queue_length
+            && initial_waiting > cap
+        {
+            return Err(format!(
+                "{} initial customers waiting exceed the queue cap of {}",
+                initial_waiting, cap
+            ));
+        }
+        self.arrival_kind
+            .distribution(1.0 / self.lambda)
+            .validate()
+            .map_err(|e| format!("interarrival time: {}", e))?;
+        if self.service_kind != ServiceKind::Exponential && self.max_service_time.is_some() {
+            return Err("only exponential service times can be truncated".to_string());
+        }
+        // At the configured mean, so an invalid μ is reported as the distribution's own parameter
+        let service = self.service_kind.distribution(1.0 / self.mu);
+        match self.max_service_time {
+            Some(max) => Truncated::new(service, max).validate(),
+            None => service.validate(),
+        }
+        .map_err(|e| format!("service time: {}", e))?;
+        if let Some(cutoff) = self.arrival_cutoff {
+            positive("arrival cutoff", cutoff)?;
+        }
+        if let Some(breakdowns) = &self.breakdowns {
+            positive("mean time to failure", breakdowns.mean_time_to_failure)?;
+            positive("mean repair time", breakdowns.mean_repair_time)?;
+        }
+        if let Some(standby) = &self.standby {
+            if self.breakdowns.is_none() {
+                return Err("a standby server needs breakdowns of the primary".to_string());
+            }
+            if !(standby.switchover_time.is_finite() && standby.switchover_time >= 0.0) {
+                return Err(format!(
+                    "switchover time must be finite and not negative, got {}",
+                    standby.switchover_time
+                ));
+            }
+            positive(
+                "mean time to failure of the standby",
+                standby.mean_time_to_failure,
+            )?;
+            positive("mean repair time of the standby", standby.mean_repair_time)?;
+        }
+        if let Some(setup) = &self.setup {
+            positive("mean setup time", setup.mean)?;
+            setup
+                .distribution()
+                .validate()
+                .map_err(|e| format!("setup time: {}", e))?;
+        }
+        if let Some(deadlines) = &self.deadlines {
+            positive("mean deadline", deadlines.mean)?;
+            deadlines
+                .distribution()
+                .validate()
+                .map_err(|e| format!("deadline: {}", e))?;
+        }
+        if self.discipline == QueueDiscipline::EarliestDeadlineFirst && self.deadlines.is_none() {
+            return Err("earliest deadline first needs customer deadlines".to_string());
+        }
+        if self.discipline == QueueDiscipline::RoundRobin {
+            let Some(round_robin) = self.round_robin else {
+                return Err("round robin needs a quantum".to_string());
+            };
+            positive("quantum", round_robin.quantum)?;
+            positive("minimum quantum", round_robin.min_quantum)?;
+            if round_robin.quantum < round_robin.min_quantum {
+                return Err(format!(
+                    "quantum {} is below the minimum {}; a mean service would take about {:.0} quanta",
+                    round_robin.quantum,
+                    round_robin.min_quantum,
+                    1.0 / (self.mu * round_robin.quantum)
+                ));
+            }
+            if self
+                .breakdowns
+                .is_some_and(|b| b.interrupted_service == InterruptedService::Restart)
+            {
+                return Err(
+                    "round robin keeps the work already done, so breakdowns cannot restart services"
+                        .to_string(),
+                );
+            }
+            if self.deadlines.is_some_and(|deadlines| deadlines.drop_late) {
+                return Err(
+                    "round robin cannot drop late customers who were already partly served"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(admission) = &self.admission {
+            admission.validate()?;
+        }
+        let mut previous_change = 0.0;
+        for change in &self.parameter_changes {
+            if !(change.time >= previous_change && change.time.is_finite()) {
+                return Err(format!(
+                    "parameter changes must be at finite, non-negative times in increasing order, got {}",
+                    change.time
+                ));
+            }
+            previous_change = change.time;
+            if !(change.value > 0.0 && change.value.is_finite()) {
+                return Err(format!(
+                    "a changed {} must be positive and finite, got {}",
+                    change.parameter, change.value
+                ));
+            }
+            if change.parameter == ChangedParameter::Servers && change.value.fract() != 0.0 {
+                return Err(format!(
+                    "the number of servers must be a whole number, got {}",
+                    change.value
+                ));
+            }
+        }
+        if let Some(modulation) = &self.service_modulation {
+            modulation.validate(self.mu)?;
+            for change in &self.parameter_changes {
+                if change.parameter == ChangedParameter::ServiceRate {
+                    modulation.validate(change.value)?;
+                }
+            }
+        }
+        if self.warm_up_time.is_nan() || self.warm_up_time < 0.0 {
+            return Err(format!(
+                "warm-up time must not be negative, got {}",
+                self.warm_up_time
+            ));
+        }
+        if let Some(multiple) = self.anomaly_limits.wait_multiple {
+            positive("anomaly wait multiple", multiple)?;
+        }
+        if let Some(check) = self.consistency_check {
+            if check.every_events == 0 {
+                return Err("consistency checks need at least one event between them".to_string());
+            }
+            positive("consistency tolerance", check.tolerance)?;
+            if !(check.min_time.is_finite() && check.min_time >= 0.0) {
+                return Err(format!(
+                    "consistency check start must be finite and not negative, got {}",
+                    check.min_time
+                ));
+            }
+        }
+        if let Some(target) = self
+            .service_level_targets
+            .iter()
+            .find(|target| !(target.is_finite() && **target >= 0.0))
+        {
+            return Err(format!(
+                "service level targets must be non-negative and finite, got {}",
+                target
+            ));
+        }
+        if let Some(window) = self.statistics_window {
+            positive("statistics window", window)?;
+        }
+        positive("throughput window", self.throughput_window)
+    }
+
+    /// The interarrival distribution scaled to mean one, whose draws are multiplied
+    /// by 1/λ; `None` for Poisson arrivals, which the arrival process draws itself
+    pub fn unit_interarrival_distribution(&self) -> Option<Box<dyn Distribution>> {
+        (self.arrival_kind != ServiceKind::Exponential).then(|| self.arrival_kind.distribution(1.0))
+    }
+
+    /// The service distribution scaled to mean one, whose draws are multiplied by 1/μ
+    pub fn unit_service_distribution(&self) -> Box<dyn Distribution> {
+        self.unit_service_distribution_at(self.mu)
+    }
+
+    /// The service distribution scaled to mean one for rate `mu` in place of
+    /// [`SimulationConfig::mu`]; the truncation bound is in units of 1/`mu`
+    pub fn unit_service_distribution_at(&self, mu: f64) -> Box<dyn Distribution> {
+        let distribution = self.service_kind.distribution(1.0);
+        match self.max_service_time {
+            Some(max) => Box::new(Truncated::new(distribution, max * mu)),
+            None => distribution,
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            lambda: 1.0 / 1.25,
+            mu: 1.0,
+            servers: 1,
+            waiting_room: true,
+            max_queue_length: None,
+            explosion_limit: None,
+            arrival_kind: ServiceKind::Exponential,
+            service_kind: ServiceKind::Exponential,
+            max_service_time: None,
+            service_modulation: None,
+            initial_queue: 0,
+            arrival_cutoff: None,
+            breakdowns: None,
+            standby: None,
+            setup: None,
+            deadlines: None,
+            discipline: QueueDiscipline::Fifo,
+            round_robin: None,
+            admission: None,
+            parameter_changes: Vec::new(),
+            warm_up_time: 0.0,
+            throughput_window: DEFAULT_THROUGHPUT_WINDOW,
+            statistics_window: None,
+            event_log_length: DEFAULT_EVENT_LOG_LENGTH,
+            anomaly_limits: AnomalyLimits::default(),
+            consistency_check: None,
+            service_level_targets: Vec::new(),
+            customer_trace_limit: 0,
+            seed: None,
+            rng_backend: RngBackend::default(),
+            instrumentation: false,
+            clock_audit: false,
+        }
+    }
+}
+
+/// Chainable construction of a [`SimulationConfig`], see [`SimulationConfig::builder`]
+///
+/// Unset parameters keep the defaults of the interactive prompts (λ = 0.8, μ = 1,
+/// one exponential server with an unlimited queue and no warm-up). Optional
+/// parameters are set by value; leaving a setter out leaves the option off.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationConfigBuilder {
+    config: SimulationConfig,
+}
+
+impl SimulationConfigBuilder {
+    pub fn lambda(mut self, lambda: f64) -> Self {
+        self.config.lambda = lambda;
+        self
+    }
+
+    pub fn mu(mut self, mu: f64) -> Self {
+        self.config.mu = mu;
+        self
+    }
+
+    pub fn servers(mut self, servers: usize) -> Self {
+        self.config.servers = servers;
+        self
+    }
+
+    /// `false` turns arrivals finding every server busy away: an M/M/c/c loss system
+    pub fn waiting_room(mut self, waiting_room: bool) -> Self {
+        self.config.waiting_room = waiting_room;
+        self
+    }
+
+    pub fn max_queue_length(mut self, max_queue_length: usize) -> Self {
+        self.config.max_queue_length = Some(max_queue_length);
+        self
+    }
+
+    /// Abort the run once more than `limit` customers wait
+    pub fn explosion_limit(mut self, limit: usize) -> Self {
+        self.config.explosion_limit = Some(limit);
+        self
+    }
+
+    pub fn arrival_kind(mut self, arrival_kind: ServiceKind) -> Self {
+        self.config.arrival_kind = arrival_kind;
+        self
+    }
+
+    pub fn service_kind(mut self, service_kind: ServiceKind) -> Self {
+        self.config.service_kind = service_kind;
+        self
+    }
+
+    pub fn max_service_time(mut self, max_service_time: f64) -> Self {
+        self.config.max_service_time = Some(max_service_time);
+        self
+    }
+
+    /// Serve at μ_eff = `rate(μ, wait)`, see [`ServiceModulation`]
+    pub fn service_modulation(mut self, modulation: ServiceModulation) -> Self {
+        self.config.service_modulation = Some(modulation);
+        self
+    }
+
+    pub fn initial_queue(mut self, initial_queue: usize) -> Self {
+        self.config.initial_queue = initial_queue;
+        self
+    }
+
+    pub fn arrival_cutoff(mut self, arrival_cutoff: f64) -> Self {
+        self.config.arrival_cutoff = Some(arrival_cutoff);
+        self
+    }
+
+    pub fn breakdowns(mut self, breakdowns: Breakdowns) -> Self {
+        self.config.breakdowns = Some(breakdowns);
+        self
+    }
+
+    pub fn standby(mut self, standby: Standby) -> Self {
+        self.config.standby = Some(standby);
+        self
+    }
+
+    pub fn setup(mut self, setup: SetupTime) -> Self {
+        self.config.setup = Some(setup);
+        self
+    }
+
+    pub fn deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.config.deadlines = Some(deadlines);
+        self
+    }
+
+    pub fn discipline(mut self, discipline: QueueDiscipline) -> Self {
+        self.config.discipline = discipline;
+        self
+    }
+
+    /// Serve round robin with `round_robin`'s quantum
+    pub fn round_robin(mut self, round_robin: RoundRobin) -> Self {
+        self.config.discipline = QueueDiscipline::RoundRobin;
+        self.config.round_robin = Some(round_robin);
+        self
+    }
+
+    pub fn admission(mut self, admission: AdmissionSchedule) -> Self {
+        self.config.admission = Some(admission);
+        self
+    }
+
+    pub fn parameter_changes(mut self, changes: Vec<ParameterChange>) -> Self {
+        self.config.parameter_changes = changes;
+        self
+    }
+
+    pub fn warm_up_time(mut self, warm_up_time: f64) -> Self {
+        self.config.warm_up_time = warm_up_time;
+        self
+    }
+
+    pub fn throughput_window(mut self, throughput_window: f64) -> Self {
+        self.config.throughput_window = throughput_window;
+        self
+    }
+
+    pub fn statistics_window(mut self, statistics_window: f64) -> Self {
+        self.config.statistics_window = Some(statistics_window);
+        self
+    }
+
+    pub fn event_log_length(mut self, event_log_length: usize) -> Self {
+        self.config.event_log_length = event_log_length;
+        self
+    }
+
+    pub fn anomaly_limits(mut self, anomaly_limits: AnomalyLimits) -> Self {
+        self.config.anomaly_limits = anomaly_limits;
+        self
+    }
+
+    pub fn consistency_check(mut self, check: ConsistencyCheck) -> Self {
+        self.config.consistency_check = Some(check);
+        self
+    }
+
+    pub fn service_level_targets(mut self, targets: Vec<f64>) -> Self {
+        self.config.service_level_targets = targets;
+        self
+    }
+
+    pub fn customer_trace_limit(mut self, customer_trace_limit: usize) -> Self {
+        self.config.customer_trace_limit = customer_trace_limit;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn rng_backend(mut self, rng_backend: RngBackend) -> Self {
+        self.config.rng_backend = rng_backend;
+        self
+    }
+
+    pub fn instrumentation(mut self, instrumentation: bool) -> Self {
+        self.config.instrumentation = instrumentation;
+        self
+    }
+
+    pub fn clock_audit(mut self, clock_audit: bool) -> Self {
+        self.config.clock_audit = clock_audit;
+        self
+    }
+
+    /// The configuration, if it passes [`SimulationConfig::validate`]
+    pub fn build(self) -> Result<SimulationConfig, String> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service_error(
+        configure: impl FnOnce(SimulationConfigBuilder) -> SimulationConfigBuilder,
+    ) -> String {
+        configure(SimulationConfig::builder().lambda(0.5).mu(1.0))
+            .build()
+            .expect_err("the service time is invalid")
+    }
+
+    #[test]
+    fn invalid_service_times_are_refused_before_a_run() {
+        assert!(service_error(|b| b.mu(0.0)).starts_with("service time: exponential rate"));
+        assert!(service_error(|b| b.mu(-2.0)).starts_with("service time: exponential rate"));
+        assert!(
+            service_error(|b| b.service_kind(ServiceKind::Erlang(0)))
+                .starts_with("service time: an Erlang distribution")
+        );
+        assert!(
+            service_error(|b| b.service_kind(ServiceKind::Gamma(-1.0)))
+                .starts_with("service time: gamma shape")
+        );
+        assert!(
+            service_error(|b| b.service_kind(ServiceKind::Lognormal(0.0)))
+                .starts_with("service time: lognormal σ")
+        );
+        assert!(
+            service_error(|b| b.max_service_time(0.0))
+                .starts_with("service time: truncation bound")
+        );
+        assert!(
+            service_error(|b| b.mu(-1.0).max_service_time(2.0))
+                .starts_with("service time: truncated distribution: exponential rate")
+        );
+        assert_eq!(
+            service_error(|b| b
+                .service_kind(ServiceKind::Deterministic)
+                .max_service_time(2.0)),
+            "only exponential service times can be truncated"
+        );
+    }
+
+    #[test]
+    fn invalid_interarrival_times_are_refused_before_a_run() {
+        assert!(
+            service_error(|b| b.arrival_kind(ServiceKind::Erlang(0)))
+                .starts_with("interarrival time: an Erlang distribution")
+        );
+        assert!(
+            service_error(|b| b.arrival_kind(ServiceKind::Gamma(0.0)))
+                .starts_with("interarrival time: gamma shape")
+        );
+        let deterministic = SimulationConfig::builder()
+            .arrival_kind(ServiceKind::Deterministic)
+            .build()
+            .expect("deterministic arrivals are valid");
+        assert!(deterministic.unit_interarrival_distribution().is_some());
+        assert!(
+            SimulationConfig::default()
+                .unit_interarrival_distribution()
+                .is_none()
+        );
+    }
+}
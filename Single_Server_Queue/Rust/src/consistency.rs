@@ -0,0 +1,265 @@
+//! Online checks that the statistics of a run agree with each other
+//!
+//! A bug in the statistics, such as an area integral that is not brought up to
+//! date before the state changes, does not crash anything; it only makes the
+//! reported numbers wrong. Identities that hold for every queue catch it while the
+//! run is going: Little's law for the queue, and utilization against throughput
+//! where the mean service time is known. Both are read off accumulators the
+//! statistics maintain anyway, so a check costs a few arithmetic operations.
+
+use crate::config::{QueueDiscipline, SimulationConfig};
+use crate::statistics::Statistics;
+use std::fmt;
+
+/// When and how strictly the statistics are checked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsistencyCheck {
+    /// Events between two checks
+    pub every_events: u64,
+    /// Relative disagreement beyond which a check fails
+    pub tolerance: f64,
+    /// Observed time before the first check; early on the customers still inside
+    /// make up too large a part of the totals for the identities to hold closely
+    pub min_time: f64,
+}
+
+impl Default for ConsistencyCheck {
+    fn default() -> Self {
+        Self {
+            every_events: 10_000,
+            tolerance: 0.05,
+            min_time: 10_000.0,
+        }
+    }
+}
+
+/// Identity a check compares the two sides of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Identity {
+    /// Mean queue length Lq against λ_eff·Wq, the rate of service starts times their mean wait
+    LittlesLaw,
+    /// Utilization against λ_eff/(cμ), the throughput per unit of capacity
+    Utilization,
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identity::LittlesLaw => f.pad("Little's law"),
+            Identity::Utilization => f.pad("utilization"),
+        }
+    }
+}
+
+/// A failed check: the measured side, what the identity says it should be and when
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsistencyWarning {
+    pub time: f64,
+    pub identity: Identity,
+    pub measured: f64,
+    pub expected: f64,
+}
+
+impl ConsistencyWarning {
+    pub fn relative_error(&self) -> f64 {
+        (self.measured - self.expected).abs() / self.measured.abs()
+    }
+}
+
+impl fmt::Display for ConsistencyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (measured, expected) = match self.identity {
+            Identity::LittlesLaw => ("Lq", "λ·Wq"),
+            Identity::Utilization => ("utilization", "λ/(cμ)"),
+        };
+        write!(
+            f,
+            "{} broken at t = {:.2}: {} = {:.4} but {} = {:.4} ({:.2}% off)",
+            self.identity,
+            self.time,
+            measured,
+            self.measured,
+            expected,
+            self.expected,
+            self.relative_error() * 100.0
+        )
+    }
+}
+
+/// Runs the configured checks and keeps the first failure of each identity
+#[derive(Debug, Clone)]
+pub struct ConsistencyMonitor {
+    check: ConsistencyCheck,
+    /// Whether Little's law holds for this model; customers dropped from the
+    /// queue leave without a wait being recorded, and under round robin the wait
+    /// only runs until the first quantum while the queue also holds preempted customers
+    littles_law: bool,
+    /// Whether the mean service time is 1/μ throughout the run
+    utilization: bool,
+    warnings: Vec<ConsistencyWarning>,
+}
+
+impl ConsistencyMonitor {
+    pub fn new(check: ConsistencyCheck, config: &SimulationConfig) -> Self {
+        Self {
+            check,
+            littles_law: !config
+                .deadlines
+                .is_some_and(|deadlines| deadlines.drop_late)
+                && config.discipline != QueueDiscipline::RoundRobin,
+            utilization: config.max_service_time.is_none()
+                && config.breakdowns.is_none()
+                && config.setup.is_none()
+                && config.parameter_changes.is_empty(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Whether the check is due after `event_count` events
+    #[inline]
+    pub fn is_due(&self, event_count: u64) -> bool {
+        event_count.is_multiple_of(self.check.every_events)
+    }
+
+    /// Check the statistics at `time`; returns the identities that failed for the first time
+    pub fn check(&mut self, time: f64, stats: &Statistics, mu: f64) -> &[ConsistencyWarning] {
+        let before = self.warnings.len();
+        let observed = stats.observed_time(time);
+        if observed < self.check.min_time {
+            return &[];
+        }
+        if self.littles_law && !self.has_failed(Identity::LittlesLaw) {
+            // λ_eff·Wq = (started / T)·(total wait / started)
+            let totals = stats.totals(time);
+            self.compare(
+                time,
+                Identity::LittlesLaw,
+                totals.queue_area / observed,
+                totals.wait_time / observed,
+            );
+        }
+        if self.utilization && !self.has_failed(Identity::Utilization) {
+            self.compare(
+                time,
+                Identity::Utilization,
+                stats.utilization(time),
+                stats.throughput(time) / (stats.average_servers(time) * mu),
+            );
+        }
+        &self.warnings[before..]
+    }
+
+    fn compare(&mut self, time: f64, identity: Identity, measured: f64, expected: f64) {
+        let warning = ConsistencyWarning {
+            time,
+            identity,
+            measured,
+            expected,
+        };
+        // An empty queue has nothing to compare, so only a nonzero side can fail
+        if measured.max(expected) > 0.0 && warning.relative_error() > self.check.tolerance {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn has_failed(&self, identity: Identity) -> bool {
+        self.warnings
+            .iter()
+            .any(|warning| warning.identity == identity)
+    }
+
+    /// The first failure of each identity, in the order they happened
+    pub fn warnings(&self) -> &[ConsistencyWarning] {
+        &self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::StopCondition;
+    use crate::simulation::Simulation;
+
+    const CHECK: ConsistencyCheck = ConsistencyCheck {
+        every_events: 10_000,
+        tolerance: 0.05,
+        min_time: 10_000.0,
+    };
+
+    fn config() -> SimulationConfig {
+        SimulationConfig::builder()
+            .lambda(0.8)
+            .mu(1.0)
+            .seed(12)
+            .build()
+            .expect("the configuration is valid")
+    }
+
+    /// Statistics of a healthy run, observed long enough to be checked
+    fn checked_statistics() -> (f64, Statistics) {
+        let mut simulation = Simulation::new(config());
+        simulation.run(&StopCondition::Time(50_000.0), &mut ());
+        (simulation.now(), simulation.statistics().clone())
+    }
+
+    #[test]
+    fn a_healthy_run_passes_both_identities() {
+        let (time, stats) = checked_statistics();
+        let mut monitor = ConsistencyMonitor::new(CHECK, &config());
+        assert!(monitor.check(time, &stats, 1.0).is_empty());
+
+        let mut simulation = Simulation::new(
+            SimulationConfig::builder()
+                .lambda(0.8)
+                .mu(1.0)
+                .seed(12)
+                .consistency_check(CHECK)
+                .build()
+                .unwrap(),
+        );
+        simulation.run(&StopCondition::Time(100_000.0), &mut ());
+        assert!(simulation.consistency_warnings().is_empty());
+    }
+
+    #[test]
+    fn an_inflated_wait_total_breaks_littles_law() {
+        let (time, mut stats) = checked_statistics();
+        // A wait the queue length never accounted for
+        stats.record_service_start(time, 0.5 * stats.totals(time).wait_time);
+        let mut monitor = ConsistencyMonitor::new(CHECK, &config());
+        let warnings = monitor.check(time, &stats, 1.0).to_vec();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].identity, Identity::LittlesLaw);
+        assert!((warnings[0].expected / warnings[0].measured - 1.5).abs() < 0.1);
+
+        // Each identity is reported once
+        assert!(monitor.check(time, &stats, 1.0).is_empty());
+        assert_eq!(monitor.warnings().len(), 1);
+    }
+
+    #[test]
+    fn a_wrong_service_rate_breaks_the_utilization_identity() {
+        let (time, stats) = checked_statistics();
+        let mut monitor = ConsistencyMonitor::new(CHECK, &config());
+        let warnings = monitor.check(time, &stats, 1.25);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].identity, Identity::Utilization);
+        assert!(
+            warnings[0]
+                .to_string()
+                .starts_with("utilization broken at t = ")
+        );
+    }
+
+    #[test]
+    fn nothing_is_checked_before_the_minimum_time() {
+        let (time, mut stats) = checked_statistics();
+        stats.record_service_start(time, 1e9);
+        let patient = ConsistencyCheck {
+            min_time: 2.0 * time,
+            ..CHECK
+        };
+        let mut monitor = ConsistencyMonitor::new(patient, &config());
+        assert!(monitor.check(time, &stats, 1.0).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::statistics::Statistics;
+
+/// Linear cost rates used to price a finished run
+///
+/// All rates default to zero, in which case the cost report is skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CostModel {
+    /// Cost per customer in the system per time unit
+    pub waiting_cost: f64,
+
+    /// Cost per server per time unit, whether busy or idle
+    pub server_cost: f64,
+
+    /// Penalty per customer who left unserved: turned away by a full system or a
+    /// closed queue, or dropped once its deadline had passed
+    pub loss_cost: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostBreakdown {
+    pub waiting: f64,
+    pub service: f64,
+    pub loss: f64,
+    pub total: f64,
+    pub per_time_unit: f64,
+}
+
+impl CostBreakdown {
+    /// JSON object with every part of the cost
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"waiting\": {}, \"service\": {}, \"loss\": {}, \"total\": {}, \"per_time_unit\": {}}}",
+            self.waiting, self.service, self.loss, self.total, self.per_time_unit
+        )
+    }
+}
+
+impl CostModel {
+    pub fn is_configured(&self) -> bool {
+        self.waiting_cost != 0.0 || self.server_cost != 0.0 || self.loss_cost != 0.0
+    }
+
+    /// Price the final statistics of a run
+    ///
+    /// Waiting cost uses the time integral of customers in system, so it charges
+    /// for time spent in queue and in service alike. Server cost follows the number
+    /// of servers, should it change during the run.
+    pub fn evaluate(&self, stats: &Statistics, total_time: f64) -> CostBreakdown {
+        let observed = stats.observed_time(total_time);
+        let customer_time = stats.average_customers_in_system(total_time) * observed;
+        let waiting = self.waiting_cost * customer_time;
+        let service = self.server_cost * stats.average_servers(total_time) * observed;
+        let unserved = stats.lost_customers() + stats.closed_losses() + stats.late_drops();
+        let loss = self.loss_cost * unserved as f64;
+        let total = waiting + service + loss;
+        let per_time_unit = if observed == 0.0 {
+            0.0
+        } else {
+            total / observed
+        };
+
+        CostBreakdown {
+            waiting,
+            service,
+            loss,
+            total,
+            per_time_unit,
+        }
+    }
+
+    /// Cost per time unit of a run, `None` when no rate is set and there is nothing to rank by
+    pub fn rate(&self, stats: &Statistics, total_time: f64) -> Option<f64> {
+        self.is_configured()
+            .then(|| self.evaluate(stats, total_time).per_time_unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_charged_without_rates() {
+        let model = CostModel::default();
+        assert!(!model.is_configured());
+        assert_eq!(model.rate(&Statistics::new(), 10.0), None);
+    }
+
+    #[test]
+    fn waiting_server_and_loss_costs_add_up() {
+        let mut stats = Statistics::new();
+        // Two customers from 0 to 4, one of them still there at 10; one turned away
+        stats.record_arrival(0.0);
+        stats.record_service_start(0.0, 0.0);
+        stats.record_arrival(0.0);
+        stats.record_queue_change(0.0, 1);
+        stats.record_loss();
+        stats.record_service_end(4.0, 4.0, 0);
+        stats.record_queue_change(4.0, 0);
+        stats.record_service_start(4.0, 4.0);
+        stats.record_queue_change(10.0, 0);
+
+        let model = CostModel {
+            waiting_cost: 2.0,
+            server_cost: 3.0,
+            loss_cost: 5.0,
+        };
+        let cost = model.evaluate(&stats, 10.0);
+        // Customer-time 2·4 + 1·6 = 14, one server for 10, one customer lost
+        assert_eq!(cost.waiting, 28.0);
+        assert_eq!(cost.service, 30.0);
+        assert_eq!(cost.loss, 5.0);
+        assert_eq!(cost.total, 63.0);
+        assert_eq!(cost.per_time_unit, 6.3);
+        assert_eq!(model.rate(&stats, 10.0), Some(6.3));
+    }
+}
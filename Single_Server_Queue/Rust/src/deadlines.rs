@@ -0,0 +1,164 @@
+//! Earliest deadline first against FIFO, run on the same customers
+//!
+//! Both disciplines of a pair replay one seed, so the same customers arrive with
+//! the same services and deadlines and only the order they are served in differs:
+//! the paired differences scatter far less than those of independent runs.
+
+use crate::config::{QueueDiscipline, SimulationConfig};
+use crate::simulation::Simulation;
+use crate::statistics::{Moments, t_quantile_975};
+use std::fmt;
+
+/// Deadline-related outcome compared between the disciplines, together with the
+/// slowdowns, which show who pays for serving by deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineMetric {
+    /// Share of the served customers that left after their deadline
+    TardinessProbability,
+    /// Mean time the late customers left after their deadline
+    MeanTardiness,
+    /// Longest time a customer left after its deadline
+    MaxTardiness,
+    MeanWait,
+    /// Share of the customers dropped at the front because their deadline had passed
+    LateDrops,
+    /// Median of sojourn / service, whose mean is infinite for exponential service
+    MedianSlowdown,
+    P95Slowdown,
+    MaxSlowdown,
+}
+
+impl DeadlineMetric {
+    pub const ALL: [Self; 8] = [
+        Self::TardinessProbability,
+        Self::MeanTardiness,
+        Self::MaxTardiness,
+        Self::MeanWait,
+        Self::LateDrops,
+        Self::MedianSlowdown,
+        Self::P95Slowdown,
+        Self::MaxSlowdown,
+    ];
+
+    /// Value of the metric at the end of a run
+    pub fn measure(self, simulation: &Simulation) -> f64 {
+        let stats = simulation.statistics();
+        match self {
+            Self::TardinessProbability => stats.tardiness_probability(),
+            Self::MeanTardiness => stats.mean_tardiness(),
+            Self::MaxTardiness => stats.max_tardiness(),
+            Self::MeanWait => stats.average_wait_time(),
+            Self::LateDrops => stats.late_drop_fraction(),
+            Self::MedianSlowdown => stats.slowdown_quantile(0.5).unwrap_or(0.0),
+            Self::P95Slowdown => stats.slowdown_quantile(0.95).unwrap_or(0.0),
+            Self::MaxSlowdown => stats.max_slowdown(),
+        }
+    }
+}
+
+impl fmt::Display for DeadlineMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TardinessProbability => f.pad("P(late)"),
+            Self::MeanTardiness => f.pad("mean tardiness"),
+            Self::MaxTardiness => f.pad("max tardiness"),
+            Self::MeanWait => f.pad("mean wait"),
+            Self::LateDrops => f.pad("dropped late"),
+            Self::MedianSlowdown => f.pad("median slowdown"),
+            Self::P95Slowdown => f.pad("P95 slowdown"),
+            Self::MaxSlowdown => f.pad("max slowdown"),
+        }
+    }
+}
+
+/// One metric under both disciplines, averaged over the replications
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisciplineComparison {
+    pub metric: DeadlineMetric,
+    pub fifo: f64,
+    pub edf: f64,
+    /// Mean of the paired differences EDF - FIFO
+    pub difference: f64,
+    /// 95% confidence half-width of `difference`, NaN for one replication
+    pub half_width: f64,
+}
+
+impl DisciplineComparison {
+    /// Whether the confidence interval of the difference excludes zero
+    pub fn is_significant(&self) -> bool {
+        self.difference.abs() > self.half_width
+    }
+}
+
+/// Run `config` under FIFO and under earliest deadline first for `replications`
+/// pairs and compare every [`DeadlineMetric`]
+///
+/// `run` advances a fresh simulation to the end of a run, e.g. until a number
+/// of customers have been served. Pair i uses seed `seed + i` for both of its
+/// runs. `config` must give the customers deadlines; its own discipline is ignored.
+pub fn compare_disciplines<F>(
+    config: &SimulationConfig,
+    replications: usize,
+    seed: u64,
+    mut run: F,
+) -> Result<Vec<DisciplineComparison>, String>
+where
+    F: FnMut(&mut Simulation),
+{
+    if config.deadlines.is_none() {
+        return Err("comparing disciplines needs customer deadlines".to_string());
+    }
+    if replications == 0 {
+        return Err("comparing disciplines needs at least one replication".to_string());
+    }
+    let replication_config = |i: usize, discipline| SimulationConfig {
+        seed: Some(seed.wrapping_add(i as u64)),
+        discipline,
+        ..config.clone()
+    };
+    let mut measure = |simulation: &mut Simulation| {
+        run(simulation);
+        DeadlineMetric::ALL.map(|metric| metric.measure(simulation))
+    };
+
+    // One simulation per discipline, reset for every replication
+    let mut fifo = Simulation::new(replication_config(0, QueueDiscipline::Fifo));
+    let mut edf = Simulation::new(replication_config(
+        0,
+        QueueDiscipline::EarliestDeadlineFirst,
+    ));
+    let mut fifo_moments: [Moments; DeadlineMetric::ALL.len()] = Default::default();
+    let mut edf_moments: [Moments; DeadlineMetric::ALL.len()] = Default::default();
+    let mut differences: [Moments; DeadlineMetric::ALL.len()] = Default::default();
+    for i in 0..replications {
+        if i > 0 {
+            fifo.reset(replication_config(i, QueueDiscipline::Fifo));
+            edf.reset(replication_config(
+                i,
+                QueueDiscipline::EarliestDeadlineFirst,
+            ));
+        }
+        let fifo_values = measure(&mut fifo);
+        let edf_values = measure(&mut edf);
+        for k in 0..DeadlineMetric::ALL.len() {
+            fifo_moments[k].push(fifo_values[k]);
+            edf_moments[k].push(edf_values[k]);
+            differences[k].push(edf_values[k] - fifo_values[k]);
+        }
+    }
+
+    let n = replications as f64;
+    Ok(DeadlineMetric::ALL
+        .iter()
+        .enumerate()
+        .map(|(k, &metric)| DisciplineComparison {
+            metric,
+            fifo: fifo_moments[k].mean().unwrap_or(0.0),
+            edf: edf_moments[k].mean().unwrap_or(0.0),
+            difference: differences[k].mean().unwrap_or(0.0),
+            half_width: differences[k].variance().map_or(f64::NAN, |variance| {
+                t_quantile_975(n - 1.0) * (variance / n).sqrt()
+            }),
+        })
+        .collect())
+}
@@ -0,0 +1,411 @@
+use crate::rng::Sampler;
+
+/// A source of non-negative random durations (service or inter-arrival times)
+///
+/// The random stream is owned by the caller so that entities can carry their own
+/// generator state and be cloned or forked independently.
+pub trait Distribution: Send {
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64;
+
+    fn clone_box(&self) -> Box<dyn Distribution>;
+
+    /// Check the parameters, so a misconfigured distribution is reported before
+    /// it produces NaNs or negative durations deep into a run
+    fn validate(&self) -> Result<(), String>;
+
+    /// Fraction of raw draws rejected by a truncation bound, if this is a truncated distribution
+    fn truncation_fraction(&self) -> Option<f64> {
+        None
+    }
+}
+
+impl Clone for Box<dyn Distribution> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone)]
+pub struct Exponential {
+    inv_rate: f64, // reciprocal of the rate (multiplication is faster than division)
+}
+
+impl Exponential {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            inv_rate: 1.0 / rate,
+        }
+    }
+}
+
+impl Distribution for Exponential {
+    #[inline]
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64 {
+        -rng.open_f64().ln() * self.inv_rate
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.inv_rate.is_finite() && self.inv_rate > 0.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "exponential rate must be positive and finite, got {}",
+                1.0 / self.inv_rate
+            ))
+        }
+    }
+}
+
+/// Always the same duration, for M/D/1 and other models with fixed service times
+#[derive(Clone)]
+pub struct Deterministic {
+    value: f64,
+}
+
+impl Deterministic {
+    pub fn new(value: f64) -> Self {
+        Self { value }
+    }
+}
+
+impl Distribution for Deterministic {
+    #[inline]
+    fn sample(&mut self, _rng: &mut dyn Sampler) -> f64 {
+        self.value
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.value.is_finite() && self.value > 0.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "deterministic duration must be positive and finite, got {}",
+                self.value
+            ))
+        }
+    }
+}
+
+/// Upper bound on re-draws for a single truncated sample before falling back to clamping
+const MAX_RESAMPLE_ATTEMPTS: u32 = 1_000;
+
+/// Restricts another distribution to values `<= max`
+///
+/// Draws above `max` are rejected and re-sampled, which preserves the conditional
+/// distribution of the inner one on `[0, max]`. Clamping would instead pile the
+/// excess mass onto `max`. If `max` is so small that `MAX_RESAMPLE_ATTEMPTS`
+/// draws in a row are rejected, the sample is clamped to `max` rather than looping forever.
+/// `max` must be positive; see [`Distribution::validate`].
+#[derive(Clone)]
+pub struct Truncated {
+    inner: Box<dyn Distribution>,
+    max: f64,
+    draws: u64,
+    rejected: u64,
+}
+
+impl Truncated {
+    pub fn new(inner: Box<dyn Distribution>, max: f64) -> Self {
+        Self {
+            inner,
+            max,
+            draws: 0,
+            rejected: 0,
+        }
+    }
+}
+
+impl Distribution for Truncated {
+    #[inline]
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64 {
+        for _ in 0..MAX_RESAMPLE_ATTEMPTS {
+            let value = self.inner.sample(rng);
+            self.draws += 1;
+            if value <= self.max {
+                return value;
+            }
+            self.rejected += 1;
+        }
+        self.max
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.max.is_nan() || self.max <= 0.0 {
+            return Err(format!(
+                "truncation bound must be positive, got {}",
+                self.max
+            ));
+        }
+        self.inner
+            .validate()
+            .map_err(|e| format!("truncated distribution: {}", e))
+    }
+
+    fn truncation_fraction(&self) -> Option<f64> {
+        if self.draws == 0 {
+            Some(0.0)
+        } else {
+            Some(self.rejected as f64 / self.draws as f64)
+        }
+    }
+}
+
+/// Standard normal draw by the Box-Muller transform, using two uniforms
+#[inline]
+fn standard_normal(rng: &mut dyn Sampler) -> f64 {
+    let radius = (-2.0 * rng.open_f64().ln()).sqrt();
+    radius * (std::f64::consts::TAU * rng.f64()).cos()
+}
+
+/// Sum of `phases` exponential phases of rate `rate` each, with squared
+/// coefficient of variation 1/`phases`
+#[derive(Clone)]
+pub struct Erlang {
+    phases: u32,
+    inv_rate: f64,
+}
+
+impl Erlang {
+    pub fn new(phases: u32, rate: f64) -> Self {
+        Self {
+            phases,
+            inv_rate: 1.0 / rate,
+        }
+    }
+}
+
+impl Distribution for Erlang {
+    #[inline]
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64 {
+        // Summing the logarithms, since a product of many uniforms underflows
+        let sum: f64 = (0..self.phases).map(|_| -rng.open_f64().ln()).sum();
+        sum * self.inv_rate
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.phases == 0 {
+            return Err("an Erlang distribution needs at least one phase".to_string());
+        }
+        if self.inv_rate.is_finite() && self.inv_rate > 0.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "Erlang phase rate must be positive and finite, got {}",
+                1.0 / self.inv_rate
+            ))
+        }
+    }
+}
+
+/// Gamma distribution with `shape` α and `scale` θ: mean αθ, squared coefficient of variation 1/α
+///
+/// Drawn with the Marsaglia-Tsang squeeze for α >= 1; a shape below 1 is boosted
+/// by one and the draw scaled by U^(1/α).
+#[derive(Clone)]
+pub struct Gamma {
+    shape: f64,
+    scale: f64,
+}
+
+impl Gamma {
+    pub fn new(shape: f64, scale: f64) -> Self {
+        Self { shape, scale }
+    }
+
+    fn sample_unit(shape: f64, rng: &mut dyn Sampler) -> f64 {
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = standard_normal(rng);
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+            let u = rng.open_f64();
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+}
+
+impl Distribution for Gamma {
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64 {
+        let unit = if self.shape >= 1.0 {
+            Self::sample_unit(self.shape, rng)
+        } else {
+            Self::sample_unit(self.shape + 1.0, rng) * rng.open_f64().powf(1.0 / self.shape)
+        };
+        unit * self.scale
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(self.shape > 0.0 && self.shape.is_finite()) {
+            return Err(format!(
+                "gamma shape must be positive and finite, got {}",
+                self.shape
+            ));
+        }
+        if self.scale > 0.0 && self.scale.is_finite() {
+            Ok(())
+        } else {
+            Err(format!(
+                "gamma scale must be positive and finite, got {}",
+                self.scale
+            ))
+        }
+    }
+}
+
+/// A duration whose logarithm is normal with mean `mu` and standard deviation `sigma`
+#[derive(Clone)]
+pub struct Lognormal {
+    mu: f64,
+    sigma: f64,
+}
+
+impl Lognormal {
+    pub fn new(mu: f64, sigma: f64) -> Self {
+        Self { mu, sigma }
+    }
+}
+
+impl Distribution for Lognormal {
+    #[inline]
+    fn sample(&mut self, rng: &mut dyn Sampler) -> f64 {
+        (self.mu + self.sigma * standard_normal(rng)).exp()
+    }
+
+    fn clone_box(&self) -> Box<dyn Distribution> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.mu.is_finite() {
+            return Err(format!("lognormal μ must be finite, got {}", self.mu));
+        }
+        if self.sigma > 0.0 && self.sigma.is_finite() {
+            Ok(())
+        } else {
+            Err(format!(
+                "lognormal σ must be positive and finite, got {}",
+                self.sigma
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mean of `n` draws of `distribution` from a fixed seed
+    fn mean_of(distribution: &mut dyn Distribution, n: usize) -> f64 {
+        let mut rng = fastrand::Rng::with_seed(1);
+        (0..n).map(|_| distribution.sample(&mut rng)).sum::<f64>() / n as f64
+    }
+
+    #[test]
+    fn truncation_keeps_the_conditional_distribution() {
+        let mut truncated = Truncated::new(Box::new(Exponential::new(1.0)), 1.0);
+        assert_eq!(truncated.truncation_fraction(), Some(0.0));
+        let mut rng = fastrand::Rng::with_seed(1);
+        let draws: Vec<f64> = (0..100_000).map(|_| truncated.sample(&mut rng)).collect();
+        assert!(draws.iter().all(|&draw| draw <= 1.0));
+
+        // E[X | X <= 1] = (1 - 2/e) / (1 - 1/e) for a unit exponential, where
+        // clamping would give 1 - 1/e
+        let e = std::f64::consts::E;
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        assert!((mean - (1.0 - 2.0 / e) / (1.0 - 1.0 / e)).abs() < 0.005);
+        let rejected = truncated.truncation_fraction().unwrap();
+        assert!((rejected - 1.0 / e).abs() < 0.005);
+    }
+
+    #[test]
+    fn a_bound_below_every_draw_clamps() {
+        let mut truncated = Truncated::new(Box::new(Deterministic::new(5.0)), 2.0);
+        assert_eq!(mean_of(&mut truncated, 3), 2.0);
+        assert_eq!(truncated.truncation_fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn a_bound_above_every_draw_changes_nothing() {
+        let mut truncated = Truncated::new(Box::new(Deterministic::new(0.5)), 2.0);
+        assert_eq!(mean_of(&mut truncated, 10), 0.5);
+        assert_eq!(truncated.truncation_fraction(), Some(0.0));
+    }
+
+    /// The message of a distribution that must fail validation
+    fn invalid(distribution: impl Distribution) -> String {
+        distribution
+            .validate()
+            .expect_err("the parameters are invalid")
+    }
+
+    #[test]
+    fn valid_parameters_pass() {
+        assert_eq!(Exponential::new(2.0).validate(), Ok(()));
+        assert_eq!(Deterministic::new(0.5).validate(), Ok(()));
+        assert_eq!(Erlang::new(3, 1.5).validate(), Ok(()));
+        assert_eq!(Gamma::new(0.5, 2.0).validate(), Ok(()));
+        assert_eq!(Lognormal::new(-1.0, 0.25).validate(), Ok(()));
+    }
+
+    #[test]
+    fn every_invalid_parameter_is_named() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(invalid(Exponential::new(rate)).starts_with("exponential rate"));
+            assert!(invalid(Erlang::new(2, rate)).starts_with("Erlang phase rate"));
+        }
+        for value in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(invalid(Deterministic::new(value)).starts_with("deterministic duration"));
+            assert!(invalid(Gamma::new(value, 1.0)).starts_with("gamma shape"));
+            assert!(invalid(Gamma::new(1.0, value)).starts_with("gamma scale"));
+            assert!(invalid(Lognormal::new(0.0, value)).starts_with("lognormal σ"));
+        }
+        assert_eq!(
+            invalid(Erlang::new(0, 1.0)),
+            "an Erlang distribution needs at least one phase"
+        );
+        for mu in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(invalid(Lognormal::new(mu, 1.0)).starts_with("lognormal μ"));
+        }
+    }
+
+    #[test]
+    fn truncation_needs_a_positive_bound_and_a_valid_inner_distribution() {
+        for max in [0.0, -1.0, f64::NAN] {
+            assert!(
+                Truncated::new(Box::new(Exponential::new(1.0)), max)
+                    .validate()
+                    .is_err()
+            );
+        }
+        let error = Truncated::new(Box::new(Exponential::new(-1.0)), 1.0)
+            .validate()
+            .unwrap_err();
+        assert!(error.starts_with("truncated distribution: exponential rate"));
+        assert_eq!(Exponential::new(1.0).truncation_fraction(), None);
+    }
+}
@@ -1,9 +1,96 @@
-use crate::event::Event;
+use crate::clock::ClockAudit;
+use crate::event::{Event, EventType};
+use std::time::Duration;
 
+/// Counters and handler timing for one event type
+#[derive(Debug, Clone, Default)]
+pub struct EventTypeMetrics {
+    /// Events taken off the schedule and returned by `run_step`
+    pub processed: u64,
+    /// Calls to `schedule`
+    pub scheduled: u64,
+    /// Scheduled events that replaced a still pending one (an implicit cancel)
+    pub replaced: u64,
+    /// Pending events dropped with `cancel`
+    pub cancelled: u64,
+    /// Wall-clock time spent in the handlers, as reported by the caller
+    pub handler_time: Duration,
+}
+
+/// Instrumentation collected by an engine built with [`SimulationEngine::with_instrumentation`]
+#[derive(Debug, Clone, Default)]
+pub struct EngineMetrics {
+    pub arrival: EventTypeMetrics,
+    pub departure: EventTypeMetrics,
+    pub breakdown: EventTypeMetrics,
+    pub repair: EventTypeMetrics,
+    pub switchover: EventTypeMetrics,
+    pub standby_breakdown: EventTypeMetrics,
+    pub standby_repair: EventTypeMetrics,
+    pub setup: EventTypeMetrics,
+    pub admission: EventTypeMetrics,
+    pub change: EventTypeMetrics,
+    /// Most events pending at once
+    pub peak_pending: usize,
+}
+
+impl EngineMetrics {
+    pub fn get(&self, event_type: EventType) -> &EventTypeMetrics {
+        match event_type {
+            EventType::Arrival => &self.arrival,
+            EventType::Departure => &self.departure,
+            EventType::Breakdown => &self.breakdown,
+            EventType::RepairComplete => &self.repair,
+            EventType::Switchover => &self.switchover,
+            EventType::StandbyBreakdown => &self.standby_breakdown,
+            EventType::StandbyRepair => &self.standby_repair,
+            EventType::Setup => &self.setup,
+            EventType::Admission => &self.admission,
+            EventType::ParameterChange => &self.change,
+        }
+    }
+
+    fn get_mut(&mut self, event_type: EventType) -> &mut EventTypeMetrics {
+        match event_type {
+            EventType::Arrival => &mut self.arrival,
+            EventType::Departure => &mut self.departure,
+            EventType::Breakdown => &mut self.breakdown,
+            EventType::RepairComplete => &mut self.repair,
+            EventType::Switchover => &mut self.switchover,
+            EventType::StandbyBreakdown => &mut self.standby_breakdown,
+            EventType::StandbyRepair => &mut self.standby_repair,
+            EventType::Setup => &mut self.setup,
+            EventType::Admission => &mut self.admission,
+            EventType::ParameterChange => &mut self.change,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct SimulationEngine {
     next_arrival: Option<Event>,
     next_departure: Option<Event>,
+    // A server is either up with a breakdown pending or down with a repair pending,
+    // so both share one slot
+    next_failure: Option<Event>,
+    // The standby is either switching over, serving with a breakdown pending or
+    // down with a repair pending
+    next_standby: Option<Event>,
+    // A setup only runs while the station is empty, so at most one is pending
+    next_setup: Option<Event>,
+    // Only one opening or closing of the admission schedule is pending at a time
+    next_admission: Option<Event>,
+    // Parameter changes are applied in order, each scheduling the next
+    next_change: Option<Event>,
     now: f64,
+    // Checked before every update, so an uninstrumented engine only pays for a
+    // predictable branch
+    instrumented: bool,
+    metrics: EngineMetrics,
+    // Steps of the clock relative to its magnitude, see `with_clock_audit`
+    clock_audit: Option<ClockAudit>,
+    // Sequence number given to the next scheduled event, see `Event::seq`
+    next_seq: u64,
 }
 
 impl SimulationEngine {
@@ -11,20 +98,123 @@ impl SimulationEngine {
         Self {
             next_arrival: None,
             next_departure: None,
+            next_failure: None,
+            next_standby: None,
+            next_setup: None,
+            next_admission: None,
+            next_change: None,
             now: 0.0,
+            instrumented: false,
+            metrics: EngineMetrics::default(),
+            clock_audit: None,
+            next_seq: 0,
         }
     }
 
+    /// Drop all pending events, metrics and audited steps and go back to t = 0;
+    /// instrumentation and the clock audit stay on or off as they were
+    pub fn reset(&mut self) {
+        self.next_arrival = None;
+        self.next_departure = None;
+        self.next_failure = None;
+        self.next_standby = None;
+        self.next_setup = None;
+        self.next_admission = None;
+        self.next_change = None;
+        self.now = 0.0;
+        self.next_seq = 0;
+        self.metrics = EngineMetrics::default();
+        if let Some(audit) = &mut self.clock_audit {
+            *audit = ClockAudit::new();
+        }
+    }
+
+    /// Count scheduled and processed events per type and accept handler timings
+    pub fn with_instrumentation(mut self, enabled: bool) -> Self {
+        self.instrumented = enabled;
+        self
+    }
+
     #[inline]
-    pub fn schedule(&mut self, event: Event) {
-        match event.event_type {
-            crate::event::EventType::Arrival => {
-                self.next_arrival = Some(event);
+    pub fn is_instrumented(&self) -> bool {
+        self.instrumented
+    }
+
+    /// Audit every step the clock takes, to detect a horizon at which `f64`
+    /// rounding quantizes the inter-event times (see [`crate::clock`])
+    pub fn with_clock_audit(mut self, enabled: bool) -> Self {
+        self.clock_audit = enabled.then(ClockAudit::new);
+        self
+    }
+
+    /// Steps audited so far, or `None` without a clock audit
+    pub fn clock_audit(&self) -> Option<&ClockAudit> {
+        self.clock_audit.as_ref()
+    }
+
+    /// Collected instrumentation, or `None` if the engine is not instrumented
+    pub fn metrics(&self) -> Option<&EngineMetrics> {
+        self.instrumented.then_some(&self.metrics)
+    }
+
+    /// Add the wall-clock time a handler spent on an event of `event_type`
+    #[inline]
+    pub fn record_handler_time(&mut self, event_type: EventType, elapsed: Duration) {
+        if self.instrumented {
+            self.metrics.get_mut(event_type).handler_time += elapsed;
+        }
+    }
+
+    /// Put `event` on the schedule, replacing a pending event of the same slot, and
+    /// stamp it with the next sequence number; `run_step` does not consult that
+    /// number, see `Event::seq`
+    #[inline]
+    pub fn schedule(&mut self, mut event: Event) {
+        event.seq = self.next_seq;
+        self.next_seq += 1;
+        let slot = match event.event_type {
+            EventType::Arrival => &mut self.next_arrival,
+            EventType::Departure => &mut self.next_departure,
+            EventType::Breakdown | EventType::RepairComplete => &mut self.next_failure,
+            EventType::Switchover | EventType::StandbyBreakdown | EventType::StandbyRepair => {
+                &mut self.next_standby
             }
-            crate::event::EventType::Departure => {
-                self.next_departure = Some(event);
+            EventType::Setup => &mut self.next_setup,
+            EventType::Admission => &mut self.next_admission,
+            EventType::ParameterChange => &mut self.next_change,
+        };
+        let replaced = slot.is_some();
+        *slot = Some(event);
+        if self.instrumented {
+            let metrics = self.metrics.get_mut(event.event_type);
+            metrics.scheduled += 1;
+            metrics.replaced += u64::from(replaced);
+            self.metrics.peak_pending = self.metrics.peak_pending.max(self.pending_events());
+        }
+    }
+
+    /// Drop the pending event of `event_type`, if any; returns whether one was pending
+    ///
+    /// Breakdown and repair share a slot, so cancelling either drops whichever is pending;
+    /// likewise the events of the standby server.
+    #[inline]
+    pub fn cancel(&mut self, event_type: EventType) -> bool {
+        let slot = match event_type {
+            EventType::Arrival => &mut self.next_arrival,
+            EventType::Departure => &mut self.next_departure,
+            EventType::Breakdown | EventType::RepairComplete => &mut self.next_failure,
+            EventType::Switchover | EventType::StandbyBreakdown | EventType::StandbyRepair => {
+                &mut self.next_standby
             }
+            EventType::Setup => &mut self.next_setup,
+            EventType::Admission => &mut self.next_admission,
+            EventType::ParameterChange => &mut self.next_change,
+        };
+        let cancelled = slot.take().is_some();
+        if self.instrumented && cancelled {
+            self.metrics.get_mut(event_type).cancelled += 1;
         }
+        cancelled
     }
 
     #[inline]
@@ -32,13 +222,36 @@ impl SimulationEngine {
         self.now
     }
 
+    /// Events scheduled and not yet processed, at most one per slot
+    pub fn pending_events(&self) -> usize {
+        [
+            &self.next_arrival,
+            &self.next_departure,
+            &self.next_failure,
+            &self.next_standby,
+            &self.next_setup,
+            &self.next_admission,
+            &self.next_change,
+        ]
+        .iter()
+        .filter(|slot| slot.is_some())
+        .count()
+    }
+
     #[inline]
     pub fn has_next_event(&self) -> bool {
-        self.next_arrival.is_some() || self.next_departure.is_some()
+        self.next_arrival.is_some()
+            || self.next_departure.is_some()
+            || self.next_failure.is_some()
+            || self.next_standby.is_some()
+            || self.next_setup.is_some()
+            || self.next_admission.is_some()
+            || self.next_change.is_some()
     }
 
+    /// Time of the next customer event, ignoring breakdowns, repairs, switchovers and setups
     #[inline]
-    pub fn peek_next_time(&self) -> f64 {
+    fn peek_customer_time(&self) -> f64 {
         match (&self.next_arrival, &self.next_departure) {
             (Some(arr), Some(dep)) => arr.time.min(dep.time),
             (Some(arr), None) => arr.time,
@@ -47,30 +260,205 @@ impl SimulationEngine {
         }
     }
 
+    /// Time of the next event other than an admission or parameter change
+    #[inline]
+    fn peek_station_time(&self) -> f64 {
+        let customer = self.peek_customer_time();
+        let failure = match &self.next_failure {
+            Some(failure) => customer.min(failure.time),
+            None => customer,
+        };
+        let standby = match &self.next_standby {
+            Some(standby) => failure.min(standby.time),
+            None => failure,
+        };
+        match &self.next_setup {
+            Some(setup) => standby.min(setup.time),
+            None => standby,
+        }
+    }
+
+    #[inline]
+    fn peek_change_time(&self) -> f64 {
+        self.next_change
+            .as_ref()
+            .map_or(f64::INFINITY, |change| change.time)
+    }
+
+    #[inline]
+    pub fn peek_next_time(&self) -> f64 {
+        let station = self.peek_station_time().min(self.peek_change_time());
+        match &self.next_admission {
+            Some(admission) => station.min(admission.time),
+            None => station,
+        }
+    }
+
     /// Process a single event
     ///
     /// This returns the event so the caller can dispatch it to the right entity.
     /// This design keeps the engine decoupled from entity logic.
+    ///
+    /// Events due at the same instant come out by the priority of their types below,
+    /// whatever order they were scheduled in.
     #[inline]
     pub fn run_step(&mut self) -> Option<Event> {
-        // Find which event happens next
-        let event = match (&self.next_arrival, &self.next_departure) {
-            (Some(arr), Some(dep)) => {
-                if arr.time <= dep.time {
-                    self.next_arrival.take()
-                } else {
-                    self.next_departure.take()
-                }
+        // Admission changes win every tie, so an arrival at the opening instant is
+        // admitted and one at the closing instant is not. Customer events win ties
+        // with failures, so a departure due at the instant of a breakdown still completes,
+        // and with setups, so an arrival at the end of a setup joins the queue first.
+        // Events of the standby server come right after those of the primary, so a
+        // primary repaired at the instant the standby would take over keeps the station.
+        // Parameter changes come right after admission changes, so every event at the
+        // instant of a change already sees the new parameters
+        let event = match (&self.next_admission, &self.next_failure, &self.next_setup) {
+            (Some(admission), _, _)
+                if admission.time <= self.peek_station_time().min(self.peek_change_time()) =>
+            {
+                self.next_admission.take()
+            }
+            _ if self.next_change.is_some()
+                && self.peek_change_time() <= self.peek_station_time() =>
+            {
+                self.next_change.take()
+            }
+            (_, Some(failure), _)
+                if failure.time < self.peek_customer_time()
+                    && self
+                        .next_standby
+                        .is_none_or(|standby| failure.time <= standby.time) =>
+            {
+                self.next_failure.take()
             }
-            (Some(_arr), None) => self.next_arrival.take(),
-            (None, Some(_dep)) => self.next_departure.take(),
-            (None, None) => None,
+            _ if self
+                .next_standby
+                .is_some_and(|standby| standby.time < self.peek_customer_time()) =>
+            {
+                self.next_standby.take()
+            }
+            (_, _, Some(setup)) if setup.time < self.peek_customer_time() => self.next_setup.take(),
+            _ => match (&self.next_arrival, &self.next_departure) {
+                (Some(arr), Some(dep)) => {
+                    // Arrivals win ties; every sampled duration is strictly positive
+                    // (see `Sampler::open_f64`), so an arrival never re-schedules itself
+                    // at the current instant and a tie cannot repeat forever
+                    if arr.time <= dep.time {
+                        self.next_arrival.take()
+                    } else {
+                        self.next_departure.take()
+                    }
+                }
+                (Some(_arr), None) => self.next_arrival.take(),
+                (None, Some(_dep)) => self.next_departure.take(),
+                (None, None) => self
+                    .next_failure
+                    .take()
+                    .or_else(|| self.next_standby.take())
+                    .or_else(|| self.next_setup.take()),
+            },
         };
 
         if let Some(ref e) = event {
+            if let Some(audit) = &mut self.clock_audit {
+                audit.record(e.time, e.time - self.now);
+            }
             self.now = e.time;
+            if self.instrumented {
+                self.metrics.get_mut(e.event_type).processed += 1;
+            }
         }
 
         event
     }
 }
+
+impl Default for SimulationEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(engine: &mut SimulationEngine) -> Vec<(EventType, u64)> {
+        std::iter::from_fn(|| engine.run_step())
+            .map(|event| (event.event_type, event.seq))
+            .collect()
+    }
+
+    #[test]
+    fn schedule_numbers_events_in_order() {
+        let mut engine = SimulationEngine::new();
+        engine.schedule(Event::new(1.0, EventType::Departure));
+        engine.schedule(Event::new(2.0, EventType::Breakdown));
+        engine.schedule(Event::new(3.0, EventType::Arrival));
+        assert_eq!(
+            drain(&mut engine),
+            [
+                (EventType::Departure, 0),
+                (EventType::Breakdown, 1),
+                (EventType::Arrival, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn simultaneous_events_are_dispatched_by_type_not_by_seq() {
+        let mut engine = SimulationEngine::new();
+        engine.schedule(Event::new(1.0, EventType::Setup));
+        engine.schedule(Event::new(1.0, EventType::Switchover));
+        engine.schedule(Event::new(1.0, EventType::Breakdown));
+        engine.schedule(Event::new(1.0, EventType::Departure));
+        engine.schedule(Event::new(1.0, EventType::Arrival));
+        engine.schedule(Event::new(1.0, EventType::ParameterChange));
+        engine.schedule(Event::new(1.0, EventType::Admission));
+        assert_eq!(
+            drain(&mut engine),
+            [
+                (EventType::Admission, 6),
+                (EventType::ParameterChange, 5),
+                (EventType::Arrival, 4),
+                (EventType::Departure, 3),
+                (EventType::Breakdown, 2),
+                (EventType::Switchover, 1),
+                (EventType::Setup, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_order_of_scheduling_does_not_change_the_dispatch_order() {
+        let mut forward = SimulationEngine::new();
+        forward.schedule(Event::new(1.0, EventType::Departure));
+        forward.schedule(Event::new(1.0, EventType::Arrival));
+        let mut backward = SimulationEngine::new();
+        backward.schedule(Event::new(1.0, EventType::Arrival));
+        backward.schedule(Event::new(1.0, EventType::Departure));
+
+        let types = |engine: &mut SimulationEngine| -> Vec<EventType> {
+            drain(engine)
+                .into_iter()
+                .map(|(event_type, _)| event_type)
+                .collect()
+        };
+        assert_eq!(
+            types(&mut forward),
+            [EventType::Arrival, EventType::Departure]
+        );
+        assert_eq!(
+            types(&mut backward),
+            [EventType::Arrival, EventType::Departure]
+        );
+    }
+
+    #[test]
+    fn reset_restarts_the_sequence() {
+        let mut engine = SimulationEngine::new();
+        engine.schedule(Event::new(1.0, EventType::Arrival));
+        engine.reset();
+        engine.schedule(Event::new(2.0, EventType::Arrival));
+        assert_eq!(engine.run_step().unwrap().seq, 0);
+    }
+}
@@ -1,104 +1,1156 @@
+use crate::config::{Breakdowns, InterruptedService, QueueDiscipline, ServiceModulation, Standby};
+use crate::distributions::Distribution;
 use crate::engine::SimulationEngine;
 use crate::event::{Event, EventType};
+use crate::ring_buffer::{self, RingBuffer};
+use crate::rng::Sampler;
 use crate::statistics::Statistics;
-use std::cell::RefCell;
-use std::collections::VecDeque;
-use std::rc::Rc;
+use crate::trace::{CustomerRecord, CustomerTrace};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 
+/// A customer in service, ordered so that a `BinaryHeap` pops the earliest completion
+/// and, among completions at the same instant, the service started first
+#[derive(Clone)]
+struct InService {
+    completion: f64,
+    arrival: f64,
+    start: f64,
+    /// Repair time spent in service so far, which does not count as being served
+    downtime: f64,
+    id: u64,
+    /// Which of the parallel servers serves the customer
+    slot: usize,
+    /// Absolute deadline, infinite for a customer without one
+    deadline: f64,
+    /// Service received by `completion`, earlier quanta included
+    served: f64,
+    /// Service still needed after `completion`; zero unless a quantum cuts it off
+    remaining: f64,
+    /// Position in the order services and quanta were started
+    seq: u64,
+}
+
+impl PartialEq for InService {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for InService {}
+
+impl PartialOrd for InService {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InService {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .completion
+            .total_cmp(&self.completion)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+/// A customer in the queue with the service and deadline drawn on arrival
+#[derive(Clone, Copy, Default)]
+struct Waiting {
+    arrival: f64,
+    /// Admission order, starting at 0
+    id: u64,
+    /// Service drawn at mean one, scaled to the mean in force when it starts
+    service: f64,
+    /// Absolute deadline, infinite for a customer without one
+    deadline: f64,
+}
+
+/// A waiting customer under round robin with the service it received in earlier quanta
+#[derive(Clone, Copy, Default)]
+struct Sliced {
+    /// `service` is what is left of the customer's service once it has started
+    waiting: Waiting,
+    /// Zero for a customer that has not started yet
+    served: f64,
+    /// Start of the first quantum
+    start: f64,
+}
+
+impl Sliced {
+    fn new(waiting: Waiting) -> Self {
+        Self {
+            waiting,
+            ..Default::default()
+        }
+    }
+}
+
+/// A waiting customer under earliest deadline first, ordered so that a `BinaryHeap`
+/// pops the soonest deadline and, on a tie, the earlier arrival
+#[derive(Clone, Copy)]
+struct ByDeadline {
+    waiting: Waiting,
+    /// Position in arrival order
+    order: u64,
+}
+
+impl PartialEq for ByDeadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByDeadline {}
+
+impl PartialOrd for ByDeadline {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByDeadline {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .waiting
+            .deadline
+            .total_cmp(&self.waiting.deadline)
+            .then(other.order.cmp(&self.order))
+    }
+}
+
+/// Which of the primary and the standby server are up, and which of them serves
+#[derive(Clone, Copy)]
+struct Failover {
+    standby: Standby,
+    primary_up: bool,
+    standby_up: bool,
+    /// The standby has taken over and serves while the primary is repaired
+    standby_serving: bool,
+    /// The standby is taking over; nobody is served until it has
+    switching: bool,
+}
+
+impl Failover {
+    fn new(standby: Standby) -> Self {
+        Self {
+            standby,
+            primary_up: true,
+            standby_up: true,
+            standby_serving: false,
+            switching: false,
+        }
+    }
+}
+
+/// The random streams of a station, one per source of randomness
+///
+/// Every arrival draws its service from `service` as it arrives, whether it
+/// is admitted or turned away, so the k-th draw always belongs to the k-th
+/// customer; likewise its deadline from `deadline`. Setups and breakdowns draw
+/// from streams of their own and cannot shift that correspondence. Two runs from
+/// the same seed then see the same uniforms for the same customers, and their
+/// difference reflects the change of parameters rather than a change of luck:
+/// common random numbers. Each draw
+/// transforms its uniform at mean one, e.g. `-ln(u)`, and the service takes that
+/// times the mean 1/μ in force when it starts, so a faster server gives every
+/// customer a proportionally shorter service.
+///
+/// The correspondence breaks where a draw takes a varying number of uniforms,
+/// as a truncated distribution re-sampling above its bound does, and for
+/// arrivals turned away by an admission schedule, which never reach the station.
+#[derive(Clone)]
+pub struct ServerStreams {
+    pub service: Box<dyn Sampler>,
+    pub setup: Box<dyn Sampler>,
+    /// Times to failure and repair, and the services drawn again after a restart
+    pub breakdown: Box<dyn Sampler>,
+    pub deadline: Box<dyn Sampler>,
+}
+
+impl ServerStreams {
+    /// Split off independent streams, advancing none of these
+    pub fn fork(&self) -> Self {
+        Self {
+            service: self.service.clone_box().fork(),
+            setup: self.setup.clone_box().fork(),
+            breakdown: self.breakdown.clone_box().fork(),
+            deadline: self.deadline.clone_box().fork(),
+        }
+    }
+}
+
+/// A customer in service as seen from outside the station, e.g. by a viewer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServiceInProgress {
+    /// Admission order, starting at 0
+    pub id: u64,
+    pub arrival: f64,
+    pub start: f64,
+    /// Scheduled departure; while the station is down this still excludes the repair
+    pub completion: f64,
+    /// Which of the parallel servers serves the customer, starting at 0
+    pub server: usize,
+}
+
+/// A snapshot of a station at one instant, read in constant time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerState {
+    pub queue_length: usize,
+    pub busy: bool,
+    /// Service left until the next departure, `None` while nobody is in service;
+    /// an estimate while the station is down, as it leaves out the rest of the repair
+    pub remaining_service: Option<f64>,
+    /// How long the customer next in line has waited, `None` with an empty queue;
+    /// under FIFO this is the oldest waiting customer
+    pub front_wait: Option<f64>,
+}
+
+/// A service station with one or more parallel servers sharing one queue
+///
+/// The engine holds a single pending departure, so the station keeps the completion
+/// times of all customers in service and always schedules the earliest of them.
+/// Statistics are owned by the simulation and passed in with each event.
+///
+/// With breakdowns enabled the station fails as a whole while busy. The pending
+/// departure is cancelled for the repair and rescheduled once it is complete;
+/// arrivals in the meantime queue up (or are lost without a waiting room).
+///
+/// With a setup time the station switches off whenever it empties. The next
+/// arrival starts a setup instead of a service; everyone arriving before the
+/// setup is complete queues, and services start when it is.
+///
+/// The queue is served first come, first served, or, with deadlines, earliest
+/// deadline first. Either way a customer whose deadline passes while it waits is
+/// still served and counted late, unless late customers are dropped when they
+/// reach the front.
+///
+/// Under round robin a customer is served for at most one quantum at a time and,
+/// if unfinished, goes to the back of the queue with the rest of its service; each
+/// quantum end is a departure event. Its wait is the time until its first quantum,
+/// and its whole service counts towards the server that finishes it.
+///
+/// With a standby server, a failure hands the interrupted services over to the
+/// standby after the switchover time, and the repaired primary takes them back
+/// at once. The station is down during a switchover and while both servers are.
+///
+/// The servers are interchangeable, but each customer is given the lowest-numbered
+/// free one so per-server busy times can be reported.
+#[derive(Clone)]
 pub struct Server {
-    inv_mu: f64, // reciprocal of service rate mu (multiplication is faster than division)
-    queue: VecDeque<f64>, // Queue of customer arrival times
-    busy: bool,
-    service_start_time: f64,
-    stats: Rc<RefCell<Statistics>>,
+    /// Services at mean one, scaled by `service_mean` as each starts
+    service: Box<dyn Distribution>,
+    service_mean: f64,
+    /// Speed-up of the service by the wait, with the μ the service times are drawn at
+    modulation: Option<(ServiceModulation, f64)>,
+    streams: ServerStreams,
+    /// Waiting customers under FIFO
+    queue: RingBuffer<Waiting>,
+    /// Waiting customers under earliest deadline first
+    deadline_queue: BinaryHeap<ByDeadline>,
+    /// Customers put into `deadline_queue` so far, to break ties in arrival order
+    queued_count: u64,
+    /// Waiting customers under round robin, preempted ones behind those who arrived before
+    round_robin_queue: RingBuffer<Sliced>,
+    /// Longest service before the customer goes back to the queue, infinite but under round robin
+    quantum: f64,
+    /// Most customers waiting at once
+    peak_queue_length: usize,
+    discipline: QueueDiscipline,
+    deadlines: Option<Box<dyn Distribution>>,
+    drop_late: bool,
+    servers: usize,
+    waiting_room: bool,
+    queue_limit: usize,
+    in_service: BinaryHeap<InService>,
+    /// Whether each server has a customer
+    slot_busy: Vec<bool>,
+    /// Customers admitted so far, the id of the next one
+    admitted_count: u64,
+    /// Services and quanta started, the sequence number of the next one
+    slices_started: u64,
+    breakdowns: Option<Breakdowns>,
+    failover: Option<Failover>,
+    down_since: Option<f64>,
+    setup: Option<Box<dyn Distribution>>,
+    setup_since: Option<f64>,
+    trace: CustomerTrace,
 }
 
 impl Server {
-    pub fn new(mu: f64, stats: Rc<RefCell<Statistics>>) -> Self {
+    /// A station drawing its services from `service` at mean one and scaling them to `service_mean`
+    pub fn new(service: Box<dyn Distribution>, service_mean: f64, streams: ServerStreams) -> Self {
         Self {
-            inv_mu: 1.0 / mu,
-            queue: VecDeque::new(),
-            busy: false,
-            service_start_time: 0.0,
-            stats,
+            service,
+            service_mean,
+            modulation: None,
+            streams,
+            queue: RingBuffer::new(),
+            deadline_queue: BinaryHeap::new(),
+            queued_count: 0,
+            // Only allocated when the discipline switches to round robin
+            round_robin_queue: RingBuffer::with_capacity(1),
+            quantum: f64::INFINITY,
+            peak_queue_length: 0,
+            discipline: QueueDiscipline::Fifo,
+            deadlines: None,
+            drop_late: false,
+            servers: 1,
+            waiting_room: true,
+            queue_limit: usize::MAX,
+            in_service: BinaryHeap::new(),
+            slot_busy: vec![false],
+            admitted_count: 0,
+            slices_started: 0,
+            breakdowns: None,
+            failover: None,
+            down_since: None,
+            setup: None,
+            setup_since: None,
+            trace: CustomerTrace::default(),
+        }
+    }
+
+    /// Draw from `streams` from now on, e.g. forked generators for an independent branch
+    pub fn set_streams(&mut self, streams: ServerStreams) {
+        self.streams = streams;
+    }
+
+    /// Use `servers` parallel servers; without a waiting room, arrivals finding them all busy are lost
+    pub fn set_servers(&mut self, servers: usize, waiting_room: bool) {
+        assert!(servers > 0, "a station needs at least one server");
+        self.servers = servers;
+        self.waiting_room = waiting_room;
+        self.slot_busy = vec![false; servers];
+        // Room for a customer per server, so starting a service never allocates
+        self.in_service.reserve(servers);
+    }
+
+    /// Change to `servers` parallel servers in the middle of a run
+    ///
+    /// Added servers take waiting customers at once, unless the station is down or
+    /// switching on. Removed servers finish the customer they are serving and take
+    /// no one after; the highest-numbered ones go first.
+    pub fn change_servers(
+        &mut self,
+        servers: usize,
+        engine: &mut SimulationEngine,
+        stats: &mut Statistics,
+    ) {
+        assert!(servers > 0, "a station needs at least one server");
+        let was_busy = self.is_busy();
+        self.servers = servers;
+        if self.slot_busy.len() < servers {
+            self.slot_busy.resize(servers, false);
+            self.in_service.reserve(servers - self.in_service.len());
+        }
+        while self.slot_busy.len() > servers && self.slot_busy.last() == Some(&false) {
+            self.slot_busy.pop();
+        }
+        if self.down_since.is_some() || self.setup_since.is_some() {
+            return;
+        }
+        while self.in_service.len() < self.servers && self.queue_length() > 0 {
+            self.start_service(engine, stats);
+        }
+        if !was_busy && self.is_busy() {
+            self.schedule_breakdown(engine);
+        }
+    }
+
+    /// Turn away arrivals that would make the queue longer than `limit`; `None` for no limit
+    pub fn set_queue_limit(&mut self, limit: Option<usize>) {
+        self.queue_limit = limit.unwrap_or(usize::MAX);
+    }
+
+    /// Let the station fail and be repaired; `None` makes it reliable from now on
+    ///
+    /// A breakdown or repair that is already scheduled still happens.
+    pub fn set_breakdowns(&mut self, breakdowns: Option<Breakdowns>) {
+        self.breakdowns = breakdowns;
+    }
+
+    /// Back the failing server up with `standby`; `None` leaves the station down during repairs
+    ///
+    /// Both servers start out up, whatever the state of the current ones.
+    pub fn set_standby(&mut self, standby: Option<Standby>) {
+        self.failover = standby.map(Failover::new);
+    }
+
+    /// Switch on for a duration drawn from `setup` at the start of each busy period;
+    /// `None` keeps the station on
+    ///
+    /// A setup already in progress still completes.
+    pub fn set_setup(&mut self, setup: Option<Box<dyn Distribution>>) {
+        self.setup = setup;
+    }
+
+    /// Give each arrival a deadline drawn from `deadlines` after its arrival; `None` for none
+    ///
+    /// With `drop_late`, a customer whose deadline has passed when it would start
+    /// service leaves unserved. Customers already waiting keep their deadlines.
+    pub fn set_deadlines(&mut self, deadlines: Option<Box<dyn Distribution>>, drop_late: bool) {
+        self.deadlines = deadlines;
+        self.drop_late = drop_late;
+    }
+
+    /// Serve the queue in the order of `discipline`, re-sorting the customers already waiting
+    ///
+    /// A customer part-way through its service that leaves round robin keeps only
+    /// what is left of its service and starts it as if it had not been served yet.
+    pub fn set_discipline(&mut self, discipline: QueueDiscipline) {
+        if discipline == self.discipline {
+            return;
+        }
+        // In arrival order, apart from those round robin has already rotated
+        let waiting: Vec<Sliced> = match self.discipline {
+            QueueDiscipline::Fifo => std::iter::from_fn(|| self.queue.pop_front())
+                .map(Sliced::new)
+                .collect(),
+            QueueDiscipline::EarliestDeadlineFirst => {
+                let mut waiting = std::mem::take(&mut self.deadline_queue).into_vec();
+                waiting.sort_by_key(|customer| customer.order);
+                waiting
+                    .into_iter()
+                    .map(|customer| Sliced::new(customer.waiting))
+                    .collect()
+            }
+            QueueDiscipline::RoundRobin => {
+                std::iter::from_fn(|| self.round_robin_queue.pop_front()).collect()
+            }
+        };
+        self.discipline = discipline;
+        match discipline {
+            QueueDiscipline::Fifo => {}
+            QueueDiscipline::EarliestDeadlineFirst => {
+                self.deadline_queue.reserve(ring_buffer::INITIAL_CAPACITY);
+            }
+            QueueDiscipline::RoundRobin => {
+                if self.round_robin_queue.capacity() < ring_buffer::INITIAL_CAPACITY {
+                    self.round_robin_queue = RingBuffer::new();
+                }
+            }
+        }
+        for customer in waiting {
+            self.push_sliced(customer);
+        }
+    }
+
+    /// Cut services off after `quantum` under round robin; ignored under the other disciplines
+    pub fn set_quantum(&mut self, quantum: f64) {
+        self.quantum = quantum;
+    }
+
+    /// Record the timeline of the first `limit` customers admitted
+    pub fn set_trace_limit(&mut self, limit: usize) {
+        self.trace.reset(limit);
+    }
+
+    /// Empty the station for a new run, keeping parameters, generator and buffers
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.deadline_queue.clear();
+        self.round_robin_queue.clear();
+        self.queued_count = 0;
+        self.peak_queue_length = 0;
+        self.in_service.clear();
+        self.slot_busy.fill(false);
+        self.admitted_count = 0;
+        self.slices_started = 0;
+        if let Some(failover) = &mut self.failover {
+            *failover = Failover::new(failover.standby);
+        }
+        self.down_since = None;
+        self.setup_since = None;
+        let limit = self.trace.limit();
+        self.trace.reset(limit);
+    }
+
+    pub fn customer_trace(&self) -> &CustomerTrace {
+        &self.trace
+    }
+
+    pub fn streams(&self) -> &ServerStreams {
+        &self.streams
+    }
+
+    pub fn queue_length(&self) -> usize {
+        self.queue.len() + self.deadline_queue.len() + self.round_robin_queue.len()
+    }
+
+    /// Most customers waiting at once during the run
+    pub fn peak_queue_length(&self) -> usize {
+        self.peak_queue_length
+    }
+
+    /// Largest number of waiting customers the queue buffer has had room for
+    pub fn peak_queue_capacity(&self) -> usize {
+        // The heap never shrinks, so its capacity is its largest
+        self.queue
+            .peak_capacity()
+            .max(self.deadline_queue.capacity())
+            .max(self.round_robin_queue.peak_capacity())
+    }
+
+    /// Times the queue buffer grew during the run
+    pub fn queue_reallocations(&self) -> u32 {
+        self.queue.reallocations()
+    }
+
+    /// Estimated bytes taken by the queue buffer at its largest
+    pub fn peak_queue_memory_bytes(&self) -> usize {
+        self.queue.peak_capacity() * std::mem::size_of::<Waiting>()
+            + self.deadline_queue.capacity() * std::mem::size_of::<ByDeadline>()
+            + self.round_robin_queue.peak_capacity() * std::mem::size_of::<Sliced>()
+    }
+
+    pub fn servers(&self) -> usize {
+        self.servers
+    }
+
+    pub fn busy_servers(&self) -> usize {
+        self.in_service.len()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.in_service.is_empty()
+    }
+
+    /// Customers in service, earliest departure first
+    pub fn services_in_progress(&self) -> Vec<ServiceInProgress> {
+        let mut services: Vec<ServiceInProgress> = self
+            .in_service
+            .iter()
+            .map(|customer| ServiceInProgress {
+                id: customer.id,
+                arrival: customer.arrival,
+                start: customer.start,
+                completion: customer.completion,
+                server: customer.slot,
+            })
+            .collect();
+        services.sort_by(|a, b| a.completion.total_cmp(&b.completion));
+        services
+    }
+
+    /// The queue and the service in progress as they stand at `now`
+    pub fn current_state(&self, now: f64) -> ServerState {
+        // Time stands still for the customers in service during a repair
+        let served_until = self.down_since.unwrap_or(now);
+        let front_arrival = self
+            .queue
+            .front()
+            .map(|waiting| waiting.arrival)
+            .or_else(|| {
+                self.deadline_queue
+                    .peek()
+                    .map(|customer| customer.waiting.arrival)
+            })
+            .or_else(|| {
+                self.round_robin_queue
+                    .front()
+                    .map(|customer| customer.waiting.arrival)
+            });
+        ServerState {
+            queue_length: self.queue_length(),
+            busy: self.is_busy(),
+            remaining_service: self
+                .in_service
+                .peek()
+                .map(|customer| (customer.completion - served_until).max(0.0)),
+            front_wait: front_arrival.map(|arrival| now - arrival),
+        }
+    }
+
+    /// Arrival times of the waiting customers, next to be served first
+    pub fn waiting_arrivals(&self) -> impl Iterator<Item = f64> + '_ {
+        let mut by_deadline: Vec<&ByDeadline> = self.deadline_queue.iter().collect();
+        by_deadline.sort_by(|a, b| b.cmp(a));
+        self.queue
+            .iter()
+            .map(|waiting| waiting.arrival)
+            .chain(
+                by_deadline
+                    .into_iter()
+                    .map(|customer| customer.waiting.arrival),
+            )
+            .chain(
+                self.round_robin_queue
+                    .iter()
+                    .map(|customer| customer.waiting.arrival),
+            )
+    }
+
+    /// Whether the station is broken down and waiting for its repair
+    pub fn is_down(&self) -> bool {
+        self.down_since.is_some()
+    }
+
+    /// Whether the standby server serves in place of the primary
+    pub fn is_standby_serving(&self) -> bool {
+        self.failover
+            .is_some_and(|failover| failover.standby_serving)
+    }
+
+    /// Whether the station is switching on and not yet serving
+    pub fn is_setting_up(&self) -> bool {
+        self.setup_since.is_some()
+    }
+
+    /// Scale each service time drawn at rate `mu` by μ / μ_eff as its customer enters service;
+    /// `None` serves at the drawn durations
+    ///
+    /// Customers already waiting get the speed-up of the new `mu` when they start.
+    pub fn set_service_modulation(&mut self, modulation: Option<ServiceModulation>, mu: f64) {
+        self.modulation = modulation.map(|modulation| (modulation, mu));
+    }
+
+    /// Draw services from `service` at mean one and scale them to `mean`
+    ///
+    /// A customer's draw is turned into a duration when its service starts, so
+    /// customers already waiting are served at the new mean while those in
+    /// service keep the durations they started with. A draw made under a
+    /// truncation bound keeps that bound in units of the mean, so a waiting
+    /// customer's service may exceed the bound once the mean grows.
+    pub fn set_service_distribution(&mut self, service: Box<dyn Distribution>, mean: f64) {
+        self.service = service;
+        self.service_mean = mean;
+    }
+
+    #[inline]
+    pub fn receive_customer(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let now = engine.now();
+        let unavailable = self.in_service.len() >= self.servers
+            || self.down_since.is_some()
+            || self.setup_since.is_some();
+
+        // Drawn before admission, so a customer turned away still uses up its draws
+        let service = self.service.sample(self.streams.service.as_mut());
+        let deadline = self.draw_deadline(now);
+
+        stats.record_arrival(now);
+        if unavailable && (!self.waiting_room || self.queue_length() >= self.queue_limit) {
+            stats.record_loss();
+            return;
+        }
+
+        self.admit(now, service, deadline);
+        stats.record_queue_change(now, self.queue_length());
+
+        let starts_setup = !unavailable && self.setup.is_some() && self.in_service.is_empty();
+        stats.record_admission(unavailable || starts_setup);
+        if starts_setup {
+            self.start_setup(engine, stats);
+        } else if !unavailable {
+            self.start_service(engine, stats);
+            // The time to failure only runs while busy, so start it when the station leaves idle
+            if self.in_service.len() == 1 {
+                self.schedule_breakdown(engine);
+            }
+        }
+    }
+
+    /// Put `customers` into the empty station at the current time, as if they had just arrived
+    ///
+    /// As many as there are servers start service, the rest wait; the station
+    /// starts out switched on, so there is no setup. They are not
+    /// counted as arrivals, but their waits and services count like any other.
+    pub fn preload(
+        &mut self,
+        customers: usize,
+        engine: &mut SimulationEngine,
+        stats: &mut Statistics,
+    ) {
+        if customers == 0 {
+            return;
+        }
+        let now = engine.now();
+        for _ in 0..customers {
+            let service = self.service.sample(self.streams.service.as_mut());
+            let deadline = self.draw_deadline(now);
+            self.admit(now, service, deadline);
+        }
+        stats.record_queue_change(now, self.queue_length());
+        while self.in_service.len() < self.servers && self.queue_length() > 0 {
+            self.start_service(engine, stats);
+        }
+        if self.is_busy() {
+            self.schedule_breakdown(engine);
         }
     }
 
+    /// Absolute deadline of a customer arriving at `now`
+    fn draw_deadline(&mut self, now: f64) -> f64 {
+        match &mut self.deadlines {
+            Some(deadlines) => now + deadlines.sample(self.streams.deadline.as_mut()),
+            None => f64::INFINITY,
+        }
+    }
+
+    /// Queue a customer that has just arrived, numbering it in arrival order
+    #[inline]
+    fn admit(&mut self, arrival: f64, service: f64, deadline: f64) {
+        let id = self.admitted_count;
+        self.admitted_count += 1;
+        self.push_sliced(Sliced::new(Waiting {
+            arrival,
+            id,
+            service,
+            deadline,
+        }));
+    }
+
+    /// Queue a customer; the service it already received only survives under round robin
+    #[inline]
+    fn push_sliced(&mut self, customer: Sliced) {
+        match self.discipline {
+            QueueDiscipline::Fifo => self.queue.push_back(customer.waiting),
+            QueueDiscipline::EarliestDeadlineFirst => {
+                self.deadline_queue.push(ByDeadline {
+                    waiting: customer.waiting,
+                    order: self.queued_count,
+                });
+                self.queued_count += 1;
+            }
+            QueueDiscipline::RoundRobin => self.round_robin_queue.push_back(customer),
+        }
+        self.peak_queue_length = self.peak_queue_length.max(self.queue_length());
+    }
+
     #[inline]
-    pub fn receive_customer(&mut self, engine: &mut SimulationEngine) {
+    fn pop_waiting(&mut self) -> Option<Sliced> {
+        match self.discipline {
+            QueueDiscipline::Fifo => self.queue.pop_front().map(Sliced::new),
+            QueueDiscipline::EarliestDeadlineFirst => self
+                .deadline_queue
+                .pop()
+                .map(|customer| Sliced::new(customer.waiting)),
+            QueueDiscipline::RoundRobin => self.round_robin_queue.pop_front(),
+        }
+    }
+
+    /// Start serving the next waiting customer, or its next quantum under round robin;
+    /// `false` if nobody was left to serve
+    #[inline]
+    fn start_service(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) -> bool {
         let now = engine.now();
+        let Sliced {
+            waiting:
+                Waiting {
+                    arrival: arrival_time,
+                    id,
+                    service: service_time,
+                    deadline,
+                },
+            served,
+            start,
+        } = loop {
+            let Some(customer) = self.pop_waiting() else {
+                return false;
+            };
+            if self.drop_late && customer.waiting.deadline < now {
+                stats.record_queue_change(now, self.queue_length());
+                stats.record_late_drop();
+                continue;
+            }
+            break customer;
+        };
+        let resumed = served > 0.0;
+
+        stats.record_queue_change(now, self.queue_length());
+        let service_time = if resumed {
+            stats.record_service_resume(now);
+            service_time
+        } else {
+            let wait = now - arrival_time;
+            stats.record_service_start(now, wait);
+            let service_time = service_time * self.service_mean;
+            // The speed-up applies once, to the whole service, when it first starts
+            let service_time = match &self.modulation {
+                Some((modulation, mu)) => service_time * mu / modulation.rate(*mu, wait),
+                None => service_time,
+            };
+            stats.record_service_demand(wait, service_time);
+            service_time
+        };
 
-        self.queue.push_back(now);
+        let slot = self
+            .slot_busy
+            .iter()
+            .position(|busy| !busy)
+            .expect("a service only starts with a free server");
+        self.slot_busy[slot] = true;
 
-        self.stats
-            .borrow_mut()
-            .record_queue_change(now, self.queue.len());
+        // The quantum is infinite but under round robin, so others serve to the end
+        let slice = service_time.min(self.quantum);
+        self.in_service.push(InService {
+            completion: now + slice,
+            arrival: arrival_time,
+            start: if resumed { start } else { now },
+            downtime: 0.0,
+            id,
+            slot,
+            deadline,
+            served: served + slice,
+            remaining: service_time - slice,
+            seq: self.slices_started,
+        });
+        self.slices_started += 1;
 
-        if !self.busy {
-            self.start_service(engine);
+        self.schedule_next_departure(engine);
+        true
+    }
+
+    #[inline]
+    fn schedule_next_departure(&self, engine: &mut SimulationEngine) {
+        if let Some(next) = self.in_service.peek() {
+            engine.schedule(Event::new(next.completion, EventType::Departure));
         }
     }
 
+    pub fn service_distribution(&self) -> &dyn Distribution {
+        self.service.as_ref()
+    }
+
     #[inline]
-    fn start_service(&mut self, engine: &mut SimulationEngine) {
-        if self.queue.is_empty() {
+    pub fn handle_departure(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(done) = self.in_service.pop() else {
+            return;
+        };
+        let now = engine.now();
+
+        self.slot_busy[done.slot] = false;
+        // Servers removed mid-run leave once they have finished their customer
+        while self.slot_busy.len() > self.servers && self.slot_busy.last() == Some(&false) {
+            self.slot_busy.pop();
+        }
+        if done.remaining > 0.0 {
+            // The quantum ran out: back to the end of the queue with the rest of the service
+            stats.record_preemption(now);
+            self.push_sliced(Sliced {
+                waiting: Waiting {
+                    arrival: done.arrival,
+                    id: done.id,
+                    service: done.remaining,
+                    deadline: done.deadline,
+                },
+                served: done.served,
+                start: done.start,
+            });
+            stats.record_queue_change(now, self.queue_length());
+        } else {
+            // Quanta are separated by waits, so the service is only known as their sum
+            let service_duration = if self.discipline == QueueDiscipline::RoundRobin {
+                done.served
+            } else {
+                now - done.start - done.downtime
+            };
+            stats.record_service_end(now, service_duration, done.slot);
+            stats.record_sojourn(now - done.arrival, service_duration);
+
+            self.trace.record(CustomerRecord {
+                id: done.id,
+                arrival: done.arrival,
+                service_start: done.start,
+                departure: now,
+            });
+            if done.deadline.is_finite() {
+                stats.record_deadline(now - done.deadline);
+            }
+        }
+
+        if self.in_service.len() >= self.servers || !self.start_service(engine, stats) {
+            self.schedule_next_departure(engine);
+            if self.breakdowns.is_some() && self.in_service.is_empty() {
+                engine.cancel(self.failure_type());
+            }
+        }
+    }
+
+    fn start_setup(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(setup) = &mut self.setup else {
+            return;
+        };
+        let now = engine.now();
+        let setup_time = setup.sample(self.streams.setup.as_mut());
+
+        self.setup_since = Some(now);
+        stats.record_setup_start(now);
+        engine.schedule(Event::new(now + setup_time, EventType::Setup));
+    }
+
+    /// Start serving the customers who queued up during the setup
+    ///
+    /// Should the queue have emptied in the meantime, the setup is wasted: it
+    /// still counts, the station switches off again and the next arrival starts
+    /// a new one.
+    pub fn handle_setup(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        if self.setup_since.take().is_none() {
             return;
         }
+        let now = engine.now();
+
+        // The queue was empty when the setup began, so all of it waited for the setup
+        let setup_wait = self
+            .queue
+            .iter()
+            .chain(self.deadline_queue.iter().map(|customer| customer.waiting))
+            .chain(
+                self.round_robin_queue
+                    .iter()
+                    .map(|customer| customer.waiting),
+            )
+            .map(|waiting| now - waiting.arrival)
+            .sum();
+        stats.record_setup_end(now, setup_wait);
+
+        while self.in_service.len() < self.servers && self.queue_length() > 0 {
+            self.start_service(engine, stats);
+        }
+        if self.is_busy() {
+            self.schedule_breakdown(engine);
+        }
+    }
+
+    /// Failure of the server that is serving, the standby or else the primary
+    fn failure_type(&self) -> EventType {
+        if self.is_standby_serving() {
+            EventType::StandbyBreakdown
+        } else {
+            EventType::Breakdown
+        }
+    }
 
+    fn schedule_breakdown(&mut self, engine: &mut SimulationEngine) {
+        let mean_time_to_failure = match (&self.failover, &self.breakdowns) {
+            (Some(failover), _) if failover.standby_serving => {
+                failover.standby.mean_time_to_failure
+            }
+            (_, Some(breakdowns)) => breakdowns.mean_time_to_failure,
+            _ => return,
+        };
+        let time_to_failure = -self.streams.breakdown.open_f64().ln() * mean_time_to_failure;
+        engine.schedule(Event::new(
+            engine.now() + time_to_failure,
+            self.failure_type(),
+        ));
+    }
+
+    /// Stop serving until the repair is complete, or until the standby has taken over
+    pub fn handle_breakdown(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(breakdowns) = &self.breakdowns else {
+            return;
+        };
         let now = engine.now();
-        let arrival_time = self.queue.pop_front().unwrap();
-        let wait_time = now - arrival_time;
+        let repair_time = -self.streams.breakdown.open_f64().ln() * breakdowns.mean_repair_time;
 
-        let mut stats = self.stats.borrow_mut();
-        stats.record_queue_change(now, self.queue.len());
-        stats.record_service_start(now, wait_time);
-        drop(stats);
+        self.down_since = Some(now);
+        stats.record_breakdown(now);
+        engine.cancel(EventType::Departure);
+        engine.schedule(Event::new(now + repair_time, EventType::RepairComplete));
 
-        self.busy = true;
-        self.service_start_time = now;
+        if let Some(failover) = &mut self.failover {
+            failover.primary_up = false;
+            if failover.standby_up {
+                self.start_switchover(engine, stats);
+            } else {
+                stats.record_outage(now, true);
+            }
+        }
+    }
 
-        // Generate service time from exponential distribution
-        // Using pre-computed reciprocal for faster multiplication
-        let service_time = -fastrand::f64().ln() * self.inv_mu;
+    /// Begin handing the station over to the standby, which takes it at once without a switchover time
+    fn start_switchover(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(failover) = &mut self.failover else {
+            return;
+        };
+        stats.record_failover(self.in_service.len());
+        if failover.standby.switchover_time > 0.0 {
+            failover.switching = true;
+            engine.schedule(Event::new(
+                engine.now() + failover.standby.switchover_time,
+                EventType::Switchover,
+            ));
+        } else {
+            self.take_over(engine, stats);
+        }
+    }
 
-        engine.schedule(Event::new(now + service_time, EventType::Departure));
+    /// The standby has finished taking over and serves until the primary is repaired
+    pub fn handle_switchover(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(failover) = &mut self.failover else {
+            return;
+        };
+        if !failover.switching {
+            return;
+        }
+        failover.switching = false;
+        self.take_over(engine, stats);
     }
 
-    #[inline]
-    pub fn handle_departure(&mut self, engine: &mut SimulationEngine) {
+    fn take_over(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        if let Some(failover) = &mut self.failover {
+            failover.standby_serving = true;
+        }
+        stats.record_standby_serving(engine.now(), true);
+        self.resume_service(engine, stats);
+    }
+
+    /// Stop serving until either server is repaired; the primary is down already
+    pub fn handle_standby_breakdown(
+        &mut self,
+        engine: &mut SimulationEngine,
+        stats: &mut Statistics,
+    ) {
+        let Some(failover) = &mut self.failover else {
+            return;
+        };
+        let now = engine.now();
+        let repair_time =
+            -self.streams.breakdown.open_f64().ln() * failover.standby.mean_repair_time;
+
+        failover.standby_up = false;
+        failover.standby_serving = false;
+        self.down_since = Some(now);
+        stats.record_standby_breakdown(now);
+        stats.record_outage(now, true);
+        engine.cancel(EventType::Departure);
+        engine.schedule(Event::new(now + repair_time, EventType::StandbyRepair));
+    }
+
+    /// Bring the standby back up; it takes over if the primary is still under repair
+    pub fn handle_standby_repair(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(failover) = &mut self.failover else {
+            return;
+        };
+        failover.standby_up = true;
+        if !failover.primary_up {
+            stats.record_outage(engine.now(), false);
+            self.start_switchover(engine, stats);
+        }
+    }
+
+    /// Bring the primary back up and let it take over the station
+    ///
+    /// From a standby that is serving it takes over without a break in service; a
+    /// switchover in progress is called off.
+    pub fn handle_repair(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        if let Some(failover) = &mut self.failover {
+            let now = engine.now();
+            failover.primary_up = true;
+            if failover.standby_serving {
+                failover.standby_serving = false;
+                stats.record_standby_serving(now, false);
+                if self.is_busy() {
+                    engine.cancel(EventType::StandbyBreakdown);
+                    self.schedule_breakdown(engine);
+                }
+                return;
+            }
+            if failover.switching {
+                failover.switching = false;
+                engine.cancel(EventType::Switchover);
+            } else if !failover.standby_up {
+                stats.record_outage(now, false);
+            }
+        }
+        self.resume_service(engine, stats);
+    }
+
+    /// Continue or restart the interrupted services and take customers who queued up meanwhile
+    fn resume_service(&mut self, engine: &mut SimulationEngine, stats: &mut Statistics) {
+        let Some(down_since) = self.down_since.take() else {
+            return;
+        };
         let now = engine.now();
-        let service_duration = now - self.service_start_time;
+        let downtime = now - down_since;
+        let restart = self
+            .breakdowns
+            .is_some_and(|b| b.interrupted_service == InterruptedService::Restart);
 
-        self.busy = false;
-        self.stats
-            .borrow_mut()
-            .record_service_end(now, service_duration);
+        stats.record_repair(now);
 
-        if !self.queue.is_empty() {
-            self.start_service(engine);
+        let interrupted = std::mem::take(&mut self.in_service).into_vec();
+        self.in_service = interrupted
+            .into_iter()
+            .map(|mut customer| {
+                customer.downtime += downtime;
+                customer.completion = if restart {
+                    now + self.service.sample(self.streams.breakdown.as_mut()) * self.service_mean
+                } else {
+                    customer.completion + downtime
+                };
+                customer
+            })
+            .collect();
+
+        while self.in_service.len() < self.servers && self.queue_length() > 0 {
+            self.start_service(engine, stats);
+        }
+        self.schedule_next_departure(engine);
+        if self.is_busy() {
+            self.schedule_breakdown(engine);
         }
     }
 }
 
+/// Arrival process; the simulation routes each generated customer to the server
+#[derive(Clone)]
 pub struct Client {
     inv_lambda: f64,
-    server: Rc<RefCell<Server>>,
+    cutoff: f64,
+    rng: Box<dyn Sampler>,
+    /// Interarrival times at mean one, scaled by 1/λ; `None` for Poisson arrivals
+    interarrival: Option<Box<dyn Distribution>>,
 }
 
 impl Client {
-    pub fn new(lambda: f64, server: Rc<RefCell<Server>>) -> Self {
+    pub fn new(lambda: f64, rng: Box<dyn Sampler>) -> Self {
         Self {
             inv_lambda: 1.0 / lambda,
-            server,
+            cutoff: f64::INFINITY,
+            rng,
+            interarrival: None,
         }
     }
 
+    /// Draw the interarrival times from `interarrival`, scaled to mean one; `None`
+    /// makes arrivals Poisson again
+    ///
+    /// The already scheduled next arrival is left untouched.
+    pub fn set_interarrival(&mut self, interarrival: Option<Box<dyn Distribution>>) {
+        self.interarrival = interarrival;
+    }
+
+    /// Draw from `rng` from now on, e.g. a forked generator for an independent branch
+    pub fn set_rng(&mut self, rng: Box<dyn Sampler>) {
+        self.rng = rng;
+    }
+
+    pub fn rng(&self) -> &dyn Sampler {
+        self.rng.as_ref()
+    }
+
+    /// Stop generating arrivals at `cutoff`; `None` keeps them coming forever
+    pub fn set_cutoff(&mut self, cutoff: Option<f64>) {
+        self.cutoff = cutoff.unwrap_or(f64::INFINITY);
+    }
+
+    /// Change the arrival rate; the already scheduled next arrival is left untouched
+    pub fn set_lambda(&mut self, lambda: f64) {
+        self.inv_lambda = 1.0 / lambda;
+    }
+
+    /// Schedule the next arrival after the one being processed, unless it falls past the cutoff
     #[inline]
     pub fn handle_generate(&mut self, engine: &mut SimulationEngine) {
-        self.server.borrow_mut().receive_customer(engine);
-
-        let inter_arrival_time = -fastrand::f64().ln() * self.inv_lambda;
+        let inter_arrival_time = match &mut self.interarrival {
+            None => -self.rng.open_f64().ln() * self.inv_lambda,
+            Some(interarrival) => interarrival.sample(self.rng.as_mut()) * self.inv_lambda,
+        };
         let next_time = engine.now() + inter_arrival_time;
-        engine.schedule(Event::new(next_time, EventType::Arrival));
+        if next_time < self.cutoff {
+            engine.schedule(Event::new(next_time, EventType::Arrival));
+        }
     }
 }
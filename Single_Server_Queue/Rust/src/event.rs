@@ -2,24 +2,65 @@
 pub enum EventType {
     Arrival,
     Departure,
+    Breakdown,
+    RepairComplete,
+    /// The standby server finished taking over from the failed primary
+    Switchover,
+    /// The standby server failed while serving in place of the primary
+    StandbyBreakdown,
+    StandbyRepair,
+    /// The station finished switching on and starts serving
+    Setup,
+    /// The admission schedule opens or closes the queue to new arrivals
+    Admission,
+    /// A scheduled change of the arrival rate, service rate or server count takes effect
+    ParameterChange,
+}
+
+impl std::fmt::Display for EventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            EventType::Arrival => "arrival",
+            EventType::Departure => "departure",
+            EventType::Breakdown => "breakdown",
+            EventType::RepairComplete => "repair",
+            EventType::Switchover => "switchover",
+            EventType::StandbyBreakdown => "standby breakdown",
+            EventType::StandbyRepair => "standby repair",
+            EventType::Setup => "setup",
+            EventType::Admission => "admission",
+            EventType::ParameterChange => "change",
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Event {
     pub time: f64,
     pub event_type: EventType,
+    /// Position in scheduling order, assigned by [`crate::engine::SimulationEngine::schedule`]
+    ///
+    /// Only advisory: it orders events kept outside the engine, such as a trace pushed
+    /// through a heap, by the `Ord` below. The engine itself breaks ties between events
+    /// due at the same instant by a fixed priority of their types (see
+    /// [`crate::engine::SimulationEngine::run_step`]) and never reads it.
+    pub seq: u64,
 }
 
 impl Event {
     #[inline]
     pub fn new(time: f64, event_type: EventType) -> Self {
-        Self { time, event_type }
+        Self {
+            time,
+            event_type,
+            seq: 0,
+        }
     }
 }
 
 impl PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
-        self.time == other.time
+        self.time == other.time && self.seq == other.seq
     }
 }
 
@@ -33,9 +74,56 @@ impl PartialOrd for Event {
 
 impl Ord for Event {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Reversed, so a max-heap pops the earliest event and, among events due
+        // at the same instant, the one scheduled first
         other
             .time
             .partial_cmp(&self.time)
             .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BinaryHeap;
+
+    #[test]
+    fn same_time_events_pop_in_insertion_order() {
+        let kinds = [
+            EventType::Departure,
+            EventType::Arrival,
+            EventType::Breakdown,
+            EventType::Setup,
+        ];
+        let mut heap = BinaryHeap::new();
+        for (seq, &event_type) in kinds.iter().enumerate() {
+            heap.push(Event {
+                seq: seq as u64,
+                ..Event::new(2.0, event_type)
+            });
+        }
+        heap.push(Event {
+            seq: 4,
+            ..Event::new(1.0, EventType::Admission)
+        });
+
+        assert_eq!(heap.pop().unwrap().event_type, EventType::Admission);
+        let popped: Vec<EventType> = std::iter::from_fn(|| heap.pop())
+            .map(|event| event.event_type)
+            .collect();
+        assert_eq!(popped, kinds);
+    }
+
+    #[test]
+    fn events_at_the_same_time_are_equal_only_with_the_same_seq() {
+        let first = Event::new(3.0, EventType::Arrival);
+        let second = Event {
+            seq: 1,
+            ..Event::new(3.0, EventType::Arrival)
+        };
+        assert_ne!(first, second);
+        assert!(first > second);
     }
 }
@@ -0,0 +1,178 @@
+//! The most recent events of a run, kept for debugging suspicious results
+
+use crate::event::EventType;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Events kept unless configured otherwise; about 400 KB
+pub const DEFAULT_EVENT_LOG_LENGTH: usize = 10_000;
+
+/// One processed event and the state it left behind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventLogEntry {
+    /// Position of the event in the run, starting at 1
+    pub index: u64,
+    pub time: f64,
+    pub event_type: EventType,
+    pub queue_length: usize,
+    pub customers_in_system: usize,
+}
+
+/// Fixed-size ring of the last `capacity` events
+///
+/// All memory is allocated in [`EventLog::new`]; recording overwrites the oldest
+/// entry once the ring is full, so leaving the log on costs one store per event.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    entries: Vec<EventLogEntry>,
+    capacity: usize,
+    /// Slot the next entry goes to once the ring is full
+    next: usize,
+}
+
+impl EventLog {
+    /// Log of the last `capacity` events; 0 records nothing
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, entry: EventLogEntry) {
+        if self.entries.len() < self.capacity {
+            self.entries.push(entry);
+        } else if self.capacity > 0 {
+            self.entries[self.next] = entry;
+            self.next += 1;
+            if self.next == self.capacity {
+                self.next = 0;
+            }
+        }
+    }
+
+    /// Entries from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &EventLogEntry> {
+        let (newest, oldest) = self.entries.split_at(self.next);
+        oldest.iter().chain(newest)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Forget every entry, keeping the allocation
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next = 0;
+    }
+
+    /// Write the entries, oldest first, see [`write_event_csv`]
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        write_event_csv(path, self.iter())
+    }
+}
+
+/// The event CSV format: `event,time,type,queue_length,customers_in_system`, one row per event
+pub fn write_event_csv<'a>(
+    path: &Path,
+    entries: impl IntoIterator<Item = &'a EventLogEntry>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "event,time,type,queue_length,customers_in_system")?;
+    for entry in entries {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            entry.index,
+            entry.time,
+            entry.event_type,
+            entry.queue_length,
+            entry.customers_in_system
+        )?;
+    }
+    out.flush()
+}
+
+/// Bounds beyond which a run is considered suspicious
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyLimits {
+    /// Running mean wait above this multiple of the closed-form mean wait; only
+    /// checked where a closed form exists (M/M/c without truncation, breakdowns,
+    /// setups or an admission schedule)
+    pub wait_multiple: Option<f64>,
+    /// Customers waiting in the queue above this number
+    pub max_queue_length: Option<usize>,
+}
+
+impl Default for AnomalyLimits {
+    fn default() -> Self {
+        Self {
+            wait_multiple: Some(10.0),
+            max_queue_length: None,
+        }
+    }
+}
+
+/// What made the run suspicious
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    /// An event was processed at an earlier time than the one before it
+    TimeWentBackwards {
+        previous: f64,
+        time: f64,
+    },
+    MeanWaitTooLong {
+        mean_wait: f64,
+        limit: f64,
+    },
+    QueueTooLong {
+        length: usize,
+        limit: usize,
+    },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TimeWentBackwards { previous, time } => {
+                write!(f, "time went backwards from {} to {}", previous, time)
+            }
+            Self::MeanWaitTooLong { mean_wait, limit } => write!(
+                f,
+                "mean wait {:.4} exceeds the limit of {:.4}",
+                mean_wait, limit
+            ),
+            Self::QueueTooLong { length, limit } => {
+                write!(f, "queue length {} exceeds the limit of {}", length, limit)
+            }
+        }
+    }
+}
+
+/// The first anomaly of a run together with the events that led up to it
+#[derive(Debug, Clone)]
+pub struct PostMortem {
+    pub anomaly: Anomaly,
+    pub time: f64,
+    /// Copy of the event log when the anomaly was detected, oldest first
+    pub events: Vec<EventLogEntry>,
+}
+
+impl PostMortem {
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        write_event_csv(path, &self.events)
+    }
+}
@@ -0,0 +1,115 @@
+//! The exact order of a run's first events, compared against a committed recording
+//!
+//! Aggregate statistics barely move when two events swap places or a customer
+//! is scheduled a step late, but the `(time, type)` sequence of a seeded run
+//! changes at once. The golden recording in `golden/event_order.csv` is the
+//! sequence of [`golden_config`], checked by `tests/golden_events.rs` and by
+//! `--verify`; regenerate it with
+//! `cargo run --example golden_events > golden/event_order.csv` after a change
+//! that is meant to reorder events, and review the diff.
+
+use crate::config::{
+    AdmissionSchedule, Breakdowns, ChangedParameter, ClosedArrivals, InterruptedService,
+    ParameterChange, ServiceKind, SetupTime, SimulationConfig,
+};
+use crate::event::Event;
+use crate::simulation::Simulation;
+use std::fmt::Write;
+
+/// Events in the golden recording
+pub const GOLDEN_EVENT_COUNT: usize = 400;
+
+/// The committed sequence of [`golden_config`], as written by [`to_csv`]
+pub const GOLDEN_EVENTS: &str = include_str!("../golden/event_order.csv");
+
+/// A seeded run that schedules most event types in its first few hundred events:
+/// setups, breakdowns and repairs, an admission break and a rate change; only
+/// the standby server's events are missing
+pub fn golden_config() -> SimulationConfig {
+    SimulationConfig {
+        lambda: 0.8,
+        mu: 1.0,
+        breakdowns: Some(Breakdowns {
+            mean_time_to_failure: 40.0,
+            mean_repair_time: 2.0,
+            interrupted_service: InterruptedService::Resume,
+        }),
+        setup: Some(SetupTime {
+            mean: 0.5,
+            kind: ServiceKind::Exponential,
+        }),
+        admission: Some(AdmissionSchedule {
+            open_periods: vec![(0.0, 100.0), (120.0, 1_000_000.0)],
+            closed_arrivals: ClosedArrivals::Lost,
+        }),
+        parameter_changes: vec![ParameterChange {
+            time: 150.0,
+            parameter: ChangedParameter::ArrivalRate,
+            value: 0.9,
+        }],
+        seed: Some(11),
+        ..Default::default()
+    }
+}
+
+/// The first `events` events of a run of `config`, in the order they were processed
+pub fn record_events(config: SimulationConfig, events: usize) -> Vec<Event> {
+    let mut simulation = Simulation::new(config);
+    let mut recorded = Vec::with_capacity(events);
+    while recorded.len() < events {
+        match simulation.step() {
+            Some(event) => recorded.push(event),
+            None => break,
+        }
+    }
+    recorded
+}
+
+/// One `time,type` line per event under a header; times print with every digit
+/// that tells two doubles apart
+pub fn to_csv(events: &[Event]) -> String {
+    let mut csv = String::from("time,type\n");
+    for event in events {
+        writeln!(csv, "{},{}", event.time, event.event_type).unwrap();
+    }
+    csv
+}
+
+/// First line at which a recording differs from the golden one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the event, starting at 1
+    pub index: usize,
+    /// The golden line, `None` if the golden recording ended first
+    pub expected: Option<String>,
+    /// The recorded line, `None` if the run ended first
+    pub actual: Option<String>,
+}
+
+/// Compare two recordings in [`to_csv`] form line by line
+pub fn compare(expected: &str, actual: &str) -> Result<(), Divergence> {
+    let mut expected = expected.lines().skip(1);
+    let mut actual = actual.lines().skip(1);
+    for index in 1.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return Ok(()),
+            (e, a) if e == a => {}
+            (e, a) => {
+                return Err(Divergence {
+                    index,
+                    expected: e.map(str::to_string),
+                    actual: a.map(str::to_string),
+                });
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Run [`golden_config`] and compare its events with [`GOLDEN_EVENTS`]
+pub fn check_golden() -> Result<(), Divergence> {
+    compare(
+        GOLDEN_EVENTS,
+        &to_csv(&record_events(golden_config(), GOLDEN_EVENT_COUNT)),
+    )
+}
@@ -0,0 +1,632 @@
+//! Two-level factorial experiments: which of λ, μ, the queue cap, the servers and
+//! the discipline move the mean wait, and which pairs of them act together
+//!
+//! Each of the k factors gets a low and a high level, coded -1 and +1, and all
+//! 2^k combinations are run. Cell i has factor j high where bit j of i is set,
+//! the standard order. Replication r of every cell runs on seed `seed + r`, so
+//! the cells of a replication see the same random numbers and each replication
+//! is a complete design of its own. The effects are estimated per replication and
+//! their spread across the replications gives the confidence intervals, like the
+//! paired differences of [`crate::deadlines`].
+//!
+//! The effect of a factor is the mean response at its high level minus the mean
+//! at its low level, (2 / 2^k)·Σ x_ij·y_i over the cells; an interaction of two
+//! factors uses the product of their coded levels instead. Center points, with
+//! every factor at its midpoint, show curvature the two levels cannot.
+//!
+//! With a cost model every run is also priced, and the cells can be ranked by
+//! their mean cost per time unit to pick the cheapest combination of levels.
+
+use crate::config::{QueueDiscipline, SimulationConfig};
+use crate::cost::CostModel;
+use crate::simulation::Simulation;
+use crate::statistics::{Moments, t_quantile_975};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// A parameter varied between two levels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Factor {
+    ArrivalRate {
+        low: f64,
+        high: f64,
+    },
+    ServiceRate {
+        low: f64,
+        high: f64,
+    },
+    /// Queue cap K, the customers that may wait
+    Capacity {
+        low: usize,
+        high: usize,
+    },
+    Servers {
+        low: usize,
+        high: usize,
+    },
+    Discipline {
+        low: QueueDiscipline,
+        high: QueueDiscipline,
+    },
+}
+
+impl Factor {
+    /// Name of the factor on the command line and in the results
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ArrivalRate { .. } => "lambda",
+            Self::ServiceRate { .. } => "mu",
+            Self::Capacity { .. } => "capacity",
+            Self::Servers { .. } => "servers",
+            Self::Discipline { .. } => "discipline",
+        }
+    }
+
+    /// Whether the factor has a midpoint for center points: not a discipline, and
+    /// a count only when its levels are an even distance apart
+    pub fn has_center(&self) -> bool {
+        match *self {
+            Self::ArrivalRate { .. } | Self::ServiceRate { .. } => true,
+            Self::Capacity { low, high } | Self::Servers { low, high } => (low + high) % 2 == 0,
+            Self::Discipline { .. } => false,
+        }
+    }
+
+    /// `config` with the factor at `coded` level: -1 low, 0 the midpoint, +1 high
+    pub fn apply(&self, config: SimulationConfig, coded: f64) -> SimulationConfig {
+        let mid = |low: f64, high: f64| low + (high - low) * (coded + 1.0) / 2.0;
+        let count = |low: usize, high: usize| mid(low as f64, high as f64).round() as usize;
+        match *self {
+            Self::ArrivalRate { low, high } => SimulationConfig {
+                lambda: mid(low, high),
+                ..config
+            },
+            Self::ServiceRate { low, high } => SimulationConfig {
+                mu: mid(low, high),
+                ..config
+            },
+            Self::Capacity { low, high } => SimulationConfig {
+                max_queue_length: Some(count(low, high)),
+                ..config
+            },
+            Self::Servers { low, high } => SimulationConfig {
+                servers: count(low, high),
+                ..config
+            },
+            Self::Discipline { low, high } => SimulationConfig {
+                discipline: if coded > 0.0 { high } else { low },
+                ..config
+            },
+        }
+    }
+
+    /// The factor's value at `coded` level, as shown in the results
+    pub fn level_label(&self, coded: f64) -> String {
+        let config = self.apply(SimulationConfig::default(), coded);
+        match self {
+            Self::ArrivalRate { .. } => config.lambda.to_string(),
+            Self::ServiceRate { .. } => config.mu.to_string(),
+            Self::Capacity { .. } => config.max_queue_length.unwrap_or(0).to_string(),
+            Self::Servers { .. } => config.servers.to_string(),
+            Self::Discipline { .. } => config.discipline.to_string(),
+        }
+    }
+}
+
+/// Coded level of factor `factor` in cell `cell` of the standard order
+pub fn coded_level(cell: usize, factor: usize) -> f64 {
+    if cell >> factor & 1 == 1 { 1.0 } else { -1.0 }
+}
+
+/// A main effect or a two-factor interaction, by factor index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Main(usize),
+    Interaction(usize, usize),
+}
+
+impl Term {
+    /// Every main effect of `factors` factors, then every two-factor interaction
+    pub fn all(factors: usize) -> Vec<Self> {
+        let interactions =
+            (0..factors).flat_map(|i| (i + 1..factors).map(move |j| Self::Interaction(i, j)));
+        (0..factors).map(Self::Main).chain(interactions).collect()
+    }
+
+    /// Column of the term in the design matrix at `cell`, ±1
+    pub fn sign(self, cell: usize) -> f64 {
+        match self {
+            Self::Main(i) => coded_level(cell, i),
+            Self::Interaction(i, j) => coded_level(cell, i) * coded_level(cell, j),
+        }
+    }
+
+    /// The term as `a` or `a × b` with the names of `factors`
+    pub fn label(self, factors: &[Factor]) -> String {
+        match self {
+            Self::Main(i) => factors[i].name().to_string(),
+            Self::Interaction(i, j) => format!("{} × {}", factors[i].name(), factors[j].name()),
+        }
+    }
+}
+
+/// Estimate of `term` from one response per cell of a 2^k design in standard order
+pub fn effect(term: Term, responses: &[f64]) -> f64 {
+    let contrast: f64 = responses
+        .iter()
+        .enumerate()
+        .map(|(cell, y)| term.sign(cell) * y)
+        .sum();
+    2.0 * contrast / responses.len() as f64
+}
+
+/// One effect averaged over the replications
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectEstimate {
+    pub term: Term,
+    pub estimate: f64,
+    /// 95% confidence half-width of `estimate`, NaN for one replication
+    pub half_width: f64,
+}
+
+impl EffectEstimate {
+    /// Whether the confidence interval excludes zero
+    pub fn is_significant(&self) -> bool {
+        self.estimate.abs() > self.half_width
+    }
+}
+
+/// Mean response of the design against that of its center points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curvature {
+    pub factorial_mean: f64,
+    pub center_mean: f64,
+    /// 95% confidence half-width of `center_mean - factorial_mean`, NaN with fewer
+    /// than two replications or center points
+    pub half_width: f64,
+}
+
+impl Curvature {
+    pub fn difference(&self) -> f64 {
+        self.center_mean - self.factorial_mean
+    }
+
+    /// Whether the interval excludes zero, i.e. the response is not linear between the levels
+    pub fn is_significant(&self) -> bool {
+        self.difference().abs() > self.half_width
+    }
+}
+
+/// One simulation of the experiment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExperimentRun {
+    /// Cell in standard order, `None` for a center point
+    pub cell: Option<usize>,
+    pub replication: usize,
+    pub seed: u64,
+    pub mean_wait: f64,
+    /// Cost per time unit, `None` without a cost model
+    pub cost: Option<f64>,
+}
+
+/// Where [`run_experiment`] is, reported before each cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Cell `index` (from 1) of `cells` is starting
+    Cell { index: usize, cells: usize },
+    /// The center points, this many of them, are starting
+    CenterPoints(usize),
+}
+
+/// Runs and estimates of a factorial experiment
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub factors: Vec<Factor>,
+    /// Cell by cell, the replications of each together, then the center points
+    pub runs: Vec<ExperimentRun>,
+    /// In the order of [`Term::all`]
+    pub effects: Vec<EffectEstimate>,
+    /// `None` without center points
+    pub curvature: Option<Curvature>,
+}
+
+impl Experiment {
+    /// Number of cells, 2^k
+    pub fn cells(&self) -> usize {
+        1 << self.factors.len()
+    }
+
+    /// Mean cost per time unit of each cell and of the center points (`None`),
+    /// cheapest first; empty if the runs were not priced
+    pub fn cells_by_cost(&self) -> Vec<(Option<usize>, f64)> {
+        let mut costs: Vec<(Option<usize>, Moments)> = Vec::new();
+        for run in &self.runs {
+            let Some(cost) = run.cost else {
+                continue;
+            };
+            match costs.iter_mut().find(|(cell, _)| *cell == run.cell) {
+                Some((_, moments)) => moments.push(cost),
+                None => {
+                    let mut moments = Moments::default();
+                    moments.push(cost);
+                    costs.push((run.cell, moments));
+                }
+            }
+        }
+        let mut ranked: Vec<(Option<usize>, f64)> = costs
+            .into_iter()
+            .map(|(cell, moments)| (cell, moments.mean().unwrap_or(f64::NAN)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked
+    }
+
+    /// Write every run with its factor levels to a CSV file, with its cost if priced
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let names: Vec<&str> = self.factors.iter().map(Factor::name).collect();
+        let priced = self.runs.iter().any(|run| run.cost.is_some());
+        writeln!(
+            out,
+            "cell,replication,seed,{},mean_wait{}",
+            names.join(","),
+            if priced { ",cost_per_time_unit" } else { "" }
+        )?;
+        for run in &self.runs {
+            let levels: Vec<String> = (0..self.factors.len())
+                .map(|j| {
+                    let coded = run.cell.map_or(0.0, |cell| coded_level(cell, j));
+                    self.factors[j].level_label(coded)
+                })
+                .collect();
+            let cell = run
+                .cell
+                .map_or("center".to_string(), |cell| (cell + 1).to_string());
+            write!(
+                out,
+                "{},{},{},{},{}",
+                cell,
+                run.replication + 1,
+                run.seed,
+                levels.join(","),
+                run.mean_wait
+            )?;
+            if let Some(cost) = run.cost {
+                write!(out, ",{}", cost)?;
+            }
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+}
+
+/// Run the 2^k design of `factors` around `config` with `replications` runs per
+/// cell and `center_points` runs at the center, and estimate the effects on the mean wait
+///
+/// `run` advances a fresh simulation to the end of a run, e.g. until a number of
+/// customers have been served. Replication r of every cell, and center point r,
+/// uses seed `seed + r`. Every cell is checked before the first is run, so a bad
+/// combination of levels fails at once. Each run is priced with `cost_model`
+/// if it sets any rate.
+#[allow(clippy::too_many_arguments)]
+pub fn run_experiment<F, P>(
+    config: &SimulationConfig,
+    factors: &[Factor],
+    replications: usize,
+    center_points: usize,
+    seed: u64,
+    cost_model: &CostModel,
+    mut run: F,
+    mut progress: P,
+) -> Result<Experiment, String>
+where
+    F: FnMut(&mut Simulation),
+    P: FnMut(Progress),
+{
+    if factors.is_empty() {
+        return Err("an experiment needs at least one factor".to_string());
+    }
+    if let Some((i, factor)) = factors
+        .iter()
+        .enumerate()
+        .find(|(i, factor)| factors[..*i].iter().any(|f| f.name() == factor.name()))
+    {
+        return Err(format!(
+            "factor {} is given twice (as factor {})",
+            factor.name(),
+            i + 1
+        ));
+    }
+    if replications == 0 {
+        return Err("an experiment needs at least one replication per cell".to_string());
+    }
+    if center_points > 0
+        && let Some(factor) = factors.iter().find(|factor| !factor.has_center())
+    {
+        return Err(format!(
+            "factor {} has no midpoint for center points",
+            factor.name()
+        ));
+    }
+
+    let cells = 1 << factors.len();
+    let at = |coded: &dyn Fn(usize) -> f64| {
+        factors
+            .iter()
+            .enumerate()
+            .fold(config.clone(), |config, (j, factor)| {
+                factor.apply(config, coded(j))
+            })
+    };
+    let cell_configs: Vec<SimulationConfig> = (0..cells)
+        .map(|cell| at(&|j| coded_level(cell, j)))
+        .collect();
+    let center_config = at(&|_| 0.0);
+    for (cell, cell_config) in cell_configs.iter().enumerate() {
+        cell_config.validate().map_err(|e| {
+            let levels: Vec<String> = factors
+                .iter()
+                .enumerate()
+                .map(|(j, f)| format!("{}={}", f.name(), f.level_label(coded_level(cell, j))))
+                .collect();
+            format!("cell {} ({}): {}", cell + 1, levels.join(", "), e)
+        })?;
+    }
+    if center_points > 0 {
+        center_config
+            .validate()
+            .map_err(|e| format!("center point: {}", e))?;
+    }
+
+    let with_seed = |base: &SimulationConfig, r: usize| SimulationConfig {
+        seed: Some(seed.wrapping_add(r as u64)),
+        ..base.clone()
+    };
+    // One simulation reset for every run, so its buffers are only allocated once
+    let mut simulation = Simulation::new(with_seed(&cell_configs[0], 0));
+    let mut first = true;
+    let mut simulate = |base: &SimulationConfig, r: usize| {
+        if !first {
+            simulation.reset(with_seed(base, r));
+        }
+        first = false;
+        run(&mut simulation);
+        let stats = simulation.statistics();
+        (
+            stats.average_wait_time(),
+            cost_model.rate(stats, simulation.now()),
+        )
+    };
+
+    let mut runs = Vec::with_capacity(cells * replications + center_points);
+    // responses[r][cell]: one complete design per replication
+    let mut responses = vec![vec![0.0; cells]; replications];
+    for (cell, cell_config) in cell_configs.iter().enumerate() {
+        progress(Progress::Cell {
+            index: cell + 1,
+            cells,
+        });
+        for (r, replication) in responses.iter_mut().enumerate() {
+            let (mean_wait, cost) = simulate(cell_config, r);
+            replication[cell] = mean_wait;
+            runs.push(ExperimentRun {
+                cell: Some(cell),
+                replication: r,
+                seed: seed.wrapping_add(r as u64),
+                mean_wait,
+                cost,
+            });
+        }
+    }
+    let mut center = Moments::default();
+    if center_points > 0 {
+        progress(Progress::CenterPoints(center_points));
+        for r in 0..center_points {
+            let (mean_wait, cost) = simulate(&center_config, r);
+            center.push(mean_wait);
+            runs.push(ExperimentRun {
+                cell: None,
+                replication: r,
+                seed: seed.wrapping_add(r as u64),
+                mean_wait,
+                cost,
+            });
+        }
+    }
+
+    let n = replications as f64;
+    let effects = Term::all(factors.len())
+        .into_iter()
+        .map(|term| {
+            let mut estimates = Moments::default();
+            for replication in &responses {
+                estimates.push(effect(term, replication));
+            }
+            EffectEstimate {
+                term,
+                estimate: estimates.mean().unwrap_or(0.0),
+                half_width: estimates.variance().map_or(f64::NAN, |variance| {
+                    t_quantile_975(n - 1.0) * (variance / n).sqrt()
+                }),
+            }
+        })
+        .collect();
+
+    let curvature = center.mean().map(|center_mean| {
+        let mut factorial = Moments::default();
+        for replication in &responses {
+            factorial.push(replication.iter().sum::<f64>() / cells as f64);
+        }
+        // Welch's standard error with the smaller sample's degrees of freedom, on the safe side
+        let df = replications.min(center_points) as f64 - 1.0;
+        let half_width = match (factorial.variance(), center.variance()) {
+            (Some(vf), Some(vc)) if df >= 1.0 => {
+                t_quantile_975(df) * (vf / n + vc / center_points as f64).sqrt()
+            }
+            _ => f64::NAN,
+        };
+        Curvature {
+            factorial_mean: factorial.mean().unwrap_or(0.0),
+            center_mean,
+            half_width,
+        }
+    });
+
+    Ok(Experiment {
+        factors: factors.to_vec(),
+        runs,
+        effects,
+        curvature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::StopCondition;
+
+    /// y = 10 + 3a - 2b + 0.5c + 1.5ab at the coded levels of every cell of a 2^3 design
+    fn synthetic_responses() -> Vec<f64> {
+        (0..8)
+            .map(|cell| {
+                let (a, b, c) = (
+                    coded_level(cell, 0),
+                    coded_level(cell, 1),
+                    coded_level(cell, 2),
+                );
+                10.0 + 3.0 * a - 2.0 * b + 0.5 * c + 1.5 * a * b
+            })
+            .collect()
+    }
+
+    fn experiment(
+        factors: &[Factor],
+        replications: usize,
+        center_points: usize,
+    ) -> Result<Experiment, String> {
+        let config = SimulationConfig::builder()
+            .lambda(1.0)
+            .mu(2.0)
+            .build()
+            .expect("the configuration is valid");
+        run_experiment(
+            &config,
+            factors,
+            replications,
+            center_points,
+            11,
+            &CostModel::default(),
+            |simulation| {
+                simulation.run(&StopCondition::Customers(2000), &mut ());
+            },
+            |_| {},
+        )
+    }
+
+    #[test]
+    fn effects_are_twice_the_coefficients_of_the_surface() {
+        let responses = synthetic_responses();
+        let effects: Vec<f64> = Term::all(3)
+            .into_iter()
+            .map(|term| effect(term, &responses))
+            .collect();
+        assert_eq!(effects, [6.0, -4.0, 1.0, 3.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn terms_come_in_standard_order() {
+        assert_eq!(
+            Term::all(3),
+            [
+                Term::Main(0),
+                Term::Main(1),
+                Term::Main(2),
+                Term::Interaction(0, 1),
+                Term::Interaction(0, 2),
+                Term::Interaction(1, 2),
+            ]
+        );
+        let levels: Vec<f64> = (0..4).map(|cell| coded_level(cell, 1)).collect();
+        assert_eq!(levels, [-1.0, -1.0, 1.0, 1.0]);
+        assert_eq!(Term::Interaction(0, 1).sign(1), -1.0);
+        assert_eq!(Term::Interaction(0, 1).sign(3), 1.0);
+    }
+
+    #[test]
+    fn levels_and_midpoints() {
+        let lambda = Factor::ArrivalRate {
+            low: 0.5,
+            high: 0.9,
+        };
+        assert_eq!(lambda.level_label(-1.0), "0.5");
+        assert_eq!(lambda.level_label(1.0), "0.9");
+        assert!((lambda.apply(SimulationConfig::default(), 0.0).lambda - 0.7).abs() < 1e-12);
+        assert!(Factor::Servers { low: 1, high: 3 }.has_center());
+        assert!(!Factor::Capacity { low: 2, high: 5 }.has_center());
+        let discipline = Factor::Discipline {
+            low: QueueDiscipline::Fifo,
+            high: QueueDiscipline::RoundRobin,
+        };
+        assert!(!discipline.has_center());
+        assert_eq!(discipline.level_label(1.0), "RR");
+    }
+
+    #[test]
+    fn a_faster_server_shortens_the_wait() {
+        let factors = [
+            Factor::ServiceRate {
+                low: 1.25,
+                high: 2.5,
+            },
+            Factor::Servers { low: 1, high: 3 },
+        ];
+        let experiment = experiment(&factors, 3, 2).expect("the design is valid");
+        assert_eq!(experiment.cells(), 4);
+        assert_eq!(experiment.runs.len(), 4 * 3 + 2);
+        assert_eq!(experiment.effects.len(), 3);
+        // Cell by cell with their replications, then the center points
+        assert_eq!(experiment.runs[4].cell, Some(1));
+        assert_eq!(experiment.runs[4].seed, 12);
+        assert_eq!(experiment.runs[12].cell, None);
+        for effect in &experiment.effects[..2] {
+            assert!(effect.estimate < 0.0, "{:?}", effect);
+        }
+        assert!(experiment.curvature.is_some());
+    }
+
+    #[test]
+    fn bad_designs_are_refused_before_running() {
+        let mu = Factor::ServiceRate {
+            low: 1.25,
+            high: 2.5,
+        };
+        let discipline = Factor::Discipline {
+            low: QueueDiscipline::Fifo,
+            high: QueueDiscipline::RoundRobin,
+        };
+        for (factors, replications, center_points, error) in [
+            (&[][..], 1, 0, "at least one factor"),
+            (&[mu, mu][..], 1, 0, "factor mu is given twice"),
+            (&[mu][..], 0, 0, "at least one replication"),
+            (
+                &[mu, discipline][..],
+                1,
+                1,
+                "factor discipline has no midpoint",
+            ),
+            (
+                &[Factor::ServiceRate {
+                    low: -1.0,
+                    high: 2.0,
+                }][..],
+                1,
+                0,
+                "cell 1 (mu=-1)",
+            ),
+        ] {
+            let message = experiment(factors, replications, center_points).unwrap_err();
+            assert!(message.contains(error), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! Fit parametric distributions to observed durations, such as measured service times
+//!
+//! Every family is fitted by maximum likelihood, except the Erlang, whose whole
+//! number of phases is matched to the squared coefficient of variation. Fits are
+//! ranked by their Kolmogorov-Smirnov distance to the empirical distribution. A
+//! fit is a [`ServiceKind`] with a mean, so it plugs straight into the config.
+
+use crate::config::ServiceKind;
+use crate::distributions::Distribution;
+use std::f64::consts::PI;
+
+/// Newton steps for the gamma shape, which converges in a handful from Minka's start
+const GAMMA_NEWTON_STEPS: usize = 50;
+
+/// Iterations of the incomplete gamma series and continued fraction
+const INCOMPLETE_GAMMA_ITERATIONS: usize = 500;
+
+/// Moments and range of a sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleSummary {
+    pub count: usize,
+    pub mean: f64,
+    /// Unbiased sample variance
+    pub variance: f64,
+    /// Squared coefficient of variation, variance / mean²
+    pub scv: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Summarize `values`, which must be at least two positive durations that are not all equal
+pub fn summarize(values: &[f64]) -> Result<SampleSummary, String> {
+    if values.len() < 2 {
+        return Err(format!("need at least 2 values, got {}", values.len()));
+    }
+    if let Some(bad) = values.iter().find(|x| !(x.is_finite() && **x > 0.0)) {
+        return Err(format!("values must be positive and finite, got {}", bad));
+    }
+    let count = values.len();
+    let mean = values.iter().sum::<f64>() / count as f64;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+    if variance == 0.0 {
+        return Err(format!(
+            "all values are {}, which is deterministic service",
+            mean
+        ));
+    }
+    Ok(SampleSummary {
+        count,
+        mean,
+        variance,
+        scv: variance / (mean * mean),
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(0.0, f64::max),
+    })
+}
+
+/// A fitted distribution and how well it describes the sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    pub kind: ServiceKind,
+    /// Mean of the fitted distribution
+    pub mean: f64,
+    pub log_likelihood: f64,
+    /// Largest distance between the fitted and the empirical CDF
+    pub ks_statistic: f64,
+}
+
+impl Fit {
+    fn new(kind: ServiceKind, mean: f64, values: &[f64]) -> Self {
+        Self {
+            kind,
+            mean,
+            log_likelihood: values.iter().map(|&x| log_density(kind, mean, x)).sum(),
+            ks_statistic: ks_statistic(kind, mean, values),
+        }
+    }
+
+    pub fn distribution(&self) -> Box<dyn Distribution> {
+        self.kind.distribution(self.mean)
+    }
+}
+
+/// Exponential with the sample mean
+pub fn fit_exponential(values: &[f64]) -> Result<Fit, String> {
+    let summary = summarize(values)?;
+    Ok(Fit::new(ServiceKind::Exponential, summary.mean, values))
+}
+
+/// Erlang with the sample mean and the phase count nearest 1/SCV, at least one
+pub fn fit_erlang(values: &[f64]) -> Result<Fit, String> {
+    let summary = summarize(values)?;
+    let phases = (1.0 / summary.scv).round().max(1.0) as u32;
+    Ok(Fit::new(ServiceKind::Erlang(phases), summary.mean, values))
+}
+
+/// Gamma by maximum likelihood, solving ln α - ψ(α) = ln x̄ - mean(ln x) by Newton's method
+pub fn fit_gamma(values: &[f64]) -> Result<Fit, String> {
+    let summary = summarize(values)?;
+    let mean_log = values.iter().map(|x| x.ln()).sum::<f64>() / values.len() as f64;
+    let s = summary.mean.ln() - mean_log;
+    // Minka's closed-form approximation, within about 1.5% of the root
+    let mut shape = (3.0 - s + ((s - 3.0).powi(2) + 24.0 * s).sqrt()) / (12.0 * s);
+    for _ in 0..GAMMA_NEWTON_STEPS {
+        let step = (shape.ln() - digamma(shape) - s) / (1.0 / shape - trigamma(shape));
+        // Halving keeps the shape positive when a step overshoots
+        let next = if shape - step > 0.0 {
+            shape - step
+        } else {
+            shape / 2.0
+        };
+        let converged = (next - shape).abs() <= 1e-12 * shape;
+        shape = next;
+        if converged {
+            break;
+        }
+    }
+    // The scale is x̄/α, so the fitted mean is the sample mean
+    Ok(Fit::new(ServiceKind::Gamma(shape), summary.mean, values))
+}
+
+/// Lognormal by maximum likelihood: the mean and standard deviation of ln x
+pub fn fit_lognormal(values: &[f64]) -> Result<Fit, String> {
+    summarize(values)?;
+    let n = values.len() as f64;
+    let mu = values.iter().map(|x| x.ln()).sum::<f64>() / n;
+    let sigma = (values.iter().map(|x| (x.ln() - mu).powi(2)).sum::<f64>() / n).sqrt();
+    if sigma == 0.0 {
+        return Err("the logarithms of the values do not vary".to_string());
+    }
+    let mean = (mu + 0.5 * sigma * sigma).exp();
+    Ok(Fit::new(ServiceKind::Lognormal(sigma), mean, values))
+}
+
+/// Every family fitted to `values`, the best by Kolmogorov-Smirnov distance first
+///
+/// The Erlang is left out when it would have a single phase.
+pub fn fit_all(values: &[f64]) -> Result<Vec<Fit>, String> {
+    let mut fits = vec![fit_exponential(values)?];
+    // An Erlang of one phase is the exponential again
+    let erlang = fit_erlang(values)?;
+    if erlang.kind != ServiceKind::Erlang(1) {
+        fits.push(erlang);
+    }
+    fits.push(fit_gamma(values)?);
+    fits.push(fit_lognormal(values)?);
+    fits.sort_by(|a, b| a.ks_statistic.total_cmp(&b.ks_statistic));
+    Ok(fits)
+}
+
+/// ln f(x) of the distribution of shape `kind` and mean `mean`
+fn log_density(kind: ServiceKind, mean: f64, x: f64) -> f64 {
+    let gamma = |shape: f64| {
+        let rate = shape / mean;
+        shape * rate.ln() + (shape - 1.0) * x.ln() - rate * x - ln_gamma(shape)
+    };
+    match kind {
+        ServiceKind::Exponential => -mean.ln() - x / mean,
+        ServiceKind::Deterministic => {
+            if x == mean {
+                0.0
+            } else {
+                f64::NEG_INFINITY
+            }
+        }
+        ServiceKind::Erlang(phases) => gamma(phases as f64),
+        ServiceKind::Gamma(shape) => gamma(shape),
+        ServiceKind::Lognormal(sigma) => {
+            let mu = mean.ln() - 0.5 * sigma * sigma;
+            let z = (x.ln() - mu) / sigma;
+            -x.ln() - sigma.ln() - 0.5 * (2.0 * PI).ln() - 0.5 * z * z
+        }
+    }
+}
+
+/// F(x) of the distribution of shape `kind` and mean `mean`
+fn cdf(kind: ServiceKind, mean: f64, x: f64) -> f64 {
+    match kind {
+        ServiceKind::Exponential => -(-x / mean).exp_m1(),
+        ServiceKind::Deterministic => {
+            if x >= mean {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        ServiceKind::Erlang(phases) => {
+            lower_incomplete_gamma(phases as f64, phases as f64 * x / mean)
+        }
+        ServiceKind::Gamma(shape) => lower_incomplete_gamma(shape, shape * x / mean),
+        ServiceKind::Lognormal(sigma) => {
+            let mu = mean.ln() - 0.5 * sigma * sigma;
+            standard_normal_cdf((x.ln() - mu) / sigma)
+        }
+    }
+}
+
+/// Largest gap between the fitted CDF and the empirical one, on either side of each step
+fn ks_statistic(kind: ServiceKind, mean: f64, values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len() as f64;
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let fitted = cdf(kind, mean, x);
+            ((i + 1) as f64 / n - fitted).max(fitted - i as f64 / n)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// ln Γ(x) for x > 0 by the Lanczos approximation (g = 7, 9 terms)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection, Γ(x)Γ(1 - x) = π / sin(πx)
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + (i + 1) as f64)
+        });
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// ψ(x), the derivative of ln Γ, by recurrence up to 6 and its asymptotic series
+fn digamma(mut x: f64) -> f64 {
+    let mut result = 0.0;
+    while x < 6.0 {
+        result -= 1.0 / x;
+        x += 1.0;
+    }
+    let inv2 = 1.0 / (x * x);
+    result + x.ln() - 0.5 / x - inv2 * (1.0 / 12.0 - inv2 * (1.0 / 120.0 - inv2 / 252.0))
+}
+
+/// ψ'(x), by recurrence up to 6 and its asymptotic series
+fn trigamma(mut x: f64) -> f64 {
+    let mut result = 0.0;
+    while x < 6.0 {
+        result += 1.0 / (x * x);
+        x += 1.0;
+    }
+    let inv = 1.0 / x;
+    let inv2 = inv * inv;
+    result + inv + 0.5 * inv2 + inv * inv2 * (1.0 / 6.0 - inv2 * (1.0 / 30.0 - inv2 / 42.0))
+}
+
+/// Regularized lower incomplete gamma P(a, x): the series below a + 1, the continued fraction above
+fn lower_incomplete_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..INCOMPLETE_GAMMA_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * f64::EPSILON {
+                break;
+            }
+        }
+        (sum * log_prefactor.exp()).min(1.0)
+    } else {
+        // Lentz's method for the continued fraction of Q(a, x)
+        let tiny = f64::MIN_POSITIVE / f64::EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..INCOMPLETE_GAMMA_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < f64::EPSILON {
+                break;
+            }
+        }
+        (1.0 - log_prefactor.exp() * h).max(0.0)
+    }
+}
+
+/// Φ(z), through erf(y) = P(1/2, y²) for y ≥ 0
+fn standard_normal_cdf(z: f64) -> f64 {
+    let erf = lower_incomplete_gamma(0.5, 0.5 * z * z);
+    if z >= 0.0 {
+        0.5 + 0.5 * erf
+    } else {
+        0.5 - 0.5 * erf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` draws of the distribution of shape `kind` and mean `mean`, from a fixed seed
+    fn sample(kind: ServiceKind, mean: f64, n: usize) -> Vec<f64> {
+        let mut distribution = kind.distribution(mean);
+        let mut rng = fastrand::Rng::with_seed(9);
+        (0..n).map(|_| distribution.sample(&mut rng)).collect()
+    }
+
+    fn relative_error(measured: f64, expected: f64) -> f64 {
+        (measured / expected - 1.0).abs()
+    }
+
+    #[test]
+    fn special_functions_match_tabulated_values() {
+        // Γ(5) = 24, Γ(1/2) = √π
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - PI.sqrt().ln()).abs() < 1e-12);
+        // ψ(1) = -γ, ψ'(1) = π²/6, to the few parts in a billion of the asymptotic series
+        assert!((digamma(1.0) + 0.577_215_664_901_532_9).abs() < 1e-8);
+        assert!((trigamma(1.0) - PI * PI / 6.0).abs() < 1e-8);
+        // P(1, x) = 1 - e^-x on both sides of a + 1, Φ(1.96) ≈ 0.975
+        for x in [0.5, 1.5, 10.0] {
+            assert!((lower_incomplete_gamma(1.0, x) + (-x).exp_m1()).abs() < 1e-12);
+        }
+        assert!((standard_normal_cdf(1.96) - 0.975_002_104_851_780).abs() < 1e-9);
+        assert!((standard_normal_cdf(-1.0) - 0.158_655_253_931_457).abs() < 1e-9);
+    }
+
+    #[test]
+    fn each_fit_recovers_the_parameters_it_was_drawn_with() {
+        let exponential = fit_exponential(&sample(ServiceKind::Exponential, 2.0, 20_000)).unwrap();
+        assert!(relative_error(exponential.mean, 2.0) < 0.02);
+
+        let erlang = fit_erlang(&sample(ServiceKind::Erlang(3), 1.5, 20_000)).unwrap();
+        assert_eq!(erlang.kind, ServiceKind::Erlang(3));
+        assert!(relative_error(erlang.mean, 1.5) < 0.02);
+
+        for shape in [0.4, 2.5] {
+            let gamma = fit_gamma(&sample(ServiceKind::Gamma(shape), 1.0, 20_000)).unwrap();
+            let ServiceKind::Gamma(fitted) = gamma.kind else {
+                panic!("a gamma fit of another kind: {:?}", gamma.kind);
+            };
+            assert!(
+                relative_error(fitted, shape) < 0.03,
+                "shape {}: {}",
+                shape,
+                fitted
+            );
+        }
+
+        let lognormal = fit_lognormal(&sample(ServiceKind::Lognormal(0.8), 3.0, 20_000)).unwrap();
+        let ServiceKind::Lognormal(sigma) = lognormal.kind else {
+            panic!("a lognormal fit of another kind: {:?}", lognormal.kind);
+        };
+        assert!(relative_error(sigma, 0.8) < 0.02);
+        assert!(relative_error(lognormal.mean, 3.0) < 0.03);
+    }
+
+    #[test]
+    fn the_family_drawn_from_ranks_first() {
+        for kind in [
+            ServiceKind::Exponential,
+            ServiceKind::Gamma(0.4),
+            ServiceKind::Lognormal(1.2),
+        ] {
+            let fits = fit_all(&sample(kind, 1.0, 5_000)).unwrap();
+            // The gamma contains the exponential, so either may win on exponential data
+            let best = fits[0].kind;
+            let matches = match kind {
+                ServiceKind::Exponential => {
+                    matches!(best, ServiceKind::Exponential | ServiceKind::Gamma(_))
+                }
+                _ => std::mem::discriminant(&best) == std::mem::discriminant(&kind),
+            };
+            assert!(matches, "{:?} data fitted best by {:?}", kind, best);
+            assert!(fits[0].ks_statistic < 0.02, "{:?}", fits[0]);
+            assert!(
+                fits.windows(2)
+                    .all(|w| w[0].ks_statistic <= w[1].ks_statistic)
+            );
+        }
+    }
+
+    #[test]
+    fn unusable_samples_are_refused() {
+        assert!(summarize(&[1.0]).is_err());
+        assert!(summarize(&[1.0, 0.0, 2.0]).is_err());
+        assert!(summarize(&[1.0, f64::NAN]).is_err());
+        assert!(
+            summarize(&[2.0, 2.0, 2.0])
+                .unwrap_err()
+                .contains("deterministic")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Gantt chart of the first customers of a run: one bar per customer from
+//! arrival to departure, light while waiting and dark while in service
+
+use crate::trace::CustomerRecord;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::io;
+use std::path::Path;
+
+/// Customers drawn when `--gantt-customers` is not given
+pub const DEFAULT_GANTT_CUSTOMERS: usize = 50;
+
+/// Most customers a chart can hold; beyond this the bars are too thin to read
+pub const MAX_GANTT_CUSTOMERS: usize = 2_000;
+
+const WIDTH: u32 = 1200;
+
+/// Height of one customer's row in pixels
+const ROW_HEIGHT: u32 = 16;
+
+/// Height of the caption and the time axis together
+const MARGIN_HEIGHT: u32 = 110;
+
+/// Space left between neighbouring bars, as a share of a row
+const BAR_GAP: f64 = 0.15;
+
+const WAIT_COLOR: RGBColor = RGBColor(158, 202, 225);
+const SERVICE_COLOR: RGBColor = RGBColor(33, 102, 172);
+
+/// Draw the `customers` earliest arrivals among `records` to `path`, as a PNG
+/// if its extension is `png` and as an SVG otherwise; returns the bars drawn
+///
+/// Bars are ordered by arrival, the earliest at the top.
+pub fn write_gantt(path: &Path, records: &[CustomerRecord], customers: usize) -> io::Result<usize> {
+    let mut bars = records.to_vec();
+    bars.sort_by(|a, b| a.arrival.total_cmp(&b.arrival));
+    bars.truncate(customers);
+
+    let height = MARGIN_HEIGHT + ROW_HEIGHT * bars.len().max(1) as u32;
+    let png = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    if png {
+        draw(
+            BitMapBackend::new(path, (WIDTH, height)).into_drawing_area(),
+            &bars,
+        )?;
+    } else {
+        draw(
+            SVGBackend::new(path, (WIDTH, height)).into_drawing_area(),
+            &bars,
+        )?;
+    }
+    Ok(bars.len())
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    bars: &[CustomerRecord],
+) -> io::Result<()> {
+    let to_io = |e: DrawingAreaErrorKind<DB::ErrorType>| io::Error::other(e.to_string());
+    let rows = bars.len();
+    let start = bars.first().map_or(0.0, |record| record.arrival);
+    let end = bars
+        .iter()
+        .map(|record| record.departure)
+        .fold(start, f64::max);
+    let end = if end > start { end } else { start + 1.0 };
+    // Row i spans [rows - i - 1, rows - i], so the first arrival is at the top
+    let row_top = |i: usize| (rows - i) as f64 - BAR_GAP;
+    let row_bottom = |i: usize| (rows - i - 1) as f64 + BAR_GAP;
+
+    root.fill(&WHITE).map_err(to_io)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            format!("Waiting and service of the first {} customers", rows),
+            ("sans-serif", 20),
+        )
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(start..end, 0.0..rows.max(1) as f64)
+        .map_err(to_io)?;
+    chart
+        .configure_mesh()
+        .disable_y_mesh()
+        .y_labels(rows.min(25))
+        .y_label_formatter(&|y| {
+            let row = rows.saturating_sub(y.ceil() as usize);
+            bars.get(row)
+                .map_or_else(String::new, |record| record.id.to_string())
+        })
+        .x_desc("Time")
+        .y_desc("Customer")
+        .draw()
+        .map_err(to_io)?;
+
+    chart
+        .draw_series(bars.iter().enumerate().map(|(i, record)| {
+            Rectangle::new(
+                [
+                    (record.arrival, row_bottom(i)),
+                    (record.service_start, row_top(i)),
+                ],
+                WAIT_COLOR.filled(),
+            )
+        }))
+        .map_err(to_io)?
+        .label("Waiting")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 15, y + 5)], WAIT_COLOR.filled()));
+    chart
+        .draw_series(bars.iter().enumerate().map(|(i, record)| {
+            Rectangle::new(
+                [
+                    (record.service_start, row_bottom(i)),
+                    (record.departure, row_top(i)),
+                ],
+                SERVICE_COLOR.filled(),
+            )
+        }))
+        .map_err(to_io)?
+        .label("In service")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 15, y + 5)], SERVICE_COLOR.filled()));
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(to_io)?;
+    root.present().map_err(to_io)
+}
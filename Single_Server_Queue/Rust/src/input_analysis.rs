@@ -0,0 +1,95 @@
+use rust_single_server_queue::config::ServiceKind;
+use rust_single_server_queue::fitting::{Fit, fit_all, summarize};
+use std::path::Path;
+
+/// Summarize the durations in `path`, fit every family and print the config of the best fit
+pub fn run_input_analysis(path: &Path) -> Result<(), String> {
+    let values = read_durations(path)?;
+    let summary = summarize(&values)?;
+    let fits = fit_all(&values)?;
+
+    println!("=== Input Analysis ===");
+    println!("Values: {}", summary.count);
+    println!("Mean: {:.4}", summary.mean);
+    println!("Variance: {:.4}", summary.variance);
+    println!("Squared coefficient of variation: {:.4}", summary.scv);
+    println!("Range: {:.4} to {:.4}", summary.min, summary.max);
+
+    println!();
+    println!("=== Fits (best first) ===");
+    println!(
+        "{:<38} {:>16} {:>8}",
+        "distribution", "log-likelihood", "KS"
+    );
+    for fit in &fits {
+        println!(
+            "{:<38} {:>16.2} {:>8.4}",
+            describe_fit(fit),
+            fit.log_likelihood,
+            fit.ks_statistic
+        );
+    }
+
+    let best = &fits[0];
+    println!();
+    println!("Best fit: {}", describe_fit(best));
+    println!("As service times:");
+    println!(
+        "    SimulationConfig::builder().mu({:.6}).service_kind({})",
+        1.0 / best.mean,
+        service_kind_source(best.kind)
+    );
+    if best.kind == ServiceKind::Exponential {
+        println!("As interarrival times:");
+        println!(
+            "    SimulationConfig::builder().lambda({:.6})",
+            1.0 / best.mean
+        );
+    }
+    Ok(())
+}
+
+/// Durations in `path`, one per line; blank lines, `#` comments and a header line are skipped
+fn read_durations(path: &Path) -> Result<Vec<f64>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut values = Vec::new();
+    let mut seen_line = false;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.parse::<f64>() {
+            Ok(value) => values.push(value),
+            // Only the first line may be a column name
+            Err(_) if !seen_line => {}
+            Err(_) => return Err(format!("line {}: '{}' is not a number", number + 1, line)),
+        }
+        seen_line = true;
+    }
+    Ok(values)
+}
+
+/// Parameters of a fitted shape with its mean, for the fit table
+fn describe_fit(fit: &Fit) -> String {
+    match fit.kind {
+        ServiceKind::Exponential => format!("exponential (mean {:.4})", fit.mean),
+        ServiceKind::Deterministic => format!("deterministic ({:.4})", fit.mean),
+        ServiceKind::Erlang(phases) => format!("Erlang-{} (mean {:.4})", phases, fit.mean),
+        ServiceKind::Gamma(shape) => format!("gamma (shape {:.4}, mean {:.4})", shape, fit.mean),
+        ServiceKind::Lognormal(sigma) => {
+            format!("lognormal (σ {:.4}, mean {:.4})", sigma, fit.mean)
+        }
+    }
+}
+
+/// The `ServiceKind` of a fit as Rust source
+fn service_kind_source(kind: ServiceKind) -> String {
+    match kind {
+        ServiceKind::Exponential => "ServiceKind::Exponential".to_string(),
+        ServiceKind::Deterministic => "ServiceKind::Deterministic".to_string(),
+        ServiceKind::Erlang(phases) => format!("ServiceKind::Erlang({})", phases),
+        ServiceKind::Gamma(shape) => format!("ServiceKind::Gamma({:.4})", shape),
+        ServiceKind::Lognormal(sigma) => format!("ServiceKind::Lognormal({:.4})", sigma),
+    }
+}
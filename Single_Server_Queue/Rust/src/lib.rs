@@ -0,0 +1,46 @@
+pub mod annotations;
+pub mod calibration;
+pub mod clock;
+pub mod config;
+pub mod consistency;
+pub mod cost;
+pub mod deadlines;
+pub mod distributions;
+pub mod engine;
+pub mod entities;
+pub mod event;
+pub mod event_log;
+pub mod event_order;
+pub mod experiment;
+pub mod fitting;
+pub mod gantt;
+pub mod live_viewer;
+pub mod model;
+pub mod network;
+pub mod overflow;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod plot_config;
+pub mod plotter;
+pub mod profiling;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod ring_buffer;
+pub mod rng;
+pub mod rolling_window;
+pub mod round_robin;
+pub mod run_length;
+pub mod sample_file;
+pub mod seed_scan;
+pub mod session;
+pub mod simulation;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statistics;
+pub mod steady_state;
+pub mod stream_capture;
+pub mod theory;
+pub mod time_series;
+pub mod trace;
+pub mod verification;
+pub mod wait_distribution;
@@ -0,0 +1,401 @@
+use crate::config::SimulationConfig;
+use crate::entities::ServiceInProgress;
+use crate::simulation::{Pacer, Simulation};
+use eframe::egui;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Longest the worker sleeps before checking for new commands while running
+const MAX_WORKER_SLEEP: Duration = Duration::from_millis(20);
+
+/// Number of processed events kept in the viewer's log
+const EVENT_LOG_LENGTH: usize = 25;
+
+/// Waiting customers sent to the viewer; the queue drawing has no room for more
+const MAX_DRAWN_WAITING: usize = 60;
+
+/// Servers drawn in the queue picture, the rest are summarised in a label
+const MAX_DRAWN_SERVERS: usize = 8;
+
+/// Commands sent from the viewer to the simulation worker
+#[derive(Debug, Clone, Copy)]
+pub enum LiveCommand {
+    /// Process exactly one event and pause
+    Step,
+    /// Run continuously at the given speed until paused
+    Run {
+        sim_units_per_second: f64,
+    },
+    Pause,
+}
+
+/// State of the simulation right after an event, sent from the worker to the viewer
+#[derive(Debug, Clone)]
+pub struct LiveState {
+    pub now: f64,
+    pub event_count: u64,
+    pub description: String,
+    pub queue_length: usize,
+    pub server_busy: bool,
+    pub served_customers: u64,
+    pub average_wait: f64,
+    pub servers: usize,
+    pub server_down: bool,
+    /// Customers in service, earliest departure first
+    pub in_service: Vec<ServiceInProgress>,
+    /// Arrival times of the first waiting customers, front of the queue first
+    pub waiting: Vec<f64>,
+}
+
+impl LiveState {
+    fn capture(simulation: &Simulation, description: String) -> Self {
+        let server = simulation.server();
+        let stats = simulation.statistics();
+        Self {
+            now: simulation.now(),
+            event_count: simulation.event_count(),
+            description,
+            queue_length: server.queue_length(),
+            server_busy: server.is_busy(),
+            served_customers: stats.served_customers(),
+            average_wait: stats.average_wait_time(),
+            servers: server.servers(),
+            server_down: server.is_down(),
+            in_service: server.services_in_progress(),
+            waiting: server.waiting_arrivals().take(MAX_DRAWN_WAITING).collect(),
+        }
+    }
+}
+
+/// Viewer that drives a simulation on a worker thread, one event at a time or paced
+///
+/// The simulation is owned by the worker; the two sides only exchange
+/// `LiveCommand`s and `LiveState`s over channels.
+pub struct LiveViewer {
+    commands: Sender<LiveCommand>,
+    updates: Receiver<LiveState>,
+    current: Option<LiveState>,
+    log: VecDeque<String>,
+    sim_units_per_second: f64,
+    running: bool,
+}
+
+impl LiveViewer {
+    pub fn launch(config: SimulationConfig) -> Result<(), eframe::Error> {
+        let options = eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default()
+                .with_inner_size([900.0, 700.0])
+                .with_title("Simulation - Live Viewer"),
+            ..Default::default()
+        };
+
+        eframe::run_native(
+            "Simulation Live",
+            options,
+            Box::new(move |cc| {
+                let (command_tx, command_rx) = mpsc::channel();
+                let (update_tx, update_rx) = mpsc::channel();
+                let ctx = cc.egui_ctx.clone();
+                thread::spawn(move || run_worker(config, command_rx, update_tx, ctx));
+
+                Ok(Box::new(Self {
+                    commands: command_tx,
+                    updates: update_rx,
+                    current: None,
+                    log: VecDeque::with_capacity(EVENT_LOG_LENGTH),
+                    sim_units_per_second: 10.0,
+                    running: false,
+                }))
+            }),
+        )
+    }
+
+    fn drain_updates(&mut self) {
+        while let Ok(state) = self.updates.try_recv() {
+            if self.log.len() == EVENT_LOG_LENGTH {
+                self.log.pop_back();
+            }
+            self.log
+                .push_front(format!("t = {:>12.4}  {}", state.now, state.description));
+            self.current = Some(state);
+        }
+    }
+
+    fn send(&mut self, command: LiveCommand) {
+        self.running = matches!(command, LiveCommand::Run { .. });
+        // The worker only goes away when the window does, so a failed send can be ignored
+        let _ = self.commands.send(command);
+    }
+}
+
+impl eframe::App for LiveViewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.drain_updates();
+
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| {
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                if ui.button("Step").clicked() {
+                    self.send(LiveCommand::Step);
+                }
+                if self.running {
+                    if ui.button("Pause").clicked() {
+                        self.send(LiveCommand::Pause);
+                    }
+                } else if ui.button("Run").clicked() {
+                    self.send(LiveCommand::Run {
+                        sim_units_per_second: self.sim_units_per_second,
+                    });
+                }
+
+                ui.separator();
+                let slider = ui.add(
+                    egui::Slider::new(&mut self.sim_units_per_second, 0.1..=10_000.0)
+                        .logarithmic(true)
+                        .text("sim time units / second"),
+                );
+                if slider.changed() && self.running {
+                    self.send(LiveCommand::Run {
+                        sim_units_per_second: self.sim_units_per_second,
+                    });
+                }
+            });
+            ui.add_space(6.0);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            match &self.current {
+                Some(state) => {
+                    egui::Grid::new("live_state").striped(true).show(ui, |ui| {
+                        ui.label("Clock");
+                        ui.label(format!("{:.4}", state.now));
+                        ui.end_row();
+                        ui.label("Events processed");
+                        ui.label(state.event_count.to_string());
+                        ui.end_row();
+                        ui.label("Queue length");
+                        ui.label(state.queue_length.to_string());
+                        ui.end_row();
+                        ui.label("Server");
+                        ui.label(if state.server_busy { "busy" } else { "idle" });
+                        ui.end_row();
+                        ui.label("Customers served");
+                        ui.label(state.served_customers.to_string());
+                        ui.end_row();
+                        ui.label("Average wait");
+                        ui.label(format!("{:.4}", state.average_wait));
+                        ui.end_row();
+                    });
+
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.label(egui::RichText::new("Queue").strong());
+                    draw_queue(ui, state);
+                }
+                None => {
+                    ui.label("Press Step to process the first event.");
+                }
+            }
+
+            ui.add_space(10.0);
+            ui.separator();
+            ui.label(egui::RichText::new("Recent events").strong());
+            for line in &self.log {
+                ui.monospace(line);
+            }
+        });
+    }
+}
+
+/// Picture of the station: waiting customers as a row of boxes heading for the servers
+///
+/// The front of the queue is next to the servers. Each busy server shows its
+/// customer highlighted with the time left until the departure; hovering a
+/// waiting customer shows how long it has waited so far.
+fn draw_queue(ui: &mut egui::Ui, state: &LiveState) {
+    const CUSTOMER: f32 = 26.0;
+    const GAP: f32 = 6.0;
+    const SERVER_WIDTH: f32 = 150.0;
+
+    let waiting_color = egui::Color32::from_rgb(90, 140, 220);
+    let serving_color = egui::Color32::from_rgb(235, 160, 40);
+    let down_color = egui::Color32::from_rgb(210, 60, 60);
+    let text_color = ui.visuals().strong_text_color();
+    let idle_color = ui.visuals().weak_text_color();
+    let font = egui::FontId::monospace(12.0);
+
+    let drawn_servers = state.servers.min(MAX_DRAWN_SERVERS);
+    let height = drawn_servers as f32 * (CUSTOMER + GAP) + GAP;
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), height),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+
+    let servers_left = rect.right() - SERVER_WIDTH;
+    for slot in 0..drawn_servers {
+        let top = rect.top() + GAP + slot as f32 * (CUSTOMER + GAP);
+        let server = egui::Rect::from_min_size(
+            egui::pos2(servers_left, top),
+            egui::vec2(SERVER_WIDTH, CUSTOMER),
+        );
+        let customer = state
+            .in_service
+            .iter()
+            .find(|customer| customer.server == slot);
+        let (fill, label) = match customer {
+            Some(_) if state.server_down => (down_color, "down".to_string()),
+            Some(customer) => (
+                serving_color,
+                format!(
+                    "#{} {:.2} left",
+                    customer.id,
+                    customer.completion - state.now
+                ),
+            ),
+            None => (egui::Color32::TRANSPARENT, "idle".to_string()),
+        };
+        painter.rect_filled(server, 4.0, fill);
+        painter.rect_stroke(
+            server,
+            4.0,
+            egui::Stroke::new(1.0, idle_color),
+            egui::StrokeKind::Inside,
+        );
+        let color = if fill == egui::Color32::TRANSPARENT {
+            idle_color
+        } else {
+            text_color
+        };
+        painter.text(
+            server.center(),
+            egui::Align2::CENTER_CENTER,
+            label,
+            font.clone(),
+            color,
+        );
+    }
+    if state.servers > drawn_servers {
+        let busy_hidden = state
+            .in_service
+            .iter()
+            .filter(|customer| customer.server >= drawn_servers)
+            .count();
+        painter.text(
+            egui::pos2(servers_left, rect.bottom()),
+            egui::Align2::LEFT_TOP,
+            format!(
+                "+{} servers, {} busy",
+                state.servers - drawn_servers,
+                busy_hidden
+            ),
+            font.clone(),
+            idle_color,
+        );
+    }
+
+    // The queue lines up level with the first server, its front on the right
+    let room = ((servers_left - rect.left() - 2.0 * GAP) / (CUSTOMER + GAP)).max(0.0) as usize;
+    // Leave the last box free for the count of those that do not fit
+    let drawn_waiting = if state.queue_length > room {
+        room.saturating_sub(1)
+    } else {
+        state.queue_length
+    }
+    .min(state.waiting.len());
+    let top = rect.top() + GAP;
+    for (position, arrival) in state.waiting.iter().take(drawn_waiting).enumerate() {
+        let right = servers_left - 2.0 * GAP - position as f32 * (CUSTOMER + GAP);
+        let customer = egui::Rect::from_min_size(
+            egui::pos2(right - CUSTOMER, top),
+            egui::vec2(CUSTOMER, CUSTOMER),
+        );
+        painter.rect_filled(customer, 4.0, waiting_color);
+        ui.interact(
+            customer,
+            ui.id().with(("waiting", position)),
+            egui::Sense::hover(),
+        )
+        .on_hover_text(format!(
+            "Position {} in the queue, waiting for {:.4}",
+            position + 1,
+            state.now - arrival
+        ));
+    }
+    if state.queue_length > drawn_waiting {
+        let left = servers_left - 2.0 * GAP - drawn_waiting as f32 * (CUSTOMER + GAP);
+        painter.text(
+            egui::pos2(left, top + CUSTOMER / 2.0),
+            egui::Align2::RIGHT_CENTER,
+            format!("+{}", state.queue_length - drawn_waiting),
+            font,
+            text_color,
+        );
+    }
+}
+
+/// Worker loop: blocks for commands while paused, paces events while running
+fn run_worker(
+    config: SimulationConfig,
+    commands: Receiver<LiveCommand>,
+    updates: Sender<LiveState>,
+    ctx: egui::Context,
+) {
+    let mut simulation = Simulation::new(config);
+    let mut pacer: Option<Pacer> = None;
+
+    loop {
+        let command = if pacer.is_none() {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        } else {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        };
+
+        let mut step_now = false;
+        match command {
+            Some(LiveCommand::Step) => {
+                pacer = None;
+                step_now = true;
+            }
+            Some(LiveCommand::Run {
+                sim_units_per_second,
+            }) => pacer = Some(Pacer::new(sim_units_per_second, simulation.now())),
+            Some(LiveCommand::Pause) => pacer = None,
+            None => {}
+        }
+
+        if let Some(active) = &pacer {
+            if !simulation.engine().has_next_event() {
+                pacer = None;
+                continue;
+            }
+            let delay = active.delay_until(simulation.engine().peek_next_time());
+            if delay > MAX_WORKER_SLEEP {
+                thread::sleep(MAX_WORKER_SLEEP);
+                continue;
+            }
+            thread::sleep(delay);
+            step_now = true;
+        }
+
+        if step_now && let Some(event) = simulation.step() {
+            let description = format!("{} -> {}", event.event_type, simulation.describe_state());
+            if updates
+                .send(LiveState::capture(&simulation, description))
+                .is_err()
+            {
+                return;
+            }
+            ctx.request_repaint();
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! The event loop shared by every model on a [`SimulationEngine`], see [`Model`] and [`run`]
+//!
+//! The engine owns the clock and the pending events, the model owns its entities
+//! and statistics. [`run`] takes each event off the engine, hands it to the model
+//! and then to the observer, until the stop condition is met, the events run
+//! out or an observer ends the run. A new model only has to route events to its
+//! entities; sampling, progress and stopping come with the driver.
+
+use crate::engine::SimulationEngine;
+use crate::event::Event;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// Events between two looks at the clock by a [`Deadline`], less one; reading
+/// the clock every event would cost a third of the loop
+const WALL_CLOCK_MASK: u64 = (1 << 12) - 1;
+
+/// A system whose entities are driven by the events of a [`SimulationEngine`]
+pub trait Model {
+    /// Schedule the first events, with the engine's clock at its start
+    fn init(&mut self, engine: &mut SimulationEngine);
+
+    /// Process `event`, which the engine has just taken off its list: route it
+    /// to the entity that owns it, which may schedule follow-up events
+    fn handle(&mut self, event: Event, engine: &mut SimulationEngine);
+
+    /// Customers who completed service so far, for [`StopCondition::Customers`];
+    /// a model without customers never meets that condition
+    fn customers_served(&self) -> u64 {
+        0
+    }
+
+    /// Whether the model cannot go on, e.g. after a failed replay; ends the run
+    fn is_halted(&self) -> bool {
+        false
+    }
+}
+
+/// When a run driven by [`run`] ends, besides the events running out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    /// Before the first event at or after this time
+    Time(f64),
+    /// After this many events of the run
+    Events(u64),
+    /// Once this many customers have been served
+    Customers(u64),
+    /// Only once no events are left, e.g. after arrivals stop at a cutoff
+    Drained,
+    /// Once an observer finds the steady state, but no later than this time
+    SteadyState(f64),
+}
+
+impl StopCondition {
+    /// Whether a run that processed `events` events has met the condition
+    pub fn is_met<M: Model + ?Sized>(
+        &self,
+        model: &M,
+        engine: &SimulationEngine,
+        events: u64,
+    ) -> bool {
+        match *self {
+            Self::Time(max_time) | Self::SteadyState(max_time) => {
+                engine.peek_next_time() >= max_time
+            }
+            Self::Events(max_events) => events >= max_events,
+            Self::Customers(max_customers) => model.customers_served() >= max_customers,
+            Self::Drained => false,
+        }
+    }
+}
+
+/// Watches a run of a model `M`, e.g. to sample its statistics or show progress
+pub trait Observer<M: ?Sized> {
+    /// Called after the model handled `event`; `Break` ends the run there
+    fn on_event(&mut self, model: &M, engine: &SimulationEngine, event: &Event) -> ControlFlow<()>;
+
+    /// Called once when the run ended, whatever ended it
+    fn on_finish(&mut self, _model: &M, _engine: &SimulationEngine, _outcome: &RunOutcome) {}
+}
+
+/// Why a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunEnd {
+    /// The stop condition was met
+    StopCondition,
+    /// No events were left
+    OutOfEvents,
+    /// The model could not go on, see [`Model::is_halted`]
+    Halted,
+    /// An observer returned `Break`
+    Observer,
+}
+
+/// How a run went, see [`run`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOutcome {
+    /// Events processed by the run
+    pub events: u64,
+    pub end: RunEnd,
+}
+
+/// Process the events of `engine` with `model` until `stop` is met or the run
+/// ends otherwise, showing each one to `observer`
+///
+/// The condition is checked before every event, so a time limit leaves the
+/// first event at or after it pending and the run can be continued later.
+/// The observer is a type parameter, so the loop calls it directly: pass `&mut ()`
+/// for none, a tuple or an `Option` to combine several, or a slice of
+/// `&mut dyn Observer<M>` if the set is only known at run time. Every observer of
+/// a combination sees every event, also the one at which another ends the run.
+pub fn run<M, O>(
+    model: &mut M,
+    engine: &mut SimulationEngine,
+    stop: &StopCondition,
+    observer: &mut O,
+) -> RunOutcome
+where
+    M: Model + ?Sized,
+    O: Observer<M> + ?Sized,
+{
+    let mut events = 0;
+    let end = loop {
+        if !engine.has_next_event() {
+            break RunEnd::OutOfEvents;
+        }
+        if model.is_halted() {
+            break RunEnd::Halted;
+        }
+        if stop.is_met(model, engine, events) {
+            break RunEnd::StopCondition;
+        }
+        let Some(event) = engine.run_step() else {
+            break RunEnd::OutOfEvents;
+        };
+        model.handle(event, engine);
+        events += 1;
+
+        if observer.on_event(model, engine, &event).is_break() {
+            break RunEnd::Observer;
+        }
+    };
+
+    let outcome = RunOutcome { events, end };
+    observer.on_finish(model, engine, &outcome);
+    outcome
+}
+
+/// No observer; the run loop compiles to the model alone
+impl<M: ?Sized> Observer<M> for () {
+    #[inline]
+    fn on_event(
+        &mut self,
+        _model: &M,
+        _engine: &SimulationEngine,
+        _event: &Event,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+impl<M: ?Sized, O: Observer<M> + ?Sized> Observer<M> for &mut O {
+    #[inline]
+    fn on_event(&mut self, model: &M, engine: &SimulationEngine, event: &Event) -> ControlFlow<()> {
+        (**self).on_event(model, engine, event)
+    }
+
+    fn on_finish(&mut self, model: &M, engine: &SimulationEngine, outcome: &RunOutcome) {
+        (**self).on_finish(model, engine, outcome);
+    }
+}
+
+/// An observer that may be switched off, e.g. a [`Deadline`] only set by an option
+impl<M: ?Sized, O: Observer<M>> Observer<M> for Option<O> {
+    #[inline]
+    fn on_event(&mut self, model: &M, engine: &SimulationEngine, event: &Event) -> ControlFlow<()> {
+        match self {
+            Some(observer) => observer.on_event(model, engine, event),
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn on_finish(&mut self, model: &M, engine: &SimulationEngine, outcome: &RunOutcome) {
+        if let Some(observer) = self {
+            observer.on_finish(model, engine, outcome);
+        }
+    }
+}
+
+/// Observers chosen at run time, in order
+impl<M: ?Sized, O: Observer<M>> Observer<M> for [O] {
+    fn on_event(&mut self, model: &M, engine: &SimulationEngine, event: &Event) -> ControlFlow<()> {
+        let mut flow = ControlFlow::Continue(());
+        for observer in self.iter_mut() {
+            if observer.on_event(model, engine, event).is_break() {
+                flow = ControlFlow::Break(());
+            }
+        }
+        flow
+    }
+
+    fn on_finish(&mut self, model: &M, engine: &SimulationEngine, outcome: &RunOutcome) {
+        for observer in self.iter_mut() {
+            observer.on_finish(model, engine, outcome);
+        }
+    }
+}
+
+/// Observers known at compile time, in order; each sees every event
+macro_rules! tuple_observer {
+    ($($name:ident),+) => {
+        impl<M: ?Sized, $($name: Observer<M>),+> Observer<M> for ($($name,)+) {
+            #[inline]
+            fn on_event(
+                &mut self,
+                model: &M,
+                engine: &SimulationEngine,
+                event: &Event,
+            ) -> ControlFlow<()> {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let mut flow = ControlFlow::Continue(());
+                $(
+                    if $name.on_event(model, engine, event).is_break() {
+                        flow = ControlFlow::Break(());
+                    }
+                )+
+                flow
+            }
+
+            fn on_finish(&mut self, model: &M, engine: &SimulationEngine, outcome: &RunOutcome) {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                $($name.on_finish(model, engine, outcome);)+
+            }
+        }
+    };
+}
+
+tuple_observer!(A);
+tuple_observer!(A, B);
+tuple_observer!(A, B, C);
+tuple_observer!(A, B, C, D);
+
+/// Ends a run once the wall clock passes a limit, looking at it every few thousand events
+pub struct Deadline {
+    at: Instant,
+    events: u64,
+    passed: bool,
+}
+
+impl Deadline {
+    /// A deadline `limit` from now
+    pub fn after(limit: Duration) -> Self {
+        Self {
+            at: Instant::now() + limit,
+            events: 0,
+            passed: false,
+        }
+    }
+
+    /// Whether the deadline ended the run
+    pub fn has_passed(&self) -> bool {
+        self.passed
+    }
+}
+
+impl<M: ?Sized> Observer<M> for Deadline {
+    fn on_event(
+        &mut self,
+        _model: &M,
+        _engine: &SimulationEngine,
+        _event: &Event,
+    ) -> ControlFlow<()> {
+        self.events += 1;
+        if self.events & WALL_CLOCK_MASK == 0 && Instant::now() >= self.at {
+            self.passed = true;
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,436 @@
+use crate::distributions::{Distribution, Exponential};
+use crate::engine::SimulationEngine;
+use crate::event::{Event, EventType};
+use crate::model::{self, Model, StopCondition};
+use crate::ring_buffer::RingBuffer;
+use crate::rng::{RngBackend, Sampler};
+use crate::statistics::{BatchMeans, Statistics};
+use crate::theory::jackson_arrival_rates;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// An open network of single-server stations with Markovian routing
+///
+/// Customers arrive from outside at rate `external_arrival_rates[i]` to station i,
+/// are served there at rate `service_rates[i]` and then move on to station j with
+/// probability `routing[i][j]`, or leave with probability 1 − Σ_j `routing[i][j]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConfig {
+    /// External Poisson arrival rate (γ) per station; 0 for stations only fed by routing
+    pub external_arrival_rates: Vec<f64>,
+
+    /// Service rate (μ) per station
+    pub service_rates: Vec<f64>,
+
+    /// Routing probabilities P[i][j] from station i to station j
+    pub routing: Vec<Vec<f64>>,
+
+    /// Statistics collected before this time are discarded (0 for no warm-up)
+    pub warm_up_time: f64,
+
+    /// Seed for the random streams; `None` seeds from system entropy
+    pub seed: Option<u64>,
+
+    /// Generator behind every random stream of the run
+    pub rng_backend: RngBackend,
+}
+
+impl NetworkConfig {
+    pub fn stations(&self) -> usize {
+        self.service_rates.len()
+    }
+
+    /// Check the dimensions and probabilities and solve the traffic equations
+    ///
+    /// Returns the total arrival rate λ_i of every station, or why the network
+    /// cannot reach a steady state.
+    pub fn validate(&self) -> Result<Vec<f64>, String> {
+        let n = self.stations();
+        if n == 0 {
+            return Err("the network needs at least one station".to_string());
+        }
+        if self.external_arrival_rates.len() != n || self.routing.len() != n {
+            return Err(format!(
+                "expected {} external arrival rates and {} routing rows",
+                n, n
+            ));
+        }
+        if let Some(i) = self
+            .service_rates
+            .iter()
+            .position(|mu| mu.is_nan() || *mu <= 0.0)
+        {
+            return Err(format!("station {}: service rate must be positive", i + 1));
+        }
+        if let Some(i) = self
+            .external_arrival_rates
+            .iter()
+            .position(|gamma| gamma.is_nan() || *gamma < 0.0)
+        {
+            return Err(format!(
+                "station {}: external arrival rate must not be negative",
+                i + 1
+            ));
+        }
+        if self
+            .external_arrival_rates
+            .iter()
+            .all(|gamma| *gamma == 0.0)
+        {
+            return Err("no station has external arrivals".to_string());
+        }
+        for (i, row) in self.routing.iter().enumerate() {
+            validate_routing_row(row, n).map_err(|e| format!("station {}: {}", i + 1, e))?;
+        }
+
+        let rates = jackson_arrival_rates(&self.external_arrival_rates, &self.routing).ok_or(
+            "the traffic equations have no solution; some customers never leave the network",
+        )?;
+        for (i, (lambda, mu)) in rates.iter().zip(&self.service_rates).enumerate() {
+            if lambda >= mu {
+                return Err(format!(
+                    "station {} is unstable: arrival rate {:.4} ≥ service rate {:.4}",
+                    i + 1,
+                    lambda,
+                    mu
+                ));
+            }
+        }
+        Ok(rates)
+    }
+}
+
+/// Check that `row` holds `stations` probabilities that sum to at most 1
+pub fn validate_routing_row(row: &[f64], stations: usize) -> Result<(), String> {
+    if row.len() != stations {
+        return Err(format!(
+            "expected {} routing probabilities, got {}",
+            stations,
+            row.len()
+        ));
+    }
+    if row.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        return Err("routing probabilities must be between 0 and 1".to_string());
+    }
+    if row.iter().sum::<f64>() > 1.0 + 1e-9 {
+        return Err("routing probabilities must not sum to more than 1".to_string());
+    }
+    Ok(())
+}
+
+/// A customer moving through the network
+#[derive(Debug, Clone, Copy, Default)]
+struct Customer {
+    /// Time the customer entered the network
+    entered: f64,
+    /// Time the customer joined the queue of its current station
+    arrived: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NetworkEventKind {
+    ExternalArrival,
+    Departure,
+}
+
+/// A timed event at one station, ordered so that a `BinaryHeap` pops the earliest
+/// and, among events at the same instant, the one scheduled first
+#[derive(Debug, Clone, Copy)]
+struct NetworkEvent {
+    time: f64,
+    station: usize,
+    kind: NetworkEventKind,
+    /// Position in scheduling order
+    seq: u64,
+}
+
+impl PartialEq for NetworkEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NetworkEvent {}
+
+impl PartialOrd for NetworkEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NetworkEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .time
+            .total_cmp(&self.time)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+/// One single-server station of the network
+struct Station {
+    service: Exponential,
+    service_rng: Box<dyn Sampler>,
+    inter_arrival: Option<Exponential>,
+    arrival_rng: Box<dyn Sampler>,
+    queue: RingBuffer<Customer>,
+    in_service: Option<Customer>,
+    service_start: f64,
+    stats: Statistics,
+}
+
+/// Discrete-event simulation of an open Jackson network
+///
+/// Unlike [`crate::simulation::Simulation`], whose engine holds one pending event
+/// per type, every station has its own arrival and departure stream here, so the
+/// events carry the station they belong to and live in a binary heap. Each
+/// station collects the same [`Statistics`] as the single queue.
+///
+/// The network is a [`Model`]: driven by [`model::run`], it keeps its earliest
+/// event on the engine, so the engine's clock and stop conditions apply, and
+/// the rest in its heap.
+pub struct NetworkSimulation {
+    config: NetworkConfig,
+    events: BinaryHeap<NetworkEvent>,
+    /// Events scheduled so far, the sequence number of the next one
+    scheduled: u64,
+    stations: Vec<Station>,
+    routing_rng: Box<dyn Sampler>,
+    now: f64,
+    event_count: u64,
+    warm_up_pending: bool,
+    sojourn_batches: BatchMeans,
+    total_sojourn: f64,
+    departed_customers: u64,
+}
+
+impl NetworkSimulation {
+    /// Build the network; fails with the reason if `config` does not pass [`NetworkConfig::validate`]
+    pub fn new(config: NetworkConfig) -> Result<Self, String> {
+        config.validate()?;
+        let mut master = config.rng_backend.create(config.seed);
+
+        let stations: Vec<Station> = config
+            .service_rates
+            .iter()
+            .zip(&config.external_arrival_rates)
+            .map(|(&mu, &gamma)| Station {
+                service: Exponential::new(mu),
+                service_rng: master.fork(),
+                inter_arrival: (gamma > 0.0).then(|| Exponential::new(gamma)),
+                arrival_rng: master.fork(),
+                queue: RingBuffer::new(),
+                in_service: None,
+                service_start: 0.0,
+                stats: Statistics::new(),
+            })
+            .collect();
+
+        let mut network = Self {
+            warm_up_pending: config.warm_up_time > 0.0,
+            routing_rng: master.fork(),
+            config,
+            events: BinaryHeap::new(),
+            scheduled: 0,
+            stations,
+            now: 0.0,
+            event_count: 0,
+            sojourn_batches: BatchMeans::new(),
+            total_sojourn: 0.0,
+            departed_customers: 0,
+        };
+        for station in 0..network.stations.len() {
+            if network.stations[station].inter_arrival.is_some() {
+                network.schedule(0.0, station, NetworkEventKind::ExternalArrival);
+            }
+        }
+        Ok(network)
+    }
+
+    /// Add an event; events at the same instant are processed in the order they were scheduled
+    fn schedule(&mut self, time: f64, station: usize, kind: NetworkEventKind) {
+        self.events.push(NetworkEvent {
+            time,
+            station,
+            kind,
+            seq: self.scheduled,
+        });
+        self.scheduled += 1;
+    }
+
+    /// Process the next event; returns `false` if there was none
+    pub fn step(&mut self) -> bool {
+        let Some(event) = self.events.peek().copied() else {
+            return false;
+        };
+        if self.warm_up_pending && event.time >= self.config.warm_up_time {
+            self.discard_warm_up(self.config.warm_up_time);
+        }
+
+        self.events.pop();
+        self.now = event.time;
+        self.event_count += 1;
+
+        match event.kind {
+            NetworkEventKind::ExternalArrival => {
+                let customer = Customer {
+                    entered: self.now,
+                    arrived: self.now,
+                };
+                self.join(event.station, customer);
+
+                let station = &mut self.stations[event.station];
+                if let Some(inter_arrival) = &mut station.inter_arrival {
+                    let next = self.now + inter_arrival.sample(station.arrival_rng.as_mut());
+                    self.schedule(next, event.station, NetworkEventKind::ExternalArrival);
+                }
+            }
+            NetworkEventKind::Departure => self.depart(event.station),
+        }
+        true
+    }
+
+    /// Process events until the next one would happen at or after `time`
+    pub fn run_until(&mut self, time: f64) {
+        let mut engine = SimulationEngine::new();
+        self.init(&mut engine);
+        model::run(self, &mut engine, &StopCondition::Time(time), &mut ());
+    }
+
+    /// Put the earliest pending event on `engine`, in place of the one it held
+    fn schedule_next(&self, engine: &mut SimulationEngine) {
+        engine.cancel(EventType::Arrival);
+        engine.cancel(EventType::Departure);
+        if let Some(next) = self.events.peek() {
+            let event_type = match next.kind {
+                NetworkEventKind::ExternalArrival => EventType::Arrival,
+                NetworkEventKind::Departure => EventType::Departure,
+            };
+            engine.schedule(Event::new(next.time, event_type));
+        }
+    }
+
+    /// Put `customer` in the queue of `station` and start serving if it is idle
+    fn join(&mut self, station: usize, mut customer: Customer) {
+        let now = self.now;
+        customer.arrived = now;
+        let state = &mut self.stations[station];
+        state.stats.record_arrival(now);
+        state.queue.push_back(customer);
+        state.stats.record_queue_change(now, state.queue.len());
+        if state.in_service.is_none() {
+            self.start_service(station);
+        }
+    }
+
+    fn start_service(&mut self, station: usize) {
+        let now = self.now;
+        let state = &mut self.stations[station];
+        let Some(customer) = state.queue.pop_front() else {
+            return;
+        };
+        state.stats.record_queue_change(now, state.queue.len());
+        state
+            .stats
+            .record_service_start(now, now - customer.arrived);
+        state.in_service = Some(customer);
+        state.service_start = now;
+
+        let service_time = state.service.sample(state.service_rng.as_mut());
+        self.schedule(now + service_time, station, NetworkEventKind::Departure);
+    }
+
+    /// Finish the service at `station` and route the customer on or out of the network
+    fn depart(&mut self, station: usize) {
+        let now = self.now;
+        let state = &mut self.stations[station];
+        let Some(customer) = state.in_service.take() else {
+            return;
+        };
+        state
+            .stats
+            .record_service_end(now, now - state.service_start, 0);
+        if !state.queue.is_empty() {
+            self.start_service(station);
+        }
+
+        let u = self.routing_rng.f64();
+        let mut cumulative = 0.0;
+        for (next, p) in self.config.routing[station].iter().enumerate() {
+            cumulative += p;
+            if u < cumulative {
+                self.join(next, customer);
+                return;
+            }
+        }
+
+        let sojourn = now - customer.entered;
+        self.total_sojourn += sojourn;
+        self.sojourn_batches.push(sojourn);
+        self.departed_customers += 1;
+    }
+
+    fn discard_warm_up(&mut self, time: f64) {
+        for station in &mut self.stations {
+            station.stats.discard_warm_up(time);
+        }
+        self.sojourn_batches.reset();
+        self.total_sojourn = 0.0;
+        self.departed_customers = 0;
+        self.warm_up_pending = false;
+    }
+
+    pub fn config(&self) -> &NetworkConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    pub fn event_count(&self) -> u64 {
+        self.event_count
+    }
+
+    /// Statistics of station `station`, counted from 0
+    pub fn station_statistics(&self, station: usize) -> &Statistics {
+        &self.stations[station].stats
+    }
+
+    /// Customers that left the network (after any warm-up)
+    pub fn departed_customers(&self) -> u64 {
+        self.departed_customers
+    }
+
+    /// Mean time from entering to leaving the network, measured per customer
+    pub fn average_sojourn_time(&self) -> f64 {
+        if self.departed_customers == 0 {
+            0.0
+        } else {
+            self.total_sojourn / self.departed_customers as f64
+        }
+    }
+
+    /// Mean sojourn time and its 95% confidence half-width from batch means
+    pub fn sojourn_confidence_interval(&self) -> Option<(f64, f64)> {
+        self.sojourn_batches.confidence_interval()
+    }
+}
+
+impl Model for NetworkSimulation {
+    fn init(&mut self, engine: &mut SimulationEngine) {
+        self.schedule_next(engine);
+    }
+
+    /// The engine only holds a copy of the earliest event of the heap, so this
+    /// processes that one and puts the next on the engine
+    fn handle(&mut self, _event: Event, engine: &mut SimulationEngine) {
+        self.step();
+        self.schedule_next(engine);
+    }
+
+    fn customers_served(&self) -> u64 {
+        self.departed_customers
+    }
+}
@@ -0,0 +1,407 @@
+use crate::distributions::{Distribution, Exponential};
+use crate::engine::SimulationEngine;
+use crate::event::{Event, EventType};
+use crate::model::{self, Model, StopCondition};
+use crate::ring_buffer::RingBuffer;
+use crate::rng::{RngBackend, Sampler};
+use crate::statistics::Statistics;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// One station of an overflow system: `servers` exponential servers and a
+/// waiting room of `queue_capacity` places
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverflowStation {
+    pub servers: usize,
+    /// Service rate (μ) of each server
+    pub service_rate: f64,
+    /// Customers that can wait while every server is busy; 0 for a pure loss station
+    pub queue_capacity: usize,
+}
+
+impl OverflowStation {
+    /// Customers the station holds when full
+    pub fn capacity(&self) -> usize {
+        self.servers + self.queue_capacity
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.servers == 0 {
+            return Err("needs at least one server".to_string());
+        }
+        if !(self.service_rate.is_finite() && self.service_rate > 0.0) {
+            return Err("service rate must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A primary M/M/c/K station whose turned-away customers overflow to a secondary one
+///
+/// Customers arrive as a Poisson stream at rate `arrival_rate` and join the
+/// primary station; one who finds it full goes to the secondary station instead,
+/// and only one who finds both full is lost. Both stations have finite room, so
+/// the system reaches a steady state at every arrival rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverflowConfig {
+    /// Poisson arrival rate (λ) at the primary station
+    pub arrival_rate: f64,
+    pub primary: OverflowStation,
+    pub secondary: OverflowStation,
+
+    /// Statistics collected before this time are discarded (0 for no warm-up)
+    pub warm_up_time: f64,
+
+    /// Seed for the random streams; `None` seeds from system entropy
+    pub seed: Option<u64>,
+
+    /// Generator behind every random stream of the run
+    pub rng_backend: RngBackend,
+}
+
+impl OverflowConfig {
+    /// Check the rates and server counts, naming the station at fault
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.arrival_rate.is_finite() && self.arrival_rate > 0.0) {
+            return Err("arrival rate must be positive".to_string());
+        }
+        self.primary
+            .validate()
+            .map_err(|e| format!("primary station: {}", e))?;
+        self.secondary
+            .validate()
+            .map_err(|e| format!("secondary station: {}", e))
+    }
+}
+
+/// Where a customer was served
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPath {
+    Primary,
+    Secondary,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OverflowEventKind {
+    Arrival,
+    Departure { path: OverflowPath, server: usize },
+}
+
+/// A timed event, ordered so that a `BinaryHeap` pops the earliest and, among
+/// events at the same instant, the one scheduled first
+#[derive(Debug, Clone, Copy)]
+struct OverflowEvent {
+    time: f64,
+    kind: OverflowEventKind,
+    /// Position in scheduling order
+    seq: u64,
+}
+
+impl PartialEq for OverflowEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OverflowEvent {}
+
+impl PartialOrd for OverflowEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OverflowEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .time
+            .total_cmp(&self.time)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+/// Servers, waiting room and statistics of one station
+struct Station {
+    config: OverflowStation,
+    service: Exponential,
+    service_rng: Box<dyn Sampler>,
+    /// Arrival times of the waiting customers
+    queue: RingBuffer<f64>,
+    /// Start of the service in progress at each server, `None` while it is idle
+    service_starts: Vec<Option<f64>>,
+    busy: usize,
+    stats: Statistics,
+}
+
+impl Station {
+    fn new(config: OverflowStation, service_rng: Box<dyn Sampler>) -> Self {
+        Self {
+            config,
+            service: Exponential::new(config.service_rate),
+            service_rng,
+            queue: RingBuffer::new(),
+            service_starts: vec![None; config.servers],
+            busy: 0,
+            stats: Statistics::with_servers(config.servers),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.busy + self.queue.len() >= self.config.capacity()
+    }
+}
+
+/// Discrete-event simulation of an overflow system, see [`OverflowConfig`]
+///
+/// Like [`crate::network::NetworkSimulation`] the events carry the station they
+/// belong to and live in a binary heap. Each station keeps the same
+/// [`Statistics`] as the single queue: the primary counts every arrival and
+/// the customers it turned away as its losses, the secondary counts the
+/// overflow as its arrivals and the customers lost to the whole system as its
+/// losses. The primary's blocking probability is therefore the loss the system
+/// would have without the secondary station, measured in the same run.
+///
+/// As a [`Model`] the system keeps its earliest event on the engine and the
+/// rest in its heap, the same way as the network.
+pub struct OverflowSimulation {
+    config: OverflowConfig,
+    events: BinaryHeap<OverflowEvent>,
+    /// Events scheduled so far, the sequence number of the next one
+    scheduled: u64,
+    inter_arrival: Exponential,
+    arrival_rng: Box<dyn Sampler>,
+    primary: Station,
+    secondary: Station,
+    now: f64,
+    event_count: u64,
+    warm_up_pending: bool,
+}
+
+impl OverflowSimulation {
+    /// Build the system; fails with the reason if `config` does not pass [`OverflowConfig::validate`]
+    pub fn new(config: OverflowConfig) -> Result<Self, String> {
+        config.validate()?;
+        let mut master = config.rng_backend.create(config.seed);
+
+        let mut simulation = Self {
+            inter_arrival: Exponential::new(config.arrival_rate),
+            arrival_rng: master.fork(),
+            primary: Station::new(config.primary, master.fork()),
+            secondary: Station::new(config.secondary, master.fork()),
+            warm_up_pending: config.warm_up_time > 0.0,
+            config,
+            events: BinaryHeap::new(),
+            scheduled: 0,
+            now: 0.0,
+            event_count: 0,
+        };
+        simulation.schedule(0.0, OverflowEventKind::Arrival);
+        Ok(simulation)
+    }
+
+    /// Add an event; events at the same instant are processed in the order they were scheduled
+    fn schedule(&mut self, time: f64, kind: OverflowEventKind) {
+        self.events.push(OverflowEvent {
+            time,
+            kind,
+            seq: self.scheduled,
+        });
+        self.scheduled += 1;
+    }
+
+    fn station_mut(&mut self, path: OverflowPath) -> &mut Station {
+        match path {
+            OverflowPath::Primary => &mut self.primary,
+            OverflowPath::Secondary => &mut self.secondary,
+        }
+    }
+
+    /// Process the next event; returns `false` if there was none
+    pub fn step(&mut self) -> bool {
+        let Some(event) = self.events.pop() else {
+            return false;
+        };
+        if self.warm_up_pending && event.time >= self.config.warm_up_time {
+            self.discard_warm_up(self.config.warm_up_time);
+        }
+        self.now = event.time;
+        self.event_count += 1;
+
+        match event.kind {
+            OverflowEventKind::Arrival => {
+                self.arrive();
+                let next = self.now + self.inter_arrival.sample(self.arrival_rng.as_mut());
+                self.schedule(next, OverflowEventKind::Arrival);
+            }
+            OverflowEventKind::Departure { path, server } => self.depart(path, server),
+        }
+        true
+    }
+
+    /// Process events until the next one would happen at or after `time`
+    pub fn run_until(&mut self, time: f64) {
+        let mut engine = SimulationEngine::new();
+        self.init(&mut engine);
+        model::run(self, &mut engine, &StopCondition::Time(time), &mut ());
+    }
+
+    /// Put the earliest pending event on `engine`, in place of the one it held
+    fn schedule_next(&self, engine: &mut SimulationEngine) {
+        engine.cancel(EventType::Arrival);
+        engine.cancel(EventType::Departure);
+        if let Some(next) = self.events.peek() {
+            let event_type = match next.kind {
+                OverflowEventKind::Arrival => EventType::Arrival,
+                OverflowEventKind::Departure { .. } => EventType::Departure,
+            };
+            engine.schedule(Event::new(next.time, event_type));
+        }
+    }
+
+    /// Send an arrival to the primary station, or to the secondary one if the primary is full
+    fn arrive(&mut self) {
+        let now = self.now;
+        self.primary.stats.record_arrival(now);
+        if !self.primary.is_full() {
+            self.join(OverflowPath::Primary);
+            return;
+        }
+        self.primary.stats.record_loss();
+
+        self.secondary.stats.record_arrival(now);
+        if self.secondary.is_full() {
+            self.secondary.stats.record_loss();
+        } else {
+            self.join(OverflowPath::Secondary);
+        }
+    }
+
+    /// Queue an arrival at the station on `path`, which has room, and serve it if a server is free
+    fn join(&mut self, path: OverflowPath) {
+        let now = self.now;
+        let station = self.station_mut(path);
+        let delayed = station.busy == station.config.servers;
+        station.stats.record_admission(delayed);
+        station.queue.push_back(now);
+        station.stats.record_queue_change(now, station.queue.len());
+        if !delayed {
+            self.start_service(path);
+        }
+    }
+
+    /// Start serving the head of the queue on `path` at an idle server
+    fn start_service(&mut self, path: OverflowPath) {
+        let now = self.now;
+        let station = self.station_mut(path);
+        let Some(server) = station.service_starts.iter().position(Option::is_none) else {
+            return;
+        };
+        let Some(arrived) = station.queue.pop_front() else {
+            return;
+        };
+        station.stats.record_queue_change(now, station.queue.len());
+        station.stats.record_service_start(now, now - arrived);
+        station.service_starts[server] = Some(now);
+        station.busy += 1;
+
+        let service_time = station.service.sample(station.service_rng.as_mut());
+        self.schedule(
+            now + service_time,
+            OverflowEventKind::Departure { path, server },
+        );
+    }
+
+    fn depart(&mut self, path: OverflowPath, server: usize) {
+        let now = self.now;
+        let station = self.station_mut(path);
+        let Some(started) = station.service_starts[server].take() else {
+            return;
+        };
+        station.busy -= 1;
+        station.stats.record_service_end(now, now - started, server);
+        if !station.queue.is_empty() {
+            self.start_service(path);
+        }
+    }
+
+    fn discard_warm_up(&mut self, time: f64) {
+        self.primary.stats.discard_warm_up(time);
+        self.secondary.stats.discard_warm_up(time);
+        self.warm_up_pending = false;
+    }
+
+    pub fn config(&self) -> &OverflowConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    pub fn event_count(&self) -> u64 {
+        self.event_count
+    }
+
+    /// Statistics of the station on `path`
+    pub fn statistics(&self, path: OverflowPath) -> &Statistics {
+        match path {
+            OverflowPath::Primary => &self.primary.stats,
+            OverflowPath::Secondary => &self.secondary.stats,
+        }
+    }
+
+    /// Customers that arrived at the system (after any warm-up)
+    pub fn arrivals(&self) -> u64 {
+        self.primary.stats.arrived_customers()
+    }
+
+    /// Customers the primary station turned away, whether or not the secondary took them
+    pub fn overflowed(&self) -> u64 {
+        self.primary.stats.lost_customers()
+    }
+
+    /// Customers that found both stations full
+    pub fn lost(&self) -> u64 {
+        self.secondary.stats.lost_customers()
+    }
+
+    /// Share of the arrivals lost to the whole system
+    pub fn loss_probability(&self) -> f64 {
+        match self.arrivals() {
+            0 => 0.0,
+            arrivals => self.lost() as f64 / arrivals as f64,
+        }
+    }
+
+    /// Share of the arrivals that found the primary station full, the loss
+    /// probability the primary station would have on its own
+    pub fn overflow_probability(&self) -> f64 {
+        self.primary.stats.blocking_probability()
+    }
+
+    /// Squared coefficient of variation of the times between overflowing customers,
+    /// 1 for a Poisson stream; `None` until a few customers overflowed
+    ///
+    /// The primary station sends its overflow in bursts while it stays full, so
+    /// the stream is more variable than the Poisson arrivals that feed it.
+    pub fn overflow_scv(&self) -> Option<f64> {
+        self.secondary.stats.interarrival_times().scv()
+    }
+}
+
+impl Model for OverflowSimulation {
+    fn init(&mut self, engine: &mut SimulationEngine) {
+        self.schedule_next(engine);
+    }
+
+    /// Processes the earliest event of the heap, of which the engine held a copy
+    fn handle(&mut self, _event: Event, engine: &mut SimulationEngine) {
+        self.step();
+        self.schedule_next(engine);
+    }
+
+    fn customers_served(&self) -> u64 {
+        self.primary.stats.served_customers() + self.secondary.stats.served_customers()
+    }
+}
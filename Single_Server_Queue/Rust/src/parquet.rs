@@ -0,0 +1,212 @@
+use crate::config::SimulationConfig;
+use crate::time_series::{SimulationTimeSeries, TimeSeries};
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::errors::Result;
+use ::parquet::file::metadata::KeyValue;
+use ::parquet::file::properties::WriterProperties;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Writes the fixed-interval samples of one or more runs into a single Parquet file
+///
+/// Every row is one sample time with all metrics as typed columns (counts as
+/// `UInt64`, everything else as full-precision `Float64`, null where a confidence
+/// bound does not exist yet) and the run it belongs
+/// to in `run_id`. Each run's configuration is stored as key-value metadata under
+/// `run.<id>.<field>`, so a whole study can be loaded and filtered as one table.
+pub struct ParquetRunWriter {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+}
+
+impl ParquetRunWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let schema = schema();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+        Ok(Self { writer, schema })
+    }
+
+    pub fn write_run(
+        &mut self,
+        run_id: u32,
+        label: &str,
+        config: &SimulationConfig,
+        time_series: &SimulationTimeSeries,
+    ) -> Result<()> {
+        self.writer.append_key_value_metadata(KeyValue::new(
+            format!("run.{}.label", run_id),
+            label.to_string(),
+        ));
+        for (key, value) in run_metadata(config) {
+            self.writer
+                .append_key_value_metadata(KeyValue::new(format!("run.{}.{}", run_id, key), value));
+        }
+
+        let batch = record_batch(&self.schema, run_id, time_series)?;
+        self.writer.write(&batch)
+    }
+
+    /// Flush the remaining rows and write the footer; the file is unreadable without this
+    pub fn close(self) -> Result<()> {
+        self.writer.close().map(|_| ())
+    }
+}
+
+impl SimulationTimeSeries {
+    /// Write the samples of a single run to `path` as run 0
+    pub fn write_parquet(&self, path: &Path, config: &SimulationConfig) -> Result<()> {
+        let mut writer = ParquetRunWriter::create(path)?;
+        writer.write_run(0, "run", config, self)?;
+        writer.close()
+    }
+}
+
+/// Configuration of a run as `(field, value)` strings
+pub fn run_metadata(config: &SimulationConfig) -> Vec<(&'static str, String)> {
+    fn optional(value: Option<impl ToString>) -> String {
+        value.map_or_else(String::new, |v| v.to_string())
+    }
+
+    vec![
+        ("lambda", config.lambda.to_string()),
+        ("mu", config.mu.to_string()),
+        ("servers", config.servers.to_string()),
+        ("waiting_room", config.waiting_room.to_string()),
+        ("max_queue_length", optional(config.max_queue_length)),
+        ("explosion_limit", optional(config.explosion_limit)),
+        ("arrival_kind", config.arrival_kind.to_string()),
+        ("service_kind", config.service_kind.to_string()),
+        ("max_service_time", optional(config.max_service_time)),
+        (
+            "service_modulation",
+            optional(config.service_modulation.as_ref()),
+        ),
+        ("arrival_cutoff", optional(config.arrival_cutoff)),
+        (
+            "mean_time_to_failure",
+            optional(config.breakdowns.map(|b| b.mean_time_to_failure)),
+        ),
+        (
+            "mean_repair_time",
+            optional(config.breakdowns.map(|b| b.mean_repair_time)),
+        ),
+        (
+            "interrupted_service",
+            optional(config.breakdowns.map(|b| b.interrupted_service)),
+        ),
+        (
+            "switchover_time",
+            optional(config.standby.map(|s| s.switchover_time)),
+        ),
+        (
+            "standby_mean_time_to_failure",
+            optional(config.standby.map(|s| s.mean_time_to_failure)),
+        ),
+        (
+            "standby_mean_repair_time",
+            optional(config.standby.map(|s| s.mean_repair_time)),
+        ),
+        ("mean_setup_time", optional(config.setup.map(|s| s.mean))),
+        ("setup_kind", optional(config.setup.map(|s| s.kind))),
+        ("warm_up_time", config.warm_up_time.to_string()),
+        ("throughput_window", config.throughput_window.to_string()),
+        ("statistics_window", optional(config.statistics_window)),
+        ("seed", optional(config.seed)),
+        ("rng_backend", config.rng_backend.to_string()),
+    ]
+}
+
+fn schema() -> SchemaRef {
+    let float = |name| Field::new(name, DataType::Float64, false);
+    let count = |name| Field::new(name, DataType::UInt64, false);
+    Arc::new(Schema::new(vec![
+        Field::new("run_id", DataType::UInt32, false),
+        float("time"),
+        count("queue_length"),
+        float("mean_wait_time"),
+        Field::new("mean_wait_lower", DataType::Float64, true),
+        Field::new("mean_wait_upper", DataType::Float64, true),
+        float("utilization"),
+        count("customers_served"),
+        count("customers_arrived"),
+        count("customers_in_system"),
+        float("throughput"),
+        float("windowed_throughput"),
+        count("busy_servers"),
+        Field::new("wait_cv", DataType::Float64, true),
+    ]))
+}
+
+fn record_batch(schema: &SchemaRef, run_id: u32, ts: &SimulationTimeSeries) -> Result<RecordBatch> {
+    // All fixed-interval series are sampled together, so they line up row by row
+    let rows = [
+        ts.queue_length.len(),
+        ts.mean_wait_time.len(),
+        ts.mean_wait_ci.len(),
+        ts.utilization.len(),
+        ts.customers_served.len(),
+        ts.customers_arrived.len(),
+        ts.customers_in_system.len(),
+        ts.throughput.len(),
+        ts.windowed_throughput.len(),
+        ts.busy_servers.len(),
+        ts.wait_cv.len(),
+    ]
+    .into_iter()
+    .min()
+    .unwrap_or(0);
+
+    fn floats(series: &TimeSeries<f64>, rows: usize) -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(
+            series.data()[..rows].iter().map(|(_, v)| *v),
+        ))
+    }
+    fn counts<T: Copy + Into<u64>>(series: &TimeSeries<T>, rows: usize) -> ArrayRef {
+        Arc::new(UInt64Array::from_iter_values(
+            series.data()[..rows].iter().map(|(_, v)| (*v).into()),
+        ))
+    }
+    fn bounds(
+        series: &TimeSeries<Option<(f64, f64)>>,
+        rows: usize,
+        bound: fn((f64, f64)) -> f64,
+    ) -> ArrayRef {
+        Arc::new(Float64Array::from_iter(
+            series.data()[..rows].iter().map(|(_, ci)| ci.map(bound)),
+        ))
+    }
+    fn usizes(series: &TimeSeries<usize>, rows: usize) -> ArrayRef {
+        Arc::new(UInt64Array::from_iter_values(
+            series.data()[..rows].iter().map(|(_, v)| *v as u64),
+        ))
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt32Array::from(vec![run_id; rows])),
+        Arc::new(Float64Array::from_iter_values(
+            ts.queue_length.data()[..rows].iter().map(|(t, _)| *t),
+        )),
+        usizes(&ts.queue_length, rows),
+        floats(&ts.mean_wait_time, rows),
+        bounds(&ts.mean_wait_ci, rows, |(lower, _)| lower),
+        bounds(&ts.mean_wait_ci, rows, |(_, upper)| upper),
+        floats(&ts.utilization, rows),
+        counts(&ts.customers_served, rows),
+        counts(&ts.customers_arrived, rows),
+        usizes(&ts.customers_in_system, rows),
+        floats(&ts.throughput, rows),
+        floats(&ts.windowed_throughput, rows),
+        usizes(&ts.busy_servers, rows),
+        Arc::new(Float64Array::from_iter(
+            ts.wait_cv.data()[..rows].iter().map(|(_, cv)| *cv),
+        )),
+    ];
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
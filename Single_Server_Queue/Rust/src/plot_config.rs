@@ -0,0 +1,435 @@
+//! Which panels the viewer shows, in which order and how, read from the `[plots]` section of a TOML file
+//!
+//! Every key of `[plots]` names a panel; the panels appear in the order of the
+//! file, two per row. Each panel's table may set
+//!
+//! ```toml
+//! [plots.queue_length]
+//! color = "#1f77b4"       # main series, as #rrggbb
+//! title = "Queue length"  # legend name of the main series (heading of the heatmap)
+//! y_min = 0.0             # with y_max the initial y range, alone always included
+//! y_max = 40.0
+//! line_width = 2.0
+//! log_scale = true        # log10 y axis; samples at or below zero are left out
+//!
+//! [plots.mean_wait_time]  # an empty table keeps the panel's defaults
+//! ```
+//!
+//! A file without a `[plots]` section, or with an empty one or only unknown names,
+//! keeps the default layout of every panel but the opt-in `slowdown_histogram`. Unknown panel names and options a panel cannot
+//! apply are reported as warnings and skipped; malformed values are errors.
+
+use serde::Deserialize;
+use std::path::Path;
+
+/// One panel of the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Panel {
+    QueueLength,
+    MeanWaitTime,
+    CustomersInSystem,
+    Utilization,
+    ArrivalRate,
+    DepartureRate,
+    ArrivalDepartureStaircase,
+    Throughput,
+    CustomersServed,
+    Ensemble,
+    BusyServers,
+    CustomersDistribution,
+    QueueHeatmap,
+    WaitCv,
+    WaitHistogram,
+    /// Opt-in: only shown when `[plots]` names it
+    SlowdownHistogram,
+    /// Empirical CDF of the waits against the M/M/1 one
+    WaitEcdf,
+    /// Quantiles of the waits against the M/M/1 ones
+    WaitQq,
+    /// Chosen metrics normalized onto one scale
+    Overlay,
+}
+
+impl Panel {
+    /// Every panel in the default order, cumulative counters last
+    pub const ALL: [Self; 19] = [
+        Self::QueueLength,
+        Self::MeanWaitTime,
+        Self::CustomersInSystem,
+        Self::Utilization,
+        Self::ArrivalRate,
+        Self::DepartureRate,
+        Self::ArrivalDepartureStaircase,
+        Self::Throughput,
+        Self::CustomersServed,
+        Self::Ensemble,
+        Self::BusyServers,
+        Self::CustomersDistribution,
+        Self::QueueHeatmap,
+        Self::WaitCv,
+        Self::WaitHistogram,
+        Self::SlowdownHistogram,
+        Self::WaitEcdf,
+        Self::WaitQq,
+        Self::Overlay,
+    ];
+
+    /// Key of the panel under `[plots]`, also the id of its plot
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::QueueLength => "queue_length",
+            Self::MeanWaitTime => "mean_wait_time",
+            Self::CustomersInSystem => "customers_in_system",
+            Self::Utilization => "utilization",
+            Self::ArrivalRate => "arrival_rate",
+            Self::DepartureRate => "departure_rate",
+            Self::ArrivalDepartureStaircase => "arrival_departure_staircase",
+            Self::Throughput => "throughput",
+            Self::CustomersServed => "customers_served",
+            Self::Ensemble => "ensemble",
+            Self::BusyServers => "busy_servers",
+            Self::CustomersDistribution => "customers_distribution",
+            Self::QueueHeatmap => "queue_heatmap",
+            Self::WaitCv => "wait_cv",
+            Self::WaitHistogram => "wait_histogram",
+            Self::SlowdownHistogram => "slowdown_histogram",
+            Self::WaitEcdf => "wait_ecdf",
+            Self::WaitQq => "wait_qq",
+            Self::Overlay => "overlay",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|panel| panel.name() == name)
+    }
+
+    /// Whether the panel draws a series whose color can be chosen
+    fn has_color(self) -> bool {
+        !matches!(self, Self::QueueHeatmap | Self::Overlay)
+    }
+
+    /// Whether the panel draws its data as lines rather than bars or cells
+    fn has_lines(self) -> bool {
+        !matches!(
+            self,
+            Self::QueueHeatmap
+                | Self::CustomersDistribution
+                | Self::WaitHistogram
+                | Self::SlowdownHistogram
+                | Self::WaitQq
+        )
+    }
+
+    /// Whether the panel is part of the layout when no `[plots]` section says otherwise
+    fn shown_by_default(self) -> bool {
+        self != Self::SlowdownHistogram
+    }
+
+    /// Whether the panel can put its series on a log scale; the others draw bands,
+    /// bars or reference lines that would need their own transform
+    fn has_log_scale(self) -> bool {
+        matches!(
+            self,
+            Self::QueueLength
+                | Self::CustomersInSystem
+                | Self::Utilization
+                | Self::ArrivalRate
+                | Self::DepartureRate
+                | Self::ArrivalDepartureStaircase
+                | Self::Throughput
+                | Self::CustomersServed
+        )
+    }
+}
+
+/// How one panel is drawn; `None` keeps the panel's own choice
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PanelStyle {
+    pub color: Option<[u8; 3]>,
+    pub title: Option<String>,
+    pub y_min: Option<f64>,
+    pub y_max: Option<f64>,
+    pub line_width: Option<f32>,
+    pub log_scale: bool,
+}
+
+/// Options of one panel as written in the file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawStyle {
+    color: Option<String>,
+    title: Option<String>,
+    y_min: Option<f64>,
+    y_max: Option<f64>,
+    line_width: Option<f32>,
+    #[serde(default)]
+    log_scale: bool,
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    plots: Option<toml::Table>,
+}
+
+/// The panels of the viewer in display order, each with its style
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotLayout {
+    pub panels: Vec<(Panel, PanelStyle)>,
+}
+
+impl Default for PlotLayout {
+    fn default() -> Self {
+        Self {
+            panels: Panel::ALL
+                .into_iter()
+                .filter(|panel| panel.shown_by_default())
+                .map(|panel| (panel, PanelStyle::default()))
+                .collect(),
+        }
+    }
+}
+
+impl PlotLayout {
+    /// Read the layout from a TOML file, see [`Self::parse`]
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// The layout described by the `[plots]` section of `text`, together with a
+    /// warning for every panel or option that was skipped
+    pub fn parse(text: &str) -> Result<(Self, Vec<String>), String> {
+        let config: RawConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        let Some(plots) = config.plots.filter(|plots| !plots.is_empty()) else {
+            return Ok((Self::default(), Vec::new()));
+        };
+
+        let mut warnings = Vec::new();
+        let mut panels = Vec::new();
+        for (name, value) in plots {
+            let Some(panel) = Panel::from_name(&name) else {
+                warnings.push(format!(
+                    "unknown plot '{}' skipped; known plots are {}",
+                    name,
+                    Panel::ALL.map(Panel::name).join(", ")
+                ));
+                continue;
+            };
+            let raw: RawStyle = value
+                .try_into()
+                .map_err(|e| format!("plots.{}: {}", name, e.to_string().trim_end()))?;
+            let style = panel_style(panel, raw, &mut warnings)
+                .map_err(|e| format!("plots.{}: {}", name, e))?;
+            panels.push((panel, style));
+        }
+
+        if panels.is_empty() {
+            warnings.push("[plots] names no known plot, showing all of them".to_string());
+            return Ok((Self::default(), warnings));
+        }
+        Ok((Self { panels }, warnings))
+    }
+
+    /// Style of `panel`, the default one when the layout leaves it out
+    pub fn style(&self, panel: Panel) -> PanelStyle {
+        self.panels
+            .iter()
+            .find(|(p, _)| *p == panel)
+            .map(|(_, style)| style.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Check the options of one panel, dropping with a warning those it cannot apply
+fn panel_style(
+    panel: Panel,
+    raw: RawStyle,
+    warnings: &mut Vec<String>,
+) -> Result<PanelStyle, String> {
+    let mut style = PanelStyle {
+        color: raw.color.as_deref().map(parse_hex_color).transpose()?,
+        title: raw.title,
+        y_min: raw.y_min,
+        y_max: raw.y_max,
+        line_width: raw.line_width,
+        log_scale: raw.log_scale,
+    };
+
+    for (name, y) in [("y_min", style.y_min), ("y_max", style.y_max)] {
+        if let Some(y) = y
+            && !y.is_finite()
+        {
+            return Err(format!("{} must be finite", name));
+        }
+    }
+    if let (Some(y_min), Some(y_max)) = (style.y_min, style.y_max)
+        && y_min >= y_max
+    {
+        return Err("y_min must be below y_max".to_string());
+    }
+    if let Some(width) = style.line_width
+        && !(width.is_finite() && width > 0.0)
+    {
+        return Err("line_width must be positive".to_string());
+    }
+
+    let mut ignore = |option: &str| {
+        warnings.push(format!(
+            "plots.{}: {} is ignored by this plot",
+            panel.name(),
+            option
+        ));
+    };
+    if style.color.is_some() && !panel.has_color() {
+        ignore("color");
+        style.color = None;
+    }
+    if style.line_width.is_some() && !panel.has_lines() {
+        ignore("line_width");
+        style.line_width = None;
+    }
+    if style.log_scale && !panel.has_log_scale() {
+        ignore("log_scale");
+        style.log_scale = false;
+    }
+    if style.log_scale
+        && [style.y_min, style.y_max]
+            .into_iter()
+            .flatten()
+            .any(|y| y <= 0.0)
+    {
+        return Err("y_min and y_max must be positive on a log scale".to_string());
+    }
+    Ok(style)
+}
+
+/// `#rrggbb`, or the same without the `#`, as red, green and blue
+fn parse_hex_color(text: &str) -> Result<[u8; 3], String> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("color '{}' is not of the form #rrggbb", text));
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> (PlotLayout, Vec<String>) {
+        PlotLayout::parse(text).expect("the layout parses")
+    }
+
+    fn names(layout: &PlotLayout) -> Vec<&'static str> {
+        layout
+            .panels
+            .iter()
+            .map(|(panel, _)| panel.name())
+            .collect()
+    }
+
+    #[test]
+    fn panels_follow_the_file_with_their_styles() {
+        let (layout, warnings) = parse(
+            r##"
+            [plots.mean_wait_time]
+            [plots.queue_length]
+            color = "#1F77b4"
+            title = "Queue"
+            y_min = 1.0
+            y_max = 40.0
+            line_width = 2.5
+            log_scale = true
+            "##,
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(names(&layout), ["mean_wait_time", "queue_length"]);
+        assert_eq!(
+            layout.style(Panel::QueueLength),
+            PanelStyle {
+                color: Some([0x1f, 0x77, 0xb4]),
+                title: Some("Queue".to_string()),
+                y_min: Some(1.0),
+                y_max: Some(40.0),
+                line_width: Some(2.5),
+                log_scale: true,
+            }
+        );
+        assert_eq!(layout.style(Panel::MeanWaitTime), PanelStyle::default());
+        // A panel the file leaves out gets the default style if asked for
+        assert_eq!(layout.style(Panel::Utilization), PanelStyle::default());
+    }
+
+    #[test]
+    fn without_plots_every_panel_but_the_opt_in_one_is_shown() {
+        for text in ["", "seed = 3", "[plots]"] {
+            let (layout, warnings) = parse(text);
+            assert_eq!(layout, PlotLayout::default());
+            assert!(warnings.is_empty());
+        }
+        let default = PlotLayout::default();
+        assert_eq!(default.panels.len(), Panel::ALL.len() - 1);
+        assert!(!names(&default).contains(&"slowdown_histogram"));
+        let (opted_in, _) = parse("[plots.slowdown_histogram]");
+        assert_eq!(names(&opted_in), ["slowdown_histogram"]);
+    }
+
+    #[test]
+    fn unknown_panels_and_options_a_panel_cannot_apply_are_skipped_with_warnings() {
+        let (layout, warnings) = parse(
+            r##"
+            [plots.queue_lenght]
+            [plots.queue_heatmap]
+            color = "#ff0000"
+            line_width = 3.0
+            [plots.wait_histogram]
+            log_scale = true
+            "##,
+        );
+        assert_eq!(names(&layout), ["queue_heatmap", "wait_histogram"]);
+        assert_eq!(layout.style(Panel::QueueHeatmap), PanelStyle::default());
+        assert!(!layout.style(Panel::WaitHistogram).log_scale);
+        assert_eq!(warnings.len(), 4, "{:?}", warnings);
+        assert!(warnings[0].starts_with("unknown plot 'queue_lenght' skipped"));
+        assert_eq!(
+            warnings[1],
+            "plots.queue_heatmap: color is ignored by this plot"
+        );
+
+        // Only unknown names fall back to the default layout
+        let (layout, warnings) = parse("[plots.nothing]");
+        assert_eq!(layout, PlotLayout::default());
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn malformed_values_are_errors() {
+        for (text, error) in [
+            (
+                "[plots.utilization]\ncolor = \"red\"",
+                "plots.utilization: color 'red'",
+            ),
+            (
+                "[plots.utilization]\ny_min = 2.0\ny_max = 1.0",
+                "plots.utilization: y_min must be below",
+            ),
+            (
+                "[plots.utilization]\nline_width = 0.0",
+                "plots.utilization: line_width must be",
+            ),
+            (
+                "[plots.utilization]\nlog_scale = true\ny_min = 0.0",
+                "plots.utilization: y_min and y_max must be positive",
+            ),
+            (
+                "[plots.utilization]\nopacity = 0.5",
+                "plots.utilization: unknown field `opacity`",
+            ),
+            ("[plots]\nutilization = 3", "plots.utilization: "),
+        ] {
+            let message = PlotLayout::parse(text).unwrap_err();
+            assert!(message.starts_with(error), "{:?} gave {:?}", text, message);
+        }
+    }
+}
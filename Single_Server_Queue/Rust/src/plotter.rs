@@ -1,20 +1,389 @@
-use crate::time_series::SimulationTimeSeries;
+use crate::annotations::{Annotation, AnnotationCategory};
+use crate::plot_config::{Panel, PanelStyle, PlotLayout};
+use crate::sample_file::{Sample, SampleFile, replace_sampled_series};
+use crate::session::{
+    DEFAULT_HISTOGRAM_BUCKETS, DEFAULT_OVERLAY_METRICS, PanelView, Session, SessionRun, ViewState,
+};
+use crate::theory::mm1_wait_cdf;
+use crate::time_series::{
+    DEFAULT_MAX_SAMPLES, EnsembleSeries, Histogram, Normalization, NormalizedSeries,
+    SimulationTimeSeries, TimeSeries, derivative, merge_bins, normalize,
+};
+use crate::wait_distribution::{QQ_POINTS, WaitDistribution, mm1_qq_points};
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
+use egui_plot::{
+    Bar, BarChart, HLine, Line, Plot, PlotPoint, PlotPoints, Points, Polygon, Text, VLine,
+};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 pub struct InteractivePlotViewer {
-    time_series: SimulationTimeSeries,
+    runs: Vec<RunSeries>,
+    markers: TimeMarkers,
+    ensemble: Option<EnsembleSeries>,
+    show_ensemble_bands: bool,
+    show_wait_ci: bool,
+    /// Whether the wait histogram and ECDF panels draw the M/M/1 distribution over the runs
+    show_wait_theory: bool,
+    stack_servers: bool,
+    server_limit: Option<usize>,
+    steady_state_distribution: Option<Vec<f64>>,
+    theoretical_wait_cv: Option<f64>,
+    /// λ and μ of the M/M/1 queue whose wait distribution the runs follow
+    theoretical_wait: Option<(f64, f64)>,
+    distribution_snapshot: usize,
+    /// Customers per bar of the distribution panel
+    distribution_bucket_width: usize,
+    distribution_playing: bool,
+    last_distribution_step: f64,
+    throughput_view: ThroughputView,
+    throughput_window: Option<f64>,
+    /// Whether the mean wait and utilization panels show their statistics-window values
+    windowed_statistics: bool,
+    statistics_window: Option<f64>,
+    /// Whether the queue length and customers in system panels show their interval averages
+    interval_averages: bool,
+    overlay: OverlaySelection,
+    /// Bars the wait histogram is merged into, at most
+    wait_histogram_buckets: usize,
+    /// Bars the slowdown histogram is merged into, at most
+    slowdown_histogram_buckets: usize,
     plot_states: PlotStates,
+    /// Panels shown, in order, and how each is drawn
+    layout: PlotLayout,
+    /// Observed-vs-theoretical table of the runs in markdown, shown under the title
+    report: String,
+    /// Outcome of the last window export or session save or open, shown under the title
+    export_status: Option<String>,
+    /// Session file operation waiting for its path to be confirmed
+    session_action: Option<SessionAction>,
+    session_path: String,
+}
+
+/// What the session window does with the path once confirmed
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SessionAction {
+    Save,
+    Open,
+}
+
+/// File offered by the session window until another is entered
+const DEFAULT_SESSION_FILE: &str = "session.msgpack";
+
+/// Which throughput the throughput panel shows
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ThroughputView {
+    /// Departures per time unit since the start, which settles down as the run goes on
+    Cumulative,
+    /// Departures per time unit over the most recent window, which shows fluctuations
+    Windowed,
+}
+
+/// Seconds each P_n snapshot stays on screen while the convergence animation plays
+const DISTRIBUTION_FRAME_SECONDS: f64 = 0.1;
+
+/// Largest number of sample intervals a derivative can be smoothed over
+const MAX_DERIVATIVE_STRIDE: usize = 100;
+
+/// Upper bound on polygons per quantile band; longer series are decimated
+const MAX_BAND_SEGMENTS: usize = 500;
+
+/// Columns the queue-length heatmap splits the visible time range into
+const HEATMAP_COLUMNS: usize = 150;
+
+/// Most bars the slider of a histogram panel offers
+const MAX_HISTOGRAM_BUCKETS: usize = 200;
+
+/// Points the theoretical wait CDF is drawn through
+const THEORY_CURVE_POINTS: usize = 200;
+
+/// Pointer distance in pixels within which an annotation line shows its label
+const ANNOTATION_HOVER_PIXELS: f32 = 6.0;
+
+/// Everything marked on the time axis of every plot: the warm-up period and the annotations
+struct TimeMarkers {
+    warm_up_time: Option<f64>,
+    annotations: Vec<Annotation>,
+    /// Whether each of [`AnnotationCategory::ALL`] is drawn, toggled in the header
+    shown: [bool; AnnotationCategory::ALL.len()],
+}
+
+impl TimeMarkers {
+    fn new(warm_up_time: Option<f64>) -> Self {
+        Self {
+            warm_up_time,
+            annotations: Vec::new(),
+            shown: [true; AnnotationCategory::ALL.len()],
+        }
+    }
+
+    fn is_shown(&self, category: AnnotationCategory) -> bool {
+        self.shown[category as usize]
+    }
+
+    /// One checkbox per category that occurs, to hide its lines on every plot
+    fn category_filter(&mut self, ui: &mut egui::Ui) {
+        ui.label("Annotations:");
+        for category in AnnotationCategory::ALL {
+            if self.annotations.iter().any(|a| a.category == category) {
+                let text = egui::RichText::new(category.name()).color(annotation_color(category));
+                ui.checkbox(&mut self.shown[category as usize], text);
+            }
+        }
+    }
+
+    /// Shade the warm-up between `y_min` and `y_max` and draw a vertical line per shown
+    /// annotation, labelled while the pointer is near it and parameter changes always
+    ///
+    /// The lines are named after their category, so the plot legend lists each
+    /// category once and can hide it on that plot alone.
+    fn draw(&self, plot_ui: &mut egui_plot::PlotUi, y_min: f64, y_max: f64, theme: &ThemeColors) {
+        if let Some(warm_up) = self.warm_up_time {
+            // The simulation annotates the warm-up end itself; one line is enough
+            let annotated = self.annotations.iter().any(|annotation| {
+                annotation.category == AnnotationCategory::Phase && annotation.time == warm_up
+            });
+            draw_warm_up(plot_ui, warm_up, y_min, y_max, !annotated, theme);
+        }
+
+        let pointer_x = plot_ui
+            .response()
+            .hovered()
+            .then(|| plot_ui.pointer_coordinate())
+            .flatten()
+            .map(|pointer| plot_ui.screen_from_plot(pointer).x);
+        let top = plot_ui.plot_bounds().max()[1];
+
+        for annotation in &self.annotations {
+            if !self.is_shown(annotation.category) {
+                continue;
+            }
+            let color = annotation_color(annotation.category);
+            plot_ui.vline(
+                VLine::new(annotation.category.name(), annotation.time)
+                    .stroke(egui::Stroke::new(1.0, color)),
+            );
+
+            let x = plot_ui
+                .screen_from_plot(PlotPoint::new(annotation.time, top))
+                .x;
+            let hovered =
+                pointer_x.is_some_and(|pointer_x| (pointer_x - x).abs() <= ANNOTATION_HOVER_PIXELS);
+            // Parameter changes are few and explain what follows them, so they stay labelled
+            if hovered || annotation.category == AnnotationCategory::Change {
+                let text = if annotation.category == AnnotationCategory::Change {
+                    format!("{} at t={:.0}", annotation.label, annotation.time)
+                } else {
+                    format!("{} (t = {:.0})", annotation.label, annotation.time)
+                };
+                plot_ui.text(
+                    Text::new(
+                        annotation.category.name(),
+                        PlotPoint::new(annotation.time, top),
+                        text,
+                    )
+                    .color(theme.annotation_text(color))
+                    .anchor(egui::Align2::LEFT_TOP),
+                );
+            }
+        }
+    }
+}
+
+/// Hover text of a plot point from the name of its series and the point
+type HoverLabel<'a> = dyn Fn(&str, &PlotPoint) -> String + 'a;
+
+/// Metrics drawn on the overlay panel and how they are put on one scale
+struct OverlaySelection {
+    /// Whether each of [`SimulationTimeSeries::METRIC_NAMES`] is drawn, toggled above the panel
+    shown: [bool; SimulationTimeSeries::METRIC_NAMES.len()],
+    normalization: Normalization,
+}
+
+impl OverlaySelection {
+    /// The metrics called `names` shown, unknown names ignored
+    fn new<S: AsRef<str>>(names: &[S]) -> Self {
+        Self {
+            shown: SimulationTimeSeries::METRIC_NAMES
+                .map(|metric| names.iter().any(|name| name.as_ref() == metric)),
+            normalization: Normalization::MinMax,
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        SimulationTimeSeries::METRIC_NAMES
+            .into_iter()
+            .zip(self.shown)
+            .filter(|(_, shown)| *shown)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// One checkbox per metric some run sampled and the choice of normalization;
+    /// true if the normalization changed
+    fn controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        sampled: &[bool; SimulationTimeSeries::METRIC_NAMES.len()],
+    ) -> bool {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Metrics:");
+            for (k, name) in SimulationTimeSeries::METRIC_NAMES.into_iter().enumerate() {
+                if sampled[k] {
+                    ui.checkbox(&mut self.shown[k], name);
+                }
+            }
+        });
+        let before = self.normalization;
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.normalization,
+                Normalization::MinMax,
+                "Min-max [0, 1]",
+            );
+            ui.radio_value(&mut self.normalization, Normalization::ZScore, "Z-score");
+        });
+        self.normalization != before
+    }
+}
+
+/// Legend name of a metric taken over the statistics window, naming its length when known
+fn window_legend(metric: &str, window: Option<f64>) -> String {
+    match window {
+        Some(window) => format!("{} over the last {} time units", metric, window),
+        None => format!("Windowed {}", metric.to_lowercase()),
+    }
+}
+
+/// Line color of an annotation category, readable on light and dark backgrounds
+fn annotation_color(category: AnnotationCategory) -> egui::Color32 {
+    match category {
+        AnnotationCategory::Phase => egui::Color32::from_rgb(90, 140, 220),
+        AnnotationCategory::Change => egui::Color32::from_rgb(20, 160, 170),
+        AnnotationCategory::Failure => egui::Color32::from_rgb(220, 50, 50),
+        AnnotationCategory::Repair => egui::Color32::from_rgb(40, 170, 90),
+        AnnotationCategory::Threshold => egui::Color32::from_rgb(230, 150, 20),
+        AnnotationCategory::Custom => egui::Color32::from_rgb(170, 90, 200),
+    }
+}
+
+/// Samples of one labelled run together with the series derived from them
+struct RunSeries {
+    label: String,
+    time_series: SimulationTimeSeries,
+    arrival_rate: Vec<(f64, f64)>,
+    departure_rate: Vec<(f64, f64)>,
+    /// The file the run's samples were streamed to, if they were
+    streamed: Option<StreamedSamples>,
+}
+
+impl RunSeries {
+    /// Show `samples` in the series of the sample file's metrics
+    fn show_samples(&mut self, samples: &[Sample]) {
+        replace_sampled_series(&mut self.time_series, samples);
+        self.departure_rate = self.time_series.customers_served.differenced(|v| v as f64);
+    }
+
+    /// The cumulative mean wait, or the one over the statistics window
+    fn mean_wait(&self, windowed: bool) -> &TimeSeries<f64> {
+        if windowed {
+            &self.time_series.windowed_mean_wait
+        } else {
+            &self.time_series.mean_wait_time
+        }
+    }
+}
+
+/// A run whose samples were streamed to a chunked sample file, see `--stream-chunks`
+///
+/// Only the recent samples the writer kept are held in memory. Once a panel is
+/// zoomed or panned out before the first of them, the range shown, and half of
+/// it again on either side, is read from the file thinned to at most
+/// [`DEFAULT_MAX_SAMPLES`]; it is read again when the view leaves that range or
+/// shrinks to a quarter of it, so zooming in brings back the resolution.
+struct StreamedSamples {
+    file: SampleFile<BufReader<File>>,
+    tail: Vec<Sample>,
+    /// Time range read from the file and shown instead of the tail
+    loaded: Option<(f64, f64)>,
+}
+
+impl StreamedSamples {
+    /// The samples to show for the view `(start, end)`, `None` if those shown will do
+    fn samples_for(&mut self, start: f64, end: f64) -> io::Result<Option<Vec<Sample>>> {
+        let tail_start = self
+            .tail
+            .first()
+            .map_or(f64::INFINITY, |sample| sample.time);
+        if start >= tail_start {
+            return Ok(self.loaded.take().map(|_| self.tail.clone()));
+        }
+        if let Some((low, high)) = self.loaded
+            && low <= start
+            && end <= high
+            && 4.0 * (end - start) >= high - low
+        {
+            return Ok(None);
+        }
+        let margin = (end - start) / 2.0;
+        let (low, high) = (start - margin, end + margin);
+        let samples = self.file.thinned_range(low, high, DEFAULT_MAX_SAMPLES)?;
+        self.loaded = Some((low, high));
+        Ok(Some(samples))
+    }
+}
+
+/// Line colors for runs after the first when several runs are compared
+const RUN_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(230, 120, 20),
+    egui::Color32::from_rgb(40, 160, 60),
+    egui::Color32::from_rgb(150, 60, 190),
+    egui::Color32::from_rgb(200, 40, 120),
+];
+
+/// Layer colors of the per-server utilization, repeated for more servers
+const SERVER_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(46, 139, 87),
+    egui::Color32::from_rgb(70, 130, 180),
+    egui::Color32::from_rgb(218, 165, 32),
+    egui::Color32::from_rgb(199, 21, 133),
+    egui::Color32::from_rgb(106, 90, 205),
+    egui::Color32::from_rgb(205, 92, 92),
+];
+
+/// How a series is drawn
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlotStyle {
+    /// Straight segments between samples
+    Line,
+    /// Each value held constant over the interval ending at its sample time
+    Step,
+    /// Each value held from its sample time until the next sample (counting processes)
+    Staircase,
 }
 
 #[derive(Clone)]
 struct PlotStates {
     queue: PlotState,
     wait: PlotState,
+    wait_cv: PlotState,
     util: PlotState,
     customers: PlotState,
     customers_in_system: PlotState,
     throughput: PlotState,
+    arrivals: PlotState,
+    departures: PlotState,
+    staircase: PlotState,
+    ensemble: PlotState,
+    busy_servers: PlotState,
+    distribution: PlotState,
+    heatmap: PlotState,
+    wait_histogram: PlotState,
+    slowdown_histogram: PlotState,
+    wait_ecdf: PlotState,
+    wait_qq: PlotState,
+    overlay: PlotState,
 }
 
 impl PlotStates {
@@ -22,18 +391,109 @@ impl PlotStates {
         Self {
             queue: PlotState::new(),
             wait: PlotState::new(),
+            wait_cv: PlotState::new(),
             util: PlotState::new(),
             customers: PlotState::new(),
             customers_in_system: PlotState::new(),
             throughput: PlotState::new(),
+            arrivals: PlotState::new(),
+            departures: PlotState::new(),
+            staircase: PlotState::new(),
+            ensemble: PlotState::new(),
+            busy_servers: PlotState::new(),
+            // The x axis counts customers, so there is no time window to export
+            distribution: PlotState {
+                exports_window: false,
+                ..PlotState::new()
+            },
+            heatmap: PlotState::new(),
+            // The x axes are the wait and the slowdown, not time
+            wait_histogram: PlotState {
+                exports_window: false,
+                ..PlotState::new()
+            },
+            slowdown_histogram: PlotState {
+                exports_window: false,
+                ..PlotState::new()
+            },
+            wait_ecdf: PlotState {
+                exports_window: false,
+                ..PlotState::new()
+            },
+            wait_qq: PlotState {
+                exports_window: false,
+                ..PlotState::new()
+            },
+            overlay: PlotState::new(),
         }
     }
+
+    /// Every panel with the name its view is saved under in a session
+    fn named_mut(&mut self) -> [(&'static str, &mut PlotState); 19] {
+        [
+            ("queue", &mut self.queue),
+            ("wait", &mut self.wait),
+            ("wait_cv", &mut self.wait_cv),
+            ("util", &mut self.util),
+            ("customers", &mut self.customers),
+            ("customers_in_system", &mut self.customers_in_system),
+            ("throughput", &mut self.throughput),
+            ("arrivals", &mut self.arrivals),
+            ("departures", &mut self.departures),
+            ("staircase", &mut self.staircase),
+            ("ensemble", &mut self.ensemble),
+            ("busy_servers", &mut self.busy_servers),
+            ("distribution", &mut self.distribution),
+            ("heatmap", &mut self.heatmap),
+            ("wait_histogram", &mut self.wait_histogram),
+            ("slowdown_histogram", &mut self.slowdown_histogram),
+            ("wait_ecdf", &mut self.wait_ecdf),
+            ("wait_qq", &mut self.wait_qq),
+            ("overlay", &mut self.overlay),
+        ]
+    }
+
+    /// Time range shown by the panels of the metrics a sample file holds, joined
+    /// over those drawn so far; `None` before the first frame
+    fn sampled_view(&self) -> Option<(f64, f64)> {
+        [
+            &self.queue,
+            &self.wait,
+            &self.util,
+            &self.customers,
+            &self.customers_in_system,
+            &self.throughput,
+            &self.departures,
+            &self.busy_servers,
+        ]
+        .iter()
+        .filter_map(|state| state.visible_bounds)
+        .map(|bounds| (bounds.min()[0], bounds.max()[0]))
+        .reduce(|(start, end), (min, max)| (start.min(min), end.max(max)))
+    }
+
+    /// The time window of the first panel whose export button was clicked
+    fn take_export_request(&mut self) -> Option<(f64, f64)> {
+        self.named_mut()
+            .into_iter()
+            .find_map(|(_, state)| state.export_request.take())
+    }
 }
 
 #[derive(Clone)]
 struct PlotState {
     reset_bounds: bool,
     target_bounds: Option<egui_plot::PlotBounds>,
+    /// Plot the rate of change of the metric instead of the metric itself
+    derivative: bool,
+    /// Sample intervals each derivative point is taken over
+    derivative_stride: usize,
+    /// Whether the x axis is time, so the panel offers to export the visible window
+    exports_window: bool,
+    /// Visible `(start, end)` time range to export, set by the panel's button
+    export_request: Option<(f64, f64)>,
+    /// Range shown in the last frame, `None` until the panel has been drawn
+    visible_bounds: Option<egui_plot::PlotBounds>,
 }
 
 impl PlotState {
@@ -41,6 +501,53 @@ impl PlotState {
         Self {
             reset_bounds: false,
             target_bounds: None,
+            derivative: false,
+            derivative_stride: 1,
+            exports_window: true,
+            export_request: None,
+            visible_bounds: None,
+        }
+    }
+
+    fn view(&self) -> PanelView {
+        PanelView {
+            bounds: self
+                .visible_bounds
+                .map(|bounds| (bounds.min(), bounds.max())),
+            derivative: self.derivative,
+            derivative_stride: self.derivative_stride,
+        }
+    }
+
+    /// Show the range and derivative setting of a saved view from the next frame on
+    fn restore(&mut self, view: PanelView) {
+        self.derivative = view.derivative;
+        self.derivative_stride = view.derivative_stride.clamp(1, MAX_DERIVATIVE_STRIDE);
+        match view.bounds {
+            Some((min, max)) => {
+                self.reset_bounds = false;
+                self.target_bounds = Some(egui_plot::PlotBounds::from_min_max(min, max));
+            }
+            None => self.reset(),
+        }
+    }
+
+    /// "Show derivative" toggle with its smoothing stride; the axes are rescaled
+    /// on toggling since the derivative lives on a different scale
+    fn derivative_controls(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .checkbox(&mut self.derivative, "Show derivative")
+            .changed()
+        {
+            self.reset();
+        }
+        if self.derivative {
+            ui.add(
+                egui::DragValue::new(&mut self.derivative_stride)
+                    .range(1..=MAX_DERIVATIVE_STRIDE)
+                    .prefix("over ")
+                    .suffix(" samples"),
+            );
         }
     }
 
@@ -96,6 +603,11 @@ struct ThemeColors {
     button_fill: egui::Color32,
     button_stroke: egui::Color32,
     text: egui::Color32,
+    warm_up_fill: egui::Color32,
+    warm_up_line: egui::Color32,
+    /// Heatmap colors for no, half and all of the time, starting from the frame
+    /// fill so rarely visited cells fade into the background
+    heatmap: [egui::Color32; 3],
 }
 
 impl ThemeColors {
@@ -109,6 +621,13 @@ impl ThemeColors {
                 button_fill: egui::Color32::from_rgba_unmultiplied(50, 50, 50, 240),
                 button_stroke: egui::Color32::from_rgb(80, 80, 80),
                 text: egui::Color32::from_rgb(220, 220, 220),
+                warm_up_fill: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 20),
+                warm_up_line: egui::Color32::from_rgb(160, 160, 160),
+                heatmap: [
+                    egui::Color32::from_rgb(40, 40, 40),
+                    egui::Color32::from_rgb(190, 70, 50),
+                    egui::Color32::from_rgb(255, 235, 130),
+                ],
             }
         } else {
             Self {
@@ -117,17 +636,264 @@ impl ThemeColors {
                 button_fill: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 240),
                 button_stroke: egui::Color32::from_rgb(180, 180, 180),
                 text: egui::Color32::from_rgb(40, 40, 40),
+                warm_up_fill: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 20),
+                warm_up_line: egui::Color32::from_rgb(110, 110, 110),
+                heatmap: [
+                    egui::Color32::WHITE,
+                    egui::Color32::from_rgb(70, 130, 180),
+                    egui::Color32::from_rgb(10, 30, 90),
+                ],
             }
         }
     }
+
+    /// Label color of an annotation drawn in `line`: the line's hue pulled towards the
+    /// text color, so light hues stay readable on white and dark ones on black
+    fn annotation_text(&self, line: egui::Color32) -> egui::Color32 {
+        line.lerp_to_gamma(self.text, 0.45)
+    }
+
+    /// Heatmap color of a cell where `fraction` of the column's time was spent
+    fn heat_color(&self, fraction: f64) -> egui::Color32 {
+        let [low, mid, high] = self.heatmap;
+        let t = fraction.clamp(0.0, 1.0) as f32 * 2.0;
+        if t <= 1.0 {
+            low.lerp_to_gamma(mid, t)
+        } else {
+            mid.lerp_to_gamma(high, t - 1.0)
+        }
+    }
 }
 
 impl InteractivePlotViewer {
-    pub fn new(time_series: SimulationTimeSeries) -> Self {
+    /// Each run is a `(label, samples)` pair; with more than one run every panel
+    /// overlays one line per run and the legend names them.
+    ///
+    /// `warm_up_time` marks the transient excluded from the statistics; it is drawn
+    /// as a shaded band with a vertical rule at its end on every plot.
+    pub fn new(runs: Vec<(String, SimulationTimeSeries)>, warm_up_time: Option<f64>) -> Self {
+        let runs = runs
+            .into_iter()
+            .map(|(label, time_series)| RunSeries {
+                arrival_rate: time_series.customers_arrived.differenced(|v| v as f64),
+                departure_rate: time_series.customers_served.differenced(|v| v as f64),
+                label,
+                time_series,
+                streamed: None,
+            })
+            .collect();
+
         Self {
-            time_series,
+            runs,
+            markers: TimeMarkers::new(warm_up_time),
+            ensemble: None,
+            show_ensemble_bands: true,
+            show_wait_ci: true,
+            show_wait_theory: true,
+            stack_servers: true,
+            server_limit: None,
+            steady_state_distribution: None,
+            theoretical_wait_cv: None,
+            theoretical_wait: None,
+            distribution_snapshot: 0,
+            distribution_bucket_width: 1,
+            distribution_playing: false,
+            last_distribution_step: 0.0,
+            throughput_view: ThroughputView::Cumulative,
+            throughput_window: None,
+            windowed_statistics: false,
+            statistics_window: None,
+            interval_averages: false,
+            overlay: OverlaySelection::new(&DEFAULT_OVERLAY_METRICS),
+            wait_histogram_buckets: DEFAULT_HISTOGRAM_BUCKETS,
+            slowdown_histogram_buckets: DEFAULT_HISTOGRAM_BUCKETS,
             plot_states: PlotStates::new(),
+            layout: PlotLayout::default(),
+            report: String::new(),
+            export_status: None,
+            session_action: None,
+            session_path: DEFAULT_SESSION_FILE.to_string(),
+        }
+    }
+
+    /// Reopen a saved session as it was left, see [`Self::session`]
+    pub fn from_session(session: Session) -> Self {
+        let runs = session
+            .runs
+            .into_iter()
+            .map(|run| (run.label, run.time_series))
+            .collect();
+        let mut viewer = Self::new(runs, session.warm_up_time)
+            .with_annotations(session.annotations)
+            .with_report(session.report);
+        viewer.ensemble = session.ensemble;
+        viewer.server_limit = session.server_limit;
+        viewer.steady_state_distribution = session.steady_state_distribution;
+        viewer.theoretical_wait_cv = session.theoretical_wait_cv;
+        viewer.theoretical_wait = session.theoretical_wait;
+        viewer.throughput_window = session.throughput_window;
+        viewer.statistics_window = session.statistics_window;
+
+        let view = session.view;
+        for category in view.hidden_annotations {
+            viewer.markers.shown[category as usize] = false;
+        }
+        viewer.show_ensemble_bands = view.show_ensemble_bands;
+        viewer.show_wait_ci = view.show_wait_ci;
+        viewer.show_wait_theory = view.show_wait_theory;
+        viewer.stack_servers = view.stack_servers;
+        viewer.throughput_view = if view.windowed_throughput {
+            ThroughputView::Windowed
+        } else {
+            ThroughputView::Cumulative
+        };
+        viewer.windowed_statistics = view.windowed_statistics;
+        viewer.interval_averages = view.interval_averages;
+        viewer.overlay = OverlaySelection::new(&view.overlay_metrics);
+        viewer.overlay.normalization = view.overlay_normalization;
+        viewer.distribution_snapshot = view.distribution_snapshot;
+        viewer.distribution_bucket_width = view.distribution_bucket_width.max(1);
+        viewer.wait_histogram_buckets = view.wait_histogram_buckets.max(1);
+        viewer.slowdown_histogram_buckets = view.slowdown_histogram_buckets.max(1);
+        for (name, state) in viewer.plot_states.named_mut() {
+            if let Some(panel) = view.panels.get(name) {
+                state.restore(*panel);
+            }
+        }
+        viewer
+    }
+
+    /// The runs, report and annotations together with the current zoom of every panel and toggles
+    pub fn session(&self) -> Session {
+        let panels = self
+            .plot_states
+            .clone()
+            .named_mut()
+            .into_iter()
+            .map(|(name, state)| (name.to_string(), state.view()))
+            .collect();
+        Session {
+            runs: self
+                .runs
+                .iter()
+                .map(|run| SessionRun {
+                    label: run.label.clone(),
+                    time_series: run.time_series.clone(),
+                })
+                .collect(),
+            report: self.report.clone(),
+            annotations: self.markers.annotations.clone(),
+            warm_up_time: self.markers.warm_up_time,
+            ensemble: self.ensemble.clone(),
+            server_limit: self.server_limit,
+            steady_state_distribution: self.steady_state_distribution.clone(),
+            theoretical_wait_cv: self.theoretical_wait_cv,
+            theoretical_wait: self.theoretical_wait,
+            throughput_window: self.throughput_window,
+            statistics_window: self.statistics_window,
+            view: ViewState {
+                panels,
+                hidden_annotations: AnnotationCategory::ALL
+                    .into_iter()
+                    .filter(|&category| !self.markers.is_shown(category))
+                    .collect(),
+                show_ensemble_bands: self.show_ensemble_bands,
+                show_wait_ci: self.show_wait_ci,
+                show_wait_theory: self.show_wait_theory,
+                stack_servers: self.stack_servers,
+                windowed_throughput: self.throughput_view == ThroughputView::Windowed,
+                windowed_statistics: self.windowed_statistics,
+                interval_averages: self.interval_averages,
+                distribution_snapshot: self.distribution_snapshot,
+                distribution_bucket_width: self.distribution_bucket_width,
+                wait_histogram_buckets: self.wait_histogram_buckets,
+                slowdown_histogram_buckets: self.slowdown_histogram_buckets,
+                overlay_metrics: self.overlay.names(),
+                overlay_normalization: self.overlay.normalization,
+            },
+            ..Session::default()
+        }
+    }
+
+    /// Mark `annotations` on the time axis of every plot, with a per-category filter
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.markers.annotations = annotations;
+        self
+    }
+
+    /// Show the run labelled `label` from the sample file its samples were streamed
+    /// to, starting on the `tail` of recent samples and reading older ranges from
+    /// `file` as they come into view, see [`StreamedSamples`]
+    pub fn with_sample_file(
+        mut self,
+        label: &str,
+        file: SampleFile<BufReader<File>>,
+        tail: Vec<Sample>,
+    ) -> Self {
+        if let Some(run) = self.runs.iter_mut().find(|run| run.label == label) {
+            run.show_samples(&tail);
+            run.streamed = Some(StreamedSamples {
+                file,
+                tail,
+                loaded: None,
+            });
         }
+        self
+    }
+
+    /// Add a panel showing queue length quantiles across replications
+    pub fn with_ensemble(mut self, ensemble: EnsembleSeries) -> Self {
+        self.ensemble = Some(ensemble);
+        self
+    }
+
+    /// Add a busy-servers panel with a reference line at the `servers` capacity
+    pub fn with_server_limit(mut self, servers: usize) -> Self {
+        self.server_limit = Some(servers);
+        self
+    }
+
+    /// Overlay the theoretical steady-state P_n on the number-in-system distribution panel
+    pub fn with_steady_state_distribution(mut self, distribution: Vec<f64>) -> Self {
+        self.steady_state_distribution = Some(distribution);
+        self
+    }
+
+    /// Draw the steady-state coefficient of variation of the wait on the wait CV panel
+    pub fn with_theoretical_wait_cv(mut self, cv: f64) -> Self {
+        self.theoretical_wait_cv = Some(cv);
+        self
+    }
+
+    /// Overlay the wait distribution of an M/M/1 queue with rates `lambda` and `mu`
+    /// on the wait ECDF panel and plot the waits' quantiles against its own
+    pub fn with_theoretical_wait(mut self, lambda: f64, mu: f64) -> Self {
+        self.theoretical_wait = Some((lambda, mu));
+        self
+    }
+
+    /// Name the window length in the legend of the windowed throughput
+    pub fn with_throughput_window(mut self, window: f64) -> Self {
+        self.throughput_window = Some(window);
+        self
+    }
+
+    /// Name the window length in the legends of the windowed mean wait and utilization
+    pub fn with_statistics_window(mut self, window: f64) -> Self {
+        self.statistics_window = Some(window);
+        self
+    }
+
+    /// Show the observed-vs-theoretical `report`, a markdown table, in a collapsible section
+    pub fn with_report(mut self, report: String) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Show the panels of `layout` in its order and style instead of all of them
+    pub fn with_plot_layout(mut self, layout: PlotLayout) -> Self {
+        self.layout = layout;
+        self
     }
 
     pub fn launch(self) -> Result<(), eframe::Error> {
@@ -145,24 +911,308 @@ impl InteractivePlotViewer {
         )
     }
 
+    /// Read the range in view from the sample file of every streamed run that needs it
+    ///
+    /// A read error is shown in the header and leaves the run on the samples it has.
+    fn load_visible_samples(&mut self) {
+        let Some((start, end)) = self.plot_states.sampled_view() else {
+            return;
+        };
+        for run in &mut self.runs {
+            let Some(streamed) = &mut run.streamed else {
+                continue;
+            };
+            match streamed.samples_for(start, end) {
+                Ok(Some(samples)) => run.show_samples(&samples),
+                Ok(None) => {}
+                Err(e) => {
+                    self.export_status =
+                        Some(format!("Error reading the samples of {}: {}", run.label, e));
+                    run.streamed = None;
+                }
+            }
+        }
+    }
+
+    /// Write the samples of every run between `start` and `end` to a CSV file named
+    /// after the window, and note the outcome for the header
+    fn export_window(&mut self, start: f64, end: f64) {
+        let path = PathBuf::from(format!("window_{:.0}_{:.0}.csv", start, end));
+        self.export_status = Some(match write_window_csv(&path, &self.runs, start, end) {
+            Ok(0) => format!("No samples between t = {:.2} and {:.2}", start, end),
+            Ok(rows) => format!("Wrote {} samples to {}", rows, path.display()),
+            Err(e) => format!("Error writing {}: {}", path.display(), e),
+        });
+    }
+
+    /// File menu above the panels, opening the session window
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Save Session…").clicked() {
+                        self.session_action = Some(SessionAction::Save);
+                        ui.close();
+                    }
+                    if ui.button("Open Session…").clicked() {
+                        self.session_action = Some(SessionAction::Open);
+                        ui.close();
+                    }
+                });
+            });
+        });
+    }
+
+    /// Window asking for the file to save the session to or open it from
+    fn session_window(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.session_action else {
+            return;
+        };
+        let title = match action {
+            SessionAction::Save => "Save Session",
+            SessionAction::Open => "Open Session",
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    let response = ui.text_edit_singleline(&mut self.session_path);
+                    confirmed |=
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                });
+                ui.horizontal(|ui| {
+                    confirmed |= ui.button(title).clicked();
+                    cancelled |= ui.button("Cancel").clicked();
+                });
+            });
+
+        if cancelled {
+            self.session_action = None;
+        } else if confirmed {
+            self.session_action = None;
+            let path = PathBuf::from(self.session_path.trim());
+            match action {
+                SessionAction::Save => self.save_session(&path),
+                SessionAction::Open => self.open_session(&path),
+            }
+        }
+    }
+
+    fn save_session(&mut self, path: &Path) {
+        self.export_status = Some(match self.session().save(path) {
+            Ok(()) => format!("Saved the session to {}", path.display()),
+            Err(e) => format!("Error writing {}: {}", path.display(), e),
+        });
+    }
+
+    /// Replace everything shown by the session in `path`; on failure nothing changes
+    fn open_session(&mut self, path: &Path) {
+        match Session::load(path) {
+            Ok(session) => {
+                let session_path = std::mem::take(&mut self.session_path);
+                *self = Self::from_session(session);
+                self.session_path = session_path;
+                self.export_status = Some(format!("Opened the session in {}", path.display()));
+            }
+            Err(e) => {
+                self.export_status = Some(format!("Error reading {}: {}", path.display(), e));
+            }
+        }
+    }
+
     /// Generic plot creation function that handles all data types
+    ///
+    /// With a `quantity` name the panel gets a derivative toggle, which swaps the
+    /// series for their rate of change labelled "d(quantity)/dt". The configured
+    /// title, y range and log scale apply to the metric, not its rate of change.
+    #[allow(clippy::too_many_arguments)]
     fn create_plot<T, F>(
+        ui: &mut egui::Ui,
+        plot_id: &str,
+        legend_name: &str,
+        quantity: Option<&str>,
+        color: egui::Color32,
+        style: PlotStyle,
+        series: &[(&str, &[(f64, T)])],
+        markers: &TimeMarkers,
+        state: &mut PlotState,
+        look: &PanelStyle,
+        theme: &ThemeColors,
+        to_f64: F,
+    ) where
+        T: Copy,
+        F: Fn(T) -> f64,
+    {
+        let Some(quantity) = quantity else {
+            Self::draw_series(
+                ui,
+                plot_id,
+                legend_name,
+                color,
+                style,
+                series,
+                markers,
+                state,
+                look,
+                theme,
+                to_f64,
+            );
+            return;
+        };
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| state.derivative_controls(ui));
+            if !state.derivative {
+                Self::draw_series(
+                    ui,
+                    plot_id,
+                    legend_name,
+                    color,
+                    style,
+                    series,
+                    markers,
+                    state,
+                    look,
+                    theme,
+                    to_f64,
+                );
+                return;
+            }
+
+            let derived: Vec<(&str, Vec<(f64, f64)>)> = series
+                .iter()
+                .map(|(label, data)| (*label, derivative(data, state.derivative_stride, &to_f64)))
+                .collect();
+            let derived: Vec<(&str, &[(f64, f64)])> = derived
+                .iter()
+                .map(|(label, data)| (*label, data.as_slice()))
+                .collect();
+            let derived_look = PanelStyle {
+                title: None,
+                y_min: None,
+                y_max: None,
+                log_scale: false,
+                ..look.clone()
+            };
+            Self::draw_series(
+                ui,
+                plot_id,
+                &format!("d({})/dt", quantity),
+                color,
+                PlotStyle::Line,
+                &derived,
+                markers,
+                state,
+                &derived_look,
+                theme,
+                |v| v,
+            );
+        });
+    }
+
+    /// One line per series in a plot panel, drawn in the given style
+    ///
+    /// On a log scale the lines are drawn through the log10 of the values, leaving
+    /// out samples at or below zero, and the axis is labelled with the values.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_series<T, F>(
         ui: &mut egui::Ui,
         plot_id: &str,
         legend_name: &str,
         color: egui::Color32,
-        data: &[(f64, T)],
+        style: PlotStyle,
+        series: &[(&str, &[(f64, T)])],
+        markers: &TimeMarkers,
         state: &mut PlotState,
+        look: &PanelStyle,
         theme: &ThemeColors,
         to_f64: F,
     ) where
         T: Copy,
         F: Fn(T) -> f64,
     {
-        if data.is_empty() {
+        if series.iter().all(|(_, data)| data.is_empty()) {
             return;
         }
 
+        let legend_name = look.title.as_deref().unwrap_or(legend_name);
+        let color = main_color(look, color);
+        let to_y = |v: T| {
+            let y = to_f64(v);
+            if look.log_scale { y.log10() } else { y }
+        };
+        Self::show_plot_panel(ui, plot_id, state, look, theme, |plot_ui| {
+            let (y_min, y_max) = series
+                .iter()
+                .flat_map(|(_, data)| data.iter())
+                .map(|(_, v)| to_y(*v))
+                .filter(|y| y.is_finite())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+                    (lo.min(y), hi.max(y))
+                });
+            markers.draw(plot_ui, y_min, y_max, theme);
+
+            for (index, (label, data)) in series.iter().enumerate() {
+                let mut points: Vec<[f64; 2]> = match style {
+                    PlotStyle::Line => data.iter().map(|(t, v)| [*t, to_y(*v)]).collect(),
+                    PlotStyle::Step => step_points(data, &to_y),
+                    PlotStyle::Staircase => staircase_points(data, &to_y),
+                };
+                if look.log_scale {
+                    points.retain(|[_, y]| y.is_finite());
+                }
+                let (name, line_color) = if series.len() == 1 {
+                    (legend_name.to_string(), color)
+                } else if index == 0 {
+                    (format!("{} ({})", legend_name, label), color)
+                } else {
+                    (
+                        format!("{} ({})", legend_name, label),
+                        RUN_COLORS[(index - 1) % RUN_COLORS.len()],
+                    )
+                };
+                plot_ui.line(styled_line(
+                    Line::new(name, PlotPoints::from(points)).color(line_color),
+                    look,
+                ));
+            }
+        });
+    }
+
+    /// Framed plot panel with overlay zoom/reset controls; `draw` adds the plot items
+    ///
+    /// The configured y range of the panel is the one shown at first and after a
+    /// reset; on a log scale `draw` gives log10 values and the axis shows the values.
+    fn show_plot_panel<D>(
+        ui: &mut egui::Ui,
+        plot_id: &str,
+        state: &mut PlotState,
+        look: &PanelStyle,
+        theme: &ThemeColors,
+        draw: D,
+    ) where
+        D: FnOnce(&mut egui_plot::PlotUi),
+    {
+        Self::show_labelled_plot_panel(ui, plot_id, state, look, theme, None, draw);
+    }
+
+    /// [`Self::show_plot_panel`] with the hover label of a point given by `label`
+    /// from the name of the series and the point, when given
+    fn show_labelled_plot_panel<D>(
+        ui: &mut egui::Ui,
+        plot_id: &str,
+        state: &mut PlotState,
+        look: &PanelStyle,
+        theme: &ThemeColors,
+        label: Option<&HoverLabel<'_>>,
+        draw: D,
+    ) where
+        D: FnOnce(&mut egui_plot::PlotUi),
+    {
         egui::Frame::new()
             .fill(theme.frame_fill)
             .stroke(egui::Stroke::new(1.0, theme.frame_stroke))
@@ -175,6 +1225,7 @@ impl InteractivePlotViewer {
                     let mut zoom_in_clicked = false;
                     let mut zoom_out_clicked = false;
                     let mut reset_clicked = false;
+                    let mut export_clicked = false;
                     let available_width = ui.available_width();
 
                     // Create plot with overlay buttons
@@ -195,15 +1246,35 @@ impl InteractivePlotViewer {
                             if state.take_reset() {
                                 plot = plot.auto_bounds([true, true]);
                             }
+                            let to_axis = |y: f64| if look.log_scale { y.log10() } else { y };
+                            match (look.y_min.map(to_axis), look.y_max.map(to_axis)) {
+                                (Some(y_min), Some(y_max)) => {
+                                    plot = plot.default_y_bounds(y_min, y_max);
+                                }
+                                (Some(y), None) | (None, Some(y)) => plot = plot.include_y(y),
+                                (None, None) => {}
+                            }
+                            if let Some(label) = label {
+                                plot = plot.label_formatter(label);
+                            } else if look.log_scale {
+                                plot = plot
+                                    .y_axis_formatter(|mark, _| format_log_tick(mark.value))
+                                    .label_formatter(|name, point| {
+                                        let value = format_log_tick(point.y);
+                                        if name.is_empty() {
+                                            format!("x = {:.2}\ny = {}", point.x, value)
+                                        } else {
+                                            format!("{}\nx = {:.2}\ny = {}", name, point.x, value)
+                                        }
+                                    });
+                            }
 
                             let plot_response = plot.show(ui, |plot_ui| {
                                 if let Some(bounds) = state.take_target_bounds() {
                                     plot_ui.set_plot_bounds(bounds);
                                 }
 
-                                let points: PlotPoints =
-                                    data.iter().map(|(t, v)| [*t, to_f64(*v)]).collect();
-                                plot_ui.line(Line::new(legend_name, points).color(color));
+                                draw(plot_ui);
 
                                 plot_ui.plot_bounds()
                             });
@@ -212,10 +1283,13 @@ impl InteractivePlotViewer {
                         });
 
                     let (current_bounds, plot_rect) = plot_response.inner;
+                    state.visible_bounds = Some(current_bounds);
                     ui.allocate_space(egui::vec2(available_width, 260.0));
 
                     // Overlay control buttons
-                    let button_pos = egui::pos2(plot_rect.right() - 135.0, plot_rect.top() + 8.0);
+                    let controls_width = if state.exports_window { 180.0 } else { 135.0 };
+                    let button_pos =
+                        egui::pos2(plot_rect.right() - controls_width, plot_rect.top() + 8.0);
 
                     egui::Area::new(egui::Id::new(plot_id).with("_controls"))
                         .fixed_pos(button_pos)
@@ -247,6 +1321,16 @@ impl InteractivePlotViewer {
                                         {
                                             reset_clicked = true;
                                         }
+                                        if state.exports_window
+                                            && ui
+                                                .button(egui::RichText::new("CSV").size(13.0))
+                                                .on_hover_text(
+                                                    "Export the samples of the visible time range",
+                                                )
+                                                .clicked()
+                                        {
+                                            export_clicked = true;
+                                        }
                                     });
                                 });
                         });
@@ -264,99 +1348,1251 @@ impl InteractivePlotViewer {
                         state.reset();
                         ui.ctx().request_repaint();
                     }
+                    if export_clicked {
+                        state.export_request =
+                            Some((current_bounds.min()[0], current_bounds.max()[0]));
+                    }
                 });
             });
     }
 
+    fn plot_panel(&mut self, panel: Panel, ui: &mut egui::Ui, theme: &ThemeColors) {
+        match panel {
+            Panel::QueueLength => self.plot_queue_length(ui, theme),
+            Panel::MeanWaitTime => self.plot_mean_wait_time(ui, theme),
+            Panel::CustomersInSystem => self.plot_customers_in_system(ui, theme),
+            Panel::Utilization => self.plot_utilization(ui, theme),
+            Panel::ArrivalRate => self.plot_arrival_rate(ui, theme),
+            Panel::DepartureRate => self.plot_departure_rate(ui, theme),
+            Panel::ArrivalDepartureStaircase => self.plot_arrival_departure_staircase(ui, theme),
+            Panel::Throughput => self.plot_throughput(ui, theme),
+            Panel::CustomersServed => self.plot_customers_served(ui, theme),
+            Panel::Ensemble => self.plot_ensemble(ui, theme),
+            Panel::BusyServers => self.plot_busy_servers(ui, theme),
+            Panel::CustomersDistribution => self.plot_customers_distribution(ui, theme),
+            Panel::QueueHeatmap => self.plot_queue_heatmap(ui, theme),
+            Panel::WaitCv => self.plot_wait_cv(ui, theme),
+            Panel::WaitHistogram => self.plot_wait_histogram(ui, theme),
+            Panel::SlowdownHistogram => self.plot_slowdown_histogram(ui, theme),
+            Panel::WaitEcdf => self.plot_wait_ecdf(ui, theme),
+            Panel::WaitQq => self.plot_wait_qq(ui, theme),
+            Panel::Overlay => self.plot_overlay(ui, theme),
+        }
+    }
+
     fn plot_queue_length(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.queue_length.data();
-        Self::create_plot(
+        let has_averages = self
+            .runs
+            .iter()
+            .any(|run| !run.time_series.queue_length_average.is_empty());
+        ui.vertical(|ui| {
+            if has_averages {
+                Self::interval_average_controls(ui, &mut self.interval_averages);
+            }
+            if has_averages && self.interval_averages {
+                let series: Vec<(&str, &[(f64, f64)])> = self
+                    .runs
+                    .iter()
+                    .map(|run| {
+                        (
+                            run.label.as_str(),
+                            run.time_series.queue_length_average.data(),
+                        )
+                    })
+                    .collect();
+                Self::create_plot(
+                    ui,
+                    "queue_length",
+                    "Queue Length Averaged per Interval",
+                    Some("queue length"),
+                    egui::Color32::BLUE,
+                    PlotStyle::Line,
+                    &series,
+                    &self.markers,
+                    &mut self.plot_states.queue,
+                    &self.layout.style(Panel::QueueLength),
+                    theme,
+                    |v| v,
+                );
+                return;
+            }
+            let series: Vec<(&str, &[(f64, _)])> = self
+                .runs
+                .iter()
+                .map(|run| (run.label.as_str(), run.time_series.queue_length.data()))
+                .collect();
+            Self::create_plot(
+                ui,
+                "queue_length",
+                "Queue Length Over Time",
+                Some("queue length"),
+                egui::Color32::BLUE,
+                PlotStyle::Line,
+                &series,
+                &self.markers,
+                &mut self.plot_states.queue,
+                &self.layout.style(Panel::QueueLength),
+                theme,
+                |v| v as f64,
+            );
+        });
+    }
+
+    /// Radio buttons between the instantaneous samples and the interval averages
+    fn interval_average_controls(ui: &mut egui::Ui, interval_averages: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.radio_value(interval_averages, false, "Instantaneous");
+            ui.radio_value(interval_averages, true, "Interval average");
+        });
+    }
+
+    /// Mean wait of every run, optionally with the batch-means 95% confidence band around it,
+    /// or its rate of change to judge by eye whether it has settled
+    fn plot_mean_wait_time(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        if self
+            .runs
+            .iter()
+            .all(|run| run.time_series.mean_wait_time.is_empty())
+        {
+            return;
+        }
+
+        let runs = &self.runs;
+        let markers = &self.markers;
+        let look = self.layout.style(Panel::MeanWaitTime);
+        let main = main_color(&look, egui::Color32::RED);
+        let show_ci = &mut self.show_wait_ci;
+        let windowed = &mut self.windowed_statistics;
+        let has_window = runs
+            .iter()
+            .any(|run| !run.time_series.windowed_mean_wait.is_empty());
+        let windowed_name = window_legend("Mean wait", self.statistics_window);
+        let state = &mut self.plot_states.wait;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if has_window {
+                    ui.radio_value(windowed, false, "Cumulative");
+                    ui.radio_value(windowed, true, "Sliding window");
+                }
+                state.derivative_controls(ui);
+                let cumulative = !has_window || !*windowed;
+                if !state.derivative && cumulative {
+                    ui.checkbox(show_ci, "Show 95% confidence band (batch means)");
+                }
+            });
+            let windowed = has_window && *windowed;
+            // The band bounds the cumulative mean wait itself, not its rate of change
+            let show_ci = *show_ci && !state.derivative && !windowed;
+            let derivatives: Option<Vec<Vec<(f64, f64)>>> = state.derivative.then(|| {
+                runs.iter()
+                    .map(|run| {
+                        run.mean_wait(windowed)
+                            .derivative(state.derivative_stride, |v| v)
+                    })
+                    .collect()
+            });
+
+            Self::show_plot_panel(ui, "mean_wait_time", state, &look, theme, |plot_ui| {
+                let (y_min, y_max) = match &derivatives {
+                    Some(derivatives) => derivatives
+                        .iter()
+                        .flatten()
+                        .fold((0.0, 0.0), |(lo, hi), (_, v)| (v.min(lo), v.max(hi))),
+                    None => (
+                        0.0,
+                        runs.iter()
+                            .flat_map(|run| run.mean_wait(windowed).data())
+                            .map(|(_, v)| *v)
+                            .fold(0.0, f64::max),
+                    ),
+                };
+                markers.draw(plot_ui, y_min, y_max, theme);
+
+                for (index, run) in runs.iter().enumerate() {
+                    let legend_name = if derivatives.is_some() {
+                        "d(mean wait)/dt"
+                    } else if windowed {
+                        windowed_name.as_str()
+                    } else {
+                        look.title.as_deref().unwrap_or("Mean Wait Time Over Time")
+                    };
+                    let (name, color) = if runs.len() == 1 {
+                        (legend_name.to_string(), main)
+                    } else {
+                        (
+                            format!("{} ({})", legend_name, run.label),
+                            if index == 0 {
+                                main
+                            } else {
+                                RUN_COLORS[(index - 1) % RUN_COLORS.len()]
+                            },
+                        )
+                    };
+
+                    if show_ci {
+                        // Samples before enough batches exist have no interval;
+                        // each stretch with one gets its own band
+                        let band_name = format!("{} 95% CI", name);
+                        let data = run.time_series.mean_wait_ci.data();
+                        for stretch in data.split(|(_, ci)| ci.is_none()) {
+                            let band: Vec<_> = stretch
+                                .iter()
+                                .filter_map(|(t, ci)| ci.map(|(lo, hi)| (*t, lo, hi)))
+                                .collect();
+                            draw_band(plot_ui, &band_name, &band, color.gamma_multiply(0.25));
+                        }
+                    }
+
+                    let data = match &derivatives {
+                        Some(derivatives) => derivatives[index].as_slice(),
+                        None => run.mean_wait(windowed).data(),
+                    };
+                    let points: PlotPoints = data.iter().map(|(t, v)| [*t, *v]).collect();
+                    plot_ui.line(styled_line(Line::new(name, points).color(color), &look));
+                }
+            });
+        });
+    }
+
+    /// Running coefficient of variation of the waits of every run, against the
+    /// steady-state value where theory has one; it levelling off is another sign
+    /// that the run has converged
+    fn plot_wait_cv(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        if self.runs.iter().all(|run| {
+            run.time_series
+                .wait_cv
+                .data()
+                .iter()
+                .all(|(_, cv)| cv.is_none())
+        }) {
+            return;
+        }
+
+        let runs = &self.runs;
+        let markers = &self.markers;
+        let theory = self.theoretical_wait_cv;
+        let look = self.layout.style(Panel::WaitCv);
+        let legend_name = look
+            .title
+            .as_deref()
+            .unwrap_or("Wait Coefficient of Variation");
+        let main = main_color(&look, egui::Color32::from_rgb(178, 34, 34));
+        Self::show_plot_panel(
             ui,
-            "queue_length",
-            "Queue Length Over Time",
-            egui::Color32::BLUE,
-            data,
-            &mut self.plot_states.queue,
+            "wait_cv",
+            &mut self.plot_states.wait_cv,
+            &look,
             theme,
-            |v| v as f64,
+            |plot_ui| {
+                let y_max = runs
+                    .iter()
+                    .flat_map(|run| run.time_series.wait_cv.data())
+                    .filter_map(|(_, cv)| *cv)
+                    .chain(theory)
+                    .fold(0.0, f64::max);
+                markers.draw(plot_ui, 0.0, y_max, theme);
+                if let Some(cv) = theory {
+                    plot_ui.hline(
+                        HLine::new(format!("Steady state (M/M/1): {:.3}", cv), cv)
+                            .stroke(egui::Stroke::new(1.5, egui::Color32::GRAY)),
+                    );
+                }
+                for (index, run) in runs.iter().enumerate() {
+                    let (name, color) = if runs.len() == 1 {
+                        (legend_name.to_string(), main)
+                    } else {
+                        (
+                            format!("{} ({})", legend_name, run.label),
+                            if index == 0 {
+                                main
+                            } else {
+                                RUN_COLORS[(index - 1) % RUN_COLORS.len()]
+                            },
+                        )
+                    };
+                    // The samples before enough customers waited have no value
+                    let points: PlotPoints = run
+                        .time_series
+                        .wait_cv
+                        .data()
+                        .iter()
+                        .filter_map(|(t, cv)| cv.map(|cv| [*t, cv]))
+                        .collect();
+                    plot_ui.line(styled_line(Line::new(name, points).color(color), &look));
+                }
+            },
         );
     }
 
-    fn plot_mean_wait_time(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.mean_wait_time.data();
-        Self::create_plot(
+    /// Share of the first run's waits per bar, the customers served at once drawn as
+    /// a spike at 0, with the M/M/1 shares over them where the runs follow one
+    fn plot_wait_histogram(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(run) = self.runs.first() else {
+            return;
+        };
+        let theory = self.theoretical_wait;
+        let cdf = |t| theory.and_then(|(lambda, mu)| mm1_wait_cdf(lambda, mu, t));
+        let show_theory = &mut self.show_wait_theory;
+        let buckets = &mut self.wait_histogram_buckets;
+        let state = &mut self.plot_states.wait_histogram;
+        let look = self.layout.style(Panel::WaitHistogram);
+        ui.vertical(|ui| {
+            if theory.is_some() && !run.time_series.wait_histogram.is_empty() {
+                ui.checkbox(show_theory, "Compare with M/M/1");
+            }
+            let reference: Option<&dyn Fn(f64) -> Option<f64>> =
+                (theory.is_some() && *show_theory).then_some(&cdf);
+            Self::histogram_panel(
+                ui,
+                "wait_histogram",
+                &run.time_series.wait_histogram,
+                run.time_series.zero_waits,
+                reference,
+                &format!("Share of waits ({})", run.label),
+                egui::Color32::from_rgb(205, 92, 92),
+                buckets,
+                state,
+                &look,
+                theme,
+            );
+        });
+    }
+
+    fn plot_slowdown_histogram(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(run) = self.runs.first() else {
+            return;
+        };
+        Self::histogram_panel(
             ui,
-            "mean_wait_time",
-            "Mean Wait Time Over Time",
-            egui::Color32::RED,
-            data,
-            &mut self.plot_states.wait,
+            "slowdown_histogram",
+            &run.time_series.slowdown_histogram,
+            0,
+            None,
+            &format!(
+                "Share of log₁₀ slowdowns, sojourn / service ({})",
+                run.label
+            ),
+            egui::Color32::from_rgb(106, 90, 205),
+            &mut self.slowdown_histogram_buckets,
+            &mut self.plot_states.slowdown_histogram,
+            &self.layout.style(Panel::SlowdownHistogram),
             theme,
-            |v| v,
         );
     }
 
-    fn plot_utilization(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.utilization.data();
-        Self::create_plot(
+    /// Empirical CDF of the first run's waits as steps, with the M/M/1 CDF over it
+    /// where the runs follow one; both jump at 0 by the share of customers served at once
+    fn plot_wait_ecdf(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(run) = self.runs.first() else {
+            return;
+        };
+        let waits =
+            WaitDistribution::new(&run.time_series.wait_histogram, run.time_series.zero_waits);
+        let ecdf = waits.ecdf();
+        let Some(&(longest, _)) = ecdf.last() else {
+            return;
+        };
+        // Start at the origin so the atom at 0 is drawn as a vertical jump
+        let data: Vec<(f64, f64)> = std::iter::once((0.0, 0.0)).chain(ecdf).collect();
+        let steps = staircase_points(&data, &|v| v);
+        let has_theory = self.theoretical_wait.is_some();
+        let show_theory = &mut self.show_wait_theory;
+        let theory = self
+            .theoretical_wait
+            .filter(|_| *show_theory)
+            .and_then(|(lambda, mu)| {
+                let atom = mm1_wait_cdf(lambda, mu, 0.0)?;
+                let curve = (0..=THEORY_CURVE_POINTS).filter_map(|k| {
+                    let t = longest * k as f64 / THEORY_CURVE_POINTS as f64;
+                    Some([t, mm1_wait_cdf(lambda, mu, t)?])
+                });
+                Some(
+                    std::iter::once([0.0, 0.0])
+                        .chain(std::iter::once([0.0, atom]))
+                        .chain(curve)
+                        .collect::<Vec<_>>(),
+                )
+            });
+        let look = self.layout.style(Panel::WaitEcdf);
+        let name = look
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Empirical CDF of the waits ({})", run.label));
+        let color = main_color(&look, egui::Color32::from_rgb(205, 92, 92));
+        let state = &mut self.plot_states.wait_ecdf;
+        ui.vertical(|ui| {
+            if has_theory {
+                ui.checkbox(show_theory, "Compare with M/M/1");
+            }
+            Self::show_plot_panel(ui, "wait_ecdf", state, &look, theme, |plot_ui| {
+                if let Some(theory) = theory {
+                    plot_ui.line(
+                        Line::new("M/M/1", PlotPoints::from(theory))
+                            .stroke(egui::Stroke::new(1.5, egui::Color32::GRAY)),
+                    );
+                }
+                plot_ui.line(styled_line(
+                    Line::new(name, PlotPoints::from(steps)).color(color),
+                    &look,
+                ));
+            });
+        });
+    }
+
+    /// Quantiles of the first run's waits against the M/M/1 ones, with the line y = x
+    /// a correct model follows; only where the runs follow an M/M/1 queue
+    fn plot_wait_qq(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let (Some(run), Some((lambda, mu))) = (self.runs.first(), self.theoretical_wait) else {
+            return;
+        };
+        let waits =
+            WaitDistribution::new(&run.time_series.wait_histogram, run.time_series.zero_waits);
+        let Some(qq) = mm1_qq_points(&waits, lambda, mu, QQ_POINTS) else {
+            return;
+        };
+        let points: Vec<[f64; 2]> = qq.iter().map(|p| [p.theoretical, p.empirical]).collect();
+        let largest = points.iter().flatten().copied().fold(0.0, f64::max);
+        let look = self.layout.style(Panel::WaitQq);
+        let name = look
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Wait quantiles, M/M/1 vs simulated ({})", run.label));
+        let color = main_color(&look, egui::Color32::from_rgb(205, 92, 92));
+        Self::show_plot_panel(
             ui,
-            "utilization",
-            "Server Utilization Over Time (0-1)",
-            egui::Color32::GREEN,
-            data,
-            &mut self.plot_states.util,
+            "wait_qq",
+            &mut self.plot_states.wait_qq,
+            &look,
             theme,
-            |v| v,
+            |plot_ui| {
+                plot_ui.line(
+                    Line::new(
+                        "y = x",
+                        PlotPoints::from(vec![[0.0, 0.0], [largest, largest]]),
+                    )
+                    .stroke(egui::Stroke::new(1.5, egui::Color32::GRAY)),
+                );
+                plot_ui.points(Points::new(name, points).radius(2.5).color(color));
+            },
+        );
+    }
+
+    /// The chosen metrics of every run normalized onto one scale, to see how they
+    /// rise and fall together; the legend gives the range each was mapped from and
+    /// hovering a line shows its original value
+    fn plot_overlay(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let metrics: Vec<_> = self
+            .runs
+            .iter()
+            .map(|run| (run.label.as_str(), run.time_series.metrics()))
+            .collect();
+        let mut sampled = [false; SimulationTimeSeries::METRIC_NAMES.len()];
+        for (_, run_metrics) in &metrics {
+            for (k, (_, samples)) in run_metrics.iter().enumerate() {
+                sampled[k] |= !samples.is_empty();
+            }
+        }
+        if !sampled.contains(&true) {
+            return;
+        }
+
+        ui.vertical(|ui| {
+            if self.overlay.controls(ui, &sampled) {
+                self.plot_states.overlay.reset();
+            }
+            let normalization = self.overlay.normalization;
+            let several_runs = metrics.len() > 1;
+            let lines: Vec<(String, NormalizedSeries)> = metrics
+                .iter()
+                .flat_map(|(label, run_metrics)| {
+                    run_metrics
+                        .iter()
+                        .zip(self.overlay.shown)
+                        .filter(|(_, shown)| *shown)
+                        .filter_map(move |((name, samples), _)| {
+                            let series = normalize(samples, normalization)?;
+                            let range = match normalization {
+                                Normalization::MinMax => format!(
+                                    "{} [{} – {}]",
+                                    name,
+                                    format_value(series.min),
+                                    format_value(series.max)
+                                ),
+                                Normalization::ZScore => format!(
+                                    "{} (mean {}, sd {})",
+                                    name,
+                                    format_value(series.mean),
+                                    format_value(series.std_dev)
+                                ),
+                            };
+                            let legend = if several_runs {
+                                format!("{}: {}", label, range)
+                            } else {
+                                range
+                            };
+                            Some((legend, series))
+                        })
+                })
+                .collect();
+
+            let label = |name: &str, point: &PlotPoint| match lines
+                .iter()
+                .find(|(legend, _)| legend == name)
+            {
+                Some((_, series)) => format!(
+                    "{}\nt = {:.2}\nvalue = {}",
+                    name,
+                    point.x,
+                    format_value(series.denormalize(point.y))
+                ),
+                None => format!("t = {:.2}\ny = {:.3}", point.x, point.y),
+            };
+            let look = self.layout.style(Panel::Overlay);
+            let markers = &self.markers;
+            Self::show_labelled_plot_panel(
+                ui,
+                "overlay",
+                &mut self.plot_states.overlay,
+                &look,
+                theme,
+                Some(&label),
+                |plot_ui| {
+                    let (y_min, y_max) = lines
+                        .iter()
+                        .flat_map(|(_, series)| series.points.iter())
+                        .fold((0.0, 1.0), |(lo, hi): (f64, f64), (_, y)| {
+                            (lo.min(*y), hi.max(*y))
+                        });
+                    markers.draw(plot_ui, y_min, y_max, theme);
+                    for (legend, series) in &lines {
+                        let points: Vec<[f64; 2]> =
+                            series.points.iter().map(|(t, y)| [*t, *y]).collect();
+                        plot_ui.line(styled_line(
+                            Line::new(legend.as_str(), PlotPoints::from(points)),
+                            &look,
+                        ));
+                    }
+                },
+            );
+        });
+    }
+
+    /// Share of the counts of `histogram` per bar, at a slider-chosen number of bars
+    ///
+    /// Runs keep their waits and slowdowns as fine histograms, not one by one; fewer
+    /// bars sum neighbouring bins, so the bars are whole multiples of the stored width.
+    /// The `zeros` exact zeros among the first bin's counts are taken out of it and
+    /// drawn as a spike at 0, a point mass no bar width can show. With the M/M/1 CDF
+    /// as `reference` its share per bar is drawn as steps over the bars, and its own
+    /// point mass at 0 as a marker next to the spike.
+    #[allow(clippy::too_many_arguments)]
+    fn histogram_panel(
+        ui: &mut egui::Ui,
+        plot_id: &str,
+        histogram: &Histogram,
+        zeros: u64,
+        reference: Option<&dyn Fn(f64) -> Option<f64>>,
+        legend_name: &str,
+        color: egui::Color32,
+        buckets: &mut usize,
+        state: &mut PlotState,
+        look: &PanelStyle,
+        theme: &ThemeColors,
+    ) {
+        if histogram.is_empty() {
+            return;
+        }
+        let bins = histogram.counts().len();
+        let most = bins.min(MAX_HISTOGRAM_BUCKETS);
+        *buckets = (*buckets).clamp(1, most);
+        let factor = bins.div_ceil(*buckets);
+        let width = factor as f64 * histogram.width();
+        let total = histogram.total() as f64;
+        let name = look.title.as_deref().unwrap_or(legend_name);
+        let color = main_color(look, color);
+        let zeros = zeros.min(histogram.counts()[0]);
+        let mut positive = histogram.clone();
+        positive.remove_from_first_bin(zeros);
+        let counts = merge_bins(positive.counts(), factor);
+
+        // The reference share of every bar, without its point mass when the zeros are apart
+        let reference = reference.and_then(|cdf| {
+            let atom = cdf(0.0)?;
+            let below_first = if zeros > 0 { atom } else { 0.0 };
+            let steps = (0..counts.len())
+                .map(|k| {
+                    let (lo, hi) = (k as f64 * width, (k + 1) as f64 * width);
+                    let below = if k == 0 { below_first } else { cdf(lo)? };
+                    let share = cdf(hi)? - below;
+                    Some([[lo, share], [hi, share]])
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some((atom, steps.concat()))
+        });
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Bars");
+                ui.add(egui::Slider::new(buckets, 1..=most));
+                ui.label(format!("width {:.4}", width));
+            });
+            Self::show_plot_panel(ui, plot_id, state, look, theme, |plot_ui| {
+                let bars = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &count)| {
+                        Bar::new((k as f64 + 0.5) * width, count as f64 / total).width(width)
+                    })
+                    .collect();
+                plot_ui.bar_chart(BarChart::new(name, bars).color(color));
+                if zeros > 0 {
+                    let share = zeros as f64 / total;
+                    plot_ui.line(
+                        Line::new(
+                            "Exactly 0",
+                            PlotPoints::from(vec![[0.0, 0.0], [0.0, share]]),
+                        )
+                        .stroke(egui::Stroke::new(3.0, color)),
+                    );
+                    plot_ui.points(
+                        Points::new("Exactly 0", vec![[0.0, share]])
+                            .radius(4.0)
+                            .color(color),
+                    );
+                }
+                if let Some((atom, steps)) = reference {
+                    plot_ui.line(
+                        Line::new("M/M/1", PlotPoints::from(steps))
+                            .stroke(egui::Stroke::new(1.5, egui::Color32::GRAY)),
+                    );
+                    if zeros > 0 {
+                        plot_ui.points(
+                            Points::new("M/M/1", vec![[0.0, atom]])
+                                .shape(egui_plot::MarkerShape::Diamond)
+                                .radius(5.0)
+                                .color(egui::Color32::GRAY),
+                        );
+                    }
+                }
+            });
+        });
+    }
+
+    /// Utilization of every run, or for a single run with several servers the
+    /// share of capacity each server contributes, stacked up to the total
+    fn plot_utilization(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let split = matches!(
+            self.runs.as_slice(),
+            [run] if !run.time_series.server_utilization.is_empty()
         );
+        if !split {
+            self.plot_total_utilization(ui, theme);
+            return;
+        }
+
+        ui.vertical(|ui| {
+            ui.checkbox(&mut self.stack_servers, "Split by server");
+            if !self.stack_servers {
+                self.plot_total_utilization(ui, theme);
+                return;
+            }
+
+            let per_server = self.runs[0].time_series.server_utilization.data();
+            let servers = per_server[0].1.len();
+            let layers: Vec<(String, egui::Color32)> = (0..servers)
+                .map(|k| {
+                    (
+                        format!("Server {}", k + 1),
+                        SERVER_COLORS[k % SERVER_COLORS.len()],
+                    )
+                })
+                .collect();
+            let markers = &self.markers;
+            Self::show_plot_panel(
+                ui,
+                "utilization",
+                &mut self.plot_states.util,
+                &self.layout.style(Panel::Utilization),
+                theme,
+                |plot_ui| {
+                    markers.draw(plot_ui, 0.0, 1.0, theme);
+                    draw_stacked(plot_ui, &layers, per_server, 1.0 / servers as f64);
+                },
+            );
+        });
+    }
+
+    fn plot_total_utilization(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let has_window = self
+            .runs
+            .iter()
+            .any(|run| !run.time_series.windowed_utilization.is_empty());
+        let windowed = has_window && self.windowed_statistics;
+        let series: Vec<(&str, &[(f64, _)])> = self
+            .runs
+            .iter()
+            .map(|run| {
+                let data = if windowed {
+                    run.time_series.windowed_utilization.data()
+                } else {
+                    run.time_series.utilization.data()
+                };
+                (run.label.as_str(), data)
+            })
+            .collect();
+        let windowed_name = window_legend("Utilization", self.statistics_window);
+        let legend_name = if windowed {
+            windowed_name.as_str()
+        } else {
+            "Server Utilization Over Time (0-1)"
+        };
+
+        ui.vertical(|ui| {
+            if has_window {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.windowed_statistics, false, "Cumulative");
+                    ui.radio_value(&mut self.windowed_statistics, true, "Sliding window");
+                });
+            }
+            Self::create_plot(
+                ui,
+                "utilization",
+                legend_name,
+                Some("utilization"),
+                egui::Color32::GREEN,
+                PlotStyle::Line,
+                &series,
+                &self.markers,
+                &mut self.plot_states.util,
+                &self.layout.style(Panel::Utilization),
+                theme,
+                |v| v,
+            );
+        });
     }
 
     fn plot_customers_served(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.customers_served.data();
+        let series: Vec<(&str, &[(f64, _)])> = self
+            .runs
+            .iter()
+            .map(|run| (run.label.as_str(), run.time_series.customers_served.data()))
+            .collect();
         Self::create_plot(
             ui,
             "customers_served",
             "Customers Served Over Time",
+            Some("customers served"),
             egui::Color32::from_rgb(128, 0, 128),
-            data,
+            PlotStyle::Line,
+            &series,
+            &self.markers,
             &mut self.plot_states.customers,
+            &self.layout.style(Panel::CustomersServed),
             theme,
             |v| v as f64,
         );
     }
 
     fn plot_customers_in_system(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.customers_in_system.data();
+        let has_averages = self
+            .runs
+            .iter()
+            .any(|run| !run.time_series.customers_in_system_average.is_empty());
+        let color = egui::Color32::from_rgb(255, 140, 0);
+        ui.vertical(|ui| {
+            if has_averages {
+                Self::interval_average_controls(ui, &mut self.interval_averages);
+            }
+            if has_averages && self.interval_averages {
+                let series: Vec<(&str, &[(f64, f64)])> = self
+                    .runs
+                    .iter()
+                    .map(|run| {
+                        (
+                            run.label.as_str(),
+                            run.time_series.customers_in_system_average.data(),
+                        )
+                    })
+                    .collect();
+                Self::create_plot(
+                    ui,
+                    "customers_in_system",
+                    "Customers in System Averaged per Interval",
+                    Some("customers in system"),
+                    color,
+                    PlotStyle::Line,
+                    &series,
+                    &self.markers,
+                    &mut self.plot_states.customers_in_system,
+                    &self.layout.style(Panel::CustomersInSystem),
+                    theme,
+                    |v| v,
+                );
+                return;
+            }
+            let series: Vec<(&str, &[(f64, _)])> = self
+                .runs
+                .iter()
+                .map(|run| {
+                    (
+                        run.label.as_str(),
+                        run.time_series.customers_in_system.data(),
+                    )
+                })
+                .collect();
+            Self::create_plot(
+                ui,
+                "customers_in_system",
+                "Customers in System Over Time",
+                Some("customers in system"),
+                color,
+                PlotStyle::Line,
+                &series,
+                &self.markers,
+                &mut self.plot_states.customers_in_system,
+                &self.layout.style(Panel::CustomersInSystem),
+                theme,
+                |v| v as f64,
+            );
+        });
+    }
+
+    fn plot_arrival_rate(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let series: Vec<(&str, &[(f64, f64)])> = self
+            .runs
+            .iter()
+            .map(|run| (run.label.as_str(), run.arrival_rate.as_slice()))
+            .collect();
         Self::create_plot(
             ui,
-            "customers_in_system",
-            "Customers in System Over Time",
-            egui::Color32::from_rgb(255, 140, 0),
-            data,
-            &mut self.plot_states.customers_in_system,
+            "arrival_rate",
+            "Arrivals per Time Unit (per interval)",
+            None,
+            egui::Color32::from_rgb(70, 130, 180),
+            PlotStyle::Step,
+            &series,
+            &self.markers,
+            &mut self.plot_states.arrivals,
+            &self.layout.style(Panel::ArrivalRate),
             theme,
-            |v| v as f64,
+            |v| v,
         );
     }
 
-    fn plot_throughput(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
-        let data = self.time_series.throughput.data();
+    fn plot_departure_rate(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let series: Vec<(&str, &[(f64, f64)])> = self
+            .runs
+            .iter()
+            .map(|run| (run.label.as_str(), run.departure_rate.as_slice()))
+            .collect();
         Self::create_plot(
             ui,
-            "throughput",
-            "System Throughput (customers/time)",
-            egui::Color32::from_rgb(0, 128, 128),
-            data,
-            &mut self.plot_states.throughput,
+            "departure_rate",
+            "Departures per Time Unit (per interval)",
+            None,
+            egui::Color32::from_rgb(178, 34, 34),
+            PlotStyle::Step,
+            &series,
+            &self.markers,
+            &mut self.plot_states.departures,
+            &self.layout.style(Panel::DepartureRate),
             theme,
             |v| v,
         );
     }
+
+    fn plot_arrival_departure_staircase(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let labels: Vec<(String, String)> = self
+            .runs
+            .iter()
+            .map(|run| {
+                if self.runs.len() == 1 {
+                    ("arrivals".to_string(), "departures".to_string())
+                } else {
+                    (
+                        format!("{} arrivals", run.label),
+                        format!("{} departures", run.label),
+                    )
+                }
+            })
+            .collect();
+        let series: Vec<(&str, &[(f64, u64)])> = self
+            .runs
+            .iter()
+            .zip(&labels)
+            .flat_map(|(run, (arrivals, departures))| {
+                [
+                    (
+                        arrivals.as_str(),
+                        run.time_series.cumulative_arrivals.data(),
+                    ),
+                    (
+                        departures.as_str(),
+                        run.time_series.cumulative_departures.data(),
+                    ),
+                ]
+            })
+            .collect();
+        Self::create_plot(
+            ui,
+            "arrival_departure_staircase",
+            "Cumulative Count (start of run, event times)",
+            None,
+            egui::Color32::from_rgb(70, 130, 180),
+            PlotStyle::Staircase,
+            &series,
+            &self.markers,
+            &mut self.plot_states.staircase,
+            &self.layout.style(Panel::ArrivalDepartureStaircase),
+            theme,
+            |v| v as f64,
+        );
+    }
+
+    fn plot_busy_servers(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(servers) = self.server_limit else {
+            return;
+        };
+        if self
+            .runs
+            .iter()
+            .all(|run| run.time_series.busy_servers.is_empty())
+        {
+            return;
+        }
+
+        let runs = &self.runs;
+        let markers = &self.markers;
+        let look = self.layout.style(Panel::BusyServers);
+        let legend_name = look.title.as_deref().unwrap_or("Busy Servers");
+        let main = main_color(&look, egui::Color32::from_rgb(0, 128, 128));
+        Self::show_plot_panel(
+            ui,
+            "busy_servers",
+            &mut self.plot_states.busy_servers,
+            &look,
+            theme,
+            |plot_ui| {
+                markers.draw(plot_ui, 0.0, servers as f64, theme);
+                plot_ui.hline(
+                    HLine::new(format!("All {} servers busy", servers), servers as f64)
+                        .stroke(egui::Stroke::new(1.5, egui::Color32::RED)),
+                );
+                for (index, run) in runs.iter().enumerate() {
+                    let points: PlotPoints =
+                        step_points(run.time_series.busy_servers.data(), &|v| v as f64).into();
+                    let (name, color) = if runs.len() == 1 {
+                        (legend_name.to_string(), main)
+                    } else {
+                        (
+                            format!("{} ({})", legend_name, run.label),
+                            if index == 0 {
+                                main
+                            } else {
+                                RUN_COLORS[(index - 1) % RUN_COLORS.len()]
+                            },
+                        )
+                    };
+                    plot_ui.line(styled_line(Line::new(name, points).color(color), &look));
+                }
+            },
+        );
+    }
+
+    /// Empirical P_n of the first run at a slider-selected snapshot, which can be
+    /// played back to watch it converge toward the steady-state shape
+    ///
+    /// A second slider merges neighbouring n into wider bars, summing their
+    /// probabilities, to smooth out a long, ragged tail.
+    fn plot_customers_distribution(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(run) = self.runs.first() else {
+            return;
+        };
+        let snapshots = run.time_series.customers_distribution.data();
+        if snapshots.is_empty() {
+            return;
+        }
+        let last = snapshots.len() - 1;
+
+        if self.distribution_playing {
+            let now = ui.input(|i| i.time);
+            if now - self.last_distribution_step >= DISTRIBUTION_FRAME_SECONDS {
+                self.last_distribution_step = now;
+                if self.distribution_snapshot >= last {
+                    self.distribution_playing = false;
+                } else {
+                    self.distribution_snapshot += 1;
+                }
+            }
+            ui.ctx().request_repaint();
+        }
+        self.distribution_snapshot = self.distribution_snapshot.min(last);
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                let label = if self.distribution_playing {
+                    "Pause"
+                } else {
+                    "Play"
+                };
+                if ui.button(label).clicked() {
+                    if !self.distribution_playing && self.distribution_snapshot == last {
+                        self.distribution_snapshot = 0;
+                    }
+                    self.distribution_playing = !self.distribution_playing;
+                }
+                ui.add(
+                    egui::Slider::new(&mut self.distribution_snapshot, 0..=last).show_value(false),
+                );
+                ui.label(format!(
+                    "t = {:.0}",
+                    snapshots[self.distribution_snapshot].0
+                ));
+            });
+
+            let (_, empirical) = &snapshots[self.distribution_snapshot];
+            let steady_state = self.steady_state_distribution.as_deref();
+            // Show the support of the empirical distribution, plus the theoretical
+            // tail until it becomes negligible
+            let theory_len = steady_state.map_or(0, |p| {
+                p.iter().rposition(|&p| p >= 1e-3).map_or(0, |n| n + 1)
+            });
+            let len = empirical.len().max(theory_len);
+            self.distribution_bucket_width = self.distribution_bucket_width.clamp(1, len.max(1));
+            if len > 1 {
+                ui.horizontal(|ui| {
+                    ui.label("Customers per bar");
+                    ui.add(egui::Slider::new(
+                        &mut self.distribution_bucket_width,
+                        1..=len,
+                    ));
+                });
+            }
+            let width = self.distribution_bucket_width;
+            // Bar k covers n = k * width up to (k + 1) * width - 1 and sits at their middle
+            let center = |k: usize| (k * width) as f64 + (width - 1) as f64 / 2.0;
+            let look = self.layout.style(Panel::CustomersDistribution);
+            let bar_name = match &look.title {
+                Some(title) => title.clone(),
+                None => format!("Empirical P_n ({})", run.label),
+            };
+
+            Self::show_plot_panel(
+                ui,
+                "customers_distribution",
+                &mut self.plot_states.distribution,
+                &look,
+                theme,
+                |plot_ui| {
+                    let bars = merge_bins(empirical, width)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(k, p)| Bar::new(center(k), p).width(width as f64 - 0.2))
+                        .collect();
+                    plot_ui.bar_chart(
+                        BarChart::new(bar_name, bars)
+                            .color(main_color(&look, egui::Color32::from_rgb(100, 149, 237))),
+                    );
+
+                    if let Some(steady_state) = steady_state {
+                        let points: Vec<[f64; 2]> =
+                            merge_bins(&steady_state[..len.min(steady_state.len())], width)
+                                .into_iter()
+                                .enumerate()
+                                .map(|(k, p)| [center(k), p])
+                                .collect();
+                        plot_ui.line(
+                            Line::new("Steady state", PlotPoints::from(points.clone()))
+                                .color(egui::Color32::RED),
+                        );
+                        plot_ui.points(
+                            Points::new("Steady state", points)
+                                .radius(3.0)
+                                .color(egui::Color32::RED),
+                        );
+                    }
+                },
+            );
+        });
+    }
+
+    /// Share of time at each queue length per time column for the first run
+    ///
+    /// The columns are rebuilt from the exact occupancy for the visible time range
+    /// on every frame, so zooming in resolves shorter stretches of the run.
+    fn plot_queue_heatmap(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(run) = self.runs.first() else {
+            return;
+        };
+        let occupancy = &run.time_series.queue_occupancy;
+        if occupancy.is_empty() {
+            return;
+        }
+        let end_time = occupancy.end_time();
+        let rows = occupancy.rows();
+        let (y_min, y_max) = (-0.5, rows as f64 - 0.5);
+        let markers = &self.markers;
+        let look = self.layout.style(Panel::QueueHeatmap);
+
+        ui.vertical(|ui| {
+            match &look.title {
+                Some(title) => ui.label(title),
+                None => ui.label(format!("Queue length heatmap ({})", run.label)),
+            };
+            Self::show_plot_panel(
+                ui,
+                "queue_heatmap",
+                &mut self.plot_states.heatmap,
+                &look,
+                theme,
+                |plot_ui| {
+                    // Invisible corners, so the automatic bounds and Reset frame the
+                    // whole run rather than just the columns drawn for the current view
+                    plot_ui.points(
+                        Points::new("", vec![[0.0, y_min], [end_time, y_max]])
+                            .radius(0.0)
+                            .color(egui::Color32::TRANSPARENT),
+                    );
+                    markers.draw(plot_ui, y_min, y_max, theme);
+
+                    let bounds = plot_ui.plot_bounds();
+                    let (mut start, mut end) =
+                        (bounds.min()[0].max(0.0), bounds.max()[0].min(end_time));
+                    let mut first_row = bounds.min()[1].round().clamp(0.0, rows as f64) as usize;
+                    let mut last_row =
+                        (bounds.max()[1].round() + 1.0).clamp(0.0, rows as f64) as usize;
+                    // Before the first frame has bounds, or when panned off the data
+                    if end <= start || last_row <= first_row {
+                        (start, end) = (0.0, end_time);
+                        (first_row, last_row) = (0, rows);
+                    }
+
+                    let grid = occupancy.grid(start, end, HEATMAP_COLUMNS);
+                    for (k, column) in grid.fractions.iter().enumerate() {
+                        let (t0, t1) = grid.column_span(k);
+                        for (n, fraction) in column
+                            .iter()
+                            .enumerate()
+                            .take(last_row)
+                            .skip(first_row)
+                            .filter(|(_, fraction)| **fraction > 0.0)
+                        {
+                            let (lo, hi) = (n as f64 - 0.5, n as f64 + 0.5);
+                            plot_ui.polygon(
+                                Polygon::new("", vec![[t0, lo], [t1, lo], [t1, hi], [t0, hi]])
+                                    .fill_color(theme.heat_color(*fraction))
+                                    .stroke(egui::Stroke::NONE),
+                            );
+                        }
+                    }
+                },
+            );
+            draw_colorbar(ui, theme);
+        });
+    }
+
+    fn plot_ensemble(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let Some(ensemble) = &self.ensemble else {
+            return;
+        };
+        if ensemble.points().is_empty() {
+            return;
+        }
+
+        let show_bands = &mut self.show_ensemble_bands;
+        let markers = &self.markers;
+        ui.vertical(|ui| {
+            ui.checkbox(show_bands, "Show 5–95% and 25–75% bands");
+            let show_bands = *show_bands;
+            let points = ensemble.points();
+            let look = self.layout.style(Panel::Ensemble);
+            let color = main_color(&look, egui::Color32::BLUE);
+            let median_name = match &look.title {
+                Some(title) => title.clone(),
+                None => format!(
+                    "Median queue length ({} replications)",
+                    ensemble.replications()
+                ),
+            };
+
+            Self::show_plot_panel(
+                ui,
+                "ensemble",
+                &mut self.plot_states.ensemble,
+                &look,
+                theme,
+                |plot_ui| {
+                    let y_max = points.iter().map(|p| p.p95).fold(0.0, f64::max);
+                    markers.draw(plot_ui, 0.0, y_max, theme);
+                    if show_bands {
+                        let outer: Vec<_> = points.iter().map(|p| (p.time, p.p05, p.p95)).collect();
+                        let inner: Vec<_> = points.iter().map(|p| (p.time, p.p25, p.p75)).collect();
+                        draw_band(plot_ui, "5–95%", &outer, color.gamma_multiply(0.15));
+                        draw_band(plot_ui, "25–75%", &inner, color.gamma_multiply(0.3));
+                    }
+                    let median: PlotPoints = points.iter().map(|p| [p.time, p.median]).collect();
+                    plot_ui.line(styled_line(
+                        Line::new(median_name, median).color(color),
+                        &look,
+                    ));
+                },
+            );
+        });
+    }
+
+    fn plot_throughput(&mut self, ui: &mut egui::Ui, theme: &ThemeColors) {
+        let view = &mut self.throughput_view;
+        let windowed_name = match self.throughput_window {
+            Some(window) => format!("Throughput over the last {} time units", window),
+            None => "Windowed Throughput (customers/time)".to_string(),
+        };
+        let series: Vec<(&str, &[(f64, _)])> = self
+            .runs
+            .iter()
+            .map(|run| {
+                let data = match *view {
+                    ThroughputView::Cumulative => run.time_series.throughput.data(),
+                    ThroughputView::Windowed => run.time_series.windowed_throughput.data(),
+                };
+                (run.label.as_str(), data)
+            })
+            .collect();
+        let legend_name = match *view {
+            ThroughputView::Cumulative => "System Throughput (customers/time)",
+            ThroughputView::Windowed => windowed_name.as_str(),
+        };
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(view, ThroughputView::Cumulative, "Cumulative");
+                ui.radio_value(view, ThroughputView::Windowed, "Sliding window");
+            });
+            Self::create_plot(
+                ui,
+                "throughput",
+                legend_name,
+                Some("throughput"),
+                egui::Color32::from_rgb(0, 128, 128),
+                PlotStyle::Line,
+                &series,
+                &self.markers,
+                &mut self.plot_states.throughput,
+                &self.layout.style(Panel::Throughput),
+                theme,
+                |v| v,
+            );
+        });
+    }
 }
 
 impl eframe::App for InteractivePlotViewer {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let theme = ThemeColors::from_visuals(&ctx.style().visuals);
 
+        self.load_visible_samples();
+        self.menu_bar(ctx);
+        self.session_window(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Header
             ui.vertical_centered(|ui| {
@@ -370,30 +2606,270 @@ impl eframe::App for InteractivePlotViewer {
                 ui.add_space(10.0);
             });
 
+            if !self.markers.annotations.is_empty() {
+                ui.horizontal(|ui| self.markers.category_filter(ui));
+            }
+            if let Some(status) = &self.export_status {
+                ui.label(egui::RichText::new(status).color(theme.text));
+            }
+            if !self.report.is_empty() {
+                egui::CollapsingHeader::new("Observed vs theoretical").show(ui, |ui| {
+                    ui.label(egui::RichText::new(&self.report).monospace());
+                });
+            }
+
             ui.separator();
             ui.add_space(10.0);
 
-            // 3x2 Grid layout for all 6 plots
+            // Two-column grid in the order of the layout
             let available_width = ui.available_width();
             let plot_width = (available_width - 30.0) / 2.0;
+            let panels: Vec<Panel> = self.layout.panels.iter().map(|(panel, _)| *panel).collect();
 
-            egui::Grid::new("plot_grid")
-                .spacing([15.0, 15.0])
-                .min_col_width(plot_width)
-                .max_col_width(plot_width)
-                .show(ui, |ui| {
-                    self.plot_queue_length(ui, &theme);
-                    self.plot_mean_wait_time(ui, &theme);
-                    ui.end_row();
-
-                    self.plot_customers_in_system(ui, &theme);
-                    self.plot_utilization(ui, &theme);
-                    ui.end_row();
-
-                    self.plot_throughput(ui, &theme);
-                    self.plot_customers_served(ui, &theme);
-                    ui.end_row();
-                });
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("plot_grid")
+                    .spacing([15.0, 15.0])
+                    .min_col_width(plot_width)
+                    .max_col_width(plot_width)
+                    .show(ui, |ui| {
+                        for row in panels.chunks(2) {
+                            for panel in row {
+                                self.plot_panel(*panel, ui, &theme);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
         });
+
+        if let Some((start, end)) = self.plot_states.take_export_request() {
+            self.export_window(start, end);
+        }
+    }
+}
+
+/// Write `run,metric,time,value` rows for the samples of every run at times in
+/// `[start, end]`; returns the rows written
+///
+/// Nothing is written, not even the file, when the window holds no samples.
+fn write_window_csv(path: &Path, runs: &[RunSeries], start: f64, end: f64) -> io::Result<usize> {
+    let mut rows = Vec::new();
+    for run in runs {
+        for (name, samples) in run.time_series.metrics() {
+            rows.extend(
+                samples
+                    .into_iter()
+                    .filter(|(t, _)| (start..=end).contains(t))
+                    .map(|(t, value)| (run.label.as_str(), name, t, value)),
+            );
+        }
+    }
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "run,metric,time,value")?;
+    for (label, name, t, value) in &rows {
+        writeln!(out, "{},{},{},{}", label, name, t, value)?;
+    }
+    out.flush()?;
+    Ok(rows.len())
+}
+
+/// Expand samples into a staircase: each value is held from the previous sample time
+/// up to its own sample time, so per-interval quantities render as flat steps.
+fn step_points<T, F>(data: &[(f64, T)], to_f64: &F) -> Vec<[f64; 2]>
+where
+    T: Copy,
+    F: Fn(T) -> f64,
+{
+    let mut points = Vec::with_capacity(data.len() * 2);
+    let mut prev_time = None;
+
+    for (t, v) in data {
+        let value = to_f64(*v);
+        if let Some(start) = prev_time {
+            points.push([start, value]);
+        }
+        points.push([*t, value]);
+        prev_time = Some(*t);
+    }
+
+    points
+}
+
+/// The configured color of a panel's main series, or `default` when there is none
+fn main_color(look: &PanelStyle, default: egui::Color32) -> egui::Color32 {
+    look.color
+        .map_or(default, |[r, g, b]| egui::Color32::from_rgb(r, g, b))
+}
+
+/// `line` with the configured width of its panel
+fn styled_line<'a>(line: Line<'a>, look: &PanelStyle) -> Line<'a> {
+    match look.line_width {
+        Some(width) => line.width(width),
+        None => line,
+    }
+}
+
+/// A value in a legend or hover label: three decimals below 1000, none above
+fn format_value(value: f64) -> String {
+    if value.abs() < 1e3 {
+        format!("{:.3}", value)
+    } else {
+        format!("{:.0}", value)
+    }
+}
+
+/// Axis label of the log10 value `exponent`, in plain notation over a moderate range
+fn format_log_tick(exponent: f64) -> String {
+    let value = 10f64.powf(exponent);
+    if (1e-3..1e5).contains(&value) {
+        format!("{}", (value * 1e3).round() / 1e3)
+    } else {
+        format!("{:.0e}", value)
+    }
+}
+
+/// Expand samples of a counting process into a staircase: each value holds from
+/// its own sample time until the next sample, with a vertical jump in between.
+fn staircase_points<T, F>(data: &[(f64, T)], to_f64: &F) -> Vec<[f64; 2]>
+where
+    T: Copy,
+    F: Fn(T) -> f64,
+{
+    let mut points = Vec::with_capacity(data.len() * 2);
+    let mut prev_value = None;
+
+    for (t, v) in data {
+        let value = to_f64(*v);
+        if let Some(prev) = prev_value {
+            points.push([*t, prev]);
+        }
+        points.push([*t, value]);
+        prev_value = Some(value);
+    }
+
+    points
+}
+
+/// Shade the warm-up period between `y_min` and `y_max`, with a line where it ends if `end_line`
+fn draw_warm_up(
+    plot_ui: &mut egui_plot::PlotUi,
+    warm_up: f64,
+    y_min: f64,
+    y_max: f64,
+    end_line: bool,
+    theme: &ThemeColors,
+) {
+    plot_ui.polygon(
+        Polygon::new(
+            "Warm-up",
+            vec![
+                [0.0, y_min],
+                [warm_up, y_min],
+                [warm_up, y_max],
+                [0.0, y_max],
+            ],
+        )
+        .fill_color(theme.warm_up_fill)
+        .stroke(egui::Stroke::NONE),
+    );
+    if end_line {
+        plot_ui.vline(
+            VLine::new("Warm-up end", warm_up).stroke(egui::Stroke::new(1.5, theme.warm_up_line)),
+        );
+    }
+}
+
+/// Legend of the heatmap colors: a gradient from none to all of a column's time
+fn draw_colorbar(ui: &mut egui::Ui, theme: &ThemeColors) {
+    const STEPS: usize = 64;
+    ui.horizontal(|ui| {
+        ui.label("0%");
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 12.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let step_width = rect.width() / STEPS as f32;
+        for step in 0..STEPS {
+            let left = rect.left() + step as f32 * step_width;
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.top()),
+                    egui::pos2(left + step_width + 0.5, rect.bottom()),
+                ),
+                0.0,
+                theme.heat_color((step as f64 + 0.5) / STEPS as f64),
+            );
+        }
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(1.0, theme.frame_stroke),
+            egui::StrokeKind::Inside,
+        );
+        ui.label("100% of the column's time at that queue length");
+    });
+}
+
+/// Stacked areas of `(time, components)` samples, each component scaled by `scale`
+///
+/// Layer k fills the band between the sum of the components before it and the sum
+/// including it, in the color and under the legend name of `layers[k]`; a line
+/// follows the top of the stack.
+fn draw_stacked(
+    plot_ui: &mut egui_plot::PlotUi,
+    layers: &[(String, egui::Color32)],
+    data: &[(f64, Vec<f64>)],
+    scale: f64,
+) {
+    let mut lower: Vec<f64> = vec![0.0; data.len()];
+    for (k, (name, color)) in layers.iter().enumerate() {
+        let band: Vec<(f64, f64, f64)> = data
+            .iter()
+            .zip(&mut lower)
+            .map(|((t, components), lower)| {
+                let bottom = *lower;
+                *lower += components.get(k).copied().unwrap_or(0.0) * scale;
+                (*t, bottom, *lower)
+            })
+            .collect();
+        draw_band(plot_ui, name, &band, color.gamma_multiply(0.6));
+    }
+
+    let total: PlotPoints = data
+        .iter()
+        .zip(&lower)
+        .map(|((t, _), v)| [*t, *v])
+        .collect();
+    plot_ui.line(Line::new("Total utilization", total).color(egui::Color32::GREEN));
+}
+
+/// Fill the area between `lower` and `upper` of `(time, lower, upper)` points
+///
+/// egui_plot only fills convex polygons, so the band is drawn as one quadrilateral
+/// per segment, which is convex because both of its sides are vertical.
+fn draw_band(
+    plot_ui: &mut egui_plot::PlotUi,
+    name: &str,
+    points: &[(f64, f64, f64)],
+    color: egui::Color32,
+) {
+    let stride = points.len().div_ceil(MAX_BAND_SEGMENTS).max(1);
+    let mut decimated: Vec<_> = points.iter().step_by(stride).copied().collect();
+    if let Some(last) = points.last()
+        && decimated.last().map(|p| p.0) != Some(last.0)
+    {
+        decimated.push(*last);
+    }
+
+    for pair in decimated.windows(2) {
+        let (t0, lo0, hi0) = pair[0];
+        let (t1, lo1, hi1) = pair[1];
+        plot_ui.polygon(
+            Polygon::new(name, vec![[t0, lo0], [t1, lo1], [t1, hi1], [t0, hi0]])
+                .fill_color(color)
+                .stroke(egui::Stroke::NONE),
+        );
     }
 }
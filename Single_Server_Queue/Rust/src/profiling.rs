@@ -0,0 +1,155 @@
+//! Memory held by a run's state and the heap allocations its event loop makes
+//!
+//! Allocations are only counted with the `alloc-profile` feature, and only once
+//! the binary installs [`CountingAllocator`] as its global allocator; otherwise
+//! [`allocation_counts`] stays at zero and costs nothing. The counters are
+//! process-wide, so they are meant for a single run on one thread.
+
+use crate::simulation::Simulation;
+use crate::time_series::SimulationTimeSeries;
+
+/// Whether this build counts allocations
+pub const COUNTS_ALLOCATIONS: bool = cfg!(feature = "alloc-profile");
+
+/// Heap allocations and the bytes they asked for; a reallocation counts as one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationCounts {
+    pub allocations: u64,
+    pub bytes: u64,
+}
+
+impl AllocationCounts {
+    /// What was allocated between `earlier` and `self`
+    pub fn since(self, earlier: Self) -> Self {
+        Self {
+            allocations: self.allocations - earlier.allocations,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+
+    pub fn add(&mut self, other: Self) {
+        self.allocations += other.allocations;
+        self.bytes += other.bytes;
+    }
+}
+
+#[cfg(feature = "alloc-profile")]
+mod counting {
+    use super::AllocationCounts;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+    static BYTES: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counting every allocation and reallocation
+    pub struct CountingAllocator;
+
+    fn count(bytes: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    // SAFETY: every call is forwarded unchanged to the system allocator
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            unsafe { System.alloc_zeroed(layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size);
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[inline]
+    pub fn allocation_counts() -> AllocationCounts {
+        AllocationCounts {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "alloc-profile")]
+pub use counting::{CountingAllocator, allocation_counts};
+
+/// Allocations since the program started; always zero without the `alloc-profile` feature
+#[cfg(not(feature = "alloc-profile"))]
+#[inline]
+pub fn allocation_counts() -> AllocationCounts {
+    AllocationCounts::default()
+}
+
+/// Peak memory of the simulated state after a run
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryProfile {
+    /// Most events pending at once, `None` unless the engine was instrumented
+    pub peak_pending_events: Option<usize>,
+    /// Bytes of the event list, whose slots are fixed
+    pub event_list_bytes: usize,
+    /// Most customers waiting at once
+    pub peak_queue_length: usize,
+    /// Bytes the queue buffer took at its largest
+    pub queue_bytes: usize,
+    /// Bytes held by the sampled time series, `None` for a run that was not sampled
+    pub time_series_bytes: Option<usize>,
+    /// Allocations in the run loop, sampling included; `None` unless counted
+    pub loop_allocations: Option<AllocationCounts>,
+    /// Of those, the ones made while the simulation handled its events
+    pub event_allocations: Option<AllocationCounts>,
+}
+
+impl MemoryProfile {
+    /// Read the state of `simulation` and the size of `time_series`, leaving the allocations out
+    pub fn measure(simulation: &Simulation, time_series: Option<&SimulationTimeSeries>) -> Self {
+        let server = simulation.server();
+        Self {
+            peak_pending_events: simulation
+                .engine()
+                .metrics()
+                .map(|metrics| metrics.peak_pending),
+            event_list_bytes: std::mem::size_of_val(simulation.engine()),
+            peak_queue_length: server.peak_queue_length(),
+            queue_bytes: server.peak_queue_memory_bytes(),
+            time_series_bytes: time_series.map(SimulationTimeSeries::memory_bytes),
+            loop_allocations: None,
+            event_allocations: None,
+        }
+    }
+
+    /// The profile as a JSON object, absent values as `null`
+    pub fn to_json(&self) -> String {
+        fn optional<T: ToString>(value: Option<T>) -> String {
+            value.map_or("null".to_string(), |value| value.to_string())
+        }
+        fn counts(counts: Option<AllocationCounts>) -> String {
+            counts.map_or("null".to_string(), |counts| {
+                format!(
+                    "{{\"allocations\": {}, \"bytes\": {}}}",
+                    counts.allocations, counts.bytes
+                )
+            })
+        }
+        format!(
+            "{{\"peak_pending_events\": {}, \"event_list_bytes\": {}, \"peak_queue_length\": {}, \"queue_bytes\": {}, \"time_series_bytes\": {}, \"loop_allocations\": {}, \"event_allocations\": {}}}",
+            optional(self.peak_pending_events),
+            self.event_list_bytes,
+            self.peak_queue_length,
+            self.queue_bytes,
+            optional(self.time_series_bytes),
+            counts(self.loop_allocations),
+            counts(self.event_allocations)
+        )
+    }
+}
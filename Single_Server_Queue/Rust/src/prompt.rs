@@ -0,0 +1,347 @@
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+/// Invalid answers accepted per question before the program stops with an error
+const MAX_ATTEMPTS: usize = 3;
+
+/// Interactive questions with defaults, inline help and validation
+///
+/// Reads answers from any `BufRead`, so the same logic drives stdin and canned input.
+/// An empty answer (or end of input) takes the default, `?` prints the help text, and
+/// unparsable or invalid answers are re-asked with the reason. After `MAX_ATTEMPTS`
+/// of them the program exits with an error rather than run with a value nobody chose.
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+    fail_fast: bool,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            fail_fast: false,
+        }
+    }
+
+    /// Exit with an error on the first invalid answer instead of asking again
+    ///
+    /// Meant for answers piped in from a file or script, where nobody can correct
+    /// them and falling back to a default would silently run something else.
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    pub fn read_f64_with_default<F>(
+        &mut self,
+        prompt: &str,
+        default: f64,
+        help: &str,
+        validate: F,
+    ) -> f64
+    where
+        F: Fn(f64) -> Result<(), String>,
+    {
+        self.read_value(prompt, default, help, validate)
+    }
+
+    pub fn read_u64_with_default<F>(
+        &mut self,
+        prompt: &str,
+        default: u64,
+        help: &str,
+        validate: F,
+    ) -> u64
+    where
+        F: Fn(u64) -> Result<(), String>,
+    {
+        self.read_value(prompt, default, help, validate)
+    }
+
+    /// Whitespace-separated numbers on one line, e.g. a row of a matrix
+    pub fn read_f64s_with_default<F>(
+        &mut self,
+        prompt: &str,
+        default: &[f64],
+        help: &str,
+        validate: F,
+    ) -> Vec<f64>
+    where
+        F: Fn(&[f64]) -> Result<(), String>,
+    {
+        let shown = if default.is_empty() {
+            "none".to_string()
+        } else {
+            default
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        self.read_parsed(prompt, &shown, help, |answer| {
+            let values = answer
+                .split_whitespace()
+                .map(|value| value.parse::<f64>().map_err(|_| parse_error(value)))
+                .collect::<Result<Vec<_>, _>>()?;
+            validate(&values)?;
+            Ok(values)
+        })
+        .unwrap_or_else(|| default.to_vec())
+    }
+
+    /// Numbered menu; returns the zero-based index of the chosen option
+    pub fn read_choice(&mut self, prompt: &str, options: &[&str], default: usize) -> usize {
+        writeln!(self.output, "\n{}", prompt).unwrap();
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}. {}", i + 1, option).unwrap();
+        }
+
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(answer) = self.ask(&format!("Choose [default: {}]: ", default + 1)) else {
+                return default;
+            };
+            if answer.is_empty() {
+                return default;
+            }
+            match answer.parse::<usize>() {
+                Ok(choice) if choice > 0 && choice <= options.len() => return choice - 1,
+                _ => self.reject(
+                    prompt,
+                    &format!(
+                        "'{}' is not an option; enter a number from 1 to {}",
+                        answer,
+                        options.len()
+                    ),
+                ),
+            }
+        }
+        self.give_up(prompt)
+    }
+
+    /// Yes/no question; anything but an answer starting with 'y' or 'n' takes the default
+    pub fn confirm(&mut self, prompt: &str, default: bool) -> bool {
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        match self.ask(&format!("{} {}: ", prompt, hint)) {
+            Some(answer) if answer.to_lowercase().starts_with('y') => true,
+            Some(answer) if answer.to_lowercase().starts_with('n') => false,
+            _ => default,
+        }
+    }
+
+    fn read_value<T, F>(&mut self, prompt: &str, default: T, help: &str, validate: F) -> T
+    where
+        T: FromStr + Copy + std::fmt::Display,
+        F: Fn(T) -> Result<(), String>,
+    {
+        self.read_parsed(prompt, &default.to_string(), help, |answer| {
+            let value = answer.parse::<T>().map_err(|_| parse_error(answer))?;
+            validate(value)?;
+            Ok(value)
+        })
+        .unwrap_or(default)
+    }
+
+    /// Ask until `parse` accepts the answer; `None` means the default (shown as `shown_default`) applies
+    fn read_parsed<T, P>(
+        &mut self,
+        prompt: &str,
+        shown_default: &str,
+        help: &str,
+        parse: P,
+    ) -> Option<T>
+    where
+        P: Fn(&str) -> Result<T, String>,
+    {
+        let mut attempts = 0;
+        while attempts < MAX_ATTEMPTS {
+            let answer = self.ask(&format!(
+                "{} [default: {}, ? for help]: ",
+                prompt, shown_default
+            ))?;
+            if answer.is_empty() {
+                return None;
+            }
+            if answer == "?" {
+                for line in help.lines() {
+                    self.report(line);
+                }
+                continue;
+            }
+
+            attempts += 1;
+            match parse(&answer) {
+                Ok(value) => return Some(value),
+                Err(reason) => self.reject(prompt, &reason),
+            }
+        }
+        self.give_up(prompt)
+    }
+
+    /// Print `prompt` and read one trimmed line, or `None` at end of input
+    fn ask(&mut self, prompt: &str) -> Option<String> {
+        write!(self.output, "{}", prompt).unwrap();
+        self.output.flush().unwrap();
+
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                writeln!(self.output).unwrap();
+                None
+            }
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    /// Explain why an answer was not accepted, or stop the program in fail-fast mode
+    fn reject(&mut self, prompt: &str, reason: &str) {
+        if self.fail_fast {
+            fail(prompt, reason);
+        }
+        self.report(reason);
+    }
+
+    fn report(&mut self, message: &str) {
+        writeln!(self.output, "  {}", message).unwrap();
+    }
+
+    fn give_up(&mut self, prompt: &str) -> ! {
+        fail(
+            prompt,
+            &format!("no valid answer after {} attempts", MAX_ATTEMPTS),
+        )
+    }
+}
+
+/// Stop the program over an answer to `prompt` that cannot be used
+fn fail(prompt: &str, reason: &str) -> ! {
+    eprintln!(
+        "\nError: {}: {}",
+        prompt.trim().trim_end_matches(':'),
+        reason
+    );
+    std::process::exit(2)
+}
+
+fn parse_error(answer: &str) -> String {
+    if answer.contains(',') && answer.replace(',', ".").parse::<f64>().is_ok() {
+        format!(
+            "could not parse '{}' as a number; use '.' as decimal separator",
+            answer
+        )
+    } else {
+        format!("could not parse '{}' as a number", answer)
+    }
+}
+
+pub fn positive<T: PartialOrd + Default>(value: T) -> Result<(), String> {
+    if value > T::default() {
+        Ok(())
+    } else {
+        Err("must be greater than 0".to_string())
+    }
+}
+
+pub fn non_negative<T: PartialOrd + Default>(value: T) -> Result<(), String> {
+    if value >= T::default() {
+        Ok(())
+    } else {
+        Err("must not be negative".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answer the prompter's questions from `answers`, returning what it wrote
+    fn answering<T>(
+        answers: &str,
+        ask: impl FnOnce(&mut Prompter<&[u8], Vec<u8>>) -> T,
+    ) -> (T, String) {
+        let mut prompter = Prompter::new(answers.as_bytes(), Vec::new());
+        let value = ask(&mut prompter);
+        (value, String::from_utf8(prompter.output).unwrap())
+    }
+
+    #[test]
+    fn an_empty_answer_or_the_end_of_input_takes_the_default() {
+        let (value, _) = answering("\n", |p| {
+            p.read_f64_with_default("Rate:", 0.5, "", positive)
+        });
+        assert_eq!(value, 0.5);
+        let (value, _) = answering("", |p| p.read_u64_with_default("Count:", 7, "", positive));
+        assert_eq!(value, 7);
+    }
+
+    #[test]
+    fn an_invalid_answer_is_asked_again_with_the_reason() {
+        let (value, output) = answering("abc\n-1\n2.5\n", |p| {
+            p.read_f64_with_default("Rate:", 0.5, "", positive)
+        });
+        assert_eq!(value, 2.5);
+        assert!(output.contains("could not parse 'abc' as a number"));
+        assert!(output.contains("must be greater than 0"));
+        assert_eq!(
+            output.matches("Rate: [default: 0.5, ? for help]: ").count(),
+            3
+        );
+    }
+
+    #[test]
+    fn a_decimal_comma_gets_a_hint() {
+        let (value, output) = answering("0,5\n0.25\n", |p| {
+            p.read_f64_with_default("Rate:", 1.0, "", positive)
+        });
+        assert_eq!(value, 0.25);
+        assert!(output.contains("use '.' as decimal separator"));
+    }
+
+    #[test]
+    fn help_does_not_use_up_an_attempt() {
+        let (value, output) = answering("?\n?\n?\nx\ny\n3\n", |p| {
+            p.read_u64_with_default("Count:", 1, "How many\ncustomers", positive)
+        });
+        assert_eq!(value, 3);
+        assert_eq!(output.matches("  How many\n  customers\n").count(), 3);
+    }
+
+    #[test]
+    fn a_row_of_numbers_is_validated_as_a_whole() {
+        let (values, output) = answering("1 x\n1 2\n0.5 1.5 2\n", |p| {
+            p.read_f64s_with_default("Row:", &[], "", |row| {
+                if row.len() == 3 {
+                    Ok(())
+                } else {
+                    Err("need 3 values".to_string())
+                }
+            })
+        });
+        assert_eq!(values, [0.5, 1.5, 2.0]);
+        assert!(output.contains("Row: [default: none, ? for help]: "));
+        assert!(output.contains("could not parse 'x' as a number"));
+        assert!(output.contains("need 3 values"));
+    }
+
+    #[test]
+    fn a_menu_returns_the_zero_based_choice() {
+        let options = ["first", "second", "third"];
+        let (choice, output) = answering("4\n0\n3\n", |p| p.read_choice("Pick:", &options, 0));
+        assert_eq!(choice, 2);
+        assert!(output.contains("  2. second\n"));
+        assert!(output.contains("'4' is not an option; enter a number from 1 to 3"));
+        let (choice, _) = answering("\n", |p| p.read_choice("Pick:", &options, 1));
+        assert_eq!(choice, 1);
+    }
+
+    #[test]
+    fn a_confirmation_falls_back_to_the_default() {
+        assert!(answering("Yes\n", |p| p.confirm("Go?", false)).0);
+        assert!(!answering("n\n", |p| p.confirm("Go?", true)).0);
+        assert!(answering("maybe\n", |p| p.confirm("Go?", true)).0);
+        let (answer, output) = answering("", |p| p.confirm("Go?", false));
+        assert!(!answer);
+        assert!(output.starts_with("Go? [y/N]: "));
+    }
+}
@@ -0,0 +1,337 @@
+//! Python bindings, built with `--features python`
+//!
+//! Build and install into the active virtualenv with maturin, which picks up the
+//! features from `pyproject.toml`:
+//!
+//! ```text
+//! pip install maturin
+//! maturin develop --release
+//! ```
+//!
+//! or build a wheel with `maturin build --release`. Then, from Python:
+//!
+//! ```text
+//! import rust_single_server_queue as ssq
+//! sim = ssq.Simulation(0.8, 1.0, seed=42, time=100_000)
+//! stats = sim.run()
+//! waits = sim.time_series()["mean_wait_time"]   # [(t, value), ...]
+//! rows = ssq.sweep([0.5, 0.7, 0.9], 1.0, replications=5)
+//! ```
+//!
+//! Runs release the GIL, so other Python threads keep going during long simulations.
+//!
+//! The bindings are tested from Python, with the module installed as above:
+//!
+//! ```text
+//! pip install pytest
+//! pytest tests
+//! ```
+
+use crate::config::SimulationConfig;
+use crate::cost::CostModel;
+use crate::simulation::Simulation;
+use crate::time_series::{DEFAULT_MAX_SAMPLES, SimulationTimeSeries};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Simulated time the run lasts when no stop condition is given
+const DEFAULT_RUN_TIME: f64 = 100_000.0;
+
+/// Initial sample interval for runs stopped by event or customer count
+const DEFAULT_SAMPLE_INTERVAL: f64 = 100.0;
+
+/// When a run ends
+#[derive(Clone, Copy)]
+enum StopCondition {
+    Time(f64),
+    Events(u64),
+    Customers(u64),
+}
+
+impl StopCondition {
+    fn from_kwargs(
+        time: Option<f64>,
+        events: Option<u64>,
+        customers: Option<u64>,
+    ) -> PyResult<Self> {
+        match (time, events, customers) {
+            (None, None, None) => Ok(Self::Time(DEFAULT_RUN_TIME)),
+            (Some(time), None, None) if time > 0.0 => Ok(Self::Time(time)),
+            (Some(time), None, None) => Err(PyValueError::new_err(format!(
+                "time must be positive, got {}",
+                time
+            ))),
+            (None, Some(events), None) => Ok(Self::Events(events)),
+            (None, None, Some(customers)) => Ok(Self::Customers(customers)),
+            _ => Err(PyValueError::new_err(
+                "give at most one of time, events and customers",
+            )),
+        }
+    }
+
+    fn reached(self, simulation: &Simulation) -> bool {
+        let engine = simulation.engine();
+        if !engine.has_next_event() {
+            return true;
+        }
+        match self {
+            Self::Time(time) => engine.peek_next_time() >= time,
+            Self::Events(events) => simulation.event_count() >= events,
+            Self::Customers(customers) => simulation.statistics().served_customers() >= customers,
+        }
+    }
+}
+
+/// One queue simulation, run to its stop condition from Python
+///
+/// `lambda_` is the arrival rate (`lambda` is a Python keyword) and `mu` the
+/// service rate. At most one of `time`, `events` and `customers` ends the run;
+/// without any it lasts 100 000 time units.
+/// `sample_interval` is the initial spacing of the time series; it doubles
+/// whenever a series would exceed 5 000 samples.
+#[pyclass(name = "Simulation")]
+pub struct PySimulation {
+    // Python objects may be shared between threads, the samplers inside may not
+    run: Mutex<Run>,
+}
+
+struct Run {
+    simulation: Simulation,
+    stop_condition: StopCondition,
+    time_series: SimulationTimeSeries,
+}
+
+impl PySimulation {
+    fn lock(&self) -> MutexGuard<'_, Run> {
+        self.run.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[pymethods]
+impl PySimulation {
+    #[new]
+    #[pyo3(signature = (
+        lambda_,
+        mu,
+        *,
+        seed = None,
+        time = None,
+        events = None,
+        customers = None,
+        servers = 1,
+        warm_up_time = 0.0,
+        sample_interval = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        lambda_: f64,
+        mu: f64,
+        seed: Option<u64>,
+        time: Option<f64>,
+        events: Option<u64>,
+        customers: Option<u64>,
+        servers: usize,
+        warm_up_time: f64,
+        sample_interval: Option<f64>,
+    ) -> PyResult<Self> {
+        let stop_condition = StopCondition::from_kwargs(time, events, customers)?;
+        let config = SimulationConfig {
+            lambda: lambda_,
+            mu,
+            servers,
+            warm_up_time,
+            seed,
+            ..Default::default()
+        };
+        config.validate().map_err(PyValueError::new_err)?;
+
+        let sample_interval = sample_interval.unwrap_or(match stop_condition {
+            StopCondition::Time(time) => time / (DEFAULT_MAX_SAMPLES - 1) as f64,
+            _ => DEFAULT_SAMPLE_INTERVAL,
+        });
+        if sample_interval.is_nan() || sample_interval <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "sample_interval must be positive, got {}",
+                sample_interval
+            )));
+        }
+        Ok(Self {
+            run: Mutex::new(Run {
+                simulation: Simulation::new(config),
+                stop_condition,
+                time_series: SimulationTimeSeries::new(sample_interval, DEFAULT_MAX_SAMPLES),
+            }),
+        })
+    }
+
+    /// Run to the stop condition and return the final statistics as a dict
+    ///
+    /// The GIL is released while the simulation runs. Calling it again after the
+    /// run has ended returns the same statistics.
+    fn run<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        py.detach(|| {
+            let mut run = self.lock();
+            let Run {
+                simulation,
+                stop_condition,
+                time_series,
+            } = &mut *run;
+            run_to_stop(simulation, *stop_condition, time_series);
+        });
+        statistics_dict(py, &self.lock().simulation)
+    }
+
+    /// Samples of every metric as `{name: [(time, value), ...]}`, ready for `numpy.asarray`
+    fn time_series<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (name, samples) in self.lock().time_series.metrics() {
+            dict.set_item(name, samples)?;
+        }
+        Ok(dict)
+    }
+
+    /// Simulated time reached so far
+    #[getter]
+    fn now(&self) -> f64 {
+        self.lock().simulation.now()
+    }
+}
+
+/// Run every arrival rate in `lambdas` `replications` times against service rate `mu`
+///
+/// Returns one statistics dict per run with its `lambda` and `replication`
+/// added, a shape that loads straight into a pandas DataFrame. With a `seed`,
+/// replication i of every rate uses `seed + i`, so the rates are compared under
+/// common random numbers. With any of the cost rates set, every row also gets its
+/// `cost_per_time_unit` and the rows come back cheapest first.
+#[pyfunction]
+#[pyo3(signature = (
+    lambdas,
+    mu,
+    replications = 1,
+    *,
+    time = DEFAULT_RUN_TIME,
+    seed = None,
+    waiting_cost = 0.0,
+    server_cost = 0.0,
+    loss_cost = 0.0,
+))]
+#[allow(clippy::too_many_arguments)]
+fn sweep<'py>(
+    py: Python<'py>,
+    lambdas: Vec<f64>,
+    mu: f64,
+    replications: usize,
+    time: f64,
+    seed: Option<u64>,
+    waiting_cost: f64,
+    server_cost: f64,
+    loss_cost: f64,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let stop_condition = StopCondition::from_kwargs(Some(time), None, None)?;
+    for (name, rate) in [
+        ("waiting_cost", waiting_cost),
+        ("server_cost", server_cost),
+        ("loss_cost", loss_cost),
+    ] {
+        if !rate.is_finite() || rate < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "{} must be finite and non-negative, got {}",
+                name, rate
+            )));
+        }
+    }
+    let cost_model = CostModel {
+        waiting_cost,
+        server_cost,
+        loss_cost,
+    };
+    let mut rows = Vec::with_capacity(lambdas.len() * replications);
+    for lambda in lambdas {
+        for replication in 0..replications {
+            let config = SimulationConfig {
+                lambda,
+                mu,
+                seed: seed.map(|seed| seed.wrapping_add(replication as u64)),
+                ..Default::default()
+            };
+            config.validate().map_err(PyValueError::new_err)?;
+
+            let mut simulation = Simulation::new(config);
+            py.detach(|| {
+                while !stop_condition.reached(&simulation) {
+                    simulation.step();
+                }
+            });
+
+            let row = statistics_dict(py, &simulation)?;
+            row.set_item("lambda", lambda)?;
+            row.set_item("replication", replication)?;
+            let cost = cost_model.rate(simulation.statistics(), simulation.now());
+            if let Some(cost) = cost {
+                row.set_item("cost_per_time_unit", cost)?;
+            }
+            rows.push((cost, row));
+        }
+    }
+    if cost_model.is_configured() {
+        rows.sort_by(|a, b| a.0.unwrap_or(f64::NAN).total_cmp(&b.0.unwrap_or(f64::NAN)));
+    }
+    Ok(rows.into_iter().map(|(_, row)| row).collect())
+}
+
+/// Step `simulation` until `stop_condition`, sampling `time_series` along the way
+fn run_to_stop(
+    simulation: &mut Simulation,
+    stop_condition: StopCondition,
+    time_series: &mut SimulationTimeSeries,
+) {
+    time_series.queue_occupancy.record(
+        simulation.now(),
+        simulation.statistics().current_queue_length(),
+    );
+    while !stop_condition.reached(simulation) {
+        if let Some(event) = simulation.step() {
+            let now = simulation.now();
+            time_series.record_event(
+                event.event_type,
+                now,
+                simulation.total_arrivals(),
+                simulation.total_departures(),
+            );
+            time_series.sample_statistics(now, simulation.statistics());
+        }
+    }
+}
+
+/// Final statistics of a run; the batch-means interval is `None` until enough batches exist
+fn statistics_dict<'py>(py: Python<'py>, simulation: &Simulation) -> PyResult<Bound<'py, PyDict>> {
+    let now = simulation.now();
+    let stats = simulation.statistics();
+    let dict = PyDict::new(py);
+    dict.set_item("time", now)?;
+    dict.set_item("events", simulation.event_count())?;
+    dict.set_item("customers_served", stats.served_customers())?;
+    dict.set_item("customers_lost", stats.lost_customers())?;
+    dict.set_item("average_wait_time", stats.average_wait_time())?;
+    dict.set_item("wait_confidence_interval", stats.wait_confidence_interval())?;
+    dict.set_item("wait_cv", stats.wait_cv())?;
+    dict.set_item("average_queue_length", stats.average_queue_length(now))?;
+    dict.set_item(
+        "average_customers_in_system",
+        stats.average_customers_in_system(now),
+    )?;
+    dict.set_item("utilization", stats.utilization(now))?;
+    dict.set_item("throughput", stats.throughput(now))?;
+    dict.set_item("blocking_probability", stats.blocking_probability())?;
+    Ok(dict)
+}
+
+#[pymodule]
+fn rust_single_server_queue(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySimulation>()?;
+    module.add_function(wrap_pyfunction!(sweep, module)?)?;
+    Ok(())
+}
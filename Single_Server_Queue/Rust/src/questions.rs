@@ -0,0 +1,429 @@
+use crate::cli::CliOptions;
+use crate::prompt::{Prompter, non_negative, positive};
+use rust_single_server_queue::config::{
+    AdmissionSchedule, Breakdowns, ClosedArrivals, DEFAULT_MIN_QUANTUM, Deadlines,
+    InterruptedService, QueueDiscipline, RoundRobin, ServiceKind, ServiceModulation, SetupTime,
+    SimulationConfig, Standby,
+};
+use rust_single_server_queue::consistency::ConsistencyCheck;
+use rust_single_server_queue::distributions::{Distribution, Exponential, Truncated};
+use rust_single_server_queue::event_log::{AnomalyLimits, DEFAULT_EVENT_LOG_LENGTH};
+use rust_single_server_queue::gantt::DEFAULT_GANTT_CUSTOMERS;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::statistics::DEFAULT_THROUGHPUT_WINDOW;
+use std::io::{BufRead, Write};
+
+pub const LAMBDA_HELP: &str = "\
+λ is the mean number of customers arriving per time unit; the time between
+arrivals is exponential with mean 1/λ.";
+
+pub const MU_HELP: &str = "\
+μ is the mean number of customers one server completes per time unit; service
+times have mean 1/μ. With a waiting room the queue is only stable if the traffic
+intensity ρ = λ/μ is below 1, otherwise it grows without bound.";
+
+pub const SERVERS_HELP: &str = "\
+Number of parallel servers c. Customers arriving while all c are busy are lost.";
+
+const MAX_SERVICE_HELP: &str = "\
+Service draws above this bound are re-sampled, so no service takes longer.";
+
+const LOGNORMAL_HELP: &str = "\
+Standard deviation of the logarithm of a service time. The squared coefficient of
+variation is e^(σ²) - 1: 1.5 gives about 8.5, a few very long services among
+many short ones.";
+
+const MTTF_HELP: &str = "\
+Mean busy time until the server fails. The failure clock only runs while a
+customer is in service; an idle server does not fail.";
+
+const REPAIR_HELP: &str = "\
+Mean duration of a repair. Nobody is served while the server is down, and the
+time does not count as busy.";
+
+const SWITCHOVER_HELP: &str = "\
+Time the standby server needs to take over after the primary fails. Nobody is
+served in the meantime; 0 makes the failover instant.";
+
+const STANDBY_MTTF_HELP: &str = "\
+Mean busy time until the standby fails while it serves in place of the primary.
+With both servers down, nobody is served until one of them is repaired.";
+
+const STANDBY_REPAIR_HELP: &str = "\
+Mean duration of a repair of the standby server.";
+
+pub const STATIONS_HELP: &str = "\
+Number of single-server stations in the network.";
+
+pub const EXTERNAL_RATE_HELP: &str = "\
+Rate of customers arriving at this station from outside the network (γ).
+0 for stations that only receive customers routed from other stations.";
+
+pub const STATION_MU_HELP: &str = "\
+Service rate of this station's single server (μ).";
+
+pub const ROUTING_HELP: &str = "\
+One probability per station, separated by spaces: the chance that a customer
+finishing here moves on to that station. Whatever is missing to 1 is the
+chance of leaving the network.";
+
+pub const OVERFLOW_SERVERS_HELP: &str = "\
+Number of parallel servers at this station.";
+
+pub const OVERFLOW_QUEUE_HELP: &str = "\
+Customers that can wait here while every server is busy. An arrival finding the
+primary station full overflows to the secondary one; finding that full too, it
+is lost. 0 makes the station a pure loss system.";
+
+pub const TIME_HELP: &str = "\
+Simulated time units after which the run stops.";
+
+const EVENTS_HELP: &str = "\
+Number of arrival and departure events after which the run stops.";
+
+const CUSTOMERS_HELP: &str = "\
+Number of completed services after which the run stops.";
+
+pub const OBSERVED_HELP: &str = "\
+The value measured in the field that the simulation should reproduce, in the
+same time unit as the rates.";
+
+pub const TOLERANCE_HELP: &str = "\
+The search stops once the simulated metric is this close to the observed value.";
+
+pub const CALIBRATION_REPLICATIONS_HELP: &str = "\
+Independent runs averaged for every candidate rate. The same random numbers are
+reused for every candidate, so differences come from the rate, not from chance.";
+
+pub const CALIBRATION_CUSTOMERS_HELP: &str = "\
+Customers served in each replication.";
+
+pub const ITERATIONS_HELP: &str = "\
+Candidate rates tried after the two ends of the search range before giving up.";
+
+const STEADY_STATE_HELP: &str = "\
+The run ends once the sampled mean wait has stopped drifting, judged by the
+trend over its most recent samples, or at this time if it never settles.";
+
+const CUTOFF_HELP: &str = "\
+No customers arrive after this time; the run continues until everyone inside has left.";
+
+pub const WARM_UP_HELP: &str = "\
+Statistics from the start until this time are discarded, so averages are not
+biased by the empty initial state.";
+
+const SERVICE_LEVEL_HELP: &str = "\
+Wait limits, separated by spaces, for which the share of customers who waited
+at most that long is reported, e.g. \"1 5 10\". Leave empty for none.";
+
+/// Customers traced for database export; enough for trace analysis without
+/// holding a whole long run in memory
+#[cfg(feature = "sqlite")]
+const SQLITE_TRACE_LIMIT: usize = 100_000;
+
+/// A single queue as asked for, see [`read_queue`]
+pub struct QueueSetup {
+    pub config: SimulationConfig,
+    pub stop_condition: StopCondition,
+}
+
+/// Ask for the rates, service, breakdowns, stop condition and warm-up of
+/// a queue with `servers` servers, a loss system without `waiting_room`, and
+/// build its configuration together with the command line `options`
+pub fn read_queue<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    options: &CliOptions,
+    servers: usize,
+    waiting_room: bool,
+) -> QueueSetup {
+    let (lambda, mu) = read_rates(prompter, 1.0 / 1.25, 1.0, waiting_room);
+
+    let service_options = vec![
+        "Exponential",
+        "Truncated exponential (re-sampled above a maximum)",
+        "Deterministic (every service takes 1/μ)",
+        "Lognormal (high variance)",
+    ];
+    let service_choice = prompter.read_choice("Service time distribution:", &service_options, 0);
+    let service_kind = match service_choice {
+        2 => ServiceKind::Deterministic,
+        3 => ServiceKind::Lognormal(prompter.read_f64_with_default(
+            "Lognormal σ",
+            1.5,
+            LOGNORMAL_HELP,
+            positive,
+        )),
+        _ => ServiceKind::Exponential,
+    };
+    let max_service_time = match service_choice {
+        0 | 2 | 3 => None,
+        1 => {
+            let max = prompter.read_f64_with_default(
+                "Maximum service time",
+                5.0 / mu,
+                MAX_SERVICE_HELP,
+                |max| Truncated::new(Box::new(Exponential::new(mu)), max).validate(),
+            );
+            Some(max)
+        }
+        _ => unreachable!(),
+    };
+
+    let breakdowns = if prompter.confirm("\nModel server breakdowns?", false) {
+        let mean_time_to_failure =
+            prompter.read_f64_with_default("Mean time to failure", 100.0 / mu, MTTF_HELP, positive);
+        let mean_repair_time =
+            prompter.read_f64_with_default("Mean repair time", 5.0 / mu, REPAIR_HELP, positive);
+        let interrupted_options = vec![
+            "Resume with the remaining service time",
+            "Restart with a new service time",
+        ];
+        let interrupted_choice =
+            prompter.read_choice("Interrupted services:", &interrupted_options, 0);
+        Some(Breakdowns {
+            mean_time_to_failure,
+            mean_repair_time,
+            interrupted_service: match interrupted_choice {
+                0 => InterruptedService::Resume,
+                1 => InterruptedService::Restart,
+                _ => unreachable!(),
+            },
+        })
+    } else {
+        None
+    };
+    let standby = match breakdowns {
+        Some(b) if prompter.confirm("Add a warm standby server?", false) => Some(Standby {
+            switchover_time: prompter.read_f64_with_default(
+                "Switchover time",
+                0.0,
+                SWITCHOVER_HELP,
+                non_negative,
+            ),
+            mean_time_to_failure: prompter.read_f64_with_default(
+                "Mean time to failure of the standby",
+                b.mean_time_to_failure,
+                STANDBY_MTTF_HELP,
+                positive,
+            ),
+            mean_repair_time: prompter.read_f64_with_default(
+                "Mean repair time of the standby",
+                b.mean_repair_time,
+                STANDBY_REPAIR_HELP,
+                positive,
+            ),
+        }),
+        _ => None,
+    };
+
+    // Choose stopping condition
+    let stop_options = vec![
+        "Simulation time limit",
+        "Number of events processed",
+        "Number of customers served",
+        "Close to arrivals at a cutoff time, then drain the queue",
+        "Until the mean wait reaches a steady state",
+    ];
+    let stop_choice = prompter.read_choice("Stop simulation by:", &stop_options, 0);
+
+    let stop_condition = match stop_choice {
+        0 => {
+            let time = prompter.read_f64_with_default(
+                "Simulation time",
+                10_000_000.0,
+                TIME_HELP,
+                positive,
+            );
+            StopCondition::Time(time)
+        }
+        1 => {
+            let events = prompter.read_u64_with_default(
+                "Number of events",
+                20_000_000,
+                EVENTS_HELP,
+                positive,
+            );
+            StopCondition::Events(events)
+        }
+        2 => {
+            let customers = prompter.read_u64_with_default(
+                "Number of customers",
+                10_000_000,
+                CUSTOMERS_HELP,
+                positive,
+            );
+            StopCondition::Customers(customers)
+        }
+        3 => StopCondition::Drained,
+        4 => {
+            let time = prompter.read_f64_with_default(
+                "Maximum simulation time",
+                50_000_000.0,
+                STEADY_STATE_HELP,
+                positive,
+            );
+            StopCondition::SteadyState(time)
+        }
+        _ => unreachable!(),
+    };
+    let arrival_cutoff = match stop_condition {
+        StopCondition::Drained => Some(prompter.read_f64_with_default(
+            "Arrival cutoff time",
+            1_000_000.0,
+            CUTOFF_HELP,
+            positive,
+        )),
+        _ => None,
+    };
+
+    let warm_up_time = prompter.read_f64_with_default(
+        "Warm-up period (0 = none)",
+        0.0,
+        WARM_UP_HELP,
+        non_negative,
+    );
+
+    let service_level_targets = prompter.read_f64s_with_default(
+        "Service level wait targets",
+        &[],
+        SERVICE_LEVEL_HELP,
+        |targets| targets.iter().try_for_each(|target| non_negative(*target)),
+    );
+
+    #[cfg(feature = "sqlite")]
+    let sqlite_trace_limit = if options.sqlite_path.is_some() {
+        SQLITE_TRACE_LIMIT
+    } else {
+        0
+    };
+    #[cfg(not(feature = "sqlite"))]
+    let sqlite_trace_limit = 0;
+    let gantt_customers = options.gantt_customers.unwrap_or(DEFAULT_GANTT_CUSTOMERS);
+    let customer_trace_limit = if options.gantt.is_some() {
+        sqlite_trace_limit.max(gantt_customers)
+    } else {
+        sqlite_trace_limit
+    };
+
+    let config = SimulationConfig {
+        lambda,
+        mu,
+        servers,
+        waiting_room,
+        max_queue_length: options.max_queue_length.filter(|_| waiting_room),
+        explosion_limit: options.explosion_limit,
+        arrival_kind: options.arrival_kind,
+        service_kind,
+        max_service_time,
+        service_modulation: options.wait_speed_up.map(|(per_wait, max_factor)| {
+            ServiceModulation::Linear {
+                per_wait,
+                max_factor,
+            }
+        }),
+        initial_queue: options.initial_queue,
+        admission: options
+            .admission_periods
+            .clone()
+            .map(|open_periods| AdmissionSchedule {
+                open_periods,
+                closed_arrivals: if options.defer_closed {
+                    ClosedArrivals::Deferred
+                } else {
+                    ClosedArrivals::Lost
+                },
+            }),
+        arrival_cutoff,
+        breakdowns,
+        standby,
+        setup: options.setup_mean.map(|mean| SetupTime {
+            mean,
+            kind: if options.setup_deterministic {
+                ServiceKind::Deterministic
+            } else {
+                ServiceKind::Exponential
+            },
+        }),
+        deadlines: options.deadline_mean.map(|mean| Deadlines {
+            mean,
+            kind: if options.deadline_deterministic {
+                ServiceKind::Deterministic
+            } else {
+                ServiceKind::Exponential
+            },
+            drop_late: options.drop_late,
+        }),
+        discipline: if options.edf {
+            QueueDiscipline::EarliestDeadlineFirst
+        } else if options.quantum.is_some() {
+            QueueDiscipline::RoundRobin
+        } else {
+            QueueDiscipline::Fifo
+        },
+        round_robin: options.quantum.map(|quantum| RoundRobin {
+            quantum,
+            min_quantum: options.min_quantum.unwrap_or(DEFAULT_MIN_QUANTUM),
+        }),
+        parameter_changes: options.parameter_changes.clone(),
+        warm_up_time,
+        service_level_targets,
+        customer_trace_limit,
+        seed: options.seed,
+        rng_backend: options.rng_backend,
+        instrumentation: options.instrument,
+        clock_audit: options.clock_audit,
+        throughput_window: options
+            .throughput_window
+            .unwrap_or(DEFAULT_THROUGHPUT_WINDOW),
+        statistics_window: options.statistics_window,
+        event_log_length: options.event_log_length.unwrap_or(DEFAULT_EVENT_LOG_LENGTH),
+        anomaly_limits: AnomalyLimits {
+            wait_multiple: options
+                .anomaly_wait_multiple
+                .or(AnomalyLimits::default().wait_multiple),
+            max_queue_length: options.anomaly_queue_length,
+        },
+        consistency_check: options
+            .consistency_tolerance
+            .map(|tolerance| ConsistencyCheck {
+                tolerance,
+                ..Default::default()
+            }),
+    };
+
+    QueueSetup {
+        config,
+        stop_condition,
+    }
+}
+
+/// Ask for λ and μ, asking again if an unstable queue (λ ≥ μ) is not confirmed
+pub fn read_rates<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    default_lambda: f64,
+    default_mu: f64,
+    waiting_room: bool,
+) -> (f64, f64) {
+    loop {
+        let lambda = prompter.read_f64_with_default(
+            "Arrival rate (λ)",
+            default_lambda,
+            LAMBDA_HELP,
+            positive,
+        );
+        let mu = prompter.read_f64_with_default("Service rate (μ)", default_mu, MU_HELP, |mu| {
+            Exponential::new(mu).validate()
+        });
+
+        if !waiting_room || lambda < mu {
+            return (lambda, mu);
+        }
+        println!(
+            "  Warning: λ ≥ μ (ρ = {:.4}), the queue is unstable and grows without bound",
+            lambda / mu
+        );
+        if prompter.confirm("  Continue anyway?", false) {
+            return (lambda, mu);
+        }
+    }
+}
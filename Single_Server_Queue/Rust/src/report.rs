@@ -0,0 +1,622 @@
+use rust_single_server_queue::config::{QueueDiscipline, ServiceKind, SimulationConfig};
+use rust_single_server_queue::cost::CostModel;
+use rust_single_server_queue::profiling::MemoryProfile;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::{
+    allen_cunneen_wait, erlang_b, erlang_c, mm1_wait_quantile, mm1k_customers_distribution,
+};
+use std::fmt::Write;
+
+/// How the observed-vs-theoretical comparison is printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The labelled sections of the regular results
+    #[default]
+    Plain,
+    /// One JSON object, non-finite numbers as `null`
+    Json,
+    /// A GitHub-flavored Markdown table, non-finite numbers as `n/a`
+    Markdown,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "json" => Ok(Self::Json),
+            "markdown" => Ok(Self::Markdown),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Decimals of the printed metrics unless `--precision` says otherwise
+pub const DEFAULT_PRECISION: usize = 4;
+
+/// Counts from which `--scientific` switches to scientific notation
+const SCIENTIFIC_FROM: f64 = 1e5;
+
+/// How the plain and markdown results print numbers; JSON always keeps full precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Decimals of metrics such as waits and probabilities; times, costs and
+    /// percentages get two fewer, blocking probabilities two more
+    pub precision: usize,
+    /// Print large counts, such as events or customers, in scientific notation
+    pub scientific: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            scientific: false,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// A metric
+    pub fn value(self, value: f64) -> String {
+        format!("{:.*}", self.precision, value)
+    }
+
+    /// A probability that is typically small, such as blocking, to two decimals more than a metric
+    pub fn fine(self, value: f64) -> String {
+        format!("{:.*}", self.precision + 2, value)
+    }
+
+    /// A time, cost or other total, to two decimals fewer than a metric
+    pub fn coarse(self, value: f64) -> String {
+        format!("{:.*}", self.coarse_precision(), value)
+    }
+
+    /// A fraction as a percentage, e.g. `12.34%`
+    pub fn percent(self, fraction: f64) -> String {
+        format!("{:.*}%", self.coarse_precision(), fraction * 100.0)
+    }
+
+    /// A relative deviation as a signed percentage, e.g. `+1.50%`
+    pub fn signed_percent(self, fraction: f64) -> String {
+        format!("{:+.*}%", self.coarse_precision(), fraction * 100.0)
+    }
+
+    /// A count of events or customers
+    pub fn count(self, count: u64) -> String {
+        if self.scientific && count as f64 >= SCIENTIFIC_FROM {
+            format!("{:.*e}", self.precision, count as f64)
+        } else {
+            count.to_string()
+        }
+    }
+
+    /// A large rate such as events per second, whole or in scientific notation
+    pub fn large_rate(self, rate: f64) -> String {
+        if self.scientific && rate >= SCIENTIFIC_FROM {
+            format!("{:.*e}", self.precision, rate)
+        } else {
+            format!("{:.0}", rate)
+        }
+    }
+
+    fn coarse_precision(self) -> usize {
+        self.precision.saturating_sub(2)
+    }
+}
+
+/// One metric measured by the simulation next to its closed-form value
+///
+/// `theoretical` is NaN where no closed form applies, e.g. the wait of an
+/// unstable queue; the percent error is then NaN as well.
+pub struct Comparison {
+    /// Short name to select the metric by, see [`METRIC_KEYS`]
+    pub key: &'static str,
+    pub metric: &'static str,
+    pub observed: f64,
+    pub theoretical: f64,
+}
+
+impl Comparison {
+    /// Relative deviation of the observed value from theory, in percent
+    pub fn percent_error(&self) -> f64 {
+        if self.theoretical == 0.0 {
+            return f64::NAN;
+        }
+        (self.observed - self.theoretical) / self.theoretical * 100.0
+    }
+}
+
+/// Keys of every metric [`comparisons`] can produce, for `--validate-metrics`
+pub const METRIC_KEYS: [&str; 9] = [
+    "wait",
+    "waiting",
+    "delayed_wait",
+    "queue",
+    "system",
+    "utilization",
+    "throughput",
+    "blocking",
+    "carried_load",
+];
+
+/// Why the closed forms behind [`comparisons`] do not describe `config`, if they do not
+pub fn theory_mismatch(config: &SimulationConfig) -> Option<&'static str> {
+    if config.arrival_kind != ServiceKind::Exponential {
+        Some("non-Poisson arrivals are not part of the M/M/1 or Erlang-B formulas")
+    } else if config.service_kind == ServiceKind::Deterministic {
+        Some("deterministic service makes the model M/D/1, not M/M/1")
+    } else if config.service_kind != ServiceKind::Exponential {
+        Some("non-exponential service makes the model M/G/1, not M/M/1")
+    } else if config.max_service_time.is_some() {
+        Some("truncated service times have no closed form here")
+    } else if config.breakdowns.is_some() {
+        Some("breakdowns are not part of the M/M/1 or Erlang-B formulas")
+    } else if config.setup.is_some() {
+        Some("setup times are not part of the M/M/1 or Erlang-B formulas")
+    } else if config.service_modulation.is_some() {
+        Some("services that speed up with the wait are not part of the M/M/1 formulas")
+    } else if config
+        .deadlines
+        .is_some_and(|deadlines| deadlines.drop_late)
+    {
+        Some("customers dropped at their deadline are not part of the M/M/1 formulas")
+    } else if config.discipline == QueueDiscipline::RoundRobin {
+        Some("round robin waits only run until the first quantum, unlike the M/M/1 wait")
+    } else if !config.parameter_changes.is_empty() {
+        Some("parameters change during the run, so no single model describes it")
+    } else if config.admission.is_some() || config.arrival_cutoff.is_some() {
+        Some("arrivals stop, so the run has no steady state to compare with")
+    } else if config.max_queue_length.is_some() {
+        Some("a queue cap makes the model M/M/1/K, not M/M/1")
+    } else {
+        None
+    }
+}
+
+/// Outcome of checking one comparison against a relative tolerance
+pub enum Verdict {
+    Pass,
+    Fail,
+    /// Not checked, with the reason
+    Skipped(&'static str),
+}
+
+/// Check each row against `tolerance`, a relative error such as 0.05 for 5%
+pub fn validate(rows: &[Comparison], tolerance: f64) -> Vec<Verdict> {
+    rows.iter()
+        .map(|row| {
+            let error = row.percent_error().abs() / 100.0;
+            if row.theoretical.is_nan() {
+                Verdict::Skipped("no steady state, ρ ≥ 1")
+            } else if error.is_nan() {
+                Verdict::Skipped("the relative error to a theoretical 0 is undefined")
+            } else if error <= tolerance {
+                Verdict::Pass
+            } else {
+                Verdict::Fail
+            }
+        })
+        .collect()
+}
+
+/// The metrics the plain output compares against M/M/c or Erlang-B theory
+pub fn comparisons(simulation: &Simulation) -> Vec<Comparison> {
+    let total_time = simulation.now();
+    let stats = simulation.statistics();
+    let config = simulation.config();
+    let (lambda, mu) = (config.lambda, config.mu);
+    let row = |key, metric, observed, theoretical| Comparison {
+        key,
+        metric,
+        observed,
+        theoretical,
+    };
+
+    if config.is_loss_system() {
+        let offered_load = lambda / mu;
+        let blocking = erlang_b(config.servers, offered_load);
+        let carried_load = offered_load * (1.0 - blocking);
+        return vec![
+            row(
+                "blocking",
+                "Blocking probability",
+                stats.blocking_probability(),
+                blocking,
+            ),
+            row(
+                "carried_load",
+                "Carried load",
+                stats.average_busy_servers(total_time),
+                carried_load,
+            ),
+            row(
+                "utilization",
+                "Utilization",
+                stats.utilization(total_time),
+                carried_load / config.servers as f64,
+            ),
+            row(
+                "throughput",
+                "Throughput",
+                stats.throughput(total_time),
+                lambda * (1.0 - blocking),
+            ),
+        ];
+    }
+
+    // M/M/c through the Erlang-C probability of waiting, which is ρ for one server.
+    // Without a steady state only utilization and throughput have a limit
+    let capacity = config.servers as f64 * mu;
+    let (waiting, wait) = match erlang_c(config.servers, lambda / mu) {
+        Some(waiting) => (waiting, waiting / (capacity - lambda)),
+        None => (f64::NAN, f64::NAN),
+    };
+    vec![
+        row("wait", "Wait time", stats.average_wait_time(), wait),
+        row(
+            "waiting",
+            "Probability of waiting",
+            stats.probability_of_waiting(),
+            waiting,
+        ),
+        // The wait of those who wait is exponential with rate cμ - λ
+        row(
+            "delayed_wait",
+            "Wait when delayed",
+            stats.conditional_mean_wait().unwrap_or(f64::NAN),
+            wait / waiting,
+        ),
+        row(
+            "queue",
+            "Queue length",
+            stats.average_queue_length(total_time),
+            lambda * wait,
+        ),
+        row(
+            "system",
+            "Customers in system",
+            stats.average_customers_in_system(total_time),
+            lambda * wait + lambda / mu,
+        ),
+        row(
+            "utilization",
+            "Utilization",
+            stats.utilization(total_time),
+            (lambda / capacity).min(1.0),
+        ),
+        row(
+            "throughput",
+            "Throughput",
+            stats.throughput(total_time),
+            lambda.min(capacity),
+        ),
+    ]
+}
+
+/// Markdown table with a percent-error column, ready to paste into an issue or report
+pub fn markdown_table(rows: &[Comparison], numbers: NumberFormat) -> String {
+    fn cell(value: f64, format: impl Fn(f64) -> String) -> String {
+        if value.is_finite() {
+            format(value)
+        } else {
+            "n/a".to_string()
+        }
+    }
+
+    let mut table = String::new();
+    writeln!(table, "| Metric | Observed | Theoretical | Error (%) |").unwrap();
+    writeln!(table, "| --- | ---: | ---: | ---: |").unwrap();
+    for row in rows {
+        writeln!(
+            table,
+            "| {} | {} | {} | {} |",
+            row.metric,
+            cell(row.observed, |value| numbers.value(value)),
+            cell(row.theoretical, |value| numbers.value(value)),
+            cell(row.percent_error(), |value| numbers.coarse(value))
+        )
+        .unwrap();
+    }
+    table
+}
+
+/// JSON has no NaN or infinity, so those become `null`
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// The `comparison` entries shared by the simulated and the analytic report
+fn json_rows(rows: &[Comparison]) -> String {
+    rows.iter()
+        .map(|row| {
+            format!(
+                "    {{\"metric\": \"{}\", \"observed\": {}, \"theoretical\": {}, \"percent_error\": {}}}",
+                row.metric,
+                json_number(row.observed),
+                json_number(row.theoretical),
+                json_number(row.percent_error())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+/// JSON object with the simulated time, one entry per metric, the run's annotations
+/// and its cost, `null` when no cost rate is set
+pub fn json_report(
+    simulation: &Simulation,
+    rows: &[Comparison],
+    memory: &MemoryProfile,
+    cost_model: &CostModel,
+) -> String {
+    let cost = if cost_model.is_configured() {
+        cost_model
+            .evaluate(simulation.statistics(), simulation.now())
+            .to_json()
+    } else {
+        "null".to_string()
+    };
+    format!(
+        "{{\n  \"analytic\": false,\n  \"simulation_time\": {},\n  \"events\": {},\n  \"customers_served\": {},\n  \"comparison\": [\n{}\n  ],\n  \"annotations\": {},\n  \"memory\": {},\n  \"cost\": {}\n}}\n",
+        json_number(simulation.now()),
+        simulation.event_count(),
+        simulation.statistics().served_customers(),
+        json_rows(rows),
+        simulation.annotations().to_json(),
+        memory.to_json(),
+        cost
+    )
+}
+
+/// Wait-time quantiles reported for M/M/1, where the wait distribution is known
+pub const ANALYTIC_PERCENTILES: [f64; 4] = [0.5, 0.9, 0.95, 0.99];
+
+/// Closed-form metrics of a configuration, for `--analytic-only` and `--analytic-compare`
+pub struct AnalyticModel {
+    /// Kendall notation of the model the formulas describe, e.g. `M/M/1/10`
+    pub name: String,
+    /// The metrics, with NaN on the observed side
+    pub rows: Vec<Comparison>,
+    /// `(p, t)` with P(Wq ≤ t) = p; empty unless the model is M/M/1
+    pub wait_percentiles: Vec<(f64, f64)>,
+}
+
+/// The exact steady-state metrics of `config`, or why it has none
+///
+/// Covers M/M/c, M/G/1 by Pollaczek-Khinchine, M/M/1/K and loss systems, whose
+/// Erlang-B blocking holds for any service distribution.
+pub fn analytic_model(config: &SimulationConfig) -> Result<AnalyticModel, String> {
+    let unsupported = if config.arrival_kind != ServiceKind::Exponential {
+        Some("non-Poisson arrivals are compared with G/M/1 after a run, not here")
+    } else if config.max_service_time.is_some() {
+        Some("truncated service times have no closed form")
+    } else if config.breakdowns.is_some() {
+        Some("breakdowns are only covered by the simulated results")
+    } else if config.setup.is_some() {
+        Some("setup times are only covered by the simulated results")
+    } else if config.service_modulation.is_some() {
+        Some("services that speed up with the wait have no closed form")
+    } else if config
+        .deadlines
+        .is_some_and(|deadlines| deadlines.drop_late)
+    {
+        Some("customers dropped at their deadline have no closed form")
+    } else if config.discipline == QueueDiscipline::RoundRobin {
+        Some("round robin has no closed form; processor sharing is its limit")
+    } else if !config.parameter_changes.is_empty() {
+        Some("parameters change during the run, so no single model describes it")
+    } else if config.admission.is_some() || config.arrival_cutoff.is_some() {
+        Some("arrivals stop, so there is no steady state")
+    } else {
+        None
+    };
+    if let Some(reason) = unsupported {
+        return Err(reason.to_string());
+    }
+
+    let (lambda, mu, servers) = (config.lambda, config.mu, config.servers);
+    let offered_load = lambda / mu;
+    let exponential = config.service_kind == ServiceKind::Exponential;
+    let row = |key, metric, theoretical| Comparison {
+        key,
+        metric,
+        observed: f64::NAN,
+        theoretical,
+    };
+
+    if config.is_loss_system() {
+        let blocking = erlang_b(servers, offered_load);
+        let carried_load = offered_load * (1.0 - blocking);
+        return Ok(AnalyticModel {
+            name: format!(
+                "M/{}/{}/{}",
+                if exponential { "M" } else { "G" },
+                servers,
+                servers
+            ),
+            rows: vec![
+                row("blocking", "Blocking probability", blocking),
+                row("carried_load", "Carried load", carried_load),
+                row("utilization", "Utilization", carried_load / servers as f64),
+                row("throughput", "Throughput", lambda * (1.0 - blocking)),
+            ],
+            wait_percentiles: Vec::new(),
+        });
+    }
+
+    if let Some(limit) = config.max_queue_length {
+        if servers > 1 || !exponential {
+            return Err(
+                "a capped queue only has a closed form with one server and exponential service (M/M/1/K)"
+                    .to_string(),
+            );
+        }
+        // K counts the customer in service besides the waiting ones
+        let capacity = limit + 1;
+        let p = mm1k_customers_distribution(offered_load, capacity);
+        let blocking = p[capacity];
+        let in_system: f64 = p.iter().enumerate().map(|(n, p)| n as f64 * p).sum();
+        let busy = 1.0 - p[0];
+        let throughput = lambda * (1.0 - blocking);
+        return Ok(AnalyticModel {
+            name: format!("M/M/1/{}", capacity),
+            rows: vec![
+                row("wait", "Wait time", (in_system - busy) / throughput),
+                // Of the arrivals not blocked, those finding the server busy
+                row(
+                    "waiting",
+                    "Probability of waiting",
+                    (busy - blocking) / (1.0 - blocking),
+                ),
+                row("queue", "Queue length", in_system - busy),
+                row("system", "Customers in system", in_system),
+                row("utilization", "Utilization", busy),
+                row("throughput", "Throughput", throughput),
+                row("blocking", "Blocking probability", blocking),
+            ],
+            wait_percentiles: Vec::new(),
+        });
+    }
+
+    let rho = offered_load / servers as f64;
+    if rho >= 1.0 {
+        return Err(format!(
+            "ρ = λ/(cμ) = {:.4} is not below 1, so the queue grows without bound and has no \
+             steady state; lower λ, raise μ or cap the queue with --max-queue",
+            rho
+        ));
+    }
+    let (name, waiting, wait) = if exponential {
+        // Erlang-C is ρ for one server; stability was checked above
+        let waiting = erlang_c(servers, offered_load).unwrap_or(f64::NAN);
+        (
+            format!("M/M/{}", servers),
+            waiting,
+            waiting / (servers as f64 * mu - lambda),
+        )
+    } else if servers == 1 {
+        // Kingman's formula with Poisson arrivals is Pollaczek-Khinchine, exact for M/G/1
+        let wait = allen_cunneen_wait(1, 1.0 / lambda, 1.0 / mu, 1.0, config.service_kind.scv())
+            .unwrap_or(f64::NAN);
+        let name = if config.service_kind == ServiceKind::Deterministic {
+            "M/D/1"
+        } else {
+            "M/G/1"
+        };
+        // An arrival waits exactly when it finds the server busy
+        (name.to_string(), rho, wait)
+    } else {
+        return Err(
+            "several servers with non-exponential service have no closed form, only the \
+             Allen-Cunneen approximation a simulated run prints"
+                .to_string(),
+        );
+    };
+    let wait_percentiles = if exponential && servers == 1 {
+        ANALYTIC_PERCENTILES
+            .iter()
+            .filter_map(|&p| Some((p, mm1_wait_quantile(lambda, mu, p)?)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(AnalyticModel {
+        name,
+        rows: vec![
+            row("wait", "Wait time", wait),
+            row("waiting", "Probability of waiting", waiting),
+            row("delayed_wait", "Wait when delayed", wait / waiting),
+            row("queue", "Queue length", lambda * wait),
+            row(
+                "system",
+                "Customers in system",
+                lambda * wait + offered_load,
+            ),
+            row("utilization", "Utilization", rho),
+            row("throughput", "Throughput", lambda),
+        ],
+        wait_percentiles,
+    })
+}
+
+/// The simulated value of the metric named `key`, one of [`METRIC_KEYS`]
+fn observed(simulation: &Simulation, key: &str) -> f64 {
+    let (total_time, stats) = (simulation.now(), simulation.statistics());
+    match key {
+        "wait" => stats.average_wait_time(),
+        "waiting" => stats.probability_of_waiting(),
+        "delayed_wait" => stats.conditional_mean_wait().unwrap_or(f64::NAN),
+        "queue" => stats.average_queue_length(total_time),
+        "system" => stats.average_customers_in_system(total_time),
+        "utilization" => stats.utilization(total_time),
+        "throughput" => stats.throughput(total_time),
+        "blocking" => stats.blocking_probability(),
+        "carried_load" => stats.average_busy_servers(total_time),
+        _ => f64::NAN,
+    }
+}
+
+/// The analytic `rows` with the simulated values filled in beside them
+pub fn analytic_comparisons(simulation: &Simulation, rows: Vec<Comparison>) -> Vec<Comparison> {
+    rows.into_iter()
+        .map(|row| Comparison {
+            observed: observed(simulation, row.key),
+            ..row
+        })
+        .collect()
+}
+
+/// Markdown table of the analytic metrics, the wait percentiles as rows of their own
+pub fn analytic_markdown_table(model: &AnalyticModel, numbers: NumberFormat) -> String {
+    let mut table = String::new();
+    writeln!(table, "| Metric | Value |").unwrap();
+    writeln!(table, "| --- | ---: |").unwrap();
+    for row in &model.rows {
+        writeln!(
+            table,
+            "| {} | {} |",
+            row.metric,
+            numbers.value(row.theoretical)
+        )
+        .unwrap();
+    }
+    for (p, wait) in &model.wait_percentiles {
+        writeln!(
+            table,
+            "| Wait time P{} | {} |",
+            (p * 100.0).round(),
+            numbers.value(*wait)
+        )
+        .unwrap();
+    }
+    table
+}
+
+/// The JSON report of a run that was never simulated: `analytic` is true, the
+/// observed values are `null` and there are no events, annotations or memory
+pub fn analytic_json_report(model: &AnalyticModel) -> String {
+    let percentiles = model
+        .wait_percentiles
+        .iter()
+        .map(|(p, wait)| {
+            format!(
+                "{{\"percentile\": {}, \"wait\": {}}}",
+                json_number(p * 100.0),
+                json_number(*wait)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{{\n  \"analytic\": true,\n  \"model\": \"{}\",\n  \"simulation_time\": null,\n  \"events\": 0,\n  \"customers_served\": 0,\n  \"comparison\": [\n{}\n  ],\n  \"wait_percentiles\": [{}],\n  \"annotations\": [],\n  \"memory\": null\n}}\n",
+        model.name,
+        json_rows(&model.rows),
+        percentiles
+    )
+}
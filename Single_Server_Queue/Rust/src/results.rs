@@ -0,0 +1,1004 @@
+use crate::report::{self, NumberFormat, OutputFormat};
+use crate::studies::has_mg1_theory;
+use rust_single_server_queue::clock::CLOCK_AUDIT_THRESHOLD;
+use rust_single_server_queue::config::{
+    Breakdowns, QueueDiscipline, ServiceKind, SetupTime, SimulationConfig,
+};
+use rust_single_server_queue::cost::CostModel;
+use rust_single_server_queue::event::EventType;
+use rust_single_server_queue::profiling::MemoryProfile;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::{
+    allen_cunneen_wait, erlang_b, gm1_root, gm1_wait, mg1_processor_sharing_sojourn,
+    mm1_breakdown_wait, mm1_wait_cv, setup_extra_wait,
+};
+
+/// Print the results of a finished run in `format`: the statistics, their costs
+/// and the theory the run can be held against
+pub fn print_results(
+    simulation: &Simulation,
+    elapsed_secs: f64,
+    memory: &MemoryProfile,
+    cost_model: &CostModel,
+    format: OutputFormat,
+    numbers: NumberFormat,
+) {
+    let total_time = simulation.now();
+    let event_count = simulation.event_count();
+    let stats = simulation.statistics();
+
+    println!("=== Simulation Results ===");
+    println!("Total simulation time: {}", numbers.coarse(total_time));
+    println!("Events processed: {}", numbers.count(event_count));
+    println!(
+        "Customers served: {}",
+        numbers.count(stats.served_customers())
+    );
+    println!(
+        "Average wait time: {}",
+        numbers.value(stats.average_wait_time())
+    );
+    println!(
+        "Average queue length: {}",
+        numbers.value(stats.average_queue_length(total_time))
+    );
+    println!(
+        "Average customers in system: {}",
+        numbers.value(stats.average_customers_in_system(total_time))
+    );
+    println!(
+        "Server utilization: {}",
+        numbers.value(stats.utilization(total_time))
+    );
+    if simulation.config().waiting_room {
+        println!(
+            "Probability of waiting: {}",
+            numbers.value(stats.probability_of_waiting())
+        );
+        if let Some(mean) = stats.conditional_mean_wait() {
+            let quantile = |q| stats.conditional_wait_quantile(q).unwrap_or(f64::NAN);
+            println!(
+                "Wait when delayed ({} customers, {} at service start): mean {}, std dev {}, p50 {}, p90 {}, p99 {}",
+                numbers.count(stats.delayed_services()),
+                numbers.value(stats.delay_probability().unwrap_or(0.0)),
+                numbers.value(mean),
+                numbers.value(stats.conditional_wait_variance().unwrap_or(0.0).sqrt()),
+                numbers.value(quantile(0.5)),
+                numbers.value(quantile(0.9)),
+                numbers.value(quantile(0.99))
+            );
+        }
+    }
+    println!(
+        "System throughput: {}",
+        numbers.value(stats.throughput(total_time))
+    );
+    if let Some(windows) = stats.departure_windows(total_time) {
+        print!(
+            "Departures per {} time units ({} windows): mean {}, variance {}",
+            windows.width,
+            windows.windows,
+            numbers.value(windows.mean),
+            numbers.value(windows.variance)
+        );
+        match windows.index_of_dispersion() {
+            Some(dispersion) => println!(", index of dispersion {}", numbers.value(dispersion)),
+            None => println!(),
+        }
+    }
+    if let Some(window) = stats.windowed_stats(total_time) {
+        println!(
+            "Last {} time units{}: mean wait {}, mean queue {}, utilization {}, throughput {}",
+            window.span,
+            if window.coarse {
+                " (coarse buckets)"
+            } else {
+                ""
+            },
+            numbers.value(window.mean_wait),
+            numbers.value(window.mean_queue_length),
+            numbers.value(window.utilization),
+            numbers.value(window.throughput)
+        );
+    }
+    if let Some((mean, half_width)) = stats.batch_means_wait() {
+        println!(
+            "Mean wait 95% CI (batch means, {} batches): {} ± {}",
+            stats.wait_batch_count(),
+            numbers.value(mean),
+            numbers.value(half_width)
+        );
+    }
+    if let Some((estimate, standard_error)) = stats.jackknife_wait() {
+        println!(
+            "Mean wait (jackknife): {}, standard error {}",
+            numbers.value(estimate),
+            numbers.value(standard_error)
+        );
+    }
+    if let Some(cv) = stats.wait_cv() {
+        println!("Wait coefficient of variation: {}", numbers.value(cv));
+    }
+    // The mean slowdown is infinite for exponential service, so the median stands in for it
+    if let (Some(median), Some(p95)) = (stats.slowdown_quantile(0.5), stats.slowdown_quantile(0.95))
+    {
+        println!(
+            "Slowdown (sojourn / service): median {}, P95 {}, max {}",
+            numbers.value(median),
+            numbers.value(p95),
+            numbers.value(stats.max_slowdown())
+        );
+    }
+    for (target, level) in stats.service_levels() {
+        println!(
+            "Served within a wait of {}: {}",
+            target,
+            numbers.percent(level)
+        );
+    }
+    if let Some(autocorrelation) = stats.service_autocorrelation() {
+        println!(
+            "Service time lag-1 autocorrelation: {}",
+            numbers.value(autocorrelation)
+        );
+    }
+    if simulation.config().service_modulation.is_some()
+        && let Some(correlation) = stats.wait_service_correlation()
+    {
+        println!(
+            "Correlation of wait and service time: {}",
+            numbers.value(correlation)
+        );
+    }
+    if let Some(fraction) = simulation
+        .server()
+        .service_distribution()
+        .truncation_fraction()
+    {
+        println!(
+            "Service draws rejected by truncation: {}",
+            numbers.value(fraction)
+        );
+    }
+
+    if let Some(cutoff) = simulation.config().arrival_cutoff {
+        match simulation.backlog_at_cutoff() {
+            Some(backlog) => println!("Customers in system at cutoff ({:.0}): {}", cutoff, backlog),
+            None => println!("Arrival cutoff ({:.0}) not reached", cutoff),
+        }
+        if let Some(drain_time) = simulation.drain_time() {
+            println!("Drain time after cutoff: {}", numbers.value(drain_time));
+        }
+    }
+
+    if let Some(admission) = &simulation.config().admission {
+        println!(
+            "Arrivals while closed ({}): {} lost, {} deferred",
+            admission.closed_arrivals,
+            stats.closed_losses(),
+            stats.deferred_customers()
+        );
+        let last_closing = admission.open_periods.last().map_or(0.0, |p| p.1);
+        match simulation.overtime() {
+            Some(overtime) => println!(
+                "Overtime after the last closing: {}",
+                numbers.value(overtime)
+            ),
+            None if total_time < last_closing => {
+                println!("Last closing ({:.0}) not reached", last_closing)
+            }
+            None => println!("Still serving after the last closing ({:.0})", last_closing),
+        }
+    }
+
+    if let Some(limit) = simulation.config().max_queue_length
+        && stats.lost_customers() > 0
+    {
+        println!();
+        println!(
+            "!!! WARNING: the queue reached its cap of {} waiting customers !!!",
+            limit
+        );
+        println!(
+            "!!! {} arrivals ({} of all) were turned away; these results describe a",
+            numbers.count(stats.lost_customers()),
+            numbers.value(stats.blocking_probability())
+        );
+        println!("!!! finite-capacity system, not the uncapped queue");
+    }
+
+    let warnings = simulation.consistency_warnings();
+    if !warnings.is_empty() {
+        println!();
+        println!("!!! WARNING: the statistics contradict each other; they may be wrong !!!");
+        for warning in warnings {
+            println!("!!! {}", warning);
+        }
+    }
+
+    if simulation.config().is_loss_system() {
+        println!("Customers lost: {}", numbers.count(stats.lost_customers()));
+    }
+    match format {
+        OutputFormat::Plain => print_theory(simulation, numbers),
+        OutputFormat::Markdown => {
+            println!();
+            println!("=== Observed vs Theoretical ===");
+            print!(
+                "{}",
+                report::markdown_table(&report::comparisons(simulation), numbers)
+            );
+        }
+        OutputFormat::Json => {
+            println!();
+            print!(
+                "{}",
+                report::json_report(
+                    simulation,
+                    &report::comparisons(simulation),
+                    memory,
+                    cost_model
+                )
+            );
+        }
+    }
+
+    if let Some(breakdowns) = simulation.config().breakdowns {
+        print_breakdowns(simulation, breakdowns, numbers);
+    }
+    if let Some(breakdowns) = simulation.config().breakdowns
+        && simulation.config().standby.is_some()
+    {
+        print_standby(simulation, breakdowns, numbers);
+    }
+    if let Some(setup) = simulation.config().setup {
+        print_setup(simulation, setup, numbers);
+    }
+    if simulation.config().deadlines.is_some() {
+        print_deadlines(simulation, numbers);
+    }
+    if simulation.config().discipline == QueueDiscipline::RoundRobin {
+        print_round_robin(simulation, numbers);
+    }
+    if !simulation.config().parameter_changes.is_empty() {
+        print_phases(simulation, numbers);
+    }
+
+    if cost_model.is_configured() {
+        let cost = cost_model.evaluate(stats, total_time);
+        println!();
+        println!("=== Cost Breakdown ===");
+        println!("Waiting cost: {}", numbers.coarse(cost.waiting));
+        println!("Server cost: {}", numbers.coarse(cost.service));
+        if cost_model.loss_cost != 0.0 {
+            println!("Lost customer cost: {}", numbers.coarse(cost.loss));
+        }
+        println!("Total cost: {}", numbers.coarse(cost.total));
+        println!("Cost per time unit: {}", numbers.value(cost.per_time_unit));
+    }
+
+    println!();
+    println!("=== Performance Metrics ===");
+    println!("Wall-clock time: {:.2}s", elapsed_secs);
+    println!(
+        "Events per second: {}",
+        numbers.large_rate(event_count as f64 / elapsed_secs)
+    );
+    println!(
+        "Events per simulated time unit: {}",
+        numbers.value(event_count as f64 / total_time)
+    );
+    let server = simulation.server();
+    println!(
+        "Queue buffer: peak capacity {}, {} reallocations, about {:.1} MiB",
+        server.peak_queue_capacity(),
+        server.queue_reallocations(),
+        server.peak_queue_memory_bytes() as f64 / (1024.0 * 1024.0)
+    );
+    println!("Peak queue length: {}", memory.peak_queue_length);
+    match memory.peak_pending_events {
+        Some(peak) => println!(
+            "Event list: {} bytes, at most {} events pending",
+            memory.event_list_bytes, peak
+        ),
+        None => println!("Event list: {} bytes", memory.event_list_bytes),
+    }
+    if let Some(bytes) = memory.time_series_bytes {
+        println!("Time series: about {:.1} KiB", bytes as f64 / 1024.0);
+    }
+    if let (Some(run_loop), Some(events)) = (memory.loop_allocations, memory.event_allocations) {
+        println!(
+            "Allocations in the run loop: {} ({} bytes), {} of them ({} bytes) handling events",
+            run_loop.allocations, run_loop.bytes, events.allocations, events.bytes
+        );
+    }
+    if let Some(metrics) = simulation.engine().metrics() {
+        println!(
+            "{:<17} {:>14} {:>14} {:>12} {:>12} {:>14} {:>10}",
+            "event", "processed", "scheduled", "replaced", "cancelled", "handler time", "ns/event"
+        );
+        let mut event_types = vec![EventType::Arrival, EventType::Departure];
+        if simulation.config().breakdowns.is_some() {
+            event_types.extend([EventType::Breakdown, EventType::RepairComplete]);
+        }
+        if simulation.config().standby.is_some() {
+            event_types.extend([
+                EventType::Switchover,
+                EventType::StandbyBreakdown,
+                EventType::StandbyRepair,
+            ]);
+        }
+        if simulation.config().setup.is_some() {
+            event_types.push(EventType::Setup);
+        }
+        if simulation.config().admission.is_some() {
+            event_types.push(EventType::Admission);
+        }
+        if !simulation.config().parameter_changes.is_empty() {
+            event_types.push(EventType::ParameterChange);
+        }
+        for event_type in event_types {
+            let m = metrics.get(event_type);
+            let per_event = if m.processed == 0 {
+                0.0
+            } else {
+                m.handler_time.as_nanos() as f64 / m.processed as f64
+            };
+            println!(
+                "{:<17} {:>14} {:>14} {:>12} {:>12} {:>13.3}s {:>10.1}",
+                event_type,
+                m.processed,
+                m.scheduled,
+                m.replaced,
+                m.cancelled,
+                m.handler_time.as_secs_f64(),
+                per_event
+            );
+        }
+    }
+    if let Some(audit) = simulation.engine().clock_audit() {
+        println!();
+        println!("=== Clock Audit ===");
+        println!("Steps audited: {}", audit.steps());
+        if let (Some((time, dt)), Some(ratio), Some(rounding)) = (
+            audit.smallest_step(),
+            audit.smallest_ratio(),
+            audit.worst_rounding(),
+        ) {
+            println!(
+                "Shortest step for the clock: {:.3e} at t = {:.3e} ({:.2e} of the clock)",
+                dt, time, ratio
+            );
+            println!("Worst relative rounding of a step: {:.2e}", rounding);
+        }
+        match audit.warning() {
+            Some(warning) => println!("Warning: {}", warning),
+            None => println!(
+                "The clock resolved every step to within {:.0e} of its length",
+                CLOCK_AUDIT_THRESHOLD
+            ),
+        }
+    }
+}
+
+/// Check the observed metrics against theory within a relative `tolerance`, printing
+/// a verdict per metric; returns false if any failed
+///
+/// `metrics` limits the check to those keys of [`report::METRIC_KEYS`]. A model the
+/// formulas do not describe is skipped with a note and does not fail.
+pub fn validate_run(simulation: &Simulation, tolerance: f64, metrics: Option<&[String]>) -> bool {
+    println!();
+    println!("=== Validation (tolerance {:.2}%) ===", tolerance * 100.0);
+    if let Some(reason) = report::theory_mismatch(simulation.config()) {
+        println!("Skipped: {}", reason);
+        return true;
+    }
+
+    let rows: Vec<_> = report::comparisons(simulation)
+        .into_iter()
+        .filter(|row| metrics.is_none_or(|keys| keys.iter().any(|key| key == row.key)))
+        .collect();
+    let verdicts = report::validate(&rows, tolerance);
+    let mut passed = true;
+    for (row, verdict) in rows.iter().zip(&verdicts) {
+        match verdict {
+            report::Verdict::Pass | report::Verdict::Fail => {
+                let fail = matches!(verdict, report::Verdict::Fail);
+                passed &= !fail;
+                println!(
+                    "{} {}: observed {:.4}, theoretical {:.4}, error {:+.2}%",
+                    if fail { "FAIL" } else { "PASS" },
+                    row.metric,
+                    row.observed,
+                    row.theoretical,
+                    row.percent_error()
+                );
+            }
+            report::Verdict::Skipped(reason) => println!("SKIP {}: {}", row.metric, reason),
+        }
+    }
+    if rows.is_empty() {
+        println!("No selected metric applies to this model");
+    }
+    passed
+}
+
+/// Print the closed-form metrics of `config` without simulating; false if it has none
+pub fn run_analytic(
+    config: &SimulationConfig,
+    format: OutputFormat,
+    numbers: NumberFormat,
+) -> bool {
+    let model = match report::analytic_model(config) {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("Error: no analytic results: {}", e);
+            return false;
+        }
+    };
+    match format {
+        OutputFormat::Plain => {
+            println!();
+            println!("=== Analytic Results ({}) ===", model.name);
+            for row in &model.rows {
+                let value = if row.key == "blocking" {
+                    numbers.fine(row.theoretical)
+                } else {
+                    numbers.value(row.theoretical)
+                };
+                println!("{}: {}", row.metric, value);
+            }
+            if !model.wait_percentiles.is_empty() {
+                println!("Wait time percentiles:");
+                for (p, wait) in &model.wait_percentiles {
+                    println!("  P{}: {}", (p * 100.0).round(), numbers.value(*wait));
+                }
+            }
+        }
+        OutputFormat::Markdown => {
+            println!();
+            println!("=== Analytic Results ({}) ===", model.name);
+            print!("{}", report::analytic_markdown_table(&model, numbers));
+        }
+        OutputFormat::Json => print!("{}", report::analytic_json_report(&model)),
+    }
+    true
+}
+
+/// The observed metrics next to the exact closed forms of the configured model
+pub fn print_analytic_comparison(
+    simulation: &Simulation,
+    memory: &MemoryProfile,
+    cost_model: &CostModel,
+    format: OutputFormat,
+    numbers: NumberFormat,
+) {
+    println!();
+    let model = match report::analytic_model(simulation.config()) {
+        Ok(model) => model,
+        Err(e) => {
+            println!("No analytic comparison: {}", e);
+            return;
+        }
+    };
+    let rows = report::analytic_comparisons(simulation, model.rows);
+    if format == OutputFormat::Json {
+        print!(
+            "{}",
+            report::json_report(simulation, &rows, memory, cost_model)
+        );
+    } else {
+        println!("=== Simulated vs Analytic ({}) ===", model.name);
+        print!("{}", report::markdown_table(&rows, numbers));
+    }
+}
+
+/// Plain-text comparison: loss-system measurements, then the M/M/1 or Erlang-B values
+fn print_theory(simulation: &Simulation, numbers: NumberFormat) {
+    let total_time = simulation.now();
+    let stats = simulation.statistics();
+    let (lambda, mu) = (simulation.config().lambda, simulation.config().mu);
+
+    if simulation.config().is_loss_system() {
+        let servers = simulation.config().servers;
+        let offered_load = lambda / mu;
+        let blocking = erlang_b(servers, offered_load);
+
+        println!(
+            "Blocking probability: {}",
+            numbers.fine(stats.blocking_probability())
+        );
+        println!(
+            "Carried load (mean busy servers): {}",
+            numbers.value(stats.average_busy_servers(total_time))
+        );
+        // Each phase is a model of its own, so the phase table stands in for theory;
+        // Erlang-B holds for any service but only for Poisson arrivals
+        if !simulation.config().parameter_changes.is_empty()
+            || simulation.config().arrival_kind != ServiceKind::Exponential
+        {
+            return;
+        }
+
+        println!();
+        println!("=== Theoretical Values (Erlang-B, M/M/c/c) ===");
+        println!("Offered load: {} Erlangs", numbers.value(offered_load));
+        println!("Expected blocking probability: {}", numbers.fine(blocking));
+        println!(
+            "Expected carried load: {}",
+            numbers.value(offered_load * (1.0 - blocking))
+        );
+        println!(
+            "Expected utilization: {}",
+            numbers.value(offered_load * (1.0 - blocking) / servers as f64)
+        );
+        println!(
+            "Expected throughput: {}",
+            numbers.value(lambda * (1.0 - blocking))
+        );
+    } else {
+        // Setups lengthen every wait, so they get their own decomposition; round robin
+        // is compared with processor sharing and a speed-up by the wait with a paired run
+        if simulation.config().setup.is_some()
+            || simulation.config().service_modulation.is_some()
+            || !simulation.config().parameter_changes.is_empty()
+            || simulation.config().discipline == QueueDiscipline::RoundRobin
+        {
+            return;
+        }
+        let config = simulation.config();
+        let poisson = config.arrival_kind == ServiceKind::Exponential;
+        let exponential =
+            config.service_kind == ServiceKind::Exponential && config.max_service_time.is_none();
+        // For M/D/1 the two-moment formula below is exact and M/M/1 only misleads
+        if poisson && config.service_kind == ServiceKind::Exponential {
+            print_mm1_theory(lambda, mu, numbers);
+        }
+        // Exponential service after other arrivals is G/M/1, whose wait is exact
+        if !poisson && exponential && config.servers == 1 && config.breakdowns.is_none() {
+            print_gm1_theory(simulation, numbers);
+        }
+        // Service durations exclude repairs, so breakdowns have their own formula. With
+        // Poisson arrivals and exponential service the approximation only restates M/M/c
+        if config.breakdowns.is_none() && !(poisson && exponential) {
+            print_two_moment(simulation, numbers);
+        }
+    }
+}
+
+/// Mean wait from the measured first two moments of interarrival and service times
+fn print_two_moment(simulation: &Simulation, numbers: NumberFormat) {
+    let stats = simulation.statistics();
+    let (arrivals, services) = (stats.interarrival_times(), stats.service_times());
+    let (Some(mean_interarrival), Some(ca2), Some(mean_service), Some(cs2)) = (
+        arrivals.mean(),
+        arrivals.scv(),
+        services.mean(),
+        services.scv(),
+    ) else {
+        return;
+    };
+    let servers = simulation.config().servers;
+
+    println!();
+    if servers == 1 {
+        println!("=== Two-Moment Approximation (Kingman, G/G/1) ===");
+    } else {
+        println!("=== Two-Moment Approximation (Allen-Cunneen, G/G/c) ===");
+    }
+    println!("Interarrival time SCV (ca²): {}", numbers.value(ca2));
+    println!("Service time SCV (cs²): {}", numbers.value(cs2));
+    match allen_cunneen_wait(servers, mean_interarrival, mean_service, ca2, cs2) {
+        Some(approximation) => {
+            let simulated = stats.average_wait_time();
+            println!("Approximate wait time: {}", numbers.value(approximation));
+            println!(
+                "Simulated wait time: {} ({} from the approximation)",
+                numbers.value(simulated),
+                numbers.signed_percent((simulated - approximation) / approximation)
+            );
+        }
+        None => println!("Measured load is at least 1; the queue has no steady state"),
+    }
+}
+
+/// The exact G/M/1 mean wait from the root σ of the interarrival transform,
+/// against the simulated one
+fn print_gm1_theory(simulation: &Simulation, numbers: NumberFormat) {
+    let config = simulation.config();
+    let (lambda, mu) = (config.lambda, config.mu);
+    let mean_interarrival = 1.0 / lambda;
+
+    println!();
+    println!(
+        "=== Theoretical Values (G/M/1, {} interarrival times) ===",
+        config.arrival_kind
+    );
+    if config
+        .arrival_kind
+        .laplace_transform(mean_interarrival, 0.0)
+        .is_none()
+    {
+        println!("No closed-form transform of the interarrival times; see the approximation");
+        return;
+    }
+    let transform = |s: f64| {
+        config
+            .arrival_kind
+            .laplace_transform(mean_interarrival, s)
+            .unwrap_or(f64::NAN)
+    };
+    let (Some(sigma), Some(wait)) = (
+        gm1_root(mean_interarrival, mu, transform),
+        gm1_wait(mean_interarrival, mu, transform),
+    ) else {
+        println!("Unstable: ρ = λ/μ ≥ 1");
+        return;
+    };
+    let simulated = simulation.statistics().average_wait_time();
+    println!(
+        "Root σ (probability an arrival waits): {}",
+        numbers.value(sigma)
+    );
+    println!("Expected wait time: {}", numbers.value(wait));
+    println!("Expected queue length: {}", numbers.value(lambda * wait));
+    println!(
+        "Simulated wait time: {} ({} from G/M/1)",
+        numbers.value(simulated),
+        numbers.signed_percent((simulated - wait) / wait)
+    );
+}
+
+/// Closed-form M/M/1 values to compare the simulation against
+fn print_mm1_theory(lambda: f64, mu: f64, numbers: NumberFormat) {
+    let rho = lambda / mu;
+    let theoretical_wait = rho / (mu - lambda);
+    let theoretical_queue = rho * rho / (1.0 - rho);
+    let theoretical_customers_in_system = rho / (1.0 - rho);
+    let theoretical_throughput = lambda;
+
+    println!();
+    println!("=== Theoretical Values (M/M/1) ===");
+    println!("Expected wait time: {}", numbers.value(theoretical_wait));
+    println!("Expected probability of waiting: {}", numbers.value(rho));
+    // Exp(μ - λ): the median and upper percentiles are -ln(1 - q)/(μ - λ)
+    if rho < 1.0 {
+        let delayed_quantile = |q: f64| -(1.0 - q).ln() / (mu - lambda);
+        println!(
+            "Expected wait when delayed: mean {}, std dev {}, p50 {}, p90 {}, p99 {}",
+            numbers.value(1.0 / (mu - lambda)),
+            numbers.value(1.0 / (mu - lambda)),
+            numbers.value(delayed_quantile(0.5)),
+            numbers.value(delayed_quantile(0.9)),
+            numbers.value(delayed_quantile(0.99))
+        );
+    }
+    println!(
+        "Expected queue length: {}",
+        numbers.value(theoretical_queue)
+    );
+    println!(
+        "Expected customers in system: {}",
+        numbers.value(theoretical_customers_in_system)
+    );
+    println!("Expected utilization: {}", numbers.value(rho));
+    println!(
+        "Expected throughput: {}",
+        numbers.value(theoretical_throughput)
+    );
+    if let Some(cv) = mm1_wait_cv(rho) {
+        println!(
+            "Expected wait coefficient of variation: {}",
+            numbers.value(cv)
+        );
+    }
+}
+
+/// Availability and how much the repairs inflate the mean wait
+fn print_breakdowns(simulation: &Simulation, breakdowns: Breakdowns, numbers: NumberFormat) {
+    let config = simulation.config();
+    let total_time = simulation.now();
+    let stats = simulation.statistics();
+
+    println!();
+    println!("=== Breakdowns ===");
+    println!("Breakdowns: {}", numbers.count(stats.breakdowns()));
+    // With a standby the station is only down while it takes over or is down too
+    println!(
+        "{}: {}",
+        if config.standby.is_some() {
+            "Time out of service"
+        } else {
+            "Time under repair"
+        },
+        numbers.coarse(stats.down_time(total_time))
+    );
+    println!(
+        "Availability: {}",
+        numbers.value(stats.availability(total_time))
+    );
+
+    // The closed forms assume a single server with plain exponential service, and
+    // no standby to take over during the repairs
+    if config.servers != 1
+        || !config.waiting_room
+        || config.arrival_kind != ServiceKind::Exponential
+        || config.service_kind != ServiceKind::Exponential
+        || config.max_service_time.is_some()
+        || config.setup.is_some()
+        || config.standby.is_some()
+        || config.service_modulation.is_some()
+    {
+        return;
+    }
+    let (lambda, mu) = (config.lambda, config.mu);
+    let rho = lambda / mu;
+    let repair_fraction = breakdowns.mean_repair_time / breakdowns.mean_time_to_failure;
+    println!(
+        "Expected availability: {}",
+        numbers.value((1.0 - rho * repair_fraction).max(0.0))
+    );
+
+    if rho < 1.0 {
+        let reliable_wait = rho / (mu - lambda);
+        println!(
+            "Expected wait without breakdowns: {}",
+            numbers.value(reliable_wait)
+        );
+        println!(
+            "Wait inflation from breakdowns: {}x",
+            numbers.value(stats.average_wait_time() / reliable_wait)
+        );
+    }
+    match mm1_breakdown_wait(
+        lambda,
+        mu,
+        breakdowns.mean_time_to_failure,
+        breakdowns.mean_repair_time,
+    ) {
+        Some(wait) => println!(
+            "Expected wait with breakdowns (M/G/1): {}",
+            numbers.value(wait)
+        ),
+        None => println!("Unstable with breakdowns: λ(1 + r/MTTF)/μ ≥ 1"),
+    }
+}
+
+/// Who served when, and the mean wait against a server that never fails and one without standby
+fn print_standby(simulation: &Simulation, breakdowns: Breakdowns, numbers: NumberFormat) {
+    let config = simulation.config();
+    let total_time = simulation.now();
+    let stats = simulation.statistics();
+    let observed = stats.observed_time(total_time);
+    let down_time = stats.down_time(total_time);
+    let standby_time = stats.standby_time(total_time);
+    let outage_time = stats.outage_time(total_time);
+
+    println!();
+    println!("=== Standby Server ===");
+    println!("Failovers: {}", numbers.count(stats.failovers()));
+    println!(
+        "Services interrupted by a failover: {}",
+        numbers.count(stats.failover_interruptions())
+    );
+    println!(
+        "Standby breakdowns: {}",
+        numbers.count(stats.standby_breakdowns())
+    );
+    println!(
+        "Time served by the primary: {}",
+        numbers.coarse(observed - standby_time - down_time)
+    );
+    println!(
+        "Time served by the standby: {}",
+        numbers.coarse(standby_time)
+    );
+    println!(
+        "Time switching over: {}",
+        numbers.coarse(down_time - outage_time)
+    );
+    println!(
+        "Time with both servers down: {}",
+        numbers.coarse(outage_time)
+    );
+    println!(
+        "Availability with the standby: {}",
+        numbers.value(stats.availability(total_time))
+    );
+    println!(
+        "Mean wait with the standby: {}",
+        numbers.value(stats.average_wait_time())
+    );
+
+    // The closed forms assume a single server with plain exponential service
+    if config.servers != 1
+        || !config.waiting_room
+        || config.arrival_kind != ServiceKind::Exponential
+        || config.service_kind != ServiceKind::Exponential
+        || config.max_service_time.is_some()
+        || config.setup.is_some()
+        || config.service_modulation.is_some()
+    {
+        return;
+    }
+    let (lambda, mu) = (config.lambda, config.mu);
+    if lambda < mu {
+        println!(
+            "Expected wait with a server that never fails: {}",
+            numbers.value(lambda / (mu * (mu - lambda)))
+        );
+    }
+    match mm1_breakdown_wait(
+        lambda,
+        mu,
+        breakdowns.mean_time_to_failure,
+        breakdowns.mean_repair_time,
+    ) {
+        Some(wait) => println!(
+            "Expected wait with the failing server alone (M/G/1): {}",
+            numbers.value(wait)
+        ),
+        None => println!("Unstable with the failing server alone: λ(1 + r/MTTF)/μ ≥ 1"),
+    }
+}
+
+/// The metrics of each stretch between scheduled parameter changes
+fn print_phases(simulation: &Simulation, numbers: NumberFormat) {
+    println!();
+    println!("=== Phases ===");
+    println!(
+        "{:<14} {:>10} {:>10} {:>7} {:>7} {:>7} {:>9} {:>10} {:>10} {:>11} {:>11}",
+        "phase",
+        "start",
+        "end",
+        "λ",
+        "μ",
+        "servers",
+        "served",
+        "mean wait",
+        "mean queue",
+        "utilization",
+        "throughput"
+    );
+    for phase in simulation.phases() {
+        if phase.observed == 0.0 {
+            println!(
+                "{:<14} {:>10} {:>10} {:>7} {:>7} {:>7} (within the warm-up)",
+                phase.label,
+                numbers.coarse(phase.start),
+                numbers.coarse(phase.end),
+                phase.lambda,
+                phase.mu,
+                phase.servers
+            );
+            continue;
+        }
+        println!(
+            "{:<14} {:>10} {:>10} {:>7} {:>7} {:>7} {:>9} {:>10} {:>10} {:>11} {:>11}",
+            phase.label,
+            numbers.coarse(phase.start),
+            numbers.coarse(phase.end),
+            phase.lambda,
+            phase.mu,
+            phase.servers,
+            numbers.count(phase.served),
+            numbers.value(phase.mean_wait),
+            numbers.value(phase.mean_queue_length),
+            numbers.value(phase.utilization),
+            numbers.value(phase.throughput)
+        );
+    }
+}
+
+/// How many customers left after their deadline and by how much
+fn print_deadlines(simulation: &Simulation, numbers: NumberFormat) {
+    let stats = simulation.statistics();
+    println!();
+    println!("=== Deadlines ({}) ===", simulation.config().discipline);
+    println!(
+        "Served after their deadline: {}",
+        numbers.percent(stats.tardiness_probability())
+    );
+    println!(
+        "Mean tardiness of the late: {}",
+        numbers.value(stats.mean_tardiness())
+    );
+    println!("Max tardiness: {}", numbers.value(stats.max_tardiness()));
+    if simulation.config().deadlines.is_some_and(|d| d.drop_late) {
+        println!(
+            "Dropped late: {} ({})",
+            numbers.count(stats.late_drops()),
+            numbers.percent(stats.late_drop_fraction())
+        );
+    }
+}
+
+/// How long customers stayed relative to their service, and what the quanta cost in events
+fn print_round_robin(simulation: &Simulation, numbers: NumberFormat) {
+    let stats = simulation.statistics();
+    println!();
+    println!("=== Round Robin ===");
+    println!(
+        "Mean sojourn time: {}",
+        numbers.value(stats.average_sojourn_time())
+    );
+    if let Some(median) = stats.slowdown_quantile(0.5) {
+        println!("Median slowdown: {}", numbers.value(median));
+    }
+    if let Some(slope) = stats.sojourn_per_service() {
+        println!("Sojourn per unit of service: {}", numbers.value(slope));
+    }
+    let customer_events = stats.arrived_customers() + stats.served_customers();
+    println!(
+        "Quanta cut short: {} ({}x the events of FIFO)",
+        numbers.count(stats.preemptions()),
+        numbers.coarse(1.0 + stats.preemptions() as f64 / customer_events.max(1) as f64)
+    );
+    let config = simulation.config();
+    if has_mg1_theory(config)
+        && let Some(sojourn) = mg1_processor_sharing_sojourn(config.lambda, 1.0 / config.mu)
+    {
+        println!(
+            "Processor sharing (quantum → 0): sojourn {}, slowdown {}",
+            numbers.value(sojourn),
+            numbers.value(sojourn * config.mu)
+        );
+    }
+}
+
+/// How often the station switched on and how much of the wait that cost
+fn print_setup(simulation: &Simulation, setup: SetupTime, numbers: NumberFormat) {
+    let config = simulation.config();
+    let total_time = simulation.now();
+    let stats = simulation.statistics();
+
+    println!();
+    println!("=== Setup Times ===");
+    println!("Setups: {}", numbers.count(stats.setups()));
+    println!(
+        "Time switching on: {}",
+        numbers.coarse(stats.setup_time(total_time))
+    );
+    println!(
+        "Mean setup time: {}",
+        numbers.value(stats.average_setup_time())
+    );
+    println!(
+        "Share of the wait spent waiting for a setup: {}",
+        numbers.percent(stats.setup_wait_share())
+    );
+
+    // The decomposition holds for a single server with Poisson arrivals and untruncated service
+    if config.servers != 1
+        || !config.waiting_room
+        || config.arrival_kind != ServiceKind::Exponential
+        || config.max_service_time.is_some()
+        || config.breakdowns.is_some()
+        || config.service_modulation.is_some()
+    {
+        return;
+    }
+    let (lambda, mu) = (config.lambda, config.mu);
+    let service_scv = config.service_kind.scv();
+    // Allen-Cunneen is exact for M/G/1, where it is the Pollaczek-Khinchine formula
+    let Some(base_wait) = allen_cunneen_wait(1, 1.0 / lambda, 1.0 / mu, 1.0, service_scv) else {
+        println!("Unstable: ρ = λ/μ ≥ 1");
+        return;
+    };
+    let extra_wait = setup_extra_wait(lambda, setup.mean, setup.second_moment());
+    let expected = base_wait + extra_wait;
+    let simulated = stats.average_wait_time();
+    println!();
+    println!("=== Theoretical Values (M/G/1 with setup) ===");
+    println!("Expected wait without setups: {}", numbers.value(base_wait));
+    println!(
+        "Expected extra wait from setups: {}",
+        numbers.value(extra_wait)
+    );
+    println!("Expected wait with setups: {}", numbers.value(expected));
+    println!(
+        "Simulated wait time: {} ({} from the decomposition)",
+        numbers.value(simulated),
+        numbers.signed_percent((simulated - expected) / expected)
+    );
+}
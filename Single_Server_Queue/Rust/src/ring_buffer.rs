@@ -0,0 +1,124 @@
+/// Slots allocated up front, enough for any stable queue without growing
+pub const INITIAL_CAPACITY: usize = 1 << 10;
+
+/// FIFO ring buffer with a power-of-two capacity that doubles when full
+///
+/// Works like a `VecDeque` but keeps track of its own growth: every reallocation
+/// is counted and the largest capacity is remembered, so an overloaded run can
+/// report how much memory its queue took. Clearing keeps the allocation.
+#[derive(Clone)]
+pub struct RingBuffer<T> {
+    slots: Vec<T>,
+    head: usize,
+    len: usize,
+    reallocations: u32,
+    peak_capacity: usize,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(INITIAL_CAPACITY)
+    }
+
+    /// Buffer for at least `capacity` elements before the first reallocation
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+        Self {
+            slots: vec![T::default(); capacity],
+            head: 0,
+            len: 0,
+            reallocations: 0,
+            peak_capacity: capacity,
+        }
+    }
+
+    #[inline]
+    pub fn push_back(&mut self, value: T) {
+        if self.len == self.slots.len() {
+            self.grow();
+        }
+        let mask = self.slots.len() - 1;
+        self.slots[(self.head + self.len) & mask] = value;
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.slots[self.head];
+        self.head = (self.head + 1) & (self.slots.len() - 1);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Oldest element, if any
+    #[inline]
+    pub fn front(&self) -> Option<T> {
+        (self.len > 0).then(|| self.slots[self.head])
+    }
+
+    /// Elements from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        let mask = self.slots.len() - 1;
+        (0..self.len).map(move |i| self.slots[(self.head + i) & mask])
+    }
+
+    /// Double the capacity and move the elements to the front in FIFO order
+    #[cold]
+    fn grow(&mut self) {
+        let capacity = self.slots.len() * 2;
+        let mut slots = Vec::with_capacity(capacity);
+        let (wrapped, front) = self.slots.split_at(self.head);
+        slots.extend_from_slice(front);
+        slots.extend_from_slice(wrapped);
+        slots.resize(capacity, T::default());
+
+        self.slots = slots;
+        self.head = 0;
+        self.reallocations += 1;
+        self.peak_capacity = self.peak_capacity.max(capacity);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all elements; the capacity and growth statistics are kept
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Largest capacity this buffer has had
+    pub fn peak_capacity(&self) -> usize {
+        self.peak_capacity
+    }
+
+    /// Number of times the buffer had to grow
+    pub fn reallocations(&self) -> u32 {
+        self.reallocations
+    }
+
+    /// Bytes currently allocated for the elements
+    pub fn memory_bytes(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<T>()
+    }
+}
+
+impl<T: Copy + Default> Default for RingBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,422 @@
+use std::fmt;
+
+/// A stream of uniform random numbers driving the simulation
+///
+/// Entities only see this trait, so the generator can be swapped through
+/// [`RngBackend`] without touching `Server` or `Client`. Running the same
+/// model on two backends is a cheap check that results do not depend on the
+/// generator.
+pub trait Sampler: Send {
+    /// Uniform sample in `[0, 1)`
+    fn f64(&mut self) -> f64;
+
+    /// Uniform sample in the open interval `(0, 1)`
+    ///
+    /// For inverse-transform draws like `-ln(u)`: a zero from [`Sampler::f64`]
+    /// would give an infinite duration, so it is rejected, and since `u < 1`
+    /// the result is strictly positive. Any other draw is passed through, so
+    /// seeded runs are unchanged unless they hit an exact zero.
+    #[inline]
+    fn open_f64(&mut self) -> f64 {
+        loop {
+            let u = self.f64();
+            if u > 0.0 {
+                return u;
+            }
+        }
+    }
+
+    /// Split off an independent generator, advancing this one
+    fn fork(&mut self) -> Box<dyn Sampler>;
+
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+impl Clone for Box<dyn Sampler> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Sampler for fastrand::Rng {
+    #[inline]
+    fn f64(&mut self) -> f64 {
+        fastrand::Rng::f64(self)
+    }
+
+    fn fork(&mut self) -> Box<dyn Sampler> {
+        Box::new(fastrand::Rng::fork(self))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// xoshiro256++ by Blackman and Vigna
+///
+/// Slower than `fastrand`'s wyrand but with a 2^256 - 1 period and well studied
+/// equidistribution in up to four dimensions, which matters more the longer and
+/// more numerous the runs of a study get. Forked streams are separated by the
+/// 2^128-step jump, so they can never overlap.
+#[derive(Clone)]
+pub struct Xoshiro256PlusPlus {
+    s: [u64; 4],
+}
+
+impl Xoshiro256PlusPlus {
+    /// Expand a 64-bit seed into the full state with SplitMix64, as the authors recommend
+    pub fn seed_from_u64(seed: u64) -> Self {
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        Self {
+            s: [next(), next(), next(), next()],
+        }
+    }
+
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    /// Advance the state by 2^128 steps
+    fn jump(&mut self) {
+        const JUMP: [u64; 4] = [
+            0x180E_C6D3_3CFD_0ABA,
+            0xD5A6_1266_F0C9_392C,
+            0xA958_2618_E03F_C9AA,
+            0x39AB_DC45_29B1_661C,
+        ];
+
+        let mut jumped = [0u64; 4];
+        for word in JUMP {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    for (j, s) in jumped.iter_mut().zip(self.s) {
+                        *j ^= s;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = jumped;
+    }
+}
+
+impl Sampler for Xoshiro256PlusPlus {
+    #[inline]
+    fn f64(&mut self) -> f64 {
+        // Top 53 bits give every representable multiple of 2^-53 in [0, 1)
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    fn fork(&mut self) -> Box<dyn Sampler> {
+        let child = self.clone();
+        self.jump();
+        Box::new(child)
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// PCG32 (PCG-XSH-RR 64/32) by O'Neill, owned by this crate
+///
+/// The fixed algorithm of [`RngBackend::Pcg32`]: the generator, the way a seed
+/// becomes its state, the way two outputs become an `f64` and the way streams
+/// fork are all defined here and pinned by [`check_pcg32_golden`], so a seeded
+/// run gives the same numbers whatever version of a dependency is built against.
+/// Never change any of them; add a new backend instead.
+#[derive(Clone)]
+pub struct Pcg32 {
+    state: u64,
+    /// Odd increment selecting one of the 2^63 streams
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    /// Stream of [`Self::seed_from_u64`], the increment of the reference implementation's initializer
+    const DEFAULT_STREAM: u64 = 0xDA3E_39CB_94B9_5BDB;
+
+    /// Generator at `initial_state` on stream `stream`, seeded like `pcg32_srandom_r`
+    pub fn new(initial_state: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(initial_state);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self::new(seed, Self::DEFAULT_STREAM)
+    }
+
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Two outputs, the first in the high half
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let high = u64::from(self.next_u32());
+        (high << 32) | u64::from(self.next_u32())
+    }
+}
+
+impl Sampler for Pcg32 {
+    #[inline]
+    fn f64(&mut self) -> f64 {
+        // Top 53 bits of two outputs, like the other backends
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// The child starts from the next output on a stream chosen by the one after
+    fn fork(&mut self) -> Box<dyn Sampler> {
+        let initial_state = self.next_u64();
+        let stream = self.next_u64();
+        Box::new(Self::new(initial_state, stream))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// First outputs of `Pcg32::new(42, 54)`, as printed by the reference `pcg32-demo`
+pub const PCG32_REFERENCE_OUTPUTS: [u32; 6] = [
+    0xA15C_02B7,
+    0x7B47_F409,
+    0xBA1D_3330,
+    0x83D2_F293,
+    0xBFA4_784B,
+    0xCBED_606E,
+];
+
+/// Bits of the first `f64` draws of the stable backend seeded with 1: three from
+/// the master generator, then three from its first fork
+pub const PCG32_GOLDEN_DRAWS: [u64; 6] = [
+    0x3FEE_0C7B_8976_2483,
+    0x3FEF_085E_5854_CB6D,
+    0x3FD3_8053_6070_C6FC,
+    0x3FE0_2856_29E8_A552,
+    0x3FE1_9787_0C7B_7D4F,
+    0x3FE0_0F9E_02A6_1CB0,
+];
+
+/// Check [`Pcg32`] against the reference outputs and the draws of seeded runs
+/// against [`PCG32_GOLDEN_DRAWS`]; the error names the first value that differs
+pub fn check_pcg32_golden() -> Result<(), String> {
+    let mut reference = Pcg32::new(42, 54);
+    for (k, &expected) in PCG32_REFERENCE_OUTPUTS.iter().enumerate() {
+        let actual = reference.next_u32();
+        if actual != expected {
+            return Err(format!(
+                "output {} of Pcg32::new(42, 54) is {:#010x}, the reference {:#010x}",
+                k + 1,
+                actual,
+                expected
+            ));
+        }
+    }
+    let mut master = RngBackend::Pcg32.create(Some(1));
+    let mut draws = [0u64; 6];
+    for draw in &mut draws[..3] {
+        *draw = master.f64().to_bits();
+    }
+    let mut child = master.fork();
+    for draw in &mut draws[3..] {
+        *draw = child.f64().to_bits();
+    }
+    match draws
+        .iter()
+        .zip(PCG32_GOLDEN_DRAWS)
+        .position(|(&actual, expected)| actual != expected)
+    {
+        None => Ok(()),
+        Some(k) => Err(format!(
+            "draw {} with seed 1 is {}, the golden {}",
+            k + 1,
+            f64::from_bits(draws[k]),
+            f64::from_bits(PCG32_GOLDEN_DRAWS[k])
+        )),
+    }
+}
+
+/// Which generator a simulation draws its random numbers from
+///
+/// Only [`RngBackend::Pcg32`] is implemented in this crate. The others come
+/// from or follow dependencies whose output may change with their version, so
+/// a seeded run on them is reproducible with the same build but not forever;
+/// archive runs with the stable backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RngBackend {
+    /// `fastrand` (wyrand): the fastest option and plenty for single runs
+    #[default]
+    Fastrand,
+    /// xoshiro256++: better statistical quality for large studies, somewhat slower
+    Xoshiro256PlusPlus,
+    /// PCG32, the stable backend: a seed gives the same run across dependency updates
+    Pcg32,
+}
+
+impl RngBackend {
+    /// Create the master generator, from `seed` or from system entropy
+    pub fn create(self, seed: Option<u64>) -> Box<dyn Sampler> {
+        match self {
+            RngBackend::Fastrand => Box::new(match seed {
+                Some(seed) => fastrand::Rng::with_seed(seed),
+                None => fastrand::Rng::new(),
+            }),
+            RngBackend::Xoshiro256PlusPlus => Box::new(Xoshiro256PlusPlus::seed_from_u64(
+                seed.unwrap_or_else(|| fastrand::u64(..)),
+            )),
+            RngBackend::Pcg32 => Box::new(Pcg32::seed_from_u64(
+                seed.unwrap_or_else(|| fastrand::u64(..)),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RngBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RngBackend::Fastrand => f.pad("fastrand"),
+            RngBackend::Xoshiro256PlusPlus => f.pad("xoshiro256++"),
+            RngBackend::Pcg32 => f.pad("pcg32"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distributions::{Distribution, Exponential};
+
+    /// Hands out the given draws in order, then repeats the last one
+    #[derive(Clone)]
+    struct Scripted {
+        draws: Vec<f64>,
+        next: usize,
+    }
+
+    impl Scripted {
+        fn new(draws: &[f64]) -> Self {
+            Self {
+                draws: draws.to_vec(),
+                next: 0,
+            }
+        }
+    }
+
+    impl Sampler for Scripted {
+        fn f64(&mut self) -> f64 {
+            let draw = self.draws[self.next.min(self.draws.len() - 1)];
+            self.next += 1;
+            draw
+        }
+
+        fn fork(&mut self) -> Box<dyn Sampler> {
+            Box::new(self.clone())
+        }
+
+        fn clone_box(&self) -> Box<dyn Sampler> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn an_exact_zero_is_drawn_again() {
+        let mut rng = Scripted::new(&[0.0, 0.0, 0.25, 0.5]);
+        assert_eq!(rng.open_f64(), 0.25);
+        assert_eq!(rng.next, 3);
+        assert_eq!(rng.open_f64(), 0.5);
+    }
+
+    #[test]
+    fn an_exponential_duration_after_a_zero_is_finite() {
+        let mut rng = Scripted::new(&[0.0, 0.5]);
+        let duration = Exponential::new(1.0).sample(&mut rng);
+        assert_eq!(duration, 2f64.ln());
+    }
+
+    #[test]
+    fn no_backend_yields_zero_or_one() {
+        for backend in [
+            RngBackend::Fastrand,
+            RngBackend::Xoshiro256PlusPlus,
+            RngBackend::Pcg32,
+        ] {
+            let mut rng = backend.create(Some(7));
+            assert!(
+                (0..1_000_000)
+                    .map(|_| rng.open_f64())
+                    .all(|u| u > 0.0 && u < 1.0),
+                "{}",
+                backend
+            );
+        }
+    }
+
+    #[test]
+    fn pcg32_matches_the_reference_and_the_golden_draws() {
+        assert_eq!(check_pcg32_golden(), Ok(()));
+        let mut reference = Pcg32::new(42, 54);
+        let outputs: Vec<u32> = (0..6).map(|_| reference.next_u32()).collect();
+        assert_eq!(outputs, PCG32_REFERENCE_OUTPUTS);
+    }
+
+    #[test]
+    fn a_pcg32_draw_is_the_top_53_bits_of_two_outputs() {
+        let mut outputs = Pcg32::seed_from_u64(1);
+        let high = u64::from(outputs.next_u32());
+        let bits = (high << 32 | u64::from(outputs.next_u32())) >> 11;
+        assert_eq!(
+            (bits as f64 / (1u64 << 53) as f64).to_bits(),
+            PCG32_GOLDEN_DRAWS[0]
+        );
+    }
+
+    #[test]
+    fn pcg32_forks_and_clones_are_reproducible() {
+        let mut master = Pcg32::seed_from_u64(9);
+        let mut copy = master.clone_box();
+        let mut child = master.fork();
+        let mut same_child = copy.fork();
+        for _ in 0..1000 {
+            assert_eq!(child.f64().to_bits(), same_child.f64().to_bits());
+            assert_eq!(master.f64().to_bits(), copy.f64().to_bits());
+        }
+        // The child runs on a stream of its own, not behind its parent
+        let parent: Vec<u64> = (0..100).map(|_| master.f64().to_bits()).collect();
+        assert!((0..100).all(|_| !parent.contains(&child.f64().to_bits())));
+    }
+}
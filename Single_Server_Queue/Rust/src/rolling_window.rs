@@ -0,0 +1,284 @@
+//! The main metrics over the most recent stretch of a run, see [`RollingWindow`]
+
+use std::collections::VecDeque;
+
+/// Records a window keeps before it falls back to coarse buckets
+pub const MAX_WINDOW_RECORDS: usize = 1 << 20;
+
+/// Buckets the horizon is split into once the records are coarse
+pub const WINDOW_BUCKETS: usize = 1_000;
+
+/// What one stretch of time added to the windowed metrics
+#[derive(Debug, Clone, Copy, Default)]
+struct Contribution {
+    start: f64,
+    end: f64,
+    /// Time integrals of the queue length, customers in system, busy and available servers
+    queue_area: f64,
+    customers_area: f64,
+    busy_area: f64,
+    capacity_area: f64,
+    /// Waits of the customers who started service in the stretch, and their number
+    waits: f64,
+    started: u64,
+    departures: u64,
+}
+
+impl Contribution {
+    fn add(&mut self, other: &Self) {
+        self.queue_area += other.queue_area;
+        self.customers_area += other.customers_area;
+        self.busy_area += other.busy_area;
+        self.capacity_area += other.capacity_area;
+        self.waits += other.waits;
+        self.started += other.started;
+        self.departures += other.departures;
+    }
+
+    fn subtract(&mut self, other: &Self) {
+        self.queue_area -= other.queue_area;
+        self.customers_area -= other.customers_area;
+        self.busy_area -= other.busy_area;
+        self.capacity_area -= other.capacity_area;
+        self.waits -= other.waits;
+        self.started -= other.started;
+        self.departures -= other.departures;
+    }
+
+    /// The time integrals scaled by `share`, the counts left as they are
+    fn scale_areas(&mut self, share: f64) {
+        self.queue_area *= share;
+        self.customers_area *= share;
+        self.busy_area *= share;
+        self.capacity_area *= share;
+    }
+}
+
+/// The state of the system after an event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowState {
+    pub queue_length: usize,
+    pub customers_in_system: usize,
+    pub busy_servers: usize,
+    pub servers: usize,
+}
+
+/// Running totals of the statistics after an event; the window keeps what changed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowCounters {
+    /// Sum of the waits of the customers who started service
+    pub wait_time: f64,
+    pub started: u64,
+    pub served: u64,
+}
+
+/// Metrics over the last `width` time units, see [`RollingWindow::stats`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowedStats {
+    /// Time units the values cover: the width, or less early in the run
+    pub span: f64,
+    /// Mean wait of the customers who started service within the window; 0 without any
+    pub mean_wait: f64,
+    pub mean_queue_length: f64,
+    pub mean_customers_in_system: f64,
+    pub utilization: f64,
+    /// Departures per time unit
+    pub throughput: f64,
+    pub departures: u64,
+    /// Whether the window fell back to coarse buckets and its edge is approximate
+    pub coarse: bool,
+}
+
+/// Queue length, utilization, waits and departures over a sliding horizon
+///
+/// The window is told the state and running totals after every event. Each
+/// stretch between two events becomes a record of its time integrals, and the
+/// waits and departures of the event ending it are added to it; records expire
+/// once they end before the horizon, and the one straddling it counts with the
+/// share of its time inside. Since the state is constant within a record the
+/// values are exact, and memory grows with the events in the window.
+///
+/// Should that exceed [`MAX_WINDOW_RECORDS`], new stretches are merged into
+/// buckets of 1/[`WINDOW_BUCKETS`] of the width for the rest of the run. The
+/// bucket at the horizon is then split by time as if its events were spread
+/// evenly, and its waits and departures count until it expires entirely, so the
+/// values may include up to one bucket's worth of older customers.
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    width: f64,
+    records: VecDeque<Contribution>,
+    /// Sum of the records
+    totals: Contribution,
+    /// Start of the window's observation, the run's start or the end of its warm-up
+    start: f64,
+    /// Time, state and running totals at the latest event
+    last_time: f64,
+    last_state: WindowState,
+    last_counters: WindowCounters,
+    coarse: bool,
+}
+
+impl RollingWindow {
+    /// A window over the last `width` time units, observing an empty system from t = 0
+    pub fn new(width: f64) -> Self {
+        assert!(width > 0.0, "the statistics window must be positive");
+        Self {
+            width,
+            records: VecDeque::new(),
+            totals: Contribution::default(),
+            start: 0.0,
+            last_time: 0.0,
+            last_state: WindowState::default(),
+            last_counters: WindowCounters::default(),
+            coarse: false,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Forget everything before `time`, from when the system is in `state` and the
+    /// running totals start over from zero; keeps the allocation
+    pub fn restart(&mut self, time: f64, state: WindowState) {
+        self.records.clear();
+        self.totals = Contribution::default();
+        self.start = time;
+        self.last_time = time;
+        self.last_state = state;
+        self.last_counters = WindowCounters::default();
+        self.coarse = false;
+    }
+
+    /// Whether the records were merged into buckets, see [`RollingWindow`]
+    pub fn is_coarse(&self) -> bool {
+        self.coarse
+    }
+
+    /// Bytes allocated for the records
+    pub fn memory_bytes(&self) -> usize {
+        self.records.capacity() * std::mem::size_of::<Contribution>()
+    }
+
+    /// An event at `time` left the system in `state` with the running totals `counters`
+    pub fn observe(&mut self, time: f64, state: WindowState, counters: WindowCounters) {
+        if time > self.last_time {
+            let elapsed = time - self.last_time;
+            let previous = self.last_state;
+            self.push(Contribution {
+                start: self.last_time,
+                end: time,
+                queue_area: previous.queue_length as f64 * elapsed,
+                customers_area: previous.customers_in_system as f64 * elapsed,
+                busy_area: previous.busy_servers as f64 * elapsed,
+                capacity_area: previous.servers as f64 * elapsed,
+                ..Contribution::default()
+            });
+            self.expire(time);
+        }
+
+        let instant = Contribution {
+            waits: counters.wait_time - self.last_counters.wait_time,
+            started: counters.started - self.last_counters.started,
+            departures: counters.served - self.last_counters.served,
+            ..Contribution::default()
+        };
+        if instant.started + instant.departures > 0 {
+            if self.records.is_empty() {
+                self.records.push_back(Contribution {
+                    start: time,
+                    end: time,
+                    ..Contribution::default()
+                });
+            }
+            self.records.back_mut().unwrap().add(&instant);
+            self.totals.add(&instant);
+        }
+
+        self.last_time = time;
+        self.last_state = state;
+        self.last_counters = counters;
+    }
+
+    /// Append a stretch, into the latest bucket once the records are coarse
+    fn push(&mut self, record: Contribution) {
+        self.totals.add(&record);
+        let bucket = self.width / WINDOW_BUCKETS as f64;
+        match self.records.back_mut() {
+            Some(back) if self.coarse && back.end - back.start < bucket => {
+                back.add(&record);
+                back.end = record.end;
+            }
+            _ => {
+                self.coarse |= self.records.len() >= MAX_WINDOW_RECORDS;
+                self.records.push_back(record);
+            }
+        }
+    }
+
+    /// Drop the records that ended at or before the horizon of `time`
+    fn expire(&mut self, time: f64) {
+        let horizon = time - self.width;
+        while let Some(front) = self.records.front()
+            && front.end <= horizon
+        {
+            self.totals.subtract(front);
+            self.records.pop_front();
+        }
+    }
+
+    /// The metrics over the window ending at `time`, at or after the latest event
+    pub fn stats(&self, time: f64) -> WindowedStats {
+        let horizon = time - self.width;
+        let mut sums = self.totals;
+
+        // Stretches that expired since the latest event, and the part of the next before the horizon
+        for record in &self.records {
+            if record.start >= horizon {
+                break;
+            }
+            let mut outside = *record;
+            if record.end > horizon {
+                outside.scale_areas((horizon - record.start) / (record.end - record.start));
+                outside.waits = 0.0;
+                outside.started = 0;
+                outside.departures = 0;
+            }
+            sums.subtract(&outside);
+        }
+
+        let open = (time - self.last_time.max(horizon)).max(0.0);
+        let state = self.last_state;
+        sums.queue_area += state.queue_length as f64 * open;
+        sums.customers_area += state.customers_in_system as f64 * open;
+        sums.busy_area += state.busy_servers as f64 * open;
+        sums.capacity_area += state.servers as f64 * open;
+
+        let span = self.width.min(time - self.start).max(0.0);
+        let per_time = |area: f64| {
+            if span > 0.0 {
+                area.max(0.0) / span
+            } else {
+                0.0
+            }
+        };
+        WindowedStats {
+            span,
+            mean_wait: if sums.started == 0 {
+                0.0
+            } else {
+                sums.waits.max(0.0) / sums.started as f64
+            },
+            mean_queue_length: per_time(sums.queue_area),
+            mean_customers_in_system: per_time(sums.customers_area),
+            utilization: if sums.capacity_area > 0.0 {
+                sums.busy_area.max(0.0) / sums.capacity_area
+            } else {
+                0.0
+            },
+            throughput: per_time(sums.departures as f64),
+            departures: sums.departures,
+            coarse: self.coarse,
+        }
+    }
+}
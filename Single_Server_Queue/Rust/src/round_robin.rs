@@ -0,0 +1,101 @@
+//! Round robin against FIFO, and how round robin approaches processor sharing
+//!
+//! Every discipline replays the same seeds, so the same customers arrive with the
+//! same services and only the order of service differs. As the quantum shrinks,
+//! round robin approaches processor sharing, under which a customer's sojourn is
+//! proportional to its service; the price is one event per quantum end.
+
+use crate::config::{DEFAULT_MIN_QUANTUM, QueueDiscipline, RoundRobin, SimulationConfig};
+use crate::simulation::Simulation;
+use crate::statistics::Moments;
+
+/// Outcome of one discipline, averaged over the replications
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantumOutcome {
+    /// `None` for FIFO
+    pub quantum: Option<f64>,
+    pub mean_sojourn: f64,
+    /// Median of sojourn / service, whose mean is infinite for exponential service
+    pub median_slowdown: f64,
+    pub p95_slowdown: f64,
+    pub max_slowdown: f64,
+    /// Least-squares slope of the sojourn against the service through the origin
+    pub sojourn_per_service: f64,
+    /// Events per customer relative to FIFO's one arrival and one departure
+    pub event_inflation: f64,
+}
+
+/// Run `config` under FIFO and under round robin with each of `quanta`, each for
+/// `replications` seeds, FIFO first
+///
+/// `run` advances a fresh simulation to the end of a run, e.g. until a number of
+/// customers have been served. Replication i uses seed `seed + i` under every
+/// discipline. Each quantum must be at least the configured minimum; `config`'s own
+/// discipline is ignored.
+pub fn compare_quanta<F>(
+    config: &SimulationConfig,
+    quanta: &[f64],
+    replications: usize,
+    seed: u64,
+    mut run: F,
+) -> Result<Vec<QuantumOutcome>, String>
+where
+    F: FnMut(&mut Simulation),
+{
+    if replications == 0 {
+        return Err("comparing quanta needs at least one replication".to_string());
+    }
+    let min_quantum = config
+        .round_robin
+        .map_or(DEFAULT_MIN_QUANTUM, |round_robin| round_robin.min_quantum);
+    let discipline_config = |quantum: Option<f64>| SimulationConfig {
+        discipline: match quantum {
+            Some(_) => QueueDiscipline::RoundRobin,
+            None => QueueDiscipline::Fifo,
+        },
+        round_robin: quantum.map(|quantum| RoundRobin {
+            quantum,
+            min_quantum,
+        }),
+        ..config.clone()
+    };
+
+    std::iter::once(None)
+        .chain(quanta.iter().copied().map(Some))
+        .map(|quantum| {
+            let base = discipline_config(quantum);
+            base.validate()?;
+            // One simulation per discipline, reset for every replication
+            let replication_config = |i: usize| SimulationConfig {
+                seed: Some(seed.wrapping_add(i as u64)),
+                ..base.clone()
+            };
+            let mut simulation = Simulation::new(replication_config(0));
+            let mut moments: [Moments; 6] = Default::default();
+            for i in 0..replications {
+                if i > 0 {
+                    simulation.reset(replication_config(i));
+                }
+                run(&mut simulation);
+                let stats = simulation.statistics();
+                let customer_events = (stats.arrived_customers() + stats.served_customers()) as f64;
+                moments[0].push(stats.average_sojourn_time());
+                moments[1].push(stats.slowdown_quantile(0.5).unwrap_or(f64::NAN));
+                moments[2].push(stats.sojourn_per_service().unwrap_or(f64::NAN));
+                moments[3].push(1.0 + stats.preemptions() as f64 / customer_events);
+                moments[4].push(stats.slowdown_quantile(0.95).unwrap_or(f64::NAN));
+                moments[5].push(stats.max_slowdown());
+            }
+            let mean = |k: usize| moments[k].mean().unwrap_or(f64::NAN);
+            Ok(QuantumOutcome {
+                quantum,
+                mean_sojourn: mean(0),
+                median_slowdown: mean(1),
+                sojourn_per_service: mean(2),
+                event_inflation: mean(3),
+                p95_slowdown: mean(4),
+                max_slowdown: mean(5),
+            })
+        })
+        .collect()
+}
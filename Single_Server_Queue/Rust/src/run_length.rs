@@ -0,0 +1,96 @@
+//! How many customers a run needs for a given precision of the mean wait, from a short pilot run
+//!
+//! Consecutive waits are strongly correlated, so a run of n customers tells less
+//! than n independent waits would. The pilot measures how much less: the batch
+//! means give the variance of the mean wait with the correlation included, the
+//! plain variance of the waits gives it as if they were independent, and their
+//! ratio is the factor by which the correlation inflates the variance. The half-width
+//! shrinks with the square root of the run length, so the pilot's relative
+//! half-width extrapolates to the length that reaches the target.
+
+use crate::config::SimulationConfig;
+use crate::model::StopCondition;
+use crate::simulation::Simulation;
+use crate::statistics::t_quantile_975;
+
+/// Customers served in the pilot run unless configured otherwise
+pub const DEFAULT_PILOT_CUSTOMERS: u64 = 20_000;
+
+/// What a pilot run says about the run length, see [`plan_run_length`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunLengthPlan {
+    /// Waits the pilot observed after its warm-up
+    pub pilot_customers: u64,
+    pub mean_wait: f64,
+    /// Half-width of the pilot's 95% interval over its mean wait
+    pub relative_half_width: f64,
+    /// Variance of the mean wait over what independent waits would give; the
+    /// pilot's waits count as this many times fewer independent ones
+    pub variance_inflation: f64,
+    pub target_relative_half_width: f64,
+    /// Customers to serve after the warm-up for the target half-width
+    pub required_customers: u64,
+}
+
+impl RunLengthPlan {
+    /// Independent waits the pilot's correlated ones are worth
+    pub fn effective_sample_size(&self) -> f64 {
+        self.pilot_customers as f64 / self.variance_inflation
+    }
+
+    /// Whether the pilot alone already reaches the target
+    pub fn pilot_suffices(&self) -> bool {
+        self.relative_half_width <= self.target_relative_half_width
+    }
+}
+
+/// Run `config` for `pilot_customers` served customers after its warm-up and
+/// extrapolate how many a run needs for a 95% interval of the mean wait whose
+/// half-width is `target_rel_halfwidth` times the mean
+///
+/// The estimate rests on the pilot's own interval, which comes from at most 40
+/// batches, so it is good to a few tens of percent: a pilot long enough for its
+/// batches to be nearly independent matters more than an exact target. Fails if
+/// the pilot yields no interval or no customer waited.
+pub fn plan_run_length(
+    config: &SimulationConfig,
+    pilot_customers: u64,
+    target_rel_halfwidth: f64,
+) -> Result<RunLengthPlan, String> {
+    if !(target_rel_halfwidth > 0.0 && target_rel_halfwidth.is_finite()) {
+        return Err("the target relative half-width must be positive".to_string());
+    }
+    config.validate()?;
+
+    let mut simulation = Simulation::new(config.clone());
+    simulation.run(&StopCondition::Customers(pilot_customers), &mut ());
+
+    let stats = simulation.statistics();
+    let (mean_wait, half_width) = stats.batch_means_wait().ok_or_else(|| {
+        format!(
+            "the pilot ended with {} customers served, too few for a confidence interval",
+            stats.served_customers()
+        )
+    })?;
+    let waits = stats.wait_times();
+    let variance = waits.variance().unwrap_or(0.0);
+    if mean_wait <= 0.0 || variance <= 0.0 {
+        return Err(
+            "no customer of the pilot waited, so the mean wait has no relative precision"
+                .to_string(),
+        );
+    }
+
+    let observed = waits.count();
+    let standard_error = half_width / t_quantile_975((stats.wait_batch_count() - 1) as f64);
+    let relative_half_width = half_width / mean_wait;
+    let scale = (relative_half_width / target_rel_halfwidth).powi(2);
+    Ok(RunLengthPlan {
+        pilot_customers: observed,
+        mean_wait,
+        relative_half_width,
+        variance_inflation: observed as f64 * standard_error.powi(2) / variance,
+        target_relative_half_width: target_rel_halfwidth,
+        required_customers: (observed as f64 * scale).ceil() as u64,
+    })
+}
@@ -0,0 +1,1212 @@
+use crate::cli::CliOptions;
+use crate::prompt::Prompter;
+use crate::questions::{QueueSetup, read_rates};
+use crate::report::{self, NumberFormat, OutputFormat};
+use crate::results::{print_analytic_comparison, print_results, validate_run};
+use crate::studies::run_study;
+use rust_single_server_queue::annotations::Annotations;
+use rust_single_server_queue::config::{QueueDiscipline, ServiceKind, SimulationConfig};
+use rust_single_server_queue::cost::CostModel;
+use rust_single_server_queue::engine::SimulationEngine;
+use rust_single_server_queue::event::Event;
+use rust_single_server_queue::event_log::PostMortem;
+use rust_single_server_queue::gantt::{DEFAULT_GANTT_CUSTOMERS, write_gantt};
+use rust_single_server_queue::live_viewer::LiveViewer;
+use rust_single_server_queue::model::{Deadline, Observer, RunOutcome, StopCondition};
+use rust_single_server_queue::plot_config::PlotLayout;
+use rust_single_server_queue::plotter::InteractivePlotViewer;
+use rust_single_server_queue::profiling::{
+    AllocationCounts, COUNTS_ALLOCATIONS, MemoryProfile, allocation_counts,
+};
+use rust_single_server_queue::sample_file::{
+    ChunkedSampleWriter, DEFAULT_CHUNK_SIZE, SAMPLE_METRICS, Sample, SampleFile,
+};
+use rust_single_server_queue::simulation::{QueueModel, Simulation};
+use rust_single_server_queue::statistics::Statistics;
+use rust_single_server_queue::steady_state::SteadyStateDetector;
+use rust_single_server_queue::stream_capture::{StreamCapture, StreamRecording};
+use rust_single_server_queue::theory::{
+    loss_customers_distribution, mm1_customers_distribution, mm1_wait_cv,
+};
+use rust_single_server_queue::time_series::{
+    CsvSampleStream, DEFAULT_MAX_SAMPLES, EnsembleSeries, SimulationTimeSeries,
+};
+use rust_single_server_queue::wait_distribution::{
+    QQ_POINTS, WaitDistribution, mm1_qq_points, write_ecdf_csv, write_qq_csv,
+};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// File the events before an anomaly are written to unless `--event-dump` says otherwise
+const DEFAULT_EVENT_DUMP: &str = "post_mortem.csv";
+
+/// Events between two progress dots, less one: about a million
+const PROGRESS_MASK: u64 = (1 << 20) - 1;
+
+/// Run the queue of `setup`, and a second configuration with `--compare`, write
+/// the outputs `options` asks for and open the viewer on the samples
+///
+/// The prompter is dropped before a step-through demonstration, which reads stdin itself.
+pub fn run_queue<R: BufRead, W: Write>(
+    options: &CliOptions,
+    setup: QueueSetup,
+    plot_layout: PlotLayout,
+    mut prompter: Prompter<R, W>,
+) {
+    let QueueSetup {
+        config,
+        stop_condition,
+    } = setup;
+    let cost_model = options.cost_model;
+    let warm_up_time = config.warm_up_time;
+
+    // The series coarsen their interval as the run goes on, so the initial one only
+    // matters for short runs: a known horizon is split evenly, otherwise start at
+    // one sample per mean interarrival time
+    let max_samples = options.max_samples.unwrap_or(DEFAULT_MAX_SAMPLES);
+    let sample_interval = match stop_condition {
+        StopCondition::Time(t) | StopCondition::SteadyState(t) => t / (max_samples - 1) as f64,
+        StopCondition::Events(_) | StopCondition::Customers(_) | StopCondition::Drained => {
+            1.0 / config.lambda
+        }
+    };
+    let new_time_series = || {
+        let mut time_series = SimulationTimeSeries::new(sample_interval, max_samples);
+        if options.interval_averages {
+            time_series = time_series.with_interval_averages();
+        }
+        if options.skip_initial_sample {
+            time_series.skip_initial_sample()
+        } else {
+            time_series
+        }
+    };
+    let chunk_size = options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+
+    let mut configs = vec![("A".to_string(), config.clone())];
+    if options.compare {
+        println!();
+        println!("=== Second Configuration (B) ===");
+        let (lambda, mu) = read_rates(&mut prompter, config.lambda, config.mu, config.waiting_room);
+        configs.push((
+            "B".to_string(),
+            SimulationConfig {
+                lambda,
+                mu,
+                ..config.clone()
+            },
+        ));
+    } else {
+        configs[0].0 = "Run".to_string();
+    }
+    // Release stdin for the step-through demonstration
+    drop(prompter);
+
+    if options.live {
+        println!("\nOpening live viewer...");
+        if let Err(e) = LiveViewer::launch(config) {
+            eprintln!("Error launching live viewer: {}", e);
+        }
+        return;
+    }
+    if options.step || options.pace.is_some() {
+        run_demonstration(
+            config,
+            &stop_condition,
+            options.pace,
+            options.fast_forward,
+            &cost_model,
+            options.format,
+            options.numbers,
+        );
+        return;
+    }
+
+    print_parameters(&config, &stop_condition, sample_interval, max_samples);
+    if run_study(options, &config, &stop_condition, new_time_series) {
+        return;
+    }
+
+    #[cfg(feature = "parquet")]
+    let mut parquet_writer = options.parquet_path.as_deref().and_then(|path| {
+        rust_single_server_queue::parquet::ParquetRunWriter::create(path)
+            .map_err(|e| eprintln!("Error creating {}: {}", path.display(), e))
+            .ok()
+    });
+
+    let capture = if let Some(path) = &options.replay_rng {
+        match StreamRecording::load(path) {
+            Ok(recording) => {
+                println!(
+                    "Replaying {} random draws from {}\n",
+                    recording.draws.len(),
+                    path.display()
+                );
+                Some(StreamCapture::replay(recording))
+            }
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        options.record_rng.as_ref().map(|_| StreamCapture::record())
+    };
+
+    let mut runs = Vec::with_capacity(configs.len());
+    let mut sample_files = Vec::new();
+    let mut run_report = String::new();
+    let mut annotations = Annotations::default();
+    let mut validation_failed = false;
+    for (label, config) in configs {
+        if options.compare {
+            println!("=== Running configuration {} ===", label);
+        }
+
+        let mut simulation = match &capture {
+            Some(capture) => Simulation::with_capture(config, capture.clone()),
+            None => Simulation::new(config),
+        };
+        let stream = options.stream_csv.as_ref().and_then(|path| {
+            let path = if options.compare {
+                labelled_path(path, &label)
+            } else {
+                path.clone()
+            };
+            open_stream(&path, sample_interval)
+        });
+        let chunks = options.stream_chunks.as_ref().and_then(|path| {
+            let path = if options.compare {
+                labelled_path(path, &label)
+            } else {
+                path.clone()
+            };
+            open_chunks(&path, sample_interval, chunk_size, max_samples)
+        });
+        let (time_series, elapsed_secs, memory, streamed) = run_simulation(
+            &mut simulation,
+            &stop_condition,
+            new_time_series(),
+            stream,
+            chunks,
+            options.max_wall_time,
+        );
+        if let Some(explosion) = simulation.queue_explosion() {
+            eprintln!("Error: {}", explosion);
+            std::process::exit(1);
+        }
+        if let StopCondition::SteadyState(max_time) = stop_condition {
+            print_steady_state(&time_series, warm_up_time, max_time);
+        }
+        print_results(
+            &simulation,
+            elapsed_secs,
+            &memory,
+            &cost_model,
+            options.format,
+            options.numbers,
+        );
+        write_run_files(
+            options,
+            options.compare.then_some(label.as_str()),
+            &simulation,
+            &time_series,
+        );
+        if let Some(tolerance) = options.validate {
+            validation_failed |=
+                !validate_run(&simulation, tolerance, options.validate_metrics.as_deref());
+        }
+        if options.analytic_compare {
+            print_analytic_comparison(
+                &simulation,
+                &memory,
+                &cost_model,
+                options.format,
+                options.numbers,
+            );
+        }
+        if simulation.config().service_modulation.is_some() {
+            print_speed_up_baseline(&simulation, &stop_condition, options.numbers);
+        }
+        if let Some(capture) = &capture
+            && !finish_capture(&simulation, capture, options.record_rng.as_deref())
+        {
+            std::process::exit(1);
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(path) = &options.sqlite_path {
+            export_sqlite(path, options.sqlite_replace, &simulation, &time_series);
+        }
+        #[cfg(feature = "parquet")]
+        export_parquet(
+            &mut parquet_writer,
+            runs.len() as u32,
+            &label,
+            &simulation,
+            &time_series,
+        );
+
+        for annotation in simulation.annotations().as_slice() {
+            let text = if options.compare {
+                format!("{} ({})", annotation.label, label)
+            } else {
+                annotation.label.clone()
+            };
+            annotations.push(annotation.time, text, annotation.category);
+        }
+
+        if options.compare {
+            let gap = if run_report.is_empty() { "" } else { "\n" };
+            run_report.push_str(&format!("{}Configuration {}\n", gap, label));
+        }
+        run_report.push_str(&report::markdown_table(
+            &report::comparisons(&simulation),
+            options.numbers,
+        ));
+
+        if let Some((path, tail)) = streamed {
+            sample_files.push((label.clone(), path, tail));
+        }
+        runs.push((label, time_series));
+        println!();
+    }
+
+    if let Some(path) = &options.annotations_csv {
+        match annotations.write_csv(path) {
+            Ok(()) => println!(
+                "Wrote {} annotations to {}\n",
+                annotations.len(),
+                path.display()
+            ),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+
+    let mut ensemble = None;
+    if options.replications > 1 {
+        println!("=== Additional Replications ===");
+        let mut replications = Vec::with_capacity(options.replications - 1);
+        // One simulation reset for every replication, so its buffers are only allocated once
+        let mut simulation = Simulation::new(config.clone());
+        for i in 1..options.replications {
+            simulation.reset(SimulationConfig {
+                seed: config.seed.map(|seed| seed.wrapping_add(i as u64)),
+                ..config.clone()
+            });
+            let stream = options.stream_csv.as_ref().and_then(|path| {
+                open_stream(
+                    &labelled_path(path, &format!("replication_{}", i + 1)),
+                    sample_interval,
+                )
+            });
+            let chunks = options.stream_chunks.as_ref().and_then(|path| {
+                open_chunks(
+                    &labelled_path(path, &format!("replication_{}", i + 1)),
+                    sample_interval,
+                    chunk_size,
+                    max_samples,
+                )
+            });
+            let (time_series, _, _, _) = run_simulation(
+                &mut simulation,
+                &stop_condition,
+                new_time_series(),
+                stream,
+                chunks,
+                options.max_wall_time,
+            );
+            let stats = simulation.statistics();
+            println!(
+                "Replication {}: average wait {:.4}, average queue length {:.4}",
+                i + 1,
+                stats.average_wait_time(),
+                stats.average_queue_length(simulation.now())
+            );
+            #[cfg(feature = "parquet")]
+            export_parquet(
+                &mut parquet_writer,
+                i as u32,
+                &format!("replication {}", i + 1),
+                &simulation,
+                &time_series,
+            );
+            replications.push(time_series);
+        }
+
+        let series: Vec<_> = std::iter::once(&runs[0].1)
+            .chain(&replications)
+            .map(|time_series| &time_series.queue_length)
+            .collect();
+        let queue_ensemble = EnsembleSeries::from_replications(&series, |v| v as f64);
+
+        if let Some(path) = &options.ensemble_csv {
+            match queue_ensemble.write_csv(path) {
+                Ok(()) => println!("Wrote queue length quantiles to {}", path.display()),
+                Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+            }
+        }
+        ensemble = Some(queue_ensemble);
+        println!();
+    }
+
+    #[cfg(feature = "parquet")]
+    if let (Some(writer), Some(path)) = (parquet_writer, &options.parquet_path) {
+        match writer.close() {
+            Ok(()) => println!("Wrote samples to {}\n", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+
+    // A validation run is checked by its exit status, not looked at
+    if options.validate.is_some() {
+        std::process::exit(if validation_failed { 1 } else { 0 });
+    }
+
+    launch_viewer(
+        runs,
+        sample_files,
+        &config,
+        annotations,
+        run_report,
+        ensemble,
+        plot_layout,
+    );
+}
+
+/// The parameters of a queue run, printed before it starts
+fn print_parameters(
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    sample_interval: f64,
+    max_samples: usize,
+) {
+    let SimulationConfig {
+        lambda,
+        mu,
+        servers,
+        waiting_room,
+        arrival_cutoff,
+        breakdowns,
+        warm_up_time,
+        ..
+    } = *config;
+    println!();
+    println!("=== High-Performance Rust Single Server Queue Simulation ===");
+    println!("Parameters:");
+    println!("  Arrival rate (λ): {:.4}", lambda);
+    println!("  Service rate (μ): {:.4}", mu);
+    if config.arrival_kind != ServiceKind::Exponential {
+        println!(
+            "  Interarrival times: {} with mean {:.4}",
+            config.arrival_kind,
+            1.0 / lambda
+        );
+    }
+    match stop_condition {
+        StopCondition::Time(t) => println!("  Stop condition: Simulation time <= {:.0}", t),
+        StopCondition::Events(e) => println!("  Stop condition: Events processed <= {}", e),
+        StopCondition::Customers(c) => println!("  Stop condition: Customers served <= {}", c),
+        StopCondition::Drained => println!(
+            "  Stop condition: Arrivals stop at {:.0}, then the queue drains",
+            arrival_cutoff.unwrap_or(0.0)
+        ),
+        StopCondition::SteadyState(t) => println!(
+            "  Stop condition: Mean wait in steady state, at most until {:.0}",
+            t
+        ),
+    }
+    if waiting_room {
+        println!("  Traffic intensity (ρ=λ/μ): {:.4}", lambda / mu);
+    } else {
+        println!("  Loss system with {} servers (M/M/c/c)", servers);
+        println!("  Offered load (a=λ/μ): {:.4} Erlangs", lambda / mu);
+    }
+    if let Some(limit) = config.max_queue_length {
+        println!("  Queue capped at {} waiting customers", limit);
+    }
+    if let Some(b) = breakdowns {
+        println!(
+            "  Breakdowns: MTTF {:.4}, mean repair {:.4}, interrupted services {}",
+            b.mean_time_to_failure, b.mean_repair_time, b.interrupted_service
+        );
+    }
+    if let Some(standby) = config.standby {
+        println!(
+            "  Standby: switchover {:.4}, MTTF {:.4}, mean repair {:.4}",
+            standby.switchover_time, standby.mean_time_to_failure, standby.mean_repair_time
+        );
+    }
+    if let Some(setup) = config.setup {
+        println!("  Setup time: {} with mean {:.4}", setup.kind, setup.mean);
+    }
+    if let Some(modulation) = &config.service_modulation {
+        println!("  Service rate after a wait: {}", modulation);
+    }
+    if let Some(deadlines) = config.deadlines {
+        println!(
+            "  Deadlines: {} with mean {:.4}{}",
+            deadlines.kind,
+            deadlines.mean,
+            if deadlines.drop_late {
+                ", late customers dropped"
+            } else {
+                ""
+            }
+        );
+        println!("  Queue discipline: {}", config.discipline);
+    }
+    if let Some(round_robin) = config.round_robin {
+        println!("  Round robin: quantum {:.4}", round_robin.quantum);
+    }
+    if warm_up_time > 0.0 {
+        println!("  Warm-up period: {:.0}", warm_up_time);
+    }
+    println!("  Random generator: {}", config.rng_backend);
+    println!("  Initial sample interval: {:.4}", sample_interval);
+    println!("  Samples per series: at most {}", max_samples);
+    println!();
+}
+
+/// Write the files `options` asks for about a finished run, labelled when runs are compared
+fn write_run_files(
+    options: &CliOptions,
+    label: Option<&str>,
+    simulation: &Simulation,
+    time_series: &SimulationTimeSeries,
+) {
+    let path_for = |path: &Path| match label {
+        Some(label) => labelled_path(path, label),
+        None => path.to_path_buf(),
+    };
+    if let Some(path) = options.gantt.as_deref().map(path_for) {
+        let customers = options.gantt_customers.unwrap_or(DEFAULT_GANTT_CUSTOMERS);
+        let records = simulation.server().customer_trace().records();
+        match write_gantt(&path, records, customers) {
+            Ok(bars) => println!(
+                "Drew {} customers as a Gantt chart to {}",
+                bars,
+                path.display()
+            ),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+    if let Some(post_mortem) = simulation.post_mortem() {
+        let path = options
+            .event_dump
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_EVENT_DUMP));
+        write_post_mortem(post_mortem, &path_for(path));
+    }
+    if let Some(path) = options.server_utilization_csv.as_deref().map(path_for) {
+        match time_series.write_server_utilization_csv(&path) {
+            Ok(()) => println!("Wrote per-server utilization to {}", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = options.occupancy_csv.as_deref().map(path_for) {
+        match time_series.queue_occupancy.write_csv(&path) {
+            Ok(()) => println!("Wrote the queue length occupancy to {}", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+    if options.wait_ecdf_csv.is_some() || options.wait_qq_csv.is_some() {
+        write_wait_distribution(options, label, simulation.config(), time_series);
+    }
+}
+
+/// Open the interactive viewer on the sampled runs, with the theory that fits `config`
+///
+/// A run in `sample_files` is shown from the file its samples were streamed to,
+/// starting on the recent samples kept in memory.
+fn launch_viewer(
+    runs: Vec<(String, SimulationTimeSeries)>,
+    sample_files: Vec<(String, PathBuf, Vec<Sample>)>,
+    config: &SimulationConfig,
+    annotations: Annotations,
+    run_report: String,
+    ensemble: Option<EnsembleSeries>,
+    plot_layout: PlotLayout,
+) {
+    let SimulationConfig {
+        lambda,
+        mu,
+        servers,
+        waiting_room,
+        warm_up_time,
+        ..
+    } = *config;
+    println!("=== Launching Interactive Viewer ===");
+    println!("Samples collected: {}", runs[0].1.queue_length.len());
+    println!("Opening interactive plot window...");
+    println!("Use scroll wheel to zoom, drag to pan!");
+
+    let mut viewer = InteractivePlotViewer::new(runs, (warm_up_time > 0.0).then_some(warm_up_time));
+    viewer = viewer
+        .with_throughput_window(config.throughput_window)
+        .with_annotations(annotations.as_slice().to_vec())
+        .with_report(run_report)
+        .with_plot_layout(plot_layout);
+    for (label, path, tail) in sample_files {
+        match SampleFile::open(&path) {
+            Ok(file) => viewer = viewer.with_sample_file(&label, file, tail),
+            Err(e) => eprintln!("Error reading {}: {}", path.display(), e),
+        }
+    }
+    if let Some(ensemble) = ensemble {
+        viewer = viewer.with_ensemble(ensemble);
+    }
+    if let Some(window) = config.statistics_window {
+        viewer = viewer.with_statistics_window(window);
+    }
+    if waiting_room {
+        if let Some(distribution) = mm1_customers_distribution(lambda / mu, 50) {
+            viewer = viewer.with_steady_state_distribution(distribution);
+        }
+        if servers == 1
+            && report::theory_mismatch(config).is_none()
+            && let Some(cv) = mm1_wait_cv(lambda / mu)
+        {
+            viewer = viewer.with_theoretical_wait_cv(cv);
+        }
+        if let Some((lambda, mu)) = mm1_wait_rates(config) {
+            viewer = viewer.with_theoretical_wait(lambda, mu);
+        }
+    } else {
+        viewer = viewer
+            .with_server_limit(servers)
+            .with_steady_state_distribution(loss_customers_distribution(servers, lambda / mu));
+    }
+    if let Err(e) = viewer.launch() {
+        eprintln!("Error launching interactive viewer: {}", e);
+    }
+}
+
+/// Whether a run stepped by hand goes on: events are left, the replay has not
+/// failed and the stop condition is not met
+fn should_continue(simulation: &Simulation, condition: &StopCondition) -> bool {
+    simulation.engine().has_next_event()
+        && simulation.replay_error().is_none()
+        && simulation.queue_explosion().is_none()
+        && !condition.is_met(
+            simulation.model(),
+            simulation.engine(),
+            simulation.event_count(),
+        )
+}
+
+/// `path` with the run label in front of its file name, so compared runs get separate files
+fn labelled_path(path: &Path, label: &str) -> PathBuf {
+    path.with_file_name(format!(
+        "{}_{}",
+        label,
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ))
+}
+
+/// λ and μ of the M/M/1 queue whose wait distribution a run of `config` follows,
+/// or `None` if its waits are not distributed like that
+///
+/// Beyond [`report::theory_mismatch`] this needs first-come first-served: other
+/// disciplines keep the mean wait but reorder who waits how long.
+fn mm1_wait_rates(config: &SimulationConfig) -> Option<(f64, f64)> {
+    (config.servers == 1
+        && config.waiting_room
+        && config.discipline == QueueDiscipline::Fifo
+        && config.lambda < config.mu
+        && report::theory_mismatch(config).is_none())
+    .then_some((config.lambda, config.mu))
+}
+
+/// Write the ECDF and the QQ data of the waits of a finished run to the files
+/// `options` asks for, labelled when runs are compared
+fn write_wait_distribution(
+    options: &CliOptions,
+    label: Option<&str>,
+    config: &SimulationConfig,
+    time_series: &SimulationTimeSeries,
+) {
+    let waits = WaitDistribution::new(&time_series.wait_histogram, time_series.zero_waits);
+    let theory = mm1_wait_rates(config);
+    let path_for = |path: &PathBuf| match label {
+        Some(label) => labelled_path(path, label),
+        None => path.clone(),
+    };
+    if let Some(path) = options.wait_ecdf_csv.as_ref().map(path_for) {
+        match write_ecdf_csv(&path, &waits, theory) {
+            Ok(()) => println!("Wrote the wait ECDF to {}", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = options.wait_qq_csv.as_ref().map(path_for) {
+        let Some(points) =
+            theory.and_then(|(lambda, mu)| mm1_qq_points(&waits, lambda, mu, QQ_POINTS))
+        else {
+            eprintln!(
+                "Skipping {}: the QQ plot needs an M/M/1 FIFO queue with waits",
+                path.display()
+            );
+            return;
+        };
+        match write_qq_csv(&path, &points) {
+            Ok(()) => println!("Wrote the wait QQ data to {}", path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Report the anomaly of a run and dump the events that led up to it to `path`
+fn write_post_mortem(post_mortem: &PostMortem, path: &Path) {
+    println!();
+    println!(
+        "!!! WARNING: anomaly at t = {:.4}: {}",
+        post_mortem.time, post_mortem.anomaly
+    );
+    match post_mortem.write_csv(path) {
+        Ok(()) => println!(
+            "!!! The last {} events before it are in {}",
+            post_mortem.events.len(),
+            path.display()
+        ),
+        Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+    }
+}
+
+/// Sample stream to `path`, or `None` after reporting why it cannot be created
+fn open_stream(path: &Path, interval: f64) -> Option<(PathBuf, CsvSampleStream)> {
+    match CsvSampleStream::create(path, interval) {
+        Ok(stream) => Some((path.to_path_buf(), stream)),
+        Err(e) => {
+            eprintln!("Error creating {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// A run's samples streamed to disk in chunks, see `--stream-chunks`
+type ChunkFile = ChunkedSampleWriter<BufWriter<File>>;
+
+/// Chunked sample file at `path` keeping the last `recent` samples in memory for
+/// the viewer, or `None` after reporting why it cannot be created
+fn open_chunks(
+    path: &Path,
+    interval: f64,
+    chunk_size: usize,
+    recent: usize,
+) -> Option<(PathBuf, ChunkFile)> {
+    match ChunkedSampleWriter::create(path, interval, chunk_size, recent) {
+        Ok(writer) => Some((path.to_path_buf(), writer)),
+        Err(e) => {
+            eprintln!("Error creating {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Samples a run into its time series and streams and shows progress on the way
+struct RunRecorder {
+    time_series: SimulationTimeSeries,
+    stream: Option<(PathBuf, CsvSampleStream)>,
+    chunks: Option<(PathBuf, ChunkFile)>,
+    /// Looks at every new mean-wait sample under [`StopCondition::SteadyState`]
+    detector: Option<SteadyStateDetector>,
+    cap_warned: bool,
+    /// Allocations when the previous event was handed over, and those made by handling events
+    last_counts: AllocationCounts,
+    event_allocations: AllocationCounts,
+}
+
+impl Observer<QueueModel> for RunRecorder {
+    fn on_event(
+        &mut self,
+        model: &QueueModel,
+        engine: &SimulationEngine,
+        event: &Event,
+    ) -> ControlFlow<()> {
+        self.event_allocations
+            .add(allocation_counts().since(self.last_counts));
+        let now = engine.now();
+        let stats = model.statistics();
+
+        if !self.cap_warned && stats.lost_customers() > 0 {
+            eprintln!(
+                "\nWARNING: the queue is full at t = {:.2}; further arrivals are turned away",
+                now
+            );
+            self.cap_warned = true;
+        }
+
+        let time_series = &mut self.time_series;
+        time_series.record_event(
+            event.event_type,
+            now,
+            model.total_arrivals(),
+            model.total_departures(),
+        );
+
+        let samples = time_series.mean_wait_time.len();
+        time_series.sample_statistics(now, stats);
+        if let Some((path, writer)) = &mut self.stream
+            && let Err(e) = writer.sample_statistics(now, stats)
+        {
+            eprintln!("\nError writing {}: {}", path.display(), e);
+            self.stream = None;
+        }
+        if let Some((path, writer)) = &mut self.chunks
+            && let Err(e) = writer.sample_statistics(now, stats)
+        {
+            eprintln!("\nError writing {}: {}", path.display(), e);
+            self.chunks = None;
+        }
+        let mut flow = ControlFlow::Continue(());
+        if let Some(detector) = &self.detector
+            && time_series.mean_wait_time.len() > samples
+            && detector.is_steady(after_warm_up(
+                time_series.mean_wait_time.data(),
+                model.config().warm_up_time,
+            ))
+        {
+            flow = ControlFlow::Break(());
+        }
+
+        // Progress indicator every 2^20 events, a mask being cheaper than a modulo
+        if model.event_count() & PROGRESS_MASK == 0 {
+            print!(".");
+            io::stdout().flush().unwrap();
+        }
+        self.last_counts = allocation_counts();
+        flow
+    }
+
+    fn on_finish(&mut self, model: &QueueModel, _engine: &SimulationEngine, _outcome: &RunOutcome) {
+        println!("\n");
+        let stats = model.statistics();
+        self.time_series
+            .wait_histogram
+            .clone_from(stats.wait_histogram());
+        self.time_series.zero_waits = stats.immediate_services();
+        self.time_series
+            .slowdown_histogram
+            .clone_from(stats.slowdown_histogram());
+    }
+}
+
+/// Run a fresh or reset simulation to the stop condition, filling the empty `time_series` along the way
+///
+/// With a `stream` every sample is also written to its file as it is taken, and
+/// with `chunks` appended to its file a chunk at a time; a write error is
+/// reported and ends the stream, not the run.
+/// Returns the samples, the wall-clock seconds spent and the memory the run took,
+/// with the allocations of the loop if this build counts them, and the chunked
+/// file written with the recent samples the writer kept.
+pub fn run_simulation(
+    simulation: &mut Simulation,
+    stop_condition: &StopCondition,
+    mut time_series: SimulationTimeSeries,
+    stream: Option<(PathBuf, CsvSampleStream)>,
+    chunks: Option<(PathBuf, ChunkFile)>,
+    max_wall_time: Option<Duration>,
+) -> (
+    SimulationTimeSeries,
+    f64,
+    MemoryProfile,
+    Option<(PathBuf, Vec<Sample>)>,
+) {
+    let start_time = Instant::now();
+    // An initial queue is there from t = 0, before the first event is sampled
+    time_series.queue_occupancy.record(
+        simulation.now(),
+        simulation.statistics().current_queue_length(),
+    );
+    let loop_start = allocation_counts();
+    let recorder = RunRecorder {
+        time_series,
+        stream,
+        chunks,
+        detector: matches!(stop_condition, StopCondition::SteadyState(_))
+            .then(SteadyStateDetector::default),
+        cap_warned: simulation.config().max_queue_length.is_none(),
+        last_counts: loop_start,
+        event_allocations: AllocationCounts::default(),
+    };
+    let deadline = max_wall_time.map(Deadline::after);
+
+    let mut observers = (recorder, deadline);
+    simulation.run(stop_condition, &mut observers);
+    let (recorder, deadline) = observers;
+
+    let loop_allocations = allocation_counts().since(loop_start);
+    // A run may also end at a steady state found at the event the deadline passed
+    if let Some(limit) = max_wall_time
+        && deadline.is_some_and(|deadline| deadline.has_passed())
+    {
+        print_wall_clock_stop(simulation, stop_condition, limit);
+    }
+
+    let RunRecorder {
+        time_series,
+        stream,
+        chunks,
+        event_allocations,
+        ..
+    } = recorder;
+    if let Some((path, writer)) = stream {
+        let rows = writer.rows();
+        match writer.finish() {
+            Ok(()) => println!("Streamed {} samples to {}", rows, path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+    let mut streamed = None;
+    if let Some((path, writer)) = chunks {
+        let samples = writer.samples();
+        let tail = writer.recent().iter().copied().collect();
+        match writer.finish() {
+            Ok(_) => {
+                println!(
+                    "Streamed {} samples in chunks to {}",
+                    samples,
+                    path.display()
+                );
+                streamed = Some((path, tail));
+            }
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+    }
+
+    let elapsed_secs = start_time.elapsed().as_secs_f64();
+    let memory = MemoryProfile {
+        loop_allocations: COUNTS_ALLOCATIONS.then_some(loop_allocations),
+        event_allocations: COUNTS_ALLOCATIONS.then_some(event_allocations),
+        ..MemoryProfile::measure(simulation, Some(&time_series))
+    };
+    (time_series, elapsed_secs, memory, streamed)
+}
+
+/// Save a recording to `record_path`, or report how a replay went; false if the replay failed
+fn finish_capture(
+    simulation: &Simulation,
+    capture: &StreamCapture,
+    record_path: Option<&Path>,
+) -> bool {
+    println!();
+    if let Some(path) = record_path {
+        let recording = capture.recording();
+        match recording.save(path) {
+            Ok(()) => println!(
+                "Recorded {} random draws to {}",
+                recording.draws.len(),
+                path.display()
+            ),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        }
+        return true;
+    }
+    if let Some(error) = simulation.replay_error() {
+        eprintln!(
+            "Error: the replay stopped at t = {:.4}: {}",
+            simulation.now(),
+            error
+        );
+        return false;
+    }
+    match capture.remaining() {
+        0 => println!("Replayed every recorded draw"),
+        // Fewer draws than recorded: the run stopped earlier or this build draws less
+        left => println!(
+            "Replay finished with {} recorded draws unused; the run stopped earlier or draws less",
+            left
+        ),
+    }
+    true
+}
+
+/// Run the configuration again without the service speed-up and compare the two
+///
+/// The speed-up draws no random numbers, so from the same seed both runs see the
+/// same arrivals and service draws and differ only by the speed-up.
+fn print_speed_up_baseline(
+    simulation: &Simulation,
+    stop_condition: &StopCondition,
+    numbers: NumberFormat,
+) {
+    let config = simulation.config();
+    let mut baseline = Simulation::new(SimulationConfig {
+        service_modulation: None,
+        ..config.clone()
+    });
+    baseline.run(stop_condition, &mut ());
+
+    let (modulated, unmodulated) = (simulation.statistics(), baseline.statistics());
+    let correlation = |stats: &Statistics| {
+        stats
+            .wait_service_correlation()
+            .map_or_else(|| "-".to_string(), |c| numbers.value(c))
+    };
+    println!();
+    println!("=== Speed-up by the wait vs. service at μ ===");
+    if config.seed.is_none() {
+        println!(
+            "Without --seed the baseline draws its own random numbers, so the pair is not matched"
+        );
+    }
+    println!("{:<36} {:>12} {:>12}", "", "speed-up", "baseline");
+    println!(
+        "{:<36} {:>12} {:>12}",
+        "Mean wait",
+        numbers.value(modulated.average_wait_time()),
+        numbers.value(unmodulated.average_wait_time())
+    );
+    println!(
+        "{:<36} {:>12} {:>12}",
+        "Utilization",
+        numbers.value(modulated.utilization(simulation.now())),
+        numbers.value(unmodulated.utilization(baseline.now()))
+    );
+    println!(
+        "{:<36} {:>12} {:>12}",
+        "Correlation of wait and service time",
+        correlation(modulated),
+        correlation(unmodulated)
+    );
+    println!(
+        "Change of the mean wait: {}",
+        numbers.value(modulated.average_wait_time() - unmodulated.average_wait_time())
+    );
+}
+
+/// Report that a run was cut short by `--max-wall-time` and how far it got
+fn print_wall_clock_stop(simulation: &Simulation, condition: &StopCondition, limit: Duration) {
+    let now = simulation.now();
+    let reached = match condition {
+        StopCondition::Time(max_time) => Some((now / max_time, "of the simulation time")),
+        StopCondition::Events(max_events) => Some((
+            simulation.event_count() as f64 / *max_events as f64,
+            "of the events",
+        )),
+        StopCondition::Customers(max_customers) => Some((
+            simulation.statistics().served_customers() as f64 / *max_customers as f64,
+            "of the customers",
+        )),
+        StopCondition::Drained => None,
+        StopCondition::SteadyState(max_time) => {
+            Some((now / max_time, "of the maximum simulation time"))
+        }
+    };
+    println!(
+        "!!! WARNING: the wall-clock limit of {:.2}s was reached at t = {:.2} after {} events !!!",
+        limit.as_secs_f64(),
+        now,
+        simulation.event_count()
+    );
+    match reached {
+        Some((fraction, target)) => println!(
+            "!!! The run got through {:.2}% {}; these results describe the partial run",
+            fraction * 100.0,
+            target
+        ),
+        None => println!("!!! The queue had not drained; these results describe the partial run"),
+    }
+    println!();
+}
+
+/// Samples from the end of the warm-up on; earlier ones describe the discarded transient
+fn after_warm_up(samples: &[(f64, f64)], warm_up_time: f64) -> &[(f64, f64)] {
+    &samples[samples.partition_point(|(t, _)| *t < warm_up_time)..]
+}
+
+/// Whether a steady-state run ended because the mean wait settled or because it hit the cap
+fn print_steady_state(time_series: &SimulationTimeSeries, warm_up_time: f64, max_time: f64) {
+    let detector = SteadyStateDetector::default();
+    let samples = after_warm_up(time_series.mean_wait_time.data(), warm_up_time);
+    match samples.last() {
+        Some((time, _)) if detector.is_steady(samples) => println!(
+            "Steady state reached at t = {:.0}: the mean wait drifted by less than {}% over the last {} samples",
+            time,
+            detector.tolerance() * 100.0,
+            detector.window()
+        ),
+        _ => println!(
+            "!!! WARNING: hit the time cap of {:.0} before the mean wait settled; the results may still be transient",
+            max_time
+        ),
+    }
+}
+
+/// Console demonstration: either one event per Enter press (`pace == None`) or
+/// events printed as they happen with simulated time running at `pace` units per second
+fn run_demonstration(
+    config: SimulationConfig,
+    stop_condition: &StopCondition,
+    pace: Option<f64>,
+    fast_forward: Option<f64>,
+    cost_model: &CostModel,
+    format: OutputFormat,
+    numbers: NumberFormat,
+) {
+    let mut simulation = Simulation::new(config);
+    let start_time = Instant::now();
+
+    let skip_to = |simulation: &mut Simulation, time: f64| {
+        // Not past the end of a timed run
+        let time = match *stop_condition {
+            StopCondition::Time(end) | StopCondition::SteadyState(end) => time.min(end),
+            _ => time,
+        };
+        let started = Instant::now();
+        let events = simulation.run_until(time);
+        println!(
+            "Fast-forwarded to t = {:.4}: {} events in {:.3}s, {}",
+            simulation.now(),
+            events,
+            started.elapsed().as_secs_f64(),
+            simulation.describe_state()
+        );
+    };
+    if let Some(time) = fast_forward {
+        skip_to(&mut simulation, time);
+    }
+
+    let print_event = |simulation: &Simulation, event: &Event| {
+        println!(
+            "t = {:>12.4}  {:<9} -> {}",
+            event.time,
+            event.event_type,
+            simulation.describe_state()
+        );
+    };
+
+    match pace {
+        Some(sim_units_per_second) => {
+            println!(
+                "\nRunning at {} time units per second",
+                sim_units_per_second
+            );
+            simulation.run_paced(f64::INFINITY, sim_units_per_second, |simulation, event| {
+                print_event(simulation, event);
+                if should_continue(simulation, stop_condition) {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            });
+        }
+        None => {
+            println!(
+                "\nPress Enter to process the next event, a time + Enter to fast-forward to it, 'q' + Enter to finish"
+            );
+            let mut input = String::new();
+            while should_continue(&simulation, stop_condition) {
+                input.clear();
+                if io::stdin().read_line(&mut input).unwrap() == 0 || input.trim() == "q" {
+                    break;
+                }
+                if let Ok(time) = input.trim().parse::<f64>() {
+                    skip_to(&mut simulation, time);
+                } else if let Some(event) = simulation.step() {
+                    print_event(&simulation, &event);
+                }
+            }
+        }
+    }
+
+    if let Some(explosion) = simulation.queue_explosion() {
+        eprintln!("\nError: {}", explosion);
+        std::process::exit(1);
+    }
+    println!();
+    print_results(
+        &simulation,
+        start_time.elapsed().as_secs_f64(),
+        &MemoryProfile::measure(&simulation, None),
+        cost_model,
+        format,
+        numbers,
+    );
+}
+
+/// Print the samples of a chunked sample file as CSV, only those in `range` if given
+///
+/// A chunk cut off by a killed run is left out with a warning.
+pub fn print_sample_range(path: &Path, range: Option<(f64, f64)>) -> io::Result<()> {
+    let mut file = SampleFile::open(path)?;
+    if let Some(truncated) = file.truncated() {
+        eprintln!("Warning: {}: {}", path.display(), truncated);
+    }
+    eprintln!(
+        "{} samples in {} chunks of up to {}{}",
+        file.len(),
+        file.chunks(),
+        file.chunk_size(),
+        file.time_span()
+            .map_or(String::new(), |(first, last)| format!(
+                ", from t = {} to {}",
+                first, last
+            ))
+    );
+    let (start, end) = range.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+    let samples = file.range(start, end)?;
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    writeln!(out, "time,{}", SAMPLE_METRICS.join(","))?;
+    for sample in samples {
+        write!(out, "{}", sample.time)?;
+        for value in sample.values {
+            write!(out, ",{}", value)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+#[cfg(feature = "sqlite")]
+fn export_sqlite(
+    path: &std::path::Path,
+    replace: Option<i64>,
+    simulation: &Simulation,
+    time_series: &SimulationTimeSeries,
+) {
+    use rust_single_server_queue::sqlite;
+
+    let summary = sqlite::RunSummary {
+        config: simulation.config(),
+        total_time: simulation.now(),
+        event_count: simulation.event_count(),
+        served_customers: simulation.statistics().served_customers(),
+    };
+    let result = sqlite::open(path).and_then(|mut connection| {
+        sqlite::write_run(
+            &mut connection,
+            replace,
+            &summary,
+            time_series,
+            simulation.server().customer_trace().records(),
+        )
+    });
+    match result {
+        Ok(run_id) => println!("Stored run {} in {}", run_id, path.display()),
+        Err(e) => eprintln!("Error writing SQLite database: {}", e),
+    }
+}
+
+/// Append a run to the Parquet file; after an error the writer is dropped and
+/// later runs are skipped
+#[cfg(feature = "parquet")]
+fn export_parquet(
+    writer: &mut Option<rust_single_server_queue::parquet::ParquetRunWriter>,
+    run_id: u32,
+    label: &str,
+    simulation: &Simulation,
+    time_series: &SimulationTimeSeries,
+) {
+    let Some(active) = writer else {
+        return;
+    };
+    if let Err(e) = active.write_run(run_id, label, simulation.config(), time_series) {
+        eprintln!("Error writing Parquet run {}: {}", run_id, e);
+        *writer = None;
+    }
+}
@@ -0,0 +1,636 @@
+//! Samples of a run streamed to disk in chunks, and read back by time range
+//!
+//! A run aiming at millions of samples cannot keep them all in memory, and
+//! thinning them as [`crate::time_series::SimulationTimeSeries`] does gives up
+//! the resolution. [`ChunkedSampleWriter`] instead collects samples in a chunk
+//! of fixed size and appends each full chunk to a file, keeping only the most
+//! recent samples in memory for the viewer. [`SampleFile`] indexes the chunks
+//! of such a file and loads just those overlapping a requested time range, so a
+//! viewer zooming out over a long run reads a thinned range, never the whole file.
+//!
+//! The file is a magic header, the chunk size and the number of metrics as
+//! `u32`, then the chunks: each the number of samples it holds as a `u32` and the
+//! samples, the time and every metric of [`SAMPLE_METRICS`] as little-endian
+//! `f64`. Every chunk is flushed as it is written. A process killed while writing
+//! leaves at most the last chunk cut off, which [`SampleFile`] ignores and reports
+//! through [`SampleFile::truncated`].
+
+use crate::statistics::Statistics;
+use crate::time_series::{SimulationTimeSeries, TimeSeries};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// First bytes of a sample file, the last one being the format version
+const MAGIC: &[u8; 8] = b"SSQSMP\0\x01";
+
+/// Bytes before the first chunk: magic, chunk size and metric count
+const HEADER_BYTES: u64 = 16;
+
+/// Bytes of the sample count in front of every chunk
+const CHUNK_HEADER_BYTES: u64 = 4;
+
+/// Samples per chunk unless configured otherwise
+pub const DEFAULT_CHUNK_SIZE: usize = 4_096;
+
+/// Metrics of a [`Sample`] in file order, after its time
+pub const SAMPLE_METRICS: [&str; 7] = [
+    "queue_length",
+    "mean_wait_time",
+    "utilization",
+    "customers_served",
+    "customers_in_system",
+    "throughput",
+    "busy_servers",
+];
+
+/// Bytes of one sample on disk
+const SAMPLE_BYTES: u64 = 8 * (1 + SAMPLE_METRICS.len() as u64);
+
+/// The metrics of [`SAMPLE_METRICS`] at one time; counts are stored as `f64`,
+/// which is exact up to 2⁵³
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    pub values: [f64; SAMPLE_METRICS.len()],
+}
+
+impl Sample {
+    /// The metrics of `stats` at `now`
+    pub fn from_statistics(now: f64, stats: &Statistics) -> Self {
+        Self {
+            time: now,
+            values: [
+                stats.current_queue_length() as f64,
+                stats.average_wait_time(),
+                stats.instantaneous_utilization(now),
+                stats.served_customers() as f64,
+                stats.current_customers_in_system() as f64,
+                stats.throughput(now),
+                stats.busy_servers() as f64,
+            ],
+        }
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.time.to_le_bytes())?;
+        for value in self.values {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let mut read_f64 = || -> io::Result<f64> {
+            let mut bytes = [0; 8];
+            input.read_exact(&mut bytes)?;
+            Ok(f64::from_le_bytes(bytes))
+        };
+        let time = read_f64()?;
+        let mut values = [0.0; SAMPLE_METRICS.len()];
+        for value in &mut values {
+            *value = read_f64()?;
+        }
+        Ok(Self { time, values })
+    }
+}
+
+/// Put `samples` in place of the series of [`SAMPLE_METRICS`] in `time_series`,
+/// leaving the others as they are
+///
+/// The viewer shows a streamed run this way: the recent samples kept in memory,
+/// or a range read back from its file.
+pub fn replace_sampled_series(time_series: &mut SimulationTimeSeries, samples: &[Sample]) {
+    // Indices into the values in the order of SAMPLE_METRICS
+    let series = |index: usize| {
+        samples
+            .iter()
+            .map(move |sample| (sample.time, sample.values[index]))
+    };
+    time_series.queue_length =
+        TimeSeries::from_samples(series(0).map(|(t, v)| (t, v as usize)).collect());
+    time_series.mean_wait_time = TimeSeries::from_samples(series(1).collect());
+    time_series.utilization = TimeSeries::from_samples(series(2).collect());
+    time_series.customers_served =
+        TimeSeries::from_samples(series(3).map(|(t, v)| (t, v as u64)).collect());
+    time_series.customers_in_system =
+        TimeSeries::from_samples(series(4).map(|(t, v)| (t, v as usize)).collect());
+    time_series.throughput = TimeSeries::from_samples(series(5).collect());
+    time_series.busy_servers =
+        TimeSeries::from_samples(series(6).map(|(t, v)| (t, v as usize)).collect());
+}
+
+/// Fixed-interval samples appended to a file chunk by chunk as they are taken
+///
+/// Samples are taken on the grid of [`crate::time_series::CsvSampleStream`] and
+/// never coarsened. The chunk being filled and the `recent` samples kept for the
+/// viewer are all the writer holds, so memory does not grow with the run.
+/// The pending chunk is written, shorter than the others, on
+/// [`ChunkedSampleWriter::finish`] and when the writer is dropped, also while
+/// unwinding from a panic; a killed process loses at most that chunk.
+pub struct ChunkedSampleWriter<W: Write> {
+    /// `None` once finished, so the drop writes nothing more
+    out: Option<W>,
+    interval: f64,
+    next_sample_time: f64,
+    chunk: Vec<Sample>,
+    chunk_size: usize,
+    recent: VecDeque<Sample>,
+    recent_limit: usize,
+    samples: u64,
+    chunks: u64,
+}
+
+impl ChunkedSampleWriter<BufWriter<File>> {
+    /// Create the file at `path`, see [`ChunkedSampleWriter::new`]
+    pub fn create(
+        path: &Path,
+        interval: f64,
+        chunk_size: usize,
+        recent_limit: usize,
+    ) -> io::Result<Self> {
+        Self::new(
+            BufWriter::new(File::create(path)?),
+            interval,
+            chunk_size,
+            recent_limit,
+        )
+    }
+}
+
+impl<W: Write> ChunkedSampleWriter<W> {
+    /// Write the header to `out`; samples go out in chunks of `chunk_size`, and the
+    /// last `recent_limit` of them stay readable through [`ChunkedSampleWriter::recent`]
+    ///
+    /// `interval` and `chunk_size` must be positive.
+    pub fn new(
+        mut out: W,
+        interval: f64,
+        chunk_size: usize,
+        recent_limit: usize,
+    ) -> io::Result<Self> {
+        assert!(interval > 0.0, "the sample interval must be positive");
+        assert!(
+            chunk_size > 0 && u32::try_from(chunk_size).is_ok(),
+            "a chunk holds between 1 and u32::MAX samples"
+        );
+        out.write_all(MAGIC)?;
+        out.write_all(&(chunk_size as u32).to_le_bytes())?;
+        out.write_all(&(SAMPLE_METRICS.len() as u32).to_le_bytes())?;
+        Ok(Self {
+            out: Some(out),
+            interval,
+            next_sample_time: 0.0,
+            chunk: Vec::with_capacity(chunk_size),
+            chunk_size,
+            recent: VecDeque::with_capacity(recent_limit),
+            recent_limit,
+            samples: 0,
+            chunks: 0,
+        })
+    }
+
+    /// Take a sample from `stats` at `now` if one is due
+    #[inline]
+    pub fn sample_statistics(&mut self, now: f64, stats: &Statistics) -> io::Result<()> {
+        if now < self.next_sample_time {
+            return Ok(());
+        }
+        // Grid times that passed without an event get no sample, as in the CSV stream
+        self.next_sample_time = ((now / self.interval).floor() + 1.0) * self.interval;
+        self.push(Sample::from_statistics(now, stats))
+    }
+
+    /// Append `sample`, writing the chunk once it is full; times must not decrease
+    pub fn push(&mut self, sample: Sample) -> io::Result<()> {
+        if self.recent_limit > 0 {
+            if self.recent.len() == self.recent_limit {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(sample);
+        }
+        self.chunk.push(sample);
+        self.samples += 1;
+        if self.chunk.len() == self.chunk_size {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Write and flush the pending samples as a chunk, if there are any
+    fn write_chunk(&mut self) -> io::Result<()> {
+        let Some(out) = &mut self.out else {
+            return Ok(());
+        };
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        out.write_all(&(self.chunk.len() as u32).to_le_bytes())?;
+        for sample in &self.chunk {
+            sample.write_to(out)?;
+        }
+        out.flush()?;
+        self.chunk.clear();
+        self.chunks += 1;
+        Ok(())
+    }
+
+    /// The most recent samples, oldest first, whether written yet or not
+    pub fn recent(&self) -> &VecDeque<Sample> {
+        &self.recent
+    }
+
+    /// Samples taken so far
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Chunks written so far
+    pub fn chunks(&self) -> u64 {
+        self.chunks
+    }
+
+    /// Write the pending chunk and hand back the output, reporting any error the
+    /// drop would swallow
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk()?;
+        let mut out = self.out.take().expect("the output is only taken by finish");
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+impl<W: Write> Drop for ChunkedSampleWriter<W> {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here; `finish` reports them
+        let _ = self.write_chunk();
+        if let Some(out) = &mut self.out {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Where a chunk is in the file and the times it spans
+#[derive(Debug, Clone, Copy)]
+struct ChunkIndex {
+    /// Offset of its first sample
+    offset: u64,
+    samples: u64,
+    first_time: f64,
+    last_time: f64,
+}
+
+/// The cut-off last chunk of a sample file, which was ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedChunk {
+    /// Offset of the chunk's sample count
+    pub offset: u64,
+    /// Bytes of it in the file
+    pub bytes: u64,
+    /// Bytes a complete chunk would have, 0 if even its sample count is cut off
+    pub expected: u64,
+}
+
+impl fmt::Display for TruncatedChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the last chunk, at byte {}, is cut off after {} bytes",
+            self.offset, self.bytes
+        )?;
+        if self.expected > 0 {
+            write!(f, " of {}", self.expected)?;
+        }
+        write!(
+            f,
+            " and was ignored; the run that wrote it was probably killed"
+        )
+    }
+}
+
+/// A file written by [`ChunkedSampleWriter`], indexed by chunk for range queries
+///
+/// Opening reads only the sample counts and the first and last time of every
+/// chunk; samples are read when a range asks for them.
+pub struct SampleFile<R> {
+    input: R,
+    chunk_size: usize,
+    chunks: Vec<ChunkIndex>,
+    truncated: Option<TruncatedChunk>,
+}
+
+impl SampleFile<BufReader<File>> {
+    /// Index the sample file at `path`
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> SampleFile<R> {
+    /// Index the samples in `input`, ignoring a cut-off last chunk
+    pub fn from_reader(mut input: R) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let length = input.seek(SeekFrom::End(0))?;
+        input.seek(SeekFrom::Start(0))?;
+
+        let mut header = [0; HEADER_BYTES as usize];
+        input
+            .read_exact(&mut header)
+            .map_err(|_| invalid("not a sample file: the header is missing".to_string()))?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("not a sample file".to_string()));
+        }
+        let chunk_size = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let metrics = u32::from_le_bytes(header[12..16].try_into().unwrap()) as usize;
+        if metrics != SAMPLE_METRICS.len() {
+            return Err(invalid(format!(
+                "the file has {} metrics per sample, this build reads {}",
+                metrics,
+                SAMPLE_METRICS.len()
+            )));
+        }
+
+        let mut chunks = Vec::new();
+        let mut truncated = None;
+        let mut offset = HEADER_BYTES;
+        while offset < length {
+            let left = length - offset;
+            if left < CHUNK_HEADER_BYTES {
+                truncated = Some(TruncatedChunk {
+                    offset,
+                    bytes: left,
+                    expected: 0,
+                });
+                break;
+            }
+            let mut count = [0; CHUNK_HEADER_BYTES as usize];
+            input.seek(SeekFrom::Start(offset))?;
+            input.read_exact(&mut count)?;
+            let samples = u32::from_le_bytes(count) as u64;
+            if samples == 0 || samples > chunk_size as u64 {
+                return Err(invalid(format!(
+                    "the chunk at byte {} claims {} samples, at most {} fit",
+                    offset, samples, chunk_size
+                )));
+            }
+            let expected = CHUNK_HEADER_BYTES + samples * SAMPLE_BYTES;
+            if left < expected {
+                truncated = Some(TruncatedChunk {
+                    offset,
+                    bytes: left,
+                    expected,
+                });
+                break;
+            }
+
+            let first = offset + CHUNK_HEADER_BYTES;
+            let first_time = Sample::read_from(&mut input)?.time;
+            input.seek(SeekFrom::Start(first + (samples - 1) * SAMPLE_BYTES))?;
+            let last_time = Sample::read_from(&mut input)?.time;
+            chunks.push(ChunkIndex {
+                offset: first,
+                samples,
+                first_time,
+                last_time,
+            });
+            offset += expected;
+        }
+
+        Ok(Self {
+            input,
+            chunk_size,
+            chunks,
+            truncated,
+        })
+    }
+
+    /// Samples in the complete chunks
+    pub fn len(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.samples).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Complete chunks in the file
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Times of the first and the last sample, `None` without any
+    pub fn time_span(&self) -> Option<(f64, f64)> {
+        Some((
+            self.chunks.first()?.first_time,
+            self.chunks.last()?.last_time,
+        ))
+    }
+
+    /// The cut-off last chunk that was ignored, if there was one
+    pub fn truncated(&self) -> Option<TruncatedChunk> {
+        self.truncated
+    }
+
+    /// Every sample with a time in `[start, end]`
+    pub fn range(&mut self, start: f64, end: f64) -> io::Result<Vec<Sample>> {
+        self.thinned_range(start, end, usize::MAX)
+    }
+
+    /// Samples with a time in `[start, end]`, every k-th of the chunks overlapping
+    /// the range so that at most `max_samples` are returned
+    ///
+    /// Only the overlapping chunks are read. The stride is taken from their sample
+    /// counts, so a range cutting a chunk short may return fewer samples than the limit.
+    pub fn thinned_range(
+        &mut self,
+        start: f64,
+        end: f64,
+        max_samples: usize,
+    ) -> io::Result<Vec<Sample>> {
+        // Times never decrease, so the chunks are sorted by both of their times
+        let first = self.chunks.partition_point(|chunk| chunk.last_time < start);
+        let last = self.chunks.partition_point(|chunk| chunk.first_time <= end);
+        if first >= last || max_samples == 0 {
+            return Ok(Vec::new());
+        }
+        let overlapping = &self.chunks[first..last];
+        let candidates: u64 = overlapping.iter().map(|chunk| chunk.samples).sum();
+        let stride = candidates.div_ceil(max_samples as u64).max(1);
+
+        let mut samples = Vec::new();
+        let mut index = 0;
+        for chunk in overlapping {
+            self.input.seek(SeekFrom::Start(chunk.offset))?;
+            for _ in 0..chunk.samples {
+                let sample = Sample::read_from(&mut self.input)?;
+                if index % stride == 0 && sample.time >= start && sample.time <= end {
+                    samples.push(sample);
+                }
+                index += 1;
+            }
+        }
+        Ok(samples)
+    }
+}
+
+/// Write `samples` in chunks of `chunk_size`, cut the file off `cut` bytes before
+/// its end and read it back; fails unless exactly the samples of the complete
+/// chunks come back, with the last chunk reported as cut off
+///
+/// Runs in memory, as the check behind `--verify` of the writer's and the
+/// reader's handling of a file left behind by a killed run.
+pub fn check_truncated_file(samples: usize, chunk_size: usize, cut: u64) -> Result<(), String> {
+    let mut writer =
+        ChunkedSampleWriter::new(Vec::new(), 1.0, chunk_size, 0).map_err(|e| e.to_string())?;
+    for i in 0..samples {
+        let time = i as f64;
+        let sample = Sample {
+            time,
+            values: [time; SAMPLE_METRICS.len()],
+        };
+        writer.push(sample).map_err(|e| e.to_string())?;
+    }
+    let mut bytes = writer.finish().map_err(|e| e.to_string())?;
+    let last_chunk = match samples % chunk_size {
+        0 => chunk_size,
+        partial => partial,
+    };
+    if samples == 0 || cut == 0 || cut >= CHUNK_HEADER_BYTES + last_chunk as u64 * SAMPLE_BYTES {
+        return Err("the cut must fall inside the last chunk".to_string());
+    }
+    bytes.truncate(bytes.len() - cut as usize);
+
+    let mut file = SampleFile::from_reader(io::Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let complete = (samples - last_chunk) as u64;
+    if file.truncated().is_none() {
+        return Err("the cut-off chunk was not reported".to_string());
+    }
+    if file.len() != complete {
+        return Err(format!(
+            "{} samples were read back, the complete chunks hold {}",
+            file.len(),
+            complete
+        ));
+    }
+    let read = file
+        .range(f64::NEG_INFINITY, f64::INFINITY)
+        .map_err(|e| e.to_string())?;
+    match read
+        .iter()
+        .enumerate()
+        .find(|(i, sample)| sample.time != *i as f64 || sample.values[0] != *i as f64)
+    {
+        Some((i, sample)) => Err(format!(
+            "sample {} came back as time {} and value {}",
+            i, sample.time, sample.values[0]
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: f64) -> Sample {
+        Sample {
+            time,
+            values: [time * 2.0; SAMPLE_METRICS.len()],
+        }
+    }
+
+    /// The bytes of `samples` at times 0, 1, ... written in chunks of `chunk_size`
+    fn written(samples: usize, chunk_size: usize, recent: usize) -> (Vec<u8>, Vec<Sample>) {
+        let mut writer = ChunkedSampleWriter::new(Vec::new(), 1.0, chunk_size, recent).unwrap();
+        for i in 0..samples {
+            writer.push(sample(i as f64)).unwrap();
+        }
+        let tail = writer.recent().iter().copied().collect();
+        (writer.finish().unwrap(), tail)
+    }
+
+    #[test]
+    fn samples_read_back_as_written() {
+        let (bytes, tail) = written(10, 4, 3);
+        assert_eq!(tail, vec![sample(7.0), sample(8.0), sample(9.0)]);
+
+        let mut file = SampleFile::from_reader(io::Cursor::new(bytes)).unwrap();
+        assert_eq!(file.chunks(), 3);
+        assert_eq!(file.chunk_size(), 4);
+        assert_eq!(file.len(), 10);
+        assert_eq!(file.time_span(), Some((0.0, 9.0)));
+        assert_eq!(file.truncated(), None);
+        let all = file.range(f64::NEG_INFINITY, f64::INFINITY).unwrap();
+        assert_eq!(all, (0..10).map(|i| sample(i as f64)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_range_crossing_a_chunk_boundary_reads_both_chunks() {
+        let (bytes, _) = written(12, 4, 0);
+        let mut file = SampleFile::from_reader(io::Cursor::new(bytes)).unwrap();
+        let range = file.range(2.5, 5.0).unwrap();
+        assert_eq!(range, vec![sample(3.0), sample(4.0), sample(5.0)]);
+        assert!(file.range(20.0, 30.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_thinned_range_keeps_every_kth_sample() {
+        let (bytes, _) = written(12, 4, 0);
+        let mut file = SampleFile::from_reader(io::Cursor::new(bytes)).unwrap();
+        let thinned = file.thinned_range(0.0, 11.0, 4).unwrap();
+        let times: Vec<f64> = thinned.iter().map(|sample| sample.time).collect();
+        assert_eq!(times, vec![0.0, 3.0, 6.0, 9.0]);
+    }
+
+    #[test]
+    fn a_cut_off_last_chunk_is_ignored_and_reported() {
+        let (mut bytes, _) = written(10, 4, 0);
+        // The last chunk holds 2 samples; cut it in the middle of its second
+        bytes.truncate(bytes.len() - 5);
+        let mut file = SampleFile::from_reader(io::Cursor::new(bytes)).unwrap();
+        assert_eq!(file.len(), 8);
+        let truncated = file.truncated().expect("the last chunk is cut off");
+        assert_eq!(
+            truncated,
+            TruncatedChunk {
+                offset: HEADER_BYTES + 2 * (CHUNK_HEADER_BYTES + 4 * SAMPLE_BYTES),
+                bytes: CHUNK_HEADER_BYTES + 2 * SAMPLE_BYTES - 5,
+                expected: CHUNK_HEADER_BYTES + 2 * SAMPLE_BYTES,
+            }
+        );
+        let all = file.range(f64::NEG_INFINITY, f64::INFINITY).unwrap();
+        assert_eq!(all.last(), Some(&sample(7.0)));
+
+        // Cut just before its end, and inside its sample count
+        for cut in [1, CHUNK_HEADER_BYTES + 2 * SAMPLE_BYTES - 1] {
+            check_truncated_file(10, 4, cut).unwrap();
+        }
+        // Without the whole chunk the file is complete again
+        assert!(check_truncated_file(10, 4, CHUNK_HEADER_BYTES + 2 * SAMPLE_BYTES).is_err());
+    }
+
+    #[test]
+    fn a_file_without_the_header_is_rejected() {
+        let (bytes, _) = written(3, 4, 0);
+        assert!(SampleFile::from_reader(io::Cursor::new(bytes[..10].to_vec())).is_err());
+        let mut bytes = bytes;
+        bytes[0] = b'X';
+        assert!(SampleFile::from_reader(io::Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn samples_replace_only_the_series_of_the_file() {
+        let mut time_series = SimulationTimeSeries::new(1.0, 10);
+        time_series.wait_cv.sample(0.0, Some(1.0));
+        replace_sampled_series(&mut time_series, &[sample(1.0), sample(2.0)]);
+        assert_eq!(time_series.queue_length.data(), &[(1.0, 2), (2.0, 4)]);
+        assert_eq!(time_series.throughput.data(), &[(1.0, 2.0), (2.0, 4.0)]);
+        assert_eq!(time_series.wait_cv.len(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+//! The same configuration run under many seeds, to see how far a single run's numbers can move
+//!
+//! Every run is kept with its seed, so an outlier can be reproduced on its own,
+//! and each metric is summarized by its range, mean, standard deviation and a
+//! histogram across the seeds. A terminating simulation, whose independent days
+//! each run on a seed of their own, is summarized the same way. With a cost
+//! model the runs are also priced and can be ranked by their cost.
+
+use crate::config::SimulationConfig;
+use crate::cost::CostModel;
+use crate::simulation::Simulation;
+use crate::statistics::{Moments, t_quantile_975};
+use crate::verification::VerifiedMetric;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Coefficient of variation across seeds above which a metric is flagged as unreliable from one run
+pub const DEFAULT_CV_THRESHOLD: f64 = 0.05;
+
+/// Bins of the histogram of each metric across seeds
+pub const HISTOGRAM_BINS: usize = 10;
+
+/// The metrics of one run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedRun {
+    pub seed: u64,
+    /// One value per metric of the scan, in its order
+    pub values: Vec<f64>,
+    /// Cost per time unit, `None` unless the scan has a cost model
+    pub cost: Option<f64>,
+}
+
+/// How one metric varied across the seeds
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSpread {
+    pub metric: VerifiedMetric,
+    /// Runs the spread is taken over
+    pub runs: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Sample standard deviation, 0 for a single seed
+    pub std_dev: f64,
+    /// Runs per equal-width bin from `min` to `max`; all in the first if every run agrees
+    pub histogram: Vec<usize>,
+}
+
+impl MetricSpread {
+    /// Standard deviation relative to the mean, `None` for a zero mean
+    pub fn coefficient_of_variation(&self) -> Option<f64> {
+        (self.mean != 0.0).then(|| self.std_dev / self.mean.abs())
+    }
+
+    /// 95% confidence half-width of `mean`, taking the runs as independent; `None` for a single run
+    pub fn confidence_half_width(&self) -> Option<f64> {
+        (self.runs > 1).then(|| {
+            t_quantile_975((self.runs - 1) as f64) * self.std_dev / (self.runs as f64).sqrt()
+        })
+    }
+}
+
+/// Per-seed results of a scan, in the order the seeds were run
+#[derive(Debug, Clone)]
+pub struct SeedScan {
+    metrics: Vec<VerifiedMetric>,
+    cost_model: CostModel,
+    runs: Vec<SeedRun>,
+}
+
+impl SeedScan {
+    pub fn new(metrics: Vec<VerifiedMetric>) -> Self {
+        Self {
+            metrics,
+            cost_model: CostModel::default(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Price every run with `cost_model`; one without any rate leaves the runs unpriced
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    /// The metrics that describe `config`: waits and queues with a waiting room,
+    /// blocking where arrivals can be turned away
+    pub fn metrics_for(config: &SimulationConfig) -> Vec<VerifiedMetric> {
+        use VerifiedMetric::*;
+
+        if config.is_loss_system() {
+            return vec![CustomersInSystem, Utilization, Throughput, Blocking];
+        }
+        let mut metrics = vec![
+            Wait,
+            ProbabilityOfWaiting,
+            QueueLength,
+            CustomersInSystem,
+            Utilization,
+            Throughput,
+        ];
+        if config.max_queue_length.is_some() {
+            metrics.push(Blocking);
+        }
+        metrics
+    }
+
+    /// Measure the finished run of `seed`
+    pub fn record(&mut self, seed: u64, simulation: &Simulation) {
+        let values = self
+            .metrics
+            .iter()
+            .map(|metric| metric.measure(simulation))
+            .collect();
+        let cost = self
+            .cost_model
+            .rate(simulation.statistics(), simulation.now());
+        self.runs.push(SeedRun { seed, values, cost });
+    }
+
+    pub fn metrics(&self) -> &[VerifiedMetric] {
+        &self.metrics
+    }
+
+    pub fn runs(&self) -> &[SeedRun] {
+        &self.runs
+    }
+
+    /// The priced runs, cheapest first; empty without a cost model
+    pub fn ranked_by_cost(&self) -> Vec<&SeedRun> {
+        let mut ranked: Vec<&SeedRun> = self.runs.iter().filter(|run| run.cost.is_some()).collect();
+        ranked.sort_by(|a, b| a.cost.unwrap().total_cmp(&b.cost.unwrap()));
+        ranked
+    }
+
+    /// Spread of every metric across the runs, `None` before the first run
+    pub fn spreads(&self, bins: usize) -> Option<Vec<MetricSpread>> {
+        if self.runs.is_empty() {
+            return None;
+        }
+        let spreads = self
+            .metrics
+            .iter()
+            .enumerate()
+            .map(|(index, &metric)| {
+                let values: Vec<f64> = self.runs.iter().map(|run| run.values[index]).collect();
+                let mut moments = Moments::default();
+                values.iter().for_each(|&value| moments.push(value));
+                let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+                let mut histogram = vec![0; bins.max(1)];
+                let width = (max - min) / histogram.len() as f64;
+                for value in values {
+                    let bin = if width > 0.0 {
+                        ((value - min) / width) as usize
+                    } else {
+                        0
+                    };
+                    // The maximum lands on the upper edge of the last bin
+                    histogram[bin.min(bins.max(1) - 1)] += 1;
+                }
+
+                MetricSpread {
+                    metric,
+                    runs: self.runs.len(),
+                    min,
+                    max,
+                    mean: moments.mean().unwrap_or(0.0),
+                    std_dev: moments.variance().map_or(0.0, f64::sqrt),
+                    histogram,
+                }
+            })
+            .collect();
+        Some(spreads)
+    }
+
+    /// `seed,<metric>,...` with one row per run, the metrics by [`VerifiedMetric::key`],
+    /// and a last `cost_per_time_unit` column if the runs are priced
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let priced = self.cost_model.is_configured();
+        write!(out, "seed")?;
+        for metric in &self.metrics {
+            write!(out, ",{}", metric.key())?;
+        }
+        if priced {
+            write!(out, ",cost_per_time_unit")?;
+        }
+        writeln!(out)?;
+        for run in &self.runs {
+            write!(out, "{}", run.seed)?;
+            for value in &run.values {
+                write!(out, ",{}", value)?;
+            }
+            if let Some(cost) = run.cost {
+                write!(out, ",{}", cost)?;
+            }
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+}
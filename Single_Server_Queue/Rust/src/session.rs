@@ -0,0 +1,171 @@
+//! Everything the interactive viewer shows, saved to one file to be reopened without re-simulating
+//!
+//! A session is MessagePack with named fields. Every field has a default, so a
+//! file written before a field existed still loads, with that field at its
+//! default; fields a newer version added are ignored. [`SESSION_FORMAT_VERSION`]
+//! only changes when an existing field changes its meaning.
+
+use crate::annotations::{Annotation, AnnotationCategory};
+use crate::time_series::{EnsembleSeries, Normalization, SimulationTimeSeries};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Bars the histogram panels start with
+pub const DEFAULT_HISTOGRAM_BUCKETS: usize = 40;
+
+/// Metrics the overlay panel starts with
+pub const DEFAULT_OVERLAY_METRICS: [&str; 3] = ["queue_length", "utilization", "throughput"];
+
+/// Format written by [`Session::save`]; files of a later format are refused
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// The runs of a viewer together with what was marked on them and how they were being looked at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub version: u32,
+    pub runs: Vec<SessionRun>,
+    /// Observed-vs-theoretical comparison of the runs, as a markdown table
+    pub report: String,
+    pub annotations: Vec<Annotation>,
+    pub warm_up_time: Option<f64>,
+    pub ensemble: Option<EnsembleSeries>,
+    pub server_limit: Option<usize>,
+    pub steady_state_distribution: Option<Vec<f64>>,
+    /// Steady-state coefficient of variation of the wait, drawn as a reference line
+    pub theoretical_wait_cv: Option<f64>,
+    /// λ and μ of the M/M/1 queue whose wait distribution the runs follow
+    pub theoretical_wait: Option<(f64, f64)>,
+    pub throughput_window: Option<f64>,
+    pub statistics_window: Option<f64>,
+    pub view: ViewState,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            version: SESSION_FORMAT_VERSION,
+            runs: Vec::new(),
+            report: String::new(),
+            annotations: Vec::new(),
+            warm_up_time: None,
+            ensemble: None,
+            server_limit: None,
+            steady_state_distribution: None,
+            theoretical_wait_cv: None,
+            theoretical_wait: None,
+            throughput_window: None,
+            statistics_window: None,
+            view: ViewState::default(),
+        }
+    }
+}
+
+/// One labelled run and its samples
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionRun {
+    pub label: String,
+    pub time_series: SimulationTimeSeries,
+}
+
+/// The viewer's toggles and the visible range of each panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// Per panel, by the panel's plot id
+    pub panels: BTreeMap<String, PanelView>,
+    /// Annotation categories unticked in the header
+    pub hidden_annotations: Vec<AnnotationCategory>,
+    pub show_ensemble_bands: bool,
+    pub show_wait_ci: bool,
+    /// Whether the wait histogram and ECDF panels draw the M/M/1 distribution over the runs
+    pub show_wait_theory: bool,
+    pub stack_servers: bool,
+    /// Whether the throughput panel shows the windowed instead of the cumulative throughput
+    pub windowed_throughput: bool,
+    /// Whether the mean wait and utilization panels show their statistics-window values
+    pub windowed_statistics: bool,
+    /// Whether the queue length and customers in system panels show their interval averages
+    pub interval_averages: bool,
+    /// P_n snapshot shown in the distribution panel
+    pub distribution_snapshot: usize,
+    /// Customers per bar of the distribution panel
+    pub distribution_bucket_width: usize,
+    /// Bars the wait histogram is merged into, at most
+    pub wait_histogram_buckets: usize,
+    /// Bars the slowdown histogram is merged into, at most
+    pub slowdown_histogram_buckets: usize,
+    /// Names of the metrics drawn on the overlay panel, see [`SimulationTimeSeries::METRIC_NAMES`]
+    pub overlay_metrics: Vec<String>,
+    pub overlay_normalization: Normalization,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            panels: BTreeMap::new(),
+            hidden_annotations: Vec::new(),
+            show_ensemble_bands: true,
+            show_wait_ci: true,
+            show_wait_theory: true,
+            stack_servers: true,
+            windowed_throughput: false,
+            windowed_statistics: false,
+            interval_averages: false,
+            distribution_snapshot: 0,
+            distribution_bucket_width: 1,
+            wait_histogram_buckets: DEFAULT_HISTOGRAM_BUCKETS,
+            slowdown_histogram_buckets: DEFAULT_HISTOGRAM_BUCKETS,
+            overlay_metrics: DEFAULT_OVERLAY_METRICS.map(String::from).to_vec(),
+            overlay_normalization: Normalization::MinMax,
+        }
+    }
+}
+
+/// Zoom and derivative setting of one panel
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelView {
+    /// Visible `(min, max)` corners as `[x, y]`, `None` for the automatic range
+    pub bounds: Option<([f64; 2], [f64; 2])>,
+    pub derivative: bool,
+    pub derivative_stride: usize,
+}
+
+impl Default for PanelView {
+    fn default() -> Self {
+        Self {
+            bounds: None,
+            derivative: false,
+            derivative_stride: 1,
+        }
+    }
+}
+
+impl Session {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let bytes = rmp_serde::to_vec_named(self).map_err(io::Error::other)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&bytes)?;
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let session: Self = rmp_serde::from_read(BufReader::new(File::open(path)?))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if session.version > SESSION_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "session format {} is newer than the supported {}",
+                    session.version, SESSION_FORMAT_VERSION
+                ),
+            ));
+        }
+        Ok(session)
+    }
+}
@@ -0,0 +1,1290 @@
+use crate::annotations::{AnnotationCategory, Annotations};
+use crate::config::{ChangedParameter, ClosedArrivals, SimulationConfig};
+use crate::consistency::{ConsistencyMonitor, ConsistencyWarning};
+use crate::engine::SimulationEngine;
+use crate::entities::{Client, Server, ServerState, ServerStreams};
+use crate::event::{Event, EventType};
+use crate::event_log::{Anomaly, EventLog, EventLogEntry, PostMortem};
+use crate::model::{self, Model, Observer, RunOutcome, StopCondition};
+use crate::rng::Sampler;
+use crate::statistics::{Statistics, Totals};
+use crate::stream_capture::{ReplayError, StreamCapture, StreamTag};
+use crate::theory::erlang_c;
+use crate::time_series::SimulationTimeSeries;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// Clock, counts and running averages of a simulation, see [`Simulation::progress_iter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressReport {
+    pub time: f64,
+    pub event_count: u64,
+    pub customers_served: u64,
+    pub queue_length: usize,
+    pub mean_wait: f64,
+    /// Batch-means 95% interval of the mean wait, `None` until there are enough batches
+    pub mean_wait_ci: Option<(f64, f64)>,
+    pub mean_queue_length: f64,
+    pub utilization: f64,
+}
+
+/// Statistics, time series and final figures of a run, see [`Simulation::run_sampled`]
+///
+/// The clock and the counts are those of the simulation when the run ended, so
+/// they include the warm-up and any earlier runs; `statistics` and
+/// `served_customers` only cover the time after the warm-up.
+#[derive(Clone)]
+pub struct SimulationResult {
+    pub statistics: Statistics,
+    pub time_series: SimulationTimeSeries,
+    /// Simulated time when the run ended
+    pub total_time: f64,
+    /// Events processed since t = 0
+    pub event_count: u64,
+    pub served_customers: u64,
+    /// Wall-clock time the run took
+    pub elapsed: Duration,
+    /// Events of this run and why it ended
+    pub outcome: RunOutcome,
+}
+
+/// Where a run was aborted because its queue grew past
+/// [`SimulationConfig::explosion_limit`], see [`Simulation::queue_explosion`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueExplosion {
+    pub time: f64,
+    pub queue_length: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for QueueExplosion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "queue exploded — model is unstable: {} customers waiting at t = {:.4}, more than the limit of {}",
+            self.queue_length, self.time, self.limit
+        )
+    }
+}
+
+/// The stretch of a run between two scheduled parameter changes, see [`Simulation::phases`]
+///
+/// The averages only cover the observed part of the phase, after any warm-up;
+/// a phase that ended before the warm-up did has no observed time and zero averages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub start: f64,
+    pub end: f64,
+    /// Length of the phase after the warm-up
+    pub observed: f64,
+    /// The change that started the phase, e.g. "μ = 2", or "Start" for the first
+    pub label: String,
+    pub lambda: f64,
+    pub mu: f64,
+    pub servers: usize,
+    pub arrivals: u64,
+    pub served: u64,
+    /// Mean wait of the customers who started service in the phase
+    pub mean_wait: f64,
+    pub mean_queue_length: f64,
+    /// Time-average busy servers over the phase's servers
+    pub utilization: f64,
+    pub throughput: f64,
+}
+
+/// Where a phase began, with the parameters in force from there on
+#[derive(Debug, Clone)]
+struct PhaseMark {
+    start: f64,
+    label: String,
+    lambda: f64,
+    mu: f64,
+    servers: usize,
+    /// Totals when the phase began, or when the warm-up ended if that was later
+    totals: Totals,
+}
+
+/// Steps a simulation and reports on it every few events, see [`Simulation::progress_iter`]
+pub struct ProgressIter<'a> {
+    simulation: &'a mut Simulation,
+    report_every: u64,
+}
+
+impl Iterator for ProgressIter<'_> {
+    type Item = ProgressReport;
+
+    fn next(&mut self) -> Option<ProgressReport> {
+        let mut processed = 0;
+        while processed < self.report_every && self.simulation.step().is_some() {
+            processed += 1;
+        }
+        (processed > 0).then(|| self.simulation.progress_report())
+    }
+}
+
+/// Samples every event of a run into a time series, see [`Simulation::run_sampled`]
+struct SeriesSampler(SimulationTimeSeries);
+
+impl Observer<QueueModel> for SeriesSampler {
+    fn on_event(
+        &mut self,
+        model: &QueueModel,
+        engine: &SimulationEngine,
+        event: &Event,
+    ) -> ControlFlow<()> {
+        let now = engine.now();
+        self.0.record_event(
+            event.event_type,
+            now,
+            model.total_arrivals(),
+            model.total_departures(),
+        );
+        self.0.sample_statistics(now, model.statistics());
+        ControlFlow::Continue(())
+    }
+}
+
+/// Maps simulated time onto wall-clock time at a fixed speed
+pub struct Pacer {
+    sim_units_per_second: f64,
+    wall_start: Instant,
+    sim_start: f64,
+}
+
+impl Pacer {
+    /// Start pacing from simulated time `sim_start`, now
+    pub fn new(sim_units_per_second: f64, sim_start: f64) -> Self {
+        Self {
+            sim_units_per_second,
+            wall_start: Instant::now(),
+            sim_start,
+        }
+    }
+
+    /// Wall-clock time left until `sim_time` is due (zero if already late)
+    pub fn delay_until(&self, sim_time: f64) -> Duration {
+        let due = (sim_time - self.sim_start).max(0.0) / self.sim_units_per_second;
+        Duration::from_secs_f64(due).saturating_sub(self.wall_start.elapsed())
+    }
+}
+
+/// A complete single server queue: engine, entities, statistics and random streams
+///
+/// The engine holds the clock and the pending events, the [`QueueModel`] everything
+/// else, and [`Simulation::step`] hands each event from one to the other. The whole
+/// simulation is `Send`, e.g. for running replications on separate threads. Use
+/// [`Simulation::fork`] or [`Simulation::fork_with`] to branch a run.
+pub struct Simulation {
+    engine: SimulationEngine,
+    model: QueueModel,
+}
+
+/// The entities, statistics and bookkeeping of a [`Simulation`]: the queue with
+/// one or more servers as a [`Model`]
+///
+/// All entities are plain fields and events are routed to them by `handle`, so the
+/// hot path has no shared pointers or borrow checks.
+pub struct QueueModel {
+    config: SimulationConfig,
+    stats: Statistics,
+    server: Server,
+    client: Client,
+    event_count: u64,
+    /// Time of the latest event, to notice the clock going backwards
+    last_event_time: f64,
+    arrivals: u64,
+    departures: u64,
+    warm_up_pending: bool,
+    last_departure_time: f64,
+    backlog_at_cutoff: Option<usize>,
+    /// Whether arrivals are admitted under the admission schedule
+    admission_open: bool,
+    /// Openings and closings processed so far; even counts mean the next change opens
+    admission_changes: usize,
+    /// Arrivals waiting outside the closed queue for the next opening
+    deferred: usize,
+    last_closing: Option<f64>,
+    overtime: Option<f64>,
+    failures: u64,
+    queue_cap_reached: bool,
+    annotations: Annotations,
+    event_log: EventLog,
+    /// Running mean wait that counts as an anomaly, from the configured multiple
+    wait_limit: Option<f64>,
+    post_mortem: Option<PostMortem>,
+    /// Set once the queue outgrows the explosion limit; halts the run
+    explosion: Option<QueueExplosion>,
+    consistency: Option<ConsistencyMonitor>,
+    /// Scheduled parameter changes applied so far
+    changes_applied: usize,
+    /// Start of each phase, empty for a run without parameter changes
+    phase_marks: Vec<PhaseMark>,
+    /// Recording or replay of the random streams, `None` for generated draws
+    capture: Option<StreamCapture>,
+}
+
+// Simulations are moved to worker threads, so keep them `Send`
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Simulation>();
+};
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Self {
+        Self::build(config, None)
+    }
+
+    /// A simulation whose random streams are recorded into or replayed from `capture`
+    ///
+    /// Keep a clone of `capture` to save the recording after the run; a replay
+    /// that fails stops the run, see [`Simulation::replay_error`].
+    pub fn with_capture(config: SimulationConfig, capture: StreamCapture) -> Self {
+        Self::build(config, Some(capture))
+    }
+
+    fn build(config: SimulationConfig, capture: Option<StreamCapture>) -> Self {
+        let mut master = config.rng_backend.create(config.seed);
+
+        let mut stats = Statistics::with_servers(config.servers);
+        stats.set_throughput_window(config.throughput_window);
+        stats.set_statistics_window(config.statistics_window);
+        stats.set_service_level_targets(&config.service_level_targets);
+        let (client_rng, streams) = streams(master.as_mut(), capture.as_ref());
+        let mut client = Client::new(config.lambda, client_rng);
+        client.set_cutoff(config.arrival_cutoff);
+        client.set_interarrival(config.unit_interarrival_distribution());
+        let mut server = Server::new(config.unit_service_distribution(), 1.0 / config.mu, streams);
+        server.set_servers(config.servers, config.waiting_room);
+        server.set_queue_limit(config.max_queue_length);
+        server.set_trace_limit(config.customer_trace_limit);
+        server.set_breakdowns(config.breakdowns);
+        server.set_standby(config.standby);
+        server.set_setup(config.setup.map(|setup| setup.distribution()));
+        server.set_service_modulation(config.service_modulation.clone(), config.mu);
+        set_deadlines(&mut server, &config);
+
+        let mut engine = SimulationEngine::new()
+            .with_instrumentation(config.instrumentation)
+            .with_clock_audit(config.clock_audit);
+        let mut model = QueueModel {
+            warm_up_pending: config.warm_up_time > 0.0,
+            stats,
+            server,
+            client,
+            event_count: 0,
+            last_event_time: 0.0,
+            arrivals: 0,
+            departures: 0,
+            last_departure_time: 0.0,
+            backlog_at_cutoff: None,
+            admission_open: true,
+            admission_changes: 0,
+            deferred: 0,
+            last_closing: None,
+            overtime: None,
+            failures: 0,
+            queue_cap_reached: false,
+            annotations: Annotations::default(),
+            event_log: EventLog::new(config.event_log_length),
+            wait_limit: anomaly_wait_limit(&config),
+            post_mortem: None,
+            explosion: None,
+            consistency: consistency_monitor(&config),
+            changes_applied: 0,
+            phase_marks: Vec::new(),
+            capture,
+            config,
+        };
+        model.init(&mut engine);
+
+        Self { engine, model }
+    }
+
+    /// Start over with `config` from t = 0, reusing the allocated queue, trace and statistics buffers
+    ///
+    /// The random streams are derived from the seed exactly as in [`Simulation::new`],
+    /// so a reset run produces the same results as a freshly constructed one.
+    pub fn reset(&mut self, config: SimulationConfig) {
+        let mut master = config.rng_backend.create(config.seed);
+        let model = &mut self.model;
+
+        // The current count, which a scheduled change may have moved off the configured one
+        if config.servers == model.stats.servers() {
+            model.stats.reset();
+        } else {
+            model.stats = Statistics::with_servers(config.servers);
+        }
+        model.stats.set_throughput_window(config.throughput_window);
+        model.stats.set_statistics_window(config.statistics_window);
+        model
+            .stats
+            .set_service_level_targets(&config.service_level_targets);
+        model.server.reset();
+        let (client_rng, streams) = streams(master.as_mut(), None);
+        model.client.set_rng(client_rng);
+        model.server.set_streams(streams);
+        model.capture = None;
+        model
+            .server
+            .set_service_distribution(config.unit_service_distribution(), 1.0 / config.mu);
+        model
+            .server
+            .set_servers(config.servers, config.waiting_room);
+        model.server.set_queue_limit(config.max_queue_length);
+        model.server.set_trace_limit(config.customer_trace_limit);
+        model.server.set_breakdowns(config.breakdowns);
+        model.server.set_standby(config.standby);
+        model
+            .server
+            .set_setup(config.setup.map(|setup| setup.distribution()));
+        model
+            .server
+            .set_service_modulation(config.service_modulation.clone(), config.mu);
+        set_deadlines(&mut model.server, &config);
+        model.client.set_lambda(config.lambda);
+        model.client.set_cutoff(config.arrival_cutoff);
+        model
+            .client
+            .set_interarrival(config.unit_interarrival_distribution());
+
+        model.deferred = 0;
+        model.last_closing = None;
+        model.overtime = None;
+        model.warm_up_pending = config.warm_up_time > 0.0;
+        model.config = config;
+        model.event_count = 0;
+        model.last_event_time = 0.0;
+        model.arrivals = 0;
+        model.departures = 0;
+        model.last_departure_time = 0.0;
+        model.backlog_at_cutoff = None;
+        model.failures = 0;
+        model.queue_cap_reached = false;
+        model.annotations.clear();
+        if model.event_log.capacity() == model.config.event_log_length {
+            model.event_log.clear();
+        } else {
+            model.event_log = EventLog::new(model.config.event_log_length);
+        }
+        model.wait_limit = anomaly_wait_limit(&model.config);
+        model.post_mortem = None;
+        model.explosion = None;
+        model.consistency = consistency_monitor(&model.config);
+
+        if model.config.instrumentation == self.engine.is_instrumented()
+            && model.config.clock_audit == self.engine.clock_audit().is_some()
+        {
+            self.engine.reset();
+        } else {
+            self.engine = SimulationEngine::new()
+                .with_instrumentation(model.config.instrumentation)
+                .with_clock_audit(model.config.clock_audit);
+        }
+        model.init(&mut self.engine);
+    }
+
+    /// Process the next event and dispatch it to the entity that owns it
+    #[inline]
+    pub fn step(&mut self) -> Option<Event> {
+        let event = self.engine.run_step()?;
+        self.model.handle(event, &mut self.engine);
+        Some(event)
+    }
+
+    /// Run until `stop` is met, the events run out or an observer ends the run, see [`model::run`]
+    ///
+    /// [`StopCondition::Events`] counts the events of this run, and a failed
+    /// replay ends it, see [`Simulation::replay_error`].
+    pub fn run<O>(&mut self, stop: &StopCondition, observer: &mut O) -> RunOutcome
+    where
+        O: Observer<QueueModel> + ?Sized,
+    {
+        model::run(&mut self.model, &mut self.engine, stop, observer)
+    }
+
+    /// Run like [`Simulation::run`] while sampling `time_series`, and collect the
+    /// statistics, the series and the final clock, counts and wall-clock time
+    ///
+    /// The series is sampled before the observer sees each event.
+    pub fn run_sampled<O>(
+        &mut self,
+        stop: &StopCondition,
+        mut time_series: SimulationTimeSeries,
+        observer: &mut O,
+    ) -> SimulationResult
+    where
+        O: Observer<QueueModel> + ?Sized,
+    {
+        let started = Instant::now();
+        // An initial queue is there from the start, before the first event is sampled
+        time_series
+            .queue_occupancy
+            .record(self.now(), self.model.stats.current_queue_length());
+        let mut observers = (SeriesSampler(time_series), observer);
+        let outcome = self.run(stop, &mut observers);
+        let (sampler, _) = observers;
+        SimulationResult {
+            statistics: self.model.stats.clone(),
+            time_series: sampler.0,
+            total_time: self.now(),
+            event_count: self.model.event_count,
+            served_customers: self.model.stats.served_customers(),
+            elapsed: started.elapsed(),
+            outcome,
+        }
+    }
+
+    /// Process events until the next one would happen at or after `time`; returns how many
+    ///
+    /// Nothing is sampled or printed on the way, so this is the quickest way past
+    /// a long warm-up or to the moment a bug shows. Every event is still handled
+    /// by [`Simulation::step`], so statistics, warm-up reset, event log and
+    /// anomaly checks end up exactly as after a sampled run to the same time.
+    pub fn run_until(&mut self, time: f64) -> u64 {
+        let start = self.model.event_count;
+        while self.engine.has_next_event() && self.engine.peek_next_time() < time {
+            self.step();
+        }
+        self.model.event_count - start
+    }
+
+    /// Iterator that processes `report_every` events per item and yields the state after them
+    ///
+    /// The last item may cover fewer events when the event list runs dry; after it
+    /// the iterator ends. Each event is processed completely within one call to
+    /// `next`, so dropping the iterator at any point, e.g. on a stopping rule of
+    /// the caller's, leaves the simulation consistent: its statistics can be read
+    /// and it can be run further.
+    pub fn progress_iter(&mut self, report_every: u64) -> ProgressIter<'_> {
+        assert!(report_every > 0, "a report needs at least one event");
+        ProgressIter {
+            simulation: self,
+            report_every,
+        }
+    }
+
+    /// Process events up to `time` with simulated time advancing at
+    /// `sim_units_per_second`, calling `on_event` after each one
+    ///
+    /// The thread sleeps before each event until it is due, so this is meant for
+    /// demonstrations, not for collecting statistics quickly. Returning
+    /// `ControlFlow::Break` from `on_event` stops the run early.
+    pub fn run_paced<F>(&mut self, time: f64, sim_units_per_second: f64, mut on_event: F)
+    where
+        F: FnMut(&Simulation, &Event) -> ControlFlow<()>,
+    {
+        let pacer = Pacer::new(sim_units_per_second, self.now());
+        while self.engine.has_next_event() && self.engine.peek_next_time() < time {
+            std::thread::sleep(pacer.delay_until(self.engine.peek_next_time()));
+            if let Some(event) = self.step()
+                && on_event(self, &event).is_break()
+            {
+                break;
+            }
+        }
+    }
+
+    /// The current state as a [`ProgressReport`]
+    pub fn progress_report(&self) -> ProgressReport {
+        let now = self.now();
+        ProgressReport {
+            time: now,
+            event_count: self.model.event_count,
+            customers_served: self.model.stats.served_customers(),
+            queue_length: self.model.stats.current_queue_length(),
+            mean_wait: self.model.stats.average_wait_time(),
+            mean_wait_ci: self.model.stats.wait_confidence_interval(),
+            mean_queue_length: self.model.stats.average_queue_length(now),
+            utilization: self.model.stats.utilization(now),
+        }
+    }
+
+    /// One-line summary of the current state, e.g. for printing after a step
+    pub fn describe_state(&self) -> String {
+        let server = &self.model.server;
+        let served = self.model.stats.served_customers();
+        if server.servers() == 1 {
+            let state = if server.is_down() {
+                "down"
+            } else if server.is_setting_up() {
+                "setting up"
+            } else if server.is_busy() {
+                "busy"
+            } else {
+                "idle"
+            };
+            format!(
+                "queue: {}, server: {}, served: {}",
+                server.queue_length(),
+                state,
+                served
+            )
+        } else {
+            format!(
+                "queue: {}, busy servers: {}/{}{}, served: {}, lost: {}",
+                server.queue_length(),
+                server.busy_servers(),
+                server.servers(),
+                if server.is_down() {
+                    " (down)"
+                } else if server.is_setting_up() {
+                    " (setting up)"
+                } else {
+                    ""
+                },
+                served,
+                self.model.stats.lost_customers()
+            )
+        }
+    }
+
+    /// Branch the simulation with independent random streams and unchanged parameters
+    pub fn fork(&self) -> Self {
+        self.fork_with(|_| {})
+    }
+
+    /// Branch the simulation and change its parameters from this point on
+    ///
+    /// The branch is a deep copy (pending events, queue, statistics) whose random
+    /// streams are split from the original, so the two continue independently.
+    /// Events already scheduled, the services in progress among them, keep their
+    /// drawn times; customers already waiting are served at the new μ when they
+    /// start, and otherwise only later draws use the new parameters. The branch keeps the
+    /// original's schedule of parameter changes, whatever `configure` does to it.
+    pub fn fork_with<F>(&self, configure: F) -> Self
+    where
+        F: FnOnce(&mut SimulationConfig),
+    {
+        let client_rng = self.model.client.rng().clone_box().fork();
+        let server_streams = self.model.server.streams().fork();
+        let mut branch = self.deep_copy(client_rng, server_streams);
+        let (original, model) = (&self.model, &mut branch.model);
+
+        configure(&mut model.config);
+        model.config.parameter_changes = original.config.parameter_changes.clone();
+        if model.config.lambda != original.config.lambda {
+            model.client.set_lambda(model.config.lambda);
+        }
+        if model.config.arrival_cutoff != original.config.arrival_cutoff {
+            model.client.set_cutoff(model.config.arrival_cutoff);
+        }
+        if model.config.arrival_kind != original.config.arrival_kind {
+            model
+                .client
+                .set_interarrival(model.config.unit_interarrival_distribution());
+        }
+        if model.config.max_queue_length != original.config.max_queue_length {
+            model.server.set_queue_limit(model.config.max_queue_length);
+        }
+        if model.config.breakdowns != original.config.breakdowns {
+            model.server.set_breakdowns(model.config.breakdowns);
+        }
+        if model.config.standby != original.config.standby {
+            model.server.set_standby(model.config.standby);
+        }
+        if model.config.setup != original.config.setup {
+            model
+                .server
+                .set_setup(model.config.setup.map(|setup| setup.distribution()));
+        }
+        if model.config.deadlines != original.config.deadlines
+            || model.config.discipline != original.config.discipline
+        {
+            set_deadlines(&mut model.server, &model.config);
+        }
+        if model.config.mu != original.config.mu
+            || model.config.service_kind != original.config.service_kind
+            || model.config.max_service_time != original.config.max_service_time
+        {
+            model.server.set_service_distribution(
+                model.config.unit_service_distribution(),
+                1.0 / model.config.mu,
+            );
+        }
+        if model.config.mu != original.config.mu
+            || model.config.service_modulation != original.config.service_modulation
+        {
+            model
+                .server
+                .set_service_modulation(model.config.service_modulation.clone(), model.config.mu);
+        }
+
+        branch
+    }
+
+    /// Branch the simulation with copies of the current random streams
+    ///
+    /// Both runs will produce exactly the same events from here on, which is only
+    /// useful for checking reproducibility.
+    pub fn fork_identical(&self) -> Self {
+        let client_rng = self.model.client.rng().clone_box();
+        let server_streams = self.model.server.streams().clone();
+        self.deep_copy(client_rng, server_streams)
+    }
+
+    fn deep_copy(&self, client_rng: Box<dyn Sampler>, server_streams: ServerStreams) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            model: self.model.deep_copy(client_rng, server_streams),
+        }
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.model.config
+    }
+
+    pub fn engine(&self) -> &SimulationEngine {
+        &self.engine
+    }
+
+    /// Entities, statistics and bookkeeping, as the driver's observers see them
+    pub fn model(&self) -> &QueueModel {
+        &self.model
+    }
+
+    #[inline]
+    pub fn now(&self) -> f64 {
+        self.engine.now()
+    }
+
+    #[inline]
+    pub fn event_count(&self) -> u64 {
+        self.model.event_count
+    }
+
+    /// Arrivals since t = 0, including any warm-up period
+    #[inline]
+    pub fn total_arrivals(&self) -> u64 {
+        self.model.arrivals
+    }
+
+    /// Departures since t = 0, including any warm-up period
+    #[inline]
+    pub fn total_departures(&self) -> u64 {
+        self.model.departures
+    }
+
+    /// Customers in the system when arrivals stopped, once the cutoff has passed
+    pub fn backlog_at_cutoff(&self) -> Option<usize> {
+        self.model.config.arrival_cutoff?;
+        match self.model.backlog_at_cutoff {
+            Some(backlog) => Some(backlog),
+            // Emptied before the cutoff with no arrival left to process
+            None if !self.engine.has_next_event() => Some(0),
+            None => None,
+        }
+    }
+
+    /// Time from the arrival cutoff until the last customer left, once the system has drained
+    pub fn drain_time(&self) -> Option<f64> {
+        let cutoff = self.model.config.arrival_cutoff?;
+        if self.engine.has_next_event() {
+            return None;
+        }
+        Some((self.model.last_departure_time - cutoff).max(0.0))
+    }
+
+    /// Whether the admission schedule currently lets arrivals in; always without one
+    pub fn is_admitting(&self) -> bool {
+        self.model.admission_open
+    }
+
+    /// Time from the last closing of the admission schedule until the system was empty,
+    /// once that has happened
+    pub fn overtime(&self) -> Option<f64> {
+        self.model.overtime
+    }
+
+    #[inline]
+    pub fn statistics(&self) -> &Statistics {
+        &self.model.stats
+    }
+
+    pub fn server(&self) -> &Server {
+        &self.model.server
+    }
+
+    /// The station as it stands at the current simulated time
+    pub fn server_state(&self) -> ServerState {
+        self.model.server.current_state(self.now())
+    }
+
+    /// Statistics of each phase between scheduled parameter changes so far, the
+    /// last one up to now; empty for a run without parameter changes
+    pub fn phases(&self) -> Vec<Phase> {
+        let now = self.now();
+        let current = self.model.stats.totals(now);
+        self.model
+            .phase_marks
+            .iter()
+            .enumerate()
+            .map(|(i, mark)| {
+                let (end, totals) = match self.model.phase_marks.get(i + 1) {
+                    Some(next) => (next.start, next.totals),
+                    None => (now, current),
+                };
+                let from = mark.totals;
+                let observed = (totals.time - from.time).max(0.0);
+                let started = totals.started - from.started;
+                let served = totals.served - from.served;
+                let per_time = |value: f64| {
+                    if observed > 0.0 {
+                        value / observed
+                    } else {
+                        0.0
+                    }
+                };
+                Phase {
+                    start: mark.start,
+                    end,
+                    observed,
+                    label: mark.label.clone(),
+                    lambda: mark.lambda,
+                    mu: mark.mu,
+                    servers: mark.servers,
+                    arrivals: totals.arrivals - from.arrivals,
+                    served,
+                    mean_wait: if started > 0 {
+                        (totals.wait_time - from.wait_time) / started as f64
+                    } else {
+                        0.0
+                    },
+                    mean_queue_length: per_time(totals.queue_area - from.queue_area),
+                    utilization: per_time(totals.busy_area - from.busy_area) / mark.servers as f64,
+                    throughput: per_time(served as f64),
+                }
+            })
+            .collect()
+    }
+
+    /// Warm-up end, arrival cutoff, failures, repairs and threshold crossings so far,
+    /// plus whatever was added with [`Simulation::annotate`]
+    pub fn annotations(&self) -> &Annotations {
+        &self.model.annotations
+    }
+
+    /// The most recent events, e.g. to dump them on demand with [`EventLog::write_csv`]
+    pub fn event_log(&self) -> &EventLog {
+        &self.model.event_log
+    }
+
+    /// Why a replay of recorded draws stopped, if it did; the run ends at that event
+    pub fn replay_error(&self) -> Option<ReplayError> {
+        self.model.capture.as_ref().and_then(StreamCapture::error)
+    }
+
+    /// Where the queue outgrew [`SimulationConfig::explosion_limit`], which ended the run
+    pub fn queue_explosion(&self) -> Option<QueueExplosion> {
+        self.model.explosion
+    }
+
+    /// The first anomaly of the run with the events leading up to it, if one occurred
+    pub fn post_mortem(&self) -> Option<&PostMortem> {
+        self.model.post_mortem.as_ref()
+    }
+
+    /// Checks of the statistics that failed, empty unless `consistency_check` is configured
+    pub fn consistency_warnings(&self) -> &[ConsistencyWarning] {
+        self.model
+            .consistency
+            .as_ref()
+            .map_or(&[], ConsistencyMonitor::warnings)
+    }
+
+    /// Mark a moment of the run, e.g. when a metric observed between steps crosses a limit
+    ///
+    /// The annotation is drawn on every plot and exported next to the built-in ones.
+    pub fn annotate(&mut self, time: f64, label: impl Into<String>, category: AnnotationCategory) {
+        self.model.annotations.push(time, label, category);
+    }
+}
+
+impl Model for QueueModel {
+    fn init(&mut self, engine: &mut SimulationEngine) {
+        engine.schedule(Event::new(0.0, EventType::Arrival));
+        self.server
+            .preload(self.config.initial_queue, engine, &mut self.stats);
+        self.admission_open = schedule_first_admission(&self.config, engine);
+        self.admission_changes = 0;
+        self.phase_marks = schedule_first_change(&self.config, engine);
+        self.changes_applied = 0;
+    }
+
+    /// Route the event to the entity that owns it, then log it and check for anomalies
+    #[inline]
+    fn handle(&mut self, event: Event, engine: &mut SimulationEngine) {
+        // The state is constant between events, so the reset can be applied at the
+        // exact warm-up instant before the first event past it is processed.
+        if self.warm_up_pending && event.time >= self.config.warm_up_time {
+            self.stats.discard_warm_up(self.config.warm_up_time);
+            self.warm_up_pending = false;
+            self.annotations.push(
+                self.config.warm_up_time,
+                "Warm-up end",
+                AnnotationCategory::Phase,
+            );
+            for mark in &mut self.phase_marks {
+                mark.totals = Totals {
+                    time: self.config.warm_up_time,
+                    ..Default::default()
+                };
+            }
+        }
+        if let Some(cutoff) = self.config.arrival_cutoff
+            && self.backlog_at_cutoff.is_none()
+            && event.time >= cutoff
+        {
+            self.backlog_at_cutoff = Some(self.stats.current_customers_in_system());
+            self.annotations
+                .push(cutoff, "Arrivals stop", AnnotationCategory::Phase);
+        }
+
+        let previous_time = self.last_event_time;
+        self.last_event_time = event.time;
+        self.event_count += 1;
+        if let Some(capture) = &self.capture {
+            capture.begin_event(self.event_count);
+        }
+        let handler_start = engine.is_instrumented().then(Instant::now);
+
+        match event.event_type {
+            EventType::Arrival => {
+                self.arrivals += 1;
+                if self.admission_open {
+                    self.server.receive_customer(engine, &mut self.stats);
+                } else {
+                    self.turn_away(event.time);
+                }
+                self.client.handle_generate(engine);
+                if !self.queue_cap_reached
+                    && self.config.max_queue_length.is_some()
+                    && self.stats.lost_customers() > 0
+                {
+                    self.queue_cap_reached = true;
+                    self.annotations.push(
+                        event.time,
+                        "Queue cap reached",
+                        AnnotationCategory::Threshold,
+                    );
+                }
+            }
+            EventType::Departure => {
+                self.departures += 1;
+                self.last_departure_time = event.time;
+                self.server.handle_departure(engine, &mut self.stats);
+                if let Some(closing) = self.last_closing
+                    && self.overtime.is_none()
+                    && self.stats.current_customers_in_system() == 0
+                {
+                    self.overtime = Some(event.time - closing);
+                }
+            }
+            EventType::Breakdown => {
+                self.server.handle_breakdown(engine, &mut self.stats);
+                self.failures += 1;
+                self.annotations.push(
+                    event.time,
+                    format!("Failure #{}", self.failures),
+                    AnnotationCategory::Failure,
+                );
+            }
+            EventType::RepairComplete => {
+                self.server.handle_repair(engine, &mut self.stats);
+                self.annotations.push(
+                    event.time,
+                    format!("Repair #{}", self.failures),
+                    AnnotationCategory::Repair,
+                );
+            }
+            EventType::Switchover => {
+                self.server.handle_switchover(engine, &mut self.stats);
+                self.annotations
+                    .push(event.time, "Standby serving", AnnotationCategory::Repair);
+            }
+            EventType::StandbyBreakdown => {
+                self.server
+                    .handle_standby_breakdown(engine, &mut self.stats);
+                self.annotations
+                    .push(event.time, "Standby failure", AnnotationCategory::Failure);
+            }
+            EventType::StandbyRepair => {
+                self.server.handle_standby_repair(engine, &mut self.stats);
+                self.annotations
+                    .push(event.time, "Standby repair", AnnotationCategory::Repair);
+            }
+            EventType::Setup => self.server.handle_setup(engine, &mut self.stats),
+            EventType::Admission => self.handle_admission(event.time, engine),
+            EventType::ParameterChange => self.handle_parameter_change(event.time, engine),
+        }
+
+        if let Some(start) = handler_start {
+            engine.record_handler_time(event.event_type, start.elapsed());
+        }
+
+        self.event_log.record(EventLogEntry {
+            index: self.event_count,
+            time: event.time,
+            event_type: event.event_type,
+            queue_length: self.stats.current_queue_length(),
+            customers_in_system: self.stats.current_customers_in_system(),
+        });
+        if self.post_mortem.is_none()
+            && let Some(anomaly) = self.detect_anomaly(previous_time, event.time)
+        {
+            self.post_mortem = Some(PostMortem {
+                anomaly,
+                time: event.time,
+                events: self.event_log.iter().copied().collect(),
+            });
+        }
+        if let Some(limit) = self.config.explosion_limit {
+            let queue_length = self.stats.current_queue_length();
+            if queue_length > limit {
+                self.explosion = Some(QueueExplosion {
+                    time: event.time,
+                    queue_length,
+                    limit,
+                });
+            }
+        }
+        if let Some(monitor) = &mut self.consistency
+            && monitor.is_due(self.event_count)
+        {
+            for warning in monitor.check(event.time, &self.stats, self.config.mu) {
+                self.annotations.push(
+                    event.time,
+                    format!("{} broken", warning.identity),
+                    AnnotationCategory::Threshold,
+                );
+            }
+        }
+        if self.stats.has_rolling_window() {
+            self.stats.observe_window(event.time);
+        }
+    }
+
+    #[inline]
+    fn customers_served(&self) -> u64 {
+        self.stats.served_customers()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.explosion.is_some()
+            || self
+                .capture
+                .as_ref()
+                .is_some_and(|capture| capture.error().is_some())
+    }
+}
+
+impl QueueModel {
+    /// An arrival found the queue closed: defer it to the next opening if there is
+    /// one and the schedule says so, otherwise it is lost
+    fn turn_away(&mut self, time: f64) {
+        let defer = self.config.admission.as_ref().is_some_and(|schedule| {
+            schedule.closed_arrivals == ClosedArrivals::Deferred
+                && self.admission_changes / 2 < schedule.open_periods.len()
+        });
+        if defer {
+            self.deferred += 1;
+            self.stats.record_deferral();
+        } else {
+            self.stats.record_closed_loss(time);
+        }
+    }
+
+    /// Open or close the queue and schedule the next change of the admission schedule
+    fn handle_admission(&mut self, time: f64, engine: &mut SimulationEngine) {
+        let Some(schedule) = &self.config.admission else {
+            return;
+        };
+        let period = self.admission_changes / 2;
+        let opening = self.admission_changes.is_multiple_of(2);
+        let close = schedule.open_periods[period].1;
+        let next_open = schedule.open_periods.get(period + 1).map(|p| p.0);
+        self.admission_changes += 1;
+
+        if opening {
+            self.admission_open = true;
+            self.annotations
+                .push(time, "Opens", AnnotationCategory::Phase);
+            // Everyone turned back while closed arrives at once
+            for _ in 0..std::mem::take(&mut self.deferred) {
+                self.server.receive_customer(engine, &mut self.stats);
+            }
+            engine.schedule(Event::new(close, EventType::Admission));
+        } else {
+            self.admission_open = false;
+            self.annotations
+                .push(time, "Closes", AnnotationCategory::Phase);
+            match next_open {
+                Some(open) => engine.schedule(Event::new(open, EventType::Admission)),
+                None => {
+                    self.last_closing = Some(time);
+                    if self.stats.current_customers_in_system() == 0 {
+                        self.overtime = Some(0.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply the next scheduled parameter change, start a phase and schedule the change after it
+    fn handle_parameter_change(&mut self, time: f64, engine: &mut SimulationEngine) {
+        let Some(&change) = self.config.parameter_changes.get(self.changes_applied) else {
+            return;
+        };
+        self.changes_applied += 1;
+        let Some(previous) = self.phase_marks.last() else {
+            return;
+        };
+        let mut mark = PhaseMark {
+            start: time,
+            label: change.to_string(),
+            totals: self.stats.totals(time),
+            ..previous.clone()
+        };
+
+        let before = match change.parameter {
+            ChangedParameter::ArrivalRate => previous.lambda,
+            ChangedParameter::ServiceRate => previous.mu,
+            ChangedParameter::Servers => previous.servers as f64,
+        };
+        let annotation = format!("{}: {} → {}", change.parameter, before, change.value);
+
+        match change.parameter {
+            ChangedParameter::ArrivalRate => {
+                mark.lambda = change.value;
+                self.client.set_lambda(change.value);
+            }
+            ChangedParameter::ServiceRate => {
+                mark.mu = change.value;
+                self.server.set_service_distribution(
+                    self.config.unit_service_distribution_at(change.value),
+                    1.0 / change.value,
+                );
+                self.server
+                    .set_service_modulation(self.config.service_modulation.clone(), change.value);
+            }
+            ChangedParameter::Servers => {
+                mark.servers = change.value as usize;
+                self.stats.set_servers(time, mark.servers);
+                self.server
+                    .change_servers(mark.servers, engine, &mut self.stats);
+            }
+        }
+        self.annotations
+            .push(time, annotation, AnnotationCategory::Change);
+        self.phase_marks.push(mark);
+
+        if let Some(next) = self.config.parameter_changes.get(self.changes_applied) {
+            engine.schedule(Event::new(next.time, EventType::ParameterChange));
+        }
+    }
+
+    /// The first configured bound the state after an event violates, if any
+    #[inline]
+    fn detect_anomaly(&self, previous_time: f64, time: f64) -> Option<Anomaly> {
+        if time < previous_time {
+            return Some(Anomaly::TimeWentBackwards {
+                previous: previous_time,
+                time,
+            });
+        }
+        if let Some(limit) = self.wait_limit {
+            let mean_wait = self.stats.average_wait_time();
+            if mean_wait > limit {
+                return Some(Anomaly::MeanWaitTooLong { mean_wait, limit });
+            }
+        }
+        if let Some(limit) = self.config.anomaly_limits.max_queue_length {
+            let length = self.stats.current_queue_length();
+            if length > limit {
+                return Some(Anomaly::QueueTooLong { length, limit });
+            }
+        }
+        None
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn statistics(&self) -> &Statistics {
+        &self.stats
+    }
+
+    pub fn server(&self) -> &Server {
+        &self.server
+    }
+
+    #[inline]
+    pub fn event_count(&self) -> u64 {
+        self.event_count
+    }
+
+    /// Arrivals since t = 0, including any warm-up period
+    #[inline]
+    pub fn total_arrivals(&self) -> u64 {
+        self.arrivals
+    }
+
+    /// Departures since t = 0, including any warm-up period
+    #[inline]
+    pub fn total_departures(&self) -> u64 {
+        self.departures
+    }
+
+    fn deep_copy(&self, client_rng: Box<dyn Sampler>, server_streams: ServerStreams) -> Self {
+        let mut server = self.server.clone();
+        server.set_streams(server_streams);
+        let mut client = self.client.clone();
+        client.set_rng(client_rng);
+
+        Self {
+            config: self.config.clone(),
+            stats: self.stats.clone(),
+            server,
+            client,
+            event_count: self.event_count,
+            last_event_time: self.last_event_time,
+            arrivals: self.arrivals,
+            departures: self.departures,
+            warm_up_pending: self.warm_up_pending,
+            last_departure_time: self.last_departure_time,
+            backlog_at_cutoff: self.backlog_at_cutoff,
+            admission_open: self.admission_open,
+            admission_changes: self.admission_changes,
+            deferred: self.deferred,
+            last_closing: self.last_closing,
+            overtime: self.overtime,
+            failures: self.failures,
+            queue_cap_reached: self.queue_cap_reached,
+            annotations: self.annotations.clone(),
+            event_log: self.event_log.clone(),
+            wait_limit: self.wait_limit,
+            post_mortem: self.post_mortem.clone(),
+            explosion: self.explosion,
+            consistency: self.consistency.clone(),
+            changes_applied: self.changes_applied,
+            phase_marks: self.phase_marks.clone(),
+            // The copied streams no longer feed the recording or follow its events
+            capture: None,
+        }
+    }
+}
+
+/// The client's stream and the server's, forked from `master` in a fixed order and
+/// recorded or replayed through `capture` if given
+///
+/// The setup, breakdown and deadline streams are split off after the service and
+/// arrival streams, so those two come from the seed as they did before the
+/// others had streams of their own.
+fn streams(
+    master: &mut dyn Sampler,
+    capture: Option<&StreamCapture>,
+) -> (Box<dyn Sampler>, ServerStreams) {
+    let service = master.fork();
+    let client = master.fork();
+    let streams = ServerStreams {
+        service,
+        setup: master.fork(),
+        breakdown: master.fork(),
+        deadline: master.fork(),
+    };
+    let Some(capture) = capture else {
+        return (client, streams);
+    };
+    (
+        capture.wrap(StreamTag::Arrivals, client),
+        ServerStreams {
+            service: capture.wrap(StreamTag::Service, streams.service),
+            setup: capture.wrap(StreamTag::Setup, streams.setup),
+            breakdown: capture.wrap(StreamTag::Breakdown, streams.breakdown),
+            deadline: capture.wrap(StreamTag::Deadline, streams.deadline),
+        },
+    )
+}
+
+/// Hand `config`'s deadlines and queue discipline to `server`
+fn set_deadlines(server: &mut Server, config: &SimulationConfig) {
+    server.set_deadlines(
+        config.deadlines.map(|deadlines| deadlines.distribution()),
+        config
+            .deadlines
+            .is_some_and(|deadlines| deadlines.drop_late),
+    );
+    server.set_quantum(
+        config
+            .round_robin
+            .map_or(f64::INFINITY, |round_robin| round_robin.quantum),
+    );
+    server.set_discipline(config.discipline);
+}
+
+/// Schedule the first opening of `config`'s admission schedule; returns whether
+/// the queue admits arrivals until then
+fn schedule_first_admission(config: &SimulationConfig, engine: &mut SimulationEngine) -> bool {
+    match config
+        .admission
+        .as_ref()
+        .and_then(|s| s.open_periods.first())
+    {
+        Some(&(open, _)) => {
+            engine.schedule(Event::new(open, EventType::Admission));
+            false
+        }
+        None => true,
+    }
+}
+
+/// Schedule the first of `config`'s parameter changes; returns the mark of the
+/// initial phase, or no marks for a run without changes
+fn schedule_first_change(
+    config: &SimulationConfig,
+    engine: &mut SimulationEngine,
+) -> Vec<PhaseMark> {
+    let Some(first) = config.parameter_changes.first() else {
+        return Vec::new();
+    };
+    engine.schedule(Event::new(first.time, EventType::ParameterChange));
+    vec![PhaseMark {
+        start: 0.0,
+        label: "Start".to_string(),
+        lambda: config.lambda,
+        mu: config.mu,
+        servers: config.servers,
+        totals: Totals::default(),
+    }]
+}
+
+fn consistency_monitor(config: &SimulationConfig) -> Option<ConsistencyMonitor> {
+    config
+        .consistency_check
+        .map(|check| ConsistencyMonitor::new(check, config))
+}
+
+/// Running mean wait beyond which a run counts as anomalous: the configured multiple
+/// of the M/M/c mean wait, or `None` without a closed form or a stable queue
+fn anomaly_wait_limit(config: &SimulationConfig) -> Option<f64> {
+    let multiple = config.anomaly_limits.wait_multiple?;
+    if config.is_loss_system()
+        || config.max_service_time.is_some()
+        || config.breakdowns.is_some()
+        || config.setup.is_some()
+        || config.service_modulation.is_some()
+        || config.admission.is_some()
+        || !config.parameter_changes.is_empty()
+        || config
+            .deadlines
+            .is_some_and(|deadlines| deadlines.drop_late)
+    {
+        return None;
+    }
+    let offered_load = config.lambda / config.mu;
+    let waiting = erlang_c(config.servers, offered_load)?;
+    let mean_wait = waiting / (config.servers as f64 * config.mu - config.lambda);
+    Some(multiple * mean_wait)
+}
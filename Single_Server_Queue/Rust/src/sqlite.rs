@@ -0,0 +1,124 @@
+use crate::config::SimulationConfig;
+use crate::time_series::SimulationTimeSeries;
+use crate::trace::CustomerRecord;
+use rusqlite::{Connection, params};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        lambda REAL NOT NULL,
+        mu REAL NOT NULL,
+        max_service_time REAL,
+        warm_up_time REAL NOT NULL,
+        seed INTEGER,
+        total_time REAL NOT NULL,
+        events INTEGER NOT NULL,
+        served_customers INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS samples (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        time REAL NOT NULL,
+        metric TEXT NOT NULL,
+        value REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS customers (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        id INTEGER NOT NULL,
+        arrival REAL NOT NULL,
+        service_start REAL NOT NULL,
+        departure REAL NOT NULL,
+        class INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS samples_run ON samples(run_id, metric);
+    CREATE INDEX IF NOT EXISTS customers_run ON customers(run_id);
+";
+
+/// Summary values of a finished run stored in the `runs` table
+pub struct RunSummary<'a> {
+    pub config: &'a SimulationConfig,
+    pub total_time: f64,
+    pub event_count: u64,
+    pub served_customers: u64,
+}
+
+/// Open (or create) the database at `path` and make sure the schema exists
+pub fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+/// Store a run with its samples and customer trace, returning the run id
+///
+/// Without `replace` a new run row is appended. With `replace = Some(id)` any
+/// existing data for that id is deleted first and the run is stored under it.
+/// Everything happens in one transaction, so a failed export leaves no partial run.
+pub fn write_run(
+    connection: &mut Connection,
+    replace: Option<i64>,
+    summary: &RunSummary,
+    time_series: &SimulationTimeSeries,
+    customers: &[CustomerRecord],
+) -> rusqlite::Result<i64> {
+    let tx = connection.transaction()?;
+
+    if let Some(run_id) = replace {
+        tx.execute("DELETE FROM samples WHERE run_id = ?1", params![run_id])?;
+        tx.execute("DELETE FROM customers WHERE run_id = ?1", params![run_id])?;
+        tx.execute("DELETE FROM runs WHERE id = ?1", params![run_id])?;
+    }
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let config = summary.config;
+    tx.execute(
+        "INSERT INTO runs (id, created_at, lambda, mu, max_service_time, warm_up_time, seed,
+                           total_time, events, served_customers)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            replace,
+            created_at,
+            config.lambda,
+            config.mu,
+            config.max_service_time,
+            config.warm_up_time,
+            config.seed.map(|s| s as i64),
+            summary.total_time,
+            summary.event_count as i64,
+            summary.served_customers as i64,
+        ],
+    )?;
+    let run_id = tx.last_insert_rowid();
+
+    {
+        let mut insert_sample = tx
+            .prepare("INSERT INTO samples (run_id, time, metric, value) VALUES (?1, ?2, ?3, ?4)")?;
+        for (metric, samples) in time_series.metrics() {
+            for (time, value) in samples {
+                insert_sample.execute(params![run_id, time, metric, value])?;
+            }
+        }
+
+        let mut insert_customer = tx.prepare(
+            "INSERT INTO customers (run_id, id, arrival, service_start, departure, class)
+             VALUES (?1, ?2, ?3, ?4, ?5, NULL)",
+        )?;
+        for customer in customers {
+            insert_customer.execute(params![
+                run_id,
+                customer.id as i64,
+                customer.arrival,
+                customer.service_start,
+                customer.departure,
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(run_id)
+}
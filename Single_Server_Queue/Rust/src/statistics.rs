@@ -1,3 +1,394 @@
+use crate::ring_buffer::RingBuffer;
+use crate::rolling_window::{RollingWindow, WindowCounters, WindowState, WindowedStats};
+use crate::time_series::Histogram;
+
+/// Online lag-1 autocorrelation of a sequence, from running sums of values and products
+#[derive(Clone, Default)]
+pub struct LagOneAutocorrelation {
+    count: u64,
+    sum: f64,
+    sum_sq: f64,
+    sum_lag_product: f64,
+    first: f64,
+    last: f64,
+}
+
+impl LagOneAutocorrelation {
+    #[inline]
+    pub fn push(&mut self, value: f64) {
+        if self.count == 0 {
+            self.first = value;
+        } else {
+            self.sum_lag_product += self.last * value;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+        self.last = value;
+    }
+
+    /// Estimate of corr(x_i, x_{i-1}), or `None` with fewer than three values or zero variance
+    ///
+    /// Uses the overall mean m: Σ(x_i - m)(x_{i-1} - m) / Σ(x_i - m)², with the cross
+    /// term expanded so only the first and last values need to be remembered.
+    pub fn value(&self) -> Option<f64> {
+        if self.count < 3 {
+            return None;
+        }
+
+        let n = self.count as f64;
+        let mean = self.sum / n;
+        let denominator = self.sum_sq - n * mean * mean;
+        if denominator <= 0.0 {
+            return None;
+        }
+
+        let numerator = self.sum_lag_product
+            - mean * ((self.sum - self.first) + (self.sum - self.last))
+            + (n - 1.0) * mean * mean;
+        Some(numerator / denominator)
+    }
+}
+
+/// Online Pearson correlation of pairs, from running means and co-moments (Welford's update)
+#[derive(Clone, Default)]
+pub struct Correlation {
+    count: u64,
+    mean_x: f64,
+    mean_y: f64,
+    sum_sq_x: f64,
+    sum_sq_y: f64,
+    sum_products: f64,
+}
+
+impl Correlation {
+    #[inline]
+    pub fn push(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        let n = self.count as f64;
+        let delta_x = x - self.mean_x;
+        let delta_y = y - self.mean_y;
+        self.mean_x += delta_x / n;
+        self.mean_y += delta_y / n;
+        self.sum_sq_x += delta_x * (x - self.mean_x);
+        self.sum_sq_y += delta_y * (y - self.mean_y);
+        self.sum_products += delta_x * (y - self.mean_y);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimate of corr(x, y), or `None` with fewer than three pairs or a constant x or y
+    pub fn value(&self) -> Option<f64> {
+        if self.count < 3 || self.sum_sq_x <= 0.0 || self.sum_sq_y <= 0.0 {
+            return None;
+        }
+        Some(self.sum_products / (self.sum_sq_x * self.sum_sq_y).sqrt())
+    }
+}
+
+/// Online mean and variance of a sequence (Welford's update), for the two-moment approximations
+#[derive(Clone, Default)]
+pub struct Moments {
+    count: u64,
+    mean: f64,
+    sum_sq_deviations: f64,
+}
+
+impl Moments {
+    #[inline]
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.sum_sq_deviations += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of the values, `None` before the first
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Sample variance, `None` with fewer than two values
+    pub fn variance(&self) -> Option<f64> {
+        (self.count >= 2).then(|| self.sum_sq_deviations / (self.count - 1) as f64)
+    }
+
+    /// Squared coefficient of variation, sample variance / mean²
+    ///
+    /// 1 for exponential values, 0 for constants. `None` with fewer than two
+    /// values or a zero mean.
+    pub fn scv(&self) -> Option<f64> {
+        if self.count < 2 || self.mean == 0.0 {
+            return None;
+        }
+        let variance = self.sum_sq_deviations / (self.count - 1) as f64;
+        Some(variance / (self.mean * self.mean))
+    }
+}
+
+/// Time units covered by the windowed throughput unless configured otherwise
+pub const DEFAULT_THROUGHPUT_WINDOW: f64 = 1_000.0;
+
+/// Customers who had to wait before [`Statistics::wait_cv`] reports a value; with
+/// fewer, the mean wait is close to zero and the ratio says nothing
+const WAIT_CV_MIN_DELAYED: u64 = 10;
+
+/// Waits up to this long count as none when customers are split at service start,
+/// see [`Statistics::delay_probability`]
+///
+/// A customer served on arrival has a wait of exactly 0, the difference of two
+/// equal clock readings, so the tolerance only guards against rounding in how a
+/// wait comes about. It is far below any meaningful wait and below the finest bin
+/// of the wait histogram, so the immediate starts all sit in its first bin.
+pub const ZERO_WAIT_TOLERANCE: f64 = 1e-9;
+
+/// Numbers of customers in system the time-per-state buffer has room for before it
+/// grows, more than a stable queue reaches in practice
+const INITIAL_STATES: usize = 256;
+
+/// Departures counted in consecutive windows of equal length
+///
+/// A counter for the window in progress rolls over at each window boundary and
+/// feeds the finished count into a Welford accumulator, so memory stays constant.
+/// Windows without a departure count as 0.
+#[derive(Clone)]
+struct WindowCounts {
+    width: f64,
+    /// End of the window in progress
+    window_end: f64,
+    current: u64,
+    counts: Moments,
+}
+
+impl WindowCounts {
+    fn new(start: f64, width: f64) -> Self {
+        Self {
+            width,
+            window_end: start + width,
+            current: 0,
+            counts: Moments::default(),
+        }
+    }
+
+    #[inline]
+    fn record(&mut self, time: f64) {
+        while time >= self.window_end {
+            self.close_window();
+        }
+        self.current += 1;
+    }
+
+    fn close_window(&mut self) {
+        self.counts.push(self.current as f64);
+        self.current = 0;
+        self.window_end += self.width;
+    }
+
+    /// Counts of the windows complete at `time`, including those that ended since the last departure
+    fn completed_at(&self, time: f64) -> Moments {
+        let mut windows = self.clone();
+        while time >= windows.window_end {
+            windows.close_window();
+        }
+        windows.counts
+    }
+}
+
+/// Mean and variance of the departures per window, see [`Statistics::departure_windows`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepartureWindows {
+    /// Length of each window in time units
+    pub width: f64,
+    /// Complete windows the moments are taken over
+    pub windows: u64,
+    pub mean: f64,
+    /// Sample variance of the per-window counts
+    pub variance: f64,
+}
+
+impl DepartureWindows {
+    /// Variance over mean of the counts: 1 for a Poisson output process, above 1
+    /// for bursty and below 1 for smoothed departures; `None` without departures
+    pub fn index_of_dispersion(&self) -> Option<f64> {
+        (self.mean > 0.0).then(|| self.variance / self.mean)
+    }
+}
+
+/// Batches kept at least this many; reaching twice as many merges neighbouring pairs
+const MIN_BATCHES: usize = 20;
+
+/// Non-overlapping batch means of a sequence whose length is not known in advance
+///
+/// Starts with batches of one value and doubles the batch size by merging
+/// neighbours whenever `2 * MIN_BATCHES` batches are complete, so there are always
+/// between `MIN_BATCHES` and `2 * MIN_BATCHES` batches of equal size once enough
+/// values have been seen. Larger batches make the batch means closer to independent.
+#[derive(Clone)]
+pub struct BatchMeans {
+    batch_size: u64,
+    /// `(sum, count)` of each complete batch
+    batches: Vec<(f64, u64)>,
+    current_sum: f64,
+    current_count: u64,
+}
+
+impl BatchMeans {
+    pub fn new() -> Self {
+        Self {
+            batch_size: 1,
+            batches: Vec::with_capacity(2 * MIN_BATCHES),
+            current_sum: 0.0,
+            current_count: 0,
+        }
+    }
+
+    /// Forget all values, keeping the batch buffer
+    pub fn reset(&mut self) {
+        self.batch_size = 1;
+        self.batches.clear();
+        self.current_sum = 0.0;
+        self.current_count = 0;
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: f64) {
+        self.current_sum += value;
+        self.current_count += 1;
+        if self.current_count < self.batch_size {
+            return;
+        }
+
+        self.batches.push((self.current_sum, self.current_count));
+        self.current_sum = 0.0;
+        self.current_count = 0;
+        if self.batches.len() == 2 * MIN_BATCHES {
+            self.merge_pairs();
+        }
+    }
+
+    /// Combine neighbouring batches in place, so the buffer is never reallocated
+    fn merge_pairs(&mut self) {
+        let merged = self.batches.len().div_ceil(2);
+        for i in 0..merged {
+            let pair = &self.batches[2 * i..(2 * i + 2).min(self.batches.len())];
+            self.batches[i] = pair
+                .iter()
+                .fold((0.0, 0), |(s, c), (ps, pc)| (s + ps, c + pc));
+        }
+        self.batches.truncate(merged);
+        self.batch_size *= 2;
+    }
+
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Grand mean and 95% confidence half-width from the complete batches
+    ///
+    /// Treats the batch means as independent normal samples (Student t with
+    /// k - 1 degrees of freedom); `None` with fewer than `MIN_BATCHES` batches.
+    pub fn confidence_interval(&self) -> Option<(f64, f64)> {
+        let k = self.batches.len();
+        if k < MIN_BATCHES {
+            return None;
+        }
+
+        let means: Vec<f64> = self.batches.iter().map(|(s, c)| s / *c as f64).collect();
+        let mean = means.iter().sum::<f64>() / k as f64;
+        let variance = means.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (k - 1) as f64;
+        let half_width = t_quantile_975((k - 1) as f64) * (variance / k as f64).sqrt();
+        Some((mean, half_width))
+    }
+
+    /// Delete-one-batch jackknife of the overall mean: bias-corrected estimate and standard error
+    ///
+    /// Unlike [`BatchMeans::confidence_interval`] this also uses the incomplete last
+    /// batch. The estimate is the ratio Σ values / Σ counts, so batches of unequal
+    /// size are weighted correctly; with equal sizes both methods agree exactly.
+    pub fn jackknife(&self) -> Option<(f64, f64)> {
+        let mut batches = self.batches.clone();
+        if self.current_count > 0 {
+            batches.push((self.current_sum, self.current_count));
+        }
+        let k = batches.len();
+        if k < MIN_BATCHES {
+            return None;
+        }
+
+        let total_sum: f64 = batches.iter().map(|(s, _)| s).sum();
+        let total_count: u64 = batches.iter().map(|(_, c)| c).sum();
+        let full = total_sum / total_count as f64;
+
+        let leave_one_out: Vec<f64> = batches
+            .iter()
+            .map(|(s, c)| (total_sum - s) / (total_count - c) as f64)
+            .collect();
+        let k = k as f64;
+        let loo_mean = leave_one_out.iter().sum::<f64>() / k;
+
+        let estimate = k * full - (k - 1.0) * loo_mean;
+        let variance = (k - 1.0) / k
+            * leave_one_out
+                .iter()
+                .map(|v| (v - loo_mean).powi(2))
+                .sum::<f64>();
+        Some((estimate, variance.sqrt()))
+    }
+}
+
+impl Default for BatchMeans {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 97.5% quantile of Student's t distribution with `df` degrees of freedom
+///
+/// Cornish-Fisher expansion around the normal quantile; accurate to about 1e-3
+/// from 10 degrees of freedom on, which covers the batch counts used here.
+pub fn t_quantile_975(df: f64) -> f64 {
+    let z: f64 = 1.959_963_984_540_054;
+    z + (z.powi(3) + z) / (4.0 * df)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * df * df)
+}
+
+/// Jain's fairness index (Σx)² / (n Σx²) of per-class means, e.g. the mean sojourn
+/// or median slowdown of each customer class
+///
+/// 1 when every class fares alike, down to 1/n when one class bears all of it.
+/// `None` for fewer than two classes, where fairness between them means nothing,
+/// or when every value is 0.
+pub fn jain_index(class_means: &[f64]) -> Option<f64> {
+    let sum: f64 = class_means.iter().sum();
+    let squares: f64 = class_means.iter().map(|x| x * x).sum();
+    (class_means.len() > 1 && squares > 0.0)
+        .then(|| sum * sum / (class_means.len() as f64 * squares))
+}
+
+/// Running totals at one instant, to be differenced over an interval such as a
+/// phase of the run, see [`Statistics::totals`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub time: f64,
+    pub arrivals: u64,
+    /// Customers who started service
+    pub started: u64,
+    pub served: u64,
+    /// Waits of the customers who started service
+    pub wait_time: f64,
+    /// Integral of the queue length over time
+    pub queue_area: f64,
+    /// Integral of the number of customers in service over time
+    pub busy_area: f64,
+}
+
+#[derive(Clone)]
 pub struct Statistics {
     /// Sum of all customer wait times
     total_wait_time: f64,
@@ -5,6 +396,15 @@ pub struct Statistics {
     /// Number of customers who have been served
     served_customers: u64,
 
+    /// Number of customers who have arrived at the server
+    arrived_customers: u64,
+
+    /// Arrivals that joined the system, whether or not they had to wait
+    admitted_customers: u64,
+
+    /// Admitted arrivals that could not go straight into service
+    delayed_customers: u64,
+
     /// Total time the server has been busy
     total_busy_time: f64,
 
@@ -17,29 +417,325 @@ pub struct Statistics {
     /// Last recorded queue length
     last_queue_length: usize,
 
-    /// Current server busy state (0 or 1)
-    server_busy: bool,
+    /// Number of servers currently serving a customer
+    busy_servers: usize,
+
+    /// Number of parallel servers, used to turn busy time into a per-server utilization
+    servers: usize,
+
+    /// Busy time of each server, the parts of `total_busy_time`
+    server_busy_time: Vec<f64>,
+
+    /// When `servers` last changed mid-run, `None` if it never did
+    servers_since: Option<f64>,
+
+    /// Server time on offer in the observed time before `servers_since`
+    capacity_before: f64,
+
+    /// Number of arrivals turned away because every server was busy (loss systems only)
+    lost_customers: u64,
+
+    /// Arrivals turned away because the queue was closed
+    closed_losses: u64,
+
+    /// Arrivals while the queue was closed that came back at the next opening
+    deferred_customers: u64,
+
+    /// Failures of the station, counted when they happen
+    breakdowns: u64,
+
+    /// Completed repair time; customers in service during it are not counted as busy
+    total_down_time: f64,
+
+    /// Start of the repair in progress, if the station is down
+    down_since: Option<f64>,
+
+    /// Times the standby server started taking over from the failed primary
+    failovers: u64,
+
+    /// Customers in service when a failover started, whose service it interrupted
+    failover_interruptions: u64,
+
+    /// Failures of the standby server while it served
+    standby_breakdowns: u64,
+
+    /// Completed time the standby server spent serving in place of the primary
+    total_standby_time: f64,
+
+    /// Since when the standby server serves, if it does
+    standby_since: Option<f64>,
+
+    /// Completed time with both the primary and the standby server down
+    total_outage_time: f64,
+
+    /// Start of the outage in progress, if both servers are down
+    outage_since: Option<f64>,
+
+    /// Completed setups of the station
+    setups: u64,
+
+    /// Completed setup time
+    total_setup_time: f64,
+
+    /// Start of the setup in progress, if the station is switching on
+    setup_since: Option<f64>,
+
+    /// Part of the waits spent waiting for a setup to complete
+    setup_wait_time: f64,
+
+    /// Served customers that had a deadline
+    deadline_customers: u64,
+
+    /// Of those, the ones that left after their deadline
+    late_customers: u64,
+
+    /// Sum of the time the late customers left after their deadline
+    total_tardiness: f64,
+
+    /// Longest time a customer left after its deadline
+    max_tardiness: f64,
+
+    /// Customers dropped because their deadline passed while they waited
+    late_drops: u64,
 
     /// Area under the customers-in-system curve
     area_under_customers: f64,
 
     /// Last recorded customers in system
     last_customers_in_system: usize,
+
+    /// Time spent with exactly n customers in the system, indexed by n
+    time_in_state: Vec<f64>,
+
+    /// Recent departure times, covering at least the last `throughput_window` time units
+    recent_departures: RingBuffer<f64>,
+
+    /// Length of the sliding window for the windowed throughput
+    throughput_window: f64,
+
+    /// Departures per consecutive window of `throughput_window` time units
+    departure_windows: WindowCounts,
+
+    /// The main metrics over the most recent time units, if asked for
+    rolling_window: Option<Box<RollingWindow>>,
+
+    /// Lag-1 autocorrelation of consecutive service durations
+    service_autocorrelation: LagOneAutocorrelation,
+
+    /// Wait of each customer against the service time it got, from its service start
+    wait_service_correlation: Correlation,
+
+    /// Batch means of the waits, in order of service start
+    wait_batches: BatchMeans,
+
+    /// Time from which accumulators count (end of warm-up, 0 without one)
+    observation_start: f64,
+
+    /// Wait targets, each with the number of customers whose wait was at most the target
+    service_level_counts: Vec<(f64, u64)>,
+
+    /// Customers who started service; the denominator of the service levels
+    started_services: u64,
+
+    /// Customers who started service with a wait within [`ZERO_WAIT_TOLERANCE`]
+    immediate_services: u64,
+
+    /// Time of the latest arrival, the start of the next interarrival time
+    last_arrival_time: Option<f64>,
+
+    /// Times between consecutive arrivals, including those turned away
+    interarrival_times: Moments,
+
+    /// Service durations, without repair time
+    service_times: Moments,
+
+    /// Waits of the customers who started service
+    wait_times: Moments,
+
+    /// Waits at a fine resolution, for the histogram panel
+    wait_histogram: Histogram,
+
+    /// Sum of the times departed customers spent in the system
+    total_sojourn_time: f64,
+
+    /// Sum of each departed customer's sojourn divided by its service
+    total_slowdown: f64,
+
+    max_slowdown: f64,
+
+    /// Base-10 logarithms of the slowdowns at a fine resolution, for their quantiles
+    /// and the histogram panel; the slowdowns themselves span too many orders of
+    /// magnitude for equal bins, which would put nearly all of them in the first
+    slowdown_histogram: Histogram,
+
+    /// Sums of sojourn × service and service², for the sojourn per unit of service
+    sojourn_service_product: f64,
+    service_squares: f64,
+
+    /// Services cut off at the end of a round-robin quantum
+    preemptions: u64,
 }
 
 impl Statistics {
     pub fn new() -> Self {
+        Self::with_servers(1)
+    }
+
+    pub fn with_servers(servers: usize) -> Self {
         Self {
             total_wait_time: 0.0,
             served_customers: 0,
+            arrived_customers: 0,
+            admitted_customers: 0,
+            delayed_customers: 0,
             total_busy_time: 0.0,
             last_event_time: 0.0,
             area_under_q: 0.0,
             last_queue_length: 0,
-            server_busy: false,
+            busy_servers: 0,
+            servers,
+            server_busy_time: vec![0.0; servers],
+            servers_since: None,
+            capacity_before: 0.0,
+            lost_customers: 0,
+            closed_losses: 0,
+            deferred_customers: 0,
+            breakdowns: 0,
+            total_down_time: 0.0,
+            down_since: None,
+            failovers: 0,
+            failover_interruptions: 0,
+            standby_breakdowns: 0,
+            total_standby_time: 0.0,
+            standby_since: None,
+            total_outage_time: 0.0,
+            outage_since: None,
+            setups: 0,
+            total_setup_time: 0.0,
+            setup_since: None,
+            setup_wait_time: 0.0,
+            deadline_customers: 0,
+            late_customers: 0,
+            total_tardiness: 0.0,
+            max_tardiness: 0.0,
+            late_drops: 0,
             area_under_customers: 0.0,
             last_customers_in_system: 0,
+            time_in_state: Vec::with_capacity(INITIAL_STATES),
+            recent_departures: RingBuffer::new(),
+            throughput_window: DEFAULT_THROUGHPUT_WINDOW,
+            departure_windows: WindowCounts::new(0.0, DEFAULT_THROUGHPUT_WINDOW),
+            rolling_window: None,
+            service_autocorrelation: LagOneAutocorrelation::default(),
+            wait_service_correlation: Correlation::default(),
+            wait_batches: BatchMeans::new(),
+            observation_start: 0.0,
+            service_level_counts: Vec::new(),
+            started_services: 0,
+            immediate_services: 0,
+            last_arrival_time: None,
+            interarrival_times: Moments::default(),
+            service_times: Moments::default(),
+            wait_times: Moments::default(),
+            wait_histogram: Histogram::default(),
+            total_sojourn_time: 0.0,
+            total_slowdown: 0.0,
+            max_slowdown: 0.0,
+            slowdown_histogram: Histogram::default(),
+            sojourn_service_product: 0.0,
+            service_squares: 0.0,
+            preemptions: 0,
+        }
+    }
+
+    /// Back to the empty state at t = 0 for the same number of servers, keeping allocated buffers
+    pub fn reset(&mut self) {
+        self.discard_warm_up(0.0);
+        self.server_busy_time.truncate(self.servers);
+        self.servers_since = None;
+        self.last_queue_length = 0;
+        self.busy_servers = 0;
+        self.last_customers_in_system = 0;
+        self.down_since = None;
+        self.standby_since = None;
+        self.outage_since = None;
+        self.setup_since = None;
+        self.last_arrival_time = None;
+    }
+
+    /// Drop everything accumulated before `time` while keeping the current system state
+    ///
+    /// Called at the end of the warm-up period so averages only cover steady state.
+    /// Queue length, server state and customers in system carry over; a service in
+    /// progress only contributes its post-warm-up part to the busy time.
+    pub fn discard_warm_up(&mut self, time: f64) {
+        self.total_wait_time = 0.0;
+        self.served_customers = 0;
+        self.arrived_customers = 0;
+        self.admitted_customers = 0;
+        self.delayed_customers = 0;
+        self.lost_customers = 0;
+        self.closed_losses = 0;
+        self.deferred_customers = 0;
+        self.breakdowns = 0;
+        self.total_down_time = 0.0;
+        self.failovers = 0;
+        self.failover_interruptions = 0;
+        self.standby_breakdowns = 0;
+        self.total_standby_time = 0.0;
+        self.total_outage_time = 0.0;
+        self.setups = 0;
+        self.total_setup_time = 0.0;
+        self.setup_wait_time = 0.0;
+        self.deadline_customers = 0;
+        self.late_customers = 0;
+        self.total_tardiness = 0.0;
+        self.max_tardiness = 0.0;
+        self.late_drops = 0;
+        self.total_busy_time = 0.0;
+        self.server_busy_time.fill(0.0);
+        self.capacity_before = 0.0;
+        self.area_under_q = 0.0;
+        self.area_under_customers = 0.0;
+        self.time_in_state.clear();
+        self.recent_departures.clear();
+        self.departure_windows = WindowCounts::new(time, self.throughput_window);
+        let state = self.window_state();
+        if let Some(window) = &mut self.rolling_window {
+            window.restart(time, state);
         }
+        self.service_autocorrelation = LagOneAutocorrelation::default();
+        self.wait_service_correlation = Correlation::default();
+        self.wait_batches.reset();
+        for (_, count) in &mut self.service_level_counts {
+            *count = 0;
+        }
+        self.started_services = 0;
+        self.immediate_services = 0;
+        self.interarrival_times = Moments::default();
+        self.service_times = Moments::default();
+        self.wait_times = Moments::default();
+        self.wait_histogram.clear();
+        self.total_sojourn_time = 0.0;
+        self.total_slowdown = 0.0;
+        self.max_slowdown = 0.0;
+        self.slowdown_histogram.clear();
+        self.sojourn_service_product = 0.0;
+        self.service_squares = 0.0;
+        self.preemptions = 0;
+        self.last_event_time = time;
+        self.observation_start = time;
+    }
+
+    /// Time the observation window began, the end of the warm-up or 0 without one
+    pub fn observation_start(&self) -> f64 {
+        self.observation_start
+    }
+
+    /// Length of the observation window ending at `total_time`, excluding warm-up
+    #[inline]
+    pub fn observed_time(&self, total_time: f64) -> f64 {
+        (total_time - self.observation_start).max(0.0)
     }
 
     /// Record a change in queue length
@@ -49,46 +745,238 @@ impl Statistics {
     /// the time-weighted average queue length.
     #[inline]
     pub fn record_queue_change(&mut self, time: f64, queue_length: usize) {
-        let time_delta = time - self.last_event_time;
-        self.area_under_q += self.last_queue_length as f64 * time_delta;
-        self.area_under_customers += self.last_customers_in_system as f64 * time_delta;
+        self.accumulate_until(time);
 
         self.last_event_time = time;
         self.last_queue_length = queue_length;
 
-        // Update customers in system (queue + server if busy)
-        self.last_customers_in_system = queue_length + if self.server_busy { 1 } else { 0 };
+        // Update customers in system (queue + customers in service)
+        self.last_customers_in_system = queue_length + self.busy_servers;
     }
 
     #[inline]
-    pub fn record_service_start(&mut self, time: f64, wait_time: f64) {
-        // Update areas before changing state
+    pub fn record_arrival(&mut self, time: f64) {
+        self.arrived_customers += 1;
+        if let Some(previous) = self.last_arrival_time.replace(time) {
+            self.interarrival_times.push(time - previous);
+        }
+    }
+
+    /// An arrival joined the system; `delayed` if it found no server ready to serve it
+    #[inline]
+    pub fn record_admission(&mut self, delayed: bool) {
+        self.admitted_customers += 1;
+        self.delayed_customers += u64::from(delayed);
+    }
+
+    /// Add the time since the last change, spent in the current state, to the time-weighted accumulators
+    #[inline]
+    fn accumulate_until(&mut self, time: f64) {
         let time_delta = time - self.last_event_time;
         self.area_under_q += self.last_queue_length as f64 * time_delta;
         self.area_under_customers += self.last_customers_in_system as f64 * time_delta;
 
+        let n = self.last_customers_in_system;
+        if n >= self.time_in_state.len() {
+            self.time_in_state.resize(n + 1, 0.0);
+        }
+        self.time_in_state[n] += time_delta;
+    }
+
+    /// Remember a departure for the windowed throughput
+    ///
+    /// Departures that fell out of the window are only dropped once the buffer is
+    /// full, which keeps the common case to a single store; the buffer only grows
+    /// when a full window's worth of departures does not fit.
+    #[inline]
+    fn record_departure_time(&mut self, time: f64) {
+        if self.recent_departures.len() == self.recent_departures.capacity() {
+            let horizon = time - self.throughput_window;
+            while self
+                .recent_departures
+                .front()
+                .is_some_and(|departure| departure <= horizon)
+            {
+                self.recent_departures.pop_front();
+            }
+        }
+        self.recent_departures.push_back(time);
+    }
+
+    /// Count an arrival that found every server busy and left without service
+    #[inline]
+    pub fn record_loss(&mut self) {
+        self.lost_customers += 1;
+    }
+
+    /// Count an arrival that left because the queue was closed; it also counts as an arrival
+    pub fn record_closed_loss(&mut self, time: f64) {
+        self.record_arrival(time);
+        self.closed_losses += 1;
+    }
+
+    /// Count an arrival put off until the queue opens; it is recorded as an arrival then
+    pub fn record_deferral(&mut self) {
+        self.deferred_customers += 1;
+    }
+
+    /// The station failed at `time`; customers in service stay in the system but are not served
+    pub fn record_breakdown(&mut self, time: f64) {
+        self.breakdowns += 1;
+        self.down_since = Some(time);
+    }
+
+    /// The repair that started with the last breakdown finished at `time`
+    pub fn record_repair(&mut self, time: f64) {
+        if let Some(since) = self.down_since.take() {
+            self.total_down_time += time - since.max(self.observation_start);
+        }
+    }
+
+    /// The standby server started taking over from the primary, interrupting the
+    /// services of `interrupted` customers
+    pub fn record_failover(&mut self, interrupted: usize) {
+        self.failovers += 1;
+        self.failover_interruptions += interrupted as u64;
+    }
+
+    /// The standby server started (`true`) or stopped serving at `time`
+    pub fn record_standby_serving(&mut self, time: f64, serving: bool) {
+        if serving {
+            self.standby_since = Some(time);
+        } else if let Some(since) = self.standby_since.take() {
+            self.total_standby_time += time - since.max(self.observation_start);
+        }
+    }
+
+    /// The standby server failed at `time` while serving; the station is down as after
+    /// [`Statistics::record_breakdown`] and stays so until the next [`Statistics::record_repair`]
+    pub fn record_standby_breakdown(&mut self, time: f64) {
+        self.standby_breakdowns += 1;
+        self.record_standby_serving(time, false);
+        self.down_since = Some(time);
+    }
+
+    /// Both servers went down (`true`) at `time`, or one of them came back up
+    pub fn record_outage(&mut self, time: f64, out: bool) {
+        if out {
+            self.outage_since = Some(time);
+        } else if let Some(since) = self.outage_since.take() {
+            self.total_outage_time += time - since.max(self.observation_start);
+        }
+    }
+
+    /// The station started switching on at `time`
+    pub fn record_setup_start(&mut self, time: f64) {
+        self.setup_since = Some(time);
+    }
+
+    /// The setup in progress finished at `time`; the customers queued behind it
+    /// waited `setup_wait` for it in total
+    pub fn record_setup_end(&mut self, time: f64, setup_wait: f64) {
+        if let Some(since) = self.setup_since.take() {
+            self.total_setup_time += time - since.max(self.observation_start);
+        }
+        self.setups += 1;
+        self.setup_wait_time += setup_wait;
+    }
+
+    /// A customer with a deadline left `tardiness` after it, negative if in time
+    #[inline]
+    pub fn record_deadline(&mut self, tardiness: f64) {
+        self.deadline_customers += 1;
+        if tardiness > 0.0 {
+            self.late_customers += 1;
+            self.total_tardiness += tardiness;
+            self.max_tardiness = self.max_tardiness.max(tardiness);
+        }
+    }
+
+    /// A waiting customer was dropped because its deadline had passed
+    pub fn record_late_drop(&mut self) {
+        self.late_drops += 1;
+    }
+
+    #[inline]
+    pub fn record_service_start(&mut self, time: f64, wait_time: f64) {
+        // Update areas before changing state
+        self.accumulate_until(time);
+
         self.total_wait_time += wait_time;
-        self.server_busy = true;
+        self.wait_batches.push(wait_time);
+        self.wait_times.push(wait_time);
+        self.wait_histogram.push(wait_time);
+        self.started_services += 1;
+        self.immediate_services += u64::from(wait_time <= ZERO_WAIT_TOLERANCE);
+        for (target, count) in &mut self.service_level_counts {
+            if wait_time <= *target {
+                *count += 1;
+            }
+        }
+        self.busy_servers += 1;
         self.last_event_time = time;
 
-        // Update last_customers_in_system since server became busy
-        self.last_customers_in_system = self.last_queue_length + 1;
+        // Update last_customers_in_system since a server became busy
+        self.last_customers_in_system = self.last_queue_length + self.busy_servers;
+    }
+
+    /// A customer who waited `wait_time` entered service needing `service_time`
+    #[inline]
+    pub fn record_service_demand(&mut self, wait_time: f64, service_time: f64) {
+        self.wait_service_correlation.push(wait_time, service_time);
     }
 
+    /// A customer finished a service of `service_duration` at server number `server`
     #[inline]
-    pub fn record_service_end(&mut self, time: f64, service_duration: f64) {
+    pub fn record_service_end(&mut self, time: f64, service_duration: f64, server: usize) {
         // Update areas before changing state
-        let time_delta = time - self.last_event_time;
-        self.area_under_q += self.last_queue_length as f64 * time_delta;
-        self.area_under_customers += self.last_customers_in_system as f64 * time_delta;
+        self.accumulate_until(time);
 
         self.served_customers += 1;
-        self.total_busy_time += service_duration;
-        self.server_busy = false;
+        let busy_time = service_duration.min(time - self.observation_start);
+        self.total_busy_time += busy_time;
+        self.server_busy_time[server] += busy_time;
+        self.record_departure_time(time);
+        self.departure_windows.record(time);
+        self.service_autocorrelation.push(service_duration);
+        self.service_times.push(service_duration);
+        self.busy_servers -= 1;
         self.last_event_time = time;
 
-        // Update last_customers_in_system since server became idle
-        self.last_customers_in_system = self.last_queue_length;
+        // Update last_customers_in_system since a server became idle
+        self.last_customers_in_system = self.last_queue_length + self.busy_servers;
+    }
+
+    /// A customer's quantum ran out before its service was complete; it frees its server
+    /// and goes back to the queue, without counting as served
+    pub fn record_preemption(&mut self, time: f64) {
+        self.accumulate_until(time);
+
+        self.preemptions += 1;
+        self.busy_servers -= 1;
+        self.last_event_time = time;
+        self.last_customers_in_system = self.last_queue_length + self.busy_servers;
+    }
+
+    /// A preempted customer gets its next quantum; its wait was recorded when it first started
+    pub fn record_service_resume(&mut self, time: f64) {
+        self.accumulate_until(time);
+
+        self.busy_servers += 1;
+        self.last_event_time = time;
+        self.last_customers_in_system = self.last_queue_length + self.busy_servers;
+    }
+
+    /// A customer left after `sojourn` in the system, `service` of which it was served
+    #[inline]
+    pub fn record_sojourn(&mut self, sojourn: f64, service: f64) {
+        let slowdown = sojourn / service;
+        self.total_sojourn_time += sojourn;
+        self.total_slowdown += slowdown;
+        self.max_slowdown = self.max_slowdown.max(slowdown);
+        self.slowdown_histogram.push(slowdown.log10());
+        self.sojourn_service_product += sojourn * service;
+        self.service_squares += service * service;
     }
 
     pub fn average_wait_time(&self) -> f64 {
@@ -99,55 +987,753 @@ impl Statistics {
         }
     }
 
+    /// Mean time from arrival to departure of the customers served
+    pub fn average_sojourn_time(&self) -> f64 {
+        if self.served_customers == 0 {
+            0.0
+        } else {
+            self.total_sojourn_time / self.served_customers as f64
+        }
+    }
+
+    /// Mean ratio of a served customer's sojourn to its service, at least 1
+    ///
+    /// With exponential service E[1/S] is infinite, so this never settles: a
+    /// handful of tiny services dominate it. Reports use [`Statistics::slowdown_quantile`].
+    pub fn average_slowdown(&self) -> f64 {
+        if self.served_customers == 0 {
+            0.0
+        } else {
+            self.total_slowdown / self.served_customers as f64
+        }
+    }
+
+    /// Largest slowdown of a served customer, 0 before the first departure
+    pub fn max_slowdown(&self) -> f64 {
+        self.max_slowdown
+    }
+
+    /// Slowdown that a share `q` of the served customers stayed within, read off
+    /// the logarithmic slowdown histogram to within a fraction of a percent;
+    /// `None` before the first departure
+    pub fn slowdown_quantile(&self, q: f64) -> Option<f64> {
+        self.slowdown_histogram
+            .quantile(q)
+            .map(|log| 10f64.powf(log))
+    }
+
+    /// Histogram of log₁₀ of the slowdowns
+    pub fn slowdown_histogram(&self) -> &Histogram {
+        &self.slowdown_histogram
+    }
+
+    /// Least-squares slope of the sojourn against the service through the origin,
+    /// `None` before the first departure
+    ///
+    /// Under processor sharing a customer's expected sojourn is proportional to its
+    /// service, x/(1 - ρ) in M/G/1, so the slope is 1/(1 - ρ) whatever the distribution.
+    pub fn sojourn_per_service(&self) -> Option<f64> {
+        (self.service_squares > 0.0).then(|| self.sojourn_service_product / self.service_squares)
+    }
+
+    /// Services cut off at the end of a round-robin quantum
+    pub fn preemptions(&self) -> u64 {
+        self.preemptions
+    }
+
     pub fn average_queue_length(&self, total_time: f64) -> f64 {
-        if total_time == 0.0 {
+        let observed = self.observed_time(total_time);
+        if observed == 0.0 {
             0.0
         } else {
-            self.area_under_q / total_time
+            self.area_under_q / observed
         }
     }
 
+    /// Fraction of time an average server is busy
     pub fn utilization(&self, total_time: f64) -> f64 {
-        if total_time == 0.0 {
+        self.average_busy_servers(total_time) / self.average_servers(total_time)
+    }
+
+    /// Time-average number of servers, [`Statistics::servers`] unless it changed mid-run
+    pub fn average_servers(&self, total_time: f64) -> f64 {
+        let observed = self.observed_time(total_time);
+        match self.servers_since {
+            Some(since) if observed > 0.0 => {
+                let current = (total_time - since.max(self.observation_start)).max(0.0);
+                (self.capacity_before + self.servers as f64 * current) / observed
+            }
+            _ => self.servers as f64,
+        }
+    }
+
+    /// Change the number of servers at `time`; the utilization then relates the
+    /// busy time to the server time on offer
+    pub fn set_servers(&mut self, time: f64, servers: usize) {
+        let since = self
+            .servers_since
+            .unwrap_or(0.0)
+            .max(self.observation_start);
+        self.capacity_before += self.servers as f64 * (time - since).max(0.0);
+        self.servers_since = Some(time);
+        self.servers = servers;
+        if self.server_busy_time.len() < servers {
+            self.server_busy_time.resize(servers, 0.0);
+        }
+    }
+
+    /// Running totals up to `time`, with the current state extended to it
+    pub fn totals(&self, time: f64) -> Totals {
+        let elapsed = (time - self.last_event_time).max(0.0);
+        let queue_area = self.area_under_q + self.last_queue_length as f64 * elapsed;
+        let customers_area =
+            self.area_under_customers + self.last_customers_in_system as f64 * elapsed;
+        Totals {
+            time,
+            arrivals: self.arrived_customers,
+            started: self.started_services,
+            served: self.served_customers,
+            wait_time: self.total_wait_time,
+            queue_area,
+            busy_area: customers_area - queue_area,
+        }
+    }
+
+    /// Time-average number of busy servers, i.e. the carried load in Erlangs
+    pub fn average_busy_servers(&self, total_time: f64) -> f64 {
+        let observed = self.observed_time(total_time);
+        if observed == 0.0 {
             0.0
         } else {
-            self.total_busy_time / total_time
+            self.total_busy_time / observed
         }
     }
 
+    /// Fraction of time each server is busy; their mean is [`Statistics::utilization`]
+    pub fn server_utilizations(&self, total_time: f64) -> Vec<f64> {
+        let observed = self.observed_time(total_time);
+        self.server_busy_time
+            .iter()
+            .map(|busy| {
+                if observed == 0.0 {
+                    0.0
+                } else {
+                    busy / observed
+                }
+            })
+            .collect()
+    }
+
+    pub fn servers(&self) -> usize {
+        self.servers
+    }
+
     pub fn served_customers(&self) -> u64 {
         self.served_customers
     }
 
-    pub fn current_queue_length(&self) -> usize {
-        self.last_queue_length
+    pub fn arrived_customers(&self) -> u64 {
+        self.arrived_customers
     }
 
-    pub fn instantaneous_utilization(&self, current_time: f64) -> f64 {
-        if current_time == 0.0 {
+    /// Share of the admitted arrivals that had to wait, ρ for M/M/1 and Erlang C for M/M/c
+    ///
+    /// Unlike the utilization it is seen by arrivals rather than averaged over time,
+    /// and it counts waits caused by breakdowns and setups too.
+    pub fn probability_of_waiting(&self) -> f64 {
+        if self.admitted_customers == 0 {
+            0.0
+        } else {
+            self.delayed_customers as f64 / self.admitted_customers as f64
+        }
+    }
+
+    pub fn lost_customers(&self) -> u64 {
+        self.lost_customers
+    }
+
+    /// Arrivals turned away by a closed queue, not included in [`Statistics::lost_customers`]
+    pub fn closed_losses(&self) -> u64 {
+        self.closed_losses
+    }
+
+    /// Arrivals during closed periods that were admitted at the next opening instead
+    pub fn deferred_customers(&self) -> u64 {
+        self.deferred_customers
+    }
+
+    /// Fraction of arrivals that were lost, or 0 before the first arrival
+    pub fn blocking_probability(&self) -> f64 {
+        if self.arrived_customers == 0 {
             0.0
         } else {
-            self.total_busy_time / current_time
+            self.lost_customers as f64 / self.arrived_customers as f64
+        }
+    }
+
+    /// Servers currently serving a customer; none while the station is under repair
+    pub fn busy_servers(&self) -> usize {
+        if self.down_since.is_some() {
+            0
+        } else {
+            self.busy_servers
+        }
+    }
+
+    pub fn breakdowns(&self) -> u64 {
+        self.breakdowns
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.down_since.is_some()
+    }
+
+    /// Time spent under repair within the observation window ending at `total_time`
+    pub fn down_time(&self, total_time: f64) -> f64 {
+        let ongoing = self.down_since.map_or(0.0, |since| {
+            (total_time - since.max(self.observation_start)).max(0.0)
+        });
+        self.total_down_time + ongoing
+    }
+
+    pub fn failovers(&self) -> u64 {
+        self.failovers
+    }
+
+    /// Customers whose service a failover interrupted
+    pub fn failover_interruptions(&self) -> u64 {
+        self.failover_interruptions
+    }
+
+    pub fn standby_breakdowns(&self) -> u64 {
+        self.standby_breakdowns
+    }
+
+    /// Time the standby server served in place of the primary within the observation
+    /// window ending at `total_time`
+    pub fn standby_time(&self, total_time: f64) -> f64 {
+        let ongoing = self.standby_since.map_or(0.0, |since| {
+            (total_time - since.max(self.observation_start)).max(0.0)
+        });
+        self.total_standby_time + ongoing
+    }
+
+    /// Time with both servers down within the observation window ending at `total_time`
+    pub fn outage_time(&self, total_time: f64) -> f64 {
+        let ongoing = self.outage_since.map_or(0.0, |since| {
+            (total_time - since.max(self.observation_start)).max(0.0)
+        });
+        self.total_outage_time + ongoing
+    }
+
+    /// Setups completed within the observation window
+    pub fn setups(&self) -> u64 {
+        self.setups
+    }
+
+    /// Time spent switching on within the observation window ending at `total_time`
+    pub fn setup_time(&self, total_time: f64) -> f64 {
+        let ongoing = self.setup_since.map_or(0.0, |since| {
+            (total_time - since.max(self.observation_start)).max(0.0)
+        });
+        self.total_setup_time + ongoing
+    }
+
+    /// Mean duration of the completed setups
+    pub fn average_setup_time(&self) -> f64 {
+        if self.setups == 0 {
+            0.0
+        } else {
+            self.total_setup_time / self.setups as f64
+        }
+    }
+
+    /// Share of the total wait spent waiting for the station to switch on
+    pub fn setup_wait_share(&self) -> f64 {
+        if self.total_wait_time == 0.0 {
+            0.0
+        } else {
+            self.setup_wait_time / self.total_wait_time
+        }
+    }
+
+    /// Fraction of the served customers with a deadline that left after it
+    pub fn tardiness_probability(&self) -> f64 {
+        if self.deadline_customers == 0 {
+            0.0
+        } else {
+            self.late_customers as f64 / self.deadline_customers as f64
+        }
+    }
+
+    /// Mean time the late customers left after their deadline
+    pub fn mean_tardiness(&self) -> f64 {
+        if self.late_customers == 0 {
+            0.0
+        } else {
+            self.total_tardiness / self.late_customers as f64
+        }
+    }
+
+    /// Longest time a customer left after its deadline; 0 if none was late
+    pub fn max_tardiness(&self) -> f64 {
+        self.max_tardiness
+    }
+
+    /// Customers dropped because their deadline passed while they waited
+    pub fn late_drops(&self) -> u64 {
+        self.late_drops
+    }
+
+    /// Fraction of the customers with a deadline that reached the front only to be dropped
+    pub fn late_drop_fraction(&self) -> f64 {
+        let leaving = self.deadline_customers + self.late_drops;
+        if leaving == 0 {
+            0.0
+        } else {
+            self.late_drops as f64 / leaving as f64
+        }
+    }
+
+    /// Fraction of the observed time the station was up, 1 for servers that never fail
+    pub fn availability(&self, total_time: f64) -> f64 {
+        let observed = self.observed_time(total_time);
+        if observed == 0.0 {
+            1.0
+        } else {
+            1.0 - self.down_time(total_time) / observed
         }
     }
 
+    pub fn current_queue_length(&self) -> usize {
+        self.last_queue_length
+    }
+
+    pub fn instantaneous_utilization(&self, current_time: f64) -> f64 {
+        self.utilization(current_time)
+    }
+
     pub fn current_customers_in_system(&self) -> usize {
         self.last_customers_in_system
     }
 
     pub fn average_customers_in_system(&self, total_time: f64) -> f64 {
-        if total_time == 0.0 {
+        let observed = self.observed_time(total_time);
+        if observed == 0.0 {
             0.0
         } else {
-            self.area_under_customers / total_time
+            self.area_under_customers / observed
+        }
+    }
+
+    /// Empirical P_n: fraction of the observed time up to `current_time` with n customers in the system
+    pub fn customers_distribution(&self, current_time: f64) -> Vec<f64> {
+        let observed = self.observed_time(current_time);
+        let n = self.last_customers_in_system;
+        let mut distribution = self.time_in_state.clone();
+        if n >= distribution.len() {
+            distribution.resize(n + 1, 0.0);
+        }
+        // The current state has lasted since the last change without being accumulated yet
+        distribution[n] += (current_time - self.last_event_time).max(0.0);
+
+        if observed > 0.0 {
+            for p in &mut distribution {
+                *p /= observed;
+            }
         }
+        distribution
+    }
+
+    /// Mean wait and its 95% confidence half-width from batch means
+    pub fn batch_means_wait(&self) -> Option<(f64, f64)> {
+        self.wait_batches.confidence_interval()
+    }
+
+    /// Share of the customers who started service that had waited, decided at
+    /// service start with [`ZERO_WAIT_TOLERANCE`]; `None` before the first start
+    ///
+    /// Close to [`Statistics::probability_of_waiting`], which is decided at arrival:
+    /// a customer who arrives at a busy server and is served at once, e.g. at the
+    /// instant of a departure, counts as delayed there and not here.
+    pub fn delay_probability(&self) -> Option<f64> {
+        (self.started_services > 0).then(|| {
+            (self.started_services - self.immediate_services) as f64 / self.started_services as f64
+        })
+    }
+
+    /// Customers who started service with a wait within [`ZERO_WAIT_TOLERANCE`], all
+    /// of them in the first bin of the wait histogram
+    pub fn immediate_services(&self) -> u64 {
+        self.immediate_services
+    }
+
+    /// Customers who started service after a wait beyond [`ZERO_WAIT_TOLERANCE`]
+    pub fn delayed_services(&self) -> u64 {
+        self.started_services - self.immediate_services
+    }
+
+    /// Mean wait of the customers who had to wait, 1/(cμ - λ) for M/M/c; `None`
+    /// until one has
+    ///
+    /// The waits of the others are 0, so this and the moments below follow from
+    /// those of all waits and the number of immediate starts, without a second pass.
+    pub fn conditional_mean_wait(&self) -> Option<f64> {
+        let delayed = self.delayed_services();
+        (delayed > 0).then(|| self.total_wait_time / delayed as f64)
+    }
+
+    /// Sample variance of the waits of the customers who had to wait; `None` with fewer than two
+    pub fn conditional_wait_variance(&self) -> Option<f64> {
+        let delayed = self.delayed_services();
+        if delayed < 2 {
+            return None;
+        }
+        let all = self.wait_times.count() as f64;
+        let variance = self.wait_times.variance()?;
+        let mean = self.wait_times.mean()?;
+        let sum_squares = (all - 1.0) * variance + all * mean * mean;
+        let delayed = delayed as f64;
+        let conditional_mean = self.total_wait_time / delayed;
+        Some(((sum_squares - delayed * conditional_mean.powi(2)) / (delayed - 1.0)).max(0.0))
+    }
+
+    /// Wait that a share `q` of the customers who had to wait stayed within, read off
+    /// the wait histogram above its immediate starts; `None` until one has waited
+    pub fn conditional_wait_quantile(&self, q: f64) -> Option<f64> {
+        let delayed = self.delayed_services();
+        let total = self.wait_histogram.total();
+        if delayed == 0 || total == 0 {
+            return None;
+        }
+        let immediate = total.saturating_sub(delayed) as f64;
+        let share = (immediate + q.clamp(0.0, 1.0) * delayed as f64) / total as f64;
+        self.wait_histogram.quantile(share)
+    }
+
+    /// The wait histogram of only the customers who had to wait
+    pub fn conditional_wait_histogram(&self) -> Histogram {
+        let mut histogram = self.wait_histogram.clone();
+        histogram.remove_from_first_bin(self.immediate_services);
+        histogram
+    }
+
+    /// Coefficient of variation (standard deviation / mean) of the waits so far
+    ///
+    /// For M/M/1 it settles at √((2 - ρ)/ρ), above 1 because of the customers who
+    /// do not wait at all. `None` until a few customers have had to wait.
+    pub fn wait_cv(&self) -> Option<f64> {
+        if self.delayed_customers < WAIT_CV_MIN_DELAYED {
+            return None;
+        }
+        self.wait_times.scv().map(f64::sqrt)
+    }
+
+    /// Waits of the customers who started service, binned as finely as their range allows
+    pub fn wait_histogram(&self) -> &Histogram {
+        &self.wait_histogram
+    }
+
+    /// 95% confidence interval `(lower, upper)` of the mean wait from batch means
+    pub fn wait_confidence_interval(&self) -> Option<(f64, f64)> {
+        self.batch_means_wait()
+            .map(|(mean, half_width)| (mean - half_width, mean + half_width))
+    }
+
+    /// Jackknife estimate of the mean wait and its standard error over the same batches
+    pub fn jackknife_wait(&self) -> Option<(f64, f64)> {
+        self.wait_batches.jackknife()
+    }
+
+    pub fn wait_batch_count(&self) -> usize {
+        self.wait_batches.batch_count()
+    }
+
+    /// Lag-1 autocorrelation of service durations; should be near 0 for independent draws
+    pub fn service_autocorrelation(&self) -> Option<f64> {
+        self.service_autocorrelation.value()
+    }
+
+    /// Correlation of each customer's wait with its service time
+    ///
+    /// Near 0 while service times are independent of the waits; clearly negative
+    /// when a [`crate::config::ServiceModulation`] speeds up the long waiters.
+    pub fn wait_service_correlation(&self) -> Option<f64> {
+        self.wait_service_correlation.value()
+    }
+
+    /// Mean and variability of the times between arrivals
+    pub fn interarrival_times(&self) -> &Moments {
+        &self.interarrival_times
+    }
+
+    /// Mean and variability of the completed service durations
+    pub fn service_times(&self) -> &Moments {
+        &self.service_times
+    }
+
+    /// Mean and variance of the waits of the customers who started service
+    pub fn wait_times(&self) -> &Moments {
+        &self.wait_times
+    }
+
+    /// Measure the windowed throughput over the last `window` time units
+    /// Also the length of the windows departures are counted in; restarts those counts
+    pub fn set_throughput_window(&mut self, window: f64) {
+        assert!(window > 0.0, "the throughput window must be positive");
+        self.throughput_window = window;
+        self.departure_windows = WindowCounts::new(self.observation_start, window);
+    }
+
+    pub fn throughput_window(&self) -> f64 {
+        self.throughput_window
+    }
+
+    /// Also keep the main metrics over the last `width` time units, see
+    /// [`Statistics::windowed_stats`]; `None` stops doing so
+    pub fn set_statistics_window(&mut self, width: Option<f64>) {
+        let state = self.window_state();
+        self.rolling_window = width.map(|width| {
+            let mut window = RollingWindow::new(width);
+            window.restart(self.last_event_time, state);
+            Box::new(window)
+        });
     }
 
+    pub fn rolling_window(&self) -> Option<&RollingWindow> {
+        self.rolling_window.as_deref()
+    }
+
+    #[inline]
+    pub fn has_rolling_window(&self) -> bool {
+        self.rolling_window.is_some()
+    }
+
+    /// Pass what happened since the previous call to the rolling window; call it
+    /// after every event when [`Statistics::has_rolling_window`]
+    #[inline(never)]
+    pub fn observe_window(&mut self, time: f64) {
+        let state = self.window_state();
+        let counters = WindowCounters {
+            wait_time: self.total_wait_time,
+            started: self.started_services,
+            served: self.served_customers,
+        };
+        if let Some(window) = &mut self.rolling_window {
+            window.observe(time, state, counters);
+        }
+    }
+
+    fn window_state(&self) -> WindowState {
+        WindowState {
+            queue_length: self.last_queue_length,
+            customers_in_system: self.last_customers_in_system,
+            busy_servers: self.busy_servers,
+            servers: self.servers,
+        }
+    }
+
+    /// Mean wait, queue length, utilization and throughput over the statistics
+    /// window ending at `current_time`; `None` without a window
+    ///
+    /// The cumulative metrics settle on the long-run averages, so after a
+    /// parameter change they take ever longer to show it; these forget what lies
+    /// beyond the window and follow the current regime.
+    pub fn windowed_stats(&self, current_time: f64) -> Option<WindowedStats> {
+        self.rolling_window
+            .as_ref()
+            .map(|window| window.stats(current_time))
+    }
+
+    /// Count the customers whose wait is at most each of `targets`, from now on
+    pub fn set_service_level_targets(&mut self, targets: &[f64]) {
+        self.service_level_counts = targets.iter().map(|&target| (target, 0)).collect();
+    }
+
+    /// Fraction of the customers who started service after waiting at most `target`
+    ///
+    /// Only targets passed to [`Statistics::set_service_level_targets`] are counted;
+    /// any other target, or a run without customers yet, gives NaN.
+    pub fn service_level(&self, target: f64) -> f64 {
+        self.service_level_counts
+            .iter()
+            .find(|(tracked, _)| *tracked == target)
+            .map_or(f64::NAN, |&(_, count)| {
+                count as f64 / self.started_services as f64
+            })
+    }
+
+    /// Every tracked target with its service level, see [`Statistics::service_level`]
+    pub fn service_levels(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.service_level_counts
+            .iter()
+            .map(|&(target, _)| (target, self.service_level(target)))
+    }
+
+    /// Departures per time unit over the last `throughput_window` time units up to `current_time`
+    ///
+    /// Unlike [`Statistics::throughput`], which averages over the whole run and
+    /// flattens out, this follows short-term changes in the departure rate. Within
+    /// the first window after the start (or warm-up) it divides by the time observed.
+    pub fn windowed_throughput(&self, current_time: f64) -> f64 {
+        let span = self.throughput_window.min(self.observed_time(current_time));
+        if span <= 0.0 {
+            return 0.0;
+        }
+        let horizon = current_time - self.throughput_window;
+        let departures = self
+            .recent_departures
+            .iter()
+            .filter(|departure| *departure > horizon)
+            .count();
+        departures as f64 / span
+    }
+
+    /// Departures per time unit averaged over the whole observed time
     pub fn throughput(&self, total_time: f64) -> f64 {
-        if total_time == 0.0 {
+        let observed = self.observed_time(total_time);
+        if observed == 0.0 {
             0.0
         } else {
-            self.served_customers as f64 / total_time
+            self.served_customers as f64 / observed
+        }
+    }
+
+    /// Moments of the departures per consecutive `throughput_window` complete by `current_time`
+    ///
+    /// The windows tile the observed time from its start, so their variance shows
+    /// how bursty the output process is, e.g. before feeding it to another queue.
+    /// `None` until two windows are complete.
+    pub fn departure_windows(&self, current_time: f64) -> Option<DepartureWindows> {
+        let counts = self.departure_windows.completed_at(current_time);
+        Some(DepartureWindows {
+            width: self.throughput_window,
+            windows: counts.count(),
+            mean: counts.mean()?,
+            variance: counts.variance()?,
+        })
+    }
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moments_of(values: &[f64]) -> Moments {
+        let mut moments = Moments::default();
+        for &value in values {
+            moments.push(value);
+        }
+        moments
+    }
+
+    #[test]
+    fn moments_match_the_textbook_sample() {
+        // Mean 5, squared deviations summing to 32
+        let moments = moments_of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(moments.count(), 8);
+        assert_eq!(moments.mean(), Some(5.0));
+        assert!((moments.variance().unwrap() - 32.0 / 7.0).abs() < 1e-12);
+        assert!((moments.scv().unwrap() - 32.0 / 7.0 / 25.0).abs() < 1e-12);
+        assert_eq!(moments_of(&[3.0; 10]).scv(), Some(0.0));
+    }
+
+    #[test]
+    fn moments_need_enough_values() {
+        assert_eq!(Moments::default().mean(), None);
+        let single = moments_of(&[4.0]);
+        assert_eq!(single.mean(), Some(4.0));
+        assert_eq!((single.variance(), single.scv()), (None, None));
+        assert_eq!(moments_of(&[1.0, -1.0]).scv(), None);
+    }
+
+    /// Statistics after one service start per wait, all at t = 10
+    fn started_after(waits: &[f64]) -> Statistics {
+        let mut stats = Statistics::new();
+        for &wait in waits {
+            stats.record_service_start(10.0, wait);
+        }
+        stats
+    }
+
+    #[test]
+    fn a_wait_within_the_tolerance_counts_as_immediate() {
+        let stats = started_after(&[0.0, ZERO_WAIT_TOLERANCE]);
+        assert_eq!(stats.immediate_services(), 2);
+        assert_eq!(stats.delay_probability(), Some(0.0));
+        assert_eq!(stats.conditional_mean_wait(), None);
+
+        let stats = started_after(&[ZERO_WAIT_TOLERANCE, ZERO_WAIT_TOLERANCE.next_up()]);
+        assert_eq!(
+            (stats.immediate_services(), stats.delayed_services()),
+            (1, 1)
+        );
+        assert_eq!(stats.delay_probability(), Some(0.5));
+    }
+
+    #[test]
+    fn nothing_is_known_about_delays_before_the_first_start() {
+        let stats = Statistics::new();
+        assert_eq!(stats.delay_probability(), None);
+        assert_eq!(stats.conditional_mean_wait(), None);
+        assert_eq!(stats.conditional_wait_variance(), None);
+        // One delayed customer gives a mean but no variance
+        let stats = started_after(&[0.0, 2.0]);
+        assert_eq!(stats.conditional_mean_wait(), Some(2.0));
+        assert_eq!(stats.conditional_wait_variance(), None);
+    }
+
+    #[test]
+    fn conditional_moments_follow_from_the_pooled_ones() {
+        // Delayed waits 1, 2, 3 and 6: mean 3, squared deviations summing to 14
+        let stats = started_after(&[0.0, 1.0, 0.0, 2.0, 3.0, 6.0]);
+        assert_eq!(stats.delay_probability(), Some(4.0 / 6.0));
+        assert_eq!(stats.conditional_mean_wait(), Some(3.0));
+        let variance = stats.conditional_wait_variance().unwrap();
+        assert!((variance - 14.0 / 3.0).abs() < 1e-12, "{}", variance);
+    }
+
+    #[test]
+    fn probability_of_waiting_counts_delayed_admissions() {
+        let mut stats = Statistics::new();
+        assert_eq!(stats.probability_of_waiting(), 0.0);
+        for delayed in [true, false, false, true, false] {
+            stats.record_admission(delayed);
         }
+        assert_eq!(stats.probability_of_waiting(), 0.4);
+    }
+
+    #[test]
+    fn jain_index_of_class_means() {
+        // (1 + 2 + 3)² / (3 · (1 + 4 + 9)) = 36 / 42
+        assert!((jain_index(&[1.0, 2.0, 3.0]).unwrap() - 6.0 / 7.0).abs() < 1e-15);
+        assert_eq!(jain_index(&[2.5, 2.5]), Some(1.0));
+        // One class bears everything
+        assert_eq!(jain_index(&[4.0, 0.0, 0.0, 0.0]), Some(0.25));
+    }
+
+    #[test]
+    fn slowdown_quantiles_hold_over_orders_of_magnitude() {
+        let mut stats = Statistics::new();
+        for slowdown in [1.0, 10.0, 100.0, 1e6, 1e9] {
+            stats.record_sojourn(slowdown * 0.5, 0.5);
+        }
+        let median = stats.slowdown_quantile(0.5).unwrap();
+        assert!((median / 100.0 - 1.0).abs() < 0.01, "median {}", median);
+        assert!(stats.slowdown_quantile(0.1).unwrap() < 1.01);
+        assert_eq!(stats.max_slowdown(), 1e9);
+    }
+
+    #[test]
+    fn jain_index_needs_two_classes() {
+        assert_eq!(jain_index(&[]), None);
+        assert_eq!(jain_index(&[3.0]), None);
+        assert_eq!(jain_index(&[0.0, 0.0]), None);
     }
 }
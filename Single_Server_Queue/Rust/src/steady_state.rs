@@ -0,0 +1,93 @@
+use crate::statistics::t_quantile_975;
+
+/// Samples the trend is fitted over unless configured otherwise
+pub const DEFAULT_WINDOW: usize = 50;
+
+/// Drift over the window, relative to the level, that still counts as flat
+pub const DEFAULT_TOLERANCE: f64 = 0.01;
+
+/// Least-squares line through the most recent samples of a series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trend {
+    /// Change of the value per time unit
+    pub slope: f64,
+    /// Standard error of `slope`
+    pub standard_error: f64,
+    /// Mean of the values in the window
+    pub level: f64,
+    /// Time covered by the window
+    pub span: f64,
+}
+
+/// Decides when a running average such as the mean wait has stopped drifting
+///
+/// Fits a line through the last `window` samples and calls the series steady once
+/// even the upper end of the slope's 95% confidence interval, extrapolated over
+/// the window, moves the value by less than `tolerance` times its level. This is
+/// an equivalence test: a noisy window does not pass just because its slope is
+/// not significantly different from zero, it has to be shown to be small.
+#[derive(Debug, Clone, Copy)]
+pub struct SteadyStateDetector {
+    window: usize,
+    tolerance: f64,
+}
+
+impl SteadyStateDetector {
+    /// `window` must be at least 3 samples, so the slope has a standard error
+    pub fn new(window: usize, tolerance: f64) -> Self {
+        assert!(window >= 3, "the trend needs at least 3 samples");
+        Self { window, tolerance }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Trend of the last `window` samples, or `None` while there are fewer
+    pub fn trend(&self, samples: &[(f64, f64)]) -> Option<Trend> {
+        let recent = samples.get(samples.len().checked_sub(self.window)?..)?;
+        let n = recent.len() as f64;
+        let mean_t = recent.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let level = recent.iter().map(|(_, v)| v).sum::<f64>() / n;
+
+        let (sxx, sxy) = recent.iter().fold((0.0, 0.0), |(sxx, sxy), (t, v)| {
+            let dt = t - mean_t;
+            (sxx + dt * dt, sxy + dt * (v - level))
+        });
+        if sxx <= 0.0 {
+            return None;
+        }
+        let slope = sxy / sxx;
+        let residuals = recent
+            .iter()
+            .map(|(t, v)| (v - level - slope * (t - mean_t)).powi(2))
+            .sum::<f64>();
+        let standard_error = (residuals / (n - 2.0) / sxx).sqrt();
+
+        Some(Trend {
+            slope,
+            standard_error,
+            level,
+            span: recent[recent.len() - 1].0 - recent[0].0,
+        })
+    }
+
+    /// Whether the recent trend of `samples` is negligible
+    pub fn is_steady(&self, samples: &[(f64, f64)]) -> bool {
+        self.trend(samples).is_some_and(|trend| {
+            let df = (self.window - 2) as f64;
+            let worst_slope = trend.slope.abs() + t_quantile_975(df) * trend.standard_error;
+            worst_slope * trend.span <= self.tolerance * trend.level.abs()
+        })
+    }
+}
+
+impl Default for SteadyStateDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW, DEFAULT_TOLERANCE)
+    }
+}
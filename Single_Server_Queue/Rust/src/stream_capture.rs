@@ -0,0 +1,388 @@
+//! Record every uniform a run draws and feed the same draws into a later run
+//!
+//! A rare anomaly can take millions of events to show up again, and only with
+//! the same random inputs. A recording keeps each draw with the stream it came
+//! from and the event that drew it, so a modified build can replay them
+//! exactly. Streams are replayed independently: a change that only reorders the
+//! draws of different streams within an event still replays, while one that
+//! draws more or fewer numbers in an event is reported as a divergence at the
+//! first draw that lands on the wrong event.
+//!
+//! The file is a magic header, the number of draws and then, per draw, the
+//! stream tag byte, the event index as a varint delta from the previous draw's
+//! and the f64 bits, about ten bytes a draw.
+
+use crate::rng::Sampler;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// First bytes of a recording, the last one being the format version
+const MAGIC: &[u8; 8] = b"SSQRNG\0\x01";
+
+/// The random stream a draw came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamTag {
+    Arrivals,
+    Service,
+    Setup,
+    Breakdown,
+    Deadline,
+}
+
+impl StreamTag {
+    pub const ALL: [StreamTag; 5] = [
+        StreamTag::Arrivals,
+        StreamTag::Service,
+        StreamTag::Setup,
+        StreamTag::Breakdown,
+        StreamTag::Deadline,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get(byte as usize).copied()
+    }
+}
+
+impl fmt::Display for StreamTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            StreamTag::Arrivals => "arrivals",
+            StreamTag::Service => "service",
+            StreamTag::Setup => "setup",
+            StreamTag::Breakdown => "breakdown",
+            StreamTag::Deadline => "deadline",
+        })
+    }
+}
+
+/// One recorded uniform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Draw {
+    pub tag: StreamTag,
+    /// Event being processed when it was drawn, 0 for draws before the first event
+    pub event: u64,
+    pub value: f64,
+}
+
+/// Every draw of a run in the order they were made
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamRecording {
+    pub draws: Vec<Draw>,
+}
+
+impl StreamRecording {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&(self.draws.len() as u64).to_le_bytes())?;
+        let mut previous = 0;
+        for draw in &self.draws {
+            out.write_all(&[draw.tag as u8])?;
+            write_varint(&mut out, draw.event - previous)?;
+            out.write_all(&draw.value.to_le_bytes())?;
+            previous = draw.event;
+        }
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a random stream recording".to_string()));
+        }
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        let count = u64::from_le_bytes(bytes);
+
+        let mut draws = Vec::new();
+        let mut event = 0;
+        for index in 0..count {
+            let truncated = |e: io::Error| match e.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    invalid(format!("recording ends after {} of {} draws", index, count))
+                }
+                _ => e,
+            };
+            let mut tag = [0; 1];
+            input.read_exact(&mut tag).map_err(truncated)?;
+            let tag = StreamTag::from_byte(tag[0]).ok_or_else(|| {
+                invalid(format!("unknown stream tag {} at draw {}", tag[0], index))
+            })?;
+            event += read_varint(&mut input).map_err(truncated)?;
+            input.read_exact(&mut bytes).map_err(truncated)?;
+            draws.push(Draw {
+                tag,
+                event,
+                value: f64::from_le_bytes(bytes),
+            });
+        }
+        Ok(Self { draws })
+    }
+}
+
+/// LEB128: seven bits per byte, the high bit set on all but the last
+fn write_varint(out: &mut impl Write, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        out.write_all(&[(value as u8) | 0x80])?;
+        value >>= 7;
+    }
+    out.write_all(&[value as u8])
+}
+
+fn read_varint(input: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        input.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "event index does not fit in 64 bits",
+    ))
+}
+
+/// Why a replay could not go on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The run asked the stream for more draws than were recorded
+    Exhausted { tag: StreamTag, event: u64 },
+    /// The next recorded draw of the stream belongs to another event, so this
+    /// build draws a different number of times somewhere before
+    Diverged {
+        tag: StreamTag,
+        recorded_event: u64,
+        event: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Exhausted { tag, event } => write!(
+                f,
+                "the {} stream ran out of recorded draws at event {}",
+                tag, event
+            ),
+            ReplayError::Diverged {
+                tag,
+                recorded_event,
+                event,
+            } => write!(
+                f,
+                "the {} stream drew at event {} a number recorded at event {}; the draws per event changed",
+                tag, event, recorded_event
+            ),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Mode {
+    Record(Vec<Draw>),
+    /// Per stream, the draws not yet replayed
+    Replay([VecDeque<(u64, f64)>; 5]),
+}
+
+#[derive(Clone)]
+struct Shared {
+    mode: Mode,
+    event: u64,
+    /// Off in copies, whose events are no longer counted
+    check_events: bool,
+    error: Option<ReplayError>,
+}
+
+/// Handle to a recording or replay shared by the streams of one simulation
+///
+/// Hand a clone to [`Simulation::with_capture`](crate::simulation::Simulation::with_capture)
+/// and keep one to read the recording or the replay error afterwards.
+#[derive(Clone)]
+pub struct StreamCapture {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl StreamCapture {
+    /// Record the draws of the simulation this is given to
+    pub fn record() -> Self {
+        Self::new(Mode::Record(Vec::new()))
+    }
+
+    /// Serve the draws of `recording` instead of generating them
+    pub fn replay(recording: StreamRecording) -> Self {
+        let mut streams: [VecDeque<(u64, f64)>; 5] = Default::default();
+        for draw in recording.draws {
+            streams[draw.tag.index()].push_back((draw.event, draw.value));
+        }
+        Self::new(Mode::Replay(streams))
+    }
+
+    fn new(mode: Mode) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                mode,
+                event: 0,
+                check_events: true,
+                error: None,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        // A panic elsewhere cannot leave the draws half-updated
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `inner` recorded under `tag`, or replaced by the recorded draws of `tag`
+    pub(crate) fn wrap(&self, tag: StreamTag, inner: Box<dyn Sampler>) -> Box<dyn Sampler> {
+        match self.lock().mode {
+            Mode::Record(_) => Box::new(RecordingSampler {
+                tag,
+                inner,
+                capture: self.clone(),
+            }),
+            Mode::Replay(_) => Box::new(ReplaySampler {
+                tag,
+                capture: self.clone(),
+            }),
+        }
+    }
+
+    /// The draws that follow belong to `event`
+    pub(crate) fn begin_event(&self, event: u64) {
+        self.lock().event = event;
+    }
+
+    /// The draws recorded so far; empty for a replay
+    pub fn recording(&self) -> StreamRecording {
+        match &self.lock().mode {
+            Mode::Record(draws) => StreamRecording {
+                draws: draws.clone(),
+            },
+            Mode::Replay(_) => StreamRecording::default(),
+        }
+    }
+
+    /// The first draw a replay could not serve
+    pub fn error(&self) -> Option<ReplayError> {
+        self.lock().error
+    }
+
+    /// Recorded draws the replay has not used, in every stream together
+    pub fn remaining(&self) -> usize {
+        match &self.lock().mode {
+            Mode::Record(_) => 0,
+            Mode::Replay(streams) => streams.iter().map(VecDeque::len).sum(),
+        }
+    }
+
+    /// An independent copy for a forked simulation, which no longer checks events
+    fn detached(&self) -> Self {
+        let mut shared = self.lock().clone();
+        shared.check_events = false;
+        Self {
+            shared: Arc::new(Mutex::new(shared)),
+        }
+    }
+}
+
+/// Passes the draws of `inner` through, appending each to the recording
+///
+/// Copies and forks draw from copies of `inner` without recording, so a branch
+/// of the run does not mix its draws into the recording.
+struct RecordingSampler {
+    tag: StreamTag,
+    inner: Box<dyn Sampler>,
+    capture: StreamCapture,
+}
+
+impl Sampler for RecordingSampler {
+    fn f64(&mut self) -> f64 {
+        let value = self.inner.f64();
+        let mut shared = self.capture.lock();
+        let event = shared.event;
+        if let Mode::Record(draws) = &mut shared.mode {
+            draws.push(Draw {
+                tag: self.tag,
+                event,
+                value,
+            });
+        }
+        value
+    }
+
+    fn fork(&mut self) -> Box<dyn Sampler> {
+        self.inner.fork()
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        self.inner.clone_box()
+    }
+}
+
+/// Serves the recorded draws of its stream in order
+///
+/// After the first error it keeps returning 0.5, a harmless uniform, until the
+/// run checks [`StreamCapture::error`] and stops. Copies replay the remaining
+/// draws on their own.
+struct ReplaySampler {
+    tag: StreamTag,
+    capture: StreamCapture,
+}
+
+impl Sampler for ReplaySampler {
+    fn f64(&mut self) -> f64 {
+        let mut shared = self.capture.lock();
+        if shared.error.is_some() {
+            return 0.5;
+        }
+        let (event, check_events) = (shared.event, shared.check_events);
+        let Mode::Replay(streams) = &mut shared.mode else {
+            unreachable!("replay samplers only come from a replay");
+        };
+        let error = match streams[self.tag.index()].front() {
+            None => ReplayError::Exhausted {
+                tag: self.tag,
+                event,
+            },
+            Some(&(recorded_event, _)) if check_events && recorded_event != event => {
+                ReplayError::Diverged {
+                    tag: self.tag,
+                    recorded_event,
+                    event,
+                }
+            }
+            Some(&(_, value)) => {
+                streams[self.tag.index()].pop_front();
+                return value;
+            }
+        };
+        shared.error = Some(error);
+        0.5
+    }
+
+    fn fork(&mut self) -> Box<dyn Sampler> {
+        self.clone_box()
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(ReplaySampler {
+            tag: self.tag,
+            capture: self.capture.detached(),
+        })
+    }
+}
@@ -0,0 +1,732 @@
+use crate::cli::CliOptions;
+use crate::prompt::{Prompter, positive};
+use crate::questions::{
+    CALIBRATION_CUSTOMERS_HELP, CALIBRATION_REPLICATIONS_HELP, ITERATIONS_HELP, LAMBDA_HELP,
+    MU_HELP, OBSERVED_HELP, TOLERANCE_HELP,
+};
+use crate::report;
+use crate::runs::run_simulation;
+use rust_single_server_queue::calibration::{
+    CalibratedParameter, CalibrationMetric, calibrate, evaluate,
+};
+use rust_single_server_queue::config::{
+    DEFAULT_MIN_QUANTUM, QueueDiscipline, RoundRobin, ServiceKind, SimulationConfig,
+};
+use rust_single_server_queue::cost::CostModel;
+use rust_single_server_queue::deadlines::compare_disciplines;
+use rust_single_server_queue::distributions::{Distribution, Exponential};
+use rust_single_server_queue::experiment::{Factor, Progress, coded_level, run_experiment};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::rng::RngBackend;
+use rust_single_server_queue::round_robin::compare_quanta;
+use rust_single_server_queue::run_length::{DEFAULT_PILOT_CUSTOMERS, plan_run_length};
+use rust_single_server_queue::seed_scan::{DEFAULT_CV_THRESHOLD, HISTOGRAM_BINS, SeedScan};
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::{allen_cunneen_wait, mg1_processor_sharing_sojourn};
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+use rust_single_server_queue::verification::VerifiedMetric;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Where `--seed-scan` writes the per-seed metrics unless `--seed-scan-csv` says otherwise
+const DEFAULT_SEED_SCAN_CSV: &str = "seed_scan.csv";
+
+/// Where `--experiment` writes its runs unless `--experiment-csv` says otherwise
+const DEFAULT_EXPERIMENT_CSV: &str = "experiment.csv";
+
+/// Most seeds or cells the cost ranking of a study lists
+const COST_RANKING_ROWS: usize = 10;
+
+/// Seeds each discipline of `--compare-quanta` runs
+const QUANTUM_COMPARISON_SEEDS: usize = 10;
+
+/// Run the study `options` asks for instead of a single run, if any; returns
+/// whether one ran
+pub fn run_study(
+    options: &CliOptions,
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    new_time_series: impl Fn() -> SimulationTimeSeries,
+) -> bool {
+    if let Some(target) = options.plan_run_length {
+        run_length_plan(
+            config,
+            options.pilot_customers.unwrap_or(DEFAULT_PILOT_CUSTOMERS),
+            target,
+        );
+        return true;
+    }
+    if let Some(seeds) = options.seed_scan {
+        let path = options
+            .seed_scan_csv
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SEED_SCAN_CSV));
+        run_seed_scan(
+            config,
+            stop_condition,
+            seeds,
+            new_time_series,
+            &path,
+            options.seed_scan_cv.unwrap_or(DEFAULT_CV_THRESHOLD),
+            options.cost_model,
+        );
+        return true;
+    }
+    if let Some(seeds) = options.compare_disciplines {
+        run_discipline_comparison(config, stop_condition, seeds);
+        return true;
+    }
+    if let Some(quanta) = &options.compare_quanta {
+        let config = SimulationConfig {
+            round_robin: Some(RoundRobin {
+                quantum: f64::INFINITY,
+                min_quantum: options.min_quantum.unwrap_or(DEFAULT_MIN_QUANTUM),
+            }),
+            ..config.clone()
+        };
+        run_quantum_comparison(&config, stop_condition, quanta);
+        return true;
+    }
+    if let Some(replications) = options.experiment {
+        let path = options
+            .experiment_csv
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_EXPERIMENT_CSV));
+        run_factorial_experiment(
+            config,
+            stop_condition,
+            &options.factors,
+            replications,
+            options.center_points,
+            &path,
+            &options.cost_model,
+        );
+        return true;
+    }
+    if let Some(days) = options.days {
+        let StopCondition::Time(horizon) = *stop_condition else {
+            eprintln!("Error: --days needs the simulation time as the stop condition");
+            std::process::exit(1);
+        };
+        run_terminating_days(config, horizon, days);
+        return true;
+    }
+    false
+}
+
+/// Search for the λ or μ of an M/M/1 queue that reproduces an observed metric
+pub fn run_calibration<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    rng_backend: RngBackend,
+) {
+    let parameter =
+        match prompter.read_choice("Rate to fit:", &["Service rate (μ)", "Arrival rate (λ)"], 0) {
+            0 => CalibratedParameter::ServiceRate,
+            _ => CalibratedParameter::ArrivalRate,
+        };
+    let mut config = SimulationConfig {
+        rng_backend,
+        ..Default::default()
+    };
+    match parameter {
+        CalibratedParameter::ServiceRate => {
+            config.lambda =
+                prompter.read_f64_with_default("Arrival rate (λ)", 0.8, LAMBDA_HELP, positive)
+        }
+        CalibratedParameter::ArrivalRate => {
+            config.mu = prompter.read_f64_with_default("Service rate (μ)", 1.0, MU_HELP, |mu| {
+                Exponential::new(mu).validate()
+            })
+        }
+    }
+
+    let metric = match prompter.read_choice(
+        "Observed metric:",
+        &["Mean wait", "90th percentile wait", "Utilization"],
+        0,
+    ) {
+        0 => CalibrationMetric::MeanWait,
+        1 => CalibrationMetric::WaitP90,
+        _ => CalibrationMetric::Utilization,
+    };
+    // The M/M/1 values at the default rates λ = 0.8, μ = 1
+    let default_target = match metric {
+        CalibrationMetric::MeanWait => 4.0,
+        CalibrationMetric::WaitP90 => 10.4,
+        CalibrationMetric::Utilization => 0.8,
+    };
+    let target =
+        prompter.read_f64_with_default("Observed value", default_target, OBSERVED_HELP, positive);
+    let tolerance =
+        prompter.read_f64_with_default("Tolerance", target * 0.02, TOLERANCE_HELP, positive);
+    let replications = prompter.read_u64_with_default(
+        "Replications per candidate",
+        10,
+        CALIBRATION_REPLICATIONS_HELP,
+        positive,
+    ) as usize;
+    let customers = prompter.read_u64_with_default(
+        "Customers per replication",
+        100_000,
+        CALIBRATION_CUSTOMERS_HELP,
+        positive,
+    );
+    let max_iterations =
+        prompter.read_u64_with_default("Maximum iterations", 20, ITERATIONS_HELP, positive)
+            as usize;
+
+    println!();
+    println!(
+        "=== Calibrating {} to a {} of {:.4} ± {:.4} ===",
+        parameter, metric, target, tolerance
+    );
+    // A fresh base seed per session; within it every candidate sees the same streams
+    let seed = fastrand::u64(..);
+    let start_time = Instant::now();
+    let result = calibrate(
+        |value| {
+            let mut candidate = config.clone();
+            parameter.apply(&mut candidate, value);
+            let (mean, half_width) = evaluate(&candidate, metric, replications, customers, seed);
+            println!(
+                "  {} = {:.6}: {} {:.4} ± {:.4}",
+                parameter, value, metric, mean, half_width
+            );
+            (mean, half_width)
+        },
+        parameter.bracket(&config),
+        target,
+        tolerance,
+        max_iterations,
+    );
+    let elapsed_secs = start_time.elapsed().as_secs_f64();
+
+    let calibration = match result {
+        Ok(calibration) => calibration,
+        Err(e) => {
+            println!("\nError: {}", e);
+            return;
+        }
+    };
+    println!();
+    match calibration.half_width {
+        Some(half_width) => println!(
+            "Fitted {}: {:.6} ± {:.6} (95% CI)",
+            parameter, calibration.value, half_width
+        ),
+        None => println!("Fitted {}: {:.6}", parameter, calibration.value),
+    }
+    println!(
+        "Simulated {} at the fit: {:.4} ± {:.4} (observed {})",
+        metric, calibration.estimate.mean, calibration.estimate.half_width, target
+    );
+    println!(
+        "Simulations run: {} ({} candidates × {} replications) in {:.2}s",
+        calibration.evaluations.len() * replications,
+        calibration.evaluations.len(),
+        replications,
+        elapsed_secs
+    );
+    if !calibration.converged {
+        println!(
+            "!!! WARNING: no candidate came within {} of the target in {} iterations; the fit is the closest one",
+            tolerance, max_iterations
+        );
+    }
+}
+
+/// Run `config` with `seeds` consecutive seeds, print the spread of every metric
+/// and write the per-seed values to `path`
+///
+/// The seeds start at the configured one, or at a random one printed for reproduction.
+/// With a cost model the seeds are also ranked by their cost per time unit.
+fn run_seed_scan(
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    seeds: usize,
+    new_time_series: impl Fn() -> SimulationTimeSeries,
+    path: &Path,
+    cv_threshold: f64,
+    cost_model: CostModel,
+) {
+    let base_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    println!("=== Seed Scan ({} seeds from {}) ===", seeds, base_seed);
+
+    let mut scan = SeedScan::new(SeedScan::metrics_for(config)).with_cost_model(cost_model);
+    // One simulation reset for every seed, so its buffers are only allocated once
+    let mut simulation = Simulation::new(config.clone());
+    for i in 0..seeds as u64 {
+        let seed = base_seed.wrapping_add(i);
+        simulation.reset(SimulationConfig {
+            seed: Some(seed),
+            ..config.clone()
+        });
+        run_simulation(
+            &mut simulation,
+            stop_condition,
+            new_time_series(),
+            None,
+            None,
+            None,
+        );
+        scan.record(seed, &simulation);
+
+        let run = scan.runs().last().expect("just recorded");
+        let mut values: Vec<String> = scan
+            .metrics()
+            .iter()
+            .zip(&run.values)
+            .map(|(metric, value)| format!("{} {:.4}", metric, value))
+            .collect();
+        if let Some(cost) = run.cost {
+            values.push(format!("cost {:.4}", cost));
+        }
+        println!("Seed {}: {}", seed, values.join(", "));
+    }
+
+    let Some(spreads) = scan.spreads(HISTOGRAM_BINS) else {
+        return;
+    };
+    println!();
+    println!(
+        "{:<14} {:>10} {:>10} {:>10} {:>10} {:>8}  {:<width$}",
+        "metric",
+        "min",
+        "max",
+        "mean",
+        "std dev",
+        "CV",
+        "histogram",
+        width = HISTOGRAM_BINS
+    );
+    let mut flagged = 0;
+    for spread in &spreads {
+        let cv = spread.coefficient_of_variation();
+        let unstable = cv.is_some_and(|cv| cv > cv_threshold);
+        flagged += usize::from(unstable);
+        println!(
+            "{:<14} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>8} {}{}",
+            spread.metric,
+            spread.min,
+            spread.max,
+            spread.mean,
+            spread.std_dev,
+            cv.map_or("-".to_string(), |cv| format!("{:.2}%", cv * 100.0)),
+            sparkline(&spread.histogram),
+            if unstable { "  !" } else { "" }
+        );
+    }
+    if flagged > 0 {
+        println!(
+            "! {} metric(s) vary by more than {:.2}% across seeds; a single run does not pin them down",
+            flagged,
+            cv_threshold * 100.0
+        );
+    }
+
+    let ranked = scan.ranked_by_cost();
+    if let Some(dearest) = ranked.last() {
+        println!();
+        println!("Seeds by cost per time unit, cheapest first:");
+        for run in ranked.iter().take(COST_RANKING_ROWS) {
+            println!(
+                "  seed {:<20} {:.4}",
+                run.seed,
+                run.cost.unwrap_or(f64::NAN)
+            );
+        }
+        if ranked.len() > COST_RANKING_ROWS {
+            println!(
+                "  ... dearest: seed {} at {:.4}",
+                dearest.seed,
+                dearest.cost.unwrap_or(f64::NAN)
+            );
+        }
+    }
+
+    match scan.write_csv(path) {
+        Ok(()) => println!("Wrote the per-seed metrics to {}", path.display()),
+        Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+    }
+}
+
+/// Run a pilot of `pilot_customers` and print how many customers a run of `config`
+/// needs for the mean wait's interval to be within `target` of it
+fn run_length_plan(config: &SimulationConfig, pilot_customers: u64, target: f64) {
+    println!(
+        "=== Run Length Plan (pilot of {} customers) ===",
+        pilot_customers
+    );
+    let plan = match plan_run_length(config, pilot_customers, target) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Pilot mean wait: {:.4} ± {:.4} ({:.2}% of the mean)",
+        plan.mean_wait,
+        plan.relative_half_width * plan.mean_wait,
+        plan.relative_half_width * 100.0
+    );
+    println!(
+        "Variance inflation from correlated waits: {:.1}, worth {:.0} independent waits of {}",
+        plan.variance_inflation,
+        plan.effective_sample_size(),
+        plan.pilot_customers
+    );
+    if plan.pilot_suffices() {
+        println!(
+            "The pilot already reaches ±{:.2}%; {} customers are enough",
+            target * 100.0,
+            plan.required_customers
+        );
+    } else {
+        println!(
+            "For ±{:.2}% of the mean wait, serve about {} customers{}",
+            target * 100.0,
+            plan.required_customers,
+            if config.warm_up_time > 0.0 {
+                " after the warm-up"
+            } else {
+                ""
+            }
+        );
+    }
+    println!(
+        "The estimate comes from the pilot's own interval and is good to a few tens of percent"
+    );
+}
+
+/// Run `days` independent days of `config`, each from its initial state up to
+/// `horizon`, and print the cross-day mean and confidence interval of the daily metrics
+///
+/// Unlike a steady-state run the start-up is what is being measured, so no warm-up
+/// is discarded. Customers still inside at the end of a day are not counted.
+fn run_terminating_days(config: &SimulationConfig, horizon: f64, days: usize) {
+    let base_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    println!(
+        "=== Terminating Simulation ({} days of length {:.0}, seeds from {}) ===",
+        days, horizon, base_seed
+    );
+    if config.warm_up_time > 0.0 {
+        println!("Ignoring the warm-up period: every day is observed from its start");
+    }
+    let config = SimulationConfig {
+        warm_up_time: 0.0,
+        ..config.clone()
+    };
+
+    let mut scan = SeedScan::new(SeedScan::metrics_for(&config));
+    let stop_condition = StopCondition::Time(horizon);
+    // One simulation reset for every day, so its buffers are only allocated once
+    let mut simulation = Simulation::new(config.clone());
+    for i in 0..days as u64 {
+        let seed = base_seed.wrapping_add(i);
+        simulation.reset(SimulationConfig {
+            seed: Some(seed),
+            ..config.clone()
+        });
+        simulation.run(&stop_condition, &mut ());
+        scan.record(seed, &simulation);
+    }
+
+    let Some(spreads) = scan.spreads(HISTOGRAM_BINS) else {
+        return;
+    };
+    println!(
+        "{:<14} {:>10} {:>21} {:>10} {:>10} {:>10}",
+        "metric", "mean", "95% CI", "std dev", "min", "max"
+    );
+    for spread in &spreads {
+        let half_width = spread.confidence_half_width().unwrap_or(0.0);
+        println!(
+            "{:<14} {:>10.4} {:>21} {:>10.4} {:>10.4} {:>10.4}",
+            spread.metric,
+            spread.mean,
+            format!(
+                "[{:.4}, {:.4}]",
+                spread.mean - half_width,
+                spread.mean + half_width
+            ),
+            spread.std_dev,
+            spread.min,
+            spread.max
+        );
+    }
+
+    let Some(waits) = spreads
+        .iter()
+        .find(|spread| spread.metric == VerifiedMetric::Wait)
+    else {
+        return;
+    };
+    println!();
+    println!("Daily mean waits:");
+    let width = (waits.max - waits.min) / waits.histogram.len() as f64;
+    let highest = waits.histogram.iter().copied().max().unwrap_or(0).max(1);
+    for (bin, &count) in waits.histogram.iter().enumerate() {
+        let low = waits.min + bin as f64 * width;
+        println!(
+            "  {:>10.4} - {:<10.4} {:<40} {}",
+            low,
+            low + width,
+            "#".repeat(count * 40 / highest),
+            count
+        );
+    }
+}
+
+/// Run `config` under FIFO and earliest deadline first with `seeds` consecutive
+/// seeds and print how the deadline metrics differ between the two
+fn run_discipline_comparison(
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    seeds: usize,
+) {
+    let base_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    println!(
+        "=== FIFO vs EDF ({} seed pairs from {}) ===",
+        seeds, base_seed
+    );
+    let result = compare_disciplines(config, seeds, base_seed, |simulation| {
+        simulation.run(stop_condition, &mut ());
+    });
+    let comparisons = match result {
+        Ok(comparisons) => comparisons,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    println!(
+        "{:<15} {:>10} {:>10} {:>21}",
+        "metric", "FIFO", "EDF", "EDF - FIFO (95% CI)"
+    );
+    for comparison in &comparisons {
+        println!(
+            "{:<15} {:>10.4} {:>10.4} {:>+10.4} ± {:<8.4}{}",
+            comparison.metric,
+            comparison.fifo,
+            comparison.edf,
+            comparison.difference,
+            comparison.half_width,
+            if comparison.is_significant() {
+                " *"
+            } else {
+                ""
+            }
+        );
+    }
+    println!("* the interval excludes zero; both runs of a pair serve the same customers");
+}
+
+/// Run the 2^k design of `factors` around `config` and print the effects on the
+/// mean wait, and the cells ranked by cost if `cost_model` sets a rate; every run
+/// goes to `path`
+fn run_factorial_experiment(
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    factors: &[Factor],
+    replications: usize,
+    center_points: usize,
+    path: &Path,
+    cost_model: &CostModel,
+) {
+    let base_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    println!(
+        "=== 2^{} factorial experiment ({} seeds per cell from {}) ===",
+        factors.len(),
+        replications,
+        base_seed
+    );
+    let levels = |coded: &dyn Fn(usize) -> f64| {
+        factors
+            .iter()
+            .enumerate()
+            .map(|(j, factor)| format!("{}={}", factor.name(), factor.level_label(coded(j))))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let result = run_experiment(
+        config,
+        factors,
+        replications,
+        center_points,
+        base_seed,
+        cost_model,
+        |simulation| {
+            simulation.run(stop_condition, &mut ());
+        },
+        |progress| match progress {
+            Progress::Cell { index, cells } => println!(
+                "Cell {} of {}: {}",
+                index,
+                cells,
+                levels(&|j| coded_level(index - 1, j))
+            ),
+            Progress::CenterPoints(n) => {
+                println!("{} center points: {}", n, levels(&|_| 0.0))
+            }
+        },
+    );
+    let experiment = match result {
+        Ok(experiment) => experiment,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+
+    println!();
+    println!("Effects on the mean wait:");
+    println!("{:<24} {:>21}", "term", "effect (95% CI)");
+    for effect in &experiment.effects {
+        println!(
+            "{:<24} {:>+10.4} ± {:<8.4}{}",
+            effect.term.label(factors),
+            effect.estimate,
+            effect.half_width,
+            if effect.is_significant() { " *" } else { "" }
+        );
+    }
+    println!("* the interval excludes zero; all cells of a replication share its seed");
+    if let Some(curvature) = experiment.curvature {
+        println!(
+            "Curvature: center {:.4} against the design's {:.4}, difference {:+.4} ± {:.4}{}",
+            curvature.center_mean,
+            curvature.factorial_mean,
+            curvature.difference(),
+            curvature.half_width,
+            if curvature.is_significant() { " *" } else { "" }
+        );
+    }
+    let ranked = experiment.cells_by_cost();
+    if !ranked.is_empty() {
+        println!();
+        println!("Cells by mean cost per time unit, cheapest first:");
+        for (cell, cost) in ranked.iter().take(COST_RANKING_ROWS) {
+            let label = match cell {
+                Some(cell) => levels(&|j| coded_level(*cell, j)),
+                None => format!("center: {}", levels(&|_| 0.0)),
+            };
+            println!("  {:>10.4}  {}", cost, label);
+        }
+    }
+    match experiment.write_csv(path) {
+        Ok(()) => println!(
+            "Wrote the {} runs to {}",
+            experiment.runs.len(),
+            path.display()
+        ),
+        Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+    }
+}
+
+/// Whether `config` is M/G/1 apart from its queue discipline, so the processor-sharing
+/// and Pollaczek-Khinchine formulas describe it
+pub fn has_mg1_theory(config: &SimulationConfig) -> bool {
+    let exponential_fifo = SimulationConfig {
+        service_kind: ServiceKind::Exponential,
+        discipline: QueueDiscipline::Fifo,
+        ..config.clone()
+    };
+    config.servers == 1 && report::theory_mismatch(&exponential_fifo).is_none()
+}
+
+/// Run `config` under FIFO and round robin with each of `quanta` for
+/// [`QUANTUM_COMPARISON_SEEDS`] seeds and print them against processor sharing
+fn run_quantum_comparison(
+    config: &SimulationConfig,
+    stop_condition: &StopCondition,
+    quanta: &[f64],
+) {
+    let base_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    println!(
+        "=== FIFO vs round robin ({} seeds from {}) ===",
+        QUANTUM_COMPARISON_SEEDS, base_seed
+    );
+    let result = compare_quanta(
+        config,
+        quanta,
+        QUANTUM_COMPARISON_SEEDS,
+        base_seed,
+        |simulation| {
+            simulation.run(stop_condition, &mut ());
+        },
+    );
+    let outcomes = match result {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    println!(
+        "{:<12} {:>12} {:>13} {:>15} {:>10} {:>13} {:>13}",
+        "discipline",
+        "sojourn",
+        "P50 slowdown",
+        "sojourn/service",
+        "events",
+        "P95 slowdown",
+        "max slowdown"
+    );
+    for outcome in &outcomes {
+        let discipline = match outcome.quantum {
+            Some(quantum) => format!("RR q={}", quantum),
+            None => "FIFO".to_string(),
+        };
+        println!(
+            "{:<12} {:>12.4} {:>13.4} {:>15.4} {:>9.2}x {:>13.4} {:>13.4}",
+            discipline,
+            outcome.mean_sojourn,
+            outcome.median_slowdown,
+            outcome.sojourn_per_service,
+            outcome.event_inflation,
+            outcome.p95_slowdown,
+            outcome.max_slowdown
+        );
+    }
+
+    if has_mg1_theory(config) {
+        let mean_service = 1.0 / config.mu;
+        if let Some(sojourn) = mg1_processor_sharing_sojourn(config.lambda, mean_service) {
+            println!(
+                "{:<12} {:>12.4} {:>13.4} {:>15.4}",
+                "PS theory",
+                sojourn,
+                sojourn / mean_service,
+                sojourn / mean_service
+            );
+        }
+        if let Some(wait) = allen_cunneen_wait(
+            1,
+            1.0 / config.lambda,
+            mean_service,
+            1.0,
+            config.service_kind.scv(),
+        ) {
+            println!(
+                "FIFO theory: sojourn {:.4} (Pollaczek-Khinchine)",
+                wait + mean_service
+            );
+        }
+        println!("Processor sharing is the limit of round robin as the quantum goes to zero.");
+    }
+}
+
+/// One block character per bin, its height proportional to the bin's count
+fn sparkline(counts: &[usize]) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => LEVELS[(count * LEVELS.len()).div_ceil(highest) - 1],
+        })
+        .collect()
+}
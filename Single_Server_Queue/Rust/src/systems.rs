@@ -0,0 +1,326 @@
+use crate::prompt::{Prompter, non_negative, positive};
+use crate::questions::{
+    EXTERNAL_RATE_HELP, LAMBDA_HELP, MU_HELP, OVERFLOW_QUEUE_HELP, OVERFLOW_SERVERS_HELP,
+    ROUTING_HELP, STATION_MU_HELP, STATIONS_HELP, TIME_HELP, WARM_UP_HELP,
+};
+use rust_single_server_queue::distributions::{Distribution, Exponential};
+use rust_single_server_queue::network::{NetworkConfig, NetworkSimulation, validate_routing_row};
+use rust_single_server_queue::overflow::{
+    OverflowConfig, OverflowPath, OverflowSimulation, OverflowStation,
+};
+use rust_single_server_queue::rng::RngBackend;
+use rust_single_server_queue::theory::mmck_blocking;
+use std::io::{BufRead, Write};
+use std::time::Instant;
+
+/// Ask for an open Jackson network, run it for a fixed time and compare with the product form
+pub fn run_network<R: BufRead, W: Write>(prompter: &mut Prompter<R, W>, rng_backend: RngBackend) {
+    let (config, arrival_rates) = loop {
+        let config = read_network(prompter, rng_backend);
+        match config.validate() {
+            Ok(arrival_rates) => break (config, arrival_rates),
+            Err(e) => println!("  Error: {}. Please enter the network again.", e),
+        }
+    };
+    let time = prompter.read_f64_with_default("Simulation time", 1_000_000.0, TIME_HELP, positive);
+    let warm_up_time = prompter.read_f64_with_default(
+        "Warm-up period (0 = none)",
+        0.0,
+        WARM_UP_HELP,
+        non_negative,
+    );
+
+    println!();
+    println!("=== Open Jackson Network Simulation ===");
+    println!("Stations: {}", config.stations());
+    println!("Random generator: {}", rng_backend);
+    println!();
+
+    let mut network = NetworkSimulation::new(NetworkConfig {
+        warm_up_time,
+        ..config
+    })
+    .expect("the network was validated");
+    let start_time = Instant::now();
+    network.run_until(time);
+    let elapsed_secs = start_time.elapsed().as_secs_f64();
+
+    print_network_results(&network, &arrival_rates, elapsed_secs);
+}
+
+/// Ask for the stations and routing matrix of a network
+///
+/// The defaults form a chain in which 20% of the customers leaving the last
+/// station go back to the first.
+fn read_network<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    rng_backend: RngBackend,
+) -> NetworkConfig {
+    let stations =
+        prompter.read_u64_with_default("Number of stations", 3, STATIONS_HELP, positive) as usize;
+
+    let mut external_arrival_rates = Vec::with_capacity(stations);
+    let mut service_rates = Vec::with_capacity(stations);
+    let mut routing = Vec::with_capacity(stations);
+    for i in 0..stations {
+        println!("\nStation {}:", i + 1);
+        external_arrival_rates.push(prompter.read_f64_with_default(
+            "  External arrival rate (γ)",
+            if i == 0 { 0.5 } else { 0.0 },
+            EXTERNAL_RATE_HELP,
+            non_negative,
+        ));
+        service_rates.push(prompter.read_f64_with_default(
+            "  Service rate (μ)",
+            1.0,
+            STATION_MU_HELP,
+            positive,
+        ));
+
+        let mut default_row = vec![0.0; stations];
+        if i + 1 < stations {
+            default_row[i + 1] = 1.0;
+        } else {
+            default_row[0] = 0.2;
+        }
+        routing.push(prompter.read_f64s_with_default(
+            "  Routing probabilities to each station",
+            &default_row,
+            ROUTING_HELP,
+            |row| validate_routing_row(row, stations),
+        ));
+    }
+
+    NetworkConfig {
+        external_arrival_rates,
+        service_rates,
+        routing,
+        warm_up_time: 0.0,
+        seed: None,
+        rng_backend,
+    }
+}
+
+/// Per-station results next to the product-form M/M/1 values, and the end-to-end sojourn time
+fn print_network_results(network: &NetworkSimulation, arrival_rates: &[f64], elapsed_secs: f64) {
+    let total_time = network.now();
+    let config = network.config();
+
+    println!("=== Network Results ===");
+    println!("Total simulation time: {:.2}", total_time);
+    println!("Events processed: {}", network.event_count());
+    println!("Customers completed: {}", network.departed_customers());
+    println!();
+    println!(
+        "{:<8} {:>8} {:>17} {:>17} {:>17}",
+        "station", "λ", "utilization", "queue length", "wait"
+    );
+    println!(
+        "{:<8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "", "", "sim", "theory", "sim", "theory", "sim", "theory"
+    );
+
+    let mut expected_in_network = 0.0;
+    for (i, (&lambda, &mu)) in arrival_rates.iter().zip(&config.service_rates).enumerate() {
+        let stats = network.station_statistics(i);
+        let rho = lambda / mu;
+        expected_in_network += rho / (1.0 - rho);
+        println!(
+            "{:<8} {:>8.4} {:>8.4} {:>8.4} {:>8.4} {:>8.4} {:>8.4} {:>8.4}",
+            i + 1,
+            lambda,
+            stats.utilization(total_time),
+            rho,
+            stats.average_queue_length(total_time),
+            rho * rho / (1.0 - rho),
+            stats.average_wait_time(),
+            rho / (mu - lambda)
+        );
+    }
+
+    // Little's law over the whole network: E[T] = Σ L_i / Σ γ_i
+    let external_rate: f64 = config.external_arrival_rates.iter().sum();
+    println!();
+    println!(
+        "End-to-end sojourn time: {:.4} (theory {:.4})",
+        network.average_sojourn_time(),
+        expected_in_network / external_rate
+    );
+    if let Some((mean, half_width)) = network.sojourn_confidence_interval() {
+        println!(
+            "Sojourn time 95% CI (batch means): {:.4} ± {:.4}",
+            mean, half_width
+        );
+    }
+
+    println!();
+    println!("=== Performance Metrics ===");
+    println!("Wall-clock time: {:.2}s", elapsed_secs);
+    println!(
+        "Events per second: {:.0}",
+        network.event_count() as f64 / elapsed_secs
+    );
+}
+
+/// Ask for an overflow system, run it for a fixed time and compare its loss with
+/// that of the primary station alone
+pub fn run_overflow<R: BufRead, W: Write>(prompter: &mut Prompter<R, W>, rng_backend: RngBackend) {
+    let arrival_rate =
+        prompter.read_f64_with_default("Arrival rate (λ)", 2.0, LAMBDA_HELP, positive);
+    println!("\nPrimary station:");
+    let primary = read_overflow_station(prompter, 2, 1.0, 2);
+    println!("\nSecondary (overflow) station:");
+    let secondary = read_overflow_station(prompter, 1, 1.0, 1);
+    let time = prompter.read_f64_with_default("Simulation time", 1_000_000.0, TIME_HELP, positive);
+    let warm_up_time = prompter.read_f64_with_default(
+        "Warm-up period (0 = none)",
+        0.0,
+        WARM_UP_HELP,
+        non_negative,
+    );
+
+    println!();
+    println!("=== Overflow System Simulation ===");
+    println!("Random generator: {}", rng_backend);
+    println!();
+
+    let mut system = match OverflowSimulation::new(OverflowConfig {
+        arrival_rate,
+        primary,
+        secondary,
+        warm_up_time,
+        seed: None,
+        rng_backend,
+    }) {
+        Ok(system) => system,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let start_time = Instant::now();
+    system.run_until(time);
+    let elapsed_secs = start_time.elapsed().as_secs_f64();
+
+    print_overflow_results(&system, elapsed_secs);
+}
+
+/// Ask for the servers, service rate and waiting room of one station of an overflow system
+fn read_overflow_station<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    default_servers: u64,
+    default_mu: f64,
+    default_queue: u64,
+) -> OverflowStation {
+    let servers = prompter.read_u64_with_default(
+        "  Number of servers (c)",
+        default_servers,
+        OVERFLOW_SERVERS_HELP,
+        positive,
+    ) as usize;
+    let service_rate =
+        prompter.read_f64_with_default("  Service rate (μ)", default_mu, MU_HELP, |mu| {
+            Exponential::new(mu).validate()
+        });
+    let queue_capacity = prompter.read_u64_with_default(
+        "  Waiting places (K)",
+        default_queue,
+        OVERFLOW_QUEUE_HELP,
+        non_negative,
+    ) as usize;
+    OverflowStation {
+        servers,
+        service_rate,
+        queue_capacity,
+    }
+}
+
+/// Customers and waits per path, the loss with and without the secondary station,
+/// and how far the overflow stream is from Poisson
+fn print_overflow_results(system: &OverflowSimulation, elapsed_secs: f64) {
+    let total_time = system.now();
+    let config = system.config();
+    let lambda = config.arrival_rate;
+    let primary = system.statistics(OverflowPath::Primary);
+    let secondary = system.statistics(OverflowPath::Secondary);
+
+    println!("=== Overflow Results ===");
+    println!("Total simulation time: {:.2}", total_time);
+    println!("Events processed: {}", system.event_count());
+    println!("Customers arrived: {}", system.arrivals());
+    println!();
+    println!(
+        "{:<10} {:>10} {:>8} {:>10} {:>12} {:>12}",
+        "path", "customers", "share", "wait", "utilization", "in system"
+    );
+    let arrivals = system.arrivals().max(1) as f64;
+    let primary_served = system.arrivals() - system.overflowed();
+    let secondary_served = system.overflowed() - system.lost();
+    for (name, customers, stats) in [
+        ("primary", primary_served, Some(primary)),
+        ("overflow", secondary_served, Some(secondary)),
+        ("lost", system.lost(), None),
+    ] {
+        match stats {
+            Some(stats) => println!(
+                "{:<10} {:>10} {:>8.4} {:>10.4} {:>12.4} {:>12.4}",
+                name,
+                customers,
+                customers as f64 / arrivals,
+                stats.average_wait_time(),
+                stats.utilization(total_time),
+                stats.average_customers_in_system(total_time)
+            ),
+            None => println!(
+                "{:<10} {:>10} {:>8.4}",
+                name,
+                customers,
+                customers as f64 / arrivals
+            ),
+        }
+    }
+
+    // The primary station alone is M/M/c/K and loses exactly the customers that
+    // overflow here. Were the overflow Poisson, the secondary would lose its own
+    // M/M/c/K share of them; the measured loss shows how far that is off.
+    let station_blocking = |station: &OverflowStation, rate: f64| {
+        mmck_blocking(
+            station.servers,
+            station.queue_capacity,
+            rate / station.service_rate,
+        )
+    };
+    let alone = station_blocking(&config.primary, lambda);
+    let poisson_loss = alone * station_blocking(&config.secondary, lambda * alone);
+    println!();
+    println!("{:<48} {:>10} {:>10}", "Loss probability", "sim", "theory");
+    println!(
+        "{:<48} {:>10.6} {:>10.6}",
+        "  without the secondary station",
+        system.overflow_probability(),
+        alone
+    );
+    println!(
+        "{:<48} {:>10.6} {:>10.6}",
+        "  with it (theory: if the overflow were Poisson)",
+        system.loss_probability(),
+        poisson_loss
+    );
+    match system.overflow_scv() {
+        Some(scv) => println!(
+            "\nOverflow stream SCV of interarrival times: {:.4} (Poisson: 1)\n\
+             The primary overflows in bursts while it stays full, so the stream is peaked\n\
+             and the secondary usually loses more than a Poisson stream of the same rate would.",
+            scv
+        ),
+        None => println!("\nToo few customers overflowed to measure the SCV of the stream."),
+    }
+
+    println!();
+    println!("=== Performance Metrics ===");
+    println!("Wall-clock time: {:.2}s", elapsed_secs);
+    println!(
+        "Events per second: {:.0}",
+        system.event_count() as f64 / elapsed_secs
+    );
+}
@@ -0,0 +1,419 @@
+//! Closed-form results the simulation output is checked against
+//!
+//! None of them needs a simulation, so they serve just as well on their own, e.g.
+//! [`erlang_c`] for the number of agents that keeps the chance of waiting below a target.
+
+/// Erlang-B blocking probability of an M/M/c/c system with `offered_load` = λ/μ Erlangs
+///
+/// Uses the recursion B(0) = 1, B(k) = a·B(k-1) / (k + a·B(k-1)). Every step stays
+/// in [0, 1], so unlike the textbook a^c/c! / Σ a^k/k! form it neither overflows
+/// nor loses precision for hundreds of servers.
+pub fn erlang_b(servers: usize, offered_load: f64) -> f64 {
+    (1..=servers).fold(1.0, |blocking, k| {
+        let carried = offered_load * blocking;
+        carried / (k as f64 + carried)
+    })
+}
+
+/// Blocking probability of an M/M/c/K queue: `servers` servers, `queue_places`
+/// waiting places and offered load a = λ/μ
+///
+/// Below c the states follow the Erlang loss distribution and every waiting place
+/// beyond multiplies by r = a/c, so with B = B(c, a) the full state has probability
+/// B·r^K / (1 + B·Σ_{j=1}^{K} r^j). For r > 1 numerator and denominator are divided
+/// by r^K so the powers shrink instead. Without waiting places this is Erlang B.
+pub fn mmck_blocking(servers: usize, queue_places: usize, offered_load: f64) -> f64 {
+    let b = erlang_b(servers, offered_load);
+    let r = offered_load / servers as f64;
+    if r <= 1.0 {
+        let (tail, top) = (0..queue_places).fold((0.0, 1.0), |(sum, power), _| {
+            let power = power * r;
+            (sum + power, power)
+        });
+        b * top / (1.0 + b * tail)
+    } else {
+        let shrink = r.recip();
+        let (tail, top) =
+            (0..queue_places).fold((0.0, 1.0), |(sum, power), _| (sum + power, power * shrink));
+        b / (top + b * tail)
+    }
+}
+
+/// Steady-state P_n of an M/M/1 queue, (1 - ρ)ρ^n for n < `len`, or `None` if ρ >= 1
+pub fn mm1_customers_distribution(rho: f64, len: usize) -> Option<Vec<f64>> {
+    if rho >= 1.0 {
+        return None;
+    }
+    Some(
+        std::iter::successors(Some(1.0 - rho), |p| Some(p * rho))
+            .take(len)
+            .collect(),
+    )
+}
+
+/// Coefficient of variation of the wait in queue of an M/M/1 queue, or `None` if ρ >= 1
+///
+/// The wait is 0 with probability 1 - ρ and otherwise exponential with rate μ - λ,
+/// so E[W] = ρ/(μ - λ), Var[W] = ρ(2 - ρ)/(μ - λ)² and the ratio only depends on ρ.
+pub fn mm1_wait_cv(rho: f64) -> Option<f64> {
+    (rho > 0.0 && rho < 1.0).then(|| ((2.0 - rho) / rho).sqrt())
+}
+
+/// Time t with P(Wq ≤ t) = `p` in an M/M/1 queue, or `None` if ρ >= 1
+///
+/// The wait is 0 with probability 1 - ρ and P(Wq > t) = ρ·e^{-(μ - λ)t} beyond
+/// that, so every quantile up to 1 - ρ is 0.
+pub fn mm1_wait_quantile(lambda: f64, mu: f64, p: f64) -> Option<f64> {
+    let rho = lambda / mu;
+    if rho >= 1.0 {
+        return None;
+    }
+    Some(if p <= 1.0 - rho {
+        0.0
+    } else {
+        (rho / (1.0 - p)).ln() / (mu - lambda)
+    })
+}
+
+/// P(Wq ≤ t) in an M/M/1 queue, 1 - ρ·e^{-(μ - λ)t} from t = 0 on, or `None` if ρ >= 1
+///
+/// The CDF jumps from 0 to 1 - ρ at t = 0, the share of customers who find the
+/// server idle; it is 0 for every negative t.
+pub fn mm1_wait_cdf(lambda: f64, mu: f64, t: f64) -> Option<f64> {
+    let rho = lambda / mu;
+    if rho >= 1.0 {
+        return None;
+    }
+    Some(if t < 0.0 {
+        0.0
+    } else {
+        1.0 - rho * (-(mu - lambda) * t).exp()
+    })
+}
+
+/// Steady-state P_n of an M/M/1/K queue holding at most `capacity` customers, n = 0..=K
+///
+/// The M/M/1 geometric distribution renormalized over 0..=K, so it exists for
+/// every ρ; at ρ = 1 all states are equally likely.
+pub fn mm1k_customers_distribution(rho: f64, capacity: usize) -> Vec<f64> {
+    let weights: Vec<f64> = std::iter::successors(Some(1.0), |w| Some(w * rho))
+        .take(capacity + 1)
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Mean wait in queue of an M/M/1 queue whose server fails while busy, or `None` if unstable
+///
+/// Failures arrive at rate f = 1/MTTF during service and each repair is
+/// exponential with mean r, so a service S is stretched into a completion time T
+/// with E[T] = (1 + f·r)/μ and E[T²] = 2(1 + f·r)²/μ² + 2f·r²/μ. The queue is then
+/// M/G/1 and the Pollaczek-Khinchine formula gives W = λ·E[T²] / (2(1 - λ·E[T])).
+pub fn mm1_breakdown_wait(
+    lambda: f64,
+    mu: f64,
+    mean_time_to_failure: f64,
+    mean_repair_time: f64,
+) -> Option<f64> {
+    let failure_rate = 1.0 / mean_time_to_failure;
+    let stretch = 1.0 + failure_rate * mean_repair_time;
+    let mean = stretch / mu;
+    let second_moment =
+        2.0 * stretch * stretch / (mu * mu) + 2.0 * failure_rate * mean_repair_time.powi(2) / mu;
+
+    let load = lambda * mean;
+    (load < 1.0).then(|| lambda * second_moment / (2.0 * (1.0 - load)))
+}
+
+/// Extra mean wait in an M/G/1 queue whose server needs a setup S at the start of every busy period
+///
+/// The wait decomposes into the Pollaczek-Khinchine wait without setups plus
+/// (2E[S] + λE[S²]) / (2(1 + λE[S])), given the mean and second moment of the
+/// setup time. For exponential setups the extra term is just E[S].
+pub fn setup_extra_wait(lambda: f64, setup_mean: f64, setup_second_moment: f64) -> f64 {
+    (2.0 * setup_mean + lambda * setup_second_moment) / (2.0 * (1.0 + lambda * setup_mean))
+}
+
+/// Erlang-C probability that an arrival to an M/M/c queue has to wait, or `None` if unstable
+///
+/// Derived from Erlang-B as C = B / (1 - ρ(1 - B)) with ρ = a/c, which inherits
+/// its numerical stability.
+pub fn erlang_c(servers: usize, offered_load: f64) -> Option<f64> {
+    let rho = offered_load / servers as f64;
+    if rho >= 1.0 {
+        return None;
+    }
+    let blocking = erlang_b(servers, offered_load);
+    Some(blocking / (1.0 - rho * (1.0 - blocking)))
+}
+
+/// Allen-Cunneen approximation of the mean wait in a G/G/c queue, or `None` if unstable
+///
+/// Scales the exact M/M/c wait by the average of the squared coefficients of
+/// variation of interarrival (`ca2`) and service (`cs2`) times:
+/// W ≈ C(c, a)·E[S] / (c - a) · (ca² + cs²)/2 with a = E[S]/E[A]. For one server
+/// this is Kingman's formula W ≈ ρ/(1 - ρ) · (ca² + cs²)/2 · E[S], exact for M/G/1
+/// and a standard sanity anchor otherwise, most accurate in heavy traffic.
+pub fn allen_cunneen_wait(
+    servers: usize,
+    mean_interarrival: f64,
+    mean_service: f64,
+    ca2: f64,
+    cs2: f64,
+) -> Option<f64> {
+    let offered_load = mean_service / mean_interarrival;
+    let waiting = erlang_c(servers, offered_load)?;
+    Some(waiting * mean_service / (servers as f64 - offered_load) * (ca2 + cs2) / 2.0)
+}
+
+/// Mean sojourn of an M/G/1 processor-sharing queue, E[S]/(1 - ρ), or `None` if unstable
+///
+/// A customer needing service x expects to stay x/(1 - ρ) whatever the service
+/// distribution, so the sojourn grows linearly in the job size and the mean
+/// slowdown is 1/(1 - ρ) as well. Round robin approaches this as its quantum shrinks.
+pub fn mg1_processor_sharing_sojourn(lambda: f64, mean_service: f64) -> Option<f64> {
+    let rho = lambda * mean_service;
+    (rho < 1.0).then(|| mean_service / (1.0 - rho))
+}
+
+/// Iterations the G/M/1 root search may take before it gives up
+const GM1_MAX_ITERATIONS: usize = 200;
+
+/// The G/M/1 root σ, the unique solution in (0, 1) of σ = A*(μ(1 - σ)), or `None`
+/// if the queue is unstable or the search does not converge
+///
+/// `arrival_lst` is the Laplace-Stieltjes transform A*(s) = E[e^{-sA}] of the
+/// interarrival time, which has mean `mean_interarrival`. An arriving customer
+/// finds n others with probability (1 - σ)σ^n, so σ plays the part ρ plays in
+/// M/M/1, and is ρ for Poisson arrivals. σ = 1 always solves the equation;
+/// the root below it exists exactly when ρ = 1/(μ E[A]) < 1.
+///
+/// f(σ) = A*(μ(1 - σ)) - σ is positive below the root and negative between it
+/// and 1, so the search keeps a bracket around it and takes Newton steps, with
+/// the slope from a central difference, falling back to bisection whenever a
+/// step would leave the bracket.
+pub fn gm1_root(mean_interarrival: f64, mu: f64, arrival_lst: impl Fn(f64) -> f64) -> Option<f64> {
+    if !(mu * mean_interarrival > 1.0 && mu.is_finite() && mean_interarrival.is_finite()) {
+        return None;
+    }
+    let f = |sigma: f64| arrival_lst(mu * (1.0 - sigma)) - sigma;
+
+    // The upper end has to lie past the root, which comes close to 1 in heavy traffic
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut gap = 1e-3;
+    while f(1.0 - gap) >= 0.0 {
+        gap /= 10.0;
+        if gap < 1e-15 {
+            return None;
+        }
+    }
+    high -= gap;
+
+    let mut sigma = 0.5 * (low + high);
+    for _ in 0..GM1_MAX_ITERATIONS {
+        let value = f(sigma);
+        if value == 0.0 {
+            return Some(sigma);
+        }
+        if value > 0.0 {
+            low = sigma;
+        } else {
+            high = sigma;
+        }
+        if high - low <= 1e-14 {
+            return Some(0.5 * (low + high));
+        }
+
+        let h = 1e-7_f64.min(0.5 * (high - low));
+        let slope = (f(sigma + h) - f(sigma - h)) / (2.0 * h);
+        let newton = sigma - value / slope;
+        let next = if newton.is_finite() && newton > low && newton < high {
+            newton
+        } else {
+            0.5 * (low + high)
+        };
+        if (next - sigma).abs() <= 1e-15 {
+            return Some(next);
+        }
+        sigma = next;
+    }
+    None
+}
+
+/// Mean wait in queue of a G/M/1 queue, σ/(μ(1 - σ)), or `None` where [`gm1_root`] has no root
+///
+/// Exact for any renewal arrival process with exponential service, where the
+/// two-moment [`allen_cunneen_wait`] is only an approximation; D/M/1 at ρ = 0.5,
+/// for instance, waits 0.255/μ against Kingman's 0.5/μ.
+pub fn gm1_wait(mean_interarrival: f64, mu: f64, arrival_lst: impl Fn(f64) -> f64) -> Option<f64> {
+    let sigma = gm1_root(mean_interarrival, mu, arrival_lst)?;
+    Some(sigma / (mu * (1.0 - sigma)))
+}
+
+/// Steady-state P_n of an M/M/c/c loss system: a Poisson(a) distribution truncated at c
+pub fn loss_customers_distribution(servers: usize, offered_load: f64) -> Vec<f64> {
+    // a^n/n! built up term by term to avoid factorials
+    let mut weights = Vec::with_capacity(servers + 1);
+    let mut weight = 1.0;
+    weights.push(weight);
+    for n in 1..=servers {
+        weight *= offered_load / n as f64;
+        weights.push(weight);
+    }
+
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Total arrival rates of an open Jackson network from its traffic equations
+///
+/// Solves λ_j = γ_j + Σ_i λ_i·P[i][j] for the external rates γ and the routing
+/// matrix P by Gaussian elimination with partial pivoting. Returns `None` if the
+/// system is singular, which happens when customers can circulate forever
+/// without leaving.
+pub fn jackson_arrival_rates(external: &[f64], routing: &[Vec<f64>]) -> Option<Vec<f64>> {
+    let n = external.len();
+    // Augmented matrix of (I - Pᵀ) λ = γ
+    let mut a: Vec<Vec<f64>> = (0..n)
+        .map(|j| {
+            let mut row: Vec<f64> = (0..n)
+                .map(|i| if i == j { 1.0 } else { 0.0 } - routing[i][j])
+                .collect();
+            row.push(external[j]);
+            row
+        })
+        .collect();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for (row, values) in a.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, pivot_value) in values.iter_mut().zip(&pivot_row).skip(col) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    Some((0..n).map(|j| a[j][n] / a[j][j]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServiceKind;
+
+    #[test]
+    fn erlang_b_matches_tabulated_values() {
+        assert!((erlang_b(10, 5.0) - 0.018_385).abs() < 5e-7);
+        assert!((erlang_b(5, 2.0) - 0.036_697).abs() < 5e-7);
+        // B(1, a) = a / (1 + a)
+        assert!((erlang_b(1, 3.0) - 0.75).abs() < 1e-15);
+        assert_eq!(erlang_b(0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn erlang_c_matches_tabulated_values() {
+        assert!((erlang_c(2, 1.0).unwrap() - 1.0 / 3.0).abs() < 1e-15);
+        assert!((erlang_c(10, 8.0).unwrap() - 0.409_180).abs() < 5e-7);
+        // A single server delays a share of ρ
+        for rho in [0.1, 0.5, 0.95] {
+            assert!((erlang_c(1, rho).unwrap() - rho).abs() < 1e-15);
+        }
+        assert_eq!(erlang_c(3, 3.0), None);
+    }
+
+    #[test]
+    fn hundreds_of_servers_stay_finite() {
+        let blocking = erlang_b(1_000, 950.0);
+        assert!(blocking > 0.0 && blocking < 1.0, "{}", blocking);
+        let waiting = erlang_c(1_000, 950.0).unwrap();
+        assert!(waiting > blocking && waiting < 1.0, "{}", waiting);
+    }
+
+    #[test]
+    fn no_waiting_places_is_erlang_b() {
+        for (servers, load) in [(1, 0.5), (4, 3.0), (10, 12.0)] {
+            assert!((mmck_blocking(servers, 0, load) - erlang_b(servers, load)).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn poisson_arrivals_make_the_gm1_root_rho() {
+        for rho in [0.1, 0.5, 0.9, 0.99] {
+            let mean_interarrival = 1.0 / rho;
+            let sigma = gm1_root(mean_interarrival, 1.0, |s| {
+                ServiceKind::Exponential
+                    .laplace_transform(mean_interarrival, s)
+                    .unwrap()
+            })
+            .unwrap();
+            assert!((sigma - rho).abs() < 1e-12, "ρ = {}: {}", rho, sigma);
+        }
+    }
+
+    #[test]
+    fn deterministic_arrivals_at_half_load_wait_a_quarter_of_a_service() {
+        // σ = e^{-2(1 - σ)} for D/M/1 with μ = 1 and interarrival time 2
+        let lst = |s: f64| {
+            ServiceKind::Deterministic
+                .laplace_transform(2.0, s)
+                .unwrap()
+        };
+        let sigma = gm1_root(2.0, 1.0, lst).unwrap();
+        assert!((sigma - 0.203_188).abs() < 1e-6, "{}", sigma);
+        assert!((sigma - (-2.0 * (1.0 - sigma)).exp()).abs() < 1e-12);
+        // The same queue at μ = 4 waits a quarter as long
+        let wait = gm1_wait(0.5, 4.0, |s| {
+            ServiceKind::Deterministic
+                .laplace_transform(0.5, s)
+                .unwrap()
+        })
+        .unwrap();
+        assert!((wait * 4.0 - 0.255).abs() < 1e-3, "{}", wait);
+    }
+
+    #[test]
+    fn an_unstable_gm1_queue_has_no_root() {
+        let lst = |s: f64| ServiceKind::Erlang(2).laplace_transform(1.0, s).unwrap();
+        assert_eq!(gm1_root(1.0, 1.0, lst), None);
+        assert_eq!(gm1_root(1.0, 0.8, lst), None);
+        assert_eq!(gm1_wait(1.0, 0.5, lst), None);
+    }
+
+    #[test]
+    fn the_gm1_root_is_found_in_heavy_traffic() {
+        for (kind, rho) in [
+            (ServiceKind::Deterministic, 0.999),
+            (ServiceKind::Erlang(3), 0.99999),
+            (ServiceKind::Gamma(0.5), 0.999_999),
+        ] {
+            let mean_interarrival = 1.0 / rho;
+            let lst = |s: f64| kind.laplace_transform(mean_interarrival, s).unwrap();
+            let sigma = gm1_root(mean_interarrival, 1.0, lst).unwrap();
+            assert!(sigma < 1.0, "{}: {}", kind, sigma);
+            assert!(
+                (lst(1.0 - sigma) - sigma).abs() < 1e-12,
+                "{}: {}",
+                kind,
+                sigma
+            );
+            // Kingman's heavy-traffic limit, (ca² + 1)/2 · ρ/(1 - ρ) services
+            let kingman = (kind.scv() + 1.0) / 2.0 * rho / (1.0 - rho);
+            let wait = gm1_wait(mean_interarrival, 1.0, lst).unwrap();
+            assert!(
+                (wait / kingman - 1.0).abs() < 0.01,
+                "{}: {} against {}",
+                kind,
+                wait,
+                kingman
+            );
+        }
+    }
+}
@@ -1,19 +1,106 @@
-#[derive(Debug, Clone)]
+use crate::event::EventType;
+use crate::statistics::{Statistics, Totals};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Samples a series gets when no target is given
+pub const DEFAULT_MAX_SAMPLES: usize = 5_000;
+
+/// Metric values sampled on a time grid whose spacing adapts to the run length
+///
+/// Samples are taken at the first event at or after each grid time `k * interval`:
+/// the comparison is inclusive, so an event landing exactly on a grid time is
+/// sampled, and as the first event is at t >= 0 the grid time 0 always yields a
+/// sample of the empty initial state. [`TimeSeries::skip_initial_sample`] starts
+/// the grid at `k = 1` instead. Once `max_samples` are stored and another is due, the interval doubles and
+/// every second sample is dropped, so the kept samples lie on the coarser grid
+/// again. Memory stays bounded and the samples cover the whole run evenly, however
+/// long it turns out to be; the initial interval only sets the finest resolution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeSeries<T> {
     data: Vec<(f64, T)>, // (time, value)
+    initial_interval: f64,
     sample_interval: f64,
     next_sample_time: f64,
+    max_samples: usize,
+    limit: usize, // samples beyond this are dropped
+    skip_initial: bool,
 }
 
 impl<T: Clone> TimeSeries<T> {
-    pub fn new(sample_interval: f64, max_samples: usize) -> Self {
+    /// `max_samples` must be at least 2 so thinning the samples frees room
+    pub fn new(initial_interval: f64, max_samples: usize) -> Self {
+        assert!(
+            max_samples >= 2,
+            "an adaptive series needs room for 2 samples"
+        );
         Self {
             data: Vec::with_capacity(max_samples),
-            sample_interval,
+            initial_interval,
+            sample_interval: initial_interval,
             next_sample_time: 0.0,
+            max_samples,
+            limit: usize::MAX,
+            skip_initial: false,
         }
     }
 
+    /// Leave out the sample at t = 0, so the first one is taken at `initial_interval`
+    ///
+    /// The t = 0 sample only shows the empty system and drags plots of running
+    /// averages towards zero at the start.
+    pub fn skip_initial_sample(mut self) -> Self {
+        self.skip_initial = true;
+        self.next_sample_time = self.first_grid_index() * self.sample_interval;
+        self
+    }
+
+    /// Grid index of the first sample: 1 when the t = 0 sample is skipped, else 0
+    fn first_grid_index(&self) -> f64 {
+        if self.skip_initial { 1.0 } else { 0.0 }
+    }
+
+    /// Series that records every sample offered, up to `limit` of them
+    ///
+    /// Used for event-time data such as arrival staircases, where fixed-interval
+    /// sampling would hide the individual jumps.
+    pub fn event_driven(limit: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(limit),
+            initial_interval: 0.0,
+            sample_interval: 0.0,
+            next_sample_time: 0.0,
+            max_samples: usize::MAX,
+            limit,
+            skip_initial: false,
+        }
+    }
+
+    /// Series holding exactly `samples`, which must be in time order, and taking no more
+    ///
+    /// Used for samples read back from a file, see [`crate::sample_file`].
+    pub fn from_samples(samples: Vec<(f64, T)>) -> Self {
+        Self {
+            limit: samples.len(),
+            data: samples,
+            initial_interval: 0.0,
+            sample_interval: 0.0,
+            next_sample_time: 0.0,
+            max_samples: usize::MAX,
+            skip_initial: false,
+        }
+    }
+
+    /// Drop all samples and start again from t = 0 at the initial interval, keeping the buffer
+    pub fn reset(&mut self) {
+        self.data.clear();
+        self.sample_interval = self.initial_interval;
+        self.next_sample_time = self.first_grid_index() * self.sample_interval;
+    }
+
     #[inline]
     pub fn should_sample(&self, current_time: f64) -> bool {
         current_time >= self.next_sample_time
@@ -21,7 +108,16 @@ impl<T: Clone> TimeSeries<T> {
 
     #[inline]
     pub fn sample(&mut self, current_time: f64, value: T) -> bool {
-        if self.should_sample(current_time) {
+        if !self.should_sample(current_time) {
+            return false;
+        }
+        if self.data.len() >= self.max_samples {
+            self.coarsen();
+            if !self.should_sample(current_time) {
+                return false;
+            }
+        }
+        if self.data.len() < self.limit {
             self.data.push((current_time, value));
             self.next_sample_time += self.sample_interval;
             true
@@ -30,6 +126,29 @@ impl<T: Clone> TimeSeries<T> {
         }
     }
 
+    /// Double the interval and keep the samples that lie on the coarser grid
+    fn coarsen(&mut self) {
+        // Sample i belongs to grid index i + first; the even indices stay on the grid
+        let mut index = self.first_grid_index() as usize;
+        self.data.retain(|_| {
+            index += 1;
+            index % 2 == 1
+        });
+        self.sample_interval *= 2.0;
+        self.next_sample_time =
+            (self.data.len() as f64 + self.first_grid_index()) * self.sample_interval;
+    }
+
+    /// Bytes allocated for the samples, not counting what a sample points to
+    pub fn memory_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<(f64, T)>()
+    }
+
+    /// Current spacing of the sample grid, 0 for event-driven series
+    pub fn sample_interval(&self) -> f64 {
+        self.sample_interval
+    }
+
     pub fn data(&self) -> &[(f64, T)] {
         &self.data
     }
@@ -37,33 +156,1303 @@ impl<T: Clone> TimeSeries<T> {
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Latest sample taken at or before `time`, `None` before the first sample
+    ///
+    /// Samples are stored in time order, so this is a binary search. After the last
+    /// sample its value is returned, as nothing newer was recorded.
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let after = self.data.partition_point(|(t, _)| *t <= time);
+        after.checked_sub(1).map(|index| self.data[index].1.clone())
+    }
+
+    /// Rate of change between consecutive samples of a cumulative counter
+    ///
+    /// Each output point is `(t_i, (v_i - v_{i-1}) / (t_i - t_{i-1}))`, i.e. the
+    /// per-time-unit rate over the interval ending at `t_i`. Dividing by the actual
+    /// gap keeps the result comparable when samples are not evenly spaced.
+    /// The first sample has no predecessor and pairs with a zero time gap are skipped.
+    pub fn differenced<F>(&self, to_f64: F) -> Vec<(f64, f64)>
+    where
+        F: Fn(T) -> f64,
+    {
+        derivative(&self.data, 1, to_f64)
+    }
+
+    /// Instantaneous rate of change of the metric, smoothed over `stride` sample intervals
+    ///
+    /// See [`derivative`]; with a stride of 1 this is [`TimeSeries::differenced`].
+    pub fn derivative<F>(&self, stride: usize, to_f64: F) -> Vec<(f64, f64)>
+    where
+        F: Fn(T) -> f64,
+    {
+        derivative(&self.data, stride, to_f64)
+    }
+}
+
+impl TimeSeries<f64> {
+    /// Value at `time` on the straight line between the samples around it
+    ///
+    /// `None` before the first or after the last sample, where there is nothing to
+    /// interpolate between; a sample taken exactly at `time` is returned as is.
+    pub fn interpolate_at(&self, time: f64) -> Option<f64> {
+        let after = self.data.partition_point(|(t, _)| *t < time);
+        let &(t1, v1) = self.data.get(after)?;
+        if t1 == time {
+            return Some(v1);
+        }
+        let &(t0, v0) = self.data.get(after.checked_sub(1)?)?;
+        Some(v0 + (v1 - v0) * (time - t0) / (t1 - t0))
+    }
+}
+
+/// Slope of `(t, v)` samples, `(t_i, (v_i - v_{i-stride}) / (t_i - t_{i-stride}))`
+///
+/// A `stride` above 1 smooths the result: the slope is taken across that many
+/// sample intervals, averaging out sampling noise while still dividing by the
+/// real elapsed time, so irregularly spaced samples are handled. The first
+/// `stride` samples have no predecessor and pairs without a time gap are skipped.
+pub fn derivative<T, F>(data: &[(f64, T)], stride: usize, to_f64: F) -> Vec<(f64, f64)>
+where
+    T: Clone,
+    F: Fn(T) -> f64,
+{
+    data.iter()
+        .zip(data.iter().skip(stride.max(1)))
+        .filter_map(|((t0, v0), (t1, v1))| {
+            let dt = t1 - t0;
+            if dt > 0.0 {
+                Some((*t1, (to_f64(v1.clone()) - to_f64(v0.clone())) / dt))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// How [`normalize`] puts series of different units on one scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Normalization {
+    /// Onto [0, 1], the smallest value at 0 and the largest at 1
+    #[default]
+    MinMax,
+    /// Standard deviations from the mean
+    ZScore,
+}
+
+/// Samples mapped onto a common scale, with the range and moments they were mapped from
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedSeries {
+    pub points: Vec<(f64, f64)>,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Original value at normalized 0 and original units per normalized unit
+    offset: f64,
+    scale: f64,
+}
+
+impl NormalizedSeries {
+    /// Original value of the normalized value `y`
+    pub fn denormalize(&self, y: f64) -> f64 {
+        self.offset + y * self.scale
+    }
+}
+
+/// `samples` on the scale of `method`, leaving out values that are not finite;
+/// `None` if none is
+///
+/// A constant series has no range to divide by: min-max puts it at ½ and z-scores
+/// put it at 0, with one original unit per normalized unit so it maps back exactly.
+pub fn normalize(samples: &[(f64, f64)], method: Normalization) -> Option<NormalizedSeries> {
+    let finite: Vec<(f64, f64)> = samples
+        .iter()
+        .copied()
+        .filter(|(_, v)| v.is_finite())
+        .collect();
+    if finite.is_empty() {
+        return None;
+    }
+    let (min, max) = finite
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (_, v)| {
+            (lo.min(*v), hi.max(*v))
+        });
+    let n = finite.len() as f64;
+    let mean = finite.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let std_dev = (finite.iter().map(|(_, v)| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let (offset, scale) = match method {
+        Normalization::MinMax if max > min => (min, max - min),
+        Normalization::MinMax => (min - 0.5, 1.0),
+        Normalization::ZScore if std_dev > 0.0 => (mean, std_dev),
+        Normalization::ZScore => (mean, 1.0),
+    };
+    Some(NormalizedSeries {
+        points: finite
+            .into_iter()
+            .map(|(t, v)| (t, (v - offset) / scale))
+            .collect(),
+        min,
+        max,
+        mean,
+        std_dev,
+        offset,
+        scale,
+    })
+}
+
+/// Queue lengths the occupancy tells apart; longer queues are counted in the last row
+pub const MAX_OCCUPANCY_ROWS: usize = 200;
+
+/// Time buckets the occupancy keeps before merging neighbours
+pub const MAX_OCCUPANCY_BUCKETS: usize = 1_000;
+
+/// Time spent at each queue length, accumulated exactly per time bucket
+///
+/// Every event adds the time since the previous one to the queue length that held
+/// in between, split at bucket boundaries, so unlike the sampled queue length
+/// nothing happening between samples is lost. Once a bucket beyond `max_buckets`
+/// is needed, neighbouring pairs are merged and the width doubles, like the grid
+/// of a [`TimeSeries`]; the initial width only sets the finest resolution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueOccupancy {
+    initial_width: f64,
+    bucket_width: f64,
+    max_buckets: usize,
+    /// `buckets[i][n]` is the time spent with n waiting in bucket i; rows beyond
+    /// the longest queue seen in a bucket are left out
+    buckets: Vec<Vec<f64>>,
+    last_time: f64,
+    last_length: usize,
+}
+
+impl QueueOccupancy {
+    /// `max_buckets` must be at least 2 so merging frees room
+    pub fn new(initial_width: f64, max_buckets: usize) -> Self {
+        assert!(max_buckets >= 2, "the occupancy needs room for 2 buckets");
+        Self {
+            initial_width,
+            bucket_width: initial_width,
+            max_buckets,
+            buckets: Vec::new(),
+            last_time: 0.0,
+            last_length: 0,
+        }
+    }
+
+    /// Bytes allocated for the buckets and their rows
+    pub fn memory_bytes(&self) -> usize {
+        self.buckets.capacity() * std::mem::size_of::<Vec<f64>>()
+            + self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.capacity() * std::mem::size_of::<f64>())
+                .sum::<usize>()
+    }
+
+    /// Forget the run so far and start again from an empty queue at t = 0
+    pub fn reset(&mut self) {
+        self.buckets.clear();
+        self.bucket_width = self.initial_width;
+        self.last_time = 0.0;
+        self.last_length = 0;
+    }
+
+    /// Credit the time since the previous call to the queue length then, and
+    /// remember `queue_length` as the one holding from `now` on
+    #[inline]
+    pub fn record(&mut self, now: f64, queue_length: usize) {
+        let row = self.last_length.min(MAX_OCCUPANCY_ROWS - 1);
+        // Nearly every event falls into the latest bucket at a length it has seen already
+        let latest = self.buckets.len().wrapping_sub(1);
+        if let Some(bucket) = self.buckets.last_mut() {
+            let start = latest as f64 * self.bucket_width;
+            let end = start + self.bucket_width;
+            if self.last_time >= start && self.last_time < end && now <= end && row < bucket.len() {
+                bucket[row] += now - self.last_time;
+                self.last_time = now;
+                self.last_length = queue_length;
+                return;
+            }
+        }
+        let mut start = self.last_time;
+        while start < now {
+            let mut index = self.bucket_index(start);
+            while index >= self.max_buckets {
+                self.coarsen();
+                index = self.bucket_index(start);
+            }
+            let end = now.min((index + 1) as f64 * self.bucket_width);
+            if self.buckets.len() <= index {
+                self.buckets.resize_with(index + 1, Vec::new);
+            }
+            let bucket = &mut self.buckets[index];
+            if bucket.len() <= row {
+                bucket.resize(row + 1, 0.0);
+            }
+            bucket[row] += end - start;
+            start = end;
+        }
+        self.last_time = now;
+        self.last_length = queue_length;
+    }
+
+    /// Bucket containing `time`, guarding against the division landing just below a boundary
+    fn bucket_index(&self, time: f64) -> usize {
+        let index = (time / self.bucket_width) as usize;
+        if (index + 1) as f64 * self.bucket_width <= time {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    /// Merge neighbouring pairs of buckets and double the width
+    fn coarsen(&mut self) {
+        self.buckets = self
+            .buckets
+            .chunks(2)
+            .map(|pair| {
+                let mut merged = pair[0].clone();
+                if let Some(second) = pair.get(1) {
+                    if second.len() > merged.len() {
+                        merged.resize(second.len(), 0.0);
+                    }
+                    for (total, time) in merged.iter_mut().zip(second) {
+                        *total += time;
+                    }
+                }
+                merged
+            })
+            .collect();
+        self.bucket_width *= 2.0;
+    }
+
+    pub fn bucket_width(&self) -> f64 {
+        self.bucket_width
+    }
+
+    pub fn buckets(&self) -> &[Vec<f64>] {
+        &self.buckets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Rows needed for the longest queue seen: 1 + its length, at most [`MAX_OCCUPANCY_ROWS`]
+    pub fn rows(&self) -> usize {
+        self.buckets.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Time covered so far
+    pub fn end_time(&self) -> f64 {
+        self.last_time
+    }
+
+    /// Fraction of time at each queue length in at most `columns` equal columns over `[start, end)`
+    ///
+    /// Each column sums whole buckets, so the columns snap to bucket boundaries and
+    /// there are never more of them than buckets in the range; zooming in therefore
+    /// sharpens the picture until a column is a single bucket.
+    pub fn grid(&self, start: f64, end: f64, columns: usize) -> OccupancyGrid {
+        let first = ((start.max(0.0) / self.bucket_width) as usize).min(self.buckets.len());
+        let last = ((end / self.bucket_width).ceil().max(0.0) as usize).min(self.buckets.len());
+        let span = last.saturating_sub(first);
+        let columns = columns.min(span);
+        let rows = self.rows();
+        let mut fractions = vec![vec![0.0; rows]; columns];
+        if columns == 0 {
+            return OccupancyGrid {
+                start: first as f64 * self.bucket_width,
+                column_width: 0.0,
+                fractions,
+            };
+        }
+
+        for (offset, bucket) in self.buckets[first..last].iter().enumerate() {
+            let column = &mut fractions[offset * columns / span];
+            for (total, time) in column.iter_mut().zip(bucket) {
+                *total += time;
+            }
+        }
+        for column in &mut fractions {
+            let total: f64 = column.iter().sum();
+            if total > 0.0 {
+                column.iter_mut().for_each(|value| *value /= total);
+            }
+        }
+        OccupancyGrid {
+            start: first as f64 * self.bucket_width,
+            column_width: span as f64 * self.bucket_width / columns as f64,
+            fractions,
+        }
+    }
+
+    /// Write every bucket as `start,end,queue_0,...,queue_n`, each row the fractions
+    /// of the bucket's time at each queue length
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let grid = self.grid(0.0, self.end_time(), self.buckets.len());
+        let mut out = BufWriter::new(File::create(path)?);
+        let header: Vec<String> = (0..self.rows()).map(|n| format!("queue_{}", n)).collect();
+        writeln!(out, "start,end,{}", header.join(","))?;
+        for (k, column) in grid.fractions.iter().enumerate() {
+            let (start, end) = grid.column_span(k);
+            let values: Vec<String> = column.iter().map(f64::to_string).collect();
+            writeln!(out, "{},{},{}", start, end, values.join(","))?;
+        }
+        out.flush()
+    }
+}
+
+/// Queue-length occupancy re-bucketed into equal time columns, see [`QueueOccupancy::grid`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyGrid {
+    pub start: f64,
+    pub column_width: f64,
+    /// `fractions[k][n]`: share of column k's time with n customers waiting
+    pub fractions: Vec<Vec<f64>>,
+}
+
+impl OccupancyGrid {
+    /// Start and end time of column `k`
+    pub fn column_span(&self, k: usize) -> (f64, f64) {
+        let start = self.start + k as f64 * self.column_width;
+        (start, start + self.column_width)
+    }
+}
+
+/// Finest bin width of a [`Histogram`], small enough for waits of any practical time unit
+pub const HISTOGRAM_INITIAL_WIDTH: f64 = 1e-6;
+
+/// Bins a [`Histogram`] keeps before merging neighbours
+pub const HISTOGRAM_BINS: usize = 4_096;
+
+/// Counts of a nonnegative quantity in equal bins from 0, at the finest width its range allows
+///
+/// Once a value lands beyond `max_bins`, neighbouring pairs of bins are merged and
+/// the width doubles, like the buckets of a [`QueueOccupancy`], so the bins always
+/// cover everything seen at between `max_bins / 2` and `max_bins` bins. Coarser
+/// views are taken with [`merge_bins`] without having kept the values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Histogram {
+    initial_width: f64,
+    width: f64,
+    max_bins: usize,
+    /// Bins beyond the largest value seen are left out
+    counts: Vec<u64>,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new(HISTOGRAM_INITIAL_WIDTH, HISTOGRAM_BINS)
+    }
+}
+
+impl Histogram {
+    /// `max_bins` must be at least 2 so merging frees room
+    pub fn new(initial_width: f64, max_bins: usize) -> Self {
+        assert!(max_bins >= 2, "the histogram needs room for 2 bins");
+        Self {
+            initial_width,
+            width: initial_width,
+            max_bins,
+            counts: Vec::new(),
+        }
+    }
+
+    /// Count `value`; negative values count as 0 and non-finite ones are ignored
+    #[inline]
+    pub fn push(&mut self, value: f64) {
+        // Negative values cast to bin 0, infinite ones beyond every bin
+        if let Some(count) = self.counts.get_mut((value / self.width) as usize)
+            && value.is_finite()
+        {
+            *count += 1;
+        } else {
+            self.push_beyond(value);
+        }
+    }
+
+    /// Count a value beyond the bins so far, growing or coarsening them; kept out
+    /// of line so the common case above stays cheap
+    #[cold]
+    #[inline(never)]
+    fn push_beyond(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let value = value.max(0.0);
+        let mut bin = (value / self.width) as usize;
+        while bin >= self.max_bins {
+            self.coarsen();
+            bin = (value / self.width) as usize;
+        }
+        if bin >= self.counts.len() {
+            self.counts.resize(bin + 1, 0);
+        }
+        self.counts[bin] += 1;
+    }
+
+    /// Merge neighbouring pairs of bins and double the width
+    fn coarsen(&mut self) {
+        self.counts = merge_bins(&self.counts, 2);
+        self.width *= 2.0;
+    }
+
+    /// Forget the values so far, back at the finest width
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.width = self.initial_width;
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Count of bin k, covering `[k * width, (k + 1) * width)`
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Value below which a share `q` of the counts lies, interpolated linearly
+    /// within its bin, so off by at most one bin width; `None` when empty
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let target = q.clamp(0.0, 1.0) * total as f64;
+        let mut below = 0.0;
+        for (k, &count) in self.counts.iter().enumerate() {
+            let through = below + count as f64;
+            if count > 0 && through >= target {
+                let fraction = (target - below) / count as f64;
+                return Some((k as f64 + fraction) * self.width);
+            }
+            below = through;
+        }
+        Some(self.counts.len() as f64 * self.width)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Take up to `count` values back out of bin 0, e.g. the exact zeros among them
+    pub fn remove_from_first_bin(&mut self, count: u64) {
+        if let Some(first) = self.counts.first_mut() {
+            *first = first.saturating_sub(count);
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.counts.capacity() * std::mem::size_of::<u64>()
+    }
 }
 
-#[derive(Clone)]
+/// Sums of `factor` adjacent values from the first on; the last sum takes what is left
+///
+/// Re-binning a histogram or a distribution into buckets `factor` times as wide.
+pub fn merge_bins<T>(values: &[T], factor: usize) -> Vec<T>
+where
+    T: Copy + std::iter::Sum<T>,
+{
+    values
+        .chunks(factor.max(1))
+        .map(|chunk| chunk.iter().copied().sum())
+        .collect()
+}
+
+/// Events of each type recorded for the arrival/departure staircase
+///
+/// The staircase is only readable while individual steps are visible, so it
+/// covers the start of the run at full event resolution and then stops.
+pub const STAIRCASE_EVENTS: usize = 5_000;
+
+/// The main metrics as last sampled at or before some time, see [`SimulationTimeSeries::snapshot_at`]
+///
+/// A field is `None` when its series has no sample that early.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricSnapshot {
+    pub queue_length: Option<usize>,
+    pub mean_wait_time: Option<f64>,
+    pub utilization: Option<f64>,
+    pub customers_served: Option<u64>,
+    pub customers_in_system: Option<usize>,
+    pub throughput: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulationTimeSeries {
     pub queue_length: TimeSeries<usize>,
     pub mean_wait_time: TimeSeries<f64>,
+    /// 95% confidence interval `(lower, upper)` of the mean wait from the batches
+    /// completed so far; `None` until there are enough batches
+    pub mean_wait_ci: TimeSeries<Option<(f64, f64)>>,
+    /// Coefficient of variation of the waits so far; `None` until a few customers have waited
+    pub wait_cv: TimeSeries<Option<f64>>,
     pub utilization: TimeSeries<f64>,
+    /// Busy fraction of each server so far; only sampled with more than one server
+    pub server_utilization: TimeSeries<Vec<f64>>,
     pub customers_served: TimeSeries<u64>,
+    pub customers_arrived: TimeSeries<u64>,
+    pub cumulative_arrivals: TimeSeries<u64>,
+    pub cumulative_departures: TimeSeries<u64>,
     pub customers_in_system: TimeSeries<usize>,
+    /// Time-average queue length and customers in system over the interval before
+    /// each sample; only sampled with [`SimulationTimeSeries::with_interval_averages`]
+    pub queue_length_average: TimeSeries<f64>,
+    pub customers_in_system_average: TimeSeries<f64>,
     pub throughput: TimeSeries<f64>,
+    /// Departures per time unit over a sliding window, see [`crate::statistics::Statistics::windowed_throughput`]
+    pub windowed_throughput: TimeSeries<f64>,
+    /// Mean wait and utilization over the statistics window, see
+    /// [`crate::statistics::Statistics::windowed_stats`]; only sampled with a window
+    pub windowed_mean_wait: TimeSeries<f64>,
+    pub windowed_utilization: TimeSeries<f64>,
+    pub busy_servers: TimeSeries<usize>,
+    /// Snapshots of the empirical P_n (fraction of time with n customers in the system so far)
+    pub customers_distribution: TimeSeries<Vec<f64>>,
+    /// Exact time at each queue length per time bucket, for the heatmap
+    pub queue_occupancy: QueueOccupancy,
+    /// Waits of the run after the warm-up, copied from the statistics when it ends
+    pub wait_histogram: Histogram,
+    /// Waits of exactly 0 among those in the first bin of `wait_histogram`
+    pub zero_waits: u64,
+    /// Base-10 logarithms of the slowdowns of the run after the warm-up, copied like the waits
+    pub slowdown_histogram: Histogram,
+    interval_averages: bool,
+    /// Running totals at the last interval-averaged sample
+    #[serde(skip)]
+    last_totals: Option<Totals>,
+}
+
+/// Empty series on a unit grid; a saved session lacking a series loads it as this
+impl Default for SimulationTimeSeries {
+    fn default() -> Self {
+        Self::new(1.0, DEFAULT_MAX_SAMPLES)
+    }
 }
 
 impl SimulationTimeSeries {
-    pub fn new(sample_interval: f64, max_samples: usize) -> Self {
+    /// Fixed-interval series starting at `initial_interval`, each keeping at most `max_samples`
+    ///
+    /// All of them take the same samples, so they coarsen at the same moments and
+    /// stay aligned.
+    pub fn new(initial_interval: f64, max_samples: usize) -> Self {
+        Self {
+            queue_length: TimeSeries::new(initial_interval, max_samples),
+            mean_wait_time: TimeSeries::new(initial_interval, max_samples),
+            mean_wait_ci: TimeSeries::new(initial_interval, max_samples),
+            wait_cv: TimeSeries::new(initial_interval, max_samples),
+            utilization: TimeSeries::new(initial_interval, max_samples),
+            server_utilization: TimeSeries::new(initial_interval, max_samples),
+            customers_served: TimeSeries::new(initial_interval, max_samples),
+            customers_arrived: TimeSeries::new(initial_interval, max_samples),
+            cumulative_arrivals: TimeSeries::event_driven(STAIRCASE_EVENTS),
+            cumulative_departures: TimeSeries::event_driven(STAIRCASE_EVENTS),
+            customers_in_system: TimeSeries::new(initial_interval, max_samples),
+            queue_length_average: TimeSeries::new(initial_interval, max_samples),
+            customers_in_system_average: TimeSeries::new(initial_interval, max_samples),
+            throughput: TimeSeries::new(initial_interval, max_samples),
+            windowed_throughput: TimeSeries::new(initial_interval, max_samples),
+            windowed_mean_wait: TimeSeries::new(initial_interval, max_samples),
+            windowed_utilization: TimeSeries::new(initial_interval, max_samples),
+            busy_servers: TimeSeries::new(initial_interval, max_samples),
+            customers_distribution: TimeSeries::new(initial_interval, max_samples),
+            queue_occupancy: QueueOccupancy::new(initial_interval, MAX_OCCUPANCY_BUCKETS),
+            wait_histogram: Histogram::default(),
+            zero_waits: 0,
+            slowdown_histogram: Histogram::default(),
+            interval_averages: false,
+            last_totals: None,
+        }
+    }
+
+    /// Also sample the queue length and customers in system averaged over the
+    /// interval since the previous sample
+    ///
+    /// An instantaneous sample catches whatever state the last event left, so a
+    /// busy queue plots as noise around its trend; the average over the interval,
+    /// the area under the path divided by its length, follows the trend. Once the
+    /// series coarsen, a kept sample still averages only the time since the sample
+    /// before it was taken, not the whole coarser interval.
+    pub fn with_interval_averages(self) -> Self {
         Self {
-            queue_length: TimeSeries::new(sample_interval, max_samples),
-            mean_wait_time: TimeSeries::new(sample_interval, max_samples),
-            utilization: TimeSeries::new(sample_interval, max_samples),
-            customers_served: TimeSeries::new(sample_interval, max_samples),
-            customers_in_system: TimeSeries::new(sample_interval, max_samples),
-            throughput: TimeSeries::new(sample_interval, max_samples),
+            interval_averages: true,
+            ..self
         }
     }
 
+    /// Leave out the t = 0 sample of every fixed-interval series, see [`TimeSeries::skip_initial_sample`]
+    pub fn skip_initial_sample(self) -> Self {
+        Self {
+            queue_length: self.queue_length.skip_initial_sample(),
+            mean_wait_time: self.mean_wait_time.skip_initial_sample(),
+            mean_wait_ci: self.mean_wait_ci.skip_initial_sample(),
+            wait_cv: self.wait_cv.skip_initial_sample(),
+            utilization: self.utilization.skip_initial_sample(),
+            server_utilization: self.server_utilization.skip_initial_sample(),
+            customers_served: self.customers_served.skip_initial_sample(),
+            customers_arrived: self.customers_arrived.skip_initial_sample(),
+            customers_in_system: self.customers_in_system.skip_initial_sample(),
+            queue_length_average: self.queue_length_average.skip_initial_sample(),
+            customers_in_system_average: self.customers_in_system_average.skip_initial_sample(),
+            throughput: self.throughput.skip_initial_sample(),
+            windowed_throughput: self.windowed_throughput.skip_initial_sample(),
+            windowed_mean_wait: self.windowed_mean_wait.skip_initial_sample(),
+            windowed_utilization: self.windowed_utilization.skip_initial_sample(),
+            busy_servers: self.busy_servers.skip_initial_sample(),
+            customers_distribution: self.customers_distribution.skip_initial_sample(),
+            ..self
+        }
+    }
+
+    /// Bytes allocated for all series, including the per-server and P_n vectors of each sample
+    pub fn memory_bytes(&self) -> usize {
+        let nested = |series: &TimeSeries<Vec<f64>>| {
+            series.memory_bytes()
+                + series
+                    .data()
+                    .iter()
+                    .map(|(_, values)| values.capacity() * std::mem::size_of::<f64>())
+                    .sum::<usize>()
+        };
+        self.queue_length.memory_bytes()
+            + self.mean_wait_time.memory_bytes()
+            + self.mean_wait_ci.memory_bytes()
+            + self.wait_cv.memory_bytes()
+            + self.utilization.memory_bytes()
+            + nested(&self.server_utilization)
+            + self.customers_served.memory_bytes()
+            + self.customers_arrived.memory_bytes()
+            + self.cumulative_arrivals.memory_bytes()
+            + self.cumulative_departures.memory_bytes()
+            + self.customers_in_system.memory_bytes()
+            + self.queue_length_average.memory_bytes()
+            + self.customers_in_system_average.memory_bytes()
+            + self.throughput.memory_bytes()
+            + self.windowed_throughput.memory_bytes()
+            + self.windowed_mean_wait.memory_bytes()
+            + self.windowed_utilization.memory_bytes()
+            + self.busy_servers.memory_bytes()
+            + nested(&self.customers_distribution)
+            + self.queue_occupancy.memory_bytes()
+            + self.wait_histogram.memory_bytes()
+            + self.slowdown_histogram.memory_bytes()
+    }
+
+    /// Drop all samples for a new run, keeping the buffers
+    pub fn reset(&mut self) {
+        self.queue_length.reset();
+        self.mean_wait_time.reset();
+        self.mean_wait_ci.reset();
+        self.wait_cv.reset();
+        self.utilization.reset();
+        self.server_utilization.reset();
+        self.customers_served.reset();
+        self.customers_arrived.reset();
+        self.cumulative_arrivals.reset();
+        self.cumulative_departures.reset();
+        self.customers_in_system.reset();
+        self.queue_length_average.reset();
+        self.customers_in_system_average.reset();
+        self.last_totals = None;
+        self.throughput.reset();
+        self.windowed_throughput.reset();
+        self.windowed_mean_wait.reset();
+        self.windowed_utilization.reset();
+        self.busy_servers.reset();
+        self.customers_distribution.reset();
+        self.queue_occupancy.reset();
+        self.wait_histogram.clear();
+        self.zero_waits = 0;
+        self.slowdown_histogram.clear();
+    }
+
+    /// Names of [`Self::metrics`] in their order
+    pub const METRIC_NAMES: [&'static str; 16] = [
+        "queue_length",
+        "mean_wait_time",
+        "mean_wait_lower",
+        "mean_wait_upper",
+        "wait_cv",
+        "utilization",
+        "customers_served",
+        "customers_arrived",
+        "customers_in_system",
+        "queue_length_average",
+        "customers_in_system_average",
+        "throughput",
+        "windowed_throughput",
+        "busy_servers",
+        "windowed_mean_wait",
+        "windowed_utilization",
+    ];
+
+    /// Every fixed-interval metric as `(name, samples)` with values converted to f64, for exporters
+    ///
+    /// The confidence bounds of the mean wait and the wait CV leave out samples
+    /// without a value; the windowed metrics are empty for a run without a window.
+    pub fn metrics(&self) -> Vec<(&'static str, Vec<(f64, f64)>)> {
+        let bound = |select: fn((f64, f64)) -> f64| -> Vec<(f64, f64)> {
+            self.mean_wait_ci
+                .data()
+                .iter()
+                .filter_map(|(t, ci)| ci.map(|ci| (*t, select(ci))))
+                .collect()
+        };
+
+        fn to_f64<T: Copy>(series: &TimeSeries<T>, convert: impl Fn(T) -> f64) -> Vec<(f64, f64)> {
+            series
+                .data()
+                .iter()
+                .map(|(t, v)| (*t, convert(*v)))
+                .collect()
+        }
+
+        let samples: [Vec<(f64, f64)>; 16] = [
+            to_f64(&self.queue_length, |v| v as f64),
+            to_f64(&self.mean_wait_time, |v| v),
+            bound(|(lower, _)| lower),
+            bound(|(_, upper)| upper),
+            self.wait_cv
+                .data()
+                .iter()
+                .filter_map(|(t, cv)| cv.map(|cv| (*t, cv)))
+                .collect(),
+            to_f64(&self.utilization, |v| v),
+            to_f64(&self.customers_served, |v| v as f64),
+            to_f64(&self.customers_arrived, |v| v as f64),
+            to_f64(&self.customers_in_system, |v| v as f64),
+            to_f64(&self.queue_length_average, |v| v),
+            to_f64(&self.customers_in_system_average, |v| v),
+            to_f64(&self.throughput, |v| v),
+            to_f64(&self.windowed_throughput, |v| v),
+            to_f64(&self.busy_servers, |v| v as f64),
+            to_f64(&self.windowed_mean_wait, |v| v),
+            to_f64(&self.windowed_utilization, |v| v),
+        ];
+        Self::METRIC_NAMES.into_iter().zip(samples).collect()
+    }
+
+    /// The main metrics as they stood at `time`, each from its latest sample at or before it
+    pub fn snapshot_at(&self, time: f64) -> MetricSnapshot {
+        MetricSnapshot {
+            queue_length: self.queue_length.value_at(time),
+            mean_wait_time: self.mean_wait_time.value_at(time),
+            utilization: self.utilization.value_at(time),
+            customers_served: self.customers_served.value_at(time),
+            customers_in_system: self.customers_in_system.value_at(time),
+            throughput: self.throughput.value_at(time),
+        }
+    }
+
+    /// Write each server's share of the total capacity over time, `time,server_1,...,server_c`
+    ///
+    /// The columns are the per-server busy fractions divided by the number of
+    /// servers, so each row sums to the utilization: the layers of the stacked plot.
+    /// A single server has no per-server series and gets the utilization as its column.
+    pub fn write_server_utilization_csv(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        if self.server_utilization.is_empty() {
+            writeln!(out, "time,server_1")?;
+            for (t, utilization) in self.utilization.data() {
+                writeln!(out, "{},{}", t, utilization)?;
+            }
+            return out.flush();
+        }
+
+        let servers = self.server_utilization.data()[0].1.len();
+        let header: Vec<String> = (1..=servers).map(|k| format!("server_{}", k)).collect();
+        writeln!(out, "time,{}", header.join(","))?;
+        for (t, busy) in self.server_utilization.data() {
+            let shares: Vec<String> = busy
+                .iter()
+                .map(|b| (b / servers as f64).to_string())
+                .collect();
+            writeln!(out, "{},{}", t, shares.join(","))?;
+        }
+        out.flush()
+    }
+
+    /// Record the cumulative counts right after an arrival or departure event; other events are ignored
+    #[inline]
+    pub fn record_event(
+        &mut self,
+        event_type: EventType,
+        now: f64,
+        arrivals: u64,
+        departures: u64,
+    ) {
+        match event_type {
+            EventType::Arrival => self.cumulative_arrivals.sample(now, arrivals),
+            EventType::Departure => self.cumulative_departures.sample(now, departures),
+            EventType::Breakdown
+            | EventType::RepairComplete
+            | EventType::Switchover
+            | EventType::StandbyBreakdown
+            | EventType::StandbyRepair
+            | EventType::Setup
+            | EventType::Admission
+            | EventType::ParameterChange => false,
+        };
+    }
+
     /// only need to check one
     #[inline]
     pub fn should_sample(&self, current_time: f64) -> bool {
         self.queue_length.should_sample(current_time)
     }
+
+    /// Spacing the fixed-interval series ended up with
+    pub fn sample_interval(&self) -> f64 {
+        self.queue_length.sample_interval()
+    }
+
+    /// Sample every fixed-interval metric from `stats` at `now`, if a sample is due
+    ///
+    /// Call it after every event: the queue occupancy is accumulated on each call,
+    /// whether or not a sample is due.
+    #[inline]
+    pub fn sample_statistics(&mut self, now: f64, stats: &Statistics) {
+        self.queue_occupancy
+            .record(now, stats.current_queue_length());
+        if self.should_sample(now) {
+            self.take_samples(now, stats);
+        }
+    }
+
+    /// Push every fixed-interval metric; kept out of line so the check above stays cheap
+    #[cold]
+    #[inline(never)]
+    fn take_samples(&mut self, now: f64, stats: &Statistics) {
+        self.queue_length.sample(now, stats.current_queue_length());
+        self.mean_wait_time.sample(now, stats.average_wait_time());
+        self.mean_wait_ci
+            .sample(now, stats.wait_confidence_interval());
+        self.wait_cv.sample(now, stats.wait_cv());
+        self.utilization
+            .sample(now, stats.instantaneous_utilization(now));
+        if stats.servers() > 1 {
+            self.server_utilization
+                .sample(now, stats.server_utilizations(now));
+        }
+        self.customers_served.sample(now, stats.served_customers());
+        self.customers_arrived
+            .sample(now, stats.arrived_customers());
+        self.customers_in_system
+            .sample(now, stats.current_customers_in_system());
+        if self.interval_averages {
+            self.sample_interval_averages(now, stats);
+        }
+        self.throughput.sample(now, stats.throughput(now));
+        self.windowed_throughput
+            .sample(now, stats.windowed_throughput(now));
+        if let Some(window) = stats.windowed_stats(now) {
+            self.windowed_mean_wait.sample(now, window.mean_wait);
+            self.windowed_utilization.sample(now, window.utilization);
+        }
+        self.busy_servers.sample(now, stats.busy_servers());
+        self.customers_distribution
+            .sample(now, stats.customers_distribution(now));
+    }
+
+    /// Push the areas under the queue length and customers in system since the
+    /// last sample divided by the time since it
+    ///
+    /// The areas accumulate from the start of the observation, which a warm-up
+    /// reset moves, so a last sample from before it counts from that start with
+    /// nothing accumulated. A sample at the start itself has no interval to average
+    /// over and takes the instantaneous values.
+    fn sample_interval_averages(&mut self, now: f64, stats: &Statistics) {
+        let totals = stats.totals(now);
+        let start = stats.observation_start();
+        let last = self
+            .last_totals
+            .filter(|last| last.time >= start)
+            .unwrap_or(Totals {
+                time: start,
+                ..Totals::default()
+            });
+        let span = now - last.time;
+        let (queue, customers) = if span > 0.0 {
+            let queue_area = totals.queue_area - last.queue_area;
+            let busy_area = totals.busy_area - last.busy_area;
+            (queue_area / span, (queue_area + busy_area) / span)
+        } else {
+            (
+                stats.current_queue_length() as f64,
+                stats.current_customers_in_system() as f64,
+            )
+        };
+        if self.queue_length_average.sample(now, queue) {
+            self.customers_in_system_average.sample(now, customers);
+            self.last_totals = Some(totals);
+        }
+    }
+}
+
+/// Wall-clock time between flushes of a [`CsvSampleStream`], bounding what an
+/// interrupted run can lose
+pub const STREAM_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fixed-interval samples written to a CSV file as they are taken
+///
+/// Unlike [`SimulationTimeSeries`] the stream never coarsens: every row stays at
+/// the interval it was created with and goes straight to disk, so memory does not
+/// grow with the run and the file keeps the full resolution however long it gets.
+/// Rows are buffered and flushed every [`STREAM_FLUSH_INTERVAL`], on
+/// [`CsvSampleStream::finish`] and when the stream is dropped, also while
+/// unwinding from a panic; a killed process loses at most the last interval's rows.
+/// The columns are `time` followed by the names of [`SimulationTimeSeries::metrics`],
+/// with the confidence bounds of the mean wait left empty until they exist.
+pub struct CsvSampleStream {
+    out: BufWriter<File>,
+    interval: f64,
+    next_sample_time: f64,
+    rows: u64,
+    last_flush: Instant,
+}
+
+impl CsvSampleStream {
+    /// Create the file at `path` and write the header; `interval` must be positive
+    pub fn create(path: &Path, interval: f64) -> io::Result<Self> {
+        assert!(interval > 0.0, "the stream interval must be positive");
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(
+            out,
+            "time,queue_length,mean_wait_time,mean_wait_lower,mean_wait_upper,utilization,\
+             customers_served,customers_arrived,customers_in_system,throughput,\
+             windowed_throughput,busy_servers,wait_cv"
+        )?;
+        Ok(Self {
+            out,
+            interval,
+            next_sample_time: 0.0,
+            rows: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Write a row from `stats` at `now` if one is due, as [`SimulationTimeSeries::sample_statistics`] does
+    #[inline]
+    pub fn sample_statistics(&mut self, now: f64, stats: &Statistics) -> io::Result<()> {
+        if now < self.next_sample_time {
+            return Ok(());
+        }
+        let (lower, upper) = match stats.wait_confidence_interval() {
+            Some((lower, upper)) => (lower.to_string(), upper.to_string()),
+            None => (String::new(), String::new()),
+        };
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            now,
+            stats.current_queue_length(),
+            stats.average_wait_time(),
+            lower,
+            upper,
+            stats.instantaneous_utilization(now),
+            stats.served_customers(),
+            stats.arrived_customers(),
+            stats.current_customers_in_system(),
+            stats.throughput(now),
+            stats.windowed_throughput(now),
+            stats.busy_servers(),
+            stats.wait_cv().map_or(String::new(), |cv| cv.to_string())
+        )?;
+        self.rows += 1;
+        // Grid times that passed without an event get no row, so a long idle
+        // stretch does not come out as a burst of identical rows
+        self.next_sample_time = ((now / self.interval).floor() + 1.0) * self.interval;
+        if self.last_flush.elapsed() >= STREAM_FLUSH_INTERVAL {
+            self.out.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Rows written so far
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Flush the remaining rows, reporting any error the drop would swallow
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Cross-replication quantiles of one metric at a single sample index
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EnsemblePoint {
+    pub time: f64,
+    pub p05: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+/// Distribution of a metric across independent replications over time
+///
+/// Replications are aligned by sample index: the k-th sample of every replication
+/// is taken at the first event after the same grid time, so their times differ by
+/// at most one inter-event gap and the point is placed at their mean. A replication
+/// whose grid was coarsened less often is thinned to the coarsest grid first.
+/// Replications of unequal length are truncated to the shortest (the common horizon).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleSeries {
+    points: Vec<EnsemblePoint>,
+    replications: usize,
+}
+
+impl EnsembleSeries {
+    pub fn from_replications<T, F>(replications: &[&TimeSeries<T>], to_f64: F) -> Self
+    where
+        T: Clone,
+        F: Fn(T) -> f64,
+    {
+        // Every interval is the same initial one doubled, so the ratios are powers of two
+        let coarsest = replications
+            .iter()
+            .map(|r| r.sample_interval())
+            .fold(0.0, f64::max);
+        let aligned: Vec<Vec<&(f64, T)>> = replications
+            .iter()
+            .map(|r| {
+                let stride = (coarsest / r.sample_interval()).round().max(1.0) as usize;
+                r.data().iter().step_by(stride).collect()
+            })
+            .collect();
+
+        let horizon = aligned.iter().map(Vec::len).min().unwrap_or(0);
+        let mut values = Vec::with_capacity(replications.len());
+
+        let points = (0..horizon)
+            .map(|k| {
+                values.clear();
+                let mut time_sum = 0.0;
+                for replication in &aligned {
+                    let (t, v) = replication[k];
+                    time_sum += t;
+                    values.push(to_f64(v.clone()));
+                }
+                values.sort_by(|a, b| a.total_cmp(b));
+
+                EnsemblePoint {
+                    time: time_sum / replications.len() as f64,
+                    p05: quantile(&values, 0.05),
+                    p25: quantile(&values, 0.25),
+                    median: quantile(&values, 0.5),
+                    p75: quantile(&values, 0.75),
+                    p95: quantile(&values, 0.95),
+                }
+            })
+            .collect();
+
+        Self {
+            points,
+            replications: replications.len(),
+        }
+    }
+
+    pub fn points(&self) -> &[EnsemblePoint] {
+        &self.points
+    }
+
+    pub fn replications(&self) -> usize {
+        self.replications
+    }
+
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "time,p05,p25,median,p75,p95")?;
+        for p in &self.points {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                p.time, p.p05, p.p25, p.median, p.p75, p.p95
+            )?;
+        }
+        out.flush()
+    }
+}
+
+/// Quantile of sorted values with linear interpolation between order statistics
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let position = q * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An event-driven series holding exactly `samples`
+    fn series<T: Clone>(samples: &[(f64, T)]) -> TimeSeries<T> {
+        TimeSeries::from_samples(samples.to_vec())
+    }
+
+    #[test]
+    fn differencing_divides_by_the_actual_gaps() {
+        // Gaps of 1, 3 and 0.5 with 2, 3 and 2 more departures
+        let served = series(&[(0.0, 0u64), (1.0, 2), (4.0, 5), (4.5, 7)]);
+        let rates = served.differenced(|v| v as f64);
+        // The first sample has no predecessor and gets no rate
+        assert_eq!(rates, vec![(1.0, 2.0), (4.0, 1.0), (4.5, 4.0)]);
+    }
+
+    #[test]
+    fn differencing_skips_samples_without_a_gap() {
+        let served = series(&[(1.0, 3u64), (1.0, 4), (2.0, 6)]);
+        assert_eq!(served.differenced(|v| v as f64), vec![(2.0, 2.0)]);
+        assert!(series(&[(1.0, 3u64)]).differenced(|v| v as f64).is_empty());
+    }
+
+    #[test]
+    fn a_strided_derivative_spans_the_real_elapsed_time() {
+        // A linear metric of slope 2, sampled at uneven times
+        let samples: Vec<(f64, f64)> = [0.0, 0.5, 2.0, 2.25, 5.0, 9.0]
+            .iter()
+            .map(|&t| (t, 2.0 * t + 1.0))
+            .collect();
+        for stride in 1..=3 {
+            let slopes = derivative(&samples, stride, |v| v);
+            assert_eq!(slopes.len(), samples.len() - stride);
+            assert!(slopes.iter().all(|&(_, slope)| (slope - 2.0).abs() < 1e-12));
+            assert_eq!(slopes[0].0, samples[stride].0);
+        }
+    }
+
+    #[test]
+    fn a_strided_derivative_averages_over_its_window() {
+        let queue = series(&[(0.0, 0usize), (1.0, 4), (3.0, 2), (4.0, 6), (8.0, 6)]);
+        let to_f64 = |v: usize| v as f64;
+        // (2 - 0) / 3, (6 - 4) / 3 and (6 - 2) / 5
+        assert_eq!(
+            queue.derivative(2, to_f64),
+            vec![(3.0, 2.0 / 3.0), (4.0, 2.0 / 3.0), (8.0, 0.8)]
+        );
+        // A stride of 0 is taken as 1 and a stride past the end leaves nothing
+        assert_eq!(queue.derivative(0, to_f64), queue.differenced(to_f64));
+        assert!(queue.derivative(5, to_f64).is_empty());
+    }
+
+    #[test]
+    fn a_strided_derivative_skips_windows_without_elapsed_time() {
+        let samples = [(1.0, 0.0), (1.0, 1.0), (1.0, 2.0), (3.0, 6.0)];
+        assert_eq!(derivative(&samples, 2, |v| v), vec![(3.0, 2.5)]);
+    }
+
+    /// Sample `series` at each of `times`, with the time as the value
+    fn offer(mut series: TimeSeries<f64>, times: &[f64]) -> Vec<f64> {
+        for &time in times {
+            series.sample(time, time);
+        }
+        series.data().iter().map(|&(time, _)| time).collect()
+    }
+
+    #[test]
+    fn the_first_sample_is_taken_at_the_first_event() {
+        // Grid times 0, 2, 4 and 6; an event exactly on a grid time is sampled
+        let times = [0.5, 1.0, 2.0, 3.0, 5.0, 6.5];
+        assert_eq!(
+            offer(TimeSeries::new(2.0, 10), &times),
+            [0.5, 2.0, 5.0, 6.5]
+        );
+    }
+
+    #[test]
+    fn skipping_the_initial_sample_starts_the_grid_at_one_interval() {
+        let times = [0.5, 1.0, 2.0, 3.0, 5.0, 6.5];
+        let skipped = TimeSeries::new(2.0, 10).skip_initial_sample();
+        assert_eq!(offer(skipped, &times), [2.0, 5.0, 6.5]);
+
+        // After a reset the grid starts at one interval again
+        let mut series = TimeSeries::new(2.0, 10).skip_initial_sample();
+        series.sample(3.0, 3.0);
+        series.reset();
+        assert!(!series.should_sample(1.5));
+        assert!(series.should_sample(2.0));
+    }
+
+    #[test]
+    fn thinning_a_skipped_grid_keeps_the_coarser_grid_times() {
+        // Grid 1, 2, 3, 4 at interval 1; a full series doubles to 2 and keeps 2 and 4
+        let mut series = TimeSeries::new(1.0, 4).skip_initial_sample();
+        for time in [1.0, 2.0, 3.0, 4.0, 5.0, 6.0] {
+            series.sample(time, time);
+        }
+        assert_eq!(series.sample_interval(), 2.0);
+        let kept: Vec<f64> = series.data().iter().map(|&(time, _)| time).collect();
+        assert_eq!(kept, [2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn value_at_holds_the_latest_sample_and_nothing_before_the_first() {
+        let queue = series(&[(1.0, 3usize), (2.0, 5), (4.0, 2)]);
+        assert_eq!(queue.value_at(0.5), None);
+        assert_eq!(queue.value_at(1.0), Some(3));
+        assert_eq!(queue.value_at(3.9), Some(5));
+        assert_eq!(queue.value_at(4.0), Some(2));
+        assert_eq!(queue.value_at(1e9), Some(2));
+        assert_eq!(series::<usize>(&[]).value_at(1.0), None);
+    }
+
+    #[test]
+    fn interpolation_is_limited_to_the_sampled_range() {
+        let wait = series(&[(1.0, 2.0), (3.0, 6.0)]);
+        assert_eq!(wait.interpolate_at(0.999), None);
+        assert_eq!(wait.interpolate_at(1.0), Some(2.0));
+        assert_eq!(wait.interpolate_at(2.5), Some(5.0));
+        assert_eq!(wait.interpolate_at(3.0), Some(6.0));
+        assert_eq!(wait.interpolate_at(3.001), None);
+    }
+
+    #[test]
+    fn a_snapshot_is_empty_before_the_first_sample_and_final_after_the_last() {
+        let mut stats = Statistics::new();
+        let mut time_series = SimulationTimeSeries::new(1.0, 100).skip_initial_sample();
+        // Sampled at 1, 2.5 and 3 on the grid 1, 2, 3
+        for (time, queue_length) in [(0.5, 1), (1.0, 2), (2.5, 1), (3.0, 0)] {
+            stats.record_queue_change(time, queue_length);
+            time_series.sample_statistics(time, &stats);
+        }
+
+        let empty = MetricSnapshot {
+            queue_length: None,
+            mean_wait_time: None,
+            utilization: None,
+            customers_served: None,
+            customers_in_system: None,
+            throughput: None,
+        };
+        assert_eq!(time_series.snapshot_at(0.9), empty);
+        assert_eq!(time_series.snapshot_at(1.0).queue_length, Some(2));
+        assert_eq!(time_series.snapshot_at(2.4).queue_length, Some(2));
+        assert_eq!(time_series.snapshot_at(2.9).queue_length, Some(1));
+        let last = time_series.snapshot_at(100.0);
+        assert_eq!(last.queue_length, Some(0));
+        assert_eq!(last, time_series.snapshot_at(3.0));
+        assert_eq!(last.customers_served, Some(0));
+    }
 }
@@ -0,0 +1,63 @@
+/// Timeline of a single customer through the server
+#[derive(Debug, Clone, Copy)]
+pub struct CustomerRecord {
+    /// Admission order, starting at 0; customers turned away are not counted
+    pub id: u64,
+    pub arrival: f64,
+    pub service_start: f64,
+    pub departure: f64,
+}
+
+impl CustomerRecord {
+    pub fn wait(&self) -> f64 {
+        self.service_start - self.arrival
+    }
+
+    pub fn sojourn(&self) -> f64 {
+        self.departure - self.arrival
+    }
+}
+
+/// Per-customer records of the first `limit` customers admitted, in order of departure
+///
+/// Keeping every customer of a long run would cost far more memory than the
+/// statistics themselves, so only customers whose id is below the limit are
+/// recorded. Under a discipline that overtakes, one of them may depart long
+/// after later arrivals; a customer dropped for missing its deadline never
+/// departs and leaves a gap in the ids.
+#[derive(Debug, Clone, Default)]
+pub struct CustomerTrace {
+    records: Vec<CustomerRecord>,
+    limit: usize,
+}
+
+impl CustomerTrace {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            records: Vec::with_capacity(limit.min(1 << 20)),
+            limit,
+        }
+    }
+
+    /// Drop the records and trace the customers numbered below `limit` from now on, reusing the buffer
+    pub fn reset(&mut self, limit: usize) {
+        self.records.clear();
+        self.records.reserve(limit.min(1 << 20));
+        self.limit = limit;
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    #[inline]
+    pub fn record(&mut self, record: CustomerRecord) {
+        if record.id < self.limit as u64 {
+            self.records.push(record);
+        }
+    }
+
+    pub fn records(&self) -> &[CustomerRecord] {
+        &self.records
+    }
+}
@@ -0,0 +1,279 @@
+//! Built-in models with known closed forms, simulated to check the engine end to end
+//!
+//! Each case runs with a fixed seed, so a verification is reproducible and any
+//! change in its outcome comes from a change in the code. The tolerances are
+//! about four standard errors of each metric at the case's run length, measured
+//! over independent seeds: a correct engine passes with room to spare, while a
+//! bias of a few percent in a mean wait is caught.
+
+use crate::config::{ServiceKind, SimulationConfig};
+use crate::simulation::Simulation;
+use crate::theory::{allen_cunneen_wait, erlang_c, mm1k_customers_distribution};
+use std::fmt;
+
+/// Statistics time discarded at the start of every case, a few relaxation times
+/// of even the most heavily loaded one
+const WARM_UP_TIME: f64 = 1_000.0;
+
+/// Long-run average a case compares with its closed form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifiedMetric {
+    /// Mean wait in queue of the served customers
+    Wait,
+    /// Share of the admitted arrivals that found no free server
+    ProbabilityOfWaiting,
+    QueueLength,
+    CustomersInSystem,
+    Utilization,
+    Throughput,
+    Blocking,
+}
+
+impl VerifiedMetric {
+    /// Short name, the same as the metric's key in `--validate-metrics`
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Wait => "wait",
+            Self::ProbabilityOfWaiting => "waiting",
+            Self::QueueLength => "queue",
+            Self::CustomersInSystem => "system",
+            Self::Utilization => "utilization",
+            Self::Throughput => "throughput",
+            Self::Blocking => "blocking",
+        }
+    }
+
+    /// Value of the metric at the end of a run
+    pub fn measure(self, simulation: &Simulation) -> f64 {
+        let now = simulation.now();
+        let stats = simulation.statistics();
+        match self {
+            Self::Wait => stats.average_wait_time(),
+            Self::ProbabilityOfWaiting => stats.probability_of_waiting(),
+            Self::QueueLength => stats.average_queue_length(now),
+            Self::CustomersInSystem => stats.average_customers_in_system(now),
+            Self::Utilization => stats.utilization(now),
+            Self::Throughput => stats.throughput(now),
+            Self::Blocking => stats.blocking_probability(),
+        }
+    }
+}
+
+impl fmt::Display for VerifiedMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wait => f.pad("wait"),
+            Self::ProbabilityOfWaiting => f.pad("P(wait)"),
+            Self::QueueLength => f.pad("queue length"),
+            Self::CustomersInSystem => f.pad("in system"),
+            Self::Utilization => f.pad("utilization"),
+            Self::Throughput => f.pad("throughput"),
+            Self::Blocking => f.pad("blocking"),
+        }
+    }
+}
+
+/// Closed-form value of one metric and the relative error it may be off by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Expectation {
+    pub metric: VerifiedMetric,
+    pub expected: f64,
+    pub tolerance: f64,
+}
+
+/// A model, how long to run it and what theory says it must produce
+#[derive(Debug, Clone)]
+pub struct VerificationCase {
+    pub name: &'static str,
+    pub config: SimulationConfig,
+    /// Customers served after the warm-up before the metrics are read
+    pub customers: u64,
+    pub expectations: Vec<Expectation>,
+}
+
+/// One metric of one case, measured and compared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Check {
+    pub case: &'static str,
+    pub metric: VerifiedMetric,
+    pub measured: f64,
+    pub expected: f64,
+    pub tolerance: f64,
+}
+
+impl Check {
+    /// Error relative to the expected value
+    pub fn error(&self) -> f64 {
+        (self.measured - self.expected) / self.expected
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error().abs() <= self.tolerance
+    }
+}
+
+/// Every check of a verification, in case order
+#[derive(Debug, Clone, Default)]
+pub struct VerificationReport {
+    pub checks: Vec<Check>,
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+}
+
+fn case_config(lambda: f64, seed: u64) -> SimulationConfig {
+    SimulationConfig {
+        lambda,
+        mu: 1.0,
+        warm_up_time: WARM_UP_TIME,
+        seed: Some(seed),
+        ..Default::default()
+    }
+}
+
+fn expect(metric: VerifiedMetric, expected: f64, tolerance: f64) -> Expectation {
+    Expectation {
+        metric,
+        expected,
+        tolerance,
+    }
+}
+
+/// M/M/1 at ρ = 0.5 and 0.9, M/D/1, M/M/2 and M/M/1/K, all with μ = 1
+pub fn builtin_cases() -> Vec<VerificationCase> {
+    use VerifiedMetric::*;
+
+    // M/M/1: W = ρ/(μ - λ), P(wait) = ρ, Lq = ρ²/(1 - ρ), L = ρ/(1 - ρ). The averages
+    // of the queue share the noise of the wait, utilization and throughput that of the rate
+    let mm1 =
+        |name, rho: f64, seed, customers, wait_tolerance, waiting_tolerance, rate_tolerance| {
+            VerificationCase {
+                name,
+                config: case_config(rho, seed),
+                customers,
+                expectations: vec![
+                    expect(Wait, rho / (1.0 - rho), wait_tolerance),
+                    expect(ProbabilityOfWaiting, rho, waiting_tolerance),
+                    expect(QueueLength, rho * rho / (1.0 - rho), wait_tolerance),
+                    expect(CustomersInSystem, rho / (1.0 - rho), wait_tolerance),
+                    expect(Utilization, rho, rate_tolerance),
+                    expect(Throughput, rho, rate_tolerance),
+                ],
+            }
+        };
+
+    // M/D/1: Pollaczek-Khinchine, to which Allen-Cunneen reduces for one server;
+    // arrivals see time averages, so P(wait) = ρ
+    let md1_rho = 0.8;
+    let md1_wait = allen_cunneen_wait(1, 1.0 / md1_rho, 1.0, 1.0, 0.0).expect("ρ < 1");
+    let md1 = VerificationCase {
+        name: "M/D/1 ρ=0.8",
+        config: SimulationConfig {
+            service_kind: ServiceKind::Deterministic,
+            ..case_config(md1_rho, 3)
+        },
+        customers: 2_000_000,
+        expectations: vec![
+            expect(Wait, md1_wait, 0.016),
+            expect(ProbabilityOfWaiting, md1_rho, 0.005),
+            expect(QueueLength, md1_rho * md1_wait, 0.016),
+            expect(CustomersInSystem, md1_rho * md1_wait + md1_rho, 0.012),
+            expect(Utilization, md1_rho, 0.003),
+            expect(Throughput, md1_rho, 0.003),
+        ],
+    };
+
+    // M/M/2: W = C(2, a)/(2μ - λ), with C(2, a) the probability of waiting
+    let mm2_lambda = 1.6;
+    let mm2_waiting = erlang_c(2, mm2_lambda).expect("ρ < 1");
+    let mm2_wait = mm2_waiting / (2.0 - mm2_lambda);
+    let mm2 = VerificationCase {
+        name: "M/M/2 ρ=0.8",
+        config: SimulationConfig {
+            servers: 2,
+            ..case_config(mm2_lambda, 4)
+        },
+        customers: 2_000_000,
+        expectations: vec![
+            expect(Wait, mm2_wait, 0.028),
+            expect(ProbabilityOfWaiting, mm2_waiting, 0.0075),
+            expect(QueueLength, mm2_lambda * mm2_wait, 0.028),
+            expect(CustomersInSystem, mm2_lambda * (mm2_wait + 1.0), 0.019),
+            expect(Utilization, mm2_lambda / 2.0, 0.0045),
+            expect(Throughput, mm2_lambda, 0.0045),
+        ],
+    };
+
+    // M/M/1/K with K = 10: the queue holds K - 1 besides the one in service
+    let (mm1k_rho, capacity) = (0.9, 10);
+    let p = mm1k_customers_distribution(mm1k_rho, capacity);
+    let blocking = p[capacity];
+    let in_system: f64 = p.iter().enumerate().map(|(n, p)| n as f64 * p).sum();
+    let busy = 1.0 - p[0];
+    let throughput = mm1k_rho * (1.0 - blocking);
+    let mm1k = VerificationCase {
+        name: "M/M/1/10 ρ=0.9",
+        config: SimulationConfig {
+            max_queue_length: Some(capacity - 1),
+            ..case_config(mm1k_rho, 5)
+        },
+        customers: 1_000_000,
+        expectations: vec![
+            expect(Wait, (in_system - busy) / throughput, 0.02),
+            // Of the arrivals not blocked, those finding the server busy
+            expect(
+                ProbabilityOfWaiting,
+                (busy - blocking) / (1.0 - blocking),
+                0.004,
+            ),
+            expect(QueueLength, in_system - busy, 0.02),
+            expect(CustomersInSystem, in_system, 0.016),
+            expect(Utilization, busy, 0.005),
+            expect(Throughput, throughput, 0.004),
+            // A rare event, so far noisier than the averages
+            expect(Blocking, blocking, 0.05),
+        ],
+    };
+
+    vec![
+        mm1("M/M/1 ρ=0.5", 0.5, 1, 1_000_000, 0.02, 0.009, 0.007),
+        mm1("M/M/1 ρ=0.9", 0.9, 2, 5_000_000, 0.035, 0.0035, 0.003),
+        md1,
+        mm2,
+        mm1k,
+    ]
+}
+
+/// Simulate `case` and check each of its expectations
+pub fn run_case(case: &VerificationCase) -> Vec<Check> {
+    let mut simulation = Simulation::new(case.config.clone());
+    while simulation.statistics().served_customers() < case.customers
+        && simulation.engine().has_next_event()
+    {
+        simulation.step();
+    }
+    case.expectations
+        .iter()
+        .map(|expectation| Check {
+            case: case.name,
+            metric: expectation.metric,
+            measured: expectation.metric.measure(&simulation),
+            expected: expectation.expected,
+            tolerance: expectation.tolerance,
+        })
+        .collect()
+}
+
+/// Run every built-in case
+pub fn verify() -> VerificationReport {
+    VerificationReport {
+        checks: builtin_cases().iter().flat_map(run_case).collect(),
+    }
+}
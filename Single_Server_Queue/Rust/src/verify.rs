@@ -0,0 +1,104 @@
+use rust_single_server_queue::clock::measure_clock_precision;
+use rust_single_server_queue::event_order::{GOLDEN_EVENT_COUNT, check_golden};
+use rust_single_server_queue::rng::check_pcg32_golden;
+use rust_single_server_queue::sample_file::check_truncated_file;
+use rust_single_server_queue::verification::{VerificationReport, builtin_cases, run_case};
+
+/// Run the built-in verification cases, printing each check as its case finishes,
+/// then the golden event order, stable generator, epoch clock and sample file checks;
+/// returns false if any failed
+pub fn run_verification() -> bool {
+    println!("=== Verification against theory ===");
+    println!(
+        "{:<16} {:<13} {:>10} {:>10} {:>8} {:>9}  verdict",
+        "case", "metric", "measured", "expected", "error", "tolerance"
+    );
+    let mut report = VerificationReport::default();
+    for case in builtin_cases() {
+        for check in run_case(&case) {
+            println!(
+                "{:<16} {:<13} {:>10.4} {:>10.4} {:>+7.2}% {:>8.2}%  {}",
+                check.case,
+                check.metric,
+                check.measured,
+                check.expected,
+                check.error() * 100.0,
+                check.tolerance * 100.0,
+                if check.passed() { "PASS" } else { "FAIL" }
+            );
+            report.checks.push(check);
+        }
+    }
+
+    let failures = report.failures().count();
+    if failures == 0 {
+        println!("All {} checks passed", report.checks.len());
+    } else {
+        println!("{} of {} checks failed", failures, report.checks.len());
+    }
+
+    println!();
+    let event_order = check_golden();
+    match &event_order {
+        Ok(()) => println!(
+            "Event order: the first {} events match the golden recording",
+            GOLDEN_EVENT_COUNT
+        ),
+        Err(divergence) => {
+            let line = |line: &Option<String>| line.clone().unwrap_or("end of events".to_string());
+            println!(
+                "Event order: FAIL at event {}: expected {}, got {}",
+                divergence.index,
+                line(&divergence.expected),
+                line(&divergence.actual)
+            );
+            println!(
+                "If the reordering is intended, regenerate golden/event_order.csv with \
+                 `cargo run --example golden_events`"
+            );
+        }
+    }
+    let stable_rng = check_pcg32_golden();
+    match &stable_rng {
+        Ok(()) => println!("Stable generator: PCG32 matches its reference and golden draws"),
+        Err(e) => println!("Stable generator: FAIL: {}", e),
+    }
+    let clock = check_epoch_clock();
+    if let Err(e) = &clock {
+        println!("Epoch clock: FAIL: {}", e);
+    }
+    // A chunk cut off inside its samples and one cut off inside its sample count
+    let sample_file = check_truncated_file(1_000, 64, 100)
+        .and_then(|()| check_truncated_file(1_024, 64, 64 * 64 + 2));
+    match &sample_file {
+        Ok(()) => println!("Sample file: a chunk cut off by a killed run is ignored and reported"),
+        Err(e) => println!("Sample file: FAIL: {}", e),
+    }
+    report.passed()
+        && event_order.is_ok()
+        && stable_rng.is_ok()
+        && clock.is_ok()
+        && sample_file.is_ok()
+}
+
+/// Advance a plain and an epoch clock by the same short steps from far horizons,
+/// printing the error of each; fails if the epoch clock lost precision
+fn check_epoch_clock() -> Result<(), String> {
+    const STEPS: u64 = 100_000;
+    const MEAN_STEP: f64 = 0.01;
+    const TOLERANCE: f64 = 1e-9;
+    for start in [1e12, 1e15] {
+        let precision = measure_clock_precision(start, MEAN_STEP, STEPS, 42);
+        println!(
+            "Clock from t = {:.0e}, {} steps of mean {}: plain f64 off by {:.2e}, epoch clock by {:.2e}",
+            start, STEPS, MEAN_STEP, precision.plain_error, precision.epoch_error
+        );
+        if precision.epoch_error.is_nan() || precision.epoch_error > TOLERANCE {
+            return Err(format!(
+                "off by {:.2e} from t = {:.0e}, more than {:.0e}",
+                precision.epoch_error, start, TOLERANCE
+            ));
+        }
+    }
+    Ok(())
+}
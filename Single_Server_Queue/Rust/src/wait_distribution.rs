@@ -0,0 +1,234 @@
+//! Empirical distribution of the waits, for ECDF and QQ plots against the M/M/1 one
+//!
+//! The waits of a run are kept as a [`Histogram`], whose first bin mixes the
+//! customers served at once with those that waited less than a bin width. The
+//! M/M/1 wait is 0 with probability 1 - ρ, so its CDF jumps at 0 and every
+//! quantile up to 1 - ρ is exactly 0. Interpolating within the first bin would
+//! smear that atom over the bin, so the zero waits are counted apart and taken
+//! out of the bin: the ECDF jumps by their share at 0 and the quantiles below it
+//! are 0, as in theory.
+
+use crate::theory::{mm1_wait_cdf, mm1_wait_quantile};
+use crate::time_series::Histogram;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Paired quantiles in a QQ plot unless asked otherwise
+pub const QQ_POINTS: usize = 200;
+
+/// The waits of a run, the positive ones binned and the zero ones counted
+#[derive(Debug, Clone)]
+pub struct WaitDistribution {
+    positive: Histogram,
+    zeros: u64,
+}
+
+impl WaitDistribution {
+    /// Split `histogram` into its `zero_waits` exact zeros, all in its first bin,
+    /// and the rest
+    pub fn new(histogram: &Histogram, zero_waits: u64) -> Self {
+        let zeros = zero_waits.min(histogram.counts().first().copied().unwrap_or(0));
+        let mut positive = histogram.clone();
+        positive.remove_from_first_bin(zeros);
+        Self { positive, zeros }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.positive.total() + self.zeros
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Share of the waits that were 0, the height of the ECDF's jump at 0
+    pub fn zero_share(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.zeros as f64 / total as f64,
+        }
+    }
+
+    /// `(t, share of waits ≤ t)` at t = 0 and at the upper edge of every bin that
+    /// holds a wait, so exact at each of them; empty without waits
+    ///
+    /// The first point is the zero waits' share. Drawn as steps rising at each
+    /// point, this is the ECDF at the resolution of the histogram.
+    pub fn ecdf(&self) -> Vec<(f64, f64)> {
+        let total = self.total();
+        if total == 0 {
+            return Vec::new();
+        }
+        let total = total as f64;
+        let width = self.positive.width();
+        let mut below = self.zeros;
+        let mut points = vec![(0.0, below as f64 / total)];
+        for (k, &count) in self.positive.counts().iter().enumerate() {
+            if count > 0 {
+                below += count;
+                points.push(((k + 1) as f64 * width, below as f64 / total));
+            }
+        }
+        points
+    }
+
+    /// Wait that a share `p` of the customers stayed within, 0 for every `p` up to
+    /// the zero waits' share and interpolated within a bin above it; `None` without waits
+    pub fn quantile(&self, p: f64) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let p = p.clamp(0.0, 1.0);
+        let zero_share = self.zero_share();
+        if p <= zero_share {
+            return Some(0.0);
+        }
+        self.positive
+            .quantile((p - zero_share) / (1.0 - zero_share))
+    }
+}
+
+/// One point of a QQ plot: the theoretical and the empirical quantile at the same probability
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QqPoint {
+    pub probability: f64,
+    pub theoretical: f64,
+    pub empirical: f64,
+}
+
+/// Quantiles of the waits against those of an M/M/1 queue with rates `lambda`
+/// and `mu` at `points` evenly spread probabilities (k + ½)/`points`; `None` if
+/// ρ >= 1 or there are no waits
+///
+/// On a correct model the points follow y = x. Both atoms at 0 meet in the origin,
+/// and a point on either axis shows that one of them holds more probability.
+pub fn mm1_qq_points(
+    waits: &WaitDistribution,
+    lambda: f64,
+    mu: f64,
+    points: usize,
+) -> Option<Vec<QqPoint>> {
+    if waits.is_empty() {
+        return None;
+    }
+    (0..points)
+        .map(|k| {
+            let probability = (k as f64 + 0.5) / points as f64;
+            Some(QqPoint {
+                probability,
+                theoretical: mm1_wait_quantile(lambda, mu, probability)?,
+                empirical: waits.quantile(probability)?,
+            })
+        })
+        .collect()
+}
+
+/// Write the ECDF points of `waits` to a CSV file, with the M/M/1 CDF of the
+/// rates `theory = (λ, μ)` at the same waits when given
+pub fn write_ecdf_csv(
+    path: &Path,
+    waits: &WaitDistribution,
+    theory: Option<(f64, f64)>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "wait,ecdf,theoretical_cdf")?;
+    for (wait, share) in waits.ecdf() {
+        let theoretical = theory
+            .and_then(|(lambda, mu)| mm1_wait_cdf(lambda, mu, wait))
+            .map_or(String::new(), |cdf| cdf.to_string());
+        writeln!(out, "{},{},{}", wait, share, theoretical)?;
+    }
+    out.flush()
+}
+
+/// Write the paired quantiles of a QQ plot to a CSV file
+pub fn write_qq_csv(path: &Path, points: &[QqPoint]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "probability,theoretical,empirical")?;
+    for point in points {
+        writeln!(
+            out,
+            "{},{},{}",
+            point.probability, point.theoretical, point.empirical
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two customers served at once and three that waited 0.25, 0.75 and 2.5,
+    /// binned 0.5 wide
+    fn five_waits() -> WaitDistribution {
+        let mut histogram = Histogram::new(0.5, 16);
+        for wait in [0.0, 0.25, 0.0, 2.5, 0.75] {
+            histogram.push(wait);
+        }
+        WaitDistribution::new(&histogram, 2)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn the_ecdf_jumps_by_the_zero_share_and_is_exact_at_occupied_edges() {
+        let waits = five_waits();
+        assert_eq!(waits.total(), 5);
+        assert!(close(waits.zero_share(), 0.4));
+        assert_eq!(
+            waits.ecdf(),
+            [(0.0, 0.4), (0.5, 0.6), (1.0, 0.8), (3.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn quantiles_are_zero_up_to_the_zero_share() {
+        let waits = five_waits();
+        for p in [0.0, 0.2, 0.4] {
+            assert_eq!(waits.quantile(p), Some(0.0));
+        }
+        // Half of the waits lie in [0, 0.25], a sixth of the positive ones
+        assert!(close(waits.quantile(0.5).unwrap(), 0.25));
+        assert!(close(waits.quantile(1.0).unwrap(), 3.0));
+        assert!(close(waits.quantile(7.0).unwrap(), 3.0));
+    }
+
+    #[test]
+    fn more_zeros_than_the_first_bin_holds_are_capped() {
+        let mut histogram = Histogram::new(0.5, 16);
+        histogram.push(0.0);
+        histogram.push(1.2);
+        let waits = WaitDistribution::new(&histogram, 5);
+        assert_eq!(waits.total(), 2);
+        assert_eq!(waits.ecdf(), [(0.0, 0.5), (1.5, 1.0)]);
+    }
+
+    #[test]
+    fn without_waits_there_is_nothing_to_plot() {
+        let waits = WaitDistribution::new(&Histogram::new(0.5, 16), 0);
+        assert!(waits.is_empty());
+        assert!(waits.ecdf().is_empty());
+        assert_eq!(waits.quantile(0.5), None);
+        assert_eq!(mm1_qq_points(&waits, 0.5, 1.0, 4), None);
+    }
+
+    #[test]
+    fn both_atoms_meet_in_the_origin_of_the_qq_plot() {
+        let points = mm1_qq_points(&five_waits(), 0.5, 1.0, 4).unwrap();
+        let probabilities: Vec<f64> = points.iter().map(|point| point.probability).collect();
+        assert_eq!(probabilities, [0.125, 0.375, 0.625, 0.875]);
+        // M/M/1 at ρ = 0.5 waits 0 with probability 0.5, the sample with 0.4
+        for point in &points[..2] {
+            assert_eq!((point.theoretical, point.empirical), (0.0, 0.0));
+        }
+        for point in &points[2..] {
+            assert!(point.theoretical > 0.0 && point.empirical > 0.0);
+        }
+        // 1 - 0.5 e^{-0.5 t} = 0.875 at t = 2 ln 4
+        assert!(close(points[3].theoretical, 2.0 * 4f64.ln()));
+    }
+}
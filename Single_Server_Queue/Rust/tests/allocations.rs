@@ -0,0 +1,44 @@
+//! Once its buffers have grown, the event loop runs without touching the heap
+#![cfg(feature = "alloc-profile")]
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::profiling::{CountingAllocator, allocation_counts};
+use rust_single_server_queue::simulation::Simulation;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The counters are process-wide, so this file holds a single test: another one
+// running on a second thread would be counted too
+#[test]
+fn stepping_allocates_nothing_after_the_warm_up() {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .max_queue_length(8)
+        .seed(6)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    // The cap bounds the queue buffer; by the first loss it has reached its final size
+    while simulation.event_count() < 200_000 {
+        simulation.step();
+    }
+    assert!(simulation.statistics().lost_customers() > 0);
+
+    // The allocator is installed and counting
+    let probe = allocation_counts();
+    std::hint::black_box(vec![0u8; 16]);
+    assert_eq!(allocation_counts().since(probe).allocations, 1);
+
+    let before = allocation_counts();
+    while simulation.event_count() < 1_200_000 {
+        simulation.step();
+    }
+    let allocated = allocation_counts().since(before);
+    assert_eq!(
+        allocated.allocations, 0,
+        "{} bytes allocated",
+        allocated.bytes
+    );
+}
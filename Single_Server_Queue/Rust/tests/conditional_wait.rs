@@ -0,0 +1,44 @@
+//! Split at service start, an M/M/1 queue delays a share ρ of its customers and
+//! those wait 1/(μ - λ) on average
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+
+#[test]
+fn delayed_customers_wait_one_over_mu_minus_lambda() {
+    for (lambda, mu) in [(0.3, 1.0), (0.8, 1.0), (1.5, 2.0)] {
+        let config = SimulationConfig::builder()
+            .lambda(lambda)
+            .mu(mu)
+            .warm_up_time(1_000.0)
+            .seed(23)
+            .build()
+            .expect("the configuration is valid");
+        let mut simulation = Simulation::new(config);
+        simulation.run(&StopCondition::Customers(400_000), &mut ());
+        let stats = simulation.statistics();
+
+        let rho = lambda / mu;
+        let delayed = stats.delay_probability().unwrap();
+        assert!((delayed - rho).abs() < 0.01, "ρ = {}: {}", rho, delayed);
+
+        let expected = 1.0 / (mu - lambda);
+        let wait = stats.conditional_mean_wait().unwrap();
+        assert!(
+            (wait - expected).abs() < 0.03 * expected,
+            "ρ = {}: {} against {}",
+            rho,
+            wait,
+            expected
+        );
+        // The delayed waits are exponential, so their variance is the mean squared
+        let variance = stats.conditional_wait_variance().unwrap();
+        assert!(
+            (variance - expected * expected).abs() < 0.1 * expected * expected,
+            "ρ = {}: {}",
+            rho,
+            variance
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! The customer trace holds the first customers to arrive, whatever order they leave in
+
+use rust_single_server_queue::config::{RoundRobin, SimulationConfig, SimulationConfigBuilder};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+
+const TRACED: usize = 20;
+
+fn traced_ids(builder: SimulationConfigBuilder) -> Vec<u64> {
+    let config = builder
+        .lambda(0.9)
+        .mu(1.0)
+        .customer_trace_limit(TRACED)
+        .seed(5)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Customers(2_000), &mut ());
+    let records = simulation.server().customer_trace().records();
+    for record in records {
+        assert!(record.arrival <= record.service_start && record.service_start <= record.departure);
+    }
+    records.iter().map(|record| record.id).collect()
+}
+
+#[test]
+fn fifo_customers_leave_in_arrival_order() {
+    let ids = traced_ids(SimulationConfig::builder());
+    assert_eq!(ids, (0..TRACED as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn round_robin_keeps_the_first_arrivals_not_the_first_departures() {
+    let mut ids = traced_ids(SimulationConfig::builder().round_robin(RoundRobin::new(0.1)));
+    // Short services overtake long ones, so the records come out of order
+    assert!(ids.windows(2).any(|pair| pair[0] > pair[1]));
+    ids.sort_unstable();
+    assert_eq!(ids, (0..TRACED as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn customers_turned_away_are_not_numbered() {
+    let ids = traced_ids(SimulationConfig::builder().servers(1).waiting_room(false));
+    assert_eq!(ids, (0..TRACED as u64).collect::<Vec<_>>());
+}
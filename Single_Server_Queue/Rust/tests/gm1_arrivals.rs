@@ -0,0 +1,94 @@
+//! Non-Poisson arrivals to an exponential server wait as G/M/1 predicts, and
+//! Poisson arrivals draw exactly as before
+
+use rust_single_server_queue::config::{ServiceKind, SimulationConfig};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::{gm1_root, gm1_wait};
+
+fn run(arrival_kind: ServiceKind, lambda: f64) -> Simulation {
+    let config = SimulationConfig::builder()
+        .lambda(lambda)
+        .mu(1.0)
+        .arrival_kind(arrival_kind)
+        .warm_up_time(1_000.0)
+        .seed(31)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Customers(400_000), &mut ());
+    simulation
+}
+
+#[test]
+fn renewal_arrivals_wait_as_gm1_predicts() {
+    for (kind, lambda) in [
+        (ServiceKind::Deterministic, 0.5),
+        (ServiceKind::Erlang(2), 0.7),
+        (ServiceKind::Gamma(0.5), 0.6),
+    ] {
+        let mean_interarrival = 1.0 / lambda;
+        let lst = |s: f64| kind.laplace_transform(mean_interarrival, s).unwrap();
+        let expected = gm1_wait(mean_interarrival, 1.0, lst).unwrap();
+        let sigma = gm1_root(mean_interarrival, 1.0, lst).unwrap();
+
+        let simulation = run(kind, lambda);
+        let stats = simulation.statistics();
+        let wait = stats.average_wait_time();
+        assert!(
+            (wait - expected).abs() < 0.04 * expected,
+            "{}: {} against {}",
+            kind,
+            wait,
+            expected
+        );
+        // An arrival waits with probability σ
+        let delayed = stats.delay_probability().unwrap();
+        assert!((delayed - sigma).abs() < 0.01, "{}: {}", kind, delayed);
+        let interarrival = stats.interarrival_times();
+        assert!(
+            (interarrival.mean().unwrap() - mean_interarrival).abs() < 0.01 * mean_interarrival
+        );
+        assert!((interarrival.scv().unwrap() - kind.scv()).abs() < 0.02 + 0.02 * kind.scv());
+    }
+}
+
+#[test]
+fn exponential_interarrival_times_are_the_poisson_default() {
+    let default = run(ServiceKind::Exponential, 0.8);
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .warm_up_time(1_000.0)
+        .seed(31)
+        .build()
+        .expect("the configuration is valid");
+    let mut plain = Simulation::new(config);
+    plain.run(&StopCondition::Customers(400_000), &mut ());
+    assert_eq!(default.now(), plain.now());
+    assert_eq!(
+        default.statistics().average_wait_time(),
+        plain.statistics().average_wait_time()
+    );
+}
+
+#[test]
+fn forks_and_resets_switch_to_the_new_arrival_shape() {
+    let scv = |simulation: &Simulation| simulation.statistics().interarrival_times().scv().unwrap();
+    let poisson = run(ServiceKind::Exponential, 0.5);
+    assert!((scv(&poisson) - 1.0).abs() < 0.02, "{}", scv(&poisson));
+
+    // Only the arrival scheduled before the fork is exponential
+    let fresh = Simulation::new(poisson.config().clone());
+    let mut forked = fresh.fork_with(|config| config.arrival_kind = ServiceKind::Deterministic);
+    forked.run(&StopCondition::Customers(50_000), &mut ());
+    assert!(scv(&forked) < 1e-3, "{}", scv(&forked));
+
+    let mut reset = poisson;
+    reset.reset(SimulationConfig {
+        arrival_kind: ServiceKind::Erlang(4),
+        ..reset.config().clone()
+    });
+    reset.run(&StopCondition::Customers(200_000), &mut ());
+    assert!((scv(&reset) - 0.25).abs() < 0.02, "{}", scv(&reset));
+}
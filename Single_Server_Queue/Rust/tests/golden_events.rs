@@ -0,0 +1,60 @@
+//! The first events of a seeded run happen in the committed order
+//!
+//! After a change meant to reorder events, regenerate the recording with
+//! `cargo run --example golden_events > golden/event_order.csv` and review the diff.
+
+use rust_single_server_queue::event_order::{
+    Divergence, GOLDEN_EVENT_COUNT, GOLDEN_EVENTS, compare, golden_config, record_events, to_csv,
+};
+
+#[test]
+fn events_follow_the_golden_recording() {
+    let recorded = to_csv(&record_events(golden_config(), GOLDEN_EVENT_COUNT));
+    if let Err(divergence) = compare(GOLDEN_EVENTS, &recorded) {
+        panic!(
+            "event {} differs: expected {:?}, got {:?}",
+            divergence.index, divergence.expected, divergence.actual
+        );
+    }
+}
+
+#[test]
+fn the_recording_includes_setups_breakdowns_admissions_and_changes() {
+    for event_type in [
+        "arrival",
+        "departure",
+        "setup",
+        "breakdown",
+        "repair",
+        "admission",
+        "change",
+    ] {
+        assert!(
+            GOLDEN_EVENTS
+                .lines()
+                .any(|line| line.ends_with(&format!(",{}", event_type))),
+            "no {} event",
+            event_type
+        );
+    }
+}
+
+#[test]
+fn a_swapped_pair_of_events_is_reported_where_it_starts() {
+    let mut lines: Vec<&str> = GOLDEN_EVENTS.lines().collect();
+    // Line 0 is the header, so events 10 and 11 are lines 10 and 11
+    lines.swap(10, 11);
+    let reordered = lines.join("\n");
+    assert_eq!(
+        compare(GOLDEN_EVENTS, &reordered),
+        Err(Divergence {
+            index: 10,
+            expected: Some(GOLDEN_EVENTS.lines().nth(10).unwrap().to_string()),
+            actual: Some(GOLDEN_EVENTS.lines().nth(11).unwrap().to_string()),
+        })
+    );
+
+    let shortened = lines[..5].join("\n");
+    let divergence = compare(GOLDEN_EVENTS, &shortened).unwrap_err();
+    assert_eq!((divergence.index, divergence.actual), (5, None));
+}
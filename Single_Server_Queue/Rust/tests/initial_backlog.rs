@@ -0,0 +1,60 @@
+//! Customers present at t = 0 are served away at the rate the spare capacity allows
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::simulation::Simulation;
+
+const BACKLOG: usize = 50;
+const LAMBDA: f64 = 0.5;
+
+fn with_backlog(servers: usize, seed: u64) -> Simulation {
+    let config = SimulationConfig::builder()
+        .lambda(LAMBDA)
+        .mu(1.0)
+        .servers(servers)
+        .initial_queue(BACKLOG)
+        .seed(seed)
+        .build()
+        .expect("the configuration is valid");
+    Simulation::new(config)
+}
+
+/// Time at which the system first becomes empty
+fn drain_time(mut simulation: Simulation) -> f64 {
+    while simulation.statistics().current_customers_in_system() > 0 {
+        simulation.step().expect("a busy system has events pending");
+    }
+    simulation.now()
+}
+
+#[test]
+fn the_backlog_is_in_the_system_before_the_first_event() {
+    for servers in [1, 3] {
+        let simulation = with_backlog(servers, 1);
+        let stats = simulation.statistics();
+        assert_eq!(simulation.now(), 0.0);
+        assert_eq!(stats.current_customers_in_system(), BACKLOG);
+        assert_eq!(stats.current_queue_length(), BACKLOG - servers);
+        assert_eq!(stats.busy_servers(), servers);
+        assert_eq!(stats.arrived_customers(), 0);
+    }
+}
+
+#[test]
+fn the_backlog_drains_at_the_spare_service_rate() {
+    for servers in [1, 3] {
+        // The queue shrinks at cμ - λ on average while all servers are busy
+        let expected = BACKLOG as f64 / (servers as f64 - LAMBDA);
+        let seeds = 40;
+        let mean = (0..seeds)
+            .map(|seed| drain_time(with_backlog(servers, seed)))
+            .sum::<f64>()
+            / seeds as f64;
+        assert!(
+            (mean / expected - 1.0).abs() < 0.15,
+            "{} servers: drained after {} on average, expected about {}",
+            servers,
+            mean,
+            expected
+        );
+    }
+}
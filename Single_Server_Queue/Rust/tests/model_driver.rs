@@ -0,0 +1,217 @@
+//! A minimal custom model driven by `model::run`, under every stop condition
+//! and with observers combined statically and at run time
+
+use rust_single_server_queue::engine::SimulationEngine;
+use rust_single_server_queue::event::{Event, EventType};
+use rust_single_server_queue::model::{self, Deadline, Model, Observer, RunEnd, StopCondition};
+use rust_single_server_queue::network::{NetworkConfig, NetworkSimulation};
+use rust_single_server_queue::rng::RngBackend;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+/// A customer arrives every time unit and leaves half a unit later, until `last` has arrived
+struct Clockwork {
+    last: u64,
+    arrived: u64,
+    departed: u64,
+}
+
+impl Clockwork {
+    fn new(last: u64) -> Self {
+        Self {
+            last,
+            arrived: 0,
+            departed: 0,
+        }
+    }
+}
+
+impl Model for Clockwork {
+    fn init(&mut self, engine: &mut SimulationEngine) {
+        engine.schedule(Event::new(1.0, EventType::Arrival));
+    }
+
+    fn handle(&mut self, event: Event, engine: &mut SimulationEngine) {
+        match event.event_type {
+            EventType::Arrival => {
+                self.arrived += 1;
+                engine.schedule(Event::new(event.time + 0.5, EventType::Departure));
+                if self.arrived < self.last {
+                    engine.schedule(Event::new(event.time + 1.0, EventType::Arrival));
+                }
+            }
+            EventType::Departure => self.departed += 1,
+            other => panic!("unexpected {} event", other),
+        }
+    }
+
+    fn customers_served(&self) -> u64 {
+        self.departed
+    }
+}
+
+/// Counts the events it sees and ends the run after `limit` of them, if given
+#[derive(Default)]
+struct Counter {
+    seen: u64,
+    limit: Option<u64>,
+    finished: Option<RunEnd>,
+}
+
+impl<M: ?Sized> Observer<M> for Counter {
+    fn on_event(
+        &mut self,
+        _model: &M,
+        _engine: &SimulationEngine,
+        _event: &Event,
+    ) -> ControlFlow<()> {
+        self.seen += 1;
+        if self.limit.is_some_and(|limit| self.seen >= limit) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn on_finish(&mut self, _model: &M, _engine: &SimulationEngine, outcome: &model::RunOutcome) {
+        self.finished = Some(outcome.end);
+    }
+}
+
+fn run_clockwork(last: u64, stop: StopCondition) -> (Clockwork, SimulationEngine, RunEnd) {
+    let mut model = Clockwork::new(last);
+    let mut engine = SimulationEngine::new();
+    model.init(&mut engine);
+    let outcome = model::run(&mut model, &mut engine, &stop, &mut ());
+    (model, engine, outcome.end)
+}
+
+#[test]
+fn time_limit_leaves_the_next_event_pending() {
+    let (model, engine, end) = run_clockwork(100, StopCondition::Time(10.0));
+    assert_eq!(end, RunEnd::StopCondition);
+    // Arrivals at 1..=9 and their departures at 1.5..=9.5
+    assert_eq!((model.arrived, model.departed), (9, 9));
+    assert_eq!(engine.peek_next_time(), 10.0);
+}
+
+#[test]
+fn event_and_customer_limits() {
+    let (model, _, end) = run_clockwork(100, StopCondition::Events(7));
+    assert_eq!(end, RunEnd::StopCondition);
+    assert_eq!((model.arrived, model.departed), (4, 3));
+
+    let (model, engine, end) = run_clockwork(100, StopCondition::Customers(5));
+    assert_eq!(end, RunEnd::StopCondition);
+    assert_eq!(model.departed, 5);
+    assert_eq!(engine.now(), 5.5);
+}
+
+#[test]
+fn drained_and_steady_state_runs_end_when_the_events_run_out() {
+    let (model, engine, end) = run_clockwork(20, StopCondition::Drained);
+    assert_eq!(end, RunEnd::OutOfEvents);
+    assert_eq!((model.arrived, model.departed), (20, 20));
+    assert_eq!(engine.now(), 20.5);
+
+    let (model, _, end) = run_clockwork(20, StopCondition::SteadyState(1_000.0));
+    assert_eq!(end, RunEnd::OutOfEvents);
+    assert_eq!(model.departed, 20);
+}
+
+#[test]
+fn a_run_can_be_continued_after_a_time_limit() {
+    let mut model = Clockwork::new(100);
+    let mut engine = SimulationEngine::new();
+    model.init(&mut engine);
+    model::run(&mut model, &mut engine, &StopCondition::Time(5.0), &mut ());
+    let outcome = model::run(&mut model, &mut engine, &StopCondition::Time(10.0), &mut ());
+    assert_eq!(outcome.events, 10);
+    assert_eq!(model.departed, 9);
+}
+
+#[test]
+fn observers_see_every_event_and_can_end_the_run() {
+    let mut model = Clockwork::new(100);
+    let mut engine = SimulationEngine::new();
+    model.init(&mut engine);
+    let mut observers = (
+        Counter {
+            limit: Some(6),
+            ..Counter::default()
+        },
+        Counter::default(),
+        Some(Deadline::after(Duration::from_secs(3_600))),
+    );
+    let outcome = model::run(
+        &mut model,
+        &mut engine,
+        &StopCondition::Drained,
+        &mut observers,
+    );
+
+    assert_eq!(outcome.end, RunEnd::Observer);
+    assert_eq!(outcome.events, 6);
+    let (breaking, watching, deadline) = observers;
+    assert_eq!(breaking.seen, 6);
+    assert_eq!(watching.seen, 6);
+    assert_eq!(watching.finished, Some(RunEnd::Observer));
+    assert!(!deadline.unwrap().has_passed());
+}
+
+#[test]
+fn observers_chosen_at_run_time() {
+    let mut first = Counter::default();
+    let mut second = Counter {
+        limit: Some(3),
+        ..Counter::default()
+    };
+    let mut observers: Vec<&mut dyn Observer<Clockwork>> = vec![&mut first, &mut second];
+
+    let mut model = Clockwork::new(100);
+    let mut engine = SimulationEngine::new();
+    model.init(&mut engine);
+    let outcome = model::run(
+        &mut model,
+        &mut engine,
+        &StopCondition::Events(10),
+        observers.as_mut_slice(),
+    );
+
+    assert_eq!(outcome.end, RunEnd::Observer);
+    assert_eq!((first.seen, second.seen), (3, 3));
+    assert_eq!(first.finished, Some(RunEnd::Observer));
+}
+
+#[test]
+fn network_runs_behind_the_model_trait() {
+    let config = NetworkConfig {
+        external_arrival_rates: vec![0.5, 0.0],
+        service_rates: vec![1.0, 1.0],
+        routing: vec![vec![0.0, 1.0], vec![0.0, 0.0]],
+        warm_up_time: 0.0,
+        seed: Some(7),
+        rng_backend: RngBackend::default(),
+    };
+    let mut network = NetworkSimulation::new(config.clone()).unwrap();
+    let mut engine = SimulationEngine::new();
+    network.init(&mut engine);
+    let mut counter = Counter::default();
+    let outcome = model::run(
+        &mut network,
+        &mut engine,
+        &StopCondition::Customers(1_000),
+        &mut counter,
+    );
+
+    assert_eq!(outcome.end, RunEnd::StopCondition);
+    assert_eq!(network.departed_customers(), 1_000);
+    assert_eq!(counter.seen, network.event_count());
+    assert_eq!(engine.now(), network.now());
+
+    // The same seed run by time through `run_until` takes the same path
+    let mut by_time = NetworkSimulation::new(config).unwrap();
+    by_time.run_until(network.now());
+    assert_eq!(by_time.departed_customers(), 999);
+    assert_eq!(by_time.event_count(), network.event_count() - 1);
+}
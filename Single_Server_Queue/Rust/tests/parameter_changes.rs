@@ -0,0 +1,64 @@
+//! A change of μ during a run applies to the customers still waiting, not to
+//! the service in progress
+
+use rust_single_server_queue::config::{
+    ChangedParameter, ParameterChange, ServiceKind, SimulationConfig,
+};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+
+/// Ten customers present at the start and one arriving at 0, the only arrival before
+/// the cutoff, served in turn at μ = 1 until μ doubles at 2.5
+fn drain_backlog(service_kind: ServiceKind) -> Simulation {
+    let config = SimulationConfig::builder()
+        .lambda(1e-9)
+        .mu(1.0)
+        .service_kind(service_kind)
+        .initial_queue(10)
+        .arrival_cutoff(1e-6)
+        .parameter_changes(vec![ParameterChange {
+            time: 2.5,
+            parameter: ChangedParameter::ServiceRate,
+            value: 2.0,
+        }])
+        .seed(3)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Drained, &mut ());
+    simulation
+}
+
+#[test]
+fn waiting_customers_are_served_at_the_new_rate() {
+    let simulation = drain_backlog(ServiceKind::Deterministic);
+    // Three services of 1.0, the third in progress at the change, then eight of 0.5
+    assert_eq!(simulation.statistics().served_customers(), 11);
+    assert_eq!(simulation.now(), 3.0 + 8.0 * 0.5);
+}
+
+#[test]
+fn random_services_keep_their_draws_across_the_change() {
+    let exponential = drain_backlog(ServiceKind::Exponential);
+    let statistics = exponential.statistics();
+    assert_eq!(statistics.served_customers(), 11);
+
+    // With the same seed the same uniforms come out, so the backlog drains no
+    // later than at μ = 1 throughout and no earlier than at μ = 2 throughout
+    let config = |mu: f64| {
+        SimulationConfig::builder()
+            .lambda(1e-9)
+            .mu(mu)
+            .initial_queue(10)
+            .arrival_cutoff(1e-6)
+            .seed(3)
+            .build()
+            .expect("the configuration is valid")
+    };
+    let mut slow = Simulation::new(config(1.0));
+    slow.run(&StopCondition::Drained, &mut ());
+    let mut fast = Simulation::new(config(2.0));
+    fast.run(&StopCondition::Drained, &mut ());
+    assert!(fast.now() < exponential.now() && exponential.now() < slow.now());
+    assert!((slow.now() - 2.0 * fast.now()).abs() < 1e-9);
+}
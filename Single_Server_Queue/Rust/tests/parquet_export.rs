@@ -0,0 +1,166 @@
+//! Samples written with `--parquet` read back with their types and run metadata
+#![cfg(feature = "parquet")]
+
+use arrow_array::{Array, Float64Array, RecordBatch, UInt32Array, UInt64Array};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::parquet::ParquetRunWriter;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+/// A short seeded run with its samples
+fn sampled_run(lambda: f64, seed: u64) -> (SimulationConfig, SimulationTimeSeries) {
+    let config = SimulationConfig::builder()
+        .lambda(lambda)
+        .mu(1.0)
+        .seed(seed)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config.clone());
+    let mut time_series = SimulationTimeSeries::new(5.0, 100);
+    while simulation.now() < 200.0 && simulation.step().is_some() {
+        time_series.sample_statistics(simulation.now(), simulation.statistics());
+    }
+    (config, time_series)
+}
+
+/// A file in the temporary directory, removed again when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("ssq-{}-{}.parquet", std::process::id(), name)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Row batches and key-value metadata of a written file
+fn read(file: &TempFile) -> (Vec<RecordBatch>, HashMap<String, String>) {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&file.0).unwrap()).unwrap();
+    let metadata = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .into_iter()
+        .flatten()
+        .filter_map(|kv| Some((kv.key.clone(), kv.value.clone()?)))
+        .collect();
+    let batches = builder.build().unwrap().collect::<Result<_, _>>().unwrap();
+    (batches, metadata)
+}
+
+/// Values of one column over all batches, `None` where null
+fn column<A: Array + 'static, T>(
+    batches: &[RecordBatch],
+    name: &str,
+    value: impl Fn(&A, usize) -> T,
+) -> Vec<Option<T>> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let array = batch
+                .column_by_name(name)
+                .unwrap_or_else(|| panic!("no {} column", name))
+                .as_any()
+                .downcast_ref::<A>()
+                .unwrap_or_else(|| panic!("{} has another type", name));
+            (0..array.len())
+                .map(|i| array.is_valid(i).then(|| value(array, i)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn floats(batches: &[RecordBatch], name: &str) -> Vec<Option<f64>> {
+    column(batches, name, |array: &Float64Array, i| array.value(i))
+}
+
+fn counts(batches: &[RecordBatch], name: &str) -> Vec<Option<u64>> {
+    column(batches, name, |array: &UInt64Array, i| array.value(i))
+}
+
+#[test]
+fn a_run_reads_back_sample_for_sample() {
+    let file = TempFile::new("single");
+    let (config, time_series) = sampled_run(0.8, 2);
+    time_series.write_parquet(&file.0, &config).unwrap();
+    let (batches, metadata) = read(&file);
+
+    let expected_times: Vec<Option<f64>> = time_series
+        .queue_length
+        .data()
+        .iter()
+        .map(|&(time, _)| Some(time))
+        .collect();
+    assert!(expected_times.len() > 30);
+    assert_eq!(floats(&batches, "time"), expected_times);
+
+    let expected_queue: Vec<Option<u64>> = time_series
+        .queue_length
+        .data()
+        .iter()
+        .map(|&(_, length)| Some(length as u64))
+        .collect();
+    assert_eq!(counts(&batches, "queue_length"), expected_queue);
+
+    let expected_wait: Vec<Option<f64>> = time_series
+        .mean_wait_time
+        .data()
+        .iter()
+        .map(|&(_, wait)| Some(wait))
+        .collect();
+    assert_eq!(floats(&batches, "mean_wait_time"), expected_wait);
+
+    // A confidence bound is null until there are enough batches for one
+    let lower = floats(&batches, "mean_wait_lower");
+    let expected_lower: Vec<Option<f64>> = time_series
+        .mean_wait_ci
+        .data()
+        .iter()
+        .map(|&(_, ci)| ci.map(|(lower, _)| lower))
+        .collect();
+    assert_eq!(lower, expected_lower);
+    assert_eq!(lower[0], None);
+
+    assert_eq!(metadata["run.0.label"], "run");
+    assert_eq!(metadata["run.0.lambda"], "0.8");
+    assert_eq!(metadata["run.0.seed"], "2");
+}
+
+#[test]
+fn several_runs_share_one_file_told_apart_by_run_id() {
+    let file = TempFile::new("study");
+    let runs = [sampled_run(0.5, 1), sampled_run(0.9, 1)];
+    let mut writer = ParquetRunWriter::create(&file.0).unwrap();
+    for (id, (config, time_series)) in runs.iter().enumerate() {
+        writer
+            .write_run(
+                id as u32,
+                &format!("rho {}", config.lambda),
+                config,
+                time_series,
+            )
+            .unwrap();
+    }
+    writer.close().unwrap();
+    let (batches, metadata) = read(&file);
+
+    let run_ids = column(&batches, "run_id", |array: &UInt32Array, i| array.value(i));
+    for (id, (_, time_series)) in runs.iter().enumerate() {
+        let rows = run_ids
+            .iter()
+            .filter(|&&run| run == Some(id as u32))
+            .count();
+        assert_eq!(rows, time_series.queue_length.len());
+    }
+    assert_eq!(metadata["run.0.label"], "rho 0.5");
+    assert_eq!(metadata["run.1.lambda"], "0.9");
+}
@@ -0,0 +1,45 @@
+//! The share of arrivals that wait converges to ρ for M/M/1 and to Erlang C for M/M/c
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::erlang_c;
+
+fn probability_of_waiting(lambda: f64, servers: usize, waiting_room: bool) -> f64 {
+    let config = SimulationConfig::builder()
+        .lambda(lambda)
+        .mu(1.0)
+        .servers(servers)
+        .waiting_room(waiting_room)
+        .seed(8)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Customers(300_000), &mut ());
+    simulation.statistics().probability_of_waiting()
+}
+
+#[test]
+fn a_single_server_delays_a_share_of_rho() {
+    for rho in [0.3, 0.6, 0.9] {
+        let delayed = probability_of_waiting(rho, 1, true);
+        assert!((delayed - rho).abs() < 0.01, "ρ = {}: {}", rho, delayed);
+    }
+}
+
+#[test]
+fn several_servers_delay_the_erlang_c_share() {
+    let expected = erlang_c(3, 2.1).unwrap();
+    let delayed = probability_of_waiting(2.1, 3, true);
+    assert!(
+        (delayed - expected).abs() < 0.01,
+        "{} against {}",
+        delayed,
+        expected
+    );
+}
+
+#[test]
+fn nobody_waits_in_a_loss_system() {
+    assert_eq!(probability_of_waiting(2.1, 3, false), 0.0);
+}
@@ -0,0 +1,92 @@
+//! A reset simulation reruns exactly as a freshly built one
+
+use rust_single_server_queue::config::{Breakdowns, InterruptedService, SimulationConfig};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::rng::RngBackend;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::statistics::Totals;
+
+/// Everything the comparison looks at after a run
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    events: u64,
+    now: f64,
+    totals: Totals,
+    lost: u64,
+    breakdowns: u64,
+    observation_start: f64,
+    instrumented: Option<(u64, u64, usize)>,
+}
+
+fn run(simulation: &mut Simulation) -> Outcome {
+    simulation.run(&StopCondition::Customers(20_000), &mut ());
+    let now = simulation.now();
+    let stats = simulation.statistics();
+    Outcome {
+        events: simulation.event_count(),
+        now,
+        totals: stats.totals(now),
+        lost: stats.lost_customers(),
+        breakdowns: stats.breakdowns(),
+        observation_start: stats.observation_start(),
+        instrumented: simulation
+            .engine()
+            .metrics()
+            .map(|m| (m.arrival.processed, m.departure.scheduled, m.peak_pending)),
+    }
+}
+
+fn configs() -> Vec<(&'static str, SimulationConfig)> {
+    let base = || SimulationConfig::builder().lambda(0.8).mu(1.0).seed(11);
+    vec![
+        ("M/M/1", base().build()),
+        ("warm-up", base().warm_up_time(500.0).build()),
+        (
+            "breakdowns",
+            base()
+                .breakdowns(Breakdowns {
+                    mean_time_to_failure: 50.0,
+                    mean_repair_time: 2.0,
+                    interrupted_service: InterruptedService::Restart,
+                })
+                .build(),
+        ),
+        (
+            "loss system",
+            base().lambda(2.0).servers(3).waiting_room(false).build(),
+        ),
+        (
+            "xoshiro",
+            base().rng_backend(RngBackend::Xoshiro256PlusPlus).build(),
+        ),
+        ("instrumented", base().instrumentation(true).build()),
+    ]
+    .into_iter()
+    .map(|(name, config)| (name, config.expect("the configuration is valid")))
+    .collect()
+}
+
+#[test]
+fn reset_and_rerun_equals_a_fresh_run() {
+    for (name, config) in configs() {
+        let fresh = run(&mut Simulation::new(config.clone()));
+
+        // Reset a simulation that already ran something else
+        let mut reused = Simulation::new(
+            SimulationConfig::builder()
+                .lambda(0.5)
+                .mu(1.0)
+                .servers(2)
+                .seed(99)
+                .build()
+                .unwrap(),
+        );
+        run(&mut reused);
+        reused.reset(config.clone());
+        assert_eq!(run(&mut reused), fresh, "{}", name);
+
+        // And once more with the same configuration
+        reused.reset(config);
+        assert_eq!(run(&mut reused), fresh, "{} after a second reset", name);
+    }
+}
@@ -0,0 +1,57 @@
+//! The final statistics of the benchmark's seeded run do not change
+//!
+//! This is the digest `examples/benchmark.rs` prints, over a shorter run. A change
+//! to the event loop that is not meant to alter results must leave it as it is.
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+
+const EVENTS: u64 = 1_000_000;
+const DIGEST: u64 = 0x324b_3d0e_1d8e_f938;
+
+/// FNV-1a over the bit patterns of the given values
+fn digest(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+fn seeded() -> Simulation {
+    Simulation::new(SimulationConfig {
+        seed: Some(42),
+        ..SimulationConfig::default()
+    })
+}
+
+fn result_digest(simulation: &Simulation) -> u64 {
+    let now = simulation.now();
+    let stats = simulation.statistics();
+    digest(&[
+        now.to_bits(),
+        stats.served_customers(),
+        stats.average_wait_time().to_bits(),
+        stats.average_queue_length(now).to_bits(),
+        stats.average_customers_in_system(now).to_bits(),
+        stats.utilization(now).to_bits(),
+    ])
+}
+
+#[test]
+fn stepping_gives_the_committed_digest() {
+    let mut simulation = seeded();
+    while simulation.event_count() < EVENTS {
+        simulation.step();
+    }
+    assert_eq!(result_digest(&simulation), DIGEST);
+}
+
+#[test]
+fn the_driver_gives_the_committed_digest() {
+    let mut simulation = seeded();
+    simulation.run(&StopCondition::Events(EVENTS), &mut ());
+    assert_eq!(result_digest(&simulation), DIGEST);
+}
@@ -0,0 +1,53 @@
+//! The simulated M/M/1 means do not depend on the random number generator
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::rng::RngBackend;
+use rust_single_server_queue::simulation::Simulation;
+
+const BACKENDS: [RngBackend; 3] = [
+    RngBackend::Fastrand,
+    RngBackend::Xoshiro256PlusPlus,
+    RngBackend::Pcg32,
+];
+
+/// Mean wait in queue and mean number in system of an M/M/1 queue at ρ = 0.5
+fn mm1_means(rng_backend: RngBackend) -> (f64, f64) {
+    let config = SimulationConfig::builder()
+        .lambda(0.5)
+        .mu(1.0)
+        .rng_backend(rng_backend)
+        .seed(42)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Customers(400_000), &mut ());
+    let stats = simulation.statistics();
+    (
+        stats.average_wait_time(),
+        stats.average_customers_in_system(simulation.now()),
+    )
+}
+
+#[test]
+fn every_backend_converges_to_the_mm1_means() {
+    let means: Vec<(f64, f64)> = BACKENDS.iter().map(|&backend| mm1_means(backend)).collect();
+    for (backend, (wait, in_system)) in BACKENDS.iter().zip(&means) {
+        // Wq = ρ / (μ - λ) = 1 and L = ρ / (1 - ρ) = 1; a run of this length
+        // has a standard error of about 1% on both
+        assert!(
+            (wait - 1.0).abs() < 0.05,
+            "{:?}: mean wait {}",
+            backend,
+            wait
+        );
+        assert!(
+            (in_system - 1.0).abs() < 0.05,
+            "{:?}: mean in system {}",
+            backend,
+            in_system
+        );
+    }
+    // The generators really differ, so the runs do too
+    assert!(means[0] != means[1] && means[1] != means[2] && means[0] != means[2]);
+}
@@ -0,0 +1,86 @@
+//! A SimulationResult carries the same figures the simulation reports after the
+//! run, and the same statistics an unsampled run reaches
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::{RunEnd, StopCondition};
+use rust_single_server_queue::simulation::{Simulation, SimulationResult};
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+
+fn config(warm_up_time: f64) -> SimulationConfig {
+    SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .warm_up_time(warm_up_time)
+        .seed(11)
+        .build()
+        .expect("the configuration is valid")
+}
+
+fn sampled(simulation: &mut Simulation, stop: StopCondition) -> SimulationResult {
+    simulation.run_sampled(&stop, SimulationTimeSeries::new(10.0, 1000), &mut ())
+}
+
+fn assert_matches(result: &SimulationResult, simulation: &Simulation) {
+    assert_eq!(result.total_time, simulation.now());
+    assert_eq!(result.event_count, simulation.event_count());
+    assert_eq!(
+        result.served_customers,
+        simulation.statistics().served_customers()
+    );
+    assert_eq!(
+        result.statistics.served_customers(),
+        result.served_customers
+    );
+    assert_eq!(
+        result.statistics.average_wait_time(),
+        simulation.statistics().average_wait_time()
+    );
+}
+
+#[test]
+fn the_result_matches_the_simulation_after_the_run() {
+    let mut simulation = Simulation::new(config(0.0));
+    let result = sampled(&mut simulation, StopCondition::Time(5_000.0));
+    assert_eq!(result.outcome.end, RunEnd::StopCondition);
+    assert_eq!(result.outcome.events, simulation.event_count());
+    assert!(result.total_time <= 5_000.0);
+    assert_matches(&result, &simulation);
+
+    // Sampled at the first event of every 10 time units
+    let samples = result.time_series.mean_wait_time.data();
+    assert!(
+        samples.len() >= 490 && samples.len() <= 501,
+        "{}",
+        samples.len()
+    );
+    assert!(samples.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert!(samples.last().unwrap().0 <= result.total_time);
+}
+
+#[test]
+fn sampling_does_not_change_the_run() {
+    let stop = StopCondition::Customers(20_000);
+    let mut plain = Simulation::new(config(500.0));
+    let outcome = plain.run(&stop, &mut ());
+    let mut sampled_run = Simulation::new(config(500.0));
+    let result = sampled(&mut sampled_run, stop);
+
+    assert_eq!(result.outcome, outcome);
+    assert_matches(&result, &plain);
+    // The counts after the warm-up, the clock and events since t = 0
+    assert!(result.served_customers < result.event_count / 2);
+}
+
+#[test]
+fn a_continued_run_counts_its_own_events_and_keeps_the_clock() {
+    let mut simulation = Simulation::new(config(0.0));
+    let first = sampled(&mut simulation, StopCondition::Time(1_000.0));
+    let second = sampled(&mut simulation, StopCondition::Time(2_000.0));
+    assert_matches(&second, &simulation);
+    assert_eq!(
+        second.event_count,
+        first.event_count + second.outcome.events
+    );
+    assert!(second.total_time > first.total_time);
+    assert!(second.served_customers > first.served_customers);
+}
@@ -0,0 +1,89 @@
+//! The time series stay within their sample cap and cover the run evenly,
+//! whether the run ends far earlier or far later than one interval per sample suggests
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::{Simulation, SimulationResult};
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+
+const MAX_SAMPLES: usize = 200;
+const LAMBDA: f64 = 0.8;
+
+/// A run sampled from one sample per mean interarrival time, as the binary does
+/// for stop conditions without a known horizon
+fn sampled(stop: StopCondition) -> SimulationResult {
+    let config = SimulationConfig::builder()
+        .lambda(LAMBDA)
+        .mu(1.0)
+        .seed(5)
+        .build()
+        .expect("the configuration is valid");
+    let time_series = SimulationTimeSeries::new(1.0 / LAMBDA, MAX_SAMPLES);
+    Simulation::new(config).run_sampled(&stop, time_series, &mut ())
+}
+
+/// Check the series of a run ending at `stop` and return its final interval
+fn check(stop: StopCondition) -> f64 {
+    let result = sampled(stop);
+    let queue = &result.time_series.queue_length;
+    let interval = queue.sample_interval();
+    assert!(!queue.is_empty(), "{:?}", stop);
+    assert!(
+        queue.len() <= MAX_SAMPLES,
+        "{:?}: {} samples",
+        stop,
+        queue.len()
+    );
+
+    // The interval only ever doubles
+    let doublings = (interval * LAMBDA).log2();
+    assert_eq!(
+        doublings,
+        doublings.round(),
+        "{:?}: interval {}",
+        stop,
+        interval
+    );
+
+    // Sample k is taken at the first event at or after grid time k * interval
+    for (k, &(time, _)) in queue.data().iter().enumerate() {
+        let grid = k as f64 * interval;
+        assert!(
+            time >= grid,
+            "{:?}: sample {} at {} before {}",
+            stop,
+            k,
+            time,
+            grid
+        );
+    }
+    // Only the last, partly filled grid step lacks a sample
+    let last = queue.data().last().unwrap().0;
+    assert!(result.total_time - last < 2.0 * interval, "{:?}", stop);
+    if doublings > 0.0 {
+        assert!(queue.len() > MAX_SAMPLES / 2, "{:?}", stop);
+    }
+    interval
+}
+
+#[test]
+fn runs_far_shorter_than_the_cap_keep_the_initial_interval() {
+    for stop in [
+        StopCondition::Customers(10),
+        StopCondition::Events(20),
+        StopCondition::Time(5.0),
+    ] {
+        assert_eq!(check(stop), 1.0 / LAMBDA);
+    }
+}
+
+#[test]
+fn runs_far_longer_than_the_cap_coarsen_within_it() {
+    for stop in [
+        StopCondition::Customers(200_000),
+        StopCondition::Events(400_000),
+        StopCondition::Time(250_000.0),
+    ] {
+        assert!(check(stop) > 100.0 / LAMBDA);
+    }
+}
@@ -0,0 +1,76 @@
+//! A snapshot of the station agrees with the full lists of customers inside it
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::entities::ServerState;
+use rust_single_server_queue::simulation::Simulation;
+
+fn simulation(servers: usize, initial_queue: usize) -> Simulation {
+    let config = SimulationConfig::builder()
+        .lambda(0.9 * servers as f64)
+        .mu(1.0)
+        .servers(servers)
+        .initial_queue(initial_queue)
+        .seed(10)
+        .build()
+        .expect("the configuration is valid");
+    Simulation::new(config)
+}
+
+/// The snapshot rebuilt from the customers in service and in the queue
+fn expected_state(simulation: &Simulation) -> ServerState {
+    let now = simulation.now();
+    let server = simulation.server();
+    let in_service = server.services_in_progress();
+    let mut waiting = server.waiting_arrivals();
+    let front_arrival = waiting.next();
+    ServerState {
+        queue_length: front_arrival.map_or(0, |_| 1 + waiting.count()),
+        busy: !in_service.is_empty(),
+        remaining_service: in_service.first().map(|service| service.completion - now),
+        front_wait: front_arrival.map(|arrival| now - arrival),
+    }
+}
+
+#[test]
+fn an_empty_station_has_nothing_to_report() {
+    assert_eq!(
+        simulation(1, 0).server_state(),
+        ServerState {
+            queue_length: 0,
+            busy: false,
+            remaining_service: None,
+            front_wait: None,
+        }
+    );
+}
+
+#[test]
+fn an_initial_backlog_shows_before_the_first_event() {
+    let simulation = simulation(2, 5);
+    let state = simulation.server_state();
+    assert_eq!(state.queue_length, 3);
+    assert!(state.busy);
+    assert!(state.remaining_service.unwrap() > 0.0);
+    assert_eq!(state.front_wait, Some(0.0));
+}
+
+#[test]
+fn the_snapshot_matches_the_customer_lists_after_every_event() {
+    for servers in [1, 3] {
+        let mut simulation = simulation(servers, 4);
+        let mut busy_with_queue = 0;
+        for _ in 0..5_000 {
+            simulation.step();
+            let state = simulation.server_state();
+            assert_eq!(
+                state,
+                expected_state(&simulation),
+                "t = {}",
+                simulation.now()
+            );
+            busy_with_queue += usize::from(state.busy && state.queue_length > 0);
+        }
+        // The comparison covered waiting customers, not just an idle station
+        assert!(busy_with_queue > 1_000, "{} servers", servers);
+    }
+}
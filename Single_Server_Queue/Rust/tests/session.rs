@@ -0,0 +1,147 @@
+//! A saved viewer session reopens as it was saved, also when written by another version
+
+use rust_single_server_queue::annotations::{Annotation, AnnotationCategory};
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::session::{
+    DEFAULT_HISTOGRAM_BUCKETS, PanelView, SESSION_FORMAT_VERSION, Session, SessionRun,
+};
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::time_series::{Normalization, SimulationTimeSeries};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// A file in the temporary directory, removed again when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("ssq-{}-{}.session", std::process::id(), name)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn session() -> Session {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .seed(4)
+        .build()
+        .expect("the configuration is valid");
+    let result = Simulation::new(config).run_sampled(
+        &StopCondition::Time(500.0),
+        SimulationTimeSeries::new(5.0, 100),
+        &mut (),
+    );
+
+    let mut session = Session {
+        runs: vec![SessionRun {
+            label: "A".to_string(),
+            time_series: result.time_series,
+        }],
+        report: "| metric | observed |\n|---|---|".to_string(),
+        annotations: vec![Annotation {
+            time: 120.5,
+            label: "μ → 1.2".to_string(),
+            category: AnnotationCategory::Change,
+        }],
+        warm_up_time: Some(50.0),
+        theoretical_wait: Some((0.8, 1.0)),
+        ..Session::default()
+    };
+    session.view.panels.insert(
+        "queue_length".to_string(),
+        PanelView {
+            bounds: Some(([10.0, 0.0], [250.0, 12.5])),
+            derivative: true,
+            derivative_stride: 4,
+        },
+    );
+    session.view.hidden_annotations = vec![AnnotationCategory::Failure];
+    session.view.windowed_throughput = true;
+    session.view.overlay_normalization = Normalization::ZScore;
+    session
+}
+
+/// Write `value` as a MessagePack map to `file`, like a session of another version
+fn write_named<T: serde::Serialize>(file: &TempFile, value: &T) {
+    fs::write(&file.0, rmp_serde::to_vec_named(value).unwrap()).unwrap();
+}
+
+#[test]
+fn a_saved_session_reopens_unchanged() {
+    let file = TempFile::new("round-trip");
+    let saved = session();
+    saved.save(&file.0).unwrap();
+    let loaded = Session::load(&file.0).unwrap();
+    // Every field is plain data whose debug output shows all of it, floats exactly
+    assert_eq!(format!("{:?}", loaded), format!("{:?}", saved));
+    assert!(!loaded.runs[0].time_series.queue_length.is_empty());
+}
+
+#[test]
+fn fields_missing_from_an_older_file_take_their_defaults() {
+    let file = TempFile::new("older");
+    let mut older = BTreeMap::new();
+    older.insert("version", 1);
+    write_named(&file, &older);
+
+    let loaded = Session::load(&file.0).unwrap();
+    assert!(loaded.runs.is_empty() && loaded.annotations.is_empty());
+    assert!(loaded.view.show_ensemble_bands);
+    assert_eq!(
+        loaded.view.wait_histogram_buckets,
+        DEFAULT_HISTOGRAM_BUCKETS
+    );
+    assert_eq!(loaded.view.overlay_metrics.len(), 3);
+}
+
+#[test]
+fn fields_of_a_newer_file_are_ignored() {
+    #[derive(serde::Serialize)]
+    struct Newer {
+        version: u32,
+        report: String,
+        added_later: Vec<u8>,
+    }
+
+    let file = TempFile::new("newer-field");
+    write_named(
+        &file,
+        &Newer {
+            version: SESSION_FORMAT_VERSION,
+            report: "kept".to_string(),
+            added_later: vec![1, 2, 3],
+        },
+    );
+    assert_eq!(Session::load(&file.0).unwrap().report, "kept");
+}
+
+#[test]
+fn a_newer_format_is_refused() {
+    let file = TempFile::new("newer-format");
+    let mut newer = BTreeMap::new();
+    newer.insert("version", SESSION_FORMAT_VERSION + 1);
+    write_named(&file, &newer);
+
+    let error = Session::load(&file.0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("newer than the supported"));
+}
+
+#[test]
+fn a_file_that_is_no_session_is_refused() {
+    let file = TempFile::new("garbage");
+    fs::write(&file.0, b"time,queue_length\n0,0\n").unwrap();
+    assert_eq!(
+        Session::load(&file.0).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+}
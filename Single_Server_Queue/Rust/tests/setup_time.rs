@@ -0,0 +1,152 @@
+//! Setups at the start of each busy period: a setup whose queue empties before it
+//! completes is wasted but counted, and the setup statistics of an M/M/1 queue
+//! follow the decomposition with the setup's extra wait
+
+use rust_single_server_queue::config::{Deadlines, ServiceKind, SetupTime, SimulationConfig};
+use rust_single_server_queue::event::EventType;
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::{allen_cunneen_wait, setup_extra_wait};
+
+/// Setups of 2 and customers who give up once 0.5 has passed without service
+fn impatient(lambda: f64, arrival_cutoff: Option<f64>) -> Simulation {
+    let mut builder = SimulationConfig::builder()
+        .lambda(lambda)
+        .mu(1.0)
+        .setup(SetupTime {
+            mean: 2.0,
+            kind: ServiceKind::Deterministic,
+        })
+        .deadlines(Deadlines {
+            mean: 0.5,
+            kind: ServiceKind::Deterministic,
+            drop_late: true,
+        })
+        .seed(5);
+    if let Some(cutoff) = arrival_cutoff {
+        builder = builder.arrival_cutoff(cutoff);
+    }
+    Simulation::new(builder.build().expect("the configuration is valid"))
+}
+
+#[test]
+fn a_setup_whose_customer_left_is_counted_and_switches_the_station_off() {
+    // A single arrival at 0, gone by the time the setup completes at 2
+    let mut simulation = impatient(1e-9, Some(1e-6));
+    let arrival = simulation.step().expect("the arrival is due");
+    assert_eq!(
+        (arrival.time, arrival.event_type),
+        (0.0, EventType::Arrival)
+    );
+    assert!(simulation.server().is_setting_up());
+    assert_eq!(simulation.statistics().setups(), 0);
+    assert_eq!(simulation.statistics().setup_time(1.0), 1.0);
+
+    let setup = simulation.step().expect("the setup completes");
+    assert_eq!((setup.time, setup.event_type), (2.0, EventType::Setup));
+    let server = simulation.server();
+    assert!(!server.is_setting_up() && !server.is_busy());
+    assert_eq!(server.queue_length(), 0);
+
+    let stats = simulation.statistics();
+    assert_eq!(stats.setups(), 1);
+    assert_eq!(stats.setup_time(2.0), 2.0);
+    assert_eq!(stats.setup_time(10.0), 2.0);
+    assert_eq!(stats.average_setup_time(), 2.0);
+    assert_eq!(stats.late_drops(), 1);
+    assert_eq!(stats.served_customers(), 0);
+    assert_eq!(stats.setup_wait_share(), 0.0);
+}
+
+#[test]
+fn the_next_arrival_after_a_wasted_setup_starts_a_new_one() {
+    let mut simulation = impatient(1.0, None);
+    let mut setup_events = 0;
+    let mut wasted = 0;
+    let mut after_wasted = 0;
+    let mut station_off = false;
+    while simulation.event_count() < 50_000 {
+        let event = simulation.step().expect("arrivals go on");
+        let server = simulation.server();
+        match event.event_type {
+            EventType::Setup => {
+                setup_events += 1;
+                station_off = !server.is_busy();
+                if station_off {
+                    wasted += 1;
+                    assert!(!server.is_setting_up());
+                    assert_eq!(server.queue_length(), 0);
+                }
+            }
+            EventType::Arrival if station_off => {
+                assert!(server.is_setting_up());
+                after_wasted += 1;
+                station_off = false;
+            }
+            _ => {}
+        }
+    }
+    assert!(
+        wasted > 0 && after_wasted >= wasted - 1,
+        "{} {}",
+        wasted,
+        after_wasted
+    );
+
+    let stats = simulation.statistics();
+    assert_eq!(stats.setups(), setup_events);
+    assert_eq!(stats.average_setup_time(), 2.0);
+    // Only a setup still in progress adds to the completed ones
+    let in_progress = stats.setup_time(simulation.now()) - 2.0 * setup_events as f64;
+    if simulation.server().is_setting_up() {
+        assert!(in_progress > 0.0 && in_progress <= 2.0);
+    } else {
+        assert_eq!(in_progress, 0.0);
+    }
+}
+
+#[test]
+fn setup_statistics_follow_the_decomposition() {
+    let (lambda, mu, setup_mean) = (0.5, 1.0, 1.0);
+    let config = SimulationConfig::builder()
+        .lambda(lambda)
+        .mu(mu)
+        .setup(SetupTime {
+            mean: setup_mean,
+            kind: ServiceKind::Exponential,
+        })
+        .warm_up_time(1_000.0)
+        .seed(17)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    simulation.run(&StopCondition::Customers(400_000), &mut ());
+    let stats = simulation.statistics();
+
+    // For exponential setups the extra wait is the mean setup itself
+    let extra_wait = setup_extra_wait(lambda, setup_mean, 2.0 * setup_mean * setup_mean);
+    assert!((extra_wait - setup_mean).abs() < 1e-12);
+    let base_wait = allen_cunneen_wait(1, 1.0 / lambda, 1.0 / mu, 1.0, 1.0);
+    let expected = base_wait.expect("the queue is stable") + extra_wait;
+    let wait = stats.average_wait_time();
+    assert!(
+        (wait - expected).abs() < 0.05 * expected,
+        "{} vs {}",
+        wait,
+        expected
+    );
+
+    assert!((stats.average_setup_time() - setup_mean).abs() < 0.02 * setup_mean);
+    // Every busy period starts with a setup, and an arrival finds the station
+    // off with probability (1 - ρ) / (1 + λE[S])
+    let rho = lambda / mu;
+    let expected_share = (1.0 - rho) / (1.0 + lambda * setup_mean);
+    let share = stats.setups() as f64 / stats.served_customers() as f64;
+    assert!(
+        (share - expected_share).abs() < 0.03 * expected_share,
+        "{}",
+        share
+    );
+    let wait_share = stats.setup_wait_share();
+    assert!(wait_share > 0.0 && wait_share < 1.0, "{}", wait_share);
+}
@@ -0,0 +1,139 @@
+//! A run stored with `--sqlite` reads back as it was written
+#![cfg(feature = "sqlite")]
+
+use rusqlite::{Connection, params};
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::sqlite::{RunSummary, open, write_run};
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+use std::path::Path;
+
+const TRACED: usize = 5;
+
+/// A short seeded run with its samples
+fn sampled_run() -> (Simulation, SimulationTimeSeries) {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .customer_trace_limit(TRACED)
+        .seed(2)
+        .build()
+        .expect("the configuration is valid");
+    let mut simulation = Simulation::new(config);
+    let mut time_series = SimulationTimeSeries::new(5.0, 100);
+    while simulation.now() < 200.0 && simulation.step().is_some() {
+        time_series.sample_statistics(simulation.now(), simulation.statistics());
+    }
+    (simulation, time_series)
+}
+
+fn store(
+    connection: &mut Connection,
+    replace: Option<i64>,
+    run: &(Simulation, SimulationTimeSeries),
+) -> i64 {
+    let (simulation, time_series) = run;
+    let summary = RunSummary {
+        config: simulation.config(),
+        total_time: simulation.now(),
+        event_count: simulation.event_count(),
+        served_customers: simulation.statistics().served_customers(),
+    };
+    let customers = simulation.server().customer_trace().records();
+    write_run(connection, replace, &summary, time_series, customers).expect("the run is stored")
+}
+
+fn count(connection: &Connection, table: &str, run_id: i64) -> i64 {
+    let column = if table == "runs" { "id" } else { "run_id" };
+    connection
+        .query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE {} = ?1", table, column),
+            params![run_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn opening_creates_the_tables() {
+    let connection = open(Path::new(":memory:")).unwrap();
+    let mut statement = connection
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+        .unwrap();
+    let tables: Vec<String> = statement
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(tables, ["customers", "runs", "samples"]);
+}
+
+#[test]
+fn a_run_reads_back_with_every_sample_and_customer() {
+    let mut connection = open(Path::new(":memory:")).unwrap();
+    let run = sampled_run();
+    let run_id = store(&mut connection, None, &run);
+    let (simulation, time_series) = &run;
+
+    let samples: usize = time_series
+        .metrics()
+        .iter()
+        .map(|(_, samples)| samples.len())
+        .sum();
+    assert!(samples > 0);
+    assert_eq!(count(&connection, "samples", run_id), samples as i64);
+    assert_eq!(count(&connection, "customers", run_id), TRACED as i64);
+
+    let (lambda, seed, events, served): (f64, i64, i64, i64) = connection
+        .query_row(
+            "SELECT lambda, seed, events, served_customers FROM runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(lambda, 0.8);
+    assert_eq!(seed, 2);
+    assert_eq!(events as u64, simulation.event_count());
+    assert_eq!(served as u64, simulation.statistics().served_customers());
+
+    let queue: Vec<(f64, f64)> = connection
+        .prepare("SELECT time, value FROM samples WHERE run_id = ?1 AND metric = 'queue_length' ORDER BY time")
+        .unwrap()
+        .query_map(params![run_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let expected: Vec<(f64, f64)> = time_series
+        .queue_length
+        .data()
+        .iter()
+        .map(|&(time, length)| (time, length as f64))
+        .collect();
+    assert_eq!(queue, expected);
+
+    let first_customer: (i64, f64, f64) = connection
+        .query_row(
+            "SELECT id, arrival, departure FROM customers WHERE run_id = ?1 ORDER BY id LIMIT 1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    let record = simulation.server().customer_trace().records()[0];
+    assert_eq!(first_customer, (0, record.arrival, record.departure));
+}
+
+#[test]
+fn replacing_a_run_keeps_one_copy_of_it() {
+    let mut connection = open(Path::new(":memory:")).unwrap();
+    let run = sampled_run();
+    let first = store(&mut connection, None, &run);
+    let samples = count(&connection, "samples", first);
+    let second = store(&mut connection, None, &run);
+    assert_ne!(first, second);
+
+    assert_eq!(store(&mut connection, Some(first), &run), first);
+    assert_eq!(count(&connection, "runs", first), 1);
+    assert_eq!(count(&connection, "samples", first), samples);
+    assert_eq!(count(&connection, "customers", first), TRACED as i64);
+    assert_eq!(count(&connection, "samples", second), samples);
+}
@@ -0,0 +1,150 @@
+//! A recorded run replays to the same results, and a changed number of draws is caught
+
+use rust_single_server_queue::config::{
+    Breakdowns, Deadlines, InterruptedService, ServiceKind, SetupTime, SimulationConfig,
+};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::statistics::Totals;
+use rust_single_server_queue::stream_capture::{
+    ReplayError, StreamCapture, StreamRecording, StreamTag,
+};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+const EVENTS: u64 = 50_000;
+
+/// A file in the temporary directory, removed again when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("ssq-{}-{}.rng", std::process::id(), name)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A model drawing from every stream: arrivals, service, setups, breakdowns and deadlines
+fn config(seed: u64) -> SimulationConfig {
+    SimulationConfig::builder()
+        .lambda(0.7)
+        .mu(1.0)
+        .setup(SetupTime {
+            mean: 0.5,
+            kind: ServiceKind::Exponential,
+        })
+        .breakdowns(Breakdowns {
+            mean_time_to_failure: 40.0,
+            mean_repair_time: 1.0,
+            interrupted_service: InterruptedService::Resume,
+        })
+        .deadlines(Deadlines {
+            mean: 5.0,
+            kind: ServiceKind::Exponential,
+            drop_late: false,
+        })
+        .seed(seed)
+        .build()
+        .expect("the configuration is valid")
+}
+
+fn run(simulation: &mut Simulation, events: u64) -> (f64, Totals) {
+    simulation.run(&StopCondition::Events(events), &mut ());
+    (
+        simulation.now(),
+        simulation.statistics().totals(simulation.now()),
+    )
+}
+
+fn recorded(events: u64) -> ((f64, Totals), StreamRecording) {
+    let capture = StreamCapture::record();
+    let mut simulation = Simulation::with_capture(config(1), capture.clone());
+    let result = run(&mut simulation, events);
+    (result, capture.recording())
+}
+
+#[test]
+fn a_replay_reproduces_the_run_whatever_the_seed() {
+    let (original, recording) = recorded(EVENTS);
+    for tag in StreamTag::ALL {
+        assert!(
+            recording.draws.iter().any(|draw| draw.tag == tag),
+            "no {} draws",
+            tag
+        );
+    }
+
+    let file = TempFile::new("replay");
+    recording.save(&file.0).unwrap();
+    let loaded = StreamRecording::load(&file.0).unwrap();
+    assert_eq!(loaded, recording);
+
+    let capture = StreamCapture::replay(loaded);
+    let mut replay = Simulation::with_capture(config(999), capture.clone());
+    assert_eq!(run(&mut replay, EVENTS), original);
+    assert_eq!(replay.replay_error(), None);
+    assert_eq!(capture.remaining(), 0);
+}
+
+#[test]
+fn an_extra_draw_is_reported_as_a_divergence() {
+    let (_, mut recording) = recorded(EVENTS);
+    // Leaving out one recorded service draw is what a build drawing once more sees:
+    // its next draw finds a number recorded for a later event
+    let index = recording
+        .draws
+        .iter()
+        .position(|draw| draw.tag == StreamTag::Service && draw.event > 1_000)
+        .unwrap();
+    let missing = recording.draws.remove(index);
+
+    let capture = StreamCapture::replay(recording);
+    let mut replay = Simulation::with_capture(config(1), capture.clone());
+    run(&mut replay, EVENTS);
+    match replay.replay_error() {
+        Some(ReplayError::Diverged {
+            tag: StreamTag::Service,
+            recorded_event,
+            event,
+        }) => {
+            assert_eq!(event, missing.event);
+            assert!(recorded_event > event);
+        }
+        other => panic!(
+            "expected a divergence of the service stream, got {:?}",
+            other
+        ),
+    }
+    assert!(replay.event_count() < EVENTS);
+}
+
+#[test]
+fn a_longer_run_than_recorded_runs_out_of_draws() {
+    let (_, recording) = recorded(1_000);
+    let mut replay = Simulation::with_capture(config(1), StreamCapture::replay(recording));
+    run(&mut replay, EVENTS);
+    assert!(matches!(
+        replay.replay_error(),
+        Some(ReplayError::Exhausted { .. })
+    ));
+    assert!(replay.event_count() < EVENTS);
+}
+
+#[test]
+fn a_truncated_recording_is_refused() {
+    let (_, recording) = recorded(1_000);
+    let file = TempFile::new("truncated");
+    recording.save(&file.0).unwrap();
+    let bytes = fs::read(&file.0).unwrap();
+    fs::write(&file.0, &bytes[..bytes.len() - 3]).unwrap();
+
+    let error = StreamRecording::load(&file.0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().starts_with("recording ends after"));
+}
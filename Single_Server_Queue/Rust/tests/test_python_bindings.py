@@ -0,0 +1,115 @@
+"""Tests of the Python bindings, see src/python.rs
+
+Build the module into the active virtualenv first, then run pytest:
+
+    pip install maturin pytest
+    maturin develop
+    pytest tests
+
+Cargo only runs the .rs files in tests/, so this file is left to pytest.
+"""
+
+import threading
+
+import pytest
+
+import rust_single_server_queue as ssq
+
+
+def test_run_returns_the_final_statistics():
+    stats = ssq.Simulation(0.5, 1.0, seed=1, customers=2_000).run()
+    assert stats["customers_served"] >= 2_000
+    assert stats["customers_lost"] == 0
+    assert 0.0 < stats["utilization"] < 1.0
+    assert stats["average_wait_time"] > 0.0
+    assert stats["blocking_probability"] == 0.0
+
+
+def test_a_seed_reproduces_the_run():
+    first = ssq.Simulation(0.8, 1.0, seed=7, time=5_000).run()
+    second = ssq.Simulation(0.8, 1.0, seed=7, time=5_000).run()
+    assert first == second
+
+
+def test_running_again_after_the_end_changes_nothing():
+    sim = ssq.Simulation(0.8, 1.0, seed=7, events=10_000)
+    first = sim.run()
+    assert sim.run() == first
+    assert sim.now == first["time"]
+
+
+def test_time_series_are_time_ordered_pairs():
+    sim = ssq.Simulation(0.8, 1.0, seed=3, time=1_000, sample_interval=10.0)
+    sim.run()
+    series = sim.time_series()
+    assert {"queue_length", "mean_wait_time", "utilization"} <= series.keys()
+    samples = series["queue_length"]
+    assert len(samples) > 50
+    times = [time for time, _ in samples]
+    assert times == sorted(times)
+    assert all(time <= 1_000 for time in times)
+
+
+def test_time_series_convert_to_numpy():
+    numpy = pytest.importorskip("numpy")
+    sim = ssq.Simulation(0.8, 1.0, seed=3, time=1_000)
+    sim.run()
+    array = numpy.asarray(sim.time_series()["mean_wait_time"])
+    assert array.ndim == 2 and array.shape[1] == 2
+
+
+@pytest.mark.parametrize(
+    "kwargs",
+    [
+        {"time": 10.0, "events": 10},
+        {"time": -1.0},
+        {"sample_interval": 0.0},
+    ],
+)
+def test_invalid_stop_conditions_raise(kwargs):
+    with pytest.raises(ValueError):
+        ssq.Simulation(0.5, 1.0, **kwargs)
+
+
+def test_invalid_rates_raise():
+    with pytest.raises(ValueError):
+        ssq.Simulation(-0.5, 1.0)
+
+
+def test_sweep_returns_a_row_per_rate_and_replication():
+    rows = ssq.sweep([0.3, 0.6], 1.0, replications=2, seed=3, time=20_000)
+    assert [(row["lambda"], row["replication"]) for row in rows] == [
+        (0.3, 0),
+        (0.3, 1),
+        (0.6, 0),
+        (0.6, 1),
+    ]
+    assert "cost_per_time_unit" not in rows[0]
+    light = sum(row["average_wait_time"] for row in rows[:2])
+    heavy = sum(row["average_wait_time"] for row in rows[2:])
+    assert light < heavy
+
+
+def test_a_priced_sweep_comes_back_cheapest_first():
+    rows = ssq.sweep([0.9, 0.3, 0.6], 1.0, seed=3, time=20_000, waiting_cost=1.0)
+    costs = [row["cost_per_time_unit"] for row in rows]
+    assert costs == sorted(costs)
+    # Only waiting is priced, so the lightest load is the cheapest
+    assert rows[0]["lambda"] == 0.3
+
+
+def test_negative_costs_raise():
+    with pytest.raises(ValueError):
+        ssq.sweep([0.5], 1.0, loss_cost=-1.0)
+
+
+def test_other_threads_run_during_a_simulation():
+    sim = ssq.Simulation(0.9, 1.0, seed=1, time=5_000_000)
+    worker = threading.Thread(target=sim.run)
+    ticks = 0
+    worker.start()
+    while worker.is_alive():
+        ticks += 1
+    worker.join()
+    # A run holding the GIL would leave this thread a handful of iterations at most
+    assert ticks > 1_000
@@ -0,0 +1,63 @@
+//! The interarrival and service moments of a run, and the two-moment wait built from them
+
+use rust_single_server_queue::config::{ServiceKind, SimulationConfig};
+use rust_single_server_queue::model::StopCondition;
+use rust_single_server_queue::simulation::Simulation;
+use rust_single_server_queue::theory::allen_cunneen_wait;
+
+#[test]
+fn measured_scvs_and_waits_match_the_two_moment_approximation() {
+    // Service kind and the squared coefficient of variation of its durations
+    for (service_kind, cs2) in [
+        (ServiceKind::Exponential, 1.0),
+        (ServiceKind::Deterministic, 0.0),
+        (ServiceKind::Erlang(4), 0.25),
+        (ServiceKind::Gamma(0.5), 2.0),
+    ] {
+        let config = SimulationConfig::builder()
+            .lambda(0.7)
+            .mu(1.0)
+            .service_kind(service_kind)
+            .seed(3)
+            .build()
+            .expect("the configuration is valid");
+        let mut simulation = Simulation::new(config);
+        simulation.run(&StopCondition::Customers(300_000), &mut ());
+        let stats = simulation.statistics();
+
+        let arrivals = stats.interarrival_times();
+        let services = stats.service_times();
+        let ca2 = arrivals.scv().unwrap();
+        let measured_cs2 = services.scv().unwrap();
+        assert!(
+            (arrivals.mean().unwrap() - 1.0 / 0.7).abs() < 0.02,
+            "{:?}",
+            service_kind
+        );
+        assert!((ca2 - 1.0).abs() < 0.02, "{:?}: ca² {}", service_kind, ca2);
+        assert!(
+            (measured_cs2 - cs2).abs() < 0.03 * cs2.max(1.0),
+            "{:?}: cs² {}",
+            service_kind,
+            measured_cs2
+        );
+
+        // Arrivals are Poisson, so the approximation is the exact M/G/1 wait
+        let approximation = allen_cunneen_wait(
+            1,
+            arrivals.mean().unwrap(),
+            services.mean().unwrap(),
+            ca2,
+            measured_cs2,
+        )
+        .unwrap();
+        let wait = stats.average_wait_time();
+        assert!(
+            (wait / approximation - 1.0).abs() < 0.05,
+            "{:?}: simulated {} against {}",
+            service_kind,
+            wait,
+            approximation
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! The built-in cases of `--verify` still agree with theory
+//!
+//! Every case runs enough customers to pin its metrics down, some 15 seconds in a
+//! debug build against the milliseconds of the other tests, so this one only runs
+//! when asked for:
+//!
+//! ```text
+//! cargo test --release --test verification -- --ignored
+//! ```
+
+use rust_single_server_queue::verification::verify;
+
+#[test]
+#[ignore = "long-running; run with --ignored, preferably in release"]
+fn builtin_cases_match_theory() {
+    let report = verify();
+    assert!(!report.checks.is_empty());
+    let failures: Vec<String> = report
+        .failures()
+        .map(|check| {
+            format!(
+                "{} {}: measured {:.4}, expected {:.4}, error {:+.2}% beyond {:.2}%",
+                check.case,
+                check.metric,
+                check.measured,
+                check.expected,
+                check.error() * 100.0,
+                check.tolerance * 100.0
+            )
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,78 @@
+//! A run cut short by the wall clock ends with valid statistics for the part it covered
+
+use rust_single_server_queue::config::SimulationConfig;
+use rust_single_server_queue::model::{Deadline, RunEnd, StopCondition};
+use rust_single_server_queue::simulation::{Simulation, SimulationResult};
+use rust_single_server_queue::time_series::SimulationTimeSeries;
+use std::time::Duration;
+
+/// Far more simulated time than any test gets through
+const HORIZON: f64 = 1e12;
+
+/// The clock is only read every this many events
+const CHECK_EVERY: u64 = 4096;
+
+fn run_with_limit(limit: Duration) -> (SimulationResult, Deadline) {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .seed(13)
+        .build()
+        .expect("the configuration is valid");
+    let mut deadline = Deadline::after(limit);
+    let result = Simulation::new(config).run_sampled(
+        &StopCondition::Time(HORIZON),
+        SimulationTimeSeries::new(1.25, 500),
+        &mut deadline,
+    );
+    (result, deadline)
+}
+
+#[test]
+fn a_tiny_limit_leaves_valid_partial_statistics() {
+    let (result, deadline) = run_with_limit(Duration::from_millis(1));
+    assert!(deadline.has_passed());
+    assert_eq!(result.outcome.end, RunEnd::Observer);
+    assert_eq!(result.outcome.events % CHECK_EVERY, 0);
+    assert!(result.total_time > 0.0 && result.total_time < HORIZON);
+
+    let stats = &result.statistics;
+    assert!(stats.served_customers() > 0);
+    assert_eq!(result.served_customers, stats.served_customers());
+    let utilization = stats.utilization(result.total_time);
+    assert!(utilization > 0.0 && utilization < 1.0, "{}", utilization);
+    let wait = stats.average_wait_time();
+    assert!(wait.is_finite() && wait >= 0.0, "{}", wait);
+
+    let queue = &result.time_series.queue_length;
+    assert!(!queue.is_empty());
+    assert!(queue.data().last().unwrap().0 <= result.total_time);
+}
+
+#[test]
+fn an_expired_limit_stops_at_the_first_look_at_the_clock() {
+    let (result, deadline) = run_with_limit(Duration::ZERO);
+    assert!(deadline.has_passed());
+    assert_eq!(result.outcome.events, CHECK_EVERY);
+}
+
+#[test]
+fn a_generous_limit_changes_nothing() {
+    let config = SimulationConfig::builder()
+        .lambda(0.8)
+        .mu(1.0)
+        .seed(13)
+        .build()
+        .expect("the configuration is valid");
+    let stop = StopCondition::Customers(50_000);
+    let mut free = Simulation::new(config.clone());
+    free.run(&stop, &mut ());
+    let mut limited = Simulation::new(config);
+    let mut deadline = Deadline::after(Duration::from_secs(3600));
+    let outcome = limited.run(&stop, &mut deadline);
+
+    assert!(!deadline.has_passed());
+    assert_eq!(outcome.end, RunEnd::StopCondition);
+    assert_eq!(limited.now(), free.now());
+    assert_eq!(limited.event_count(), free.event_count());
+}